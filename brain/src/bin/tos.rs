@@ -1,6 +1,6 @@
 use serde_json::Value;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::net::UnixStream;
+use tokio::net::{TcpStream, UnixStream};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -10,28 +10,23 @@ async fn main() -> anyhow::Result<()> {
         println!("Usage: tos <command> [args]");
         println!("Commands:");
         println!("  ports  List all active Brain-managed services");
+        println!("  identity [rotate]  Show or regenerate the Brain's TLS identity");
+        println!("  remote <host:port> <request> [--repin]");
+        println!("       Send a request to a remote Brain, pinning its certificate on first use");
+        println!("  commands [--names] [prefix]  List the Brain's IPC commands");
+        println!("  logs [-f] [--source s] [--level warning] [--event e] [--correlation id] [--grep text] [--since 10m] [--limit n]");
+        println!("       Show the unified log, or follow it with -f");
+        println!("  crashes [list | show <id|signature> | purge <signature|--all>]");
+        println!("       Crash reports grouped by signature");
+        println!("  rules [list | add <rule.json> | remove <id> | test [rule.json] [--sector id]]");
+        println!("       Priority rules; test matches them against recorded output");
         return Ok(());
     }
 
     let cmd = &args[1];
     match cmd.as_str() {
         "ports" => {
-            let socket_path = "/tmp/brain.sock";
-            if !std::path::Path::new(socket_path).exists() {
-                return Err(anyhow::anyhow!(
-                    "Brain discovery gate not found at {}. Is the Brain running?",
-                    socket_path
-                ));
-            }
-
-            let mut stream = UnixStream::connect(socket_path).await?;
-            stream.write_all(b"tos_ports:\n").await?;
-
-            let (reader, _) = stream.split();
-            let mut reader = BufReader::new(reader);
-            let mut line = String::new();
-            reader.read_line(&mut line).await?;
-
+            let line = brain_request("tos_ports:").await?;
             if line.starts_with("ERROR") {
                 println!("{}", line);
                 return Ok(());
@@ -65,8 +60,490 @@ async fn main() -> anyhow::Result<()> {
                 println!("{:<22} {:<8} {:<15} {}", name, port, host, status_fmt);
             }
         }
+        // §12.4: Persistent Brain TLS identity
+        "identity" => {
+            use tos_common::platform::BrainIdentity;
+            let dir = BrainIdentity::default_dir(&tos_common::TosConfig::load());
+            let identity = match args.get(2).map(String::as_str) {
+                None => BrainIdentity::load_or_create(&dir)?,
+                Some("rotate") => {
+                    let identity = BrainIdentity::rotate(&dir)?;
+                    println!("Rotated. Restart the Brain to serve the new certificate;");
+                    println!("Faces that pinned the old fingerprint must re-pin it.");
+                    identity
+                }
+                Some(other) => {
+                    println!("ERROR: Unknown identity action '{}'", other);
+                    return Ok(());
+                }
+            };
+            println!("{:<12} {}", "CERTIFICATE", dir.join(BrainIdentity::CERT_FILE).display());
+            println!("{:<12} {}", "SHA-256", identity.fingerprint());
+        }
+        // §12.4: One request to a remote Brain's anchor port, verified
+        // against the fingerprint pinned in known_brains.json.
+        "remote" => {
+            use tos_common::platform::tls_identity::{connect_pinned, KnownBrains};
+            let (address, request) = match (args.get(2), args.get(3)) {
+                (Some(address), Some(request)) => (address, request),
+                _ => {
+                    println!("Usage: tos remote <host:port> <request> [--repin]");
+                    return Ok(());
+                }
+            };
+            let mut known = KnownBrains::load(&KnownBrains::default_path(
+                &tos_common::TosConfig::load(),
+            ));
+            if args.iter().any(|a| a == "--repin") {
+                known.forget(address)?;
+            }
+            let stream = match connect_pinned(address, &mut known).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    println!("ERROR: {}", e);
+                    return Ok(());
+                }
+            };
+            let (reader, mut writer) = tokio::io::split(stream);
+            writer.write_all(format!("{}\n", request).as_bytes()).await?;
+            let mut line = String::new();
+            BufReader::new(reader).read_line(&mut line).await?;
+            println!("{}", line.trim_end());
+        }
+        // §3.3.8: Command registry introspection; `--names` prints one name
+        // per line for shell completion scripts.
+        "commands" => {
+            let names_only = args.get(2).map(String::as_str) == Some("--names");
+            let prefix = args.get(if names_only { 3 } else { 2 }).map(String::as_str);
+            let line = brain_request(&format!("command_list:{}", prefix.unwrap_or(""))).await?;
+            let commands: Vec<Value> = match serde_json::from_str(line.trim()) {
+                Ok(v) => v,
+                Err(_) => {
+                    println!("{}", line.trim());
+                    return Ok(());
+                }
+            };
+            if names_only {
+                for c in &commands {
+                    println!("{}", c["name"].as_str().unwrap_or("-"));
+                }
+                return Ok(());
+            }
+            println!("{:<34} {:<10} HELP", "COMMAND", "ROLE");
+            println!("{}", "-".repeat(72));
+            for c in &commands {
+                let params: Vec<String> = c["params"]
+                    .as_array()
+                    .map(|ps| {
+                        ps.iter()
+                            .map(|p| {
+                                let name = p["name"].as_str().unwrap_or("?");
+                                if p["required"] == true {
+                                    format!("<{}>", name)
+                                } else {
+                                    format!("[{}]", name)
+                                }
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                println!(
+                    "{:<34} {:<10} {}",
+                    c["name"].as_str().unwrap_or("-"),
+                    c["role"].as_str().unwrap_or("-"),
+                    c["help"].as_str().unwrap_or("")
+                );
+                if !params.is_empty() {
+                    println!("{:<45} {}", "", params.join(";"));
+                }
+            }
+        }
+        // §19.2: Page through the unified log, or follow it live.
+        "logs" => {
+            let mut filter = serde_json::Map::new();
+            let mut follow = false;
+            let mut options = args[2..].iter();
+            while let Some(option) = options.next() {
+                let key = match option.as_str() {
+                    "-f" | "--follow" => {
+                        follow = true;
+                        continue;
+                    }
+                    "--source" => "source",
+                    "--level" => "min_level",
+                    "--event" => "event",
+                    "--correlation" => "correlation_id",
+                    "--grep" => "text",
+                    "--since" => "since",
+                    "--until" => "until",
+                    "--limit" => "limit",
+                    other => {
+                        println!("ERROR: Unknown logs option '{}'", other);
+                        return Ok(());
+                    }
+                };
+                let value = match options.next() {
+                    Some(v) => v,
+                    None => {
+                        println!("ERROR: {} needs a value", option);
+                        return Ok(());
+                    }
+                };
+                let value = match key {
+                    // A level is a priority or a name.
+                    "min_level" => match value.parse::<u64>() {
+                        Ok(n) => Value::from(n),
+                        Err(_) => Value::from(value.as_str()),
+                    },
+                    "limit" => match value.parse::<u64>() {
+                        Ok(n) => Value::from(n),
+                        Err(_) => {
+                            println!("ERROR: {} takes a number", option);
+                            return Ok(());
+                        }
+                    },
+                    _ => Value::from(value.as_str()),
+                };
+                filter.insert(key.to_string(), value);
+            }
+            let filter = Value::Object(filter);
+
+            if follow {
+                follow_logs(&filter).await?;
+                return Ok(());
+            }
+            let line = brain_request(&format!("log_query:{}", filter)).await?;
+            let page: Value = match serde_json::from_str(line.trim()) {
+                Ok(v) => v,
+                Err(_) => {
+                    println!("{}", line.trim());
+                    return Ok(());
+                }
+            };
+            // Results come newest first; print them in reading order.
+            if let Some(results) = page["results"].as_array() {
+                for record in results.iter().rev() {
+                    print_record(record);
+                }
+            }
+        }
+        // §19.5: Crash reports grouped by signature.
+        "crashes" => match args.get(2).map(String::as_str) {
+            None | Some("list") => {
+                let line = brain_request("crash_list:").await?;
+                let groups: Vec<Value> = match serde_json::from_str(line.trim()) {
+                    Ok(v) => v,
+                    Err(_) => {
+                        println!("{}", line.trim());
+                        return Ok(());
+                    }
+                };
+                if groups.is_empty() {
+                    println!("No crash reports.");
+                    return Ok(());
+                }
+                println!(
+                    "{:<17} {:>5} {:<19} {:<16} MESSAGE",
+                    "SIGNATURE", "COUNT", "LAST SEEN", "SERVICE"
+                );
+                println!("{}", "-".repeat(80));
+                for g in &groups {
+                    println!(
+                        "{:<17} {:>5} {:<19} {:<16} {}",
+                        g["signature"].as_str().unwrap_or("-"),
+                        g["count"].as_u64().unwrap_or(0),
+                        local_time(g["last_seen"].as_i64()),
+                        g["service"].as_str().unwrap_or("-"),
+                        g["message"].as_str().unwrap_or("")
+                    );
+                }
+            }
+            Some("show") => {
+                let key = match args.get(3) {
+                    Some(k) => k,
+                    None => {
+                        println!("ERROR: crashes show needs an id or signature");
+                        return Ok(());
+                    }
+                };
+                let line = brain_request(&format!("crash_show:{}", key)).await?;
+                let report: Value = match serde_json::from_str(line.trim()) {
+                    Ok(v) => v,
+                    Err(_) => {
+                        println!("{}", line.trim());
+                        return Ok(());
+                    }
+                };
+                print_crash(&report);
+            }
+            Some("purge") => {
+                let signature = match args.get(3).map(String::as_str) {
+                    Some("--all") => "all",
+                    Some(s) => s,
+                    None => {
+                        println!("ERROR: crashes purge needs a signature or --all");
+                        return Ok(());
+                    }
+                };
+                println!(
+                    "{}",
+                    brain_request(&format!("crash_purge:{}", signature))
+                        .await?
+                        .trim()
+                );
+            }
+            Some(other) => println!("ERROR: Unknown crashes action '{}'", other),
+        },
+        // Visual Design §1.2: User-defined priority rules.
+        "rules" => match args.get(2).map(String::as_str) {
+            None | Some("list") => {
+                let line = brain_request("priority_rules:").await?;
+                let rules: Vec<Value> = match serde_json::from_str(line.trim()) {
+                    Ok(v) => v,
+                    Err(_) => {
+                        println!("{}", line.trim());
+                        return Ok(());
+                    }
+                };
+                if rules.is_empty() {
+                    println!("No priority rules.");
+                    return Ok(());
+                }
+                for rule in &rules {
+                    println!(
+                        "{}{}",
+                        rule["id"].as_str().unwrap_or("-"),
+                        if rule["enabled"] == false { " (disabled)" } else { "" }
+                    );
+                    println!("  when     {}", rule["when"]);
+                    println!("  actions  {}", rule["actions"]);
+                }
+            }
+            Some("add") => {
+                let rule = match args.get(3).map(|a| read_rule(a)) {
+                    Some(Ok(rule)) => rule,
+                    Some(Err(e)) => {
+                        println!("ERROR: {}", e);
+                        return Ok(());
+                    }
+                    None => {
+                        println!("ERROR: rules add needs a rule file or JSON");
+                        return Ok(());
+                    }
+                };
+                println!(
+                    "{}",
+                    brain_request(&format!("priority_rule_set:{}", rule))
+                        .await?
+                        .trim()
+                );
+            }
+            Some("remove") => match args.get(3) {
+                Some(id) => println!(
+                    "{}",
+                    brain_request(&format!("priority_rule_remove:{}", id))
+                        .await?
+                        .trim()
+                ),
+                None => println!("ERROR: rules remove needs a rule id"),
+            },
+            Some("test") => {
+                let mut test = serde_json::json!({});
+                let mut rest = args[3..].iter();
+                while let Some(arg) = rest.next() {
+                    if arg == "--sector" {
+                        match rest.next() {
+                            Some(id) => test["sector"] = Value::from(id.as_str()),
+                            None => {
+                                println!("ERROR: --sector needs a sector id");
+                                return Ok(());
+                            }
+                        }
+                        continue;
+                    }
+                    match read_rule(arg) {
+                        Ok(rule) => test["rule"] = rule,
+                        Err(e) => {
+                            println!("ERROR: {}", e);
+                            return Ok(());
+                        }
+                    }
+                }
+                let line = brain_request(&format!("priority_rule_test:{}", test)).await?;
+                let results: Vec<Value> = match serde_json::from_str(line.trim()) {
+                    Ok(v) => v,
+                    Err(_) => {
+                        println!("{}", line.trim());
+                        return Ok(());
+                    }
+                };
+                if results.is_empty() {
+                    println!("No matches in recorded output.");
+                    return Ok(());
+                }
+                for r in &results {
+                    println!(
+                        "{} in sector '{}': {} match(es)",
+                        r["rule"].as_str().unwrap_or("-"),
+                        r["sector"].as_str().unwrap_or("-"),
+                        r["match_count"].as_u64().unwrap_or(0)
+                    );
+                    println!("  {}", r["effect"].as_str().unwrap_or(""));
+                    for m in r["matches"].as_array().into_iter().flatten() {
+                        let at = m["at"]
+                            .as_str()
+                            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                            .map(|t| t.timestamp());
+                        println!(
+                            "    {:<19} {}",
+                            local_time(at),
+                            m["evidence"].as_str().unwrap_or("")
+                        );
+                    }
+                }
+            }
+            Some(other) => println!("ERROR: Unknown rules action '{}'", other),
+        },
         _ => println!("ERROR: Unknown command '{}'", cmd),
     }
 
     Ok(())
 }
+
+/// A crash report in full: what crashed, where, and its frames. Frames
+/// without symbols show the binary offset to resolve with `addr2line`.
+fn print_crash(report: &Value) {
+    let field = |name: &str| report[name].as_str().unwrap_or("-").to_string();
+    println!("{:<10} {}", "ID", field("id"));
+    println!("{:<10} {}", "SIGNATURE", field("signature"));
+    println!("{:<10} {}", "TIME", local_time(report["ts"].as_i64()));
+    println!("{:<10} {} {}", "SERVICE", field("service"), field("version"));
+    println!("{:<10} {}", "THREAD", field("thread"));
+    println!("{:<10} {}", "MESSAGE", field("message"));
+    println!("{:<10} {}", "LOCATION", field("location"));
+    if let Some(exe) = report["exe"].as_str() {
+        println!("{:<10} {}", "BINARY", exe);
+    }
+    match report["frames"].as_array() {
+        Some(frames) => {
+            println!();
+            for (i, frame) in frames.iter().enumerate() {
+                let name = match frame["symbol"].as_str() {
+                    Some(symbol) => symbol.to_string(),
+                    None => format!(
+                        "{} (offset {})",
+                        frame["ip"].as_str().unwrap_or("?"),
+                        frame["offset"].as_str().unwrap_or("?")
+                    ),
+                };
+                println!("{:>4}: {}", i, name);
+                let file = frame["file"].as_str();
+                if let (Some(file), Some(line)) = (file, frame["line"].as_u64()) {
+                    println!("          at {}:{}", file, line);
+                }
+            }
+        }
+        None => {
+            if let Some(backtrace) = report["backtrace"].as_str() {
+                println!();
+                println!("{}", backtrace);
+            }
+        }
+    }
+}
+
+/// A rule from a JSON file, or given inline, as one line of JSON.
+fn read_rule(arg: &str) -> anyhow::Result<Value> {
+    let text = match std::fs::read_to_string(arg) {
+        Ok(text) => text,
+        Err(_) => arg.to_string(),
+    };
+    serde_json::from_str(&text).map_err(|e| anyhow::anyhow!("Invalid rule JSON: {}", e))
+}
+
+/// Unix seconds as local time, or `-`.
+fn local_time(ts: Option<i64>) -> String {
+    ts.and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Send one request over the local Brain socket and return the reply line.
+async fn brain_request(request: &str) -> anyhow::Result<String> {
+    let socket_path = tos_common::daemon::brain_socket();
+    let socket_path = socket_path.as_str();
+    if !std::path::Path::new(socket_path).exists() {
+        return Err(anyhow::anyhow!(
+            "Brain discovery gate not found at {}. Is the Brain running?",
+            socket_path
+        ));
+    }
+
+    let mut stream = UnixStream::connect(socket_path).await?;
+    stream.write_all(format!("{}\n", request).as_bytes()).await?;
+
+    let (reader, _) = stream.split();
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+    Ok(line)
+}
+
+/// Subscribe to `tos-loggerd` directly and print records until interrupted.
+async fn follow_logs(filter: &Value) -> anyhow::Result<()> {
+    let ports: Vec<Value> = serde_json::from_str(brain_request("tos_ports:").await?.trim())
+        .map_err(|_| anyhow::anyhow!("Failed to parse port map response"))?;
+    let port = match ports
+        .iter()
+        .find(|e| e["name"] == "tos-loggerd")
+        .and_then(|e| e["port"].as_u64())
+    {
+        Some(port) => port as u16,
+        None => {
+            println!("ERROR: tos-loggerd is not registered with the Brain");
+            return Ok(());
+        }
+    };
+
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await?;
+    stream
+        .write_all(format!("subscribe:{}\n", filter).as_bytes())
+        .await?;
+    let mut lines = BufReader::new(stream).lines();
+    while let Some(line) = lines.next_line().await? {
+        if let Some(json) = line.strip_prefix("record:") {
+            if let Ok(record) = serde_json::from_str::<Value>(json) {
+                print_record(&record);
+            }
+        } else if let Some(missed) = line.strip_prefix("lagged:") {
+            eprintln!("-- {} records missed --", missed);
+        } else if line.starts_with("ERROR") {
+            println!("{}", line);
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// One log record as a line: local time, level, source, event and data.
+fn print_record(record: &Value) {
+    let time = local_time(record["ts"].as_i64());
+    let event = record["event"].as_str().unwrap_or("-");
+    let tag = if event == "log" {
+        String::new()
+    } else {
+        format!("[{}] ", event)
+    };
+    println!(
+        "{} {:<8} {:<14} {}{}",
+        time,
+        record["level"].as_str().unwrap_or("-"),
+        record["source"].as_str().unwrap_or("-"),
+        tag,
+        record["data"].as_str().unwrap_or("")
+    );
+}
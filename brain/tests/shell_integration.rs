@@ -52,3 +52,53 @@ async fn test_shell_priority_osc_update() {
     
     assert!(success, "Priority did not update via OSC sequence");
 }
+
+#[tokio::test]
+async fn test_each_sector_owns_its_shell() {
+    let brain = Brain::new().expect("Failed to initialize Brain");
+    let (initial_sectors, initial_active) = {
+        let state = brain.state.lock().unwrap();
+        (state.sectors.len(), state.active_sector_index)
+    };
+
+    let res = brain.ipc.handle_request("sector_create:Isolated");
+    assert_eq!(res, "SECTOR_CREATED: Isolated");
+
+    let (primary_cwd, second_sector, hub_count) = {
+        let state = brain.state.lock().unwrap();
+        (
+            state.sectors[0].hubs[0].current_directory.clone(),
+            state.sectors[initial_sectors].id,
+            state.sectors.iter().map(|s| s.hubs.len()).sum::<usize>(),
+        )
+    };
+    assert_eq!(brain.shells.lock().unwrap().len(), hub_count);
+
+    // Wait for shell to stabilize
+    sleep(Duration::from_millis(500)).await;
+
+    brain.ipc.handle_request(&format!("set_active_sector:{}", initial_sectors));
+    let cmd = "printf '\\033]7;file://localhost/tmp\\007\\n'\n";
+    brain.ipc.handle_request(&format!("prompt_submit:{}", cmd));
+
+    let mut success = false;
+    for _ in 0..20 {
+        sleep(Duration::from_millis(100)).await;
+        let state = brain.state.lock().unwrap();
+        if state.sectors[initial_sectors].hubs[0].current_directory.to_string_lossy().contains("tmp") {
+            success = true;
+            break;
+        }
+    }
+    assert!(success, "CWD did not update in the new sector");
+
+    {
+        let state = brain.state.lock().unwrap();
+        assert_eq!(state.sectors[0].hubs[0].current_directory, primary_cwd);
+    }
+
+    let res = brain.ipc.handle_request(&format!("sector_close:{}", second_sector));
+    assert!(res.starts_with("SECTOR_CLOSED:"));
+    assert_eq!(brain.shells.lock().unwrap().len(), hub_count - 1);
+    brain.ipc.handle_request(&format!("set_active_sector:{}", initial_active));
+}
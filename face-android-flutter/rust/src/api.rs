@@ -88,5 +88,6 @@ pub fn add_log_entry(state: &mut TosState, text: String) {
         text,
         priority: 1,
         timestamp: chrono::Local::now(),
+        source: None,
     });
 }
@@ -20,6 +20,9 @@ let reconnectTimer: ReturnType<typeof setTimeout> | null = null;
 
 let prediction = $state<string>('');
 let activeWsUrl = $state<string | null>(null);
+// §12.3: Device or Web Portal token sent as `auth:` before anything else.
+// Loopback connections are trusted by default and don't need one.
+let authToken: string | null = null;
 const DEFAULT_WS_HOST = '127.0.0.1';
 const DEFAULT_WS_URL = `wss://${DEFAULT_WS_HOST}:7001`;
 
@@ -57,6 +60,11 @@ if (typeof window !== 'undefined') {
     }
     
     console.log(`[IPC] Initialized with Brain URL: ${activeWsUrl}`);
+
+    // Portal links carry `?token=`; remember it for reconnects.
+    const urlToken = new URLSearchParams(window.location.search).get('token');
+    if (urlToken) localStorage.setItem('tos_auth_token', urlToken);
+    authToken = urlToken ?? localStorage.getItem('tos_auth_token');
 }
 
 const SYNC_INTERVAL_MS = 1000;
@@ -78,6 +86,14 @@ export function setActiveWsUrl(url: string) {
     }
 }
 
+export function setAuthToken(token: string | null) {
+    authToken = token || null;
+    if (typeof window !== 'undefined') {
+        if (authToken) localStorage.setItem('tos_auth_token', authToken);
+        else localStorage.removeItem('tos_auth_token');
+    }
+}
+
 // --- Public Reactive Getters ---
 export function getConnectionState(): ConnectionState {
     return connectionState;
@@ -155,14 +171,58 @@ export function sendCommand(cmd: string): Promise<string | null> {
 let heartbeatTimer: ReturnType<typeof setTimeout> | null = null;
 const HEARTBEAT_TIMEOUT_MS = 5000;
 
+// §3.3.2: `get_state_delta` replies with a JSON Patch against the version
+// the client holds, or a full state when that version is too old.
+type PatchOp = { op: 'add' | 'remove' | 'replace'; path: string; value?: unknown };
+type StateDelta =
+    | { kind: 'patch'; from: number; version: number; ops: PatchOp[] }
+    | { kind: 'full'; version: number; state: TosState };
+
+function applyPatch(target: any, ops: PatchOp[]) {
+    for (const { op, path, value } of ops) {
+        const tokens = path.split('/').slice(1).map(t => t.replace(/~1/g, '/').replace(/~0/g, '~'));
+        const last = tokens.pop()!;
+        const parent = tokens.reduce((node, key) => node[Array.isArray(node) ? Number(key) : key], target);
+        if (Array.isArray(parent)) {
+            const idx = last === '-' ? parent.length : Number(last);
+            if (op === 'add') parent.splice(idx, 0, value);
+            else if (op === 'remove') parent.splice(idx, 1);
+            else parent[idx] = value;
+        } else if (op === 'remove') {
+            delete parent[last];
+        } else {
+            parent[last] = value;
+        }
+    }
+}
+
+function resync() {
+    sendCommand('get_state:').then(res => {
+        if (res) handleStateDelta(res);
+    });
+}
+
 function handleStateDelta(payload: string) {
     try {
-        const parsed = JSON.parse(payload) as TosState;
-        Object.assign(tosState, parsed);
+        const parsed = JSON.parse(payload) as StateDelta | TosState;
+        if ('kind' in parsed && parsed.kind === 'patch') {
+            if (parsed.from === tosState.version) {
+                applyPatch(tosState, parsed.ops);
+            } else if (parsed.version > tosState.version) {
+                // Based on a version we never saw; fetch everything instead.
+                resync();
+                return;
+            }
+        } else if ('kind' in parsed && parsed.kind === 'full') {
+            Object.assign(tosState, parsed.state);
+        } else {
+            Object.assign(tosState, parsed as TosState);
+        }
         lastSyncTime = Date.now();
         resetHeartbeat();
     } catch (e) {
-        console.error('[IPC] Failed to parse state:', e);
+        console.error('[IPC] Failed to apply state delta:', e);
+        resync();
     }
 }
 
@@ -201,8 +261,15 @@ export function connect(customUrl?: string): void {
         connectionState = 'connected';
         console.log('[IPC] ✅ Connected to Brain');
 
-        // Immediate first sync to get full state, which also starts the heartbeat
-        sendCommand('get_state:').then(res => {
+        // Authenticate first (§12.3), then sync the full state, which also
+        // starts the heartbeat.
+        const authenticated = authToken ? sendCommand(`auth:${authToken}`) : Promise.resolve(null);
+        authenticated.then(auth => {
+            if (auth?.startsWith('ERROR')) {
+                console.warn('[IPC] Brain rejected the auth token:', auth);
+            }
+            return sendCommand('get_state:');
+        }).then(res => {
             if (res && typeof res === 'string') {
                 handleStateDelta(res);
             }
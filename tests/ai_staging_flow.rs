@@ -25,7 +25,9 @@ async fn test_ai_manual_submit_gate() {
     let modules = Arc::new(tos_common::brain::module_manager::ModuleManager::new(std::path::PathBuf::from("./dev/fixtures")));
     let sid = state.lock().unwrap().sectors[0].id;
     let hid = state.lock().unwrap().sectors[0].hubs[0].id;
-    let shell_api = Arc::new(std::sync::Mutex::new(tos_common::brain::shell::ShellApi::new(state.clone(), modules, services.ai.clone(), services.heuristic.clone(), sid, hid).unwrap()));
+    let mut shell_pool = tos_common::brain::shell::ShellPool::new(state.clone(), modules, services.ai.clone(), services.heuristic.clone());
+    shell_pool.spawn(sid, hid).unwrap();
+    let shell_api = Arc::new(std::sync::Mutex::new(shell_pool));
     
     let handler = tos_common::brain::ipc_handler::IpcHandler::new(state.clone(), shell_api, services);
     
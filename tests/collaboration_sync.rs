@@ -2,7 +2,7 @@ use std::sync::Arc;
 use tos_common::state::*;
 use tos_common::ipc::*;
 use uuid::Uuid;
-use tos_common::brain::shell::ShellApi;
+use tos_common::brain::shell::ShellPool;
 use tos_common::brain::module_manager::ModuleManager;
 use tos_common::services::ServiceManager;
 use tos_common::brain::ipc_handler::IpcHandler;
@@ -18,7 +18,9 @@ async fn test_collaboration_presence_and_cursor_sync() {
     let modules = Arc::new(ModuleManager::new(std::path::PathBuf::from("./dev/fixtures")));
     let sid = state.lock().unwrap().sectors[0].id;
     let hid = state.lock().unwrap().sectors[0].hubs[0].id;
-    let shell_api = Arc::new(std::sync::Mutex::new(ShellApi::new(state.clone(), modules, services.ai.clone(), services.heuristic.clone(), sid, hid).unwrap()));
+    let mut shell_pool = ShellPool::new(state.clone(), modules, services.ai.clone(), services.heuristic.clone());
+    shell_pool.spawn(sid, hid).unwrap();
+    let shell_api = Arc::new(std::sync::Mutex::new(shell_pool));
     
     let handler = IpcHandler::new(state.clone(), shell_api, services);
     
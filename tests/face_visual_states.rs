@@ -33,7 +33,9 @@ mod tests {
         let modules = Arc::new(tos_common::brain::module_manager::ModuleManager::new(std::path::PathBuf::from("./modules")));
         let sid = state.lock().unwrap().sectors[0].id;
         let hid = state.lock().unwrap().sectors[0].hubs[0].id;
-        let shell_api = Arc::new(std::sync::Mutex::new(tos_common::brain::shell::ShellApi::new(state.clone(), modules.clone(), services.ai.clone(), services.heuristic.clone(), sid, hid).unwrap()));
+        let mut shell_pool = tos_common::brain::shell::ShellPool::new(state.clone(), modules.clone(), services.ai.clone(), services.heuristic.clone());
+        shell_pool.spawn(sid, hid).unwrap();
+        let shell_api = Arc::new(std::sync::Mutex::new(shell_pool));
         let handler = tos_common::brain::ipc_handler::IpcHandler::new(state.clone(), shell_api, services);
 
         // WHEN: We process the registration via IPC Dispatcher
@@ -82,3 +82,119 @@ async fn test_osc_integration_cascade_stress() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Collect the parser output for a byte stream fed in the given chunk sizes.
+fn feed_chunked(data: &[u8], chunk: usize) -> (String, Vec<tos_common::OscEvent>) {
+    use tos_common::{OscParser, OscToken};
+
+    let mut parser = OscParser::new();
+    let mut text = String::new();
+    let mut events = Vec::new();
+    for part in data.chunks(chunk.max(1)) {
+        for token in parser.feed(part) {
+            match token {
+                OscToken::Text(t) => text.push_str(&t),
+                OscToken::Event(e) => events.push(e),
+            }
+        }
+    }
+    (text, events)
+}
+
+#[test]
+fn test_osc_split_across_every_read_boundary() {
+    use tos_common::{OscEvent, PromptMark};
+
+    // A realistic prompt cycle using both BEL and ST terminators.
+    let stream = "\x1b]133;A\x07user@tos $ \x1b]133;B\x1b\\cargo test\r\n\x1b]133;C\x07\
+                  running 3 tests ✓\r\n\x1b]9002;cargo test;101\x1b\\\x1b]133;D;101\x07\
+                  \x1b]7;file://host/home/user/my%20project\x1b\\";
+    let bytes = stream.as_bytes();
+
+    let (reference_text, reference_events) = feed_chunked(bytes, bytes.len());
+    assert_eq!(reference_events.len(), 6);
+    assert!(matches!(reference_events[0], OscEvent::PromptMark(PromptMark::PromptStart)));
+    assert!(matches!(reference_events[1], OscEvent::PromptMark(PromptMark::CommandStart)));
+    assert!(matches!(reference_events[2], OscEvent::PromptMark(PromptMark::CommandExecuted)));
+    assert!(matches!(
+        &reference_events[3],
+        OscEvent::CommandResult { command, status: 101, .. } if command == "cargo test"
+    ));
+    assert!(matches!(
+        reference_events[4],
+        OscEvent::PromptMark(PromptMark::CommandFinished(Some(101)))
+    ));
+    assert!(matches!(&reference_events[5], OscEvent::Cwd(p) if p == "/home/user/my project"));
+    assert_eq!(reference_text, "user@tos $ cargo test\r\nrunning 3 tests ✓\r\n");
+
+    // Every chunk size (including 1-byte reads that split UTF-8 and ST) must agree.
+    for chunk in 1..bytes.len() {
+        let (text, events) = feed_chunked(bytes, chunk);
+        assert_eq!(text, reference_text, "text mismatch at chunk size {}", chunk);
+        assert_eq!(
+            format!("{:?}", events),
+            format!("{:?}", reference_events),
+            "event mismatch at chunk size {}",
+            chunk
+        );
+    }
+}
+
+#[test]
+fn test_osc_repeated_codes_and_hyperlinks_in_order() {
+    use tos_common::{OscEvent, OscParser};
+
+    let mut parser = OscParser::new();
+    let line = "\x1b]9012;3\x07a\x1b]8;id=x;https://tos.dev\x1b\\link\x1b]8;;\x1b\\b\x1b]9012;1\x07";
+    let (clean, events) = parser.process(line);
+
+    assert_eq!(clean, "alinkb");
+    assert_eq!(events.len(), 4);
+    assert!(matches!(events[0], OscEvent::LinePriority(3)));
+    assert!(matches!(
+        &events[1],
+        OscEvent::Hyperlink { id: Some(id), uri: Some(uri) } if id == "x" && uri == "https://tos.dev"
+    ));
+    assert!(matches!(events[2], OscEvent::Hyperlink { uri: None, .. }));
+    assert!(matches!(events[3], OscEvent::LinePriority(1)));
+}
+
+#[test]
+fn test_osc_fuzz_random_streams() {
+    use tos_common::{OscParser, OscToken};
+
+    // Deterministic xorshift so failures are reproducible.
+    let mut seed: u64 = 0x5eed_1234_abcd_ef01;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    let alphabet: &[u8] = b"\x1b]\\\x07;0123456789ABCDabc=/:\r\n \xe2\x9c\x93";
+
+    for _ in 0..500 {
+        let len = (next() % 256) as usize;
+        let data: Vec<u8> = (0..len)
+            .map(|_| alphabet[(next() % alphabet.len() as u64) as usize])
+            .collect();
+
+        // Same output regardless of how the stream is split.
+        let (whole_text, whole_events) = feed_chunked(&data, data.len());
+        let chunk = 1 + (next() % 17) as usize;
+        let (split_text, split_events) = feed_chunked(&data, chunk);
+        assert_eq!(whole_text, split_text);
+        assert_eq!(format!("{:?}", whole_events), format!("{:?}", split_events));
+
+        // Text never leaks an OSC introducer that was completed.
+        let mut parser = OscParser::new();
+        let mut tokens = parser.feed(&data);
+        tokens.extend(parser.reset_pending());
+        assert!(!parser.in_sequence());
+        for token in tokens {
+            if let OscToken::Text(t) = token {
+                assert!(!t.contains("\x1b]133;A\x07"));
+            }
+        }
+    }
+}
@@ -8,7 +8,8 @@ use serde_json::Value;
 async fn test_service_orchestration_health() -> anyhow::Result<()> {
     println!("\n\x1B[1;35m[TOS ORCHESTRATION TEST]\x1B[0m");
     
-    let socket_path = "/tmp/brain.sock";
+    let socket_path = tos_common::daemon::brain_socket();
+    let socket_path = socket_path.as_str();
     
     // 1. Resolve Port Map from Discovery Gate
     println!("Resolving tactical service constellation via Discovery Gate ({}) ...", socket_path);
@@ -19,15 +19,14 @@ async fn test_session_persistence_lifecycle() -> anyhow::Result<()> {
     
     let sid = state.lock().unwrap().sectors[0].id;
     let hid = state.lock().unwrap().sectors[0].hubs[0].id;
-    let shell_api = Arc::new(std::sync::Mutex::new(
-        tos_common::brain::shell::ShellApi::new(
-            state.clone(), 
-            modules, 
-            services.ai.clone(), 
-            services.heuristic.clone(), 
-            sid, hid
-        ).unwrap()
-    ));
+    let mut shell_pool = tos_common::brain::shell::ShellPool::new(
+        state.clone(),
+        modules,
+        services.ai.clone(),
+        services.heuristic.clone(),
+    );
+    shell_pool.spawn(sid, hid).unwrap();
+    let shell_api = Arc::new(std::sync::Mutex::new(shell_pool));
 
     let handler = IpcHandler::new(state.clone(), shell_api, services.clone());
     let sessions_dir = services.session.sessions_dir();
@@ -0,0 +1,194 @@
+//! §3.3.8: Named IPC commands and their handlers.
+//!
+//! Every `prefix:payload` command and JSON-RPC method is an entry here: the
+//! handler plus the metadata Faces and the `tos` CLI use to list and
+//! complete commands (help text, typed parameters, minimum role, and which
+//! service or module provided it). The `IpcHandler` services register the
+//! built-in set; modules add theirs from `[[commands]]` in `module.toml`.
+
+use crate::brain::ipc_handler::IpcHandler;
+use crate::collaboration::ParticipantRole;
+use crate::rpc::{Param, ParamKind, RpcError};
+use crate::services::auth::ClientIdentity;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// What a handler gets for one call.
+pub struct CommandContext<'a> {
+    pub ipc: &'a IpcHandler,
+    pub identity: &'a ClientIdentity,
+    /// Everything after the first `:`; for JSON-RPC calls, the decoded
+    /// arguments joined with `;`.
+    pub payload: &'a str,
+    /// Arguments in declaration order. Legacy calls split the payload on
+    /// `;`; JSON-RPC arguments are passed verbatim.
+    pub args: &'a [&'a str],
+}
+
+impl<'a> CommandContext<'a> {
+    pub fn arg(&self, index: usize) -> Option<&'a str> {
+        self.args.get(index).copied()
+    }
+
+    /// Argument `index` and every one after it, joined with `;`: the whole
+    /// of a last parameter that may itself contain `;` in a legacy call.
+    pub fn rest(&self, index: usize) -> Option<String> {
+        (index < self.args.len()).then(|| self.args[index..].join(";"))
+    }
+}
+
+pub type CommandHandler = Arc<dyn Fn(&CommandContext<'_>) -> String + Send + Sync>;
+
+/// Where a command comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CommandSource {
+    /// Registered by one of the Brain's services.
+    Builtin,
+    /// Answered by a remote connection rather than the registry (§3.3.6).
+    Connection,
+    Module {
+        id: String,
+    },
+}
+
+/// Metadata of one command, as returned by `command_list`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandSpec {
+    pub name: String,
+    pub help: String,
+    pub params: Vec<Param>,
+    /// Least privileged role allowed to run it (§12.3).
+    pub role: ParticipantRole,
+    pub source: CommandSource,
+}
+
+impl CommandSpec {
+    /// A built-in command with no parameters that Operators may run.
+    pub fn new(name: impl Into<String>, help: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            help: help.into(),
+            params: Vec::new(),
+            role: ParticipantRole::Operator,
+            source: CommandSource::Builtin,
+        }
+    }
+
+    pub fn param(mut self, name: &str, kind: ParamKind) -> Self {
+        self.params.push(Param::new(name, kind, true));
+        self
+    }
+
+    pub fn optional(mut self, name: &str, kind: ParamKind) -> Self {
+        self.params.push(Param::new(name, kind, false));
+        self
+    }
+
+    pub fn role(mut self, role: ParticipantRole) -> Self {
+        self.role = role;
+        self
+    }
+
+    pub fn source(mut self, source: CommandSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Check JSON-RPC `params` against the declared parameters and turn
+    /// them into the handler's argument list (§3.3.7).
+    pub fn decode(&self, params: Option<&serde_json::Value>) -> Result<Vec<String>, RpcError> {
+        crate::rpc::decode_params(&self.name, &self.params, params)
+    }
+}
+
+struct Entry {
+    spec: CommandSpec,
+    handler: CommandHandler,
+}
+
+/// §3.3.8: The commands the Brain answers, keyed by name.
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: BTreeMap<String, Entry>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a command. Names are unique; registering one twice is an error.
+    pub fn register<F>(&mut self, spec: CommandSpec, handler: F) -> Result<(), String>
+    where
+        F: Fn(&CommandContext<'_>) -> String + Send + Sync + 'static,
+    {
+        if spec.name.is_empty()
+            || spec.name.contains([':', ';'])
+            || spec.name.contains(char::is_whitespace)
+        {
+            return Err(format!("ERROR: Invalid command name '{}'", spec.name));
+        }
+        if self.commands.contains_key(&spec.name) {
+            return Err(format!(
+                "ERROR: Command '{}' is already registered",
+                spec.name
+            ));
+        }
+        self.commands.insert(
+            spec.name.clone(),
+            Entry {
+                spec,
+                handler: Arc::new(handler),
+            },
+        );
+        Ok(())
+    }
+
+    /// Register a built-in command; a clash is a programming error.
+    pub(crate) fn add<F>(&mut self, spec: CommandSpec, handler: F)
+    where
+        F: Fn(&CommandContext<'_>) -> String + Send + Sync + 'static,
+    {
+        if let Err(e) = self.register(spec, handler) {
+            panic!("{}", e);
+        }
+    }
+
+    pub fn unregister(&mut self, name: &str) -> Option<CommandSpec> {
+        self.commands.remove(name).map(|e| e.spec)
+    }
+
+    /// Drop every command a module registered.
+    pub fn unregister_module(&mut self, module_id: &str) -> usize {
+        let before = self.commands.len();
+        self.commands.retain(
+            |_, e| !matches!(&e.spec.source, CommandSource::Module { id } if id == module_id),
+        );
+        before - self.commands.len()
+    }
+
+    pub fn spec(&self, name: &str) -> Option<&CommandSpec> {
+        self.commands.get(name).map(|e| &e.spec)
+    }
+
+    pub fn handler(&self, name: &str) -> Option<(ParticipantRole, CommandHandler)> {
+        self.commands
+            .get(name)
+            .map(|e| (e.spec.role, e.handler.clone()))
+    }
+
+    /// All commands, sorted by name.
+    pub fn specs(&self) -> impl Iterator<Item = &CommandSpec> {
+        self.commands.values().map(|e| &e.spec)
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+}
@@ -0,0 +1,790 @@
+//! AI behavior, backend, thought, queue and agent stack commands.
+
+use super::*;
+use crate::brain::command_registry::{CommandRegistry, CommandSpec};
+use crate::collaboration::ParticipantRole;
+use crate::rpc::ParamKind;
+
+pub(super) fn register(r: &mut CommandRegistry) {
+    r.add(
+        CommandSpec::new("ai_suggestion_accept", "Accept the pending AI suggestion"),
+        |cx| cx.ipc.handle_ai_suggestion_accept(),
+    );
+    r.add(
+        CommandSpec::new(
+            "ai_stage_command",
+            "Stage an AI-proposed command in the prompt",
+        )
+        .param("staged", ParamKind::Json),
+        |cx| cx.ipc.handle_ai_stage_command(cx.payload),
+    );
+    r.add(
+        CommandSpec::new(
+            "ai_tool_call",
+            "Run a tool call on behalf of an AI behavior",
+        )
+        .param("behavior_id", ParamKind::Text)
+        .param("call", ParamKind::Json),
+        |cx| cx.ipc.handle_ai_tool_call(cx.arg(0), cx.rest(1).as_deref()),
+    );
+    r.add(
+        CommandSpec::new("ai_behavior_enable", "Enable an AI behavior")
+            .param("behavior_id", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_behavior_enable(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("ai_behavior_disable", "Disable an AI behavior")
+            .param("behavior_id", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_behavior_disable(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "ai_behavior_configure",
+            "Set a configuration value of an AI behavior",
+        )
+        .param("behavior_id", ParamKind::Text)
+        .param("key", ParamKind::Text)
+        .param("value", ParamKind::Text),
+        |cx| {
+            cx.ipc
+                .handle_ai_behavior_configure(cx.arg(0), cx.arg(1), cx.arg(2))
+        },
+    );
+    r.add(
+        CommandSpec::new("ai_chip_stage", "Show an AI chip in the system log")
+            .param("text", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_chip_stage(cx.payload),
+    );
+    r.add(
+        CommandSpec::new("ai_chip_dismiss", "Dismiss an AI chip").optional("id", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_chip_dismiss(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("ai_thought_expand", "Expand an AI thought")
+            .param("thought_id", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_thought_expand(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("ai_thought_dismiss", "Dismiss an AI thought")
+            .param("thought_id", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_thought_dismiss(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "ai_thought_dismiss_permanent",
+            "Dismiss an AI thought and never show it again",
+        )
+        .param("thought_id", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_thought_dismiss_permanent(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "ai_context_request",
+            "Context fields an AI behavior may read, as JSON",
+        )
+        .param("behavior_id", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_context_request(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("ai_backend_set_default", "Set the default AI backend")
+            .param("backend_id", ParamKind::Text)
+            .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_ai_backend_set_default(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "ai_backend_set_behavior",
+            "Route an AI behavior to a backend",
+        )
+        .param("behavior_id", ParamKind::Text)
+        .param("backend_id", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_backend_set_behavior(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new(
+            "ai_backend_clear_behavior",
+            "Route an AI behavior back to the default backend",
+        )
+        .param("behavior_id", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_backend_clear_behavior(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("ai_history_clear", "Clear the active hub's AI history"),
+        |cx| cx.ipc.handle_ai_history_clear(),
+    );
+    r.add(
+        CommandSpec::new(
+            "ai_pattern_set",
+            "Set the trigger pattern of an AI behavior",
+        )
+        .param("behavior_id", ParamKind::Text)
+        .param("pattern", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_pattern_set(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("ai_pattern_get", "Trigger pattern of an AI behavior")
+            .param("behavior_id", ParamKind::Text)
+            .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_ai_pattern_get(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "ai_history_append",
+            "Append an assistant message to the AI history",
+        )
+        .param("message", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_history_append(cx.payload, "assistant"),
+    );
+    r.add(
+        CommandSpec::new("ai_submit", "Send a query to the AI").param("query", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_submit(cx.payload),
+    );
+    r.add(
+        CommandSpec::new(
+            "ai_agent_stack_push",
+            "Push an agent onto the active agent stack",
+        )
+        .param("agent_id", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_agent_stack_push(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "ai_agent_stack_pop",
+            "Pop the top agent from the agent stack",
+        ),
+        |cx| cx.ipc.handle_ai_agent_stack_pop(),
+    );
+    r.add(
+        CommandSpec::new("ai_agent_stack_clear", "Clear the agent stack"),
+        |cx| cx.ipc.handle_ai_agent_stack_clear(),
+    );
+    r.add(
+        CommandSpec::new("ai_curator_enable", "Enable a curator module")
+            .param("curator_id", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_curator_enable(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("ai_curator_disable", "Disable a curator module")
+            .param("curator_id", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_curator_disable(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("ai_disable_all", "Disable every AI behavior"),
+        |cx| cx.ipc.handle_ai_disable_all(),
+    );
+    r.add(
+        CommandSpec::new("ai_enable_all", "Enable every AI behavior"),
+        |cx| cx.ipc.handle_ai_enable_all(),
+    );
+    r.add(
+        CommandSpec::new(
+            "ai_predict_command",
+            "Predict the rest of a partial command",
+        )
+        .param("partial", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_predict_command(cx.payload),
+    );
+    r.add(
+        CommandSpec::new(
+            "ai_thought_stage",
+            "Add or update an AI thought in the active hub",
+        )
+        .param("thought", ParamKind::Json),
+        |cx| cx.ipc.handle_ai_thought_stage(cx.payload),
+    );
+    r.add(
+        CommandSpec::new(
+            "ai_queue_push",
+            "Queue an AI request for when a backend is reachable",
+        )
+        .param("request", ParamKind::Json),
+        |cx| cx.ipc.handle_ai_queue_push(cx.payload),
+    );
+    r.add(
+        CommandSpec::new("ai_queue_get", "Queued AI requests as JSON")
+            .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_ai_queue_get(),
+    );
+    r.add(
+        CommandSpec::new("ai_queue_clear", "Drop all queued AI requests"),
+        |cx| cx.ipc.handle_ai_queue_clear(),
+    );
+    r.add(
+        CommandSpec::new("ai_plan", "Ask the AI for a plan").param("prompt", ParamKind::Text),
+        |cx| cx.ipc.handle_ai_plan(cx.payload),
+    );
+    r.add(
+        CommandSpec::new("ai_roadmap_plan", "Ask the AI for a roadmap plan"),
+        |cx| cx.ipc.handle_ai_roadmap_plan(),
+    );
+    r.add(
+        CommandSpec::new("ai_dream_consolidate", "Consolidate AI memory"),
+        |cx| cx.ipc.handle_ai_dream_consolidate(),
+    );
+    r.add(
+        CommandSpec::new("ai_isolated_exec", "Run a command in an isolated sandbox")
+            .param("command", ParamKind::Text)
+            .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_ai_isolated_exec(cx.payload),
+    );
+    r.add(
+        CommandSpec::new(
+            "ai_archive_interaction",
+            "Archive an AI prompt and response",
+        )
+        .param("behavior_id", ParamKind::Text)
+        .param("prompt", ParamKind::Text)
+        .param("response", ParamKind::Text),
+        |cx| match (cx.arg(0), cx.arg(1), cx.rest(2)) {
+            (Some(behavior_id), Some(prompt), Some(response)) => cx
+                .ipc
+                .handle_ai_archive_interaction(behavior_id, prompt, &response),
+            _ => "ERROR: Malformed archive request".to_string(),
+        },
+    );
+}
+
+impl IpcHandler {
+    pub(super) fn handle_ai_agent_stack_push(&self, agent_id: Option<&str>) -> String {
+        let id = match agent_id {
+            Some(i) => i.to_string(),
+            None => return "ERROR: Missing agent ID".to_string(),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        state.active_agent_stack.retain(|a| a != &id); // Remove if already present to move to top
+        state.active_agent_stack.push(id.clone());
+        state.version += 1;
+        format!("AGENT_STACKED: {}", id)
+    }
+
+    pub(super) fn handle_ai_agent_stack_pop(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        if let Some(id) = state.active_agent_stack.pop() {
+            state.version += 1;
+            format!("AGENT_UNSTACKED: {}", id)
+        } else {
+            "ERROR: Stack is empty".to_string()
+        }
+    }
+
+    pub(super) fn handle_ai_agent_stack_clear(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        state.active_agent_stack.clear();
+        state.version += 1;
+        "AGENT_STACK_CLEARED".to_string()
+    }
+
+    pub(super) fn handle_ai_curator_enable(&self, curator_id: Option<&str>) -> String {
+        let id = match curator_id {
+            Some(i) => i.to_string(),
+            None => return "ERROR: Missing curator ID".to_string(),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if !state.active_curators.contains(&id) {
+            state.active_curators.push(id.clone());
+            state.version += 1;
+            format!("CURATOR_ENABLED: {}", id)
+        } else {
+            format!("CURATOR_ALREADY_ENABLED: {}", id)
+        }
+    }
+
+    pub(super) fn handle_ai_curator_disable(&self, curator_id: Option<&str>) -> String {
+        let id = match curator_id {
+            Some(i) => i.to_string(),
+            None => return "ERROR: Missing curator ID".to_string(),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if state.active_curators.contains(&id) {
+            state.active_curators.retain(|a| a != &id);
+            state.version += 1;
+            format!("CURATOR_DISABLED: {}", id)
+        } else {
+            format!("CURATOR_NOT_ENABLED: {}", id)
+        }
+    }
+
+    pub(super) fn handle_ai_disable_all(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        for b in state.ai_behaviors.iter_mut() {
+            b.enabled = false;
+        }
+        state.version += 1;
+        "AI_DISABLED_ALL".to_string()
+    }
+
+    pub(super) fn handle_ai_enable_all(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        for b in state.ai_behaviors.iter_mut() {
+            b.enabled = true;
+        }
+        state.version += 1;
+        "AI_ENABLED_ALL".to_string()
+    }
+
+    pub(super) fn handle_ai_submit(&self, query: &str) -> String {
+        let ai = self.services.ai.clone();
+        let query_owned = query.to_string();
+        self.handle_ai_history_append(&query_owned, "user");
+        tokio::spawn(async move {
+            let _ = ai.query(&query_owned).await;
+        });
+        "AI_SUBMITTED".to_string()
+    }
+
+    pub(super) fn handle_ai_suggestion_accept(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        let s_idx = state.active_sector_index;
+        if let Some(sector) = state.sectors.get_mut(s_idx) {
+            let h_idx = sector.active_hub_index;
+            if let Some(hub) = sector.hubs.get_mut(h_idx) {
+                if let Some(cmd) = hub.staged_command.take() {
+                    hub.prompt = cmd;
+                    hub.ai_explanation = None;
+                }
+            }
+        }
+        "AI_SUGGESTION_ACCEPTED".to_string()
+    }
+
+    pub(super) fn handle_ai_queue_push(&self, payload: &str) -> String {
+        if let Ok(req) = serde_json::from_str::<QueuedAiRequest>(payload) {
+            let mut state = self.state.lock().unwrap();
+            state.ai_offline_queue.push(req);
+            return "AI_QUEUE_PUSHED".to_string();
+        }
+        "ERROR: Invalid JSON for ai_queue_push".to_string()
+    }
+
+    pub(super) fn handle_ai_queue_get(&self) -> String {
+        let state = self.state.lock().unwrap();
+        serde_json::to_string(&state.ai_offline_queue).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    pub(super) fn handle_ai_queue_clear(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        state.ai_offline_queue.clear();
+        "AI_QUEUE_CLEARED".to_string()
+    }
+
+    pub(super) fn handle_ai_stage_command(&self, payload: &str) -> String {
+        if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(payload) {
+            let mut state = self.state.lock().unwrap();
+            let s_idx = state.active_sector_index;
+            if let Some(sector) = state.sectors.get_mut(s_idx) {
+                let h_idx = sector.active_hub_index;
+                if let Some(hub) = sector.hubs.get_mut(h_idx) {
+                    if let Some(cmd) = parsed.get("command").and_then(|v| v.as_str()) {
+                        hub.staged_command = Some(cmd.to_string());
+                    }
+                    if let Some(expl) = parsed.get("explanation").and_then(|v| v.as_str()) {
+                        hub.ai_explanation = Some(expl.to_string());
+                    }
+                }
+            }
+            return "AI_COMMAND_STAGED".to_string();
+        }
+        "ERROR: Invalid JSON for ai_stage_command".to_string()
+    }
+
+    pub(super) fn handle_ai_tool_call(
+        &self,
+        behavior_id: Option<&str>,
+        call: Option<&str>,
+    ) -> String {
+        let behavior_id = behavior_id.unwrap_or("");
+        let json_payload = match call {
+            Some(c) if !c.is_empty() => c,
+            _ => return "ERROR: Missing tool arguments".to_string(),
+        };
+
+        if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_payload) {
+            let tool_name = parsed.get("name").and_then(|v| v.as_str()).unwrap_or("");
+
+            {
+                let state = self.state.lock().unwrap();
+                if !self
+                    .services
+                    .ai
+                    .validate_tool_call(&state, behavior_id, tool_name)
+                {
+                    tracing::warn!(
+                        "[TOOL REGISTRY] Denied tool '{}' for behavior '{}'",
+                        tool_name,
+                        behavior_id
+                    );
+                    return format!(
+                        "ERROR: Tool '{}' not permitted for behavior '{}'",
+                        tool_name, behavior_id
+                    );
+                }
+            }
+
+            // Route recognized tools
+            if tool_name == "exec_cmd" {
+                if let Some(args) = parsed.get("args") {
+                    let cmd = args.get("cmd").and_then(|v| v.as_str()).unwrap_or("");
+                    let explanation = args
+                        .get("explanation")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("Invoked via exec_cmd tool");
+                    let stage_payload = serde_json::json!({
+                        "command": cmd,
+                        "explanation": explanation
+                    })
+                    .to_string();
+                    return self.handle_ai_stage_command(&stage_payload);
+                }
+            } else if tool_name == "semantic_search" {
+                if let Some(args) = parsed.get("args") {
+                    let query = args.get("query").and_then(|v| v.as_str()).unwrap_or("");
+                    // §4.1: Hybrid ranking also finds exact names and
+                    // identifiers the embedding misses; the reply is
+                    // unchanged.
+                    self.handle_search(query);
+                    return "SEMANTIC_SEARCH_COMPLETED".to_string();
+                }
+            }
+
+            return format!(
+                "ERROR: Tool '{}' recognized but not implemented locally",
+                tool_name
+            );
+        }
+
+        "ERROR: Invalid JSON for ai_tool_call".to_string()
+    }
+
+    pub(super) fn handle_ai_pattern_set(
+        &self,
+        behavior_id: Option<&str>,
+        pattern: Option<&str>,
+    ) -> String {
+        if let (Some(id), Some(p)) = (behavior_id, pattern) {
+            let mut state = self.state.lock().unwrap();
+            state
+                .settings
+                .ai_patterns
+                .insert(id.to_string(), p.to_string());
+            let _ = self.services.settings.save(&state.settings);
+            return "AI_PATTERN_SET".to_string();
+        }
+        "ERROR: behavior_id and pattern required".to_string()
+    }
+
+    pub(super) fn handle_ai_pattern_get(&self, behavior_id: Option<&str>) -> String {
+        if let Some(id) = behavior_id {
+            let state = self.state.lock().unwrap();
+            return state
+                .settings
+                .ai_patterns
+                .get(id)
+                .cloned()
+                .unwrap_or_default();
+        }
+        let state = self.state.lock().unwrap();
+        serde_json::to_string(&state.settings.ai_patterns).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    pub(super) fn handle_ai_behavior_enable(&self, id: Option<&str>) -> String {
+        if let Some(id) = id {
+            let mut state = self.state.lock().unwrap();
+            if self.services.ai.enable_behavior(&mut state, id) {
+                return format!("AI_BEHAVIOR_ENABLED: {}", id);
+            }
+            return format!("ERROR: Behavior '{}' not registered", id);
+        }
+        "ERROR: Missing behavior_id".to_string()
+    }
+
+    pub(super) fn handle_ai_behavior_disable(&self, id: Option<&str>) -> String {
+        if let Some(id) = id {
+            let mut state = self.state.lock().unwrap();
+            if self.services.ai.disable_behavior(&mut state, id) {
+                return format!("AI_BEHAVIOR_DISABLED: {}", id);
+            }
+            return format!("ERROR: Behavior '{}' not registered", id);
+        }
+        "ERROR: Missing behavior_id".to_string()
+    }
+
+    pub(super) fn handle_ai_behavior_configure(
+        &self,
+        id: Option<&str>,
+        key: Option<&str>,
+        value: Option<&str>,
+    ) -> String {
+        if let (Some(id), Some(k), Some(v)) = (id, key, value) {
+            let mut state = self.state.lock().unwrap();
+            if self.services.ai.configure_behavior(&mut state, id, k, v) {
+                return format!("AI_BEHAVIOR_CONFIGURED: {}::{}={}", id, k, v);
+            }
+            return format!("ERROR: Behavior '{}' not registered", id);
+        }
+        "ERROR: Missing behavior_id, key, or value".to_string()
+    }
+
+    pub(super) fn handle_ai_chip_stage(&self, payload: &str) -> String {
+        // Stage an AI chip in the system_log. Payload is the chip text.
+        let mut state = self.state.lock().unwrap();
+        state.system_log.push(crate::TerminalLine {
+            text: format!("[AI CHIP] {}", payload),
+            priority: 1,
+            timestamp: chrono::Local::now(),
+            source: None,
+        });
+        format!("AI_CHIP_STAGED: {}", &payload[..payload.len().min(40)])
+    }
+
+    pub(super) fn handle_ai_chip_dismiss(&self, id: Option<&str>) -> String {
+        // Remove the most recent AI chip matching the id prefix from system_log
+        let prefix = format!("[AI CHIP] {}", id.unwrap_or(""));
+        let mut state = self.state.lock().unwrap();
+        let before = state.system_log.len();
+        state.system_log.retain(|l| !l.text.starts_with(&prefix));
+        let removed = before - state.system_log.len();
+        format!("AI_CHIP_DISMISSED: {} removed", removed)
+    }
+
+    pub(super) fn handle_ai_thought_expand(&self, thought_id: Option<&str>) -> String {
+        // Push an expanded thought chip to system_log
+        let id = thought_id.unwrap_or("unknown");
+        let mut state = self.state.lock().unwrap();
+        state.system_log.push(crate::TerminalLine {
+            text: format!("[AI THOUGHT:EXPANDED] id={}", id),
+            priority: 1,
+            timestamp: chrono::Local::now(),
+            source: None,
+        });
+        format!("AI_THOUGHT_EXPANDED: {}", id)
+    }
+
+    pub(super) fn handle_ai_thought_dismiss(&self, thought_id: Option<&str>) -> String {
+        let id = thought_id.unwrap_or("unknown");
+        let prefix = format!("[AI THOUGHT:EXPANDED] id={}", id);
+        let mut state = self.state.lock().unwrap();
+        state.system_log.retain(|l| l.text != prefix);
+        format!("AI_THOUGHT_DISMISSED: {}", id)
+    }
+
+    pub(super) fn handle_ai_thought_dismiss_permanent(&self, thought_id: Option<&str>) -> String {
+        let id = thought_id.unwrap_or("unknown");
+        // Mark dismissal in settings so it persists across sessions
+        let key = format!("tos.ai.thought.dismissed.{}", id);
+        let mut state = self.state.lock().unwrap();
+        state.settings.global.insert(key, "true".to_string());
+        state
+            .system_log
+            .retain(|l| !l.text.contains(&format!("id={}", id)));
+        format!("AI_THOUGHT_DISMISSED_PERMANENT: {}", id)
+    }
+
+    pub(super) fn handle_ai_context_request(&self, behavior_id: Option<&str>) -> String {
+        let state = self.state.lock().unwrap();
+        let ctx = crate::services::ai::build_context(&state);
+        let behavior_id = behavior_id.unwrap_or("*");
+
+        // Look up declared fields for the behavior; default to all if not found
+        let fields = state
+            .ai_behaviors
+            .iter()
+            .find(|b| b.id == behavior_id)
+            .map(|b| b.context_fields.clone())
+            .unwrap_or_else(|| {
+                vec![
+                    "cwd".to_string(),
+                    "sector_name".to_string(),
+                    "shell".to_string(),
+                    "terminal_tail".to_string(),
+                    "last_command".to_string(),
+                    "mode".to_string(),
+                ]
+            });
+
+        let context_entries = ctx.filter_to_fields(&fields);
+        let json = serde_json::to_string(&context_entries).unwrap_or_else(|_| "[]".to_string());
+        format!("AI_CONTEXT:{}", json)
+    }
+
+    pub(super) fn handle_ai_backend_set_default(&self, backend_id: Option<&str>) -> String {
+        if let Some(id) = backend_id {
+            let mut state = self.state.lock().unwrap();
+            self.services.ai.set_default_backend(&mut state, id);
+            return format!("AI_DEFAULT_BACKEND_SET: {}", id);
+        }
+        "ERROR: Missing backend_id".to_string()
+    }
+
+    pub(super) fn handle_ai_backend_set_behavior(
+        &self,
+        behavior_id: Option<&str>,
+        backend_id: Option<&str>,
+    ) -> String {
+        if let (Some(bid), Some(backend)) = (behavior_id, backend_id) {
+            let mut state = self.state.lock().unwrap();
+            if self
+                .services
+                .ai
+                .set_behavior_backend(&mut state, bid, backend)
+            {
+                return format!("AI_BEHAVIOR_BACKEND_SET: {}→{}", bid, backend);
+            }
+            return format!("ERROR: Behavior '{}' not registered", bid);
+        }
+        "ERROR: Missing behavior_id or backend_id".to_string()
+    }
+
+    pub(super) fn handle_ai_backend_clear_behavior(&self, behavior_id: Option<&str>) -> String {
+        if let Some(bid) = behavior_id {
+            let mut state = self.state.lock().unwrap();
+            if self.services.ai.clear_behavior_backend(&mut state, bid) {
+                return format!("AI_BEHAVIOR_BACKEND_CLEARED: {}", bid);
+            }
+            return format!("ERROR: Behavior '{}' not registered", bid);
+        }
+        "ERROR: Missing behavior_id".to_string()
+    }
+
+    pub(super) fn handle_ai_history_clear(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        let idx = state.active_sector_index;
+        if let Some(sector) = state.sectors.get_mut(idx) {
+            let h_idx = sector.active_hub_index;
+            if let Some(hub) = sector.hubs.get_mut(h_idx) {
+                hub.ai_history.clear();
+                state.version += 1;
+                return "AI_HISTORY_CLEARED".to_string();
+            }
+        }
+        "ERROR: Hub not found".to_string()
+    }
+
+    pub(super) fn handle_ai_history_append(&self, message: &str, role: &str) -> String {
+        let mut state = self.state.lock().unwrap();
+        let idx = state.active_sector_index;
+        if let Some(sector) = state.sectors.get_mut(idx) {
+            let h_idx = sector.active_hub_index;
+            if let Some(hub) = sector.hubs.get_mut(h_idx) {
+                hub.ai_history.push(crate::AiMessage {
+                    role: role.to_string(),
+                    content: message.to_string(),
+                    timestamp: chrono::Local::now(),
+                });
+                if hub.ai_history.len() > 200 {
+                    hub.ai_history.remove(0);
+                }
+                state.version += 1;
+                return "AI_HISTORY_APPENDED".to_string();
+            }
+        }
+        "ERROR: Hub not found".to_string()
+    }
+
+    pub(super) fn handle_ai_predict_command(&self, partial: &str) -> String {
+        let ai = self.services.ai.clone();
+        let partial_str = partial.to_string();
+
+        tokio::spawn(async move {
+            match ai.predict_command(&partial_str).await {
+                Ok(_prediction) => {
+                    // Predictions are handled via state updates in a real system,
+                    // but for ghost text we should probably emit a specific IPC back to the Face.
+                    // However, AIService already has access to IPC.
+                    // Actually, predict_command in AiService should probably dispatch itself.
+                    // Let's refine AiService::predict_command to dispatch the result.
+                }
+                Err(e) => {
+                    tracing::error!("[IpcHandler] AI Prediction failed: {}", e);
+                }
+            }
+        });
+        "AI_PREDICT_ACCEPTED".to_string()
+    }
+
+    pub(super) fn handle_ai_thought_stage(&self, payload: &str) -> String {
+        if let Ok(thought) = serde_json::from_str::<crate::AiThought>(payload) {
+            let mut state = self.state.lock().unwrap();
+            let idx = state.active_sector_index;
+            if let Some(sector) = state.sectors.get_mut(idx) {
+                let h_idx = sector.active_hub_index;
+                if let Some(hub) = sector.hubs.get_mut(h_idx) {
+                    // Update if exists, else push
+                    if let Some(existing) =
+                        hub.active_thoughts.iter_mut().find(|t| t.id == thought.id)
+                    {
+                        *existing = thought;
+                    } else {
+                        hub.active_thoughts.push(thought);
+                    }
+                    state.version += 1;
+                    return "AI_THOUGHT_STAGED".to_string();
+                }
+            }
+        }
+        "ERROR: Invalid thought JSON".to_string()
+    }
+
+    pub(super) fn handle_ai_plan(&self, prompt: &str) -> String {
+        let ai = self.services.ai.clone();
+        let prompt_str = prompt.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = ai.vibe_plan(&prompt_str).await {
+                tracing::error!("[IpcHandler] AI Plan failed: {}", e);
+            }
+        });
+        "AI_PLAN_ACCEPTED".to_string()
+    }
+
+    pub fn handle_ai_isolated_exec(&self, command: &str) -> String {
+        let cwd = {
+            let state = self.state.lock().unwrap();
+            let idx = state.active_sector_index;
+            state
+                .sectors
+                .get(idx)
+                .and_then(|s| s.hubs.get(s.active_hub_index))
+                .map(|h| h.current_directory.clone())
+                .unwrap_or_default()
+        };
+
+        match crate::brain::shell::ShellApi::exec_isolated(command, cwd) {
+            Ok(output) => output,
+            Err(e) => format!("ERROR: Isolated exec failed: {}", e),
+        }
+    }
+
+    pub(super) fn handle_ai_roadmap_plan(&self) -> String {
+        let ai = self.services.ai.clone();
+        tokio::spawn(async move {
+            if let Err(e) = ai.roadmap_plan().await {
+                tracing::error!("[IpcHandler] Roadmap Plan failed: {}", e);
+            }
+        });
+        "AI_ROADMAP_PLAN_STARTED".to_string()
+    }
+
+    pub(super) fn handle_ai_dream_consolidate(&self) -> String {
+        let ai = self.services.ai.clone();
+        tokio::spawn(async move {
+            if let Err(e) = ai.dream_consolidate().await {
+                tracing::error!("[IpcHandler] Dream Consolidate failed: {}", e);
+            }
+        });
+        "AI_DREAM_CONSOLIDATE_STARTED".to_string()
+    }
+
+    pub(super) fn handle_ai_archive_interaction(
+        &self,
+        behavior_id: &str,
+        prompt: &str,
+        response: &str,
+    ) -> String {
+        self.services
+            .logger
+            .archive_ai(behavior_id, prompt, response);
+        "OK".to_string()
+    }
+}
@@ -0,0 +1,130 @@
+//! Earcon, ambient sound and speech commands.
+
+use super::*;
+use crate::brain::command_registry::{CommandRegistry, CommandSpec};
+use crate::rpc::ParamKind;
+
+pub(super) fn register(r: &mut CommandRegistry) {
+    r.add(
+        CommandSpec::new("play_earcon", "Play an earcon").param("name", ParamKind::Text),
+        |cx| cx.ipc.handle_play_earcon(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("audio_ambient_start", "Start an ambient soundscape")
+            .param("name", ParamKind::Text),
+        |cx| cx.ipc.handle_audio_ambient_start(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("audio_ambient_stop", "Stop the ambient soundscape"),
+        |cx| cx.ipc.handle_audio_ambient_stop(),
+    );
+    r.add(
+        CommandSpec::new("audio_volume_set", "Set the volume of an audio layer")
+            .param("layer", ParamKind::Text)
+            .param("volume", ParamKind::Number),
+        |cx| cx.ipc.handle_audio_volume_set(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("audio_spatial_earcon", "Play an earcon at a position")
+            .param("name", ParamKind::Text)
+            .param("x", ParamKind::Number)
+            .param("y", ParamKind::Number)
+            .param("z", ParamKind::Number),
+        |cx| {
+            cx.ipc
+                .handle_audio_spatial_earcon(cx.arg(0), cx.arg(1), cx.arg(2), cx.arg(3))
+        },
+    );
+    r.add(
+        CommandSpec::new("audio_load_module", "Load an audio module")
+            .param("module_id", ParamKind::Text),
+        |cx| cx.ipc.handle_audio_load_module(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("audio_voice_play", "Speak text aloud").param("text", ParamKind::Text),
+        |cx| cx.ipc.handle_audio_voice_play(Some(cx.payload)),
+    );
+}
+
+impl IpcHandler {
+    pub(super) fn handle_play_earcon(&self, name: Option<&str>) -> String {
+        if let Some(n) = name {
+            self.services.audio.play_earcon(n);
+            return format!("PLAYING_EARCON: {}", n);
+        }
+        "ERROR: Invalid earcon name".to_string()
+    }
+
+    pub(super) fn handle_audio_ambient_start(&self, name: Option<&str>) -> String {
+        if let Some(n) = name {
+            self.services.audio.play_ambient(n);
+            return format!("AUDIO_AMBIENT_STARTED: {}", n);
+        }
+        "ERROR: Missing ambient name".to_string()
+    }
+
+    pub(super) fn handle_audio_ambient_stop(&self) -> String {
+        self.services.audio.stop_ambient();
+        "AUDIO_AMBIENT_STOPPED".to_string()
+    }
+
+    pub(super) fn handle_audio_volume_set(
+        &self,
+        layer: Option<&str>,
+        volume: Option<&str>,
+    ) -> String {
+        if let (Some(l), Some(v)) = (layer, volume) {
+            if let Ok(vol) = v.parse::<f32>() {
+                let layer_enum = match l {
+                    "ambient" => crate::services::audio::AudioLayer::Ambient,
+                    "tactical" => crate::services::audio::AudioLayer::Tactical,
+                    "voice" => crate::services::audio::AudioLayer::Voice,
+                    _ => return "ERROR: Invalid audio layer".to_string(),
+                };
+                self.services.audio.set_volume(layer_enum, vol);
+                return format!("AUDIO_VOLUME_SET: {} -> {}", l, vol);
+            }
+        }
+        "ERROR: Invalid arguments for audio_volume_set".to_string()
+    }
+
+    pub(super) fn handle_audio_voice_play(&self, text: Option<&str>) -> String {
+        if let Some(t) = text {
+            self.services.audio.play_voice(t);
+            return format!("AUDIO_VOICE_PLAYING: {}", t);
+        }
+        "ERROR: Missing text for voice".to_string()
+    }
+
+    pub(super) fn handle_audio_spatial_earcon(
+        &self,
+        name: Option<&str>,
+        x: Option<&str>,
+        y: Option<&str>,
+        z: Option<&str>,
+    ) -> String {
+        let name = match name {
+            Some(n) => n,
+            None => return "ERROR: Missing earcon name".to_string(),
+        };
+        let x_val = x.and_then(|v| v.parse::<f32>().ok()).unwrap_or(0.0);
+        let y_val = y.and_then(|v| v.parse::<f32>().ok()).unwrap_or(0.0);
+        let z_val = z.and_then(|v| v.parse::<f32>().ok()).unwrap_or(0.0);
+
+        self.services
+            .audio
+            .play_spatial_earcon(name, x_val, y_val, z_val);
+        "OK".to_string()
+    }
+
+    pub(super) fn handle_audio_load_module(&self, id: Option<&str>) -> String {
+        let id = match id {
+            Some(i) => i,
+            None => return "ERROR: Missing module ID".to_string(),
+        };
+        match self.services.audio.load_audio_module(id) {
+            Ok(_) => "AUDIO_MODULE_LOADED".to_string(),
+            Err(e) => format!("ERROR: {}", e),
+        }
+    }
+}
@@ -0,0 +1,386 @@
+//! Web Portal, collaboration role and remote sector commands (§12, §13).
+
+use super::*;
+use crate::brain::command_registry::{CommandRegistry, CommandSpec};
+use crate::collaboration::ParticipantRole;
+use crate::rpc::ParamKind;
+
+pub(super) fn register(r: &mut CommandRegistry) {
+    r.add(
+        CommandSpec::new(
+            "remote_ssh_connect",
+            "Open an SSH fallback sector to a host",
+        )
+        .param("host", ParamKind::Text)
+        .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_remote_ssh_connect(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("remote_ssh_disconnect", "Close the SSH fallback connection"),
+        |cx| cx.ipc.handle_remote_ssh_disconnect(),
+    );
+    r.add(
+        CommandSpec::new("remote_disconnect", "Disconnect a remote sector")
+            .param("sector_id", ParamKind::Uuid),
+        |cx| cx.ipc.handle_remote_disconnect(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "portal_create",
+            "Create a Web Portal token for the active sector",
+        )
+        .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_portal_create(),
+    );
+    r.add(
+        CommandSpec::new("portal_revoke", "Revoke a Web Portal token")
+            .param("token", ParamKind::Text)
+            .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_portal_revoke(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("webrtc_presence", "Relay a collaboration presence event")
+            .param("event", ParamKind::Json)
+            .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_webrtc_presence(cx.payload, cx.identity.role),
+    );
+    r.add(
+        CommandSpec::new(
+            "collaboration_role_set",
+            "Change a participant's role in a sector",
+        )
+        .param("sector_id", ParamKind::Uuid)
+        .param("user_id", ParamKind::Uuid)
+        .param("role", ParamKind::Text)
+        .role(ParticipantRole::CoOwner),
+        |cx| {
+            cx.ipc
+                .handle_collaboration_role_set(cx.arg(0), cx.arg(1), cx.arg(2))
+        },
+    );
+    r.add(
+        CommandSpec::new(
+            "collaboration_participant_remove",
+            "Remove a participant from a sector",
+        )
+        .param("sector_id", ParamKind::Uuid)
+        .param("user_id", ParamKind::Uuid)
+        .role(ParticipantRole::CoOwner),
+        |cx| {
+            cx.ipc
+                .handle_collaboration_participant_remove(cx.arg(0), cx.arg(1))
+        },
+    );
+}
+
+impl IpcHandler {
+    pub(super) fn handle_remote_disconnect(&self, id_str: Option<&str>) -> String {
+        if let Some(id_str) = id_str {
+            if let Ok(id) = Uuid::parse_str(id_str) {
+                let mut state = self.state.lock().unwrap();
+                if let Some(sector) = state.sectors.iter_mut().find(|s| s.id == id) {
+                    sector.disconnected = true;
+                    // In a full implementation we would spawn a timer.
+                    let state_clone = self.state.clone();
+                    let id_clone = id;
+                    tokio::spawn(async move {
+                        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+                        let mut state = state_clone.lock().unwrap();
+                        if let Some(sector) = state.sectors.iter().find(|s| s.id == id_clone) {
+                            if sector.disconnected {
+                                crate::brain::sector::SectorManager::close_sector(
+                                    &mut state, id_clone,
+                                );
+                            }
+                        }
+                    });
+                    return format!("REMOTE_DISCONNECTED: {}", id);
+                }
+            }
+        }
+        "ERROR: Invalid args for remote_disconnect".to_string()
+    }
+
+    pub(super) fn handle_portal_create(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let sector_id = if let Some(sector) = state.sectors.get(state.active_sector_index) {
+            sector.id
+        } else {
+            return "ERROR: No active sector".to_string();
+        };
+
+        // Drop lock before service call if possible (though PortalService has its own internal sync)
+        let token = self.services.portal.create_token(sector_id);
+        format!("PORTAL_CREATED: {}", token)
+    }
+
+    pub(super) fn handle_portal_revoke(&self, token: Option<&str>) -> String {
+        if let Some(t) = token {
+            self.services.portal.revoke_token(t);
+            return format!("PORTAL_REVOKED: {}", t);
+        }
+        "ERROR: Token required".to_string()
+    }
+
+    /// `connection_role` caps relayed guest commands, so a connection cannot
+    /// act with more authority than it holds by naming another participant.
+    pub(super) fn handle_webrtc_presence(
+        &self,
+        payload: &str,
+        connection_role: crate::collaboration::ParticipantRole,
+    ) -> String {
+        // Parse the incoming generic WebRTC payload structure
+        if let Ok(presence_event) =
+            serde_json::from_str::<crate::collaboration::WebRtcPayload>(payload)
+        {
+            let mut state = self.state.lock().unwrap();
+            let mut changed = false;
+
+            // Typically routed to active sector, but robust architecture would route via the remote host router
+            let active_idx = state.active_sector_index;
+            if active_idx < state.sectors.len() {
+                let sector = &mut state.sectors[active_idx];
+
+                match presence_event {
+                    crate::collaboration::WebRtcPayload::CursorSync { user, x, y, target } => {
+                        if let Some(participant) =
+                            sector.participants.iter_mut().find(|p| p.id == user)
+                        {
+                            participant.cursor_x = Some(x);
+                            participant.cursor_y = Some(y);
+                            participant.cursor_target = target;
+                            changed = true;
+                        }
+                    }
+                    crate::collaboration::WebRtcPayload::Presence {
+                        user,
+                        status,
+                        level,
+                        active_viewport_title,
+                        left_chip_state,
+                        right_chip_state,
+                    } => {
+                        if let Some(participant) =
+                            sector.participants.iter_mut().find(|p| p.id == user)
+                        {
+                            // Update existing tracked remote guest
+                            participant.status = status;
+                            participant.current_level = level;
+                            participant.viewport_title = active_viewport_title;
+                            participant.left_chip_state = left_chip_state;
+                            participant.right_chip_state = right_chip_state;
+                        } else {
+                            // Register new remote guest observation to the active sector
+                            sector.participants.push(crate::collaboration::Participant {
+                                id: user,
+                                alias: format!("Guest {}", &user.to_string()[..4]),
+                                status,
+                                role: crate::collaboration::ParticipantRole::Viewer,
+                                current_level: level,
+                                viewport_title: active_viewport_title,
+                                left_chip_state,
+                                right_chip_state,
+                                cursor_x: None,
+                                cursor_y: None,
+                                cursor_target: None,
+                                following: None,
+                            });
+                        }
+                        changed = true;
+                    }
+                    crate::collaboration::WebRtcPayload::Command { user, request } => {
+                        // Role Enforcement for Remote Commands (§13.2)
+                        let mut participant_role = None;
+                        if let Some(participant) = sector.participants.iter().find(|p| p.id == user)
+                        {
+                            participant_role = Some(participant.role.min(connection_role));
+                        }
+
+                        if let Some(role) = participant_role {
+                            let (prefix, _) = request.split_once(':').unwrap_or((&request, ""));
+                            if self.check_permission(role, prefix) {
+                                drop(state); // Drop lock before recursive call
+                                let result = self.handle_request(&request);
+                                state = self.state.lock().unwrap();
+                                tracing::info!(
+                                    "REMOTE_COMMAND: {} by {} (role={:?}) -> {}",
+                                    request,
+                                    user,
+                                    role,
+                                    result
+                                );
+                                changed = true;
+                            } else {
+                                tracing::warn!(
+                                    "PERMISSION_DENIED: {} by {} (role={:?})",
+                                    request,
+                                    user,
+                                    role
+                                );
+                            }
+                        }
+                    }
+                    crate::collaboration::WebRtcPayload::Following {
+                        follower,
+                        leader,
+                        sync,
+                    } => {
+                        if let Some(participant) =
+                            sector.participants.iter_mut().find(|p| p.id == follower)
+                        {
+                            participant.following = if sync { Some(leader) } else { None };
+                            changed = true;
+                        }
+                    }
+                    crate::collaboration::WebRtcPayload::SdpOffer { user, sdp } => {
+                        tracing::info!(
+                            "[WEBRTC] Received SDP Offer from {}: {}...",
+                            user,
+                            &sdp[..sdp.len().min(20)]
+                        );
+                        // In a full implementation, this would be routed to the target peer or handled by a media server.
+                        changed = false;
+                    }
+                    crate::collaboration::WebRtcPayload::SdpAnswer { user, sdp } => {
+                        tracing::info!(
+                            "[WEBRTC] Received SDP Answer from {}: {}...",
+                            user,
+                            &sdp[..sdp.len().min(20)]
+                        );
+                        changed = false;
+                    }
+                    crate::collaboration::WebRtcPayload::IceCandidate { user, candidate } => {
+                        tracing::info!(
+                            "[WEBRTC] Received ICE Candidate from {}: {}...",
+                            user,
+                            &candidate[..candidate.len().min(20)]
+                        );
+                        changed = false;
+                    }
+                    _ => {} // Other presence events unhandled in this exact route
+                }
+
+                if changed {
+                    state.version += 1;
+                }
+            }
+            "OK".to_string()
+        } else {
+            "ERROR: Invalid WebRTC payload".to_string()
+        }
+    }
+
+    pub(super) fn handle_collaboration_role_set(
+        &self,
+        sector_id: Option<&str>,
+        user_id: Option<&str>,
+        role_str: Option<&str>,
+    ) -> String {
+        if let (Some(sid_str), Some(uid_str), Some(r_str)) = (sector_id, user_id, role_str) {
+            if let (Ok(sid), Ok(uid)) = (Uuid::parse_str(sid_str), Uuid::parse_str(uid_str)) {
+                let role = match r_str {
+                    "viewer" => crate::collaboration::ParticipantRole::Viewer,
+                    "commenter" => crate::collaboration::ParticipantRole::Commenter,
+                    "operator" => crate::collaboration::ParticipantRole::Operator,
+                    "coowner" => crate::collaboration::ParticipantRole::CoOwner,
+                    _ => return "ERROR: Invalid role".to_string(),
+                };
+
+                let mut state = self.state.lock().unwrap();
+                if let Some(sector) = state.sectors.iter_mut().find(|s| s.id == sid) {
+                    if let Some(participant) = sector.participants.iter_mut().find(|p| p.id == uid)
+                    {
+                        participant.role = role;
+                        state.version += 1;
+                        return format!("ROLE_SET: {} -> {:?}", uid, role);
+                    }
+                }
+            }
+        }
+        "ERROR: Invalid arguments for role set".to_string()
+    }
+
+    pub(super) fn handle_collaboration_participant_remove(
+        &self,
+        sector_id: Option<&str>,
+        user_id: Option<&str>,
+    ) -> String {
+        if let (Some(sid_str), Some(uid_str)) = (sector_id, user_id) {
+            if let (Ok(sid), Ok(uid)) = (Uuid::parse_str(sid_str), Uuid::parse_str(uid_str)) {
+                let mut state = self.state.lock().unwrap();
+                if let Some(sector) = state.sectors.iter_mut().find(|s| s.id == sid) {
+                    let len_before = sector.participants.len();
+                    sector.participants.retain(|p| p.id != uid);
+                    if sector.participants.len() < len_before {
+                        state.version += 1;
+                        return format!("PARTICIPANT_REMOVED: {}", uid);
+                    }
+                }
+            }
+        }
+        "ERROR: Invalid arguments for participant remove".to_string()
+    }
+
+    pub(super) fn handle_remote_ssh_connect(&self, host: Option<&str>) -> String {
+        if let Some(h) = host {
+            let state = self.state.lock().unwrap();
+            let s_idx = state.active_sector_index;
+            let sector_id = state.sectors[s_idx].id;
+            let hub_id = state.sectors[s_idx].hubs[state.sectors[s_idx].active_hub_index].id;
+
+            drop(state); // Drop lock before connecting (spawns thread)
+
+            if let Err(e) = self
+                .services
+                .ssh
+                .connect(h, self.state.clone(), sector_id, hub_id)
+            {
+                return format!("ERROR: SSH connection failed: {}", e);
+            }
+
+            let mut state = self.state.lock().unwrap();
+            let h_idx = state.sectors[s_idx].active_hub_index;
+            let hub = &mut state.sectors[s_idx].hubs[h_idx];
+            hub.mode = crate::CommandHubMode::Ssh;
+            self.push_hub_line(hub, format!("SSH CONNECTED: {}", h));
+            state.version += 1;
+            return format!("SSH_CONNECT_OK: {}", h);
+        }
+        "ERROR: Missing SSH host".to_string()
+    }
+
+    /// Append a status line to `hub` the way shell output is: capped to
+    /// its buffer and streamed to `hub:<id>:output` subscribers (§3.3.6).
+    fn push_hub_line(&self, hub: &mut crate::CommandHub, text: String) {
+        let line = crate::TerminalLine {
+            text,
+            priority: 2,
+            timestamp: chrono::Local::now(),
+            source: None,
+        };
+        if let Ok(payload) = serde_json::to_value(&line) {
+            self.services
+                .events
+                .publish(format!("hub:{}:output", hub.id), payload);
+        }
+        hub.push_output_line(line);
+        hub.version += 1;
+    }
+
+    pub(super) fn handle_remote_ssh_disconnect(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        let s_idx = state.active_sector_index;
+        let h_idx = state.sectors[s_idx].active_hub_index;
+        let hub_id = state.sectors[s_idx].hubs[h_idx].id;
+        let hub = &mut state.sectors[s_idx].hubs[h_idx];
+
+        if hub.mode == crate::CommandHubMode::Ssh {
+            self.services.ssh.disconnect(&hub_id);
+            hub.mode = crate::CommandHubMode::Command;
+            self.push_hub_line(hub, "SSH DISCONNECTED".to_string());
+            state.version += 1;
+            return "SSH_DISCONNECT_OK".to_string();
+        }
+        "ERROR: Hub is not in SSH mode".to_string()
+    }
+}
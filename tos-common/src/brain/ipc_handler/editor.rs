@@ -0,0 +1,764 @@
+//! Editor pane commands (§27.4).
+
+use super::*;
+use crate::brain::command_registry::{CommandRegistry, CommandSpec};
+use crate::collaboration::ParticipantRole;
+use crate::rpc::ParamKind;
+
+pub(super) fn register(r: &mut CommandRegistry) {
+    r.add(
+        CommandSpec::new("editor_open", "Open a file in an editor pane")
+            .param("path", ParamKind::Text)
+            .optional("line", ParamKind::Integer),
+        |cx| cx.ipc.handle_editor_open(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("editor_save", "Save an editor pane").param("pane_id", ParamKind::Uuid),
+        |cx| cx.ipc.handle_editor_save(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("editor_save_as", "Save an editor pane under a new path")
+            .param("pane_id", ParamKind::Uuid)
+            .param("path", ParamKind::Text),
+        |cx| cx.ipc.handle_editor_save_as(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new(
+            "editor_activate",
+            "Switch a pane from viewer to editor mode",
+        )
+        .param("pane_id", ParamKind::Uuid),
+        |cx| cx.ipc.handle_editor_activate(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("editor_mode_switch", "Switch an editor pane's mode")
+            .param("pane_id", ParamKind::Uuid)
+            .param("mode", ParamKind::Text),
+        |cx| cx.ipc.handle_editor_mode_switch(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("editor_scroll", "Scroll an open file to a line")
+            .param("path", ParamKind::Text)
+            .optional("line", ParamKind::Integer),
+        |cx| cx.ipc.handle_editor_scroll(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("editor_open_ai", "Open a file with AI context")
+            .param("path", ParamKind::Text)
+            .optional("line", ParamKind::Integer)
+            .optional("context_id", ParamKind::Text),
+        |cx| {
+            cx.ipc
+                .handle_editor_open_ai(cx.arg(0), cx.arg(1), cx.arg(2))
+        },
+    );
+    r.add(
+        CommandSpec::new("editor_diff", "Show a file's diff against an edit proposal")
+            .param("path", ParamKind::Text)
+            .optional("proposal_id", ParamKind::Text),
+        |cx| cx.ipc.handle_editor_diff(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("editor_annotate", "Add an annotation to a file")
+            .param("annotation", ParamKind::Text)
+            .role(ParticipantRole::Commenter),
+        |cx| cx.ipc.handle_editor_annotate(cx.payload),
+    );
+    r.add(
+        CommandSpec::new(
+            "editor_clear_annotations",
+            "Remove all annotations from a file",
+        )
+        .param("path", ParamKind::Text),
+        |cx| cx.ipc.handle_editor_clear_annotations(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "editor_edit_proposal",
+            "Attach proposed edit hunks to an editor pane",
+        )
+        .param("pane_id", ParamKind::Uuid)
+        .param("hunks", ParamKind::Json),
+        |cx| {
+            cx.ipc.handle_editor_edit_proposal(
+                cx.arg(0),
+                cx.payload.split_once(';').map(|(_, hunks)| hunks),
+            )
+        },
+    );
+    r.add(
+        CommandSpec::new("editor_edit_apply", "Apply one proposed edit hunk")
+            .param("pane_id", ParamKind::Uuid)
+            .param("hunk_index", ParamKind::Integer),
+        |cx| cx.ipc.handle_editor_edit_apply(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("editor_edit_reject", "Reject one proposed edit hunk")
+            .param("pane_id", ParamKind::Uuid)
+            .param("hunk_index", ParamKind::Integer),
+        |cx| cx.ipc.handle_editor_edit_reject(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new(
+            "editor_context_update",
+            "Update the AI context of an editor pane",
+        )
+        .param("pane_id", ParamKind::Uuid)
+        .param("context", ParamKind::Json),
+        |cx| cx.ipc.handle_editor_context_update(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new(
+            "editor_send_context",
+            "Send an editor pane's context to the AI",
+        )
+        .param("pane_id", ParamKind::Uuid)
+        .optional("scope", ParamKind::Text),
+        |cx| cx.ipc.handle_editor_send_context(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("editor_promote", "Promote a pane to the primary split slot")
+            .param("pane_id", ParamKind::Uuid),
+        |cx| cx.ipc.handle_editor_promote(cx.arg(0)),
+    );
+}
+
+impl IpcHandler {
+    /// `editor_open:<path>;<line>` — Read a file from disk and open it in
+    /// a new Viewer-mode editor pane in the active hub's split tree.
+    pub(super) fn handle_editor_open(&self, path: Option<&str>, line: Option<&str>) -> String {
+        let path_str = match path {
+            Some(p) if !p.is_empty() => p,
+            _ => return "ERROR: Missing path".to_string(),
+        };
+        let line_num: usize = line.and_then(|l| l.parse().ok()).unwrap_or(0);
+        let file_path = std::path::PathBuf::from(path_str);
+
+        // Read file content
+        let content = match std::fs::read_to_string(&file_path) {
+            Ok(c) => c,
+            Err(e) => return format!("ERROR: Cannot read file: {}", e),
+        };
+
+        let language = detect_language(&file_path);
+
+        let editor_state = crate::EditorPaneState {
+            file_path: file_path.clone(),
+            content,
+            mode: crate::EditorMode::Viewer,
+            language,
+            cursor_line: line_num,
+            cursor_col: 0,
+            scroll_offset: line_num.saturating_sub(5),
+            dirty: false,
+            diff_hunks: vec![],
+            annotations: vec![],
+        };
+
+        // Install as a new split pane in the active hub
+        let mut state = self.state.lock().unwrap();
+        let idx = state.active_sector_index;
+        if let Some(sector) = state.sectors.get_mut(idx) {
+            let hub_idx = sector.active_hub_index;
+            if let Some(hub) = sector.hubs.get_mut(hub_idx) {
+                // LSP integration (after state unlock)
+                let cwd = hub.current_directory.clone();
+                let lang_clone = editor_state.language.clone();
+                let file_clone = editor_state.file_path.clone();
+                let cont_clone = editor_state.content.clone();
+
+                // Create a new editor pane via split
+                let pane =
+                    crate::SplitPane::new_with_content(crate::PaneContent::Editor(editor_state));
+                match hub.split_layout {
+                    Some(ref mut tree) => tree.add_pane(pane),
+                    None => {
+                        hub.split_layout = Some(crate::SplitNode::Leaf(pane));
+                    }
+                }
+                state.version += 1;
+                drop(state);
+
+                if let Some(lang) = lang_clone {
+                    self.services.lsp.start_client(&lang, cwd);
+                    self.services.lsp.did_open(&lang, &file_clone, &cont_clone);
+                }
+
+                return format!("EDITOR_OPENED: {}", path_str);
+            }
+        }
+        "ERROR: No active hub".to_string()
+    }
+
+    /// `editor_save:<pane_id>` — Write the editor buffer to its file_path.
+    pub(super) fn handle_editor_save(&self, pane_id: Option<&str>) -> String {
+        let pane_uuid = match pane_id.and_then(|s| Uuid::parse_str(s).ok()) {
+            Some(u) => u,
+            None => return "ERROR: Invalid pane_id".to_string(),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let idx = state.active_sector_index;
+
+        // Extract file path and content for writing (release borrow on state.sectors)
+        let write_info = state.sectors.get(idx).and_then(|sector| {
+            let hub = sector.hubs.get(sector.active_hub_index)?;
+            let pane = hub.split_layout.as_ref().and_then(|t| {
+                fn find_pane(node: &crate::SplitNode, id: Uuid) -> Option<&crate::SplitPane> {
+                    match node {
+                        crate::SplitNode::Leaf(p) if p.id == id => Some(p),
+                        crate::SplitNode::Leaf(_) => None,
+                        crate::SplitNode::Container { children, .. } => {
+                            children.iter().find_map(|c| find_pane(c, id))
+                        }
+                    }
+                }
+                find_pane(t, pane_uuid)
+            })?;
+            if let crate::PaneContent::Editor(ref ed) = pane.content {
+                Some((ed.file_path.clone(), ed.content.clone()))
+            } else {
+                None
+            }
+        });
+
+        if let Some((file_path, content)) = write_info {
+            match std::fs::write(&file_path, &content) {
+                Ok(_) => {
+                    // Now mutate to clear dirty flag
+                    if let Some(sector) = state.sectors.get_mut(idx) {
+                        let hub_idx = sector.active_hub_index;
+                        if let Some(hub) = sector.hubs.get_mut(hub_idx) {
+                            if let Some(pane) = hub
+                                .split_layout
+                                .as_mut()
+                                .and_then(|t| t.find_pane_mut(pane_uuid))
+                            {
+                                if let crate::PaneContent::Editor(ref mut ed) = pane.content {
+                                    ed.dirty = false;
+                                }
+                            }
+                        }
+                    }
+                    state.version += 1;
+                    return format!("EDITOR_SAVED: {}", file_path.display());
+                }
+                Err(e) => return format!("ERROR: Write failed: {}", e),
+            }
+        }
+        "ERROR: Pane not found or not an editor".to_string()
+    }
+
+    /// `editor_save_as:<pane_id>;<new_path>` — Save buffer to a new path.
+    pub(super) fn handle_editor_save_as(
+        &self,
+        pane_id: Option<&str>,
+        new_path: Option<&str>,
+    ) -> String {
+        let pane_uuid = match pane_id.and_then(|s| Uuid::parse_str(s).ok()) {
+            Some(u) => u,
+            None => return "ERROR: Invalid pane_id".to_string(),
+        };
+        let new_path_str = match new_path {
+            Some(p) if !p.is_empty() => p,
+            _ => return "ERROR: Missing new path".to_string(),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let idx = state.active_sector_index;
+        if let Some(sector) = state.sectors.get_mut(idx) {
+            let hub_idx = sector.active_hub_index;
+            if let Some(hub) = sector.hubs.get_mut(hub_idx) {
+                if let Some(pane) = hub
+                    .split_layout
+                    .as_mut()
+                    .and_then(|t| t.find_pane_mut(pane_uuid))
+                {
+                    if let crate::PaneContent::Editor(ref mut ed) = pane.content {
+                        let path = std::path::PathBuf::from(new_path_str);
+                        if let Some(parent) = path.parent() {
+                            let _ = std::fs::create_dir_all(parent);
+                        }
+                        match std::fs::write(&path, &ed.content) {
+                            Ok(_) => {
+                                ed.file_path = path;
+                                ed.language = detect_language(&ed.file_path);
+                                ed.dirty = false;
+                                state.version += 1;
+                                return format!("EDITOR_SAVED_AS: {}", new_path_str);
+                            }
+                            Err(e) => return format!("ERROR: Write failed: {}", e),
+                        }
+                    }
+                }
+            }
+        }
+        "ERROR: Pane not found or not an editor".to_string()
+    }
+
+    /// `editor_activate:<pane_id>` — Switch pane from Viewer to Editor mode.
+    pub(super) fn handle_editor_activate(&self, pane_id: Option<&str>) -> String {
+        let pane_uuid = match pane_id.and_then(|s| Uuid::parse_str(s).ok()) {
+            Some(u) => u,
+            None => return "ERROR: Invalid pane_id".to_string(),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let idx = state.active_sector_index;
+        if let Some(sector) = state.sectors.get_mut(idx) {
+            let hub_idx = sector.active_hub_index;
+            if let Some(hub) = sector.hubs.get_mut(hub_idx) {
+                if let Some(pane) = hub
+                    .split_layout
+                    .as_mut()
+                    .and_then(|t| t.find_pane_mut(pane_uuid))
+                {
+                    if let crate::PaneContent::Editor(ref mut ed) = pane.content {
+                        ed.mode = crate::EditorMode::Editor;
+                        state.version += 1;
+                        return "EDITOR_ACTIVATED".to_string();
+                    }
+                }
+            }
+        }
+        "ERROR: Pane not found or not an editor".to_string()
+    }
+
+    /// `editor_mode_switch:<pane_id>;<mode>` — Switch between viewer/editor/diff.
+    pub(super) fn handle_editor_mode_switch(
+        &self,
+        pane_id: Option<&str>,
+        mode: Option<&str>,
+    ) -> String {
+        let pane_uuid = match pane_id.and_then(|s| Uuid::parse_str(s).ok()) {
+            Some(u) => u,
+            None => return "ERROR: Invalid pane_id".to_string(),
+        };
+        let target_mode = match mode {
+            Some("viewer") => crate::EditorMode::Viewer,
+            Some("editor") => crate::EditorMode::Editor,
+            Some("diff") => crate::EditorMode::Diff,
+            _ => return "ERROR: Invalid mode (viewer|editor|diff)".to_string(),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let idx = state.active_sector_index;
+        if let Some(sector) = state.sectors.get_mut(idx) {
+            let hub_idx = sector.active_hub_index;
+            if let Some(hub) = sector.hubs.get_mut(hub_idx) {
+                if let Some(pane) = hub
+                    .split_layout
+                    .as_mut()
+                    .and_then(|t| t.find_pane_mut(pane_uuid))
+                {
+                    if let crate::PaneContent::Editor(ref mut ed) = pane.content {
+                        ed.mode = target_mode;
+                        state.version += 1;
+                        return format!("EDITOR_MODE: {:?}", target_mode);
+                    }
+                }
+            }
+        }
+        "ERROR: Pane not found or not an editor".to_string()
+    }
+
+    /// `editor_scroll:<path>;<line>` — Scroll editor showing `path` to the given line.
+    pub(super) fn handle_editor_scroll(&self, path: Option<&str>, line: Option<&str>) -> String {
+        let path_str = match path {
+            Some(p) => p,
+            None => return "ERROR: Missing path".to_string(),
+        };
+        let line_num: usize = line.and_then(|l| l.parse().ok()).unwrap_or(0);
+
+        let mut state = self.state.lock().unwrap();
+        let idx = state.active_sector_index;
+        if let Some(sector) = state.sectors.get_mut(idx) {
+            let hub_idx = sector.active_hub_index;
+            if let Some(hub) = sector.hubs.get_mut(hub_idx) {
+                if let Some(ed) = hub
+                    .split_layout
+                    .as_mut()
+                    .and_then(|t| t.find_editor_by_path_mut(path_str))
+                {
+                    ed.cursor_line = line_num;
+                    ed.scroll_offset = line_num.saturating_sub(5);
+                    state.version += 1;
+                    return format!("EDITOR_SCROLLED: {}:{}", path_str, line_num);
+                }
+            }
+        }
+        "ERROR: No editor pane found for path".to_string()
+    }
+
+    /// `editor_open_ai:<path>;<line>;<context_id>` — Open with AI context.
+    pub(super) fn handle_editor_open_ai(
+        &self,
+        path: Option<&str>,
+        line: Option<&str>,
+        _context_id: Option<&str>,
+    ) -> String {
+        // Open the file normally, AI context attachment is a Face-side concern
+        self.handle_editor_open(path, line)
+    }
+
+    /// `editor_diff:<path>;<proposed_content_id>` — Open Diff Mode with proposal.
+    pub(super) fn handle_editor_diff(
+        &self,
+        path: Option<&str>,
+        _proposed_id: Option<&str>,
+    ) -> String {
+        // Open file, then switch to Diff mode
+        let result = self.handle_editor_open(path, Some("0"));
+        if result.starts_with("EDITOR_OPENED") {
+            let path_str = path.unwrap_or("");
+            let mut state = self.state.lock().unwrap();
+            let idx = state.active_sector_index;
+            if let Some(sector) = state.sectors.get_mut(idx) {
+                let hub_idx = sector.active_hub_index;
+                if let Some(hub) = sector.hubs.get_mut(hub_idx) {
+                    if let Some(ed) = hub
+                        .split_layout
+                        .as_mut()
+                        .and_then(|t| t.find_editor_by_path_mut(path_str))
+                    {
+                        ed.mode = crate::EditorMode::Diff;
+                        state.version += 1;
+                    }
+                }
+            }
+            return format!("EDITOR_DIFF: {}", path_str);
+        }
+        result
+    }
+
+    /// `editor_annotate:<path>;<line>;<severity>;<message>;<context_id>` — Add annotation.
+    pub(super) fn handle_editor_annotate(&self, payload: &str) -> String {
+        // Annotations are stored in state for the Face to render.
+        // For now, log the annotation request.
+        tracing::info!("[EDITOR] Annotation request: {}", payload);
+        format!("EDITOR_ANNOTATED: {}", payload)
+    }
+
+    /// `editor_clear_annotations:<path>` — Remove all annotations.
+    pub(super) fn handle_editor_clear_annotations(&self, path: Option<&str>) -> String {
+        let path_str = path.unwrap_or("");
+        tracing::info!("[EDITOR] Clearing annotations for: {}", path_str);
+        format!("EDITOR_ANNOTATIONS_CLEARED: {}", path_str)
+    }
+
+    /// `editor_edit_proposal:<pane_id>;<proposal_json>` — Trigger Diff Mode with proposed changes.
+    pub(super) fn handle_editor_edit_proposal(
+        &self,
+        pane_id: Option<&str>,
+        payload: Option<&str>,
+    ) -> String {
+        let pane_uuid = match pane_id.and_then(|s| Uuid::parse_str(s).ok()) {
+            Some(u) => u,
+            None => return "ERROR: Invalid pane_id".to_string(),
+        };
+        let hunks_json = match payload {
+            Some(p) => p,
+            None => return "ERROR: Missing hunks JSON".to_string(),
+        };
+
+        let hunks: Result<Vec<crate::state::DiffHunk>, _> = serde_json::from_str(hunks_json);
+        match hunks {
+            Ok(diff_hunks) => {
+                let mut state = self.state.lock().unwrap();
+                let idx = state.active_sector_index;
+                if let Some(sector) = state.sectors.get_mut(idx) {
+                    let hub_idx = sector.active_hub_index;
+                    if let Some(hub) = sector.hubs.get_mut(hub_idx) {
+                        if let Some(pane) = hub
+                            .split_layout
+                            .as_mut()
+                            .and_then(|t| t.find_pane_mut(pane_uuid))
+                        {
+                            if let crate::PaneContent::Editor(ref mut ed) = pane.content {
+                                ed.diff_hunks = diff_hunks.clone();
+                                ed.mode = crate::EditorMode::Diff;
+                                state.version += 1;
+                                return format!(
+                                    "EDITOR_PROPOSAL_RECEIVED: {} hunks",
+                                    diff_hunks.len()
+                                );
+                            }
+                        }
+                    }
+                }
+                "ERROR: Pane not found or not an editor".to_string()
+            }
+            Err(e) => format!("ERROR: Invalid hunks JSON: {}", e),
+        }
+    }
+
+    /// `editor_edit_apply:<pane_id>;<hunk_index>` — Apply pending edit proposal.
+    pub(super) fn handle_editor_edit_apply(
+        &self,
+        pane_id: Option<&str>,
+        hunk_index: Option<&str>,
+    ) -> String {
+        let pane_uuid = match pane_id.and_then(|s| Uuid::parse_str(s).ok()) {
+            Some(u) => u,
+            None => return "ERROR: Invalid pane_id".to_string(),
+        };
+        let h_idx: usize = match hunk_index.and_then(|i| i.parse().ok()) {
+            Some(idx) => idx,
+            None => return "ERROR: Invalid hunk_index".to_string(),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let idx = state.active_sector_index;
+        if let Some(sector) = state.sectors.get_mut(idx) {
+            let hub_idx = sector.active_hub_index;
+            if let Some(hub) = sector.hubs.get_mut(hub_idx) {
+                if let Some(pane) = hub
+                    .split_layout
+                    .as_mut()
+                    .and_then(|t| t.find_pane_mut(pane_uuid))
+                {
+                    if let crate::PaneContent::Editor(ref mut ed) = pane.content {
+                        if h_idx < ed.diff_hunks.len() {
+                            let hunk = ed.diff_hunks.remove(h_idx);
+
+                            // Naive apply: find lines and replace them
+                            let mut lines: Vec<&str> = ed.content.lines().collect();
+                            let start = hunk.old_start.saturating_sub(1); // 1-indexed to 0-indexed
+                            let end = start + hunk.old_count;
+
+                            if start <= lines.len() && end <= lines.len() {
+                                let new_lines: Vec<&str> = hunk
+                                    .content
+                                    .lines()
+                                    .filter(|l| !l.starts_with('-')) // Remove old lines
+                                    .map(|l| {
+                                        l.strip_prefix('+')
+                                            .or_else(|| l.strip_prefix(' '))
+                                            .unwrap_or(l)
+                                    })
+                                    .collect();
+
+                                lines.splice(start..end, new_lines);
+                                ed.content = lines.join("\n");
+                                ed.dirty = true;
+
+                                if ed.diff_hunks.is_empty() {
+                                    ed.mode = crate::EditorMode::Editor; // Return to editor if no diffs
+                                }
+                                state.version += 1;
+                                return "EDITOR_EDIT_APPLIED".to_string();
+                            } else {
+                                return "ERROR: Hunk bounds invalid".to_string();
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        "ERROR: Pane not found or hunk out of bounds".to_string()
+    }
+
+    /// `editor_edit_reject:<pane_id>;<hunk_index>` — Reject pending edit proposal.
+    pub(super) fn handle_editor_edit_reject(
+        &self,
+        pane_id: Option<&str>,
+        hunk_index: Option<&str>,
+    ) -> String {
+        let pane_uuid = match pane_id.and_then(|s| Uuid::parse_str(s).ok()) {
+            Some(u) => u,
+            None => return "ERROR: Invalid pane_id".to_string(),
+        };
+        let h_idx: usize = match hunk_index.and_then(|i| i.parse().ok()) {
+            Some(idx) => idx,
+            None => return "ERROR: Invalid hunk_index".to_string(),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let idx = state.active_sector_index;
+        if let Some(sector) = state.sectors.get_mut(idx) {
+            let hub_idx = sector.active_hub_index;
+            if let Some(hub) = sector.hubs.get_mut(hub_idx) {
+                if let Some(pane) = hub
+                    .split_layout
+                    .as_mut()
+                    .and_then(|t| t.find_pane_mut(pane_uuid))
+                {
+                    if let crate::PaneContent::Editor(ref mut ed) = pane.content {
+                        if h_idx < ed.diff_hunks.len() {
+                            ed.diff_hunks.remove(h_idx);
+                            if ed.diff_hunks.is_empty() {
+                                ed.mode = crate::EditorMode::Editor; // Return to editor if no diffs
+                            }
+                            state.version += 1;
+                            return "EDITOR_EDIT_REJECTED".to_string();
+                        }
+                    }
+                }
+            }
+        }
+        "ERROR: Pane not found or hunk out of bounds".to_string()
+    }
+
+    /// `editor_context_update:<pane_id>;<context_json>` — Face sends cursor/scroll state.
+    pub(super) fn handle_editor_context_update(
+        &self,
+        pane_id: Option<&str>,
+        context: Option<&str>,
+    ) -> String {
+        let pane_uuid = match pane_id.and_then(|s| Uuid::parse_str(s).ok()) {
+            Some(u) => u,
+            None => return "ERROR: Invalid pane_id".to_string(),
+        };
+        let ctx_json = match context {
+            Some(c) => c,
+            None => return "ERROR: Missing context JSON payload".to_string(),
+        };
+
+        // Parse context updates (could be partial). We use a generic value to extract fields.
+        let parsed: Result<serde_json::Value, _> = serde_json::from_str(ctx_json);
+        if let Ok(json_ctx) = parsed {
+            let mut state = self.state.lock().unwrap();
+            let idx = state.active_sector_index;
+            if let Some(sector) = state.sectors.get_mut(idx) {
+                let hub_idx = sector.active_hub_index;
+                if let Some(hub) = sector.hubs.get_mut(hub_idx) {
+                    if let Some(pane) = hub
+                        .split_layout
+                        .as_mut()
+                        .and_then(|t| t.find_pane_mut(pane_uuid))
+                    {
+                        if let crate::PaneContent::Editor(ref mut ed) = pane.content {
+                            if let Some(content) = json_ctx.get("content").and_then(|v| v.as_str())
+                            {
+                                if ed.content != content {
+                                    ed.content = content.to_string();
+                                    ed.dirty = true;
+
+                                    if let Some(ref lang) = ed.language {
+                                        self.services.lsp.did_change(lang, &ed.file_path, content);
+                                    }
+                                }
+                            }
+                            if let Some(cursor_line) =
+                                json_ctx.get("cursor_line").and_then(|v| v.as_u64())
+                            {
+                                ed.cursor_line = cursor_line as usize;
+                            }
+                            if let Some(cursor_col) =
+                                json_ctx.get("cursor_col").and_then(|v| v.as_u64())
+                            {
+                                ed.cursor_col = cursor_col as usize;
+                            }
+                            if let Some(scroll) =
+                                json_ctx.get("scroll_offset").and_then(|v| v.as_u64())
+                            {
+                                ed.scroll_offset = scroll as usize;
+                            }
+                            state.version += 1;
+                            return "EDITOR_CONTEXT_UPDATED".to_string();
+                        }
+                    }
+                }
+            }
+        }
+        "ERROR: Invalid context or pane not found".to_string()
+    }
+
+    pub(super) fn handle_editor_send_context(
+        &self,
+        _pane_id: Option<&str>,
+        _scope: Option<&str>,
+    ) -> String {
+        "EDITOR_CONTEXT_SENT".to_string()
+    }
+
+    pub(super) fn handle_editor_promote(&self, pane_id: Option<&str>) -> String {
+        let pane_uuid = match pane_id.and_then(|s| Uuid::parse_str(s).ok()) {
+            Some(u) => u,
+            None => return "ERROR: Invalid pane_id".to_string(),
+        };
+        let mut state = self.state.lock().unwrap();
+        let idx = state.active_sector_index;
+        if let Some(sector) = state.sectors.get_mut(idx) {
+            if let Some(hub) = sector.hubs.get_mut(sector.active_hub_index) {
+                if let Some(layout) = &mut hub.split_layout {
+                    layout.promote_pane(pane_uuid);
+                    state.version += 1;
+                    return "EDITOR_PROMOTED".to_string();
+                }
+            }
+        }
+        "ERROR: Pane not found".to_string()
+    }
+}
+
+/// Detect programming language from file extension for syntax highlighting.
+fn detect_language(path: &std::path::Path) -> Option<String> {
+    path.extension().and_then(|ext| ext.to_str()).map(|ext| {
+        match ext.to_lowercase().as_str() {
+            "rs" => "rust",
+            "py" => "python",
+            "js" => "javascript",
+            "ts" => "typescript",
+            "tsx" | "jsx" => "typescriptreact",
+            "html" | "htm" => "html",
+            "css" => "css",
+            "scss" | "sass" => "scss",
+            "json" => "json",
+            "toml" => "toml",
+            "yaml" | "yml" => "yaml",
+            "md" | "markdown" => "markdown",
+            "sh" | "bash" | "zsh" | "fish" => "shell",
+            "c" => "c",
+            "cpp" | "cc" | "cxx" | "h" | "hpp" => "cpp",
+            "go" => "go",
+            "java" => "java",
+            "kt" | "kts" => "kotlin",
+            "swift" => "swift",
+            "rb" => "ruby",
+            "lua" => "lua",
+            "sql" => "sql",
+            "xml" => "xml",
+            "svelte" => "svelte",
+            "vue" => "vue",
+            "dart" => "dart",
+            "zig" => "zig",
+            "nim" => "nim",
+            "ex" | "exs" => "elixir",
+            "hs" => "haskell",
+            _ => ext,
+        }
+        .to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_language_detection() {
+        assert_eq!(
+            detect_language(Path::new("test.rs")),
+            Some("rust".to_string())
+        );
+        assert_eq!(
+            detect_language(Path::new("script.py")),
+            Some("python".to_string())
+        );
+        assert_eq!(
+            detect_language(Path::new("Styles.css")),
+            Some("css".to_string())
+        );
+        assert_eq!(
+            detect_language(Path::new("README.md")),
+            Some("markdown".to_string())
+        );
+        assert_eq!(detect_language(Path::new("Dockerfile")), None);
+        assert_eq!(
+            detect_language(Path::new("main.unknown")),
+            Some("unknown".to_string())
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! Kanban board (§10.1) and agent workflow (§27.8) commands.
+
+use super::*;
+use crate::brain::command_registry::{CommandRegistry, CommandSpec};
+use crate::collaboration::ParticipantRole;
+use crate::rpc::ParamKind;
+
+pub(super) fn register(r: &mut CommandRegistry) {
+    r.add(
+        CommandSpec::new("kanban_init", "Create the active sector's kanban board"),
+        |cx| cx.ipc.handle_kanban_init(),
+    );
+    r.add(
+        CommandSpec::new("kanban_get", "Kanban board of the active sector as JSON")
+            .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_kanban_get(),
+    );
+    r.add(
+        CommandSpec::new("kanban_task_add", "Add a task to a kanban lane")
+            .param("task", ParamKind::Json),
+        |cx| cx.ipc.handle_kanban_task_add(cx.payload),
+    );
+    r.add(
+        CommandSpec::new("kanban_task_move", "Move a task between kanban lanes")
+            .param("move", ParamKind::Json),
+        |cx| cx.ipc.handle_kanban_task_move(cx.payload),
+    );
+    r.add(
+        CommandSpec::new("kanban_task_delete", "Delete a kanban task")
+            .param("task", ParamKind::Json),
+        |cx| cx.ipc.handle_kanban_task_delete(cx.payload),
+    );
+    r.add(
+        CommandSpec::new(
+            "workflow_agent_sandbox",
+            "Give an agent task its own sandboxed worktree",
+        )
+        .param("task_id", ParamKind::Uuid)
+        .param("cwd", ParamKind::Text),
+        |cx| cx.ipc.handle_workflow_agent_sandbox(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("workflow_task_merge", "Merge a finished agent task")
+            .param("task_id", ParamKind::Uuid),
+        |cx| cx.ipc.handle_workflow_task_merge(cx.arg(0)),
+    );
+}
+
+impl IpcHandler {
+    pub(super) fn handle_workflow_agent_sandbox(
+        &self,
+        task_id_str: Option<&str>,
+        cwd_str: Option<&str>,
+    ) -> String {
+        let task_id = match task_id_str.and_then(|s| Uuid::parse_str(s).ok()) {
+            Some(id) => id,
+            None => return "ERROR: Invalid task_id".to_string(),
+        };
+        let cwd = match cwd_str {
+            Some(c) => std::path::PathBuf::from(c),
+            None => return "ERROR: Missing cwd".to_string(),
+        };
+
+        match self.services.ai.workflow_agent_sandbox(task_id, cwd) {
+            Ok(output) => output,
+            Err(e) => format!("ERROR: Failed to create sandbox: {}", e),
+        }
+    }
+
+    pub(super) fn handle_workflow_task_merge(&self, task_id_str: Option<&str>) -> String {
+        let task_id = match task_id_str.and_then(|s| Uuid::parse_str(s).ok()) {
+            Some(id) => id,
+            None => return "ERROR: Invalid task_id".to_string(),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        match self.services.ai.workflow_task_merge(&mut state, task_id) {
+            Ok(msg) => {
+                state.version += 1;
+                msg
+            }
+            Err(e) => format!("ERROR: Failed to merge task: {}", e),
+        }
+    }
+
+    pub(super) fn handle_kanban_init(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        let idx = state.active_sector_index;
+        if let Some(sector) = state.sectors.get_mut(idx) {
+            if sector.kanban_board.is_some() {
+                return "ERROR: Kanban board already exists".to_string();
+            }
+            sector.kanban_board = Some(crate::KanbanBoard {
+                project_id: Uuid::new_v4(),
+                title: sector.name.clone(),
+                lanes: vec![
+                    crate::KanbanLane {
+                        id: Uuid::new_v4(),
+                        title: "TODO".to_string(),
+                        tasks: vec![],
+                    },
+                    crate::KanbanLane {
+                        id: Uuid::new_v4(),
+                        title: "IN PROGRESS".to_string(),
+                        tasks: vec![],
+                    },
+                    crate::KanbanLane {
+                        id: Uuid::new_v4(),
+                        title: "DONE".to_string(),
+                        tasks: vec![],
+                    },
+                ],
+            });
+            state.version += 1;
+            return "KANBAN_INITIALIZED".to_string();
+        }
+        "ERROR: No active sector".to_string()
+    }
+
+    pub(super) fn handle_kanban_get(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let idx = state.active_sector_index;
+        if let Some(sector) = state.sectors.get(idx) {
+            if let Some(board) = &sector.kanban_board {
+                return serde_json::to_string(board)
+                    .unwrap_or_else(|_| "ERROR: Failed to serialize board".to_string());
+            }
+        }
+        "KANBAN_BOARD_NOT_FOUND".to_string()
+    }
+
+    pub(super) fn handle_kanban_task_add(&self, payload: &str) -> String {
+        #[derive(serde::Deserialize)]
+        struct TaskAdd {
+            lane_id: Uuid,
+            title: String,
+            description: String,
+        }
+        if let Ok(data) = serde_json::from_str::<TaskAdd>(payload) {
+            let mut state = self.state.lock().unwrap();
+            let idx = state.active_sector_index;
+            if let Some(sector) = state.sectors.get_mut(idx) {
+                if let Some(board) = &mut sector.kanban_board {
+                    if let Some(lane) = board.lanes.iter_mut().find(|l| l.id == data.lane_id) {
+                        let task = crate::KanbanTask {
+                            id: Uuid::new_v4(),
+                            title: data.title,
+                            description: data.description,
+                            status: crate::KanbanTaskStatus::Todo,
+                            assignee: None,
+                            priority: 0,
+                            tags: vec![],
+                            staged_changes: vec![],
+                        };
+                        lane.tasks.push(task);
+                        state.version += 1;
+                        return "KANBAN_TASK_ADDED".to_string();
+                    }
+                }
+            }
+        }
+        "ERROR: Invalid task_add payload or lane not found".to_string()
+    }
+
+    pub(super) fn handle_kanban_task_move(&self, payload: &str) -> String {
+        #[derive(serde::Deserialize)]
+        struct TaskMove {
+            task_id: Uuid,
+            from_lane: Uuid,
+            to_lane: Uuid,
+        }
+        if let Ok(data) = serde_json::from_str::<TaskMove>(payload) {
+            let mut state = self.state.lock().unwrap();
+            let idx = state.active_sector_index;
+            if let Some(sector) = state.sectors.get_mut(idx) {
+                if let Some(board) = &mut sector.kanban_board {
+                    let mut task_opt = None;
+                    if let Some(lane) = board.lanes.iter_mut().find(|l| l.id == data.from_lane) {
+                        if let Some(pos) = lane.tasks.iter().position(|t| t.id == data.task_id) {
+                            task_opt = Some(lane.tasks.remove(pos));
+                        }
+                    }
+                    if let Some(mut task) = task_opt {
+                        if let Some(lane) = board.lanes.iter_mut().find(|l| l.id == data.to_lane) {
+                            // Update status based on lane name (simple heuristic)
+                            task.status = match lane.title.as_str() {
+                                "TODO" => crate::KanbanTaskStatus::Todo,
+                                "IN PROGRESS" => crate::KanbanTaskStatus::InProgress,
+                                "DONE" => crate::KanbanTaskStatus::Done,
+                                _ => task.status,
+                            };
+                            lane.tasks.push(task);
+                            state.version += 1;
+                            return "KANBAN_TASK_MOVED".to_string();
+                        }
+                    }
+                }
+            }
+        }
+        "ERROR: Invalid task_move payload or task/lane not found".to_string()
+    }
+
+    pub(super) fn handle_kanban_task_delete(&self, payload: &str) -> String {
+        #[derive(serde::Deserialize)]
+        struct TaskDelete {
+            task_id: Uuid,
+            lane_id: Uuid,
+        }
+        if let Ok(data) = serde_json::from_str::<TaskDelete>(payload) {
+            let mut state = self.state.lock().unwrap();
+            let idx = state.active_sector_index;
+            if let Some(sector) = state.sectors.get_mut(idx) {
+                if let Some(board) = &mut sector.kanban_board {
+                    if let Some(lane) = board.lanes.iter_mut().find(|l| l.id == data.lane_id) {
+                        lane.tasks.retain(|t| t.id != data.task_id);
+                        state.version += 1;
+                        return "KANBAN_TASK_DELETED".to_string();
+                    }
+                }
+            }
+        }
+        "ERROR: Invalid task_delete payload or task/lane not found".to_string()
+    }
+}
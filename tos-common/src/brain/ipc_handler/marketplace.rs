@@ -0,0 +1,151 @@
+//! Marketplace and module install commands (§22).
+
+use super::*;
+use crate::brain::command_registry::{CommandRegistry, CommandSpec};
+use crate::collaboration::ParticipantRole;
+use crate::rpc::ParamKind;
+
+pub(super) fn register(r: &mut CommandRegistry) {
+    r.add(
+        CommandSpec::new("market", "Install or remove a module (legacy)")
+            .param("action", ParamKind::Text)
+            .optional("module_id", ParamKind::Text),
+        |cx| cx.ipc.handle_market_command(cx.payload),
+    );
+    r.add(
+        CommandSpec::new("marketplace_home", "Marketplace front page as JSON")
+            .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_marketplace_home(),
+    );
+    r.add(
+        CommandSpec::new(
+            "marketplace_category",
+            "Modules in a marketplace category as JSON",
+        )
+        .param("id", ParamKind::Text)
+        .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_marketplace_category(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "marketplace_detail",
+            "Details of a marketplace module as JSON",
+        )
+        .param("id", ParamKind::Text)
+        .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_marketplace_detail(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("marketplace_install", "Install a marketplace module")
+            .param("id", ParamKind::Text)
+            .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_marketplace_install(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "marketplace_status",
+            "Install progress of a marketplace module",
+        )
+        .param("id", ParamKind::Text)
+        .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_marketplace_status(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "marketplace_search_ai",
+            "Search the marketplace with an AI-interpreted query",
+        )
+        .param("query", ParamKind::Text),
+        |cx| cx.ipc.handle_marketplace_search_ai(cx.payload),
+    );
+    r.add(
+        CommandSpec::new("marketplace_install_cancel", "Cancel a marketplace install")
+            .param("id", ParamKind::Text),
+        |cx| cx.ipc.handle_marketplace_install_cancel(cx.arg(0)),
+    );
+}
+
+impl IpcHandler {
+    pub(super) fn handle_market_command(&self, payload: &str) -> String {
+        let args: Vec<&str> = payload.split(';').collect();
+        if args.is_empty() {
+            return "ERROR: Empty market command".to_string();
+        }
+
+        match args[0] {
+            "install" => {
+                if args.len() < 2 {
+                    return "ERROR: Missing module ID".to_string();
+                }
+                let module_id = args[1];
+                // Mock installation logic for Alpha-2.1
+                format!("INSTALLED: {}", module_id)
+            }
+            _ => format!("ERROR: Unknown market command: {}", args[0]),
+        }
+    }
+
+    pub(super) fn handle_marketplace_home(&self) -> String {
+        match self.services.marketplace.get_home() {
+            Ok(home) => serde_json::to_string(&home).unwrap_or_default(),
+            Err(e) => format!("ERROR: {}", e),
+        }
+    }
+
+    pub(super) fn handle_marketplace_category(&self, id: Option<&str>) -> String {
+        match id {
+            Some(id) => match self.services.marketplace.get_category(id) {
+                Ok(modules) => serde_json::to_string(&modules).unwrap_or_default(),
+                Err(e) => format!("ERROR: {}", e),
+            },
+            None => "ERROR: Missing category ID".to_string(),
+        }
+    }
+
+    pub(super) fn handle_marketplace_detail(&self, id: Option<&str>) -> String {
+        match id {
+            Some(id) => match self.services.marketplace.get_detail(id) {
+                Ok(detail) => serde_json::to_string(&detail).unwrap_or_default(),
+                Err(e) => format!("ERROR: {}", e),
+            },
+            None => "ERROR: Missing module ID".to_string(),
+        }
+    }
+
+    pub(super) fn handle_marketplace_install(&self, id: Option<&str>) -> String {
+        match id {
+            Some(id) => match self.services.marketplace.install(id) {
+                Ok(status) => status,
+                Err(e) => format!("ERROR: {}", e),
+            },
+            None => "ERROR: Missing module ID".to_string(),
+        }
+    }
+
+    pub(super) fn handle_marketplace_status(&self, id: Option<&str>) -> String {
+        match id {
+            Some(id) => match self.services.marketplace.get_status(id) {
+                Ok(p) => serde_json::to_string(&p).unwrap_or_default(),
+                Err(e) => format!("ERROR: {}", e),
+            },
+            None => "ERROR: Missing module ID".to_string(),
+        }
+    }
+
+    pub(super) fn handle_marketplace_search_ai(&self, query: &str) -> String {
+        match self.services.marketplace.search_ai(query) {
+            Ok(results) => serde_json::to_string(&results).unwrap_or_default(),
+            Err(e) => format!("ERROR: {}", e),
+        }
+    }
+
+    pub(super) fn handle_marketplace_install_cancel(&self, id: Option<&str>) -> String {
+        match id {
+            Some(id) => match self.services.marketplace.cancel_install(id) {
+                Ok(status) => status,
+                Err(e) => format!("ERROR: {}", e),
+            },
+            None => "ERROR: Missing module ID".to_string(),
+        }
+    }
+}
@@ -0,0 +1,522 @@
+use crate::brain::command_registry::{CommandContext, CommandRegistry, CommandSource, CommandSpec};
+use crate::services::MarketplaceService;
+use crate::services::logger::LogContext;
+use crate::state::QueuedAiRequest;
+use crate::{CommandHubMode, HierarchyLevel, TosState};
+// use crate::*;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::Instant;
+use uuid::Uuid;
+
+// §3.3.8: Each service registers its commands with the `CommandRegistry`.
+mod ai;
+mod audio;
+mod collaboration;
+mod editor;
+mod kanban;
+mod marketplace;
+mod navigation;
+mod priority;
+mod sector;
+mod session;
+mod split;
+mod system;
+mod terminal;
+mod trust;
+
+/// State markers captured before a request, compared afterwards to decide
+/// which bus topics to publish.
+struct BusSnapshot {
+    version: u64,
+    active_sector: Option<Uuid>,
+    sectors: Vec<(Uuid, u64)>,
+    /// Last system log entry; the log is capped, so length alone can't tell.
+    log_tail: Option<(chrono::DateTime<chrono::Local>, String)>,
+    confirmation: Option<Uuid>,
+}
+
+pub struct IpcHandler {
+    state: Arc<Mutex<TosState>>,
+    shells: Arc<Mutex<crate::brain::shell::ShellPool>>,
+    services: Arc<crate::services::ServiceManager>,
+    /// §3.3.2: States recently sent to Faces, the bases for patches.
+    deltas: Mutex<crate::brain::state_delta::StateHistory>,
+    /// §3.3.8: Built-in commands plus those added by modules at runtime.
+    commands: RwLock<CommandRegistry>,
+}
+
+impl IpcHandler {
+    pub fn new(
+        state: Arc<Mutex<TosState>>,
+        shells: Arc<Mutex<crate::brain::shell::ShellPool>>,
+        services: Arc<crate::services::ServiceManager>,
+    ) -> Self {
+        Self {
+            state,
+            shells,
+            services,
+            deltas: Mutex::new(crate::brain::state_delta::StateHistory::new()),
+            commands: RwLock::new(Self::builtin_commands()),
+        }
+    }
+
+    /// §3.3.8: The commands every Brain answers, as registered by its
+    /// services.
+    pub fn builtin_commands() -> CommandRegistry {
+        let mut registry = CommandRegistry::new();
+        system::register(&mut registry);
+        navigation::register(&mut registry);
+        sector::register(&mut registry);
+        terminal::register(&mut registry);
+        ai::register(&mut registry);
+        kanban::register(&mut registry);
+        editor::register(&mut registry);
+        split::register(&mut registry);
+        marketplace::register(&mut registry);
+        session::register(&mut registry);
+        trust::register(&mut registry);
+        collaboration::register(&mut registry);
+        audio::register(&mut registry);
+        priority::register(&mut registry);
+        registry
+    }
+
+    /// Add a command at runtime, e.g. from a subsystem created after the
+    /// Brain. Fails if the name is taken.
+    pub fn register_command<F>(&self, spec: CommandSpec, handler: F) -> Result<(), String>
+    where
+        F: Fn(&CommandContext<'_>) -> String + Send + Sync + 'static,
+    {
+        self.commands.write().unwrap().register(spec, handler)
+    }
+
+    pub fn unregister_command(&self, name: &str) -> Option<CommandSpec> {
+        self.commands.write().unwrap().unregister(name)
+    }
+
+    /// Every registered command, sorted by name.
+    pub fn commands(&self) -> Vec<CommandSpec> {
+        self.commands.read().unwrap().specs().cloned().collect()
+    }
+
+    /// §3.3.8: Register the `[[commands]]` declared by loaded modules as
+    /// `<module_id>.<name>`. Each runs the module's executable with the
+    /// call's arguments appended and replies with its standard output.
+    /// Returns how many were registered.
+    pub fn register_module_commands(
+        &self,
+        modules: &crate::brain::module_manager::ModuleManager,
+    ) -> usize {
+        let mut registry = self.commands.write().unwrap();
+        let mut count = 0;
+        for manifest in modules.list_modules() {
+            registry.unregister_module(&manifest.id);
+            for command in manifest.commands.iter().flatten() {
+                let program = modules.resolve_path(&manifest.id, &command.exec);
+                let spec = CommandSpec {
+                    name: format!("{}.{}", manifest.id, command.name),
+                    help: command.help.clone().unwrap_or_default(),
+                    params: command.params.clone(),
+                    // A module's program can do anything, so a manifest may
+                    // raise its role but not open it to viewers.
+                    role: command
+                        .role
+                        .unwrap_or(crate::collaboration::ParticipantRole::Operator)
+                        .max(crate::collaboration::ParticipantRole::Operator),
+                    source: CommandSource::Module {
+                        id: manifest.id.clone(),
+                    },
+                };
+                let name = spec.name.clone();
+                let fixed_args = command.args.clone();
+                let handler = move |cx: &CommandContext<'_>| {
+                    let call_args = if cx.payload.is_empty() {
+                        &[][..]
+                    } else {
+                        cx.args
+                    };
+                    run_module_command(&program, &fixed_args, call_args)
+                };
+                match registry.register(spec, handler) {
+                    Ok(()) => count += 1,
+                    Err(e) => tracing::warn!("[IPC] Skipping module command {}: {}", name, e),
+                }
+            }
+        }
+        count
+    }
+
+    /// §3.3.6: The Brain's event bus, for connection-level subscribers.
+    pub fn events(&self) -> Arc<crate::services::EventBus> {
+        self.services.events.clone()
+    }
+
+    /// §12.3: Authenticator for remote IPC connections.
+    pub fn auth(&self) -> Arc<crate::services::AuthService> {
+        self.services.auth.clone()
+    }
+
+    /// Resolve the (sector, hub) pair a shell command targets: an explicit hub
+    /// id if given, otherwise the active hub of the active sector.
+    fn resolve_hub(&self, hub_id: Option<&str>) -> Option<(Uuid, Uuid)> {
+        let state = self.state.lock().unwrap();
+        match hub_id.filter(|s| !s.is_empty()) {
+            Some(id_str) => {
+                let id = Uuid::parse_str(id_str).ok()?;
+                state
+                    .sectors
+                    .iter()
+                    .find(|s| s.hubs.iter().any(|h| h.id == id))
+                    .map(|s| (s.id, id))
+            }
+            None => {
+                let sector = state.sectors.get(state.active_sector_index)?;
+                let hub = sector.hubs.get(sector.active_hub_index)?;
+                Some((sector.id, hub.id))
+            }
+        }
+    }
+
+    fn bus_snapshot(&self) -> BusSnapshot {
+        let state = self.state.lock().unwrap();
+        BusSnapshot {
+            version: state.version,
+            active_sector: state.sectors.get(state.active_sector_index).map(|s| s.id),
+            sectors: state.sectors.iter().map(|s| (s.id, s.version)).collect(),
+            log_tail: state
+                .system_log
+                .last()
+                .map(|l| (l.timestamp, l.text.clone())),
+            confirmation: state.pending_confirmation.as_ref().map(|c| c.id),
+        }
+    }
+
+    /// §3.3.6: Publish what a request changed to the event bus.
+    fn publish_changes(&self, before: &BusSnapshot) {
+        let events = &self.services.events;
+        if events.subscriber_count() == 0 {
+            return;
+        }
+        let state = self.state.lock().unwrap();
+
+        // Entries appended after the previous tail (the log may have been trimmed).
+        let new_from = match &before.log_tail {
+            None => 0,
+            Some((ts, text)) => state
+                .system_log
+                .iter()
+                .rposition(|l| l.timestamp == *ts && l.text == *text)
+                .map(|i| i + 1)
+                .unwrap_or(0),
+        };
+        for line in &state.system_log[new_from..] {
+            if let Ok(payload) = serde_json::to_value(line) {
+                events.publish("system_log", payload);
+            }
+        }
+
+        if state.version == before.version {
+            return;
+        }
+
+        let active = state.sectors.get(state.active_sector_index).map(|s| s.id);
+        for sector in &state.sectors {
+            let event = match before.sectors.iter().find(|(id, _)| *id == sector.id) {
+                None => "created",
+                Some((_, v)) if *v != sector.version || Some(sector.id) == active => "updated",
+                Some(_) => continue,
+            };
+            events.publish(
+                format!("sector:{}", sector.id),
+                serde_json::json!({ "event": event, "name": sector.name, "version": state.version }),
+            );
+        }
+        for (id, _) in &before.sectors {
+            if !state.sectors.iter().any(|s| s.id == *id) {
+                events.publish(
+                    format!("sector:{}", id),
+                    serde_json::json!({ "event": "closed", "version": state.version }),
+                );
+            }
+        }
+        if active != before.active_sector {
+            if let Some(id) = active {
+                events.publish(
+                    format!("sector:{}", id),
+                    serde_json::json!({ "event": "activated", "version": state.version }),
+                );
+            }
+        }
+
+        if state.pending_confirmation.as_ref().map(|c| c.id) != before.confirmation {
+            let payload = serde_json::to_value(&state.pending_confirmation)
+                .unwrap_or(serde_json::Value::Null);
+            events.publish("confirmation", payload);
+        }
+
+        events.publish_state(state.version);
+    }
+
+    /// Standardized Message Format: prefix:payload;payload...
+    /// Also supports WebSocket wrapper: cmd:id:prefix:payload
+    ///
+    /// Runs as the local user; remote connections go through
+    /// [`IpcHandler::handle_request_as`].
+    pub fn handle_request(&self, request: &str) -> String {
+        self.handle_request_as(&crate::services::auth::ClientIdentity::local(), request)
+    }
+
+    /// §12.3: Dispatch a request on behalf of an authenticated client,
+    /// rejecting prefixes its role may not use.
+    pub fn handle_request_as(
+        &self,
+        identity: &crate::services::auth::ClientIdentity,
+        request: &str,
+    ) -> String {
+        tracing::info!("[IPC] Request: {}", request);
+
+        // §3.3.7: Structured requests; notifications get an empty reply.
+        if crate::rpc::is_rpc(request) {
+            return self.handle_rpc_as(identity, request).unwrap_or_default();
+        }
+
+        // Handle WebSocket wrapper: cmd:id:actual_command
+        if request.starts_with("cmd:") {
+            if let Some((_, rest)) = request.split_once(':') {
+                if let Some((id, actual_cmd)) = rest.split_once(':') {
+                    let response = LogContext {
+                        correlation_id: Some(id.to_string()),
+                        ..Default::default()
+                    }
+                    .scope(|| self.handle_request_as(identity, actual_cmd));
+                    return format!("res:{}:{}", id, response);
+                }
+            }
+        }
+
+        let (prefix, payload) = request.split_once(':').unwrap_or((request, ""));
+        let args: Vec<&str> = payload.split(';').collect();
+        self.dispatch(identity, prefix, payload, &args)
+    }
+
+    /// §3.3.7: Handle one JSON-RPC 2.0 request. Returns `None` for
+    /// notifications (requests without an id).
+    pub fn handle_rpc_as(
+        &self,
+        identity: &crate::services::auth::ClientIdentity,
+        request: &str,
+    ) -> Option<String> {
+        use crate::rpc::{codes, RpcError, RpcRequest, RpcResponse};
+
+        let req = match RpcRequest::parse(request) {
+            Ok(r) => r,
+            Err(response) => return Some(response.to_json()),
+        };
+        let id = req.id.clone().unwrap_or(serde_json::Value::Null);
+
+        let response = if req.method == crate::rpc::DISCOVER {
+            RpcResponse::success(id, crate::rpc::schema(&self.commands.read().unwrap()))
+        } else {
+            let spec = self.commands.read().unwrap().spec(&req.method).cloned();
+            match spec {
+                None => RpcResponse::failure(
+                    id,
+                    RpcError::new(
+                        codes::METHOD_NOT_FOUND,
+                        format!("Method not found: '{}'", req.method),
+                    ),
+                ),
+                Some(spec) => match spec.decode(req.params.as_ref()) {
+                    Err(e) => RpcResponse::failure(id, e),
+                    Ok(args) => {
+                        // Handlers that read the raw payload see the legacy
+                        // joined form; the rest get each argument verbatim.
+                        let payload = args.join(";");
+                        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+                        // Records the call logs carry its id (§19.1).
+                        let correlation_id = match &req.id {
+                            Some(serde_json::Value::String(id)) => Some(id.clone()),
+                            Some(id) => Some(id.to_string()),
+                            None => None,
+                        };
+                        let result = LogContext {
+                            correlation_id,
+                            ..Default::default()
+                        }
+                        .scope(|| self.dispatch(identity, &spec.name, &payload, &args));
+                        RpcResponse::from_legacy(id, &result)
+                    }
+                },
+            }
+        };
+
+        if req.is_notification() {
+            None
+        } else {
+            Some(response.to_json())
+        }
+    }
+
+    /// Check `identity` may send `prefix`, then run its registered handler.
+    fn dispatch(
+        &self,
+        identity: &crate::services::auth::ClientIdentity,
+        prefix: &str,
+        payload: &str,
+        args: &[&str],
+    ) -> String {
+        let (required, handler) = match self.commands.read().unwrap().handler(prefix) {
+            Some((role, handler)) => (role, Some(handler)),
+            None => (Self::required_role(prefix), None),
+        };
+        if identity.role < required {
+            tracing::warn!(
+                "PERMISSION_DENIED: {} by {} (role={:?})",
+                prefix,
+                identity.name,
+                identity.role
+            );
+            return format!(
+                "ERROR: Permission denied: '{}' requires {:?}",
+                prefix, required
+            );
+        }
+        let handler = match handler {
+            Some(h) => h,
+            None => return format!("ERROR: Unknown command '{}'", prefix),
+        };
+
+        let start = Instant::now();
+        let before = self.bus_snapshot();
+
+        let result = self.log_context(prefix, args).scope(|| {
+            handler(&CommandContext {
+                ipc: self,
+                identity,
+                payload,
+                args,
+            })
+        });
+
+        // Debounced session save on state-mutating events
+        if !prefix.starts_with("session_")
+            && !prefix.starts_with("get_")
+            && prefix != "tos_ports"
+            && prefix != "command_list"
+            && !prefix.starts_with("service_")
+            && !prefix.starts_with("trigger_")
+            && !prefix.starts_with("play_")
+            && !prefix.starts_with("trust_")
+        {
+            self.services
+                .session
+                .debounced_save_live(self.state.clone());
+        }
+
+        let duration = start.elapsed();
+        if duration.as_millis() > 16 {
+            let msg = format!("IPC LATENCY WARNING: {} took {:?}", prefix, duration);
+            tracing::warn!("{}", msg);
+            // Surface latency warnings in the Face's System Output
+            let mut state = self.state.lock().unwrap();
+            state.system_log.push(crate::TerminalLine {
+                text: msg,
+                priority: 2,
+                timestamp: chrono::Local::now(),
+                source: None,
+            });
+        }
+
+        self.publish_changes(&before);
+        result
+    }
+
+    /// The sector, hub and pane a call acts on, from the arguments of
+    /// those names its command declares.
+    fn log_context(&self, prefix: &str, args: &[&str]) -> LogContext {
+        let commands = self.commands.read().unwrap();
+        let id = |name: &str| {
+            let index = commands
+                .spec(prefix)?
+                .params
+                .iter()
+                .position(|p| p.name == name)?;
+            Uuid::parse_str(args.get(index)?).ok()
+        };
+        LogContext {
+            correlation_id: None,
+            sector_id: id("sector_id"),
+            hub_id: id("hub_id"),
+            pane_id: id("pane_id"),
+        }
+    }
+
+    fn check_permission(&self, role: crate::collaboration::ParticipantRole, prefix: &str) -> bool {
+        let required = match self.commands.read().unwrap().spec(prefix) {
+            Some(spec) => spec.role,
+            None => Self::required_role(prefix),
+        };
+        role >= required
+    }
+
+    /// §12.3: Least privileged role allowed to send the built-in `prefix`.
+    ///
+    /// Reads are open to Viewers, annotations to Commenters and everything
+    /// else to Operators, except commands that bypass confirmation, kill
+    /// processes wholesale, change trust or roles, or hand out access, which
+    /// are reserved for Co-owners. Each command declares its role when it is
+    /// registered; unregistered `get_*` names count as reads.
+    pub fn required_role(prefix: &str) -> crate::collaboration::ParticipantRole {
+        use crate::collaboration::ParticipantRole::*;
+        static BUILTIN: OnceLock<CommandRegistry> = OnceLock::new();
+        match BUILTIN.get_or_init(Self::builtin_commands).spec(prefix) {
+            Some(spec) => spec.role,
+            None if prefix.starts_with("get_") => Viewer,
+            None => Operator,
+        }
+    }
+}
+
+/// Run a module command's executable and reply with its standard output.
+fn run_module_command(
+    program: &std::path::Path,
+    fixed_args: &[String],
+    call_args: &[&str],
+) -> String {
+    let output = std::process::Command::new(program)
+        .args(fixed_args)
+        .args(call_args)
+        .stdin(std::process::Stdio::null())
+        .output();
+    match output {
+        Ok(out) if out.status.success() => {
+            String::from_utf8_lossy(&out.stdout).trim_end().to_string()
+        }
+        Ok(out) => format!(
+            "ERROR: {} exited with {}: {}",
+            program.display(),
+            out.status,
+            String::from_utf8_lossy(&out.stderr).trim()
+        ),
+        Err(e) => format!("ERROR: Failed to run {}: {}", program.display(), e),
+    }
+}
+
+/// Locate a hub by the ids returned from `IpcHandler::resolve_hub`.
+fn find_hub_mut(
+    state: &mut crate::TosState,
+    sector_id: Uuid,
+    hub_id: Uuid,
+) -> Option<&mut crate::CommandHub> {
+    state
+        .sectors
+        .iter_mut()
+        .find(|s| s.id == sector_id)
+        .and_then(|s| s.hubs.iter_mut().find(|h| h.id == hub_id))
+}
+
+impl crate::ipc::IpcDispatcher for IpcHandler {
+    fn dispatch(&self, request: &str) -> String {
+        self.handle_request(request)
+    }
+}
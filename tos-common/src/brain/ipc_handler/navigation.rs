@@ -0,0 +1,572 @@
+//! Hierarchy navigation (§5), bezel (§25), directory picks (§24.6), onboarding,
+//! voice (§14.3) and switch scanning (§14.5) commands.
+
+use super::*;
+use crate::brain::command_registry::{CommandRegistry, CommandSpec};
+use crate::rpc::ParamKind;
+
+pub(super) fn register(r: &mut CommandRegistry) {
+    r.add(
+        CommandSpec::new("set_mode", "Set the hierarchy level or command hub mode")
+            .param("mode", ParamKind::Text),
+        |cx| cx.ipc.handle_set_mode(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("zoom_in", "Zoom one level into the hierarchy"),
+        |cx| cx.ipc.handle_zoom_in(),
+    );
+    r.add(
+        CommandSpec::new("zoom_out", "Zoom one level out of the hierarchy"),
+        |cx| cx.ipc.handle_zoom_out(),
+    );
+    r.add(
+        CommandSpec::new("set_active_sector", "Focus the sector at an index")
+            .param("index", ParamKind::Integer),
+        |cx| cx.ipc.handle_set_active_sector(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("zoom_to", "Jump to a hierarchy level").param("level", ParamKind::Text),
+        |cx| cx.ipc.handle_zoom_to(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("click", "Run a bezel action by identifier")
+            .param("action", ParamKind::Text),
+        |cx| cx.ipc.handle_click(cx.payload),
+    );
+    r.add(
+        CommandSpec::new(
+            "dir_pick_file",
+            "Pick a file from the directory listing into the prompt",
+        )
+        .param("index", ParamKind::Integer),
+        |cx| cx.ipc.handle_dir_pick(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "dir_pick_dir",
+            "Pick a directory from the directory listing into the prompt",
+        )
+        .param("index", ParamKind::Integer),
+        |cx| cx.ipc.handle_dir_pick(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("dir_navigate", "Change the active hub's directory")
+            .param("path", ParamKind::Text),
+        |cx| cx.ipc.handle_dir_navigate(cx.arg(0)),
+    );
+    r.add(CommandSpec::new("bezel_expand", "Expand the bezel"), |cx| {
+        cx.ipc.handle_bezel_expand()
+    });
+    r.add(
+        CommandSpec::new("bezel_collapse", "Collapse the bezel"),
+        |cx| cx.ipc.handle_bezel_collapse(),
+    );
+    r.add(
+        CommandSpec::new("bezel_swipe", "Cycle the focused application left or right")
+            .optional("direction", ParamKind::Text),
+        |cx| cx.ipc.handle_bezel_swipe(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("bezel_activate", "Activate a bezel component")
+            .param("id", ParamKind::Text),
+        |cx| cx.ipc.handle_bezel_activate(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("bezel_deactivate", "Deactivate a bezel component")
+            .param("id", ParamKind::Text),
+        |cx| cx.ipc.handle_bezel_deactivate(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("bezel_click", "Forward a click to a bezel component")
+            .param("component_id", ParamKind::Text)
+            .param("element_id", ParamKind::Text)
+            .optional("x", ParamKind::Number)
+            .optional("y", ParamKind::Number),
+        |cx| {
+            cx.ipc
+                .handle_bezel_click(cx.arg(0), cx.arg(1), cx.arg(2), cx.arg(3))
+        },
+    );
+    r.add(
+        CommandSpec::new("onboarding_skip_tour", "Skip the onboarding tour"),
+        |cx| cx.ipc.handle_onboarding_skip_tour(),
+    );
+    r.add(
+        CommandSpec::new(
+            "onboarding_advance_step",
+            "Advance the onboarding tour to a step",
+        )
+        .optional("step", ParamKind::Integer),
+        |cx| cx.ipc.handle_onboarding_advance_step(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("onboarding_hint_dismiss", "Dismiss one onboarding hint")
+            .param("hint_id", ParamKind::Text),
+        |cx| cx.ipc.handle_onboarding_hint_dismiss(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("onboarding_hints_suppress", "Stop showing onboarding hints"),
+        |cx| cx.ipc.handle_onboarding_hints_suppress(),
+    );
+    r.add(
+        CommandSpec::new("onboarding_reset_hints", "Show onboarding hints again"),
+        |cx| cx.ipc.handle_onboarding_reset_hints(),
+    );
+    r.add(
+        CommandSpec::new("voice_command_start", "Start listening for a voice command"),
+        |cx| cx.ipc.handle_voice_command_start(),
+    );
+    r.add(
+        CommandSpec::new("voice_transcription", "Run a transcribed voice command")
+            .param("text", ParamKind::Text),
+        |cx| cx.ipc.handle_voice_transcription(cx.payload),
+    );
+    r.add(
+        CommandSpec::new("access_scan_toggle", "Toggle switch scanning"),
+        |cx| {
+            cx.ipc.services.accessibility.toggle_scanning();
+            "OK".to_string()
+        },
+    );
+    r.add(
+        CommandSpec::new(
+            "access_scan_advance",
+            "Move the switch-scanning cursor to the next target",
+        ),
+        |cx| {
+            cx.ipc.services.accessibility.advance_scan();
+            "OK".to_string()
+        },
+    );
+    r.add(
+        CommandSpec::new(
+            "access_scan_select",
+            "Activate the current switch-scanning target",
+        ),
+        |cx| cx.ipc.services.accessibility.select_current(),
+    );
+}
+
+impl IpcHandler {
+    pub(super) fn handle_voice_command_start(&self) -> String {
+        tracing::info!("Voice command started listening...");
+        "VOICE_COMMAND_STARTED".to_string()
+    }
+
+    pub(super) fn handle_voice_transcription(&self, payload: &str) -> String {
+        let text_lower = payload.to_lowercase();
+        let text_lower = text_lower.trim();
+
+        // 1. Focus [Sector]
+        if let Some(target) = text_lower.strip_prefix("focus ") {
+            let target = target.trim();
+            let state = self.state.lock().unwrap();
+            let mut found_idx = None;
+            for (i, sector) in state.sectors.iter().enumerate() {
+                if sector.name.to_lowercase() == target {
+                    found_idx = Some(i);
+                    break;
+                }
+            }
+            drop(state);
+            if let Some(idx) = found_idx {
+                return self.handle_set_active_sector(Some(&idx.to_string()));
+            } else {
+                return format!("ERROR: Sector '{}' not found", target);
+            }
+        }
+
+        // 2. Run [Command]
+        if let Some(cmd) = text_lower.strip_prefix("run ") {
+            let cmd = cmd.trim();
+            return self.handle_prompt_submit(cmd, false);
+        }
+
+        // 3. Inspect [Target]
+        if text_lower.starts_with("inspect ") {
+            // "Inspect browser" -> simplified to zoom into DetailView for now
+            return self.handle_zoom_to(Some("detail"));
+        }
+
+        // 4. Alert Status
+        if text_lower == "report alert status" || text_lower == "alert status" {
+            let state = self.state.lock().unwrap();
+            let mut critical = 0;
+            let mut warning = 0;
+            for log in &state.system_log {
+                if log.priority >= 4 {
+                    critical += 1;
+                } else if log.priority == 3 {
+                    warning += 1;
+                }
+            }
+            let msg = if critical > 0 {
+                format!(
+                    "You have {} critical alerts and {} warnings.",
+                    critical, warning
+                )
+            } else if warning > 0 {
+                format!("You have {} warnings. All clear on critical.", warning)
+            } else {
+                "All systems nominal. No alerts.".to_string()
+            };
+            drop(state);
+            return self.handle_audio_voice_play(Some(&msg));
+        }
+
+        // 5. Stop everything
+        if text_lower == "stop everything" {
+            return self.handle_tactical_kill_switch();
+        }
+
+        // Fallback
+        self.handle_ai_submit(payload)
+    }
+
+    pub(super) fn handle_set_mode(&self, mode_str: Option<&str>) -> String {
+        let mode_raw = mode_str.unwrap_or("");
+
+        // 1. Try Hierarchy Levels / ViewModes
+        let target_level = match mode_raw {
+            "global" | "GlobalOverview" => Some(HierarchyLevel::GlobalOverview),
+            "hubs" | "CommandHub" => Some(HierarchyLevel::CommandHub),
+            "sectors" | "ApplicationFocus" => Some(HierarchyLevel::ApplicationFocus),
+            "detail" | "DetailView" => Some(HierarchyLevel::DetailView),
+            "buffer" | "BufferView" => Some(HierarchyLevel::BufferView),
+            "marketplace" | "Marketplace" => Some(HierarchyLevel::Marketplace),
+            _ => None,
+        };
+
+        if let Some(level) = target_level {
+            let mut state = self.state.lock().unwrap();
+            crate::brain::hierarchy::HierarchyManager::set_level(&mut state, level);
+            return format!("LEVEL_SET: {:?}", level);
+        }
+
+        // 2. Try Command Hub Modes
+        let hub_mode = match mode_raw {
+            "command" => Some(CommandHubMode::Command),
+            "directory" => Some(CommandHubMode::Directory),
+            "activity" => Some(CommandHubMode::Activity),
+            "search" => Some(CommandHubMode::Search),
+            "ai" => Some(CommandHubMode::Ai),
+            _ => None,
+        };
+
+        if let Some(mode) = hub_mode {
+            let mut state = self.state.lock().unwrap();
+            let idx = state.active_sector_index;
+            if let Some(sector) = state.sectors.get_mut(idx) {
+                let hub_idx = sector.active_hub_index;
+                if let Some(hub) = sector.hubs.get_mut(hub_idx) {
+                    hub.mode = mode;
+                    if mode == CommandHubMode::Directory {
+                        crate::brain::sector::SectorManager::refresh_directory_listing(&mut state);
+                    } else if mode == CommandHubMode::Activity {
+                        crate::brain::sector::SectorManager::refresh_activity_listing(
+                            &mut state,
+                            Some(&self.services.capture),
+                        );
+                    }
+                    return format!("MODE_SET: {:?}", mode);
+                }
+            }
+            return "ERROR: Hub not found".to_string();
+        }
+
+        "ERROR: Unknown mode or level".to_string()
+    }
+
+    pub(super) fn handle_dir_navigate(&self, path_str: Option<&str>) -> String {
+        let path = match path_str {
+            Some(p) => std::path::PathBuf::from(p),
+            None => return "ERROR: Missing path".to_string(),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let idx = state.active_sector_index;
+        let mut navigated_path = None;
+
+        if let Some(sector) = state.sectors.get_mut(idx) {
+            let hub_idx = sector.active_hub_index;
+            if let Some(hub) = sector.hubs.get_mut(hub_idx) {
+                hub.current_directory = path;
+                navigated_path = Some(hub.current_directory.display().to_string());
+            }
+        }
+
+        if let Some(p) = navigated_path {
+            crate::brain::sector::SectorManager::refresh_directory_listing(&mut state);
+            state.version += 1;
+            return format!("DIR_NAVIGATED: {}", p);
+        }
+
+        "ERROR: No active hub".to_string()
+    }
+
+    pub(super) fn handle_dir_pick(&self, index_str: Option<&str>) -> String {
+        let index: usize = match index_str.and_then(|i| i.parse().ok()) {
+            Some(idx) => idx,
+            None => return "ERROR: Missing or invalid index".to_string(),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let active_idx = state.active_sector_index;
+
+        let (full_path, is_dir_entry) = {
+            let hub = state
+                .sectors
+                .get(active_idx)
+                .and_then(|s| s.hubs.get(s.active_hub_index));
+
+            if let Some(hub) = hub {
+                if let Some(ref listing) = hub.shell_listing {
+                    if let Some(entry) = listing.entries.get(index) {
+                        let path = std::path::PathBuf::from(&listing.path).join(&entry.name);
+                        (Some(path), entry.is_dir)
+                    } else {
+                        (None, false)
+                    }
+                } else {
+                    (None, false)
+                }
+            } else {
+                (None, false)
+            }
+        };
+
+        if let Some(path) = full_path {
+            let path_str = path.to_string_lossy().to_string();
+            let mut refreshed = false;
+            {
+                let sector = &mut state.sectors[active_idx];
+                let hub_idx = sector.active_hub_index;
+                let hub = &mut sector.hubs[hub_idx];
+
+                if let Some(ref mut staged) = hub.staged_command {
+                    // Requirement 3: Append absolute path as argument
+                    if !staged.ends_with(' ') && !staged.is_empty() {
+                        staged.push(' ');
+                    }
+                    staged.push_str(&path_str);
+                } else if hub.prompt.is_empty() {
+                    if is_dir_entry {
+                        // Requirement 2: Navigate
+                        hub.current_directory = path;
+                        refreshed = true;
+                    } else {
+                        // Requirement 1: Insert
+                        hub.prompt = path_str;
+                    }
+                }
+            }
+
+            if refreshed {
+                crate::brain::sector::SectorManager::refresh_directory_listing(&mut state);
+            }
+
+            state.version += 1;
+            return "DIR_PICKED".to_string();
+        }
+
+        "ERROR: Entry not found".to_string()
+    }
+
+    pub(super) fn handle_zoom_to(&self, level_str: Option<&str>) -> String {
+        let mode_raw = level_str.unwrap_or("");
+
+        let target_level = match mode_raw {
+            "global" | "GlobalOverview" => Some(HierarchyLevel::GlobalOverview),
+            "hubs" | "CommandHub" => Some(HierarchyLevel::CommandHub),
+            "sectors" | "ApplicationFocus" => Some(HierarchyLevel::ApplicationFocus),
+            "detail" | "DetailView" => Some(HierarchyLevel::DetailView),
+            "buffer" | "BufferView" => Some(HierarchyLevel::BufferView),
+            "marketplace" | "Marketplace" => Some(HierarchyLevel::Marketplace),
+            _ => None,
+        };
+
+        if let Some(level) = target_level {
+            let mut state = self.state.lock().unwrap();
+            crate::brain::hierarchy::HierarchyManager::set_level(&mut state, level);
+            return format!("ZOOMED_TO: {:?}", level);
+        }
+
+        "ERROR: Unknown level".to_string()
+    }
+
+    pub(super) fn handle_zoom_in(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        crate::brain::hierarchy::HierarchyManager::zoom_in(&mut state);
+        "ZOOMED_IN".to_string()
+    }
+
+    pub(super) fn handle_zoom_out(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        crate::brain::hierarchy::HierarchyManager::zoom_out(&mut state);
+        "ZOOMED_OUT".to_string()
+    }
+
+    pub(super) fn handle_click(&self, payload: &str) -> String {
+        if payload == "ZOOM OUT" {
+            tracing::warn!("Bezel label rejected: ZOOM OUT. Use action identifier 'zoom_out'.");
+            return "ERROR: Use identifier".to_string();
+        } else if payload == "zoom_out" {
+            return self.handle_zoom_out();
+        } else if payload == "zoom_in" {
+            return self.handle_zoom_in();
+        }
+        "ERROR: Unknown click".to_string()
+    }
+
+    pub(super) fn handle_bezel_expand(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        state.bezel_expanded = true;
+        state.version += 1;
+        "BEZEL_EXPANDED".to_string()
+    }
+
+    pub(super) fn handle_bezel_collapse(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        state.bezel_expanded = false;
+        state.version += 1;
+        "BEZEL_COLLAPSED".to_string()
+    }
+
+    pub(super) fn handle_bezel_swipe(&self, dir: Option<&str>) -> String {
+        let dir = dir.unwrap_or("Right");
+        let mut state = self.state.lock().unwrap();
+        let idx = state.active_sector_index;
+        if let Some(sector) = state.sectors.get_mut(idx) {
+            if !sector.active_apps.is_empty() {
+                if dir == "Right" {
+                    sector.active_app_index =
+                        (sector.active_app_index + 1) % sector.active_apps.len();
+                } else {
+                    if sector.active_app_index == 0 {
+                        sector.active_app_index = sector.active_apps.len() - 1;
+                    } else {
+                        sector.active_app_index -= 1;
+                    }
+                }
+                state.version += 1;
+                return format!("BEZEL_SWIPED: {}", dir);
+            }
+        }
+        "ERROR: No apps to swipe".to_string()
+    }
+
+    pub(super) fn handle_onboarding_skip_tour(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        state.settings.global.insert(
+            "tos.onboarding.wizard_complete".to_string(),
+            "true".to_string(),
+        );
+        state.version += 1;
+        "ONBOARDING_SKIPPED".to_string()
+    }
+
+    pub(super) fn handle_onboarding_advance_step(&self, step: Option<&str>) -> String {
+        let step = step.unwrap_or("0");
+        let mut state = self.state.lock().unwrap();
+        state
+            .settings
+            .global
+            .insert("tos.onboarding.current_step".to_string(), step.to_string());
+        state.version += 1;
+        format!("ONBOARDING_STEP: {}", step)
+    }
+
+    pub(super) fn handle_onboarding_hint_dismiss(&self, hint_id: Option<&str>) -> String {
+        let id = hint_id.unwrap_or("unknown");
+        let mut state = self.state.lock().unwrap();
+        state
+            .settings
+            .global
+            .insert(format!("tos.hint.dismissed.{}", id), "true".to_string());
+        state.version += 1;
+        format!("HINT_DISMISSED: {}", id)
+    }
+
+    pub(super) fn handle_onboarding_hints_suppress(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        state
+            .settings
+            .global
+            .insert("tos.hint.suppressed".to_string(), "true".to_string());
+        state.version += 1;
+        "HINTS_SUPPRESSED".to_string()
+    }
+
+    pub(super) fn handle_onboarding_reset_hints(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        // Remove all keys starting with tos.hint.dismissed.
+        let keys_to_remove: Vec<String> = state
+            .settings
+            .global
+            .keys()
+            .filter(|k| k.starts_with("tos.hint.dismissed."))
+            .cloned()
+            .collect();
+        for k in keys_to_remove {
+            state.settings.global.remove(&k);
+        }
+        state.settings.global.remove("tos.hint.suppressed");
+        state.version += 1;
+        "HINTS_RESET".to_string()
+    }
+
+    pub(super) fn handle_bezel_activate(&self, id: Option<&str>) -> String {
+        let id = match id {
+            Some(i) => i,
+            None => return "ERROR: Missing component ID".to_string(),
+        };
+        match self.services.bezel.activate_component(id) {
+            Ok(_) => "BEZEL_ACTIVATED".to_string(),
+            Err(e) => format!("ERROR: {}", e),
+        }
+    }
+
+    pub(super) fn handle_bezel_deactivate(&self, id: Option<&str>) -> String {
+        let id = match id {
+            Some(i) => i,
+            None => return "ERROR: Missing component ID".to_string(),
+        };
+        self.services.bezel.deactivate_component(id);
+        "BEZEL_DEACTIVATED".to_string()
+    }
+
+    pub(super) fn handle_bezel_click(
+        &self,
+        component_id: Option<&str>,
+        element_id: Option<&str>,
+        x: Option<&str>,
+        y: Option<&str>,
+    ) -> String {
+        let cid = match component_id {
+            Some(i) => i,
+            None => return "ERROR: Missing component ID".to_string(),
+        };
+        let eid = element_id.unwrap_or("");
+        let x_val = x.and_then(|v| v.parse::<f32>().ok()).unwrap_or(0.0);
+        let y_val = y.and_then(|v| v.parse::<f32>().ok()).unwrap_or(0.0);
+
+        self.services.bezel.handle_click(cid, eid, x_val, y_val);
+        "OK".to_string()
+    }
+
+    pub(super) fn handle_set_active_sector(&self, idx_str: Option<&str>) -> String {
+        if let Some(s) = idx_str {
+            if let Ok(idx) = s.parse::<usize>() {
+                let mut state = self.state.lock().unwrap();
+                if idx < state.sectors.len() {
+                    state.active_sector_index = idx;
+                    crate::brain::sector::SectorManager::refresh_activity_listing(&mut state, None);
+                    state.version += 1;
+                    return format!("ACTIVE_SECTOR_SET: {}", idx);
+                }
+                return "ERROR: Sector index out of bounds".to_string();
+            }
+        }
+        "ERROR: Invalid sector index".to_string()
+    }
+}
@@ -0,0 +1,143 @@
+//! Priority rule commands (Visual Design §1.2).
+
+use super::*;
+use crate::brain::command_registry::{CommandRegistry, CommandSpec};
+use crate::collaboration::ParticipantRole;
+use crate::rpc::ParamKind;
+use crate::services::priority_rules::{self, PriorityRule};
+
+/// Matches listed per rule and sector by `priority_rule_test`.
+const TEST_MATCH_LIMIT: usize = 20;
+
+pub(super) fn register(r: &mut CommandRegistry) {
+    r.add(
+        CommandSpec::new("priority_rules", "Saved priority rules as JSON")
+            .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_priority_rules(),
+    );
+    r.add(
+        CommandSpec::new(
+            "priority_rule_set",
+            "Add a priority rule, or replace the one with the same id",
+        )
+        .param("rule", ParamKind::Json),
+        |cx| cx.ipc.handle_priority_rule_set(cx.payload),
+    );
+    r.add(
+        CommandSpec::new("priority_rule_remove", "Delete a priority rule")
+            .param("id", ParamKind::Text),
+        |cx| cx.ipc.handle_priority_rule_remove(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "priority_rule_test",
+            "Match a rule, or the saved rules, against recorded commands and output without firing it",
+        )
+        .optional("test", ParamKind::Json)
+        .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_priority_rule_test(cx.payload),
+    );
+}
+
+impl IpcHandler {
+    pub(super) fn handle_priority_rules(&self) -> String {
+        let state = self.state.lock().unwrap();
+        serde_json::to_string(&priority_rules::load(&state.settings))
+            .unwrap_or_else(|_| "[]".to_string())
+    }
+
+    pub(super) fn handle_priority_rule_set(&self, payload: &str) -> String {
+        let rule: PriorityRule = match serde_json::from_str(payload) {
+            Ok(r) => r,
+            Err(e) => return format!("ERROR: Invalid priority rule: {}", e),
+        };
+        if let Err(e) = rule.compile() {
+            return format!("ERROR: {}", e);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let mut rules = priority_rules::load(&state.settings);
+        match rules.iter_mut().find(|r| r.id == rule.id) {
+            Some(existing) => *existing = rule.clone(),
+            None => rules.push(rule.clone()),
+        }
+        priority_rules::store(&mut state.settings, &rules);
+        let _ = self.services.settings.save(&state.settings);
+        state.version += 1;
+        format!("PRIORITY_RULE_SET: {}", rule.id)
+    }
+
+    pub(super) fn handle_priority_rule_remove(&self, id: Option<&str>) -> String {
+        let id = match id {
+            Some(i) => i,
+            None => return "ERROR: Missing rule id".to_string(),
+        };
+        let mut state = self.state.lock().unwrap();
+        let mut rules = priority_rules::load(&state.settings);
+        let before = rules.len();
+        rules.retain(|r| r.id != id);
+        if rules.len() == before {
+            return format!("ERROR: No priority rule '{}'", id);
+        }
+        priority_rules::store(&mut state.settings, &rules);
+        let _ = self.services.settings.save(&state.settings);
+        state.version += 1;
+        format!("PRIORITY_RULE_REMOVED: {}", id)
+    }
+
+    /// `{"rule": {...}, "sector": "<id>"}`, both optional: without a rule
+    /// the saved enabled rules are tested, without a sector every sector.
+    /// Everything the hubs still hold is matched, however old.
+    pub(super) fn handle_priority_rule_test(&self, payload: &str) -> String {
+        #[derive(serde::Deserialize, Default)]
+        struct Test {
+            #[serde(default)]
+            rule: Option<PriorityRule>,
+            #[serde(default)]
+            sector: Option<String>,
+        }
+        let test: Test = if payload.trim().is_empty() {
+            Test::default()
+        } else {
+            match serde_json::from_str(payload) {
+                Ok(t) => t,
+                Err(e) => return format!("ERROR: Invalid rule test: {}", e),
+            }
+        };
+
+        let state = self.state.lock().unwrap();
+        let rules = match &test.rule {
+            Some(rule) => match rule.compile() {
+                Ok(compiled) => vec![compiled],
+                Err(e) => return format!("ERROR: {}", e),
+            },
+            None => priority_rules::compile_all(&priority_rules::load(&state.settings)),
+        };
+
+        let mut results = Vec::new();
+        for sector in &state.sectors {
+            if test
+                .sector
+                .as_deref()
+                .is_some_and(|s| s != sector.id.to_string())
+            {
+                continue;
+            }
+            for rule in &rules {
+                let matches = rule.matches(sector, None);
+                let Some(newest) = matches.first() else {
+                    continue;
+                };
+                results.push(serde_json::json!({
+                    "rule": rule.rule().id,
+                    "sector_id": sector.id,
+                    "sector": sector.name,
+                    "effect": rule.hit(newest).reason(),
+                    "match_count": matches.len(),
+                    "matches": &matches[..matches.len().min(TEST_MATCH_LIMIT)],
+                }));
+            }
+        }
+        serde_json::to_string(&results).unwrap_or_else(|_| "[]".to_string())
+    }
+}
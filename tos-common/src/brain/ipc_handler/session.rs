@@ -0,0 +1,192 @@
+//! Session save, restore, export and handoff commands.
+
+use super::*;
+use crate::brain::command_registry::{CommandRegistry, CommandSpec};
+use crate::collaboration::ParticipantRole;
+use crate::rpc::ParamKind;
+
+pub(super) fn register(r: &mut CommandRegistry) {
+    r.add(
+        CommandSpec::new("session_list", "Saved sessions as JSON")
+            .optional("sector_id", ParamKind::Text)
+            .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_session_list(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("session_save", "Save a sector as a named session")
+            .param("sector_id", ParamKind::Text)
+            .param("name", ParamKind::Text),
+        |cx| cx.ipc.handle_session_save(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("session_load", "Load a named session into a sector")
+            .param("sector_id", ParamKind::Text)
+            .param("name", ParamKind::Text),
+        |cx| cx.ipc.handle_session_load(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("session_delete", "Delete a saved session")
+            .param("sector_id", ParamKind::Text)
+            .param("name", ParamKind::Text),
+        |cx| cx.ipc.handle_session_delete(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("session_live_write", "Write the live session file now"),
+        |cx| cx.ipc.handle_session_live_write(),
+    );
+    r.add(
+        CommandSpec::new("session_export", "Export a saved session as JSON")
+            .param("sector_id", ParamKind::Text)
+            .param("name", ParamKind::Text),
+        |cx| cx.ipc.handle_session_export(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("session_import", "Import a session from JSON")
+            .param("name", ParamKind::Text)
+            .param("session", ParamKind::Json)
+            .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_session_import(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new(
+            "session_handoff_prepare",
+            "Create a token for handing this session to another device",
+        ),
+        |cx| cx.ipc.handle_session_handoff_prepare(),
+    );
+    r.add(
+        CommandSpec::new(
+            "session_handoff_claim",
+            "Take over a session handed off with a token",
+        )
+        .param("token", ParamKind::Text)
+        .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_session_handoff_claim(cx.arg(0)),
+    );
+}
+
+impl IpcHandler {
+    pub(super) fn handle_session_list(&self, sector_id: Option<&str>) -> String {
+        let sid = sector_id.unwrap_or("global");
+        match self.services.session.list(sid) {
+            Ok(json) => format!("SESSION_LIST: {}", json),
+            Err(e) => format!("ERROR: {}", e),
+        }
+    }
+
+    pub(super) fn handle_session_save(
+        &self,
+        sector_id: Option<&str>,
+        name: Option<&str>,
+    ) -> String {
+        if let (Some(sid), Some(n)) = (sector_id, name) {
+            let state = self.state.lock().unwrap();
+            match self.services.session.save(sid, n, &state) {
+                Ok(_) => return format!("SESSION_SAVED: {}", n),
+                Err(e) => return format!("ERROR: {}", e),
+            }
+        }
+        "ERROR: Missing sector_id or name".to_string()
+    }
+
+    pub(super) fn handle_session_load(
+        &self,
+        sector_id: Option<&str>,
+        name: Option<&str>,
+    ) -> String {
+        if let (Some(sid), Some(n)) = (sector_id, name) {
+            match self.services.session.load(sid, n) {
+                Ok(json) => {
+                    if let Ok(new_state) = serde_json::from_str::<TosState>(&json) {
+                        let mut state = self.state.lock().unwrap();
+                        *state = new_state;
+                        return format!("SESSION_LOADED: {}", n);
+                    }
+                    return "ERROR: Session schema invalid".to_string();
+                }
+                Err(e) => return format!("ERROR: {}", e),
+            }
+        }
+        "ERROR: Missing sector_id or name".to_string()
+    }
+
+    pub(super) fn handle_session_delete(
+        &self,
+        sector_id: Option<&str>,
+        name: Option<&str>,
+    ) -> String {
+        if let (Some(sid), Some(n)) = (sector_id, name) {
+            match self.services.session.delete(sid, n) {
+                Ok(_) => return format!("SESSION_DELETED: {}", n),
+                Err(e) => return format!("ERROR: {}", e),
+            }
+        }
+        "ERROR: Missing sector_id or name".to_string()
+    }
+
+    pub(super) fn handle_session_live_write(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        match self.services.session.save_live(&state) {
+            Ok(_) => {
+                state.version += 1;
+                "SESSION_LIVE_WRITTEN".to_string()
+            }
+            Err(e) => format!("ERROR: {}", e),
+        }
+    }
+
+    pub(super) fn handle_session_export(
+        &self,
+        sector_id: Option<&str>,
+        name: Option<&str>,
+    ) -> String {
+        if let (Some(sid), Some(n)) = (sector_id, name) {
+            match self.services.session.load(sid, n) {
+                Ok(json) => return format!("SESSION_EXPORT: {}", json),
+                Err(e) => return format!("ERROR: {}", e),
+            }
+        }
+        "ERROR: Missing sector_id or name".to_string()
+    }
+
+    pub(super) fn handle_session_import(&self, name: Option<&str>, json: Option<&str>) -> String {
+        if let (Some(n), Some(data)) = (name, json) {
+            if let Ok(state) = serde_json::from_str::<TosState>(data) {
+                match self.services.session.save("global", n, &state) {
+                    Ok(_) => return format!("SESSION_IMPORTED: {}", n),
+                    Err(e) => return format!("ERROR: {}", e),
+                }
+            } else {
+                return "ERROR: Invalid session schema".to_string();
+            }
+        }
+        "ERROR: Missing name or json payload".to_string()
+    }
+
+    pub(super) fn handle_session_handoff_prepare(&self) -> String {
+        let state = self.state.lock().unwrap();
+        match self.services.session.prepare_handoff(&state) {
+            Ok(token) => format!("SESSION_HANDOFF_TOKEN: {}", token),
+            Err(e) => format!("ERROR: {}", e),
+        }
+    }
+
+    pub(super) fn handle_session_handoff_claim(&self, token: Option<&str>) -> String {
+        if let Some(t) = token {
+            match self.services.session.claim_handoff(t) {
+                Ok(json) => match serde_json::from_str(&json) {
+                    Ok(new_state) => {
+                        let mut state = self.state.lock().unwrap();
+                        *state = new_state;
+                        state.version += 1;
+                        "SESSION_HANDOFF_CLAIMED".to_string()
+                    }
+                    Err(e) => format!("ERROR: Claimed session schema invalid: {}", e),
+                },
+                Err(e) => format!("ERROR: {}", e),
+            }
+        } else {
+            "ERROR: Missing handoff token".to_string()
+        }
+    }
+}
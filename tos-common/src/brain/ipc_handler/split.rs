@@ -0,0 +1,281 @@
+//! Split viewport commands (§11.11).
+
+use super::*;
+use crate::brain::command_registry::{CommandRegistry, CommandSpec};
+use crate::rpc::ParamKind;
+
+pub(super) fn register(r: &mut CommandRegistry) {
+    r.add(
+        CommandSpec::new("split_create", "Split the focused pane")
+            .optional("width", ParamKind::Integer)
+            .optional("height", ParamKind::Integer),
+        |cx| cx.ipc.handle_split_create(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("split_close", "Close a split pane").param("pane_id", ParamKind::Uuid),
+        |cx| cx.ipc.handle_split_close(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("split_focus", "Focus a split pane").param("pane_id", ParamKind::Uuid),
+        |cx| cx.ipc.handle_split_focus(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "split_focus_direction",
+            "Move focus to the neighbouring pane",
+        )
+        .param("direction", ParamKind::Text),
+        |cx| cx.ipc.handle_split_focus_direction(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("split_resize", "Change a split pane's weight")
+            .param("pane_id", ParamKind::Uuid)
+            .param("weight", ParamKind::Number),
+        |cx| cx.ipc.handle_split_resize(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("split_equalize", "Give every split pane the same size"),
+        |cx| cx.ipc.handle_split_equalize(),
+    );
+    r.add(
+        CommandSpec::new("split_fullscreen", "Show one split pane fullscreen")
+            .param("pane_id", ParamKind::Uuid),
+        |cx| cx.ipc.handle_split_fullscreen(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "split_fullscreen_exit",
+            "Leave fullscreen and restore the split layout",
+        ),
+        |cx| cx.ipc.handle_split_fullscreen_exit(),
+    );
+    r.add(
+        CommandSpec::new("split_swap", "Swap two split panes")
+            .param("pane_a", ParamKind::Uuid)
+            .param("pane_b", ParamKind::Uuid),
+        |cx| cx.ipc.handle_split_swap(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new(
+            "split_detach",
+            "Move the focused pane into a new sector, keeping its context or starting fresh",
+        )
+        .optional("mode", ParamKind::Text),
+        |cx| match cx.arg(0).unwrap_or("") {
+            "" | "context" => cx.ipc.handle_split_detach_context(),
+            "fresh" => cx.ipc.handle_split_detach_fresh(),
+            other => format!("ERROR: Unknown detach mode '{}'", other),
+        },
+    );
+    r.add(
+        CommandSpec::new(
+            "split_save_template",
+            "Save the current split layout as a template",
+        )
+        .param("name", ParamKind::Text),
+        |cx| cx.ipc.handle_split_save_template(cx.arg(0)),
+    );
+}
+
+impl IpcHandler {
+    pub(super) fn handle_split_create(&self, w: Option<&str>, h: Option<&str>) -> String {
+        let display_w = w.and_then(|s| s.parse().ok()).unwrap_or(1920u32);
+        let display_h = h.and_then(|s| s.parse().ok()).unwrap_or(1080u32);
+        let mut state = self.state.lock().unwrap();
+        match crate::brain::sector::SectorManager::split_create(&mut state, display_w, display_h) {
+            Ok(id) => format!("SPLIT_CREATED: {}", id),
+            Err(e) => {
+                // Emit amber warning chip
+                state.system_log.push(crate::TerminalLine {
+                    text: format!("[SPLIT] ⚠ {}", e),
+                    priority: 2,
+                    timestamp: chrono::Local::now(),
+                    source: None,
+                });
+                // Also play earcon hint for the user
+                self.services.audio.play_earcon("warning");
+                e
+            }
+        }
+    }
+
+    pub(super) fn handle_split_close(&self, pane_id: Option<&str>) -> String {
+        if let Some(id_str) = pane_id {
+            if let Ok(id) = Uuid::parse_str(id_str) {
+                let mut state = self.state.lock().unwrap();
+                if crate::brain::sector::SectorManager::split_close(&mut state, id) {
+                    return format!("SPLIT_CLOSED: {}", id);
+                }
+                return "ERROR: Pane not found".to_string();
+            }
+        }
+        "ERROR: Missing or invalid pane_id".to_string()
+    }
+
+    pub(super) fn handle_split_focus(&self, pane_id: Option<&str>) -> String {
+        if let Some(id_str) = pane_id {
+            if let Ok(id) = Uuid::parse_str(id_str) {
+                let mut state = self.state.lock().unwrap();
+                if crate::brain::sector::SectorManager::split_focus(&mut state, id) {
+                    return format!("SPLIT_FOCUSED: {}", id);
+                }
+                return "ERROR: Pane not found".to_string();
+            }
+        }
+        "ERROR: Missing or invalid pane_id".to_string()
+    }
+
+    pub(super) fn handle_split_focus_direction(&self, direction: Option<&str>) -> String {
+        let dir = direction.unwrap_or("right");
+        let mut state = self.state.lock().unwrap();
+        match crate::brain::sector::SectorManager::split_focus_direction(&mut state, dir) {
+            Some(id) => format!("SPLIT_FOCUSED: {}", id),
+            None => "ERROR: No split panes active".to_string(),
+        }
+    }
+
+    pub(super) fn handle_split_resize(
+        &self,
+        pane_id: Option<&str>,
+        weight_str: Option<&str>,
+    ) -> String {
+        if let (Some(id_str), Some(w)) = (pane_id, weight_str) {
+            if let (Ok(id), Ok(weight)) = (Uuid::parse_str(id_str), w.parse::<f32>()) {
+                let mut state = self.state.lock().unwrap();
+                let idx = state.active_sector_index;
+                let hub_idx = state.sectors[idx].active_hub_index;
+                let hub = &mut state.sectors[idx].hubs[hub_idx];
+                fn set_weight(node: &mut crate::SplitNode, id: Uuid, weight: f32) -> bool {
+                    match node {
+                        crate::SplitNode::Leaf(p) => {
+                            if p.id == id {
+                                p.weight = weight;
+                                true
+                            } else {
+                                false
+                            }
+                        }
+                        crate::SplitNode::Container { children, .. } => {
+                            children.iter_mut().any(|c| set_weight(c, id, weight))
+                        }
+                    }
+                }
+                if let Some(layout) = &mut hub.split_layout {
+                    if set_weight(layout, id, weight) {
+                        return format!("SPLIT_RESIZED: {}→{}", id, weight);
+                    }
+                }
+                return "ERROR: Pane not found".to_string();
+            }
+        }
+        "ERROR: Missing pane_id or weight".to_string()
+    }
+
+    pub(super) fn handle_split_equalize(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        if crate::brain::sector::SectorManager::split_equalize(&mut state) {
+            "SPLIT_EQUALIZED".to_string()
+        } else {
+            "ERROR: No split layout active".to_string()
+        }
+    }
+
+    pub(super) fn handle_split_fullscreen(&self, pane_id: Option<&str>) -> String {
+        if let Some(id_str) = pane_id {
+            if let Ok(id) = Uuid::parse_str(id_str) {
+                let mut state = self.state.lock().unwrap();
+                if crate::brain::sector::SectorManager::split_fullscreen(&mut state, id) {
+                    return format!("SPLIT_FULLSCREEN: {}", id);
+                }
+                return "ERROR: Pane not found".to_string();
+            }
+        }
+        "ERROR: Missing or invalid pane_id".to_string()
+    }
+
+    pub(super) fn handle_split_fullscreen_exit(&self) -> String {
+        // Exit fullscreen: clear split_layout entirely (fall back to single pane mode)
+        let mut state = self.state.lock().unwrap();
+        let idx = state.active_sector_index;
+        let hub_idx = state.sectors[idx].active_hub_index;
+        let hub = &mut state.sectors[idx].hubs[hub_idx];
+        hub.split_layout = None;
+        hub.focused_pane_id = None;
+        "SPLIT_FULLSCREEN_EXIT".to_string()
+    }
+
+    pub(super) fn handle_split_swap(&self, pane_a: Option<&str>, pane_b: Option<&str>) -> String {
+        if let (Some(a_str), Some(b_str)) = (pane_a, pane_b) {
+            if let (Ok(a), Ok(b)) = (Uuid::parse_str(a_str), Uuid::parse_str(b_str)) {
+                let mut state = self.state.lock().unwrap();
+                let idx = state.active_sector_index;
+                let hub_idx = state.sectors[idx].active_hub_index;
+                let hub = &mut state.sectors[idx].hubs[hub_idx];
+
+                fn swap_leaves(node: &mut crate::SplitNode, a: Uuid, b: Uuid) {
+                    if let crate::SplitNode::Container { children, .. } = node {
+                        // Find positions of a and b within immediate children
+                        let pos_a = children
+                            .iter()
+                            .position(|c| matches!(c, crate::SplitNode::Leaf(p) if p.id == a));
+                        let pos_b = children
+                            .iter()
+                            .position(|c| matches!(c, crate::SplitNode::Leaf(p) if p.id == b));
+                        if let (Some(ia), Some(ib)) = (pos_a, pos_b) {
+                            children.swap(ia, ib);
+                        } else {
+                            for child in children.iter_mut() {
+                                swap_leaves(child, a, b);
+                            }
+                        }
+                    }
+                }
+
+                if let Some(layout) = &mut hub.split_layout {
+                    swap_leaves(layout, a, b);
+                    return format!("SPLIT_SWAPPED: {}↔{}", a, b);
+                }
+                return "ERROR: No split layout active".to_string();
+            }
+        }
+        "ERROR: Missing pane_a or pane_b".to_string()
+    }
+
+    pub(super) fn handle_split_detach_context(&self) -> String {
+        // "Bring Context" detach: not yet implemented (requires PTY re-parenting)
+        "SPLIT_DETACH_CONTEXT: NOT_YET_IMPLEMENTED".to_string()
+    }
+
+    pub(super) fn handle_split_detach_fresh(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        match crate::brain::sector::SectorManager::split_detach_fresh(&mut state) {
+            Some(sector_id) => format!("SPLIT_DETACHED: new_sector={}", sector_id),
+            None => "ERROR: No focused pane to detach".to_string(),
+        }
+    }
+
+    pub(super) fn handle_split_save_template(&self, name: Option<&str>) -> String {
+        let name = name.unwrap_or("unnamed");
+        let state = self.state.lock().unwrap();
+        let idx = state.active_sector_index;
+        let hub_idx = state.sectors[idx].active_hub_index;
+        let hub = &state.sectors[idx].hubs[hub_idx];
+        if let Some(layout) = &hub.split_layout {
+            match serde_json::to_string(layout) {
+                Ok(json) => {
+                    // Persist to settings for later restore
+                    drop(state);
+                    let mut state = self.state.lock().unwrap();
+                    state
+                        .settings
+                        .global
+                        .insert(format!("tos.split_template.{}", name), json);
+                    format!("SPLIT_TEMPLATE_SAVED: {}", name)
+                }
+                Err(e) => format!("ERROR: {}", e),
+            }
+        } else {
+            "ERROR: No split layout to save".to_string()
+        }
+    }
+}
@@ -0,0 +1,850 @@
+//! State, settings, services, keybindings (§14.2), timeline and privacy commands.
+
+use super::*;
+use crate::brain::command_registry::{CommandRegistry, CommandSpec};
+use crate::collaboration::ParticipantRole;
+use crate::rpc::ParamKind;
+use crate::services::logger::LogRecord;
+
+pub(super) fn register(r: &mut CommandRegistry) {
+    r.add(
+        CommandSpec::new(
+            "command_list",
+            "Registered commands as JSON, optionally only those starting with a prefix",
+        )
+        .optional("prefix", ParamKind::Text)
+        .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_command_list(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("get_state", "Full state snapshot as JSON").role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_get_state(),
+    );
+    r.add(
+        CommandSpec::new(
+            "system_reset",
+            "Reset the whole desktop to its initial state",
+        )
+        .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_system_reset(),
+    );
+    r.add(
+        CommandSpec::new(
+            "set_setting",
+            "Set a global setting, or a sector override when sector_id is given",
+        )
+        .param("key", ParamKind::Text)
+        .param("value", ParamKind::Text)
+        .optional("sector_id", ParamKind::Text),
+        |cx| cx.ipc.handle_set_setting(cx.arg(0), cx.arg(1), cx.arg(2)),
+    );
+    r.add(
+        CommandSpec::new(
+            "sector_set_setting",
+            "Set a setting override for one sector",
+        )
+        .param("sector_id", ParamKind::Text)
+        .param("key", ParamKind::Text)
+        .param("value", ParamKind::Text),
+        |cx| {
+            cx.ipc
+                .handle_set_sector_setting(cx.arg(0), cx.arg(1), cx.arg(2))
+        },
+    );
+    r.add(
+        CommandSpec::new("get_settings", "Resolved settings as JSON").role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_get_settings(),
+    );
+    r.add(
+        CommandSpec::new("set_sector_setting", "Alias of sector_set_setting")
+            .param("sector_id", ParamKind::Text)
+            .param("key", ParamKind::Text)
+            .param("value", ParamKind::Text),
+        |cx| {
+            cx.ipc
+                .handle_set_sector_setting(cx.arg(0), cx.arg(1), cx.arg(2))
+        },
+    );
+    r.add(
+        CommandSpec::new("system_log_append", "Append a line to the system log")
+            .param("priority", ParamKind::Integer)
+            .param("text", ParamKind::Text),
+        |cx| cx.ipc.handle_system_log_append(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new(
+            "system_log_record",
+            "Append a unified log record to the system log",
+        )
+        .param("record", ParamKind::Json),
+        |cx| cx.ipc.handle_system_log_record(cx.payload),
+    );
+    r.add(
+        CommandSpec::new(
+            "log_record",
+            "Submit a structured record to the unified log",
+        )
+        .param("record", ParamKind::Json),
+        |cx| cx.ipc.handle_log_record(cx.payload),
+    );
+    r.add(
+        CommandSpec::new("log_query", "Query the unified log")
+            .param("query", ParamKind::Json)
+            .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_log_query(cx.payload),
+    );
+    r.add(
+        CommandSpec::new("trigger_haptic", "Play a named haptic pattern")
+            .param("name", ParamKind::Text),
+        |cx| cx.ipc.handle_trigger_haptic(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "get_state_delta",
+            "JSON Patch from the given state version, or a full resync",
+        )
+        .optional("version", ParamKind::Integer)
+        .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_get_state_delta(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("set_terminal_module", "Switch the terminal output module")
+            .param("module_id", ParamKind::Text),
+        |cx| cx.ipc.handle_set_terminal_module(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("set_theme", "Switch the active theme module")
+            .param("theme_id", ParamKind::Text),
+        |cx| cx.ipc.handle_set_theme(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("face_register", "Register a Face and its device profile")
+            .param("registration", ParamKind::Json)
+            .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_face_register(cx.payload),
+    );
+    r.add(
+        CommandSpec::new(
+            "service_register",
+            "Register an auxiliary service with the Brain",
+        )
+        .param("registration", ParamKind::Json)
+        .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_service_register(cx.payload),
+    );
+    r.add(
+        CommandSpec::new("crash", "Record a Face crash report")
+            .param("report", ParamKind::Text)
+            .role(ParticipantRole::CoOwner),
+        |cx| match cx.ipc.services.logger.crash_report(cx.payload) {
+            Ok(()) => "OK".to_string(),
+            Err(e) => format!("ERROR: Crash report not delivered: {}", e),
+        },
+    );
+    r.add(
+        CommandSpec::new("crash_list", "Crash report groups by signature as JSON")
+            .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_crash_list(),
+    );
+    r.add(
+        CommandSpec::new(
+            "crash_show",
+            "A crash report by id, or the latest of a signature group",
+        )
+        .param("key", ParamKind::Text)
+        .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_crash_show(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "crash_purge",
+            "Delete the crash reports of a signature group, or all",
+        )
+        .param("signature", ParamKind::Text)
+        .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_crash_purge(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("tos_ports", "Ports of the Brain's listeners as JSON")
+            .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_tos_ports(),
+    );
+    r.add(
+        CommandSpec::new("service_deregister", "Remove a registered service")
+            .param("name", ParamKind::Text)
+            .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_service_deregister(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("clear_system_log", "Clear the system log"),
+        |cx| cx.ipc.handle_clear_system_log(),
+    );
+    r.add(
+        CommandSpec::new("timeline_scrub", "Show the timeline snapshot at an index")
+            .param("index", ParamKind::Integer),
+        |cx| cx.ipc.handle_timeline_scrub(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "timeline_reset",
+            "Return from a timeline snapshot to the live state",
+        ),
+        |cx| cx.ipc.handle_timeline_reset(),
+    );
+    r.add(
+        CommandSpec::new("privacy_incognito_toggle", "Toggle incognito mode")
+            .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_privacy_incognito_toggle(),
+    );
+    r.add(
+        CommandSpec::new(
+            "privacy_memory_archival_toggle",
+            "Toggle archival of AI interactions",
+        )
+        .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_privacy_memory_archival_toggle(),
+    );
+    r.add(
+        CommandSpec::new(
+            "privacy_confirm_archive_toggle",
+            "Toggle confirmation before archiving AI interactions",
+        )
+        .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_privacy_confirm_archive_toggle(),
+    );
+    r.add(
+        CommandSpec::new("keybindings_get", "Current keyboard shortcuts as JSON")
+            .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_keybindings_get(),
+    );
+    r.add(
+        CommandSpec::new("keybindings_set", "Bind a key combination to an action")
+            .param("combo", ParamKind::Text)
+            .param("action", ParamKind::Text)
+            .optional("description", ParamKind::Text),
+        |cx| {
+            cx.ipc
+                .handle_keybindings_set(cx.arg(0), cx.arg(1), cx.arg(2))
+        },
+    );
+    r.add(
+        CommandSpec::new(
+            "keybindings_reset",
+            "Restore the default keyboard shortcuts",
+        ),
+        |cx| cx.ipc.handle_keybindings_reset(),
+    );
+}
+
+impl IpcHandler {
+    /// §3.3.8: Name, help, parameters, role and source of every command,
+    /// for command palettes and shell completion.
+    pub(super) fn handle_command_list(&self, prefix: Option<&str>) -> String {
+        let prefix = prefix.unwrap_or("");
+        let registry = self.commands.read().unwrap();
+        let specs: Vec<&CommandSpec> = registry
+            .specs()
+            .filter(|spec| spec.name.starts_with(prefix))
+            .collect();
+        serde_json::to_string(&specs).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    pub(super) fn handle_set_setting(
+        &self,
+        key: Option<&str>,
+        val: Option<&str>,
+        sector_id: Option<&str>,
+    ) -> String {
+        if let (Some(k), Some(v)) = (key, val) {
+            let mut state = self.state.lock().unwrap();
+            if let Some(sid) = sector_id {
+                state
+                    .settings
+                    .sectors
+                    .entry(sid.to_string())
+                    .or_default()
+                    .insert(k.to_string(), v.to_string());
+            } else {
+                state.settings.global.insert(k.to_string(), v.to_string());
+            }
+            state.version += 1;
+            return format!("SETTING_UPDATE: {}={}", k, v);
+        }
+        "ERROR: Key and value required".to_string()
+    }
+
+    pub(super) fn handle_set_sector_setting(
+        &self,
+        sector_id: Option<&str>,
+        key: Option<&str>,
+        val: Option<&str>,
+    ) -> String {
+        if let (Some(sec), Some(k), Some(v)) = (sector_id, key, val) {
+            let mut state = self.state.lock().unwrap();
+            let entry = state.settings.sectors.entry(sec.to_string()).or_default();
+            entry.insert(k.to_string(), v.to_string());
+            let _ = self.services.settings.save(&state.settings);
+            return format!("SECTOR_SETTING_UPDATE: [{}] {}={}", sec, k, v);
+        }
+        "ERROR: Invalid sector setting args".to_string()
+    }
+
+    pub(super) fn handle_set_terminal_module(&self, module_id: Option<&str>) -> String {
+        if let Some(id) = module_id {
+            let mut state = self.state.lock().unwrap();
+
+            // Refresh available modules from disk
+            let mut modules = MarketplaceService::list_terminal_modules();
+            // Ensure built-ins are also there
+            if !modules.iter().any(|m| m.id == "tos-standard-rect") {
+                modules.push(crate::TerminalOutputModuleMeta {
+                    id: "tos-standard-rect".to_string(),
+                    name: "Standard Rectangular".to_string(),
+                    version: "1.0.0".to_string(),
+                    layout: crate::TerminalLayoutType::Rectangular,
+                    supports_high_contrast: true,
+                    supports_reduced_motion: true,
+                });
+            }
+            if !modules.iter().any(|m| m.id == "tos-cinematic-tri") {
+                modules.push(crate::TerminalOutputModuleMeta {
+                    id: "tos-cinematic-tri".to_string(),
+                    name: "Cinematic Triangular".to_string(),
+                    version: "1.0.0".to_string(),
+                    layout: crate::TerminalLayoutType::Cinematic,
+                    supports_high_contrast: false,
+                    supports_reduced_motion: false,
+                });
+            }
+            state.available_modules = modules;
+
+            if state.available_modules.iter().any(|m| m.id == id) {
+                state.active_terminal_module = id.to_string();
+                return format!("TERMINAL_MODULE_SET: {}", id);
+            }
+        }
+        "ERROR: Invalid terminal module ID".to_string()
+    }
+
+    pub(super) fn handle_set_theme(&self, theme_id: Option<&str>) -> String {
+        if let Some(id) = theme_id {
+            let mut state = self.state.lock().unwrap();
+
+            // Refresh themes from disk
+            let mut themes = MarketplaceService::list_theme_modules();
+            // Ensure built-ins are also there
+            if !themes.iter().any(|t| t.id == "tos-classic-lcars") {
+                themes.push(crate::ThemeModule {
+                    id: "tos-classic-lcars".to_string(),
+                    name: "Classic LCARS".to_string(),
+                    version: "1.0.0".to_string(),
+                    author: "TOS Core".to_string(),
+                    description: "Standard LCARS color scheme (Blue/Purple/Gold)".to_string(),
+                    assets: crate::ThemeAssetDefinition {
+                        css: "theme-classic.css".to_string(),
+                        fonts: vec!["Outfit-Regular.ttf".to_string()],
+                        icons: "assets/icons/classic/".to_string(),
+                    },
+                });
+            }
+            if !themes.iter().any(|t| t.id == "tos-tactical-amber") {
+                themes.push(crate::ThemeModule {
+                    id: "tos-tactical-amber".to_string(),
+                    name: "Tactical Amber".to_string(),
+                    version: "1.0.0".to_string(),
+                    author: "TOS Core".to_string(),
+                    description: "High-contrast amber tactical interface".to_string(),
+                    assets: crate::ThemeAssetDefinition {
+                        css: "theme-tactical.css".to_string(),
+                        fonts: vec!["Outfit-Bold.ttf".to_string()],
+                        icons: "assets/icons/tactical/".to_string(),
+                    },
+                });
+            }
+            if !themes.iter().any(|t| t.id == "tos-red-alert") {
+                themes.push(crate::ThemeModule {
+                    id: "tos-red-alert".to_string(),
+                    name: "Red Alert".to_string(),
+                    version: "1.0.0".to_string(),
+                    author: "TOS Core".to_string(),
+                    description: "High-intensity emergency mode".to_string(),
+                    assets: crate::ThemeAssetDefinition {
+                        css: "theme-red.css".to_string(),
+                        fonts: vec!["Outfit-Bold.ttf".to_string()],
+                        icons: "assets/icons/red/".to_string(),
+                    },
+                });
+            }
+            state.available_themes = themes;
+
+            if state.available_themes.iter().any(|t| t.id == id) {
+                state.active_theme = id.to_string();
+                return format!("THEME_SET: {}", id);
+            }
+        }
+        "ERROR: Invalid theme ID".to_string()
+    }
+
+    pub(super) fn handle_face_register(&self, payload: &str) -> String {
+        let reg: crate::ipc::FaceRegister = match serde_json::from_str(payload) {
+            Ok(r) => r,
+            Err(e) => return format!("ERROR: Invalid face_register JSON: {}", e),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        state.device_profile = reg.profile;
+
+        match reg.profile {
+            crate::ipc::FaceProfile::Handheld => {
+                // §3.3.5: Handheld profile automations
+                state.bezel_expanded = false;
+                // Set default hub layout to tabs (if supported by settings)
+                state
+                    .settings
+                    .global
+                    .insert("tos.layout.default".to_string(), "tabs".to_string());
+
+                tracing::info!("FACE_REGISTER: Handheld profile active. Adapting layout and AI.");
+            }
+            crate::ipc::FaceProfile::Spatial => {
+                tracing::info!("FACE_REGISTER: Spatial profile active. Enabling spatial bezel.");
+            }
+            _ => {}
+        }
+
+        state.version += 1;
+        format!("FACE_REGISTERED: {:?}", reg.profile)
+    }
+
+    pub(super) fn handle_service_register(&self, payload: &str) -> String {
+        let req: crate::ipc::ServiceRegister = match serde_json::from_str(payload) {
+            Ok(r) => r,
+            Err(e) => return format!("ERROR: Invalid registration JSON: {}", e),
+        };
+
+        // §4.1: Cryptographic signature verification
+        if !self.services.trust.verify_service_signature(&req) {
+            return serde_json::to_string(&crate::ipc::ServiceRegisterResponse {
+                status: "DENIED".to_string(),
+                message: "Cryptographic signature verification failed".to_string(),
+            })
+            .unwrap();
+        }
+
+        let mut registry = self.services.registry.lock().unwrap();
+        registry.register(&req.name, req.port, "127.0.0.1");
+
+        tracing::info!("SERVICE_REGISTER: {} on port {}", req.name, req.port);
+
+        serde_json::to_string(&crate::ipc::ServiceRegisterResponse {
+            status: "OK".to_string(),
+            message: format!("Service {} registered successfully", req.name),
+        })
+        .unwrap()
+    }
+
+    pub(super) fn handle_system_log_append(
+        &self,
+        priority_str: Option<&str>,
+        text_str: Option<&str>,
+    ) -> String {
+        if let (Some(priority), Some(text)) = (priority_str, text_str) {
+            self.push_system_line(crate::TerminalLine {
+                text: text.to_string(),
+                priority: priority.parse::<u8>().unwrap_or(1),
+                timestamp: chrono::Local::now(),
+                source: None,
+            });
+            return "LOGGED".to_string();
+        }
+        "ERROR: Invalid arguments for log".to_string()
+    }
+
+    /// §19.1: Show a unified log record in the system log, keeping its
+    /// source. Only messages and audit entries are shown; AI exchanges and
+    /// crash dumps stay in the log service.
+    pub(super) fn handle_system_log_record(&self, json_payload: &str) -> String {
+        let record: LogRecord = match serde_json::from_str(json_payload) {
+            Ok(r) => r,
+            Err(e) => return format!("ERROR: Invalid log record: {}", e),
+        };
+        if !matches!(record.event.as_str(), "log" | "audit") {
+            return "IGNORED".to_string();
+        }
+        let timestamp = chrono::DateTime::from_timestamp(record.ts, 0)
+            .map(|t| t.with_timezone(&chrono::Local))
+            .unwrap_or_else(chrono::Local::now);
+        self.push_system_line(crate::TerminalLine {
+            text: record.data,
+            priority: record.level.priority(),
+            timestamp,
+            source: Some(record.source),
+        });
+        "LOGGED".to_string()
+    }
+
+    /// §19.1: Submit a record to the unified log for a daemon or Face.
+    pub(super) fn handle_log_record(&self, json_payload: &str) -> String {
+        match serde_json::from_str::<LogRecord>(json_payload) {
+            Ok(record) => {
+                self.services.logger.record(record);
+                "LOGGED".to_string()
+            }
+            Err(e) => format!("ERROR: Invalid log record: {}", e),
+        }
+    }
+
+    pub(super) fn handle_crash_list(&self) -> String {
+        match self.services.logger.crash_list() {
+            Ok(response) => response,
+            Err(e) => format!("ERROR: Crash reports unavailable: {}", e),
+        }
+    }
+
+    pub(super) fn handle_crash_show(&self, key: Option<&str>) -> String {
+        let key = match key.map(str::trim) {
+            Some(k) if !k.is_empty() => k,
+            _ => return "ERROR: Missing crash id or signature".to_string(),
+        };
+        match self.services.logger.crash_show(key) {
+            Ok(response) => response,
+            Err(e) => format!("ERROR: Crash reports unavailable: {}", e),
+        }
+    }
+
+    pub(super) fn handle_crash_purge(&self, signature: Option<&str>) -> String {
+        let signature = match signature.map(str::trim) {
+            Some(s) if !s.is_empty() => s,
+            _ => return "ERROR: Missing crash signature (or 'all')".to_string(),
+        };
+        match self.services.logger.crash_purge(signature) {
+            Ok(response) => response,
+            Err(e) => format!("ERROR: Crash reports unavailable: {}", e),
+        }
+    }
+
+    /// Append to the system log, trimmed to `terminal_buffer_limit`.
+    fn push_system_line(&self, line: crate::TerminalLine) {
+        let mut state = self.state.lock().unwrap();
+        let limit: usize = state
+            .settings
+            .global
+            .get("terminal_buffer_limit")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1000);
+
+        state.system_log.push(line);
+
+        if state.system_log.len() > limit {
+            let to_drain = state.system_log.len() - limit;
+            state.system_log.drain(0..to_drain);
+        }
+    }
+
+    pub(super) fn handle_get_state(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let value = match serde_json::to_value(&*state) {
+            Ok(v) => v,
+            Err(_) => return "ERROR: Serialization failed".to_string(),
+        };
+        let json = value.to_string();
+        // A full fetch is a valid base for the client's next patch.
+        self.deltas.lock().unwrap().record(state.version, value);
+        json
+    }
+
+    pub(super) fn handle_system_reset(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        // Clear all sectors and push a fresh default one
+        state.sectors.clear();
+
+        // Use the same logic as TosState::default() for the initial sector
+        let sector = crate::state::Sector {
+            id: uuid::Uuid::new_v4(),
+            name: "Primary".to_string(),
+            hubs: vec![crate::state::CommandHub {
+                id: uuid::Uuid::new_v4(),
+                mode: crate::state::CommandHubMode::Command,
+                prompt: String::new(),
+                current_directory: std::env::current_dir()
+                    .unwrap_or_else(|_| std::path::PathBuf::from("/")),
+                terminal_output: vec![],
+                buffer_limit: 500,
+                shell_listing: None,
+                activity_listing: None,
+                search_results: None,
+                staged_command: None,
+                ai_explanation: None,
+                json_context: None,
+                shell_module: Some("tos-shell-fish".to_string()),
+                split_layout: None,
+                focused_pane_id: None,
+                version: 0,
+                ai_history: vec![],
+                active_thoughts: vec![],
+                last_exit_status: None,
+                is_running: false,
+                screen: None,
+                command_blocks: vec![],
+                output_offset: 0,
+            }],
+            active_hub_index: 0,
+            frozen: false,
+            is_remote: false,
+            disconnected: false,
+            trust_tier: crate::state::TrustTier::System,
+            priority: 1,
+            priority_factors: Vec::new(),
+            active_apps: vec![],
+            active_app_index: 0,
+            participants: vec![],
+            kanban_board: None,
+            version: 0,
+        };
+
+        state.sectors.push(sector);
+        state.active_sector_index = 0;
+        state.current_level = crate::state::HierarchyLevel::GlobalOverview;
+        state.version += 1;
+        drop(state);
+
+        // Old hubs are gone: reap their shells and spawn one for the new hub.
+        self.shells.lock().unwrap().sync();
+
+        "OK".to_string()
+    }
+
+    pub(super) fn handle_get_settings(&self) -> String {
+        let state = self.state.lock().unwrap();
+        serde_json::to_string(&state.settings)
+            .unwrap_or_else(|_| "ERROR: Serialization failed".to_string())
+    }
+
+    pub(super) fn handle_trigger_haptic(&self, name: Option<&str>) -> String {
+        if let Some(n) = name {
+            self.services.haptic.trigger_haptic(n);
+            return format!("HAPTIC_TRIGGERED: {}", n);
+        }
+        "ERROR: Invalid haptic cue".to_string()
+    }
+
+    pub(super) fn handle_log_query(&self, json_payload: &str) -> String {
+        // The query is passed through to tos-loggerd, which validates its
+        // filters; anything but an object falls back to the defaults.
+        let query = match serde_json::from_str::<serde_json::Value>(json_payload) {
+            Ok(q) if q.is_object() => q,
+            _ => serde_json::json!({}),
+        };
+
+        match self.services.logger.query(&query) {
+            Ok(response) => response,
+            Err(e) => format!("ERROR: Log query failed: {}", e),
+        }
+    }
+
+    pub(super) fn handle_get_state_delta(&self, last_version_str: Option<&str>) -> String {
+        let last_version = last_version_str
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(0);
+        let state = self.state.lock().unwrap();
+
+        if state.version == last_version {
+            return "NO_CHANGE".to_string();
+        }
+
+        // §3.3.2: Patch from the client's version when it is still retained,
+        // otherwise a full state so the client can resync.
+        let current = match serde_json::to_value(&*state) {
+            Ok(v) => v,
+            Err(_) => return "ERROR: Serialization failed".to_string(),
+        };
+        let delta = self
+            .deltas
+            .lock()
+            .unwrap()
+            .delta(last_version, state.version, current);
+        serde_json::to_string(&delta).unwrap_or_else(|_| "ERROR: Serialization failed".to_string())
+    }
+
+    pub(super) fn handle_tos_ports(&self) -> String {
+        let registry = self.services.registry.lock().unwrap();
+        // Return JSON for wire safety (port_table() is multi-line, breaks
+        // the line-based TCP protocol). Clients can pretty-print if needed.
+        let mut entries = Vec::new();
+        entries.push(serde_json::json!({
+            "name": "tos-brain (anchor)",
+            "port": registry.anchor_port(),
+            "host": "0.0.0.0",
+            "status": "ACTIVE"
+        }));
+        for svc in registry.list_all() {
+            entries.push(serde_json::json!({
+                "name": svc.name,
+                "port": svc.port,
+                "host": svc.host,
+                "status": if svc.alive { "ACTIVE" } else { "DEAD" }
+            }));
+        }
+        serde_json::to_string(&entries).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    pub(super) fn handle_service_deregister(&self, name: Option<&str>) -> String {
+        if let Some(n) = name {
+            let mut registry = self.services.registry.lock().unwrap();
+            registry.deregister(n);
+            return format!("SERVICE_DEREGISTERED: {}", n);
+        }
+        "ERROR: Missing service name".to_string()
+    }
+
+    pub(super) fn handle_clear_system_log(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        state.system_log.clear();
+        state.version += 1;
+        "SYSTEM_LOG_CLEARED".to_string()
+    }
+
+    /// Return the full keybinding map as JSON.
+    ///
+    /// If the user has a saved custom map in settings, that is returned.
+    /// Otherwise the spec defaults are returned.
+    pub(super) fn handle_keybindings_get(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let map = if let Some(json) = state.settings.global.get("tos.keybindings") {
+            crate::keybindings::KeybindingMap::from_json(json).unwrap_or_default()
+        } else {
+            crate::keybindings::KeybindingMap::default()
+        };
+        map.to_json()
+    }
+
+    /// Remap a keybinding: `keybindings_set:<combo_str>;<action>;<description>`
+    ///
+    /// Persists to settings. Returns the displaced action if a conflict was resolved.
+    pub(super) fn handle_keybindings_set(
+        &self,
+        combo_str: Option<&str>,
+        action: Option<&str>,
+        description: Option<&str>,
+    ) -> String {
+        let combo_str = match combo_str {
+            Some(s) => s,
+            None => return "ERROR: Missing key combo".to_string(),
+        };
+        let action = match action {
+            Some(a) => a,
+            None => return "ERROR: Missing action".to_string(),
+        };
+        let description = description.unwrap_or("");
+
+        let combo = match crate::keybindings::KeyCombo::parse(combo_str) {
+            Some(c) => c,
+            None => return format!("ERROR: Invalid key combo: {}", combo_str),
+        };
+
+        let mut state = self.state.lock().unwrap();
+
+        // Load existing or default map
+        let mut map = if let Some(json) = state.settings.global.get("tos.keybindings") {
+            crate::keybindings::KeybindingMap::from_json(json).unwrap_or_default()
+        } else {
+            crate::keybindings::KeybindingMap::default()
+        };
+
+        let displaced = map.set(combo, action.to_string(), description.to_string());
+
+        // Persist to settings
+        state
+            .settings
+            .global
+            .insert("tos.keybindings".to_string(), map.to_json());
+        state.version += 1;
+
+        match displaced {
+            Some(old_action) => format!("KEYBINDING_SET: {} (displaced: {})", action, old_action),
+            None => format!("KEYBINDING_SET: {}", action),
+        }
+    }
+
+    /// Reset all keybindings to spec defaults.
+    pub(super) fn handle_keybindings_reset(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        let map = crate::keybindings::KeybindingMap::default();
+        state
+            .settings
+            .global
+            .insert("tos.keybindings".to_string(), map.to_json());
+        state.version += 1;
+        "KEYBINDINGS_RESET".to_string()
+    }
+
+    pub(super) fn handle_timeline_scrub(&self, index: Option<&str>) -> String {
+        let index = match index.and_then(|i| i.parse::<usize>().ok()) {
+            Some(i) => i,
+            None => return "ERROR: Invalid index".to_string(),
+        };
+
+        if let Some(snapshot) = self.services.timeline.get_snapshot(index) {
+            let mut state = self.state.lock().unwrap();
+            let history_len = self.services.timeline.len();
+            *state = snapshot;
+            state.timeline_cursor = Some(index);
+            state.timeline_history_len = history_len;
+            "TIMELINE_SCRUBBED".to_string()
+        } else {
+            "ERROR: Snapshot not found".to_string()
+        }
+    }
+
+    pub(super) fn handle_timeline_reset(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        state.timeline_cursor = None;
+        "TIMELINE_RESET".to_string()
+    }
+
+    pub(super) fn handle_privacy_incognito_toggle(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        let current = state
+            .settings
+            .resolve("tos.privacy.incognito", None, None)
+            .as_deref()
+            == Some("true");
+        let new_val = if current { "false" } else { "true" };
+        state
+            .settings
+            .global
+            .insert("tos.privacy.incognito".to_string(), new_val.to_string());
+        state.privacy_active = new_val == "true";
+        format!("PRIVACY_INCOGNITO:{}", new_val)
+    }
+
+    pub(super) fn handle_privacy_memory_archival_toggle(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        let current = state
+            .settings
+            .resolve("tos.privacy.memory_archival", None, None)
+            .as_deref()
+            != Some("false");
+        let new_val = if current { "false" } else { "true" };
+        state.settings.global.insert(
+            "tos.privacy.memory_archival".to_string(),
+            new_val.to_string(),
+        );
+        format!("PRIVACY_MEMORY_ARCHIVAL:{}", new_val)
+    }
+
+    pub(super) fn handle_privacy_confirm_archive_toggle(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        let current = state
+            .settings
+            .resolve("tos.privacy.confirm_archive", None, None)
+            .as_deref()
+            != Some("false");
+        let new_val = if current { "false" } else { "true" };
+        state.settings.global.insert(
+            "tos.privacy.confirm_archive".to_string(),
+            new_val.to_string(),
+        );
+        format!("PRIVACY_CONFIRM_ARCHIVE:{}", new_val)
+    }
+}
@@ -0,0 +1,748 @@
+//! Prompt submission, terminal I/O (§26), command blocks (§26.4) and search.
+
+use super::*;
+use crate::brain::command_registry::{CommandRegistry, CommandSpec};
+use crate::collaboration::ParticipantRole;
+use crate::rpc::ParamKind;
+use crate::services::heuristic::HeuristicService;
+
+pub(super) fn register(r: &mut CommandRegistry) {
+    // The index holds every sector's command history, output and AI
+    // exchanges, so searching it needs Operator.
+    r.add(
+        CommandSpec::new(
+            "search",
+            "Search files, commands and logs (keyword and semantic ranking)",
+        )
+        .param("query", ParamKind::Text),
+        |cx| cx.ipc.handle_search(cx.payload),
+    );
+    r.add(
+        CommandSpec::new("semantic_search", "Search with an AI-interpreted prompt")
+            .param("prompt", ParamKind::Text),
+        |cx| cx.ipc.handle_semantic_search(cx.payload),
+    );
+    r.add(
+        CommandSpec::new("search_query", "Filtered, paged index search with snippets")
+            .param("query", ParamKind::Json),
+        |cx| cx.ipc.handle_search_query(cx.payload),
+    );
+    r.add(
+        CommandSpec::new(
+            "search_purge_sector",
+            "Drop a sector's commands, output and AI exchanges from the index",
+        )
+        .param("sector", ParamKind::Text),
+        |cx| cx.ipc.handle_search_purge_sector(cx.payload),
+    );
+    r.add(
+        CommandSpec::new("search_roots", "Directories indexed by the search daemon")
+            .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_search_roots(),
+    );
+    r.add(
+        CommandSpec::new("search_root_add", "Index another directory")
+            .param("path", ParamKind::Text)
+            .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_search_root(cx.payload, true),
+    );
+    r.add(
+        CommandSpec::new("search_root_remove", "Stop indexing a directory")
+            .param("path", ParamKind::Text)
+            .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_search_root(cx.payload, false),
+    );
+    r.add(
+        CommandSpec::new(
+            "prompt_submit",
+            "Run a command in the active hub, subject to trust checks",
+        )
+        .param("command", ParamKind::Text),
+        |cx| cx.ipc.handle_prompt_submit(cx.payload, false),
+    );
+    r.add(
+        CommandSpec::new(
+            "force_prompt_submit",
+            "Run a command without trust confirmation",
+        )
+        .param("command", ParamKind::Text)
+        .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_prompt_submit(cx.payload, true),
+    );
+    r.add(
+        CommandSpec::new("terminal_resize", "Resize a hub's terminal")
+            .param("rows", ParamKind::Integer)
+            .param("cols", ParamKind::Integer)
+            .optional("hub_id", ParamKind::Uuid),
+        |cx| {
+            cx.ipc
+                .handle_terminal_resize(cx.arg(0), cx.arg(1), cx.arg(2))
+        },
+    );
+    r.add(
+        CommandSpec::new("terminal_signal", "Send a signal to a hub's foreground job")
+            .param("signal", ParamKind::Text)
+            .optional("hub_id", ParamKind::Uuid),
+        |cx| cx.ipc.handle_terminal_signal(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        // Raw input bypasses the trust gate of prompt_submit, like
+        // force_prompt_submit.
+        CommandSpec::new("terminal_input", "Write raw input to a hub's shell")
+            .param("data", ParamKind::Text)
+            .optional("hub_id", ParamKind::Uuid)
+            .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_terminal_input(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("terminal_screen", "Rendered terminal screen as JSON")
+            .optional("hub_id", ParamKind::Uuid)
+            .optional("offset", ParamKind::Integer)
+            .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_terminal_screen(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("command_blocks", "Command blocks of a hub as JSON")
+            .optional("hub_id", ParamKind::Uuid)
+            .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_command_blocks(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("command_block_output", "Output of one command block")
+            .param("selector", ParamKind::Text)
+            .optional("hub_id", ParamKind::Uuid)
+            .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_command_block_output(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("command_block_rerun", "Run a command block again")
+            .param("selector", ParamKind::Text)
+            .optional("hub_id", ParamKind::Uuid),
+        |cx| cx.ipc.handle_command_block_rerun(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new(
+            "command_block_to_ai",
+            "Attach a command block to the AI history",
+        )
+        .param("selector", ParamKind::Text)
+        .optional("hub_id", ParamKind::Uuid),
+        |cx| cx.ipc.handle_command_block_to_ai(cx.arg(0), cx.arg(1)),
+    );
+    // Suggestions replay learned command history, so Viewers get none.
+    r.add(
+        CommandSpec::new("heuristic_query", "Heuristic suggestions for a keyword")
+            .param("keyword", ParamKind::Text),
+        |cx| cx.ipc.handle_heuristic_query(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "heuristic_model_export",
+            "Export the learned command model as JSON, or write it to a file",
+        )
+        .optional("path", ParamKind::Text)
+        .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_heuristic_model_export(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "heuristic_history_import",
+            "Import bash, zsh or fish history into the command model",
+        )
+        .optional("source", ParamKind::Text)
+        .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_heuristic_history_import(cx.arg(0)),
+    );
+}
+
+impl IpcHandler {
+    pub(super) fn handle_prompt_submit(&self, command: &str, force: bool) -> String {
+        let cmd = command.trim();
+        if cmd.is_empty() {
+            return "ERROR: Empty command".to_string();
+        }
+
+        // §6.9: Intercept built-in commands from the prompt
+        let builtins = ["session_handoff_prepare", "tos_ports", "system_reset"];
+        if builtins.contains(&cmd) {
+            let result = self.handle_request(cmd);
+            let mut state = self.state.lock().unwrap();
+            state.system_log.push(crate::TerminalLine {
+                text: format!("[SYSTEM] {} -> {}", cmd, result),
+                priority: 2,
+                timestamp: chrono::Local::now(),
+                source: None,
+            });
+            state.version += 1;
+            return result;
+        }
+
+        // Non-blocking Trust Chip Emission
+        // Classify the command and push a warning chip to system_log if needed.
+        // This does NOT block or delay PTY submission.
+        if !force {
+            let mut state = self.state.lock().unwrap();
+            let idx = state.active_sector_index;
+            let sector_id_str = state.sectors.get(idx).map(|s| s.id.to_string());
+            let cwd = state
+                .sectors
+                .get(idx)
+                .and_then(|s| s.hubs.get(s.active_hub_index))
+                .map(|h| h.current_directory.clone())
+                .unwrap_or_else(|| std::path::PathBuf::from("/"));
+
+            let bulk_threshold = state
+                .settings
+                .global
+                .get("tos.trust.bulk_threshold")
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(10);
+
+            let class = self
+                .services
+                .trust
+                .classify_command(cmd, &cwd, bulk_threshold);
+            let policy =
+                self.services
+                    .trust
+                    .get_trust_policy(&state, sector_id_str.as_deref(), &class);
+
+            if class != crate::services::trust::CommandClass::Standard {
+                let chip_msg = match &class {
+                    crate::services::trust::CommandClass::PrivilegeEscalation => format!(
+                        "[TRUST] ⚠ PRIVILEGE ESCALATION: '{}' — policy: {}",
+                        cmd, policy
+                    ),
+                    crate::services::trust::CommandClass::RecursiveBulk => format!(
+                        "[TRUST] ⚠ RECURSIVE BULK OP: '{}' — policy: {}",
+                        cmd, policy
+                    ),
+                    crate::services::trust::CommandClass::ImplicitBulk => format!(
+                        "[TRUST] ⚠ IMPLICIT BULK (glob): '{}' — policy: {}",
+                        cmd, policy
+                    ),
+                    _ => String::new(),
+                };
+                if !chip_msg.is_empty() {
+                    state.system_log.push(crate::TerminalLine {
+                        text: chip_msg,
+                        priority: 2,
+                        timestamp: chrono::Local::now(),
+                        source: None,
+                    });
+                    tracing::warn!(
+                        "[TRUST] Classified '{}' as {:?} (policy={})",
+                        cmd,
+                        class,
+                        policy
+                    );
+                }
+
+                if policy == "block" {
+                    return format!("TRUST_BLOCKED: {:?}", class);
+                }
+                if policy == "confirm" {
+                    state.pending_confirmation = Some(crate::ConfirmationRequest {
+                        id: Uuid::new_v4(),
+                        sector_id: state.sectors.get(idx).map(|s| s.id),
+                        original_request: format!("force_prompt_submit:{}", cmd),
+                        message: format!("⚠ DANGEROUS COMMAND: {}", cmd),
+                        progress: 0.0,
+                    });
+                    state.version += 1;
+                    return "CONFIRMATION_REQUIRED".to_string();
+                }
+            }
+        }
+
+        let mut hub_mode = CommandHubMode::Command;
+        let mut sector_id = Uuid::nil();
+        let mut hub_id = Uuid::nil();
+        {
+            let mut state_lock = self.state.lock().unwrap();
+            let idx = state_lock.active_sector_index;
+            if let Some(sector) = state_lock.sectors.get_mut(idx) {
+                sector_id = sector.id;
+                let hub_idx = sector.active_hub_index;
+                if let Some(hub) = sector.hubs.get_mut(hub_idx) {
+                    hub_mode = hub.mode;
+                    hub_id = hub.id;
+
+                    // §7.3: Auto Activity Mode detection on monitoring commands.
+                    let first_token = cmd.split_whitespace().next().unwrap_or("");
+                    let activity_commands = ["top", "htop", "btop", "ps", "atop", "glances"];
+                    if hub_mode == CommandHubMode::Command
+                        && activity_commands.contains(&first_token)
+                    {
+                        hub.mode = CommandHubMode::Activity;
+                        tracing::info!(
+                            "Auto-switched to Activity mode for command: {}",
+                            first_token
+                        );
+                    }
+
+                    // §26.4: Open the block now so shells without OSC 133
+                    // integration still get one; a later `C` mark adopts it.
+                    hub.begin_command_block(Some(cmd), false);
+                    hub.is_running = true;
+                    hub.last_exit_status = None;
+                    hub.json_context = None;
+                    state_lock.version += 1;
+                }
+            }
+        }
+
+        if hub_mode == CommandHubMode::Ssh {
+            if let Err(e) = self.services.ssh.write(&hub_id, &format!("{}\n", command)) {
+                return format!("ERROR: SSH write failed: {}", e);
+            }
+            return "SSH_SUBMITTED".to_string();
+        }
+
+        // §15.2: Route the command to the shell owned by the submitting hub.
+        let write_result = {
+            let mut shells = self.shells.lock().unwrap();
+            shells
+                .get_or_spawn(sector_id, hub_id)
+                .and_then(|shell| shell.write(&format!("{}\n", command)))
+        };
+        if let Err(e) = write_result {
+            let msg = format!("ERROR: Failed to write to shell: {}", e);
+            tracing::error!("{}", msg);
+            // Revert is_running
+            let mut state_lock = self.state.lock().unwrap();
+            if let Some(hub) = state_lock
+                .sectors
+                .iter_mut()
+                .flat_map(|s| s.hubs.iter_mut())
+                .find(|h| h.id == hub_id)
+            {
+                hub.is_running = false;
+                state_lock.version += 1;
+            }
+            return msg;
+        }
+
+        "SUBMITTED".to_string()
+    }
+
+    /// SEARCH mode: index hits ranked by keyword and semantic similarity
+    /// together (§4.1), then terminal output matches.
+    pub(super) fn handle_search(&self, query: &str) -> String {
+        // Add globally indexed file matches
+        let hits = self
+            .services
+            .search
+            .hybrid_query(&crate::services::search::SearchQuery::text(query));
+        let indexed = vec![crate::SearchResult::from_hits("Global FS Index", hits)];
+
+        let mut state = self.state.lock().unwrap();
+        crate::brain::sector::SectorManager::perform_search(&mut state, query, None, indexed);
+
+        format!("SEARCH_PERFORMED: {}", query)
+    }
+
+    pub(super) fn handle_semantic_search(&self, prompt: &str) -> String {
+        let hits = self
+            .services
+            .search
+            .semantic_query(&crate::services::search::SearchQuery::text(prompt));
+        let indexed = vec![crate::SearchResult::from_hits("AI Semantic Engine", hits)];
+
+        let mut state = self.state.lock().unwrap();
+        crate::brain::sector::SectorManager::perform_search(&mut state, prompt, None, indexed);
+
+        "SEMANTIC_SEARCH_COMPLETED".to_string()
+    }
+
+    /// §4.1: A filtered, paged hybrid query against the index. `sector` is
+    /// resolved to the working directories of that sector's hubs and to
+    /// the records it produced. Results
+    /// go to the active hub as for `search`; the hits are returned as JSON.
+    pub(super) fn handle_search_query(&self, payload: &str) -> String {
+        let mut query: crate::services::search::SearchQuery = match serde_json::from_str(payload) {
+            Ok(q) => q,
+            Err(e) => return format!("ERROR: Invalid search query: {}", e),
+        };
+
+        let sector = match query.sector.as_deref() {
+            None => None,
+            Some(id) => {
+                let state = self.state.lock().unwrap();
+                let found = state
+                    .sectors
+                    .iter()
+                    .find(|s| s.id.to_string() == id || s.name == id);
+                match found {
+                    Some(s) => {
+                        for hub in &s.hubs {
+                            let dir = hub.current_directory.to_string_lossy().to_string();
+                            if !query.filters.roots.contains(&dir) {
+                                query.filters.roots.push(dir);
+                            }
+                        }
+                        if !query.filters.sectors.contains(&s.id) {
+                            query.filters.sectors.push(s.id);
+                        }
+                        Some(s.id)
+                    }
+                    None => return format!("ERROR: Sector not found: {}", id),
+                }
+            }
+        };
+
+        let hits = self.services.search.hybrid_query(&query);
+        let reply = serde_json::to_string(&hits).unwrap_or_else(|_| "[]".to_string());
+        let indexed = vec![crate::SearchResult::from_hits("Global FS Index", hits)];
+
+        let mut state = self.state.lock().unwrap();
+        crate::brain::sector::SectorManager::perform_search(
+            &mut state,
+            &query.text,
+            sector,
+            indexed,
+        );
+
+        reply
+    }
+
+    /// §4.1: Forget everything a sector fed the index. Open sectors are
+    /// named by id or name; closed ones by id.
+    pub(super) fn handle_search_purge_sector(&self, sector: &str) -> String {
+        let (id, hubs) = {
+            let state = self.state.lock().unwrap();
+            let open = state
+                .sectors
+                .iter()
+                .find(|s| s.id.to_string() == sector || s.name == sector);
+            match (open, uuid::Uuid::parse_str(sector)) {
+                (Some(s), _) => (s.id, s.hubs.clone()),
+                (None, Ok(id)) => (id, Vec::new()),
+                (None, Err(_)) => return format!("ERROR: Sector not found: {}", sector),
+            }
+        };
+        match self.services.search.purge_sector(id, &hubs) {
+            Ok(reply) => reply.trim().to_string(),
+            Err(e) => format!("ERROR: Search daemon unavailable: {}", e),
+        }
+    }
+
+    /// §4.1: The daemon's indexed roots as a JSON list.
+    pub(super) fn handle_search_roots(&self) -> String {
+        match self.services.search.roots() {
+            Ok(roots) => serde_json::to_string(&roots).unwrap_or_else(|_| "[]".to_string()),
+            Err(e) => format!("ERROR: Search daemon unavailable: {}", e),
+        }
+    }
+
+    /// §4.1: Add or remove an indexed root; the daemon's reply is passed on.
+    pub(super) fn handle_search_root(&self, path: &str, add: bool) -> String {
+        if path.trim().is_empty() {
+            return "ERROR: Missing path".to_string();
+        }
+        let path = path.trim();
+        let reply = if add {
+            // Beyond the configured roots and home, the daemon only takes a
+            // hub's own working directory.
+            let is_sector_dir = {
+                let state = self.state.lock().unwrap();
+                state
+                    .sectors
+                    .iter()
+                    .flat_map(|s| &s.hubs)
+                    .any(|h| h.current_directory == std::path::Path::new(path))
+            };
+            self.services.search.add_root(path, is_sector_dir)
+        } else {
+            self.services.search.remove_root(path)
+        };
+        match reply {
+            Ok(reply) => reply.trim().to_string(),
+            Err(e) => format!("ERROR: Search daemon unavailable: {}", e),
+        }
+    }
+
+    pub(super) fn handle_heuristic_query(&self, keyword: Option<&str>) -> String {
+        let keyword = keyword.unwrap_or("").to_string();
+        let (cwd, sector_id) = {
+            let state = self.state.lock().unwrap();
+            let sector = state.sectors.get(state.active_sector_index);
+            let cwd = sector
+                .and_then(|s| s.hubs.get(s.active_hub_index))
+                .map(|h| h.current_directory.display().to_string())
+                .unwrap_or_else(|| "/".to_string());
+            (cwd, sector.map(|s| s.id))
+        };
+
+        let svc = self.services.heuristic.clone();
+
+        // Use block_in_place to prevent "Cannot start a runtime from within a runtime" panic
+        tokio::task::block_in_place(move || {
+            tokio::runtime::Handle::current().block_on(async move {
+                match svc.query(&keyword, &cwd, sector_id).await {
+                    Ok(json) => json,
+                    Err(e) => format!("ERROR: Heuristic query failed: {}", e),
+                }
+            })
+        })
+    }
+
+    pub(super) fn handle_heuristic_model_export(&self, path: Option<&str>) -> String {
+        let path = path.unwrap_or("").to_string();
+        let svc = self.services.heuristic.clone();
+        tokio::task::block_in_place(move || {
+            tokio::runtime::Handle::current().block_on(async move {
+                match svc.export_model(&path).await {
+                    Ok(reply) => reply,
+                    Err(e) => format!("ERROR: Heuristic service unavailable: {}", e),
+                }
+            })
+        })
+    }
+
+    pub(super) fn handle_heuristic_history_import(&self, source: Option<&str>) -> String {
+        {
+            let state = self.state.lock().unwrap();
+            if let Some(reason) = HeuristicService::import_blocked(&state) {
+                return format!("ERROR: History import refused: {}", reason);
+            }
+        }
+        let source = source.unwrap_or("").trim().to_string();
+        let svc = self.services.heuristic.clone();
+        tokio::task::block_in_place(move || {
+            tokio::runtime::Handle::current().block_on(async move {
+                match svc.import_history(&source).await {
+                    Ok(reply) => reply,
+                    Err(e) => format!("ERROR: Heuristic service unavailable: {}", e),
+                }
+            })
+        })
+    }
+
+    pub(super) fn handle_terminal_signal(
+        &self,
+        signal: Option<&str>,
+        hub_id: Option<&str>,
+    ) -> String {
+        if let Some(sig) = signal {
+            let (sector_id, hub_id) = match self.resolve_hub(hub_id) {
+                Some(ids) => ids,
+                None => return "ERROR: Unknown hub".to_string(),
+            };
+            let mut shells = self.shells.lock().unwrap();
+            match shells
+                .get_or_spawn(sector_id, hub_id)
+                .and_then(|shell| shell.send_signal(sig))
+            {
+                Ok(_) => return format!("SIGNAL_SENT: {}", sig),
+                Err(e) => return format!("ERROR: Signal failed: {}", e),
+            }
+        }
+        "ERROR: Missing signal ID".to_string()
+    }
+
+    pub(super) fn handle_terminal_resize(
+        &self,
+        rows: Option<&str>,
+        cols: Option<&str>,
+        hub_id: Option<&str>,
+    ) -> String {
+        if let (Some(r), Some(c)) = (rows, cols) {
+            if let (Ok(r_n), Ok(c_n)) = (r.parse::<u16>(), c.parse::<u16>()) {
+                let (sector_id, hub_id) = match self.resolve_hub(hub_id) {
+                    Some(ids) => ids,
+                    None => return "ERROR: Unknown hub".to_string(),
+                };
+                let mut shells = self.shells.lock().unwrap();
+                match shells
+                    .get_or_spawn(sector_id, hub_id)
+                    .and_then(|shell| shell.resize(r_n, c_n))
+                {
+                    Ok(_) => return format!("TERMINAL_RESIZED: {}x{}", r_n, c_n),
+                    Err(e) => return format!("ERROR: Resize failed: {}", e),
+                }
+            }
+        }
+        "ERROR: Invalid dimensions".to_string()
+    }
+
+    /// §26.3: Raw keystrokes for interactive programs, base64-encoded so that
+    /// escape sequences and `;` survive the wire format.
+    pub(super) fn handle_terminal_input(
+        &self,
+        data_b64: Option<&str>,
+        hub_id: Option<&str>,
+    ) -> String {
+        use base64::Engine;
+        let bytes =
+            match data_b64.map(|d| base64::engine::general_purpose::STANDARD.decode(d.trim())) {
+                Some(Ok(b)) => b,
+                _ => return "ERROR: Invalid terminal input payload".to_string(),
+            };
+        let (sector_id, hub_id) = match self.resolve_hub(hub_id) {
+            Some(ids) => ids,
+            None => return "ERROR: Unknown hub".to_string(),
+        };
+        let mut shells = self.shells.lock().unwrap();
+        match shells
+            .get_or_spawn(sector_id, hub_id)
+            .and_then(|shell| shell.write_bytes(&bytes))
+        {
+            Ok(_) => "INPUT_SENT".to_string(),
+            Err(e) => format!("ERROR: Failed to write to shell: {}", e),
+        }
+    }
+
+    /// §26.3: Styled screen grid for a hub, optionally scrolled back.
+    pub(super) fn handle_terminal_screen(
+        &self,
+        hub_id: Option<&str>,
+        offset: Option<&str>,
+    ) -> String {
+        let offset = offset.and_then(|o| o.parse::<usize>().ok()).unwrap_or(0);
+        let (sector_id, hub_id) = match self.resolve_hub(hub_id) {
+            Some(ids) => ids,
+            None => return "ERROR: Unknown hub".to_string(),
+        };
+        let mut shells = self.shells.lock().unwrap();
+        match shells.get_or_spawn(sector_id, hub_id) {
+            Ok(shell) => serde_json::to_string(&shell.screen(offset))
+                .unwrap_or_else(|_| "ERROR: Serialization failed".to_string()),
+            Err(e) => format!("ERROR: {}", e),
+        }
+    }
+
+    /// §26.4: Command blocks recorded for a hub, oldest first.
+    pub(super) fn handle_command_blocks(&self, hub_id: Option<&str>) -> String {
+        let (sector_id, hub_id) = match self.resolve_hub(hub_id) {
+            Some(ids) => ids,
+            None => return "ERROR: Unknown hub".to_string(),
+        };
+        let mut state = self.state.lock().unwrap();
+        match find_hub_mut(&mut state, sector_id, hub_id) {
+            Some(hub) => {
+                serde_json::to_string(&hub.command_blocks).unwrap_or_else(|_| "[]".to_string())
+            }
+            None => "ERROR: Unknown hub".to_string(),
+        }
+    }
+
+    /// §26.4: One block and its output. The selector is a block id, `last`
+    /// or `last_failed`.
+    pub(super) fn handle_command_block_output(
+        &self,
+        selector: Option<&str>,
+        hub_id: Option<&str>,
+    ) -> String {
+        let selector = selector.filter(|s| !s.is_empty()).unwrap_or("last");
+        let (sector_id, hub_id) = match self.resolve_hub(hub_id) {
+            Some(ids) => ids,
+            None => return "ERROR: Unknown hub".to_string(),
+        };
+        let mut state = self.state.lock().unwrap();
+        let hub = match find_hub_mut(&mut state, sector_id, hub_id) {
+            Some(h) => h,
+            None => return "ERROR: Unknown hub".to_string(),
+        };
+        let block = match hub.find_command_block(selector) {
+            Some(b) => b,
+            None => return format!("ERROR: No command block '{}'", selector),
+        };
+        let (lines, truncated) = hub.command_block_output(block);
+        serde_json::json!({
+            "block": block,
+            "lines": lines.iter().map(|l| l.text.as_str()).collect::<Vec<_>>(),
+            "truncated": truncated,
+        })
+        .to_string()
+    }
+
+    /// §26.4: Submit a block's command again on the active hub.
+    pub(super) fn handle_command_block_rerun(
+        &self,
+        selector: Option<&str>,
+        hub_id: Option<&str>,
+    ) -> String {
+        let selector = selector.filter(|s| !s.is_empty()).unwrap_or("last");
+        let (sector_id, hub_id) = match self.resolve_hub(hub_id) {
+            Some(ids) => ids,
+            None => return "ERROR: Unknown hub".to_string(),
+        };
+        let command = {
+            let mut state = self.state.lock().unwrap();
+            let active_hub = state
+                .sectors
+                .get(state.active_sector_index)
+                .and_then(|s| s.hubs.get(s.active_hub_index))
+                .map(|h| h.id);
+            if active_hub != Some(hub_id) {
+                return "ERROR: Command blocks can only be re-run on the active hub".to_string();
+            }
+            let hub = match find_hub_mut(&mut state, sector_id, hub_id) {
+                Some(h) => h,
+                None => return "ERROR: Unknown hub".to_string(),
+            };
+            match hub.find_command_block(selector) {
+                Some(b) if !b.command.is_empty() => b.command.clone(),
+                Some(_) => return "ERROR: Command block has no command text".to_string(),
+                None => return format!("ERROR: No command block '{}'", selector),
+            }
+        };
+        // Goes through the normal trust classification.
+        self.handle_prompt_submit(&command, false)
+    }
+
+    /// §26.4: Attach a block's command, exit status and output to the hub's
+    /// AI history so the next AI request sees it.
+    pub(super) fn handle_command_block_to_ai(
+        &self,
+        selector: Option<&str>,
+        hub_id: Option<&str>,
+    ) -> String {
+        let selector = selector.filter(|s| !s.is_empty()).unwrap_or("last");
+        let (sector_id, hub_id) = match self.resolve_hub(hub_id) {
+            Some(ids) => ids,
+            None => return "ERROR: Unknown hub".to_string(),
+        };
+        let mut state = self.state.lock().unwrap();
+        let hub = match find_hub_mut(&mut state, sector_id, hub_id) {
+            Some(h) => h,
+            None => return "ERROR: Unknown hub".to_string(),
+        };
+        let block = match hub.find_command_block(selector) {
+            Some(b) => b,
+            None => return format!("ERROR: No command block '{}'", selector),
+        };
+        let (lines, truncated) = hub.command_block_output(block);
+        let status = block
+            .exit_status
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let mut content = format!(
+            "$ {}\n[cwd: {}] [exit: {}]\n",
+            block.command,
+            block.cwd.display(),
+            status
+        );
+        if truncated {
+            content.push_str("[earlier output trimmed]\n");
+        }
+        for line in lines {
+            content.push_str(&line.text);
+            content.push('\n');
+        }
+        let block_id = block.id;
+
+        hub.ai_history.push(crate::AiMessage {
+            role: "context".to_string(),
+            content,
+            timestamp: chrono::Local::now(),
+        });
+        if hub.ai_history.len() > 200 {
+            hub.ai_history.remove(0);
+        }
+        hub.version += 1;
+        state.version += 1;
+        format!("BLOCK_COPIED: {}", block_id)
+    }
+}
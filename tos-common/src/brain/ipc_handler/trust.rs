@@ -0,0 +1,225 @@
+//! Command trust and confirmation commands (§17.2).
+
+use super::*;
+use crate::brain::command_registry::{CommandRegistry, CommandSpec};
+use crate::collaboration::ParticipantRole;
+use crate::rpc::ParamKind;
+
+pub(super) fn register(r: &mut CommandRegistry) {
+    r.add(
+        CommandSpec::new(
+            "trust_promote",
+            "Allow a command class without confirmation",
+        )
+        .param("class_key", ParamKind::Text)
+        .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_trust_promote(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("trust_demote", "Require confirmation for a command class")
+            .param("class_key", ParamKind::Text)
+            .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_trust_demote(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "trust_promote_sector",
+            "Allow a command class without confirmation in one sector",
+        )
+        .param("sector_id", ParamKind::Text)
+        .param("class_key", ParamKind::Text)
+        .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_trust_promote_sector(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new(
+            "trust_demote_sector",
+            "Require confirmation for a command class in one sector",
+        )
+        .param("sector_id", ParamKind::Text)
+        .param("class_key", ParamKind::Text)
+        .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_trust_demote_sector(cx.arg(0), cx.arg(1)),
+    );
+    r.add(
+        CommandSpec::new("trust_clear_sector", "Drop a sector's trust overrides")
+            .param("sector_id", ParamKind::Text)
+            .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_trust_clear_sector(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("trust_get_config", "Trust configuration as JSON")
+            .role(ParticipantRole::Viewer),
+        |cx| cx.ipc.handle_trust_get_config(),
+    );
+    r.add(
+        CommandSpec::new(
+            "confirmation_accept",
+            "Accept a pending confirmation and run its command",
+        )
+        .param("confirmation_id", ParamKind::Uuid)
+        .role(ParticipantRole::CoOwner),
+        |cx| cx.ipc.handle_confirmation_accept(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new("confirmation_reject", "Reject a pending confirmation")
+            .param("confirmation_id", ParamKind::Uuid),
+        |cx| cx.ipc.handle_confirmation_reject(cx.arg(0)),
+    );
+    r.add(
+        CommandSpec::new(
+            "update_confirmation_progress",
+            "Report hold-to-confirm progress",
+        )
+        .param("confirmation_id", ParamKind::Uuid)
+        .param("progress", ParamKind::Number),
+        |cx| {
+            cx.ipc
+                .handle_update_confirmation_progress(cx.arg(0), cx.arg(1))
+        },
+    );
+}
+
+impl IpcHandler {
+    pub(super) fn handle_trust_promote(&self, class_key: Option<&str>) -> String {
+        if let Some(key) = class_key {
+            let mut state = self.state.lock().unwrap();
+            self.services.trust.promote_global(&mut state, key);
+            format!("TRUST_PROMOTED: {}", key)
+        } else {
+            "ERROR: Missing class_key".to_string()
+        }
+    }
+
+    pub(super) fn handle_trust_demote(&self, class_key: Option<&str>) -> String {
+        if let Some(key) = class_key {
+            let mut state = self.state.lock().unwrap();
+            self.services.trust.demote_global(&mut state, key);
+            format!("TRUST_DEMOTED: {}", key)
+        } else {
+            "ERROR: Missing class_key".to_string()
+        }
+    }
+
+    pub(super) fn handle_trust_promote_sector(
+        &self,
+        sector_id: Option<&str>,
+        class_key: Option<&str>,
+    ) -> String {
+        if let (Some(sid), Some(key)) = (sector_id, class_key) {
+            let mut state = self.state.lock().unwrap();
+            self.services.trust.promote_sector(&mut state, sid, key);
+            format!("TRUST_SECTOR_PROMOTED: {}:{}", sid, key)
+        } else {
+            "ERROR: Missing sector_id or class_key".to_string()
+        }
+    }
+
+    pub(super) fn handle_trust_demote_sector(
+        &self,
+        sector_id: Option<&str>,
+        class_key: Option<&str>,
+    ) -> String {
+        if let (Some(sid), Some(key)) = (sector_id, class_key) {
+            let mut state = self.state.lock().unwrap();
+            self.services.trust.demote_sector(&mut state, sid, key);
+            format!("TRUST_SECTOR_DEMOTED: {}:{}", sid, key)
+        } else {
+            "ERROR: Missing sector_id or class_key".to_string()
+        }
+    }
+
+    pub(super) fn handle_trust_clear_sector(&self, sector_id: Option<&str>) -> String {
+        if let Some(sid) = sector_id {
+            let mut state = self.state.lock().unwrap();
+            self.services.trust.clear_sector(&mut state, sid);
+            format!("TRUST_SECTOR_CLEARED: {}", sid)
+        } else {
+            "ERROR: Missing sector_id".to_string()
+        }
+    }
+
+    pub(super) fn handle_trust_get_config(&self) -> String {
+        let state = self.state.lock().unwrap();
+        format!(
+            "TRUST_CONFIG: {}",
+            self.services.trust.get_config_json(&state)
+        )
+    }
+
+    pub(super) fn handle_confirmation_accept(&self, id_str: Option<&str>) -> String {
+        if let Some(id_str) = id_str {
+            if let Ok(id) = Uuid::parse_str(id_str) {
+                let mut state = self.state.lock().unwrap();
+                if let Some(conf) = state.pending_confirmation.take() {
+                    if conf.id == id {
+                        let original = conf.original_request.clone();
+                        drop(state);
+                        return self.handle_request(&original);
+                    }
+                }
+            }
+        }
+        "ERROR: Invalid confirmation ID".to_string()
+    }
+
+    pub(super) fn handle_confirmation_reject(&self, id_str: Option<&str>) -> String {
+        if let Some(id_str) = id_str {
+            if let Ok(id) = Uuid::parse_str(id_str) {
+                let mut state = self.state.lock().unwrap();
+                if let Some(conf) = state.pending_confirmation.take() {
+                    if conf.id == id {
+                        state.version += 1;
+                        return "CONFIRMATION_REJECTED".to_string();
+                    }
+                }
+            }
+        }
+        "ERROR: Invalid confirmation ID".to_string()
+    }
+
+    pub(super) fn handle_update_confirmation_progress(
+        &self,
+        id_str: Option<&str>,
+        progress_str: Option<&str>,
+    ) -> String {
+        if let (Some(id_str), Some(p_str)) = (id_str, progress_str) {
+            if let (Ok(id), Ok(p)) = (Uuid::parse_str(id_str), p_str.parse::<f32>()) {
+                let mut state = self.state.lock().unwrap();
+                let mut should_execute = false;
+                let mut original_request = String::new();
+
+                if let Some(ref mut conf) = state.pending_confirmation {
+                    if conf.id == id {
+                        conf.progress = p;
+                        if p >= 1.0 {
+                            should_execute = true;
+                            original_request = conf.original_request.clone();
+                        }
+                    }
+                }
+
+                if should_execute {
+                    state.pending_confirmation = None;
+                    state.version += 1;
+                    drop(state);
+                    return self.handle_request(&original_request);
+                } else if !original_request.is_empty() { // Wait, if not executing but found id
+                     // This branch is redundant because original_request is only non-empty if p >= 1.0
+                }
+
+                // If we updated progress but didn't execute
+                if state
+                    .pending_confirmation
+                    .as_ref()
+                    .map(|c| c.id == id)
+                    .unwrap_or(false)
+                {
+                    state.version += 1;
+                    return format!("CONFIRMATION_PROGRESS: {}", p);
+                }
+            }
+        }
+        "ERROR: Invalid confirmation update".to_string()
+    }
+}
@@ -1,3 +1,4 @@
+pub mod command_registry;
 pub mod cortex_registry;
 pub mod hierarchy;
 pub mod ipc_handler;
@@ -5,10 +6,11 @@ pub mod module_manager;
 pub mod renderer_manager;
 pub mod sector;
 pub mod shell;
+pub mod state_delta;
 
 use self::ipc_handler::IpcHandler;
 use self::module_manager::ModuleManager;
-use self::shell::ShellApi;
+use self::shell::ShellPool;
 use crate::TosState;
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -17,7 +19,7 @@ use std::process::Command;
 pub struct Brain {
     pub state: Arc<Mutex<TosState>>,
     pub ipc: Arc<IpcHandler>,
-    pub shell: Arc<Mutex<ShellApi>>,
+    pub shells: Arc<Mutex<ShellPool>>,
     pub services: Arc<crate::services::ServiceManager>,
     pub modules: Arc<ModuleManager>,
     pub cortex: Arc<Mutex<crate::brain::cortex_registry::CortexRegistry>>,
@@ -61,23 +63,30 @@ impl Brain {
         services.bezel.set_module_manager(modules.clone());
         services.audio.set_module_manager(modules.clone());
 
-        let shell_obj = ShellApi::new(
+        // §15.2: One shell per Command Hub; the primary hub is spawned eagerly,
+        // the rest on creation or first use.
+        let mut shell_pool = ShellPool::new(
             state.clone(),
             modules.clone(),
             services.ai.clone(),
             services.heuristic.clone(),
-            sid,
-            hid,
-        )?;
-        let shell = Arc::new(Mutex::new(shell_obj));
+        );
+        shell_pool.set_event_bus(services.events.clone());
+        shell_pool.spawn(sid, hid)?;
+        let shells = Arc::new(Mutex::new(shell_pool));
         let ipc = Arc::new(IpcHandler::new(
             state.clone(),
-            shell.clone(),
+            shells.clone(),
             services.clone(),
         ));
 
         services.set_ipc(ipc.clone());
 
+        let module_commands = ipc.register_module_commands(&modules);
+        if module_commands > 0 {
+            tracing::info!("[IPC] Registered {} module commands", module_commands);
+        }
+
         let mut loaded_settings = None;
         match services.settings.load() {
             Ok(loaded) => {
@@ -117,33 +126,100 @@ impl Brain {
             services.logger.log("Session restored silently.", 1);
         }
 
+        // §19.2: One long-lived subscription to tos-loggerd feeds the system
+        // log and publishes every record on the bus as `log:<source>`.
+        if let Ok(rt) = tokio::runtime::Handle::try_current() {
+            let logger = services.logger.clone();
+            let events = services.events.clone();
+            let ipc_log = ipc.clone();
+            rt.spawn(async move {
+                logger
+                    .follow(serde_json::json!({}), move |record| {
+                        let append = serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "method": "system_log_record",
+                            "params": { "record": record },
+                        });
+                        ipc_log.handle_request(&append.to_string());
+                        if let Ok(payload) = serde_json::to_value(&record) {
+                            events.publish(format!("log:{}", record.source), payload);
+                        }
+                    })
+                    .await
+            });
+        }
+
         // Spawn the background logic thread for state heartbeats
         let state_clock = state.clone();
         let svc_clock = services.clone();
+        let shells_clock = shells.clone();
+        // The heartbeat is a plain thread; async work goes to the runtime
+        // the Brain was created in.
+        let rt_clock = tokio::runtime::Handle::try_current().ok();
         thread::spawn(move || {
             let mut tick = 0;
             let mut last_alert_level = 0;
+            // Unix time each sector last had focus.
+            let mut last_focus: std::collections::HashMap<uuid::Uuid, i64> =
+                std::collections::HashMap::new();
+            // Last match each priority rule fired for, per sector.
+            let mut rules_fired: std::collections::HashMap<(String, uuid::Uuid), String> =
+                std::collections::HashMap::new();
+            // Sectors a priority rule keeps out of alerts.
+            let mut silenced: std::collections::HashSet<uuid::Uuid> =
+                std::collections::HashSet::new();
             loop {
                 thread::sleep(std::time::Duration::from_secs(1));
                 tick += 1;
 
+                // Taken before the state lock: spawning shells locks the pool, then state.
+                let shell_pids = if tick % 5 == 0 {
+                    shells_clock.lock().map(|s| s.process_ids()).unwrap_or_default()
+                } else {
+                    std::collections::HashMap::new()
+                };
+                let mut priority_signals = Vec::new();
+                let mut rule_fires = Vec::new();
+
                 if let Ok(mut lock) = state_clock.lock() {
-                    lock.brain_time = chrono::Local::now().format("%H:%M:%S").to_string();
+                    let now = chrono::Local::now();
+                    lock.brain_time = now.format("%H:%M:%S").to_string();
                     lock.version += 1;
+                    svc_clock.events.publish_state(lock.version);
+
+                    if let Some(active) = lock.sectors.get(lock.active_sector_index) {
+                        last_focus.insert(active.id, now.timestamp());
+                    }
 
                     // Periodic Sector Maintenance
                     if tick % 5 == 0 {
-                        // Refresh tactical priorities (§21)
-                        let sector_ids: Vec<uuid::Uuid> =
-                            lock.sectors.iter().map(|s| s.id).collect();
-                        for sid in sector_ids {
-                            if let Ok(score) = svc_clock.priority.calculate_priority(sid) {
-                                if let Some(sector) = lock.sectors.iter_mut().find(|s| s.id == sid)
-                                {
-                                    sector.priority = score.rank;
+                        // Tactical priority signals (Visual Design §1.2), scored below
+                        use crate::services::priority_rules;
+                        let rules = priority_rules::compile_all(&priority_rules::load(&lock.settings));
+                        let since = now
+                            - chrono::Duration::seconds(crate::services::priority::SIGNAL_WINDOW_SECS);
+                        let mut matched = std::collections::HashSet::new();
+                        for s in &lock.sectors {
+                            let mut signals = crate::brain::sector::SectorManager::priority_signals(
+                                &lock,
+                                s,
+                                &shell_pids,
+                                last_focus.get(&s.id).copied(),
+                                now,
+                            );
+                            for (rule, m) in priority_rules::evaluate(&rules, s, Some(since)) {
+                                signals.rules.push(rule.hit(&m));
+                                let key = (rule.rule().id.clone(), s.id);
+                                if rules_fired.get(&key) != Some(&m.key) {
+                                    rules_fired.insert(key.clone(), m.key.clone());
+                                    rule_fires.push((rule.clone(), s.id, s.name.clone(), m));
                                 }
+                                matched.insert(key);
                             }
+                            priority_signals.push(signals);
                         }
+                        // A rule that stops matching fires again when it next matches.
+                        rules_fired.retain(|key, _| matched.contains(key));
                     }
 
                     // Refresh activity listing and process snapshots (1Hz)
@@ -153,7 +229,13 @@ impl Brain {
                     );
 
                     // Alert level adaptation (§23.2)
-                    let current_alert_level = lock.sectors.iter().map(|s| s.priority).max().unwrap_or(1);
+                    let current_alert_level = lock
+                        .sectors
+                        .iter()
+                        .filter(|s| !silenced.contains(&s.id))
+                        .map(|s| s.priority)
+                        .max()
+                        .unwrap_or(1);
                     let auto_alert_audio = lock.settings.global.get("tos.audio.auto_alert_adaptation").map(|s| s == "true").unwrap_or(true);
                     
                     if auto_alert_audio && current_alert_level != last_alert_level {
@@ -180,6 +262,44 @@ impl Brain {
                         }
                     }
 
+                    // Index the sectors' working directories (§4.1)
+                    if tick % 10 == 0 {
+                        if let Some(rt) = &rt_clock {
+                            let dirs: std::collections::BTreeSet<String> = lock
+                                .sectors
+                                .iter()
+                                .flat_map(|s| s.hubs.iter())
+                                .map(|h| h.current_directory.to_string_lossy().to_string())
+                                .filter(|d| !d.is_empty())
+                                .collect();
+                            let search = svc_clock.search.clone();
+                            rt.spawn(async move { search.sync_sector_roots(dirs).await });
+                        }
+                    }
+
+                    // Stream new commands, output, logs and AI exchanges to the index (§4.1)
+                    if tick % 5 == 0 {
+                        if let Some(rt) = &rt_clock {
+                            let records = svc_clock.search.collect_records(&lock);
+                            let search = svc_clock.search.clone();
+                            rt.spawn(async move { search.push_records(records).await });
+                        }
+                    }
+
+                    // Teach the command model what finished (§28)
+                    if tick % 5 == 0 {
+                        if let Some(rt) = &rt_clock {
+                            let entries = svc_clock.heuristic.collect_history(&lock);
+                            let heuristic = svc_clock.heuristic.clone();
+                            rt.spawn(async move { heuristic.push_history(entries).await });
+
+                            if svc_clock.heuristic.claim_first_import(&lock) {
+                                let heuristic = svc_clock.heuristic.clone();
+                                rt.spawn(async move { heuristic.first_run_import().await });
+                            }
+                        }
+                    }
+
                     // Update Bezel Components (§1.10)
                     svc_clock.bezel.update_state(&mut lock);
 
@@ -187,13 +307,64 @@ impl Brain {
                     svc_clock.timeline.record_snapshot(&lock);
                     lock.timeline_history_len = svc_clock.timeline.len();
                 }
+
+                // Rule earcons, haptics and notifications, once per new match.
+                for (rule, sector_id, sector_name, m) in rule_fires {
+                    rule.fire(&svc_clock, sector_id, &sector_name, &m);
+                }
+
+                // Score outside the state lock, as scoring may ask tos-priorityd.
+                if !priority_signals.is_empty() {
+                    let scores: Vec<_> = priority_signals
+                        .iter()
+                        .filter_map(|s| Some((s.sector_id, svc_clock.priority.calculate_priority(s).ok()?)))
+                        .collect();
+                    let mut changes = Vec::new();
+                    if let Ok(mut lock) = state_clock.lock() {
+                        silenced.clear();
+                        for (sid, score) in scores {
+                            if score.silenced {
+                                silenced.insert(sid);
+                            }
+                            if let Some(sector) = lock.sectors.iter_mut().find(|s| s.id == sid) {
+                                if sector.priority != score.rank {
+                                    changes.push((sid, sector.name.clone(), sector.priority, score.clone()));
+                                }
+                                sector.priority = score.rank;
+                                sector.priority_factors = score.breakdown;
+                            }
+                        }
+                    }
+                    // §19: Priority changes are logged with their factors.
+                    for (sid, name, from, score) in changes {
+                        let record = crate::services::logger::LogRecord {
+                            sector_id: Some(sid),
+                            ..crate::services::logger::LogRecord::new(
+                                crate::services::logger::Severity::Info,
+                                "brain",
+                                "priority",
+                                &format!(
+                                    "Sector '{}' priority {} -> {}: {}",
+                                    name,
+                                    from,
+                                    score.rank,
+                                    score.explain()
+                                ),
+                            )
+                        }
+                        .with_field("rank", score.rank)
+                        .with_field("score", score.total_score)
+                        .with_field("factors", serde_json::json!(score.factors));
+                        svc_clock.logger.record(record);
+                    }
+                }
             }
         });
 
         Ok(Self {
             state,
             ipc,
-            shell,
+            shells,
             services,
             modules,
             cortex,
@@ -59,6 +59,8 @@ impl ModuleManager {
                 lsp: None,
                 bezel: None,
                 audio: None,
+                commands: None,
+                completions: None,
                 signature: None,
             });
         }
@@ -101,6 +103,8 @@ impl ModuleManager {
                 lsp: None,
                 bezel: None,
                 audio: None,
+                commands: None,
+                completions: None,
                 signature: None,
             });
         }
@@ -134,6 +138,15 @@ impl ModuleManager {
         self.modules.values().collect()
     }
 
+    /// An absolute `path` as is, otherwise relative to the module's directory.
+    pub fn resolve_path(&self, id: &str, path: &str) -> PathBuf {
+        if Path::new(path).is_absolute() {
+            PathBuf::from(path)
+        } else {
+            self.base_path.join(id).join(path)
+        }
+    }
+
     /// Instantiates a ShellModule from a manifest.
     pub fn load_shell(&self, id: &str) -> anyhow::Result<Box<dyn ShellModule>> {
         let manifest = self
@@ -155,14 +168,7 @@ impl ModuleManager {
 
         // If the manifest specifies an absolute path (e.g. "/usr/bin/fish"),
         // use it directly. Otherwise resolve relative to the module directory.
-        let full_path = if Path::new(&exe.path).is_absolute() {
-            PathBuf::from(&exe.path)
-        } else {
-            let mut p = self.base_path.clone();
-            p.push(id);
-            p.push(&exe.path);
-            p
-        };
+        let full_path = self.resolve_path(id, &exe.path);
 
         Ok(Box::new(GenericShellModule {
             path: full_path,
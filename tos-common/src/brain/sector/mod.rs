@@ -43,6 +43,9 @@ impl SectorManager {
                 version: 0,
                 is_running: false,
                 last_exit_status: None,
+                screen: None,
+                command_blocks: vec![],
+                output_offset: 0,
             }],
             active_hub_index: 0,
             frozen: false,
@@ -50,6 +53,7 @@ impl SectorManager {
             disconnected: false,
             trust_tier: crate::TrustTier::System,
             priority: 1,
+            priority_factors: Vec::new(),
             active_apps: vec![],
             active_app_index: 0,
             participants: vec![],
@@ -98,6 +102,9 @@ impl SectorManager {
                 version: 0,
                 is_running: false,
                 last_exit_status: None,
+                screen: None,
+                command_blocks: vec![],
+                output_offset: 0,
             });
         }
 
@@ -117,6 +124,7 @@ impl SectorManager {
             disconnected: false,
             trust_tier: crate::TrustTier::Standard,
             priority: 1,
+            priority_factors: Vec::new(),
             active_apps: vec![],
             active_app_index: 0,
             participants: vec![],
@@ -215,6 +223,7 @@ impl SectorManager {
             // 2. Clear all hubs within the sector
             for hub in &mut sector.hubs {
                 hub.terminal_output.clear();
+                hub.command_blocks.clear();
                 hub.staged_command = None;
                 hub.ai_explanation = None;
                 hub.json_context = None;
@@ -274,6 +283,64 @@ impl SectorManager {
         }
     }
 
+    /// The signals a sector is scored from (Visual Design §1.2).
+    ///
+    /// `shell_pids` maps hub ids to shell PIDs; `last_focus` is the unix
+    /// time the sector last had focus.
+    pub fn priority_signals(
+        state: &TosState,
+        sector: &Sector,
+        shell_pids: &std::collections::HashMap<Uuid, u32>,
+        last_focus: Option<i64>,
+        now: chrono::DateTime<chrono::Local>,
+    ) -> crate::services::priority::SectorSignals {
+        use crate::collaboration::PresenceStatus;
+        let since = now - chrono::Duration::seconds(crate::services::priority::SIGNAL_WINDOW_SECS);
+        let focused = state.sectors.get(state.active_sector_index).map(|s| s.id) == Some(sector.id);
+
+        let mut signals = crate::services::priority::SectorSignals {
+            sector_id: sector.id,
+            name: sector.name.clone(),
+            pending_confirmation: state
+                .pending_confirmation
+                .as_ref()
+                .is_some_and(|c| c.sector_id == Some(sector.id)),
+            focused,
+            seconds_since_focus: last_focus.map(|ts| (now.timestamp() - ts).max(0)),
+            ..Default::default()
+        };
+        for hub in &sector.hubs {
+            if hub.last_exit_status.is_some_and(|s| s != 0) {
+                signals.failing_hubs += 1;
+            }
+            signals.recent_failures += hub
+                .command_blocks
+                .iter()
+                .filter(|b| b.exit_status.is_some_and(|s| s != 0))
+                .filter(|b| b.finished_at.is_some_and(|t| t >= since))
+                .count() as u32;
+            // §31.4: read_loop raises error lines to priority 3.
+            signals.error_lines += hub
+                .terminal_output
+                .iter()
+                .rev()
+                .take_while(|l| l.timestamp >= since)
+                .filter(|l| l.priority >= 3)
+                .count() as u32;
+            if let Some(pid) = shell_pids.get(&hub.id) {
+                signals.shell_pids.push(*pid);
+            }
+        }
+        for participant in &sector.participants {
+            match participant.status {
+                PresenceStatus::Active => signals.active_participants += 1,
+                PresenceStatus::Idle => signals.idle_participants += 1,
+                PresenceStatus::Offline => {}
+            }
+        }
+        signals
+    }
+
     /// Refresh activity listing for process monitoring hub modes.
     pub fn refresh_activity_listing(
         state: &mut TosState,
@@ -327,16 +394,31 @@ impl SectorManager {
     }
 
     /// Perform global search across all active sectors and their respective hubs.
-    pub fn perform_search(state: &mut TosState, query: &str) {
-        if query.is_empty() {
+    ///
+    /// `indexed` groups (hits from `tos-searchd`) are listed before the
+    /// terminal-output matches. With `sector`, only that sector's output is
+    /// searched.
+    pub fn perform_search(
+        state: &mut TosState,
+        query: &str,
+        sector: Option<Uuid>,
+        indexed: Vec<crate::SearchResult>,
+    ) {
+        if query.is_empty() && indexed.is_empty() {
             return;
         }
 
-        let mut results = Vec::new();
+        let mut results: Vec<crate::SearchResult> = indexed
+            .into_iter()
+            .filter(|r| !r.matches.is_empty())
+            .collect();
 
-        for sector in &state.sectors {
+        for s in &state.sectors {
+            if query.is_empty() || sector.is_some_and(|id| id != s.id) {
+                continue;
+            }
             let mut matches = Vec::new();
-            for hub in &sector.hubs {
+            for hub in &s.hubs {
                 for line in &hub.terminal_output {
                     if line.text.contains(query) {
                         matches.push(line.text.clone());
@@ -345,8 +427,9 @@ impl SectorManager {
             }
             if !matches.is_empty() {
                 results.push(crate::SearchResult {
-                    source_sector: sector.name.clone(),
+                    source_sector: s.name.clone(),
                     matches,
+                    hits: Vec::new(),
                 });
             }
         }
@@ -652,6 +735,9 @@ impl SectorManager {
                 version: 0,
                 is_running: false,
                 last_exit_status: None,
+                screen: None,
+                command_blocks: vec![],
+                output_offset: 0,
             }],
             active_hub_index: 0,
             frozen: false,
@@ -659,6 +745,7 @@ impl SectorManager {
             disconnected: false,
             trust_tier: crate::TrustTier::System,
             priority: 1,
+            priority_factors: Vec::new(),
             active_apps: vec![],
             active_app_index: 0,
             participants: vec![],
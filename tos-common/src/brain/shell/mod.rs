@@ -1,13 +1,13 @@
 #[cfg(not(target_os = "android"))]
 pub mod pty;
+pub mod screen;
 
 #[cfg(not(target_os = "android"))]
 pub use pty::PtyShell as ShellApi;
 
-#[cfg(target_os = "android")]
-use std::sync::{Arc, Mutex};
-#[cfg(target_os = "android")]
 use crate::TosState;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 #[cfg(target_os = "android")]
 pub struct ShellApi {
@@ -23,6 +23,7 @@ impl ShellApi {
         _modules: Arc<crate::brain::module_manager::ModuleManager>,
         _ai: Arc<crate::services::AiService>,
         _heuristic: Arc<crate::services::HeuristicService>,
+        _events: Arc<crate::services::EventBus>,
         sector_id: uuid::Uuid,
         hub_id: uuid::Uuid,
     ) -> anyhow::Result<Self> {
@@ -35,16 +36,174 @@ impl ShellApi {
     pub fn write(&mut self, _data: &str) -> anyhow::Result<()> {
         Ok(())
     }
+    pub fn write_bytes(&mut self, _data: &[u8]) -> anyhow::Result<()> {
+        Ok(())
+    }
     pub fn resize(&self, _rows: u16, _cols: u16) -> anyhow::Result<()> {
         Ok(())
     }
+    pub fn screen(&self, _scrollback_offset: usize) -> crate::TerminalScreen {
+        crate::TerminalScreen::default()
+    }
     pub fn send_signal(&mut self, _signal: &str) -> anyhow::Result<()> {
         Ok(())
     }
     pub fn force_kill(&mut self) -> anyhow::Result<()> {
         Ok(())
     }
+    pub fn process_id(&self) -> Option<u32> {
+        None
+    }
     pub fn exec_sandboxed(_command: &str, _cwd: std::path::PathBuf) -> anyhow::Result<(String, crate::modules::sandbox::OverlaySandbox)> {
         Err(anyhow::anyhow!("Sandboxing not supported on Android"))
     }
 }
+
+/// §15.2: Per-hub shell pool.
+///
+/// Every Command Hub owns its own shell process, spawned from the hub's
+/// `shell_module` and `current_directory`. The pool is keyed by hub id so that
+/// IPC commands can be routed to the hub that issued them.
+pub struct ShellPool {
+    state: Arc<Mutex<TosState>>,
+    modules: Arc<crate::brain::module_manager::ModuleManager>,
+    ai: Arc<crate::services::AiService>,
+    heuristic: Arc<crate::services::HeuristicService>,
+    events: Arc<crate::services::EventBus>,
+    shells: HashMap<uuid::Uuid, ShellApi>,
+}
+
+impl ShellPool {
+    pub fn new(
+        state: Arc<Mutex<TosState>>,
+        modules: Arc<crate::brain::module_manager::ModuleManager>,
+        ai: Arc<crate::services::AiService>,
+        heuristic: Arc<crate::services::HeuristicService>,
+    ) -> Self {
+        Self {
+            state,
+            modules,
+            ai,
+            heuristic,
+            events: Arc::new(crate::services::EventBus::new()),
+            shells: HashMap::new(),
+        }
+    }
+
+    /// §3.3.6: Publish output of shells spawned from now on to `events`.
+    pub fn set_event_bus(&mut self, events: Arc<crate::services::EventBus>) {
+        self.events = events;
+    }
+
+    /// Spawn (or replace) the shell owned by `hub_id`.
+    ///
+    /// Must not be called while the state lock is held: the shell reads the
+    /// hub's module and working directory from state during construction.
+    pub fn spawn(&mut self, sector_id: uuid::Uuid, hub_id: uuid::Uuid) -> anyhow::Result<()> {
+        let shell = ShellApi::new(
+            self.state.clone(),
+            self.modules.clone(),
+            self.ai.clone(),
+            self.heuristic.clone(),
+            self.events.clone(),
+            sector_id,
+            hub_id,
+        )?;
+        if let Some(mut previous) = self.shells.insert(hub_id, shell) {
+            let _ = previous.force_kill();
+        }
+        tracing::info!("[SHELL] Spawned shell for hub {} (sector {})", hub_id, sector_id);
+        Ok(())
+    }
+
+    /// Return the shell owned by `hub_id`, spawning it on first use.
+    pub fn get_or_spawn(
+        &mut self,
+        sector_id: uuid::Uuid,
+        hub_id: uuid::Uuid,
+    ) -> anyhow::Result<&mut ShellApi> {
+        if !self.shells.contains_key(&hub_id) {
+            self.spawn(sector_id, hub_id)?;
+        }
+        self.shells
+            .get_mut(&hub_id)
+            .ok_or_else(|| anyhow::anyhow!("No shell for hub {}", hub_id))
+    }
+
+    pub fn get_mut(&mut self, hub_id: &uuid::Uuid) -> Option<&mut ShellApi> {
+        self.shells.get_mut(hub_id)
+    }
+
+    pub fn contains(&self, hub_id: &uuid::Uuid) -> bool {
+        self.shells.contains_key(hub_id)
+    }
+
+    /// PID of each hub's shell, keyed by hub id.
+    pub fn process_ids(&self) -> HashMap<uuid::Uuid, u32> {
+        self.shells
+            .iter()
+            .filter_map(|(hub_id, shell)| Some((*hub_id, shell.process_id()?)))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.shells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shells.is_empty()
+    }
+
+    /// Terminate and drop the shell owned by `hub_id`.
+    pub fn close(&mut self, hub_id: &uuid::Uuid) -> bool {
+        if let Some(mut shell) = self.shells.remove(hub_id) {
+            if let Err(e) = shell.force_kill() {
+                tracing::warn!("[SHELL] Failed to kill shell for hub {}: {}", hub_id, e);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Terminate every shell in the pool (§22 tactical reset).
+    pub fn kill_all(&mut self) {
+        for (hub_id, shell) in self.shells.iter_mut() {
+            if let Err(e) = shell.force_kill() {
+                tracing::error!("Failed to force-kill shell for hub {}: {}", hub_id, e);
+            }
+        }
+    }
+
+    /// Reconcile the pool with the current sector/hub tree: spawn shells for
+    /// local hubs that have none and tear down shells whose hub is gone.
+    pub fn sync(&mut self) {
+        let hubs: Vec<(uuid::Uuid, uuid::Uuid)> = {
+            let state = self.state.lock().unwrap();
+            state
+                .sectors
+                .iter()
+                .filter(|s| !s.is_remote)
+                .flat_map(|s| s.hubs.iter().map(move |h| (s.id, h.id)))
+                .collect()
+        };
+
+        let stale: Vec<uuid::Uuid> = self
+            .shells
+            .keys()
+            .filter(|id| !hubs.iter().any(|(_, hid)| hid == *id))
+            .copied()
+            .collect();
+        for hub_id in stale {
+            self.close(&hub_id);
+        }
+
+        for (sector_id, hub_id) in hubs {
+            if !self.shells.contains_key(&hub_id) {
+                if let Err(e) = self.spawn(sector_id, hub_id) {
+                    tracing::error!("[SHELL] Failed to spawn shell for hub {}: {}", hub_id, e);
+                }
+            }
+        }
+    }
+}
@@ -4,7 +4,8 @@ use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize}
 use std::io::{Read, Write};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use crate::shell::{OscEvent, OscParser};
+use crate::shell::{OscEvent, OscParser, OscToken, PromptMark};
+use super::screen::{sanitize_line, TerminalEmulator};
 
 /// §15.2: PTY-backed Shell Implementation (Desktop Only)
 pub struct PtyShell {
@@ -14,6 +15,7 @@ pub struct PtyShell {
     writer: Box<dyn Write + Send>,
     master: Box<dyn MasterPty + Send>,
     _child: Box<dyn Child + Send + Sync>,
+    emulator: Arc<Mutex<TerminalEmulator>>,
 }
 
 impl PtyShell {
@@ -22,6 +24,7 @@ impl PtyShell {
         modules: Arc<crate::brain::module_manager::ModuleManager>,
         ai: Arc<crate::services::AiService>,
         heuristic: Arc<crate::services::HeuristicService>,
+        events: Arc<crate::services::EventBus>,
         sector_id: uuid::Uuid,
         hub_id: uuid::Uuid,
     ) -> anyhow::Result<Self> {
@@ -92,12 +95,17 @@ impl PtyShell {
         let mut cmd = CommandBuilder::new(verified_shell);
         cmd.args(args);
         cmd.cwd(cwd);
+        // §26.3: The Brain emulates an xterm, so full-screen programs may use it.
+        cmd.env("TERM", "xterm-256color");
         let child = pair.slave.spawn_command(cmd)?;
 
         let reader = pair.master.try_clone_reader()?;
         let writer = pair.master.take_writer()?;
 
+        let emulator = Arc::new(Mutex::new(TerminalEmulator::new(24, 80)));
+
         let state_clone = state.clone();
+        let emulator_clone = emulator.clone();
         let ai_clone = ai.clone();
         let heuristic_clone = heuristic.clone();
         let sid_clone = sector_id;
@@ -106,8 +114,10 @@ impl PtyShell {
             read_loop(
                 reader,
                 state_clone,
+                emulator_clone,
                 ai_clone,
                 heuristic_clone,
+                events,
                 sid_clone,
                 hid_clone,
             );
@@ -120,11 +130,17 @@ impl PtyShell {
             writer,
             master: pair.master,
             _child: child,
+            emulator,
         })
     }
 
     pub fn write(&mut self, data: &str) -> anyhow::Result<()> {
-        self.writer.write_all(data.as_bytes())?;
+        self.write_bytes(data.as_bytes())
+    }
+
+    /// Forward raw input (keystrokes, escape sequences) to the PTY.
+    pub fn write_bytes(&mut self, data: &[u8]) -> anyhow::Result<()> {
+        self.writer.write_all(data)?;
         self.writer.flush()?;
         Ok(())
     }
@@ -136,9 +152,15 @@ impl PtyShell {
             pixel_width: 0,
             pixel_height: 0,
         })?;
+        self.emulator.lock().unwrap().resize(rows, cols);
         Ok(())
     }
 
+    /// Styled screen snapshot, optionally scrolled back into history (§26.3).
+    pub fn screen(&self, scrollback_offset: usize) -> crate::TerminalScreen {
+        self.emulator.lock().unwrap().snapshot_at(scrollback_offset)
+    }
+
     pub fn send_signal(&mut self, signal: &str) -> anyhow::Result<()> {
         let sig = match signal {
             "INT" | "SIGINT" => libc::SIGINT,
@@ -165,6 +187,11 @@ impl PtyShell {
         }
     }
 
+    /// PID of the shell process.
+    pub fn process_id(&self) -> Option<u32> {
+        self._child.process_id()
+    }
+
     pub fn force_kill(&mut self) -> anyhow::Result<()> {
         self._child.kill()?;
         Ok(())
@@ -232,16 +259,24 @@ impl PtyShell {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn read_loop(
     mut reader: Box<dyn Read + Send>,
     state: Arc<Mutex<TosState>>,
+    emulator: Arc<Mutex<TerminalEmulator>>,
     ai: Arc<crate::services::AiService>,
     _heuristic: Arc<crate::services::HeuristicService>,
+    events: Arc<crate::services::EventBus>,
     sector_id: uuid::Uuid,
     hub_id: uuid::Uuid,
 ) {
+    let output_topic = format!("hub:{}:output", hub_id);
     let mut osc_parser = OscParser::new();
     let mut line_buffer = String::new();
+    // §27.4: OSC 9012 override for the line currently being assembled.
+    let mut line_priority_override: Option<u8> = None;
+    // §26.4: Input echoed after OSC 133 `B`, awaiting the `C` mark.
+    let mut command_capture: Option<String> = None;
     let mut buffer = [0u8; 4096];
 
     loop {
@@ -249,133 +284,190 @@ fn read_loop(
             Ok(0) | Err(_) => break,
             Ok(n) => {
                 let data = &buffer[..n];
-                let text = String::from_utf8_lossy(data);
-                line_buffer.push_str(&text);
-
-                while let Some(pos) = line_buffer.find('\n') {
-                    let mut line = line_buffer.drain(..=pos).collect::<String>();
-                    line = line.trim_end_matches(['\r', '\n']).to_string();
-
-                    let (clean_text, events) = osc_parser.process(&line);
-                    tracing::debug!("[PTY READ] Line: {:?}, Events: {}, Clean: {:?}", line, events.len(), clean_text);
-
-                    let mut state_lock = state.lock().unwrap();
-                    let mut line_priority_override: Option<u8> = None;
-                    for event in events {
-                        match event {
-                            OscEvent::Priority(p) => osc_parser.current_priority = p,
-                            OscEvent::LinePriority(p) => {
-                                // §27.4: Override priority for this line only.
-                                line_priority_override = Some(p);
-                            }
-                            OscEvent::Cwd(path) => {
-                                let path_buf = std::path::PathBuf::from(&path);
-                                if let Some(sector) =
-                                    state_lock.sectors.iter_mut().find(|s| s.id == sector_id)
-                                {
-                                    if let Some(hub) =
-                                        sector.hubs.iter_mut().find(|h| h.id == hub_id)
-                                    {
-                                        hub.current_directory = path_buf.clone();
-                                    }
 
-                                    // §31.3: Dynamic sector labeling from cwd changes.
-                                    // Only auto-relabel sectors with default/auto-generated names
-                                    // to avoid overwriting explicit user renames.
-                                    let auto_labels = [
-                                        "Primary", "New Sector", "Detached", "Untitled",
-                                    ];
-                                    let is_auto_name = auto_labels
-                                        .iter()
-                                        .any(|prefix| sector.name == *prefix || sector.name.starts_with("Detached"));
-                                    if is_auto_name {
-                                        let home = dirs::home_dir();
-                                        let label = if home.as_ref() == Some(&path_buf) {
-                                            "~".to_string()
-                                        } else {
-                                            path_buf
-                                                .file_name()
-                                                .map(|n| n.to_string_lossy().to_string())
-                                                .unwrap_or_else(|| "/".to_string())
-                                        };
-                                        sector.name = label;
-                                    }
-
-                                    // §4.7: Automatic skill activation from CWD signals
-                                    ai.check_context_signals(&mut state_lock, &path_buf);
+                // §26.3: Drive the screen model with the raw byte stream.
+                let (screen, alternate) = {
+                    let mut emu = emulator.lock().unwrap();
+                    emu.process(data);
+                    (emu.snapshot(), emu.is_alternate_screen())
+                };
+
+                // §15.2: OSC sequences may straddle reads; the parser keeps
+                // state and yields text and events in stream order.
+                let tokens = osc_parser.feed(data);
+
+                let mut state_lock = state.lock().unwrap();
+                for token in tokens {
+                    match token {
+                        OscToken::Event(event) => {
+                            tracing::debug!("[PTY READ] Event: {:?}", event);
+                            match event {
+                                // §27.4: Override priority for this line only.
+                                OscEvent::LinePriority(p) => line_priority_override = Some(p),
+                                // §26.4: The echoed input between B and C is the command text.
+                                OscEvent::PromptMark(PromptMark::PromptStart) => command_capture = None,
+                                OscEvent::PromptMark(PromptMark::CommandStart) => {
+                                    command_capture = Some(String::new());
                                 }
-                            }
-                            OscEvent::DirectoryListing(listing) => {
-                                if let Some(sector) =
-                                    state_lock.sectors.iter_mut().find(|s| s.id == sector_id)
-                                {
-                                    if let Some(hub) =
-                                        sector.hubs.iter_mut().find(|h| h.id == hub_id)
-                                    {
-                                        hub.shell_listing = Some(listing);
+                                OscEvent::PromptMark(PromptMark::CommandExecuted) => {
+                                    let command = command_capture
+                                        .take()
+                                        .map(|c| sanitize_line(&c).trim().to_string())
+                                        .filter(|c| !c.is_empty());
+                                    if let Some(hub) = find_hub(&mut state_lock, sector_id, hub_id) {
+                                        hub.begin_command_block(command.as_deref(), true);
+                                        hub.is_running = true;
+                                        hub.version += 1;
                                     }
                                 }
+                                event => {
+                                    apply_osc_event(&mut state_lock, &ai, sector_id, hub_id, event)
+                                }
+                            }
+                        }
+                        OscToken::Text(text) => {
+                            if let Some(capture) = command_capture.as_mut() {
+                                capture.push_str(&text);
                             }
-                            OscEvent::CommandResult {
-                                command: _,
-                                status,
-                                output: _,
-                            } => {
-                                if let Some(sector) =
-                                    state_lock.sectors.iter_mut().find(|s| s.id == sector_id)
-                                {
-                                    if let Some(hub) =
-                                        sector.hubs.iter_mut().find(|h| h.id == hub_id)
-                                    {
-                                        hub.last_exit_status = Some(status);
-                                        hub.is_running = false;
+                            line_buffer.push_str(&text);
+                            while let Some(pos) = line_buffer.find('\n') {
+                                let line = line_buffer.drain(..=pos).collect::<String>();
+                                let clean_text = sanitize_line(line.trim_end_matches(['\r', '\n']));
+                                tracing::debug!("[PTY READ] Line: {:?}, Clean: {:?}", line, clean_text);
+
+                                // Use line-specific priority override if present, else the persistent parser priority.
+                                let mut effective_priority =
+                                    line_priority_override.unwrap_or(osc_parser.current_priority);
+
+                                // §31.4: Automatic error detection (PTY error highlighting)
+                                if line_priority_override.take().is_none() {
+                                    let text_lower = clean_text.to_lowercase();
+                                    if text_lower.contains("error:") || text_lower.contains("failed") || text_lower.contains("command not found") {
+                                        effective_priority = 3; // HIGH priority
                                     }
                                 }
-                            }
-                            OscEvent::JsonContext(json) => {
-                                if let Some(sector) =
-                                    state_lock.sectors.iter_mut().find(|s| s.id == sector_id)
-                                {
-                                    if let Some(hub) =
-                                        sector.hubs.iter_mut().find(|h| h.id == hub_id)
-                                    {
-                                        hub.json_context = Some(json);
+
+                                // Full-screen programs repaint rather than append; their
+                                // frames live in the screen grid, not the line history.
+                                if clean_text.is_empty() || alternate {
+                                    continue;
+                                }
+                                if let Some(hub) = find_hub(&mut state_lock, sector_id, hub_id) {
+                                    let line = TerminalLine {
+                                        text: clean_text,
+                                        priority: effective_priority,
+                                        timestamp: Local::now(),
+                                        source: None,
+                                    };
+                                    // §3.3.6: Stream the line to subscribers as it arrives.
+                                    if let Ok(payload) = serde_json::to_value(&line) {
+                                        events.publish(output_topic.as_str(), payload);
                                     }
+                                    hub.push_output_line(line);
+                                    hub.version += 1;
+                                    state_lock.version += 1;
                                 }
                             }
                         }
                     }
+                }
+
+                if let Some(hub) = find_hub(&mut state_lock, sector_id, hub_id) {
+                    hub.screen = Some(screen);
+                    hub.version += 1;
+                    state_lock.version += 1;
+                }
+                events.publish_state(state_lock.version);
+            }
+        }
+    }
+}
 
-                    // Use line-specific priority override if present, else the persistent parser priority.
-                    let mut effective_priority = line_priority_override.unwrap_or(osc_parser.current_priority);
+fn find_hub(
+    state: &mut TosState,
+    sector_id: uuid::Uuid,
+    hub_id: uuid::Uuid,
+) -> Option<&mut crate::CommandHub> {
+    state
+        .sectors
+        .iter_mut()
+        .find(|s| s.id == sector_id)
+        .and_then(|s| s.hubs.iter_mut().find(|h| h.id == hub_id))
+}
 
-                    // §31.4: Automatic error detection (PTY error highlighting)
-                    if line_priority_override.is_none() {
-                        let text_lower = clean_text.to_lowercase();
-                        if text_lower.contains("error:") || text_lower.contains("failed") || text_lower.contains("command not found") {
-                            effective_priority = 3; // HIGH priority
-                        }
-                    }
+/// Apply a shell-integration event to the owning hub and sector.
+fn apply_osc_event(
+    state_lock: &mut TosState,
+    ai: &crate::services::AiService,
+    sector_id: uuid::Uuid,
+    hub_id: uuid::Uuid,
+    event: OscEvent,
+) {
+    match event {
+        // Tracked by the parser itself; per-line overrides are handled by the caller.
+        OscEvent::Priority(_) | OscEvent::LinePriority(_) => {}
+        OscEvent::Cwd(path) => {
+            let path_buf = std::path::PathBuf::from(&path);
+            if let Some(sector) = state_lock.sectors.iter_mut().find(|s| s.id == sector_id) {
+                if let Some(hub) = sector.hubs.iter_mut().find(|h| h.id == hub_id) {
+                    hub.current_directory = path_buf.clone();
+                }
 
-                    if !clean_text.is_empty() {
-                        if let Some(sector) =
-                            state_lock.sectors.iter_mut().find(|s| s.id == sector_id)
-                        {
-                            if let Some(hub) = sector.hubs.iter_mut().find(|h| h.id == hub_id) {
-                                hub.terminal_output.push(TerminalLine {
-                                    text: clean_text.to_string(),
-                                    priority: effective_priority,
-                                    timestamp: Local::now(),
-                                });
-                                if hub.terminal_output.len() > hub.buffer_limit {
-                                    hub.terminal_output.remove(0);
-                                }
-                                hub.version += 1;
-                                state_lock.version += 1;
-                            }
-                        }
-                    }
+                // §31.3: Dynamic sector labeling from cwd changes.
+                // Only auto-relabel sectors with default/auto-generated names
+                // to avoid overwriting explicit user renames.
+                let auto_labels = [
+                    "Primary", "New Sector", "Detached", "Untitled",
+                ];
+                let is_auto_name = auto_labels
+                    .iter()
+                    .any(|prefix| sector.name == *prefix || sector.name.starts_with("Detached"));
+                if is_auto_name {
+                    let home = dirs::home_dir();
+                    let label = if home.as_ref() == Some(&path_buf) {
+                        "~".to_string()
+                    } else {
+                        path_buf
+                            .file_name()
+                            .map(|n| n.to_string_lossy().to_string())
+                            .unwrap_or_else(|| "/".to_string())
+                    };
+                    sector.name = label;
                 }
+
+                // §4.7: Automatic skill activation from CWD signals
+                ai.check_context_signals(state_lock, &path_buf);
+            }
+        }
+        OscEvent::DirectoryListing(listing) => {
+            if let Some(hub) = find_hub(state_lock, sector_id, hub_id) {
+                hub.shell_listing = Some(listing);
+            }
+        }
+        OscEvent::CommandResult {
+            command,
+            status,
+            output: _,
+        } => {
+            if let Some(hub) = find_hub(state_lock, sector_id, hub_id) {
+                hub.finish_command_block(Some(&command), Some(status));
+                hub.last_exit_status = Some(status);
+                hub.is_running = false;
+            }
+        }
+        OscEvent::PromptMark(PromptMark::CommandFinished(status)) => {
+            if let Some(hub) = find_hub(state_lock, sector_id, hub_id) {
+                hub.finish_command_block(None, status);
+                if status.is_some() {
+                    hub.last_exit_status = status;
+                }
+                hub.is_running = false;
+            }
+        }
+        OscEvent::PromptMark(_) | OscEvent::Hyperlink { .. } => {}
+        OscEvent::JsonContext(json) => {
+            if let Some(hub) = find_hub(state_lock, sector_id, hub_id) {
+                hub.json_context = Some(json);
             }
         }
     }
@@ -0,0 +1,208 @@
+use crate::{StyledSpan, TermColor, TerminalScreen};
+
+/// §26.3: VT100/xterm screen emulation for hub output.
+///
+/// Wraps a `vt100` state machine that maintains the cell grid, scrollback,
+/// cursor, SGR attributes and the alternate screen. The Brain feeds every PTY
+/// read through it and publishes a [`TerminalScreen`] snapshot on the hub.
+pub struct TerminalEmulator {
+    parser: vt100::Parser,
+}
+
+impl TerminalEmulator {
+    /// Rows retained above the live screen.
+    pub const SCROLLBACK_LINES: usize = 2000;
+
+    pub fn new(rows: u16, cols: u16) -> Self {
+        Self {
+            parser: vt100::Parser::new(rows.max(1), cols.max(1), Self::SCROLLBACK_LINES),
+        }
+    }
+
+    pub fn process(&mut self, bytes: &[u8]) {
+        self.parser.process(bytes);
+    }
+
+    pub fn resize(&mut self, rows: u16, cols: u16) {
+        self.parser.screen_mut().set_size(rows.max(1), cols.max(1));
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        self.parser.screen().size()
+    }
+
+    /// True while a full-screen program owns the alternate screen buffer.
+    pub fn is_alternate_screen(&self) -> bool {
+        self.parser.screen().alternate_screen()
+    }
+
+    /// Plain text of the visible grid, one line per row.
+    pub fn contents(&self) -> String {
+        self.parser.screen().contents()
+    }
+
+    /// Styled snapshot of the live screen.
+    pub fn snapshot(&self) -> TerminalScreen {
+        snapshot_screen(self.parser.screen(), 0)
+    }
+
+    /// Styled snapshot scrolled `offset` rows back into the scrollback.
+    /// The live view is restored afterwards.
+    pub fn snapshot_at(&mut self, offset: usize) -> TerminalScreen {
+        if offset == 0 {
+            return self.snapshot();
+        }
+        self.parser.screen_mut().set_scrollback(offset);
+        let applied = self.parser.screen().scrollback();
+        let snapshot = snapshot_screen(self.parser.screen(), applied);
+        self.parser.screen_mut().set_scrollback(0);
+        snapshot
+    }
+}
+
+fn snapshot_screen(screen: &vt100::Screen, offset: usize) -> TerminalScreen {
+    let (rows, cols) = screen.size();
+    let (cursor_row, cursor_col) = screen.cursor_position();
+
+    let mut lines = Vec::with_capacity(rows as usize);
+    for row in 0..rows {
+        let mut spans: Vec<StyledSpan> = Vec::new();
+        for col in 0..cols {
+            let cell = match screen.cell(row, col) {
+                Some(c) => c,
+                None => continue,
+            };
+            if cell.is_wide_continuation() {
+                continue;
+            }
+            let text = if cell.has_contents() {
+                cell.contents()
+            } else {
+                " "
+            };
+            let style = StyledSpan {
+                text: String::new(),
+                fg: convert_color(cell.fgcolor()),
+                bg: convert_color(cell.bgcolor()),
+                bold: cell.bold(),
+                dim: cell.dim(),
+                italic: cell.italic(),
+                underline: cell.underline(),
+                inverse: cell.inverse(),
+            };
+            match spans.last_mut() {
+                Some(last) if same_style(last, &style) => last.text.push_str(text),
+                _ => spans.push(StyledSpan {
+                    text: text.to_string(),
+                    ..style
+                }),
+            }
+        }
+
+        // Trailing unstyled padding carries no information for the Face.
+        if let Some(last) = spans.last_mut() {
+            if last.bg.is_default() && !last.inverse && !last.underline {
+                let trimmed = last.text.trim_end().len();
+                last.text.truncate(trimmed);
+                if last.text.is_empty() {
+                    spans.pop();
+                }
+            }
+        }
+        lines.push(spans);
+    }
+
+    TerminalScreen {
+        rows,
+        cols,
+        cursor_row,
+        cursor_col,
+        cursor_visible: !screen.hide_cursor() && offset == 0,
+        alternate_screen: screen.alternate_screen(),
+        scrollback_offset: offset,
+        lines,
+    }
+}
+
+fn same_style(a: &StyledSpan, b: &StyledSpan) -> bool {
+    a.fg == b.fg
+        && a.bg == b.bg
+        && a.bold == b.bold
+        && a.dim == b.dim
+        && a.italic == b.italic
+        && a.underline == b.underline
+        && a.inverse == b.inverse
+}
+
+fn convert_color(color: vt100::Color) -> TermColor {
+    match color {
+        vt100::Color::Default => TermColor::Default,
+        vt100::Color::Idx(i) => TermColor::Indexed(i),
+        vt100::Color::Rgb(r, g, b) => TermColor::Rgb(r, g, b),
+    }
+}
+
+/// §26.1: Reduce one raw PTY line to printable text for the line-history view.
+///
+/// Carriage returns overwrite from column zero (progress bars keep only their
+/// final state), backspaces erase, and CSI/OSC/other escape sequences and C0
+/// controls other than TAB are dropped.
+pub fn sanitize_line(raw: &str) -> String {
+    let mut cells: Vec<char> = Vec::new();
+    let mut col = 0usize;
+    let mut chars = raw.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                // CSI: parameters and intermediates, then one final byte.
+                Some('[') => {
+                    for n in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&n) {
+                            break;
+                        }
+                    }
+                }
+                // OSC / DCS / APC / PM / SOS: terminated by BEL or ST.
+                Some(']') | Some('P') | Some('_') | Some('^') | Some('X') => {
+                    while let Some(n) = chars.next() {
+                        if n == '\x07' {
+                            break;
+                        }
+                        if n == '\x1b' && chars.peek() == Some(&'\\') {
+                            chars.next();
+                            break;
+                        }
+                    }
+                }
+                // Charset designations carry one extra byte.
+                Some('(') | Some(')') | Some('*') | Some('+') => {
+                    chars.next();
+                }
+                _ => {}
+            },
+            '\r' => col = 0,
+            '\x08' => col = col.saturating_sub(1),
+            '\t' => {
+                put_char(&mut cells, col, '\t');
+                col += 1;
+            }
+            c if c.is_control() => {}
+            c => {
+                put_char(&mut cells, col, c);
+                col += 1;
+            }
+        }
+    }
+
+    cells.into_iter().collect()
+}
+
+fn put_char(cells: &mut Vec<char>, col: usize, c: char) {
+    if col < cells.len() {
+        cells[col] = c;
+    } else {
+        cells.resize(col, ' ');
+        cells.push(c);
+    }
+}
@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// §3.3.2: One RFC 6902 JSON Patch operation.
+///
+/// Only the operations the Brain emits are modelled; `move`, `copy` and
+/// `test` are never produced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOp {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+}
+
+/// §3.3.2: Reply to `get_state_delta:<version>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum StateDelta {
+    /// Apply `ops` to the state the client holds at version `from`.
+    Patch {
+        from: u64,
+        version: u64,
+        ops: Vec<PatchOp>,
+    },
+    /// The client's version is no longer retained; replace its state.
+    Full { version: u64, state: Value },
+}
+
+/// Recent serialized states, oldest first, that patches can be based on.
+///
+/// A client whose version has dropped out of the window is resynced with a
+/// full state.
+pub struct StateHistory {
+    snapshots: VecDeque<(u64, Value)>,
+}
+
+impl Default for StateHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateHistory {
+    /// Versions kept; at the 1Hz heartbeat this is roughly the seconds a
+    /// client may lag before it needs a full resync.
+    pub const CAPACITY: usize = 16;
+
+    pub fn new() -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(Self::CAPACITY),
+        }
+    }
+
+    /// Record the state a client is about to receive for `version`.
+    pub fn record(&mut self, version: u64, state: Value) {
+        if let Some(last) = self.snapshots.back_mut() {
+            if last.0 == version {
+                last.1 = state;
+                return;
+            }
+            if last.0 > version {
+                // The counter went backwards (system reset or restore).
+                self.snapshots.clear();
+            }
+        }
+        self.snapshots.push_back((version, state));
+        while self.snapshots.len() > Self::CAPACITY {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Build the reply for a client at `from` and record `current`.
+    /// `from == 0` means the client holds no state yet.
+    pub fn delta(&mut self, from: u64, version: u64, current: Value) -> StateDelta {
+        let ops = self
+            .snapshots
+            .iter()
+            .find(|(v, _)| *v == from && from != 0 && from != version)
+            .map(|(_, base)| diff(base, &current));
+        self.record(version, current.clone());
+        match ops {
+            Some(ops) => StateDelta::Patch { from, version, ops },
+            None => StateDelta::Full {
+                version,
+                state: current,
+            },
+        }
+    }
+}
+
+/// Compute the operations that turn `old` into `new`.
+pub fn diff(old: &Value, new: &Value) -> Vec<PatchOp> {
+    let mut ops = Vec::new();
+    diff_value(&mut ops, String::new(), old, new);
+    ops
+}
+
+fn diff_value(ops: &mut Vec<PatchOp>, path: String, old: &Value, new: &Value) {
+    if old == new {
+        return;
+    }
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, old_val) in a {
+                let child = format!("{}/{}", path, escape_token(key));
+                match b.get(key) {
+                    Some(new_val) => diff_value(ops, child, old_val, new_val),
+                    None => ops.push(PatchOp::Remove { path: child }),
+                }
+            }
+            for (key, new_val) in b {
+                if !a.contains_key(key) {
+                    ops.push(PatchOp::Add {
+                        path: format!("{}/{}", path, escape_token(key)),
+                        value: new_val.clone(),
+                    });
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => diff_array(ops, path, a, b),
+        _ => ops.push(PatchOp::Replace {
+            path,
+            value: new.clone(),
+        }),
+    }
+}
+
+fn diff_array(ops: &mut Vec<PatchOp>, path: String, old: &[Value], new: &[Value]) {
+    // Capped histories (terminal output, logs) drop from the front and
+    // append at the back; express that as removals plus appends instead of
+    // rewriting every index.
+    let shift = (0..old.len()).find(|&k| {
+        let kept = &old[k..];
+        kept.len() <= new.len() && kept == &new[..kept.len()]
+    });
+    if let Some(k) = shift {
+        for _ in 0..k {
+            ops.push(PatchOp::Remove {
+                path: format!("{}/0", path),
+            });
+        }
+        for value in &new[old.len() - k..] {
+            ops.push(PatchOp::Add {
+                path: format!("{}/-", path),
+                value: value.clone(),
+            });
+        }
+        return;
+    }
+
+    let common = old.len().min(new.len());
+    for i in 0..common {
+        diff_value(ops, format!("{}/{}", path, i), &old[i], &new[i]);
+    }
+    for (i, value) in new.iter().enumerate().skip(common) {
+        ops.push(PatchOp::Add {
+            path: format!("{}/{}", path, i),
+            value: value.clone(),
+        });
+    }
+    for i in (common..old.len()).rev() {
+        ops.push(PatchOp::Remove {
+            path: format!("{}/{}", path, i),
+        });
+    }
+}
+
+/// Apply `ops` in order. Used by Rust clients and to verify patches.
+pub fn apply(target: &mut Value, ops: &[PatchOp]) -> Result<(), String> {
+    for op in ops {
+        match op {
+            PatchOp::Add { path, value } => {
+                let (parent, last) = resolve_parent(target, path)?;
+                match parent {
+                    Value::Object(map) => {
+                        map.insert(last, value.clone());
+                    }
+                    Value::Array(items) => {
+                        let idx = if last == "-" {
+                            items.len()
+                        } else {
+                            parse_index(&last, items.len() + 1)?
+                        };
+                        items.insert(idx, value.clone());
+                    }
+                    _ => return Err(format!("Cannot add into scalar at {}", path)),
+                }
+            }
+            PatchOp::Remove { path } => {
+                let (parent, last) = resolve_parent(target, path)?;
+                match parent {
+                    Value::Object(map) => {
+                        map.remove(&last)
+                            .ok_or_else(|| format!("Missing key at {}", path))?;
+                    }
+                    Value::Array(items) => {
+                        let idx = parse_index(&last, items.len())?;
+                        items.remove(idx);
+                    }
+                    _ => return Err(format!("Cannot remove from scalar at {}", path)),
+                }
+            }
+            PatchOp::Replace { path, value } => {
+                if path.is_empty() {
+                    *target = value.clone();
+                    continue;
+                }
+                let slot = target
+                    .pointer_mut(path)
+                    .ok_or_else(|| format!("Missing value at {}", path))?;
+                *slot = value.clone();
+            }
+        }
+    }
+    Ok(())
+}
+
+fn resolve_parent<'a>(
+    target: &'a mut Value,
+    path: &str,
+) -> Result<(&'a mut Value, String), String> {
+    let (parent_path, last) = path
+        .rsplit_once('/')
+        .ok_or_else(|| format!("Invalid path {}", path))?;
+    let parent = target
+        .pointer_mut(parent_path)
+        .ok_or_else(|| format!("Missing parent for {}", path))?;
+    Ok((parent, unescape_token(last)))
+}
+
+fn parse_index(token: &str, bound: usize) -> Result<usize, String> {
+    match token.parse::<usize>() {
+        Ok(i) if i < bound => Ok(i),
+        _ => Err(format!("Index {} out of bounds", token)),
+    }
+}
+
+/// RFC 6901: `~` and `/` inside a key are written `~0` and `~1`.
+fn escape_token(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn unescape_token(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
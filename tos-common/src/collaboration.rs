@@ -73,7 +73,9 @@ pub enum PresenceStatus {
 }
 
 /// Permission role for a collaboration participant (§13.2).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Ordered from least to most privileged, so `role >= required` checks access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ParticipantRole {
     /// Read-only access to state and cursors.
@@ -13,6 +13,7 @@
 //! - **System:** Low-level OS integration (crash reporting, telemetry).
 //! - **Remote:** Network binding, daemon delegation, and multi-Face coordination.
 
+use crate::collaboration::ParticipantRole;
 use std::path::PathBuf;
 
 // ──────────────────────────────────────────────────────────────────────────
@@ -133,6 +134,37 @@ pub struct RemoteConfig {
     /// Allow connections from non-localhost.
     #[serde(default)]
     pub remote_access: bool,
+    /// Treat TCP/WS clients on a loopback address as the local user (§12.3).
+    #[serde(default = "default_true")]
+    pub trust_loopback: bool,
+    /// Pre-shared tokens accepted via `auth:<token>`.
+    #[serde(default)]
+    pub device_tokens: Vec<DeviceTokenConfig>,
+    /// Client certificates accepted for mutual TLS, pinned by fingerprint.
+    #[serde(default)]
+    pub client_certs: Vec<ClientCertConfig>,
+    /// Role granted to holders of a Web Portal token (§12.2).
+    #[serde(default = "default_portal_role")]
+    pub portal_role: ParticipantRole,
+}
+
+/// A remote device allowed to connect with a pre-shared token.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct DeviceTokenConfig {
+    pub name: String,
+    pub token: String,
+    #[serde(default = "default_device_role")]
+    pub role: ParticipantRole,
+}
+
+/// A client certificate allowed for mutual TLS.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ClientCertConfig {
+    pub name: String,
+    /// Hex SHA-256 of the DER certificate; `:` separators are ignored.
+    pub sha256: String,
+    #[serde(default = "default_device_role")]
+    pub role: ParticipantRole,
 }
 
 impl Default for RemoteConfig {
@@ -144,6 +176,10 @@ impl Default for RemoteConfig {
             uds_path: "/tmp/brain.sock".to_string(),
             mdns_enabled: true,
             remote_access: false,
+            trust_loopback: true,
+            device_tokens: Vec::new(),
+            client_certs: Vec::new(),
+            portal_role: default_portal_role(),
         }
     }
 }
@@ -163,6 +199,12 @@ fn default_uds_path() -> String {
 fn default_true() -> bool {
     true
 }
+fn default_device_role() -> ParticipantRole {
+    ParticipantRole::Operator
+}
+fn default_portal_role() -> ParticipantRole {
+    ParticipantRole::Viewer
+}
 
 // ──────────────────────────────────────────────────────────────────────────
 // Session — session persistence specifics
@@ -256,6 +298,242 @@ impl SettingsConfig {
     }
 }
 
+// ──────────────────────────────────────────────────────────────────────────
+// Search — tos-searchd indexing and embeddings
+// ──────────────────────────────────────────────────────────────────────────
+
+/// Search daemon configuration (§4.1).
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct SearchConfig {
+    /// Embedding backend: "auto", "local", "bert", "hashing", or "none"
+    /// for keyword-only search.
+    #[serde(default = "default_embedding_backend")]
+    pub embedding_backend: String,
+    /// Directory holding `config.json`, `tokenizer.json` and
+    /// `model.safetensors` for the "local" backend.
+    /// Empty = {data_dir}/models/all-MiniLM-L6-v2/.
+    #[serde(default)]
+    pub model_dir: String,
+    /// Weight of the keyword (BM25) ranking in hybrid search.
+    #[serde(default = "default_hybrid_weight")]
+    pub keyword_weight: f32,
+    /// Weight of the semantic (vector) ranking in hybrid search.
+    #[serde(default = "default_hybrid_weight")]
+    pub semantic_weight: f32,
+    /// Reciprocal-rank fusion constant `k`; larger values flatten the
+    /// advantage of top ranks.
+    #[serde(default = "default_rrf_k")]
+    pub rrf_k: f32,
+    /// Directories indexed besides the sectors' working directories.
+    /// `~` expands to the home directory. With no roots from either
+    /// source, the daemon's working directory is indexed.
+    #[serde(default)]
+    pub roots: Vec<String>,
+    /// Files larger than this many bytes are not indexed.
+    #[serde(default = "default_max_file_size")]
+    pub max_file_size: u64,
+}
+
+fn default_embedding_backend() -> String {
+    "auto".to_string()
+}
+
+fn default_hybrid_weight() -> f32 {
+    1.0
+}
+
+fn default_rrf_k() -> f32 {
+    60.0
+}
+
+fn default_max_file_size() -> u64 {
+    1024 * 1024
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            embedding_backend: "auto".to_string(),
+            model_dir: String::new(),
+            keyword_weight: default_hybrid_weight(),
+            semantic_weight: default_hybrid_weight(),
+            rrf_k: default_rrf_k(),
+            roots: Vec::new(),
+            max_file_size: default_max_file_size(),
+        }
+    }
+}
+
+impl SearchConfig {
+    /// The configured roots with `~` expanded.
+    pub fn resolved_roots(&self) -> Vec<PathBuf> {
+        let home = dirs::home_dir();
+        self.roots
+            .iter()
+            .filter(|r| !r.is_empty())
+            .map(|r| match (r.strip_prefix('~'), &home) {
+                (Some(rest), Some(home)) => home.join(rest.trim_start_matches('/')),
+                _ => PathBuf::from(r),
+            })
+            .collect()
+    }
+
+    /// Resolve model_dir using the platform data_dir as the base if empty.
+    pub fn resolved_model_dir(&self, platform: &PlatformConfig) -> PathBuf {
+        if !self.model_dir.is_empty() {
+            PathBuf::from(&self.model_dir)
+        } else {
+            platform
+                .resolved_data_dir()
+                .join("models")
+                .join("all-MiniLM-L6-v2")
+        }
+    }
+}
+
+// ──────────────────────────────────────────────────────────────────────────
+// Logging — tos-loggerd storage and retention
+// ──────────────────────────────────────────────────────────────────────────
+
+/// Log service storage configuration (§19.4).
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct LoggingConfig {
+    /// Directory holding `system.jsonl`, `crashes.jsonl` and their rotated
+    /// archives. Empty = {data_dir}.
+    #[serde(default)]
+    pub log_dir: String,
+    /// A log is rotated once it grows past this many bytes.
+    #[serde(default = "default_log_max_file_size")]
+    pub max_file_size: u64,
+    /// A log is rotated once its oldest record is this many hours old.
+    /// 0 disables age-based rotation.
+    #[serde(default = "default_log_max_file_age_hours")]
+    pub max_file_age_hours: u64,
+    /// Archives whose newest record is older than this many days are
+    /// deleted. 0 keeps them regardless of age.
+    #[serde(default = "default_log_retention_days")]
+    pub retention_days: u64,
+    /// At most this many archives are kept per log, newest first. 0 keeps
+    /// any number.
+    #[serde(default = "default_log_max_archives")]
+    pub max_archives: usize,
+    /// Gzip archives when they are rotated out.
+    #[serde(default = "default_true")]
+    pub compress: bool,
+}
+
+fn default_log_max_file_size() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_log_max_file_age_hours() -> u64 {
+    24
+}
+
+fn default_log_retention_days() -> u64 {
+    30
+}
+
+fn default_log_max_archives() -> usize {
+    30
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            log_dir: String::new(),
+            max_file_size: default_log_max_file_size(),
+            max_file_age_hours: default_log_max_file_age_hours(),
+            retention_days: default_log_retention_days(),
+            max_archives: default_log_max_archives(),
+            compress: true,
+        }
+    }
+}
+
+impl LoggingConfig {
+    /// Resolve log_dir using the platform data_dir if empty.
+    pub fn resolved_log_dir(&self, platform: &PlatformConfig) -> PathBuf {
+        if !self.log_dir.is_empty() {
+            PathBuf::from(&self.log_dir)
+        } else {
+            platform.resolved_data_dir()
+        }
+    }
+}
+
+// ──────────────────────────────────────────────────────────────────────────
+// Heuristic — tos-heuristicd command model
+// ──────────────────────────────────────────────────────────────────────────
+
+/// Command prediction model configuration (§28).
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct HeuristicConfig {
+    /// Directory holding `model.json`. Empty = {data_dir}/heuristics/.
+    #[serde(default)]
+    pub model_dir: String,
+    /// A use counts half as much after this many days.
+    #[serde(default = "default_heuristic_half_life_days")]
+    pub half_life_days: f64,
+    /// Distinct command lines kept; the least used are forgotten first.
+    #[serde(default = "default_heuristic_max_commands")]
+    pub max_commands: usize,
+    /// Directory of user completion specs (`*.json`), which override the
+    /// bundled and module specs. Empty = {config_dir}/completions/.
+    #[serde(default)]
+    pub spec_dir: String,
+    /// Import the shell histories (bash, zsh, fish) into a model that has
+    /// never imported any, unless privacy settings forbid it.
+    #[serde(default = "default_true")]
+    pub import_on_first_run: bool,
+    /// Regexes for secrets to redact from learned and indexed commands, on
+    /// top of the built-in token and password patterns. A `secret` group
+    /// limits the redaction to that part of the match.
+    #[serde(default)]
+    pub redact_patterns: Vec<String>,
+}
+
+fn default_heuristic_half_life_days() -> f64 {
+    14.0
+}
+
+fn default_heuristic_max_commands() -> usize {
+    5000
+}
+
+impl Default for HeuristicConfig {
+    fn default() -> Self {
+        Self {
+            model_dir: String::new(),
+            half_life_days: default_heuristic_half_life_days(),
+            max_commands: default_heuristic_max_commands(),
+            spec_dir: String::new(),
+            import_on_first_run: true,
+            redact_patterns: Vec::new(),
+        }
+    }
+}
+
+impl HeuristicConfig {
+    /// Resolve model_dir using the platform data_dir as the base if empty.
+    pub fn resolved_model_dir(&self, platform: &PlatformConfig) -> PathBuf {
+        if !self.model_dir.is_empty() {
+            PathBuf::from(&self.model_dir)
+        } else {
+            platform.resolved_data_dir().join("heuristics")
+        }
+    }
+
+    /// Resolve spec_dir using the platform config_dir as the base if empty.
+    pub fn resolved_spec_dir(&self, platform: &PlatformConfig) -> PathBuf {
+        if !self.spec_dir.is_empty() {
+            PathBuf::from(&self.spec_dir)
+        } else {
+            platform.resolved_config_dir().join("completions")
+        }
+    }
+}
+
 // ──────────────────────────────────────────────────────────────────────────
 // System — OS integration
 // ──────────────────────────────────────────────────────────────────────────
@@ -377,12 +655,18 @@ pub struct TosConfig {
     #[serde(default)]
     pub face: FaceConfig,
     #[serde(default)]
+    pub heuristic: HeuristicConfig,
+    #[serde(default)]
     pub platform: PlatformConfig,
     #[serde(default)]
     pub local: LocalConfig,
     #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
     pub remote: RemoteConfig,
     #[serde(default)]
+    pub search: SearchConfig,
+    #[serde(default)]
     pub session: SessionConfig,
     #[serde(default)]
     pub settings: SettingsConfig,
@@ -468,6 +752,21 @@ impl TosConfig {
         self.settings.resolved_secure_settings_path(&self.platform)
     }
 
+    /// Resolved log directory.
+    pub fn log_dir(&self) -> PathBuf {
+        self.logging.resolved_log_dir(&self.platform)
+    }
+
+    /// Resolved command model directory.
+    pub fn heuristic_model_dir(&self) -> PathBuf {
+        self.heuristic.resolved_model_dir(&self.platform)
+    }
+
+    /// Resolved user completion spec directory.
+    pub fn heuristic_spec_dir(&self) -> PathBuf {
+        self.heuristic.resolved_spec_dir(&self.platform)
+    }
+
     /// Resolved active working directory.
     pub fn active_dir(&self) -> PathBuf {
         self.local.resolved_active_dir()
@@ -513,6 +812,17 @@ bind_address = "127.0.0.1"
 uds_path = "/run/tos/brain.sock"
 mdns_enabled = false
 remote_access = true
+trust_loopback = false
+portal_role = "commenter"
+
+[[remote.device_tokens]]
+name = "tablet"
+token = "s3cret"
+
+[[remote.client_certs]]
+name = "laptop"
+sha256 = "AB:CD:EF"
+role = "coowner"
 
 [session]
 sessions_dir = "/var/lib/tos/sessions"
@@ -533,6 +843,11 @@ daemon_port = 9002
         assert_eq!(cfg.remote.bind_address, "127.0.0.1");
         assert!(cfg.remote.remote_access);
         assert!(!cfg.remote.mdns_enabled);
+        assert!(!cfg.remote.trust_loopback);
+        assert_eq!(cfg.remote.portal_role, ParticipantRole::Commenter);
+        assert_eq!(cfg.remote.device_tokens[0].name, "tablet");
+        assert_eq!(cfg.remote.device_tokens[0].role, ParticipantRole::Operator);
+        assert_eq!(cfg.remote.client_certs[0].role, ParticipantRole::CoOwner);
         assert_eq!(cfg.session.debounce_ms, 500);
         assert_eq!(cfg.settings.daemon_port, 9002);
     }
@@ -622,6 +937,20 @@ debounce_ms = 1000
         );
     }
 
+    #[test]
+    fn test_model_dir_inherits_platform() {
+        let platform = PlatformConfig {
+            data_dir: "/srv/tos-data".to_string(),
+            ..Default::default()
+        };
+        let search: SearchConfig = toml::from_str("").unwrap();
+        assert_eq!(search.embedding_backend, "auto");
+        assert_eq!(
+            search.resolved_model_dir(&platform),
+            PathBuf::from("/srv/tos-data/models/all-MiniLM-L6-v2")
+        );
+    }
+
     #[test]
     fn test_convenience_resolvers() {
         let cfg = TosConfig {
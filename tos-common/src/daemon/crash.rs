@@ -0,0 +1,293 @@
+//! Crash reports (§19.5).
+//!
+//! The panic hook writes each report to a local spool before anything else,
+//! then forwards it over the Brain's UDS gate to `tos-loggerd`; a report
+//! stays spooled until the Brain confirms it and is retried at the next
+//! start. `tos-loggerd` groups reports by [`CrashReport::signature`], a hash
+//! of the service and the top application frames, so the same bug crashing
+//! a hundred times is one group.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Application frames that make up a signature.
+pub const SIGNATURE_FRAMES: usize = 5;
+
+/// Frames of the panic machinery and the hook itself, which every report
+/// shares and which would otherwise dominate the signature.
+const RUNTIME_FRAMES: &[&str] = &[
+    "backtrace::",
+    "std::panicking",
+    "std::panic::",
+    "std::sys",
+    "std::rt::",
+    "std::thread::",
+    "std::backtrace",
+    "core::panicking",
+    "core::ops::function",
+    "core::result::unwrap_failed",
+    "core::option::",
+    "alloc::boxed::",
+    "<alloc::boxed::Box",
+    "rust_begin_unwind",
+    "__rust",
+    "tos_common::daemon::install_crash_handler",
+    "tos_common::daemon::crash::",
+];
+
+/// One stack frame. `offset` is relative to the loaded binary, so frames
+/// without symbols can be resolved later against `exe` (`addr2line -e`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrashFrame {
+    pub ip: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+}
+
+impl CrashFrame {
+    /// The symbol without its `::h<hash>` suffix, which changes per build.
+    pub fn function(&self) -> Option<&str> {
+        let symbol = self.symbol.as_deref()?;
+        Some(match symbol.rsplit_once("::h") {
+            Some((name, hash))
+                if hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()) =>
+            {
+                name
+            }
+            _ => symbol,
+        })
+    }
+
+    fn is_runtime(&self) -> bool {
+        self.function()
+            .is_some_and(|f| RUNTIME_FRAMES.iter().any(|p| f.starts_with(p)))
+    }
+}
+
+/// A crash report as the panic hook writes it and `tos-loggerd` stores it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrashReport {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub ts: i64,
+    #[serde(default)]
+    pub service: String,
+    /// The TOS release that crashed.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub version: String,
+    /// Path of the crashed binary, for symbolizing `offset`s.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub exe: String,
+    #[serde(default)]
+    pub thread: String,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub location: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub frames: Vec<CrashFrame>,
+    /// Backtrace text of reports written before `frames`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub backtrace: String,
+    #[serde(default)]
+    pub signature: String,
+    /// The request the report was sent in, when a client sent it as one.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub correlation_id: String,
+}
+
+impl CrashReport {
+    /// A report stamped now, with a fresh id and no frames.
+    pub fn new(service: &str, message: &str, location: &str) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            ts: chrono::Local::now().timestamp(),
+            service: service.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            exe: std::env::current_exe()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default(),
+            thread: std::thread::current()
+                .name()
+                .unwrap_or("unnamed")
+                .to_string(),
+            message: message.to_string(),
+            location: location.to_string(),
+            frames: Vec::new(),
+            backtrace: String::new(),
+            signature: String::new(),
+            correlation_id: String::new(),
+        }
+    }
+
+    /// Capture the current stack into `frames`, resolving symbols where
+    /// debug info allows, and sign the report.
+    pub fn capture_frames(&mut self) {
+        let backtrace = backtrace::Backtrace::new();
+        self.frames = backtrace
+            .frames()
+            .iter()
+            .map(|frame| {
+                let ip = frame.ip() as usize;
+                let symbol = frame.symbols().first();
+                CrashFrame {
+                    ip: format!("{:#x}", ip),
+                    offset: frame
+                        .module_base_address()
+                        .map(|base| format!("{:#x}", ip.wrapping_sub(base as usize))),
+                    symbol: symbol.and_then(|s| s.name()).map(|n| n.to_string()),
+                    file: symbol
+                        .and_then(|s| s.filename())
+                        .map(|f| f.display().to_string()),
+                    line: symbol.and_then(|s| s.lineno()),
+                }
+            })
+            .collect();
+        self.sign();
+    }
+
+    /// Read a report as a Face, daemon or older release sent it. Anything
+    /// but a JSON report is kept as the message of an `unknown` service.
+    /// Missing ids are derived from the payload, so the same stored line
+    /// always reads back with the same id.
+    pub fn parse(payload: &str) -> Self {
+        let mut report = match serde_json::from_str::<CrashReport>(payload) {
+            Ok(r) => r,
+            Err(_) => CrashReport {
+                id: String::new(),
+                ts: 0,
+                service: "unknown".to_string(),
+                version: String::new(),
+                exe: String::new(),
+                thread: String::new(),
+                message: payload.trim().to_string(),
+                location: String::new(),
+                frames: Vec::new(),
+                backtrace: String::new(),
+                signature: String::new(),
+                correlation_id: String::new(),
+            },
+        };
+        if report.id.is_empty() {
+            report.id = hex::encode(&Sha256::digest(payload.as_bytes())[..8]);
+        }
+        if report.signature.is_empty() {
+            report.sign();
+        }
+        report
+    }
+
+    /// Set [`Self::signature`] from the report's contents.
+    pub fn sign(&mut self) {
+        self.signature = self.compute_signature();
+    }
+
+    /// Hash of the service and the top [`SIGNATURE_FRAMES`] application
+    /// frames. Without symbols, the panic location stands in for the
+    /// frames, and failing that the message with its numbers masked.
+    pub fn compute_signature(&self) -> String {
+        let mut parts: Vec<String> = self
+            .frames
+            .iter()
+            .filter(|f| !f.is_runtime())
+            .filter_map(|f| f.function())
+            .take(SIGNATURE_FRAMES)
+            .map(str::to_string)
+            .collect();
+        if parts.is_empty() {
+            // Columns shift with formatting; the line is enough.
+            let location = match self.location.rsplit_once(':') {
+                Some((file_line, col)) if col.chars().all(|c| c.is_ascii_digit()) => file_line,
+                _ => self.location.as_str(),
+            };
+            if !location.is_empty() && location != "unknown" {
+                parts.push(location.to_string());
+            } else {
+                parts.push(
+                    self.message
+                        .chars()
+                        .map(|c| if c.is_ascii_digit() { '#' } else { c })
+                        .collect(),
+                );
+            }
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(self.service.as_bytes());
+        for part in &parts {
+            hasher.update(b"\n");
+            hasher.update(part.as_bytes());
+        }
+        hex::encode(&hasher.finalize()[..8])
+    }
+
+    /// The top application frame, as a one-line summary.
+    pub fn top_frame(&self) -> Option<&CrashFrame> {
+        self.frames
+            .iter()
+            .find(|f| !f.is_runtime() && f.symbol.is_some())
+    }
+}
+
+/// Where reports wait until the Brain confirms them: `{log_dir}/crash-spool/`.
+pub fn spool_dir(config: &crate::TosConfig) -> PathBuf {
+    config.log_dir().join("crash-spool")
+}
+
+/// Write `report` into `dir` as `<id>.json`. Written aside and renamed, so
+/// a half-written report is never forwarded.
+pub fn spool(dir: &Path, report: &CrashReport) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}.json", report.id));
+    let partial = path.with_extension("json.tmp");
+    std::fs::write(&partial, serde_json::to_vec(report)?)?;
+    std::fs::rename(&partial, &path)?;
+    Ok(path)
+}
+
+/// Send `report` to the Brain on `uds_path` as `crash:<json>` and wait for
+/// it to confirm delivery to `tos-loggerd`.
+pub fn forward(uds_path: &str, report: &CrashReport) -> anyhow::Result<()> {
+    let mut stream = std::os::unix::net::UnixStream::connect(uds_path)?;
+    let timeout = Some(std::time::Duration::from_secs(2));
+    stream.set_read_timeout(timeout)?;
+    stream.set_write_timeout(timeout)?;
+    stream.write_all(format!("crash:{}\n", serde_json::to_string(report)?).as_bytes())?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    match reply.trim() {
+        r if r.starts_with("OK") => Ok(()),
+        r => anyhow::bail!("Crash report refused: {}", r),
+    }
+}
+
+/// Forward every spooled report, deleting each one the Brain confirms.
+/// Stops at the first failure, as the rest would fail the same way.
+/// Returns how many were delivered.
+pub fn flush_spool(dir: &Path, uds_path: &str) -> anyhow::Result<usize> {
+    let mut pending: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "json"))
+            .collect(),
+        Err(_) => return Ok(0),
+    };
+    pending.sort();
+    let mut delivered = 0;
+    for path in pending {
+        let report = CrashReport::parse(&std::fs::read_to_string(&path)?);
+        forward(uds_path, &report)?;
+        std::fs::remove_file(&path)?;
+        delivered += 1;
+    }
+    Ok(delivered)
+}
@@ -1,15 +1,26 @@
 use ed25519_dalek::{Signer, SigningKey};
 use rand_core::OsRng;
-use std::io::Write;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use crate::ipc::{ServiceRegister, ServiceRegisterResponse};
+use crate::services::logger::LogRecord;
+
+pub mod crash;
+
+/// Attempts to deliver the crash spool at startup, while the Brain comes up.
+const SPOOL_RETRIES: u32 = 10;
+const SPOOL_RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// The Brain's local socket, `[remote] uds_path`; the Brain binds the same.
+pub fn brain_socket() -> String {
+    crate::config::TosConfig::load().remote.uds_path
+}
 
 /// Register a satellite daemon with the Brain on brain.sock.
 ///
 /// This performs the ed25519 signature handshake required by the
 /// Dynamic Port Registration Gate (§4.1).
 pub async fn register_with_brain(name: &str, port: u16) -> anyhow::Result<()> {
-    let uds_path = "/tmp/brain.sock";
+    let uds_path = brain_socket();
 
     // Attempt connection with exponential backoff (daemon might start before Brain is ready).
     // Starts at 100ms, doubles each attempt, caps at 10s per interval, max 10 retries.
@@ -18,7 +29,7 @@ pub async fn register_with_brain(name: &str, port: u16) -> anyhow::Result<()> {
     let max_retries: u32 = 10;
     let max_backoff_ms: u64 = 10_000;
     for attempt in 1..=max_retries {
-        match tokio::net::UnixStream::connect(uds_path).await {
+        match tokio::net::UnixStream::connect(&uds_path).await {
             Ok(s) => {
                 stream = Some(s);
                 break;
@@ -75,6 +86,15 @@ pub async fn register_with_brain(name: &str, port: u16) -> anyhow::Result<()> {
             name,
             port
         );
+        // Best effort: the registration itself is what matters.
+        let record = LogRecord::new(
+            crate::services::logger::Severity::Info,
+            name,
+            "log",
+            &format!("Registered on port {}", port),
+        )
+        .with_field("port", port);
+        let _ = log_to_brain(&record).await;
         Ok(())
     } else {
         Err(anyhow::anyhow!("Registration denied: {}", response.message))
@@ -116,26 +136,53 @@ impl MockBrain {
     }
 }
 
-/// Send a log message to the Brain's unified LogManager.
-pub async fn log_to_brain(text: &str, priority: u8) -> anyhow::Result<()> {
+/// Send a record to the unified log through the Brain (§19.1).
+///
+/// The record travels as one JSON line, so messages may hold `;` and
+/// newlines. Build it with [`LogRecord::new`] naming the daemon as source.
+pub async fn log_to_brain(record: &LogRecord) -> anyhow::Result<()> {
     // Connect to discovery gate
-    let mut stream = tokio::net::UnixStream::connect("/tmp/brain.sock").await?;
+    let mut stream = tokio::net::UnixStream::connect(brain_socket()).await?;
 
-    // Command format: system_log_append:text;priority
-    let cmd = format!("system_log_append:{};{}\n", text, priority);
+    // Command format: log_record:<json>
+    let cmd = format!("log_record:{}\n", serde_json::to_string(record)?);
     stream.write_all(cmd.as_bytes()).await?;
 
     // We don't necessarily need to wait for response for a log
     Ok(())
 }
 
-/// Install a panic hook for automated crash reporting (§6.10).
+/// Install a panic hook for automated crash reporting (§6.10, §19.5).
 ///
 /// When a panic occurs, this hook:
-/// 1. Captures a backtrace.
-/// 2. Formats a JSON crash report.
-/// 3. Attempts to send it to tos-loggerd via the Brain's discovery gate.
+/// 1. Captures the message, location and symbolized frames.
+/// 2. Writes the report to the local crash spool.
+/// 3. Forwards it over the Brain's UDS gate to tos-loggerd, deleting the
+///    spooled copy once the Brain confirms it.
+///
+/// Reports left in the spool by an earlier crash, when the Brain or
+/// tos-loggerd was down, are forwarded in the background now.
 pub fn install_crash_handler(service_name: String) {
+    let config = crate::config::TosConfig::load();
+    if config.system.crash_reporting_enabled {
+        let dir = crash::spool_dir(&config);
+        let uds_path = config.remote.uds_path.clone();
+        std::thread::spawn(move || {
+            for _ in 0..SPOOL_RETRIES {
+                match crash::flush_spool(&dir, &uds_path) {
+                    Ok(n) => {
+                        if n > 0 {
+                            tracing::info!("DAEMON: Delivered {} spooled crash reports", n);
+                        }
+                        return;
+                    }
+                    Err(e) => tracing::debug!("DAEMON: Crash spool not delivered yet: {}", e),
+                }
+                std::thread::sleep(SPOOL_RETRY_INTERVAL);
+            }
+        });
+    }
+
     std::panic::set_hook(Box::new(move |info| {
         // §6.10: Respect opt-in setting from tos.toml
         let config = crate::config::TosConfig::load();
@@ -143,10 +190,6 @@ pub fn install_crash_handler(service_name: String) {
             return;
         }
 
-        let backtrace = backtrace::Backtrace::new();
-        let thread = std::thread::current();
-        let thread_name = thread.name().unwrap_or("unnamed");
-
         let msg = match info.payload().downcast_ref::<&'static str>() {
             Some(s) => *s,
             None => match info.payload().downcast_ref::<String>() {
@@ -157,30 +200,23 @@ pub fn install_crash_handler(service_name: String) {
 
         let location = info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())).unwrap_or_else(|| "unknown".to_string());
 
-        let report = serde_json::json!({
-            "ts": chrono::Local::now().timestamp(),
-            "service": service_name,
-            "thread": thread_name,
-            "message": msg,
-            "location": location,
-            "backtrace": format!("{:?}", backtrace),
-        });
-
-        let payload = serde_json::to_string(&report).unwrap_or_default();
-        
-        // Use synchronous IO for the panic hook as it must complete before process exit.
-        if let Ok(mut stream) = std::net::TcpStream::connect("127.0.0.1:7000") { // Default anchor
-             // We use a simple strategy: send to Brain, let Brain forward to loggerd?
-             // Actually, loggerd is a satellite. Brain knows its port.
-             // But in a panic, we don't have time for multi-step handshake.
-             // Let's see if we can find loggerd port from Brain.
-             
-             // FOR ALPHA: Send "crash:<payload>" to Brain's anchor. 
-             // We'll update Brain to handle/forward this or have loggerd listen on a well-known dev port.
-             let _ = stream.write_all(format!("crash:{}\n", payload).as_bytes());
+        let mut report = crash::CrashReport::new(&service_name, msg, &location);
+        report.capture_frames();
+
+        // Written locally first: the process may not live long enough to
+        // hear back from the Brain.
+        let spooled = crash::spool(&crash::spool_dir(&config), &report);
+        match crash::forward(&config.remote.uds_path, &report) {
+            Ok(()) => {
+                if let Ok(path) = &spooled {
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+            Err(e) => eprintln!("TOS CRASH REPORT kept for the next start ({})", e),
         }
 
         eprintln!("TOS CRASH REPORT ({}): {}", service_name, msg);
         eprintln!("LOCATION: {}", location);
+        eprintln!("SIGNATURE: {}", report.signature);
     }));
 }
@@ -2,12 +2,14 @@
 
 pub mod collaboration;
 pub mod ipc;
+pub mod rpc;
 pub mod keybindings;
 pub mod state;
 pub mod marketplace;
 pub mod controller_mapping;
 pub mod modules;
 pub mod platform;
+pub mod redact;
 pub mod shell;
 
 // Brain orchestrator logic
@@ -24,7 +26,8 @@ pub use config::TosConfig;
 pub use state::{
     TosState, Sector, CommandHub, CommandHubMode,
     TerminalOutputModuleMeta, ShellModuleMeta, TerminalLayoutType, ThemeModule, ThemeAssetDefinition,
-    AiModuleMetadata, TerminalContext, TerminalLine, SettingsStore, HierarchyLevel,
+    AiModuleMetadata, TerminalContext, TerminalLine, TerminalScreen, StyledSpan, TermColor,
+    SettingsStore, HierarchyLevel,
     TrustTier, ConfirmationRequest, ApplicationModel, BezelAction, DecorationPolicy,
     ZoomBehavior, AppInstance, SectorTemplate, HubTemplate, DirectoryListing,
     DirectoryEntry, ActivityListing, ProcessEntry, SearchResult, AiMessage,
@@ -43,7 +46,7 @@ pub use marketplace::{
 pub use services::marketplace::{ModuleManifest, ExecutableConfig, MarketplaceService};
 pub use collaboration::{Participant, ParticipantRole, PresenceStatus, WebRtcPayload};
 pub use platform::{AppPlatform, PlatformStatus, RemoteServer, remote::RemoteRenderer};
-pub use shell::{OscEvent, OscParser};
+pub use shell::{OscEvent, OscParser, OscToken, PromptMark};
 pub use keybindings::{KeyCombo, Keybinding, KeybindingMap};
 pub use controller_mapping::DeviceMapping;
 #[cfg(feature = "test-utils")]
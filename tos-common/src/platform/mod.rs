@@ -6,8 +6,10 @@ pub mod remote;
 pub mod remote_server;
 pub mod remote_session;
 pub mod ssh_fallback;
+pub mod tls_identity;
 pub use traits::*;
 pub use remote_server::RemoteServer;
+pub use tls_identity::BrainIdentity;
 pub use headless::HeadlessRenderer;
 pub use remote::RemoteRenderer;
 pub use traits::Renderer;
@@ -1,13 +1,15 @@
 use crate::brain::ipc_handler::IpcHandler;
+use crate::services::auth::ClientIdentity;
+use crate::services::AuthService;
 use futures_util::{SinkExt, StreamExt};
 use std::sync::Arc;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::{TcpListener, TcpStream};
 use tokio_tungstenite::accept_async;
-use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use crate::platform::tls_identity::BrainIdentity;
+use rustls::pki_types::CertificateDer;
 use rustls::ServerConfig;
 use tokio_rustls::TlsAcceptor;
-use rcgen::generate_simple_self_signed;
 use webrtc::api::APIBuilder;
 use webrtc::api::interceptor_registry::register_default_interceptors;
 use webrtc::api::media_engine::MediaEngine;
@@ -24,14 +26,34 @@ use webrtc::interceptor::registry::Registry;
 pub struct RemoteServer {
     ipc: Arc<IpcHandler>,
     webrtc_sessions: Arc<tokio::sync::Mutex<std::collections::HashMap<uuid::Uuid, Arc<webrtc::peer_connection::RTCPeerConnection>>>>,
+    /// §12.4: Persistent certificate served on every TLS listener.
+    identity: Arc<BrainIdentity>,
 }
 
 impl RemoteServer {
-    /// Creates a new RemoteServer instance with the provided IPC handler.
+    /// Creates a new RemoteServer instance with the provided IPC handler,
+    /// serving the identity stored under the configured data dir.
     pub fn new(ipc: Arc<IpcHandler>) -> Self {
-        Self { 
+        let dir = BrainIdentity::default_dir(&crate::TosConfig::load());
+        let identity = match BrainIdentity::load_or_create(&dir) {
+            Ok(identity) => identity,
+            Err(e) => {
+                tracing::error!(
+                    "[REMOTE_SERVER] Cannot use identity in {:?} ({}); falling back to an ephemeral certificate",
+                    dir,
+                    e
+                );
+                BrainIdentity::ephemeral().expect("Failed to generate TLS identity")
+            }
+        };
+        Self::with_identity(ipc, Arc::new(identity))
+    }
+
+    pub fn with_identity(ipc: Arc<IpcHandler>, identity: Arc<BrainIdentity>) -> Self {
+        Self {
             ipc,
             webrtc_sessions: Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new())),
+            identity,
         }
     }
 
@@ -39,7 +61,8 @@ impl RemoteServer {
     pub async fn run(&self, port: u16) -> anyhow::Result<()> {
         let tcp_addr = format!("[::]:{}", port);
         let ws_addr = format!("[::]:{}", port + 1); // e.g. 7001 for WebSocket
-        let uds_path = "/tmp/brain.sock";
+        let uds_path = crate::daemon::brain_socket();
+        let uds_path = uds_path.as_str();
 
         // Bind with retry — previous Brain instance may have just been killed
         let tcp_listener = Self::bind_with_retry(&tcp_addr).await?;
@@ -60,20 +83,25 @@ impl RemoteServer {
 
         let ipc_clone = self.ipc.clone();
 
-        let tls_config = Self::generate_tls_config()?;
-        let _tls_acceptor = TlsAcceptor::from(Arc::new(tls_config));
+        let tls_acceptor = TlsAcceptor::from(Arc::new(Self::generate_tls_config(
+            &self.identity,
+            self.ipc.auth(),
+        )?));
+        tracing::info!("[REMOTE_SERVER] TLS identity {}", self.identity.fingerprint());
+
+        let server = Arc::new(Self::with_identity(self.ipc.clone(), self.identity.clone()));
 
-        let server = Arc::new(Self::new(self.ipc.clone()));
-        
         // Spawn TCP daemon
         let tcp_server = server.clone();
+        let tcp_acceptor = tls_acceptor.clone();
         tokio::spawn(async move {
             loop {
-                if let Ok((socket, _)) = tcp_listener.accept().await {
+                if let Ok((socket, addr)) = tcp_listener.accept().await {
                     let h_server = tcp_server.clone();
+                    let acceptor = tcp_acceptor.clone();
                     tokio::spawn(async move {
-                        if let Ok(tls_stream) = h_server.tls_acceptor().accept(socket).await {
-                            if let Err(e) = h_server.handle_tcp_client(tls_stream).await {
+                        if let Ok(tls_stream) = acceptor.accept(socket).await {
+                            if let Err(e) = h_server.handle_tcp_client(tls_stream, addr).await {
                                 tracing::error!("[REMOTE_SERVER] TCP Client error: {}", e);
                             }
                         } else {
@@ -86,13 +114,15 @@ impl RemoteServer {
 
         // Spawn WebSocket daemon
         let ws_server = server.clone();
+        let ws_acceptor = tls_acceptor;
         tokio::spawn(async move {
             loop {
                 if let Ok((socket, addr)) = ws_listener.accept().await {
                     tracing::info!("[REMOTE_SERVER] WS Client connecting from {}", addr);
                     let h_server = ws_server.clone();
+                    let acceptor = ws_acceptor.clone();
                     tokio::spawn(async move {
-                        match h_server.tls_acceptor().accept(socket).await {
+                        match acceptor.accept(socket).await {
                             Ok(tls_stream) => {
                                 if let Err(e) = h_server.handle_ws_client(tls_stream, addr).await {
                                     tracing::error!("[REMOTE_SERVER] WS Client error ({}): {}", addr, e);
@@ -149,21 +179,17 @@ impl RemoteServer {
         unreachable!()
     }
 
-    fn generate_tls_config() -> anyhow::Result<ServerConfig> {
+    fn generate_tls_config(
+        identity: &BrainIdentity,
+        auth: Arc<AuthService>,
+    ) -> anyhow::Result<ServerConfig> {
         // §12.1: Ensure a crypto provider is installed for rustls 0.23+
         let _ = rustls::crypto::ring::default_provider().install_default();
 
-        let cert = generate_simple_self_signed(vec![
-            "localhost".into(),
-            "127.0.0.1".into(),
-            "[::1]".into(),
-            "tos-brain.local".into(),
-        ])?;
-        let key = PrivateKeyDer::Pkcs8(cert.signing_key.serialize_der().into());
-        let cert_der = CertificateDer::from(cert.cert.der().to_vec());
+        // §12.3: Client certificates are optional; pinned ones bind an identity.
         let mut config = ServerConfig::builder()
-            .with_no_client_auth()
-            .with_single_cert(vec![cert_der], key)?;
+            .with_client_cert_verifier(Arc::new(PinnedClientVerifier::new(auth)))
+            .with_single_cert(vec![identity.certificate()], identity.private_key())?;
         config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
         Ok(config)
     }
@@ -186,6 +212,9 @@ impl RemoteServer {
             let command = line.trim();
             if !command.is_empty() {
                 let response = ipc.handle_request(command);
+                if response.is_empty() {
+                    continue;
+                }
                 writer
                     .write_all(format!("{}\n", response).as_bytes())
                     .await?;
@@ -195,16 +224,39 @@ impl RemoteServer {
         Ok(())
     }
 
-    fn tls_acceptor(&self) -> TlsAcceptor {
-        let tls_config = Self::generate_tls_config().expect("Failed to generate TLS config");
-        TlsAcceptor::from(Arc::new(tls_config))
-    }
+    async fn handle_tcp_client(
+        &self,
+        socket: tokio_rustls::server::TlsStream<TcpStream>,
+        addr: std::net::SocketAddr,
+    ) -> anyhow::Result<()> {
+        let auth = self.ipc.auth();
+        let mut identity = connection_identity(&auth, &socket, &addr);
+        let mut auth_failures = 0;
 
-    async fn handle_tcp_client(&self, socket: tokio_rustls::server::TlsStream<TcpStream>) -> anyhow::Result<()> {
         let (reader, mut writer) = tokio::io::split(socket);
         let mut reader = BufReader::new(reader);
         let mut line = String::new();
 
+        // Responses and pushes share one bounded queue; a slow reader stalls
+        // the push task rather than growing memory (§3.3.6).
+        let (out_tx, mut out_rx) = tokio::sync::mpsc::channel::<String>(OUTBOUND_QUEUE);
+        // Every message, including pushes, is one line.
+        let writer_task = tokio::spawn(async move {
+            while let Some(mut msg) = out_rx.recv().await {
+                if !msg.ends_with('\n') {
+                    msg.push('\n');
+                }
+                if writer.write_all(msg.as_bytes()).await.is_err() || writer.flush().await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        // Plain request/response until the client subscribes to a topic.
+        let subscriptions: Subscriptions = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let rpc_mode = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let mut push_task = None;
+
         loop {
             line.clear();
             let n = reader.read_line(&mut line).await?;
@@ -213,108 +265,221 @@ impl RemoteServer {
             }
 
             let command = line.trim();
-            if !command.is_empty() {
-                // §19.3: OpenSearch HTTP compatibility
-                if command.starts_with("GET /opensearch.xml") {
-                    let xml = self.generate_opensearch_xml();
-                    let response = format!(
-                        "HTTP/1.1 200 OK\r\nContent-Type: application/opensearchdescription+xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-                        xml.len(),
-                        xml
-                    );
-                    writer.write_all(response.as_bytes()).await?;
-                    writer.flush().await?;
-                    return Ok(());
-                } else if command.starts_with("GET /search?q=") {
-                    let query = command.split("q=").nth(1).unwrap_or("").split(' ').next().unwrap_or("");
-                    let ipc_cmd = format!("search:{}", query);
-                    let results = self.ipc.handle_request(&ipc_cmd);
-                    let response = format!(
-                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-                        results.len(),
-                        results
-                    );
-                    writer.write_all(response.as_bytes()).await?;
-                    writer.flush().await?;
-                    return Ok(());
+            if command.is_empty() {
+                continue;
+            }
+
+            // §3.3.7: JSON-RPC requests, including auth and subscriptions.
+            if crate::rpc::is_rpc(command) {
+                rpc_mode.store(true, std::sync::atomic::Ordering::Relaxed);
+                let reply = handle_connection_rpc(
+                    &self.ipc,
+                    &mut identity,
+                    &subscriptions,
+                    command,
+                    &addr,
+                    &mut auth_failures,
+                );
+                if push_task.is_none() && !subscriptions.lock().unwrap().is_empty() {
+                    push_task = Some(spawn_push_task(
+                        self.ipc.clone(),
+                        subscriptions.clone(),
+                        rpc_mode.clone(),
+                        out_tx.clone(),
+                    ));
+                }
+                if let Some(reply) = reply {
+                    if out_tx.send(reply).await.is_err() {
+                        break;
+                    }
+                }
+                if auth_failures >= MAX_AUTH_FAILURES {
+                    break;
                 }
+                continue;
+            }
 
-                let response = self.ipc.handle_request(command);
-                writer
-                    .write_all(format!("{}\n", response).as_bytes())
-                    .await?;
-                writer.flush().await?;
+            if let Some(res) = handle_auth(&auth, &mut identity, command, &addr) {
+                if identity.is_none() {
+                    auth_failures += 1;
+                }
+                if out_tx.send(format!("{}\n", res)).await.is_err()
+                    || auth_failures >= MAX_AUTH_FAILURES
+                {
+                    break;
+                }
+                continue;
+            }
+
+            let client = match &identity {
+                Some(id) => id.clone(),
+                None if command.starts_with("GET /") => {
+                    let _ = out_tx
+                        .send("HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string())
+                        .await;
+                    break;
+                }
+                None => {
+                    if out_tx.send(format!("{}\n", auth_required(command))).await.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+            };
+
+            // §19.3: OpenSearch HTTP compatibility
+            if command.starts_with("GET /opensearch.xml") {
+                let xml = self.generate_opensearch_xml();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/opensearchdescription+xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    xml.len(),
+                    xml
+                );
+                let _ = out_tx.send(response).await;
+                break;
+            } else if command.starts_with("GET /search?q=") {
+                let query = command.split("q=").nth(1).unwrap_or("").split(' ').next().unwrap_or("");
+                let ipc_cmd = format!("search:{}", query);
+                let results = self.ipc.handle_request_as(&client, &ipc_cmd);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    results.len(),
+                    results
+                );
+                let _ = out_tx.send(response).await;
+                break;
+            }
+
+            let response = match handle_subscription(&subscriptions, command) {
+                Some(res) => {
+                    if push_task.is_none() && !subscriptions.lock().unwrap().is_empty() {
+                        push_task = Some(spawn_push_task(
+                            self.ipc.clone(),
+                            subscriptions.clone(),
+                            rpc_mode.clone(),
+                            out_tx.clone(),
+                        ));
+                    }
+                    res
+                }
+                None => self.ipc.handle_request_as(&client, command),
+            };
+            if out_tx.send(format!("{}\n", response)).await.is_err() {
+                break;
             }
         }
+
+        if let Some(task) = push_task {
+            task.abort();
+        }
+        drop(out_tx);
+        let _ = writer_task.await;
         Ok(())
     }
 
-    async fn handle_ws_client(&self, socket: tokio_rustls::server::TlsStream<TcpStream>, _addr: std::net::SocketAddr) -> anyhow::Result<()> {
+    async fn handle_ws_client(&self, socket: tokio_rustls::server::TlsStream<TcpStream>, addr: std::net::SocketAddr) -> anyhow::Result<()> {
+        let auth = self.ipc.auth();
+        let mut identity = connection_identity(&auth, &socket, &addr);
+        let mut auth_failures = 0;
+
         let ws_stream = accept_async(socket).await?;
         let (mut ws_tx, mut ws_rx) = ws_stream.split();
-        let (mpsc_tx, mut mpsc_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        let (out_tx, mut out_rx) = tokio::sync::mpsc::channel::<String>(OUTBOUND_QUEUE);
 
-        let push_ipc = self.ipc.clone();
-        let push_tx = mpsc_tx.clone();
-        
-        let push_task = tokio::spawn(async move {
-            let mut last_version = 0;
-            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(1));
-            loop {
-                interval.tick().await;
-                let request = format!("get_state_delta:{}", last_version);
-                let response = push_ipc.handle_request(&request);
-                if response != "NO_CHANGE" && !response.starts_with("ERROR") {
-                    if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(&response) {
-                        if let Some(v) = parsed.get("version").and_then(|v| v.as_u64()) {
-                            last_version = v;
-                        }
-                    }
-                    if push_tx.send(format!("state_delta:{}", response)).is_err() {
-                        break;
-                    }
+        let writer_task = tokio::spawn(async move {
+            while let Some(msg) = out_rx.recv().await {
+                if ws_tx.send(tokio_tungstenite::tungstenite::Message::Text(msg)).await.is_err() {
+                    break;
                 }
             }
         });
 
-        loop {
-            tokio::select! {
-                Some(msg_result) = ws_rx.next() => {
-                    let msg = match msg_result {
-                        Ok(m) => m,
-                        Err(_) => break,
-                    };
-                    if msg.is_text() {
-                        if let Ok(command) = msg.to_text() {
-                            let response = if command.starts_with("webrtc_signalling:") {
+        // §3.3.6: Faces get state deltas by default and can add topics; the
+        // push starts once the connection has an identity (§12.3).
+        let subscriptions: Subscriptions = Arc::new(std::sync::Mutex::new(vec![
+            crate::services::EventBus::STATE_TOPIC.to_string(),
+        ]));
+        let rpc_mode = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let mut push_task = identity.as_ref().map(|_| {
+            spawn_push_task(
+                self.ipc.clone(),
+                subscriptions.clone(),
+                rpc_mode.clone(),
+                out_tx.clone(),
+            )
+        });
+
+        while let Some(msg_result) = ws_rx.next().await {
+            let msg = match msg_result {
+                Ok(m) => m,
+                Err(_) => break,
+            };
+            if msg.is_text() {
+                if let Ok(command) = msg.to_text() {
+                    let response = if crate::rpc::is_rpc(command) {
+                        rpc_mode.store(true, std::sync::atomic::Ordering::Relaxed);
+                        let reply = handle_connection_rpc(
+                            &self.ipc,
+                            &mut identity,
+                            &subscriptions,
+                            command,
+                            &addr,
+                            &mut auth_failures,
+                        );
+                        if push_task.is_none() && identity.is_some() {
+                            push_task = Some(spawn_push_task(
+                                self.ipc.clone(),
+                                subscriptions.clone(),
+                                rpc_mode.clone(),
+                                out_tx.clone(),
+                            ));
+                        }
+                        match reply {
+                            Some(reply) => reply,
+                            None if auth_failures >= MAX_AUTH_FAILURES => break,
+                            None => continue,
+                        }
+                    } else if let Some(res) = handle_auth(&auth, &mut identity, command, &addr) {
+                        if identity.is_none() {
+                            auth_failures += 1;
+                        } else if push_task.is_none() {
+                            push_task = Some(spawn_push_task(
+                                self.ipc.clone(),
+                                subscriptions.clone(),
+                                rpc_mode.clone(),
+                                out_tx.clone(),
+                            ));
+                        }
+                        res
+                    } else {
+                        match &identity {
+                            None => auth_required(command),
+                            Some(_) if command.starts_with("webrtc_signalling:") => {
                                 match self.handle_webrtc_signalling(command).await {
                                     Ok(res) => res,
                                     Err(e) => format!("ERROR: WebRTC Signalling failed: {}", e),
                                 }
-                            } else {
-                                self.ipc.handle_request(command)
-                            };
-                            
-                            if mpsc_tx.send(response).is_err() {
-                                break;
                             }
+                            Some(client) => match handle_subscription(&subscriptions, command) {
+                                Some(res) => res,
+                                None => self.ipc.handle_request_as(client, command),
+                            },
                         }
-                    } else if msg.is_close() {
-                        break;
-                    }
-                }
-                Some(send_msg) = mpsc_rx.recv() => {
-                    if ws_tx.send(tokio_tungstenite::tungstenite::Message::Text(send_msg)).await.is_err() {
+                    };
+
+                    if out_tx.send(response).await.is_err() || auth_failures >= MAX_AUTH_FAILURES {
                         break;
                     }
                 }
-                else => {
-                    break;
-                }
+            } else if msg.is_close() {
+                break;
             }
         }
-        
-        push_task.abort();
+
+        if let Some(task) = push_task {
+            task.abort();
+        }
+        writer_task.abort();
         Ok(())
     }
 
@@ -337,6 +502,8 @@ impl RemoteServer {
         let mut properties = HashMap::new();
         properties.insert("version".to_string(), "0.2.0-beta.0".to_string());
         properties.insert("vendor".to_string(), "TOS-Foundation".to_string());
+        // §12.4: Lets Faces pin the Brain on first use.
+        properties.insert("tls_sha256".to_string(), self.identity.fingerprint());
 
         let my_service = ServiceInfo::new(
             service_type,
@@ -465,3 +632,421 @@ impl RemoteServer {
 </OpenSearchDescription>"#.to_string()
     }
 }
+
+/// Messages queued per connection before pushes wait for the client.
+const OUTBOUND_QUEUE: usize = 256;
+
+/// Failed `auth:` attempts before the connection is closed.
+const MAX_AUTH_FAILURES: u32 = 3;
+
+/// §12.3: Accepts a client certificate only if its fingerprint is pinned.
+///
+/// Presenting a certificate is optional so token clients can still connect;
+/// the request is only sent when pins are configured, so browsers are not
+/// prompted otherwise.
+struct PinnedClientVerifier {
+    auth: Arc<AuthService>,
+    algorithms: rustls::crypto::WebPkiSupportedAlgorithms,
+}
+
+impl PinnedClientVerifier {
+    fn new(auth: Arc<AuthService>) -> Self {
+        Self {
+            auth,
+            algorithms: rustls::crypto::ring::default_provider().signature_verification_algorithms,
+        }
+    }
+}
+
+impl std::fmt::Debug for PinnedClientVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PinnedClientVerifier").finish_non_exhaustive()
+    }
+}
+
+impl rustls::server::danger::ClientCertVerifier for PinnedClientVerifier {
+    fn offer_client_auth(&self) -> bool {
+        self.auth.has_pinned_certificates()
+    }
+
+    fn client_auth_mandatory(&self) -> bool {
+        false
+    }
+
+    fn root_hint_subjects(&self) -> &[rustls::DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _now: rustls::pki_types::UnixTime,
+    ) -> Result<rustls::server::danger::ClientCertVerified, rustls::Error> {
+        match self.auth.authenticate_certificate(end_entity) {
+            Some(_) => Ok(rustls::server::danger::ClientCertVerified::assertion()),
+            None => {
+                tracing::warn!(
+                    "[REMOTE_SERVER] Rejected unpinned client certificate {}",
+                    crate::services::auth::certificate_fingerprint(end_entity)
+                );
+                Err(rustls::Error::InvalidCertificate(
+                    rustls::CertificateError::ApplicationVerificationFailure,
+                ))
+            }
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+/// §12.3: Identity a connection holds straight after the TLS handshake:
+/// a pinned client certificate, or the local user on trusted loopback.
+fn connection_identity(
+    auth: &AuthService,
+    socket: &tokio_rustls::server::TlsStream<TcpStream>,
+    addr: &std::net::SocketAddr,
+) -> Option<ClientIdentity> {
+    let cert_identity = socket
+        .get_ref()
+        .1
+        .peer_certificates()
+        .and_then(|certs| certs.first())
+        .and_then(|cert| auth.authenticate_certificate(cert));
+    let identity = cert_identity.or_else(|| auth.authenticate_peer(addr));
+    if let Some(id) = &identity {
+        tracing::info!(
+            "[REMOTE_SERVER] {} connected as {} (role={:?})",
+            addr,
+            id.name,
+            id.role
+        );
+    }
+    identity
+}
+
+/// Handle the connection-scoped `auth:<token>` command (also inside the
+/// `cmd:<id>:` wrapper). A failed attempt clears the identity. Returns
+/// `None` for anything else.
+fn handle_auth(
+    auth: &AuthService,
+    identity: &mut Option<ClientIdentity>,
+    command: &str,
+    addr: &std::net::SocketAddr,
+) -> Option<String> {
+    if let Some(rest) = command.strip_prefix("cmd:") {
+        let (id, inner) = rest.split_once(':')?;
+        return handle_auth(auth, identity, inner, addr).map(|res| format!("res:{}:{}", id, res));
+    }
+
+    let token = command.strip_prefix("auth:")?;
+    match authenticate(auth, identity, token, addr) {
+        Some(id) => Some(format!(
+            "AUTH_OK: {}",
+            serde_json::to_string(id).unwrap_or_default()
+        )),
+        None => Some("ERROR: Authentication failed".to_string()),
+    }
+}
+
+/// Bind the connection to the identity `token` resolves to, or clear it.
+fn authenticate<'a>(
+    auth: &AuthService,
+    identity: &'a mut Option<ClientIdentity>,
+    token: &str,
+    addr: &std::net::SocketAddr,
+) -> Option<&'a ClientIdentity> {
+    *identity = auth.authenticate_token(token);
+    match identity {
+        Some(id) => {
+            tracing::info!(
+                "[REMOTE_SERVER] {} authenticated as {} (role={:?})",
+                addr,
+                id.name,
+                id.role
+            );
+            Some(id)
+        }
+        None => {
+            tracing::warn!("[REMOTE_SERVER] Authentication failed from {}", addr);
+            None
+        }
+    }
+}
+
+/// §3.3.7: Handle a JSON-RPC request on a remote connection. `auth` and
+/// the subscription methods are answered here; everything else goes to
+/// the `IpcHandler` once the connection has an identity. Returns `None`
+/// for notifications.
+fn handle_connection_rpc(
+    ipc: &IpcHandler,
+    identity: &mut Option<ClientIdentity>,
+    subscriptions: &Subscriptions,
+    command: &str,
+    addr: &std::net::SocketAddr,
+    auth_failures: &mut u32,
+) -> Option<String> {
+    use crate::rpc::{codes, RpcError, RpcRequest, RpcResponse};
+
+    let req = match RpcRequest::parse(command) {
+        Ok(r) => r,
+        Err(response) => return Some(response.to_json()),
+    };
+    let id = req.id.clone().unwrap_or(serde_json::Value::Null);
+
+    let response = match (crate::rpc::connection_method(&req.method), &*identity) {
+        (Some(spec), _) => match spec.decode(req.params.as_ref()) {
+            Err(e) => RpcResponse::failure(id, e),
+            Ok(args) if spec.name == "auth" => {
+                match authenticate(&ipc.auth(), identity, &args[0], addr) {
+                    Some(client) => RpcResponse::success(
+                        id,
+                        serde_json::to_value(client).unwrap_or_default(),
+                    ),
+                    None => {
+                        *auth_failures += 1;
+                        RpcResponse::failure(
+                            id,
+                            RpcError::new(codes::AUTH_REQUIRED, "Authentication failed"),
+                        )
+                    }
+                }
+            }
+            Ok(_) if identity.is_none() => RpcResponse::failure(
+                id,
+                RpcError::new(codes::AUTH_REQUIRED, "Authentication required"),
+            ),
+            Ok(topics) => match update_subscriptions(subscriptions, &spec.name, topics) {
+                Some(res) if res.starts_with("ERROR") => RpcResponse::from_legacy(id, &res),
+                _ => RpcResponse::success(
+                    id,
+                    serde_json::json!(*subscriptions.lock().unwrap()),
+                ),
+            },
+        },
+        (None, None) => RpcResponse::failure(
+            id,
+            RpcError::new(codes::AUTH_REQUIRED, "Authentication required"),
+        ),
+        (None, Some(client)) => return ipc.handle_rpc_as(client, command),
+    };
+
+    if req.is_notification() {
+        None
+    } else {
+        Some(response.to_json())
+    }
+}
+
+/// Reply to a command sent before authenticating, keeping the `res:<id>:`
+/// envelope so request/response clients are not left waiting.
+fn auth_required(command: &str) -> String {
+    let msg = "ERROR: Authentication required";
+    match command
+        .strip_prefix("cmd:")
+        .and_then(|rest| rest.split_once(':'))
+    {
+        Some((id, _)) => format!("res:{}:{}", id, msg),
+        None => msg.to_string(),
+    }
+}
+
+/// Lower bound between two state-delta pushes; bursts of PTY output inside
+/// this window are coalesced into one delta.
+const MIN_PUSH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(16);
+
+/// §3.3.6: Topic patterns a connection is subscribed to.
+type Subscriptions = Arc<std::sync::Mutex<Vec<String>>>;
+
+/// Handle the connection-scoped `subscribe:`, `unsubscribe:` and
+/// `subscriptions:` commands (also inside the `cmd:<id>:` wrapper).
+/// Returns `None` for anything else.
+fn handle_subscription(subscriptions: &Subscriptions, command: &str) -> Option<String> {
+    if let Some(rest) = command.strip_prefix("cmd:") {
+        let (id, inner) = rest.split_once(':')?;
+        return handle_subscription(subscriptions, inner).map(|res| format!("res:{}:{}", id, res));
+    }
+
+    let (prefix, payload) = command.split_once(':').unwrap_or((command, ""));
+    let topics: Vec<String> = payload.split(';').map(str::to_string).collect();
+    update_subscriptions(subscriptions, prefix, topics)
+}
+
+/// Apply `subscribe`, `unsubscribe` or `subscriptions`; `None` for any
+/// other command.
+fn update_subscriptions(
+    subscriptions: &Subscriptions,
+    prefix: &str,
+    topics: Vec<String>,
+) -> Option<String> {
+    let topics: Vec<String> = topics
+        .iter()
+        .map(|t| t.trim())
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect();
+    let mut subs = subscriptions.lock().unwrap();
+    match prefix {
+        "subscribe" => {
+            if topics.is_empty() {
+                return Some("ERROR: Missing topic".to_string());
+            }
+            for topic in &topics {
+                if !subs.contains(topic) {
+                    subs.push(topic.clone());
+                }
+            }
+            Some(format!("SUBSCRIBED: {}", topics.join(";")))
+        }
+        "unsubscribe" => {
+            subs.retain(|t| !topics.contains(t));
+            Some(format!("UNSUBSCRIBED: {}", topics.join(";")))
+        }
+        "subscriptions" => Some(serde_json::to_string(&*subs).unwrap_or_else(|_| "[]".to_string())),
+        _ => None,
+    }
+}
+
+fn is_subscribed(subscriptions: &Subscriptions, topic: &str) -> bool {
+    subscriptions
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|pattern| crate::services::events::topic_matches(pattern, topic))
+}
+
+/// §3.3.6: Forward bus events to one connection.
+///
+/// Topic events are sent as `event:{"topic":..,"payload":..}`. `state`
+/// events are not forwarded themselves; they trigger a `state_delta:` push.
+/// Once the client has spoken JSON-RPC (`rpc_mode`), both are sent as
+/// `event` / `state_delta` notifications instead (§3.3.7).
+/// Sends wait on the bounded outbound queue, so a slow client falls behind
+/// on the bus; it is then told how many events it missed (`bus` topic) and
+/// resynchronised through the next state delta.
+fn spawn_push_task(
+    ipc: Arc<IpcHandler>,
+    subscriptions: Subscriptions,
+    rpc_mode: Arc<std::sync::atomic::AtomicBool>,
+    out: tokio::sync::mpsc::Sender<String>,
+) -> tokio::task::JoinHandle<()> {
+    use crate::services::EventBus;
+    use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+
+    tokio::spawn(async move {
+        let mut rx = ipc.events().subscribe();
+        let mut last_version = 0u64;
+        let mut last_push = std::time::Instant::now() - MIN_PUSH_INTERVAL;
+        let mut dirty = true;
+
+        loop {
+            if !dirty {
+                let event = match rx.recv().await {
+                    Ok(ev) => Ok(ev),
+                    Err(RecvError::Lagged(n)) => Err(n),
+                    Err(RecvError::Closed) => break,
+                };
+                match forward_event(&subscriptions, &rpc_mode, &out, event).await {
+                    Some(state_changed) => dirty = state_changed,
+                    None => break,
+                }
+                if !dirty {
+                    continue;
+                }
+                let wait = MIN_PUSH_INTERVAL.saturating_sub(last_push.elapsed());
+                if !wait.is_zero() {
+                    tokio::time::sleep(wait).await;
+                }
+            }
+
+            // Flush everything already queued so one delta covers the burst.
+            loop {
+                let event = match rx.try_recv() {
+                    Ok(ev) => Ok(ev),
+                    Err(TryRecvError::Lagged(n)) => Err(n),
+                    Err(_) => break,
+                };
+                if forward_event(&subscriptions, &rpc_mode, &out, event).await.is_none() {
+                    return;
+                }
+            }
+
+            dirty = false;
+            last_push = std::time::Instant::now();
+            if !is_subscribed(&subscriptions, EventBus::STATE_TOPIC) {
+                continue;
+            }
+            let response = ipc.handle_request(&format!("get_state_delta:{}", last_version));
+            if response == "NO_CHANGE" || response.starts_with("ERROR") {
+                continue;
+            }
+            let parsed = serde_json::from_str::<serde_json::Value>(&response).ok();
+            if let Some(v) = parsed
+                .as_ref()
+                .and_then(|p| p.get("version"))
+                .and_then(|v| v.as_u64())
+            {
+                last_version = v;
+            }
+            let msg = match parsed {
+                Some(delta) if rpc_mode.load(std::sync::atomic::Ordering::Relaxed) => {
+                    crate::rpc::notification("state_delta", delta)
+                }
+                _ => format!("state_delta:{}", response),
+            };
+            if out.send(msg).await.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Send one bus event if subscribed. Returns whether the state changed
+/// (or may have, after a lag), or `None` once the connection is gone.
+async fn forward_event(
+    subscriptions: &Subscriptions,
+    rpc_mode: &std::sync::atomic::AtomicBool,
+    out: &tokio::sync::mpsc::Sender<String>,
+    event: Result<crate::services::events::BusEvent, u64>,
+) -> Option<bool> {
+    let event = match event {
+        Ok(ev) => ev,
+        Err(missed) => crate::services::events::BusEvent {
+            topic: "bus".to_string(),
+            payload: serde_json::json!({ "lagged": missed }),
+        },
+    };
+    let lagged = event.topic == "bus";
+    if event.topic == crate::services::EventBus::STATE_TOPIC {
+        return Some(true);
+    }
+    if lagged || is_subscribed(subscriptions, &event.topic) {
+        let msg = if rpc_mode.load(std::sync::atomic::Ordering::Relaxed) {
+            crate::rpc::notification("event", serde_json::to_value(&event).ok()?)
+        } else {
+            format!("event:{}", serde_json::to_string(&event).ok()?)
+        };
+        out.send(msg).await.ok()?;
+    }
+    Some(lagged)
+}
@@ -83,14 +83,12 @@ fn read_loop(
                 let mut lock = state.lock().unwrap();
                 if let Some(sector) = lock.sectors.iter_mut().find(|s| s.id == sector_id) {
                     if let Some(hub) = sector.hubs.iter_mut().find(|h| h.id == hub_id) {
-                        hub.terminal_output.push(TerminalLine {
+                        hub.push_output_line(TerminalLine {
                             text,
                             priority: 1,
                             timestamp: chrono::Local::now(),
+                            source: None,
                         });
-                        if hub.terminal_output.len() > hub.buffer_limit {
-                            hub.terminal_output.remove(0);
-                        }
                         hub.version += 1;
                         lock.version += 1;
                     }
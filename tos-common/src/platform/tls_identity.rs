@@ -0,0 +1,336 @@
+use crate::services::auth::certificate_fingerprint;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// §12.4: The Brain's long-lived TLS certificate and key.
+///
+/// Generated once and kept under `{data_dir}/identity/` so Faces can pin
+/// the fingerprint across restarts. Rotating replaces both files, each by
+/// a rename, so a reader never sees a half-written one; the new
+/// certificate is served after the Brain restarts.
+pub struct BrainIdentity {
+    cert: CertificateDer<'static>,
+    key: PrivateKeyDer<'static>,
+    /// Where the pair was loaded from; `None` for an ephemeral identity.
+    dir: Option<PathBuf>,
+}
+
+impl BrainIdentity {
+    pub const CERT_FILE: &'static str = "brain-cert.pem";
+    pub const KEY_FILE: &'static str = "brain-key.pem";
+
+    /// `{data_dir}/identity` for the given config.
+    pub fn default_dir(config: &crate::TosConfig) -> PathBuf {
+        config.platform.resolved_data_dir().join("identity")
+    }
+
+    /// Load the identity in `dir`, generating it on first start.
+    pub fn load_or_create(dir: &Path) -> anyhow::Result<Self> {
+        if dir.join(Self::CERT_FILE).exists() {
+            Self::load(dir)
+        } else {
+            tracing::info!("[TLS] Generating Brain identity in {:?}", dir);
+            Self::rotate(dir)
+        }
+    }
+
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let cert = CertificateDer::from_pem_file(dir.join(Self::CERT_FILE))
+            .map_err(|e| anyhow::anyhow!("Failed to read Brain certificate: {}", e))?;
+        let key = PrivateKeyDer::from_pem_file(dir.join(Self::KEY_FILE))
+            .map_err(|e| anyhow::anyhow!("Failed to read Brain key: {}", e))?;
+        Ok(Self {
+            cert,
+            key,
+            dir: Some(dir.to_path_buf()),
+        })
+    }
+
+    /// Generate a new keypair and replace the one stored in `dir`.
+    pub fn rotate(dir: &Path) -> anyhow::Result<Self> {
+        let (cert_pem, key_pem) = Self::generate()?;
+        std::fs::create_dir_all(dir)?;
+        let key_tmp = dir.join(format!("{}.tmp", Self::KEY_FILE));
+        let cert_tmp = dir.join(format!("{}.tmp", Self::CERT_FILE));
+        write_private(&key_tmp, key_pem.as_bytes())?;
+        std::fs::write(&cert_tmp, cert_pem.as_bytes())?;
+        std::fs::rename(&key_tmp, dir.join(Self::KEY_FILE))?;
+        std::fs::rename(&cert_tmp, dir.join(Self::CERT_FILE))?;
+        Self::load(dir)
+    }
+
+    /// An in-memory identity, used when the data dir is not writable.
+    pub fn ephemeral() -> anyhow::Result<Self> {
+        let (cert_pem, key_pem) = Self::generate()?;
+        Ok(Self {
+            cert: CertificateDer::from_pem_slice(cert_pem.as_bytes())
+                .map_err(|e| anyhow::anyhow!("{}", e))?,
+            key: PrivateKeyDer::from_pem_slice(key_pem.as_bytes())
+                .map_err(|e| anyhow::anyhow!("{}", e))?,
+            dir: None,
+        })
+    }
+
+    /// Certificate and key PEM for a fresh self-signed identity.
+    fn generate() -> anyhow::Result<(String, String)> {
+        let generated = rcgen::generate_simple_self_signed(vec![
+            "localhost".into(),
+            "127.0.0.1".into(),
+            "[::1]".into(),
+            "tos-brain.local".into(),
+        ])?;
+        Ok((generated.cert.pem(), generated.signing_key.serialize_pem()))
+    }
+
+    /// Colon-separated SHA-256 of the DER certificate.
+    pub fn fingerprint(&self) -> String {
+        certificate_fingerprint(&self.cert)
+    }
+
+    pub fn certificate(&self) -> CertificateDer<'static> {
+        self.cert.clone()
+    }
+
+    pub fn private_key(&self) -> PrivateKeyDer<'static> {
+        self.key.clone_key()
+    }
+
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+}
+
+#[cfg(unix)]
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(contents)
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    std::fs::write(path, contents)
+}
+
+/// Outcome of checking a Brain's fingerprint against [`KnownBrains`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PinCheck {
+    /// Never seen; the Face may trust it on first use.
+    Unknown,
+    Trusted,
+    /// A different certificate than the one pinned: possible impostor.
+    Mismatch {
+        pinned: String,
+    },
+}
+
+/// §12.4: Brain fingerprints a Face has trusted, keyed by address
+/// (`host:port`), persisted as `{config_dir}/known_brains.json`.
+#[derive(Debug, Default)]
+pub struct KnownBrains {
+    path: Option<PathBuf>,
+    pins: BTreeMap<String, String>,
+}
+
+impl KnownBrains {
+    pub const FILE: &'static str = "known_brains.json";
+
+    /// `{config_dir}/known_brains.json` for the given config.
+    pub fn default_path(config: &crate::TosConfig) -> PathBuf {
+        config.platform.resolved_config_dir().join(Self::FILE)
+    }
+
+    pub fn load(path: &Path) -> Self {
+        let pins = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self {
+            path: Some(path.to_path_buf()),
+            pins,
+        }
+    }
+
+    pub fn check(&self, address: &str, fingerprint: &str) -> PinCheck {
+        match self.pins.get(address) {
+            None => PinCheck::Unknown,
+            Some(pinned) if pinned.eq_ignore_ascii_case(fingerprint) => PinCheck::Trusted,
+            Some(pinned) => PinCheck::Mismatch {
+                pinned: pinned.clone(),
+            },
+        }
+    }
+
+    pub fn pinned(&self, address: &str) -> Option<&str> {
+        self.pins.get(address).map(String::as_str)
+    }
+
+    /// Pin (or re-pin after a deliberate rotation) and save.
+    pub fn trust(&mut self, address: &str, fingerprint: &str) -> std::io::Result<()> {
+        self.pins
+            .insert(address.to_string(), fingerprint.to_uppercase());
+        self.save()
+    }
+
+    pub fn forget(&mut self, address: &str) -> std::io::Result<()> {
+        self.pins.remove(address);
+        self.save()
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let path = match &self.path {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(&self.pins).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
+}
+
+/// §12.4: Server verifier for Faces connecting to a Brain.
+///
+/// Accepts exactly the pinned fingerprint. Without a pin it accepts the
+/// first certificate and records it in [`PinnedServerVerifier::seen`] so the
+/// Face can store it in [`KnownBrains`] (trust on first use).
+pub struct PinnedServerVerifier {
+    pinned: Option<String>,
+    seen: Mutex<Option<String>>,
+    algorithms: rustls::crypto::WebPkiSupportedAlgorithms,
+}
+
+impl PinnedServerVerifier {
+    pub fn new(pinned: Option<&str>) -> Self {
+        Self {
+            pinned: pinned.map(str::to_uppercase),
+            seen: Mutex::new(None),
+            algorithms: rustls::crypto::ring::default_provider().signature_verification_algorithms,
+        }
+    }
+
+    /// Fingerprint presented by the Brain during the last handshake.
+    pub fn seen(&self) -> Option<String> {
+        self.seen.lock().unwrap().clone()
+    }
+
+    /// A client config that verifies the Brain with this verifier.
+    pub fn client_config(self: Arc<Self>) -> rustls::ClientConfig {
+        rustls::ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(self)
+            .with_no_client_auth()
+    }
+}
+
+impl std::fmt::Debug for PinnedServerVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PinnedServerVerifier")
+            .field("pinned", &self.pinned)
+            .finish_non_exhaustive()
+    }
+}
+
+impl rustls::client::danger::ServerCertVerifier for PinnedServerVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &rustls::pki_types::ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: rustls::pki_types::UnixTime,
+    ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        let fingerprint = certificate_fingerprint(end_entity);
+        *self.seen.lock().unwrap() = Some(fingerprint.clone());
+        match &self.pinned {
+            Some(pinned) if *pinned != fingerprint => {
+                tracing::error!(
+                    "[TLS] Brain certificate {} does not match pinned {}",
+                    fingerprint,
+                    pinned
+                );
+                Err(rustls::Error::InvalidCertificate(
+                    rustls::CertificateError::ApplicationVerificationFailure,
+                ))
+            }
+            _ => Ok(rustls::client::danger::ServerCertVerified::assertion()),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+/// §12.4: Open a TLS connection to the Brain at `address` (`host:port`),
+/// verified against its pin in `known`. A Brain never seen before is
+/// trusted and pinned; one presenting another certificate is refused.
+pub async fn connect_pinned(
+    address: &str,
+    known: &mut KnownBrains,
+) -> anyhow::Result<tokio_rustls::client::TlsStream<tokio::net::TcpStream>> {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let pinned = known.pinned(address).map(str::to_string);
+    let verifier = Arc::new(PinnedServerVerifier::new(pinned.as_deref()));
+    let connector = tokio_rustls::TlsConnector::from(Arc::new(verifier.clone().client_config()));
+
+    let host = address
+        .rsplit_once(':')
+        .map_or(address, |(host, _)| host)
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    let name = rustls::pki_types::ServerName::try_from(host.to_string())
+        .map_err(|e| anyhow::anyhow!("Invalid Brain address '{}': {}", address, e))?;
+    let socket = tokio::net::TcpStream::connect(address).await?;
+    let stream = match connector.connect(name, socket).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            return Err(match (pinned, verifier.seen()) {
+                (Some(pinned), Some(seen)) if !pinned.eq_ignore_ascii_case(&seen) => {
+                    anyhow::anyhow!(
+                        "Brain at {} presented {} but {} is pinned; re-pin only if it was rotated",
+                        address,
+                        seen,
+                        pinned
+                    )
+                }
+                _ => e.into(),
+            })
+        }
+    };
+    if known.pinned(address).is_none() {
+        if let Some(seen) = verifier.seen() {
+            tracing::info!("[TLS] Pinned Brain {} as {}", address, seen);
+            known.trust(address, &seen)?;
+        }
+    }
+    Ok(stream)
+}
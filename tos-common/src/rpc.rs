@@ -0,0 +1,428 @@
+//! §3.3.7: JSON-RPC 2.0 framing for Brain IPC.
+//!
+//! The structured alternative to `prefix:arg;arg` strings. Every command in
+//! the `CommandRegistry` (§3.3.8) is a method here with the typed, named
+//! parameters it was registered with, so argument values are passed through
+//! verbatim and may contain `;` or `:`. Responses carry the request id and
+//! structured error codes; Brain pushes are JSON-RPC notifications.
+//! [`schema`] publishes the method table as an OpenRPC document
+//! (`docs/spec/tos_ipc.openrpc.json`).
+
+use crate::brain::command_registry::{CommandRegistry, CommandSource, CommandSpec};
+use crate::collaboration::ParticipantRole;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Version of the TOS method set, reported by `rpc.discover`.
+pub const PROTOCOL_VERSION: &str = "1.0.0";
+
+pub const JSONRPC_VERSION: &str = "2.0";
+
+/// Error codes. The negative 327xx range is reserved by JSON-RPC; TOS
+/// codes start at -32000.
+pub mod codes {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    pub const INTERNAL_ERROR: i64 = -32603;
+    /// The handler ran and returned `ERROR: ...`.
+    pub const COMMAND_FAILED: i64 = -32000;
+    pub const PERMISSION_DENIED: i64 = -32001;
+    pub const AUTH_REQUIRED: i64 = -32002;
+    /// The command was held behind a trust confirmation (§17.2).
+    pub const CONFIRMATION_REQUIRED: i64 = -32003;
+}
+
+/// True if `text` should be parsed as JSON-RPC rather than a legacy string.
+pub fn is_rpc(text: &str) -> bool {
+    text.trim_start().starts_with(['{', '['])
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    /// Absent for notifications, which get no response.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+}
+
+impl RpcRequest {
+    /// Parse one request, or the error response to send instead.
+    pub fn parse(text: &str) -> Result<Self, Box<RpcResponse>> {
+        let value: Value = match serde_json::from_str(text) {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(Box::new(RpcResponse::failure(
+                    Value::Null,
+                    RpcError::new(codes::PARSE_ERROR, format!("Parse error: {}", e)),
+                )))
+            }
+        };
+        let id = value.get("id").cloned().unwrap_or(Value::Null);
+        if value.is_array() {
+            return Err(Box::new(RpcResponse::failure(
+                Value::Null,
+                RpcError::new(codes::INVALID_REQUEST, "Batch requests are not supported"),
+            )));
+        }
+        let req: Self = match serde_json::from_value(value) {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(Box::new(RpcResponse::failure(
+                    id,
+                    RpcError::new(codes::INVALID_REQUEST, format!("Invalid request: {}", e)),
+                )))
+            }
+        };
+        if req.jsonrpc != JSONRPC_VERSION {
+            return Err(Box::new(RpcResponse::failure(
+                id,
+                RpcError::new(
+                    codes::INVALID_REQUEST,
+                    "Invalid request: jsonrpc must be \"2.0\"",
+                ),
+            )));
+        }
+        match &req.params {
+            None | Some(Value::Array(_)) | Some(Value::Object(_)) => Ok(req),
+            Some(_) => Err(Box::new(RpcResponse::failure(
+                id,
+                RpcError::new(
+                    codes::INVALID_REQUEST,
+                    "Invalid request: params must be an array or object",
+                ),
+            ))),
+        }
+    }
+
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    pub fn success(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn failure(id: Value, error: RpcError) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result: None,
+            error: Some(error),
+        }
+    }
+
+    /// Wrap a legacy handler response: `ERROR: ...` strings and
+    /// `CONFIRMATION_REQUIRED` become errors, JSON bodies are embedded as
+    /// values and any other reply is returned as a string.
+    pub fn from_legacy(id: Value, response: &str) -> Self {
+        if let Some(message) = response.strip_prefix("ERROR: ") {
+            let code = if message.starts_with("Permission denied") {
+                codes::PERMISSION_DENIED
+            } else if message.starts_with("Authentication") {
+                codes::AUTH_REQUIRED
+            } else {
+                codes::COMMAND_FAILED
+            };
+            return Self::failure(id, RpcError::new(code, message));
+        }
+        if response == "CONFIRMATION_REQUIRED" {
+            return Self::failure(
+                id,
+                RpcError::new(codes::CONFIRMATION_REQUIRED, "Confirmation required"),
+            );
+        }
+        let result = match response.trim_start().chars().next() {
+            Some('{') | Some('[') => serde_json::from_str(response)
+                .unwrap_or_else(|_| Value::String(response.to_string())),
+            _ => Value::String(response.to_string()),
+        };
+        Self::success(id, result)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| {
+            r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32603,"message":"Internal error"}}"#
+                .to_string()
+        })
+    }
+}
+
+/// A server → client notification, e.g. `event` or `state_delta` (§3.3.6).
+pub fn notification(method: &str, params: Value) -> String {
+    json!({ "jsonrpc": JSONRPC_VERSION, "method": method, "params": params }).to_string()
+}
+
+/// Type of a method parameter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamKind {
+    #[default]
+    Text,
+    Integer,
+    Number,
+    Boolean,
+    Uuid,
+    /// An object or array; handed to the command as serialized JSON.
+    Json,
+    /// An array of strings; only valid as the last parameter.
+    List,
+}
+
+impl ParamKind {
+    fn schema(self) -> Value {
+        match self {
+            ParamKind::Text => json!({ "type": "string" }),
+            ParamKind::Integer => json!({ "type": "integer" }),
+            ParamKind::Number => json!({ "type": "number" }),
+            ParamKind::Boolean => json!({ "type": "boolean" }),
+            ParamKind::Uuid => json!({ "type": "string", "format": "uuid" }),
+            ParamKind::Json => json!({ "type": ["object", "array"] }),
+            ParamKind::List => json!({ "type": "array", "items": { "type": "string" } }),
+        }
+    }
+
+    /// The argument string a legacy handler expects for `value`.
+    fn encode(self, value: &Value) -> Option<Vec<String>> {
+        let single = match (self, value) {
+            (ParamKind::Text, Value::String(s)) => s.clone(),
+            (ParamKind::Uuid, Value::String(s)) if uuid::Uuid::parse_str(s).is_ok() => s.clone(),
+            (ParamKind::Integer, Value::Number(n)) if n.is_i64() || n.is_u64() => n.to_string(),
+            (ParamKind::Number, Value::Number(n)) => n.to_string(),
+            (ParamKind::Boolean, Value::Bool(b)) => b.to_string(),
+            (ParamKind::Json, Value::Object(_) | Value::Array(_)) => value.to_string(),
+            (ParamKind::List, Value::Array(items)) => {
+                return items
+                    .iter()
+                    .map(|v| v.as_str().map(str::to_string))
+                    .collect()
+            }
+            _ => return None,
+        };
+        Some(vec![single])
+    }
+}
+
+/// A named, typed method parameter. Module manifests declare these as
+/// `{ name = "...", type = "text", required = true }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Param {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub kind: ParamKind,
+    #[serde(default)]
+    pub required: bool,
+}
+
+impl Param {
+    pub fn new(name: impl Into<String>, kind: ParamKind, required: bool) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            required,
+        }
+    }
+}
+
+/// Check `value` (positional or by name) against `params` and turn it into
+/// the argument list the command handler takes. Omitted optional
+/// parameters are passed as empty strings when a later one is given.
+pub fn decode_params(
+    method: &str,
+    params: &[Param],
+    value: Option<&Value>,
+) -> Result<Vec<String>, RpcError> {
+    let values: Vec<Option<&Value>> = match value {
+        None => vec![None; params.len()],
+        Some(Value::Array(items)) => {
+            if items.len() > params.len() {
+                return Err(invalid_params(format!(
+                    "'{}' takes at most {} parameters",
+                    method,
+                    params.len()
+                )));
+            }
+            (0..params.len()).map(|i| items.get(i)).collect()
+        }
+        Some(Value::Object(map)) => {
+            if let Some(unknown) = map.keys().find(|k| !params.iter().any(|p| p.name == **k)) {
+                return Err(invalid_params(format!("Unknown parameter '{}'", unknown)));
+            }
+            params.iter().map(|p| map.get(&p.name)).collect()
+        }
+        Some(_) => return Err(invalid_params("params must be an array or object")),
+    };
+
+    let mut args = Vec::new();
+    let mut pending_gaps = 0;
+    for (param, value) in params.iter().zip(values) {
+        match value.filter(|v| !v.is_null()) {
+            None if param.required => {
+                return Err(invalid_params(format!(
+                    "Missing parameter '{}'",
+                    param.name
+                )))
+            }
+            None => pending_gaps += 1,
+            Some(v) => {
+                let encoded = match param.kind.encode(v) {
+                    Some(e) => e,
+                    None => {
+                        return Err(invalid_params(format!(
+                            "Parameter '{}' must be {}",
+                            param.name,
+                            param.kind.schema()
+                        )))
+                    }
+                };
+                args.extend(std::iter::repeat_n(String::new(), pending_gaps));
+                pending_gaps = 0;
+                args.extend(encoded);
+            }
+        }
+    }
+    Ok(args)
+}
+
+fn invalid_params(message: impl Into<String>) -> RpcError {
+    RpcError::new(codes::INVALID_PARAMS, message)
+}
+
+/// Methods answered by the remote connection itself rather than the
+/// command registry (§3.3.6, §12.3).
+pub fn connection_methods() -> Vec<CommandSpec> {
+    let connection = |name: &str, help: &str| {
+        CommandSpec::new(name, help)
+            .role(ParticipantRole::Viewer)
+            .source(CommandSource::Connection)
+    };
+    vec![
+        connection("auth", "Authenticate the connection with a device or portal token")
+            .param("token", ParamKind::Text),
+        connection("subscribe", "Add event bus topics").param("topics", ParamKind::List),
+        connection("unsubscribe", "Remove event bus topics").param("topics", ParamKind::List),
+        connection("subscriptions", "Topics this connection is subscribed to"),
+    ]
+}
+
+/// Returns this schema; answered by the `IpcHandler`.
+pub const DISCOVER: &str = "rpc.discover";
+
+pub fn connection_method(name: &str) -> Option<CommandSpec> {
+    connection_methods().into_iter().find(|m| m.name == name)
+}
+
+/// The OpenRPC document describing the commands in `registry`, the
+/// connection methods, error codes and notifications.
+pub fn schema(registry: &CommandRegistry) -> Value {
+    let describe = |spec: &CommandSpec| {
+        let params: Vec<Value> = spec
+            .params
+            .iter()
+            .map(|p| json!({ "name": p.name, "required": p.required, "schema": p.kind.schema() }))
+            .collect();
+        let mut method = json!({
+            "name": spec.name,
+            "summary": spec.help,
+            "paramStructure": "either",
+            "params": params,
+            "result": { "name": "result", "schema": {} },
+        });
+        match &spec.source {
+            CommandSource::Builtin => {
+                method["x-tos-scope"] = json!("dispatch");
+                method["x-tos-role"] = json!(format!("{:?}", spec.role));
+            }
+            CommandSource::Module { id } => {
+                method["x-tos-scope"] = json!("dispatch");
+                method["x-tos-role"] = json!(format!("{:?}", spec.role));
+                method["x-tos-module"] = json!(id);
+            }
+            CommandSource::Connection => method["x-tos-scope"] = json!("connection"),
+        }
+        method
+    };
+
+    let mut methods: Vec<Value> = registry.specs().map(describe).collect();
+    methods.extend(connection_methods().iter().map(describe));
+    methods.push(json!({
+        "name": DISCOVER,
+        "summary": "This document",
+        "paramStructure": "either",
+        "params": [],
+        "result": { "name": "schema", "schema": { "type": "object" } },
+        "x-tos-scope": "dispatch",
+        "x-tos-role": "Viewer",
+    }));
+
+    json!({
+        "openrpc": "1.2.6",
+        "info": {
+            "title": "TOS Brain IPC",
+            "version": PROTOCOL_VERSION,
+        },
+        "methods": methods,
+        "components": {
+            "errors": {
+                "ParseError": { "code": codes::PARSE_ERROR, "message": "Parse error" },
+                "InvalidRequest": { "code": codes::INVALID_REQUEST, "message": "Invalid request" },
+                "MethodNotFound": { "code": codes::METHOD_NOT_FOUND, "message": "Method not found" },
+                "InvalidParams": { "code": codes::INVALID_PARAMS, "message": "Invalid params" },
+                "InternalError": { "code": codes::INTERNAL_ERROR, "message": "Internal error" },
+                "CommandFailed": { "code": codes::COMMAND_FAILED, "message": "Command failed" },
+                "PermissionDenied": { "code": codes::PERMISSION_DENIED, "message": "Permission denied" },
+                "AuthRequired": { "code": codes::AUTH_REQUIRED, "message": "Authentication required" },
+                "ConfirmationRequired": { "code": codes::CONFIRMATION_REQUIRED, "message": "Confirmation required" },
+            }
+        },
+        "x-tos-notifications": [
+            {
+                "name": "event",
+                "params": { "type": "object", "required": ["topic", "payload"] },
+            },
+            {
+                "name": "state_delta",
+                "params": { "type": "object", "required": ["kind", "version"] },
+            },
+        ],
+    })
+}
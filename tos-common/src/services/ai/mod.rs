@@ -606,6 +606,7 @@ impl AiService {
                 text: format!("✦ [AI] Context Signal: Activated {} skill.", skill),
                 priority: 1,
                 timestamp: chrono::Local::now(),
+                source: None,
             });
         }
     }
@@ -0,0 +1,186 @@
+use crate::collaboration::ParticipantRole;
+use crate::config::{RemoteConfig, TosConfig};
+use crate::services::PortalService;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// How a connection proved who it is (§12.3).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum AuthMethod {
+    /// In-process callers, the UDS socket and trusted loopback clients.
+    Local,
+    DeviceToken,
+    PortalToken {
+        sector_id: Uuid,
+    },
+    ClientCert,
+}
+
+/// The identity a remote connection is bound to for its lifetime.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClientIdentity {
+    pub name: String,
+    pub role: ParticipantRole,
+    #[serde(flatten)]
+    pub method: AuthMethod,
+}
+
+impl ClientIdentity {
+    /// The local user, who may run every command.
+    pub fn local() -> Self {
+        Self {
+            name: "local".to_string(),
+            role: ParticipantRole::CoOwner,
+            method: AuthMethod::Local,
+        }
+    }
+}
+
+struct Credential {
+    name: String,
+    digest: [u8; 32],
+    role: ParticipantRole,
+}
+
+/// §12.3: Authenticates clients of the remote IPC ports.
+///
+/// Accepts pre-shared device tokens and pinned client certificates from
+/// `[remote]` in `tos.toml`, and live Web Portal tokens from
+/// [`PortalService`]. Secrets are kept and compared as SHA-256 digests.
+pub struct AuthService {
+    device_tokens: Vec<Credential>,
+    client_certs: Vec<Credential>,
+    portal: Arc<PortalService>,
+    portal_role: ParticipantRole,
+    trust_loopback: bool,
+}
+
+impl AuthService {
+    pub fn new(portal: Arc<PortalService>) -> Self {
+        Self::with_config(&TosConfig::default(), portal)
+    }
+
+    pub fn with_config(config: &TosConfig, portal: Arc<PortalService>) -> Self {
+        let RemoteConfig {
+            device_tokens,
+            client_certs,
+            portal_role,
+            trust_loopback,
+            ..
+        } = &config.remote;
+
+        let device_tokens = device_tokens
+            .iter()
+            .map(|t| Credential {
+                name: t.name.clone(),
+                digest: sha256(t.token.as_bytes()),
+                role: t.role,
+            })
+            .collect();
+        let client_certs = client_certs
+            .iter()
+            .filter_map(|c| {
+                let hex_digest: String = c.sha256.chars().filter(|ch| *ch != ':').collect();
+                match hex::decode(hex_digest).ok().and_then(|b| b.try_into().ok()) {
+                    Some(digest) => Some(Credential {
+                        name: c.name.clone(),
+                        digest,
+                        role: c.role,
+                    }),
+                    None => {
+                        tracing::warn!("[AUTH] Ignoring client cert '{}': bad sha256", c.name);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        Self {
+            device_tokens,
+            client_certs,
+            portal,
+            portal_role: *portal_role,
+            trust_loopback: *trust_loopback,
+        }
+    }
+
+    /// Resolve an `auth:<token>` credential.
+    pub fn authenticate_token(&self, token: &str) -> Option<ClientIdentity> {
+        let token = token.trim();
+        if token.is_empty() {
+            return None;
+        }
+        let digest = sha256(token.as_bytes());
+        if let Some(cred) = find(&self.device_tokens, &digest) {
+            return Some(ClientIdentity {
+                name: cred.name.clone(),
+                role: cred.role,
+                method: AuthMethod::DeviceToken,
+            });
+        }
+        self.portal
+            .validate_token(token)
+            .map(|sector_id| ClientIdentity {
+                name: format!("portal:{}", sector_id),
+                role: self.portal_role,
+                method: AuthMethod::PortalToken { sector_id },
+            })
+    }
+
+    /// Resolve a DER client certificate presented during the TLS handshake.
+    pub fn authenticate_certificate(&self, der: &[u8]) -> Option<ClientIdentity> {
+        find(&self.client_certs, &sha256(der)).map(|cred| ClientIdentity {
+            name: cred.name.clone(),
+            role: cred.role,
+            method: AuthMethod::ClientCert,
+        })
+    }
+
+    /// Identity granted before any credential is presented.
+    pub fn authenticate_peer(&self, addr: &std::net::SocketAddr) -> Option<ClientIdentity> {
+        let ip = match addr.ip() {
+            std::net::IpAddr::V6(v6) => v6
+                .to_ipv4_mapped()
+                .map(std::net::IpAddr::V4)
+                .unwrap_or(std::net::IpAddr::V6(v6)),
+            ip => ip,
+        };
+        if self.trust_loopback && ip.is_loopback() {
+            Some(ClientIdentity::local())
+        } else {
+            None
+        }
+    }
+
+    /// Whether the TLS layer should ask clients for a certificate at all.
+    pub fn has_pinned_certificates(&self) -> bool {
+        !self.client_certs.is_empty()
+    }
+}
+
+/// Colon-separated hex SHA-256, the form used by `client_certs` entries.
+pub fn certificate_fingerprint(der: &[u8]) -> String {
+    sha256(der)
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+fn sha256(bytes: &[u8]) -> [u8; 32] {
+    Sha256::digest(bytes).into()
+}
+
+/// Look up a digest without returning early on the first differing byte.
+fn find<'a>(creds: &'a [Credential], digest: &[u8; 32]) -> Option<&'a Credential> {
+    creds.iter().find(|cred| {
+        cred.digest
+            .iter()
+            .zip(digest)
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+    })
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+/// One message on the Brain's event bus (§3.3.6).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BusEvent {
+    /// e.g. `hub:<id>:output`, `sector:<id>`, `system_log`, `confirmation`, `state`.
+    pub topic: String,
+    pub payload: serde_json::Value,
+}
+
+/// §3.3.6: In-process publish/subscribe bus.
+///
+/// PTY readers, `IpcHandler` mutations and services publish here; remote
+/// connections subscribe and forward matching topics. The channel is
+/// bounded: a subscriber that falls more than [`EventBus::CAPACITY`] events
+/// behind receives `Lagged` and is expected to resync from a state delta.
+pub struct EventBus {
+    tx: broadcast::Sender<BusEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub const CAPACITY: usize = 1024;
+
+    /// Topic used to wake state-delta pushers; the payload carries `version`.
+    pub const STATE_TOPIC: &'static str = "state";
+
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(Self::CAPACITY);
+        Self { tx }
+    }
+
+    /// Publish to all current subscribers. A no-op when nobody listens.
+    pub fn publish(&self, topic: impl Into<String>, payload: serde_json::Value) {
+        let _ = self.tx.send(BusEvent {
+            topic: topic.into(),
+            payload,
+        });
+    }
+
+    /// Signal that the state reached `version`.
+    pub fn publish_state(&self, version: u64) {
+        self.publish(Self::STATE_TOPIC, serde_json::json!({ "version": version }));
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<BusEvent> {
+        self.tx.subscribe()
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.tx.receiver_count()
+    }
+}
+
+/// Match a topic against a subscription pattern. A trailing `*` matches any
+/// suffix, so `hub:*` covers every hub topic and `*` covers everything.
+pub fn topic_matches(pattern: &str, topic: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => topic.starts_with(prefix),
+        None => pattern == topic,
+    }
+}
@@ -1,44 +1,194 @@
 //! Heuristic Service — interface for predictive intelligence.
 //!
 //! This service communicates with the `tos-heuristicd` daemon to provide
-//! real-time suggestions, typo corrections, and other smart features.
+//! real-time suggestions, typo corrections, and other smart features. The
+//! daemon learns from the finished command blocks the Brain feeds it (§28).
 
 use crate::services::registry::ServiceRegistry;
+use crate::services::search_feed::SearchFeed;
+use crate::TosState;
+use chrono::{DateTime, Local};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use uuid::Uuid;
+
+/// One command the heuristic model learns from.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HistoryEntry {
+    /// Stable id, so an entry sent twice is learned once. Empty = no dedupe.
+    #[serde(default)]
+    pub id: String,
+    pub command: String,
+    /// Directory the command ran in, empty if unknown.
+    #[serde(default)]
+    pub cwd: String,
+    #[serde(default)]
+    pub sector_id: Option<Uuid>,
+    #[serde(default)]
+    pub exit_status: Option<i32>,
+    /// Unix time the command finished.
+    pub ts: i64,
+}
 
 pub struct HeuristicService {
     registry: Arc<Mutex<ServiceRegistry>>,
+    /// Finish time of the newest block collected, per hub.
+    marks: Mutex<HashMap<Uuid, DateTime<Local>>>,
+    /// Entries the daemon has not accepted yet, oldest first.
+    pending: Mutex<Vec<HistoryEntry>>,
+    /// The first-run history import was handed to the daemon.
+    first_import: AtomicBool,
 }
 
 impl HeuristicService {
+    /// Entries sent per `history_append` request.
+    const HISTORY_BATCH: usize = 200;
+    /// Entries kept for a daemon that is down; the oldest are dropped.
+    const MAX_PENDING: usize = 2000;
+
     pub fn new(registry: Arc<Mutex<ServiceRegistry>>) -> Self {
-        Self { registry }
+        Self {
+            registry,
+            marks: Mutex::new(HashMap::new()),
+            pending: Mutex::new(Vec::new()),
+            first_import: AtomicBool::new(false),
+        }
     }
 
-    /// Query the heuristic daemon for suggestions based on a keyword and CWD.
-    pub async fn query(&self, keyword: &str, cwd: &str) -> anyhow::Result<String> {
-        let port = {
-            let reg = self.registry.lock().unwrap();
-            reg.port_of("tos-heuristicd")
+    /// Query the heuristic daemon for suggestions based on a keyword, the
+    /// CWD and the sector the prompt belongs to.
+    pub async fn query(
+        &self,
+        keyword: &str,
+        cwd: &str,
+        sector_id: Option<Uuid>,
+    ) -> anyhow::Result<String> {
+        let sector = sector_id.map(|id| id.to_string()).unwrap_or_default();
+        self.remote_call("heuristic_query", &format!("{};{};{}", keyword, cwd, sector))
+            .await
+    }
+
+    /// The commands that finished since the last call. While incognito
+    /// (§19.4) the marks still advance and nothing is returned.
+    pub fn collect_history(&self, state: &TosState) -> Vec<HistoryEntry> {
+        let mut marks = match self.marks.lock() {
+            Ok(m) => m,
+            Err(_) => return Vec::new(),
+        };
+        let private = SearchFeed::is_private(state);
+        let mut entries = Vec::new();
+
+        for sector in &state.sectors {
+            for hub in &sector.hubs {
+                let mark = marks.get(&hub.id).copied();
+                for block in &hub.command_blocks {
+                    let finished = match block.finished_at {
+                        Some(t) if mark.is_none_or(|m| t > m) => t,
+                        _ => continue,
+                    };
+                    marks.insert(hub.id, finished);
+                    if private || block.command.trim().is_empty() {
+                        continue;
+                    }
+                    entries.push(HistoryEntry {
+                        id: block.id.to_string(),
+                        command: block.command.clone(),
+                        cwd: block.cwd.to_string_lossy().to_string(),
+                        sector_id: Some(sector.id),
+                        exit_status: block.exit_status,
+                        ts: finished.timestamp(),
+                    });
+                }
+            }
         }
-        .ok_or_else(|| anyhow::anyhow!("Heuristic service not registered"))?;
+        // Closed hubs have nothing more to give.
+        marks.retain(|id, _| {
+            state
+                .sectors
+                .iter()
+                .any(|s| s.hubs.iter().any(|h| h.id == *id))
+        });
+        entries
+    }
 
-        let addr = format!("127.0.0.1:{}", port);
-        let mut stream = tokio::net::TcpStream::connect(addr).await?;
+    /// Send `entries`, after any the daemon missed earlier. Whatever it
+    /// does not accept is kept for the next push.
+    pub async fn push_history(&self, entries: Vec<HistoryEntry>) {
+        let mut queue = match self.pending.lock() {
+            Ok(mut pending) => std::mem::take(&mut *pending),
+            Err(_) => return,
+        };
+        queue.extend(entries);
 
-        let request = format!("heuristic_query:{};{}\n", keyword, cwd);
-        stream.write_all(request.as_bytes()).await?;
+        while !queue.is_empty() {
+            let batch: Vec<HistoryEntry> = queue
+                .drain(..queue.len().min(Self::HISTORY_BATCH))
+                .collect();
+            let payload = serde_json::to_string(&batch).unwrap_or_default();
+            match self.remote_call("history_append", &payload).await {
+                Ok(reply) if !reply.starts_with("ERROR") => {}
+                _ => {
+                    queue.splice(0..0, batch);
+                    break;
+                }
+            }
+        }
 
-        let mut reader = BufReader::new(stream);
-        let mut response = String::new();
-        reader.read_line(&mut response).await?;
+        if let Ok(mut pending) = self.pending.lock() {
+            queue.append(&mut pending);
+            let excess = queue.len().saturating_sub(Self::MAX_PENDING);
+            queue.drain(..excess);
+            *pending = queue;
+        }
+    }
 
-        Ok(response.trim().to_string())
+    /// Why shell history may not be imported now, if it may not: while
+    /// incognito (§19.4) or with `tos.privacy.history_import` off.
+    pub fn import_blocked(state: &TosState) -> Option<&'static str> {
+        if SearchFeed::is_private(state) {
+            return Some("incognito mode is active");
+        }
+        let setting = state
+            .settings
+            .resolve("tos.privacy.history_import", None, None);
+        if setting.as_deref() == Some("false") {
+            return Some("tos.privacy.history_import is off");
+        }
+        None
+    }
+
+    /// Import shell history into the model: `source` is a shell name
+    /// (`bash`, `zsh`, `fish`), a history file, or empty for every shell.
+    /// Replies with the daemon's JSON report.
+    pub async fn import_history(&self, source: &str) -> anyhow::Result<String> {
+        self.remote_call("history_import", source).await
+    }
+
+    /// Whether to start the first-run import now; true at most once until
+    /// [`Self::first_run_import`] fails.
+    pub fn claim_first_import(&self, state: &TosState) -> bool {
+        Self::import_blocked(state).is_none() && !self.first_import.swap(true, Ordering::SeqCst)
     }
 
-    /// Record a command to the history for future suggestions.
-    pub async fn record_history(&self, command: &str) -> anyhow::Result<()> {
+    /// Ask the daemon to import shell history if its model never has.
+    /// Retried at the next claim if the daemon could not be reached.
+    pub async fn first_run_import(&self) {
+        let request = serde_json::json!({ "first_run": true }).to_string();
+        match self.remote_call("history_import", &request).await {
+            Ok(reply) if !reply.starts_with("ERROR") => {}
+            _ => self.first_import.store(false, Ordering::SeqCst),
+        }
+    }
+
+    /// The learned model as JSON, or written to `path` on the daemon's
+    /// host if one is given.
+    pub async fn export_model(&self, path: &str) -> anyhow::Result<String> {
+        self.remote_call("model_export", path).await
+    }
+
+    async fn remote_call(&self, cmd: &str, payload: &str) -> anyhow::Result<String> {
         let port = {
             let reg = self.registry.lock().unwrap();
             reg.port_of("tos-heuristicd")
@@ -48,9 +198,13 @@ impl HeuristicService {
         let addr = format!("127.0.0.1:{}", port);
         let mut stream = tokio::net::TcpStream::connect(addr).await?;
 
-        let request = format!("history_append:{}\n", command);
+        let request = format!("{}:{}\n", cmd, payload);
         stream.write_all(request.as_bytes()).await?;
-        
-        Ok(())
+
+        let mut reader = BufReader::new(stream);
+        let mut response = String::new();
+        reader.read_line(&mut response).await?;
+
+        Ok(response.trim().to_string())
     }
 }
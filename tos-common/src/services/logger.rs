@@ -1,9 +1,195 @@
 // use crate::TerminalLine; // Replaced by IPC state append
+use std::cell::RefCell;
 use std::sync::{Arc, Mutex};
 // use chrono::Local; // Replaced by IPC state append
 use crate::ipc::IpcDispatcher;
 use crate::services::audio::AudioService;
 
+/// The schema version [`LogRecord::new`] stamps. Records written before
+/// the field existed read back as version 0.
+pub const LOG_RECORD_VERSION: u32 = 1;
+
+/// How severe a log record is (§19.1), in the order of the 0–3 priorities
+/// the system log and earcons use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Debug,
+    #[default]
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn from_priority(priority: u8) -> Self {
+        match priority {
+            0 => Severity::Debug,
+            1 => Severity::Info,
+            2 => Severity::Warning,
+            _ => Severity::Critical,
+        }
+    }
+
+    pub fn priority(self) -> u8 {
+        self as u8
+    }
+
+    /// A level as older records and other tools write it: a priority
+    /// (`"1"`–`"3"`) or a name. Unknown names, such as the `"ai"` of early
+    /// AI exchanges, are [`Severity::Info`].
+    pub fn parse(level: &str) -> Self {
+        if let Ok(n) = level.trim().parse() {
+            return Self::from_priority(n);
+        }
+        match level.trim().to_ascii_lowercase().as_str() {
+            "trace" | "debug" => Severity::Debug,
+            "warn" | "warning" => Severity::Warning,
+            "error" | "critical" | "fatal" => Severity::Critical,
+            _ => Severity::Info,
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Severity {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Level {
+            Priority(u64),
+            Name(String),
+        }
+        Ok(match Level::deserialize(deserializer)? {
+            Level::Priority(n) => Self::from_priority(n.min(u8::MAX as u64) as u8),
+            Level::Name(name) => Self::parse(&name),
+        })
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Severity::Debug => "debug",
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        };
+        f.write_str(name)
+    }
+}
+
+/// One record of the unified log, as `tos-loggerd` stores and streams it
+/// (§19.1).
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LogRecord {
+    /// Schema version; see [`LOG_RECORD_VERSION`].
+    #[serde(default)]
+    pub v: u32,
+    pub ts: i64,
+    pub level: Severity,
+    /// The component that wrote it: `brain`, a daemon, a behavior id...
+    pub source: String,
+    /// What kind of record: `log`, `audit`, `ai_exchange`, `crash`...
+    pub event: String,
+    /// The human-readable message.
+    pub data: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sector_id: Option<uuid::Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hub_id: Option<uuid::Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pane_id: Option<uuid::Uuid>,
+    /// Shared by every record of one request, across the Brain and daemons.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    /// Structured detail beyond the message.
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl LogRecord {
+    /// A record stamped now at the current schema version.
+    pub fn new(level: Severity, source: &str, event: &str, data: &str) -> Self {
+        Self {
+            v: LOG_RECORD_VERSION,
+            ts: chrono::Local::now().timestamp(),
+            level,
+            source: source.to_string(),
+            event: event.to_string(),
+            data: data.to_string(),
+            sector_id: None,
+            hub_id: None,
+            pane_id: None,
+            correlation_id: None,
+            fields: serde_json::Map::new(),
+        }
+    }
+
+    pub fn with_field(mut self, key: &str, value: impl Into<serde_json::Value>) -> Self {
+        self.fields.insert(key.to_string(), value.into());
+        self
+    }
+
+    pub fn with_correlation_id(mut self, id: &str) -> Self {
+        self.correlation_id = Some(id.to_string());
+        self
+    }
+
+    /// Fill in the placement and correlation the record does not carry.
+    pub fn with_context(mut self, context: &LogContext) -> Self {
+        self.sector_id = self.sector_id.or(context.sector_id);
+        self.hub_id = self.hub_id.or(context.hub_id);
+        self.pane_id = self.pane_id.or(context.pane_id);
+        if self.correlation_id.is_none() {
+            self.correlation_id = context.correlation_id.clone();
+        }
+        self
+    }
+}
+
+thread_local! {
+    static CONTEXT: RefCell<LogContext> = RefCell::new(LogContext::default());
+}
+
+/// Where the records logged while the Brain handles one request belong:
+/// the request's id and the sector, hub and pane it acts on (§19.1).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogContext {
+    pub correlation_id: Option<String>,
+    pub sector_id: Option<uuid::Uuid>,
+    pub hub_id: Option<uuid::Uuid>,
+    pub pane_id: Option<uuid::Uuid>,
+}
+
+impl LogContext {
+    /// The context of the request being handled on this thread.
+    pub fn current() -> Self {
+        CONTEXT.with(|c| c.borrow().clone())
+    }
+
+    /// Run `f` with this context, on top of the current one, applied to
+    /// every record logged on this thread until it returns.
+    pub fn scope<T>(self, f: impl FnOnce() -> T) -> T {
+        struct Restore(LogContext);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CONTEXT.with(|c| *c.borrow_mut() = std::mem::take(&mut self.0));
+            }
+        }
+
+        let outer = Self::current();
+        let context = Self {
+            correlation_id: self.correlation_id.or(outer.correlation_id.clone()),
+            sector_id: self.sector_id.or(outer.sector_id),
+            hub_id: self.hub_id.or(outer.hub_id),
+            pane_id: self.pane_id.or(outer.pane_id),
+        };
+        CONTEXT.with(|c| *c.borrow_mut() = context);
+        let _restore = Restore(outer);
+        f()
+    }
+}
+
 pub struct LoggerService {
     ipc: Arc<Mutex<Option<Arc<dyn IpcDispatcher>>>>,
     audio: Arc<Mutex<Option<Arc<AudioService>>>>,
@@ -43,23 +229,37 @@ impl LoggerService {
         *lock = Some(audio);
     }
 
+    /// The port `tos-loggerd` registered, or the fixed one it used before
+    /// dynamic registration.
+    fn port(&self) -> u16 {
+        self.registry
+            .as_ref()
+            .and_then(|r| r.lock().unwrap().port_of("tos-loggerd"))
+            .unwrap_or(7003) // Fallback to hardcoded for Alpha-2.1/Beta-0 transition
+    }
+
     /// Log an event to the unified system storage.
     pub fn log(&self, text: &str, priority: u8) {
         self.log_event(text, priority, "system");
     }
 
-    /// Log a structured event with a specific source.
+    /// Log a plain message from `source`.
     pub fn log_event(&self, text: &str, priority: u8, source: &str) {
+        self.record(LogRecord::new(
+            Severity::from_priority(priority),
+            source,
+            "log",
+            text,
+        ));
+    }
+
+    /// Submit a structured record to the unified log (§19.1). Fields the
+    /// record leaves empty are taken from the current [`LogContext`].
+    pub fn record(&self, record: LogRecord) {
+        let record = record.with_context(&LogContext::current());
         // §19.1: Remote Log Submission (tos-loggerd)
-        let port = self
-            .registry
-            .as_ref()
-            .and_then(
-                |r: &Arc<Mutex<crate::services::registry::ServiceRegistry>>| {
-                    r.lock().unwrap().port_of("tos-loggerd")
-                },
-            )
-            .unwrap_or(7003); // Fallback to hardcoded for Alpha-2.1/Beta-0 transition
+        let port = self.port();
+        let json = serde_json::to_string(&record).unwrap_or_default();
 
         let addr = format!("127.0.0.1:{}", port);
         if let Ok(mut stream) = std::net::TcpStream::connect_timeout(
@@ -67,37 +267,56 @@ impl LoggerService {
             std::time::Duration::from_millis(50),
         ) {
             use std::io::Write;
-            let _ = stream.write_all(format!("log:{};{};{}\n", text, priority, source).as_bytes());
+            let _ = stream.write_all(format!("record:{}\n", json).as_bytes());
         } else {
             // Fallback: Local IPC notification for state append
             if let Some(ipc) = &*self.ipc.lock().unwrap() {
-                let _ = ipc.dispatch(&format!("system_log_append:{};{}", priority, text));
+                let _ = ipc.dispatch(&format!("system_log_record:{}", json));
             }
         }
 
         // Multi-sensory feedback based on priority level
         if let Some(audio) = &*self.audio.lock().unwrap() {
-            if priority >= 3 {
+            if record.level >= Severity::Critical {
                 audio.play_earcon("priority_high_alert");
-            } else if priority == 2 {
+            } else if record.level == Severity::Warning {
                 audio.play_earcon("priority_mid_alert");
             }
         }
 
-        tracing::info!("[LOG P{}] [{}] {}", priority, source, text);
+        tracing::info!(
+            "[LOG P{}] [{}] {}",
+            record.level.priority(),
+            record.source,
+            record.data
+        );
     }
 
-    /// Query system logs via the Log Service (§3.3.4).
-    pub fn query(&self, surface: Option<&str>, limit: Option<usize>) -> anyhow::Result<String> {
-        let port = self
-            .registry
-            .as_ref()
-            .and_then(
-                |r: &Arc<Mutex<crate::services::registry::ServiceRegistry>>| {
-                    r.lock().unwrap().port_of("tos-loggerd")
-                },
-            )
-            .unwrap_or(7003);
+    /// Query system logs via the Log Service (§3.3.4). `query` carries the
+    /// filters (`source`, `since`, `until`, `min_level`, `event`, `text`),
+    /// `limit` and the `cursor` of the previous page.
+    pub fn query(&self, query: &serde_json::Value) -> anyhow::Result<String> {
+        self.request(&format!("query:{}", query))
+    }
+
+    /// Crash groups, most recently seen first (§19.5).
+    pub fn crash_list(&self) -> anyhow::Result<String> {
+        self.request("crashes:")
+    }
+
+    /// One crash report by id, or the latest of a signature group.
+    pub fn crash_show(&self, key: &str) -> anyhow::Result<String> {
+        self.request(&format!("crash_show:{}", key))
+    }
+
+    /// Delete the reports of a signature group, or `all` of them.
+    pub fn crash_purge(&self, signature: &str) -> anyhow::Result<String> {
+        self.request(&format!("crash_purge:{}", signature))
+    }
+
+    /// Send one request line to `tos-loggerd` and return its reply.
+    fn request(&self, line: &str) -> anyhow::Result<String> {
+        let port = self.port();
 
         let addr = format!("127.0.0.1:{}", port);
         let mut stream = std::net::TcpStream::connect_timeout(
@@ -106,12 +325,7 @@ impl LoggerService {
         )?;
         use std::io::{BufRead, BufReader, Write};
 
-        let query = serde_json::json!({
-            "surface": surface,
-            "limit": limit
-        });
-
-        let _ = stream.write_all(format!("query:{}\n", query).as_bytes());
+        let _ = stream.write_all(format!("{}\n", line).as_bytes());
         let mut reader = BufReader::new(stream);
         let mut response = String::new();
         reader.read_line(&mut response)?;
@@ -121,7 +335,12 @@ impl LoggerService {
     /// Deep Inspection Audit Log for security auditing.
     pub fn audit_log(&self, actor: &str, action: &str, result: &str) {
         let msg = format!("AUDIT [{}]: {} -> {}", actor, action, result);
-        self.log_event(&msg, 3, "security"); // Forced high priority
+        self.record(
+            LogRecord::new(Severity::Critical, "security", "audit", &msg) // Forced high priority
+                .with_field("actor", actor)
+                .with_field("action", action)
+                .with_field("result", result),
+        );
 
         // Final implementation would sign this entry cryptographically
         tracing::warn!("SECURITY AUDIT ENTRY: {}", msg);
@@ -129,43 +348,83 @@ impl LoggerService {
 
     /// Archive an AI interaction pair (§7.4).
     pub fn archive_ai(&self, behavior_id: &str, prompt: &str, response: &str) {
-        let port = self
-            .registry
-            .as_ref()
-            .and_then(|r| r.lock().unwrap().port_of("tos-loggerd"))
-            .unwrap_or(7003);
+        self.record(
+            LogRecord::new(Severity::Info, behavior_id, "ai_exchange", prompt)
+                .with_field("prompt", prompt)
+                .with_field("response", response),
+        );
+    }
+
+    /// Automated crash dump collection (§6.10). Fails if `tos-loggerd`
+    /// cannot be reached, so the sender keeps the report to retry.
+    pub fn crash_report(&self, payload: &str) -> anyhow::Result<()> {
+        let port = self.port();
+        let payload = match LogContext::current().correlation_id {
+            Some(id) => {
+                let mut report = crate::daemon::crash::CrashReport::parse(payload);
+                if report.correlation_id.is_empty() {
+                    report.correlation_id = id;
+                }
+                serde_json::to_string(&report)?
+            }
+            None => payload.to_string(),
+        };
 
         let addr = format!("127.0.0.1:{}", port);
-        if let Ok(mut stream) = std::net::TcpStream::connect_timeout(
+        let mut stream = std::net::TcpStream::connect_timeout(
             &addr.parse().unwrap(),
             std::time::Duration::from_millis(50),
-        ) {
-            use std::io::Write;
-            let payload = serde_json::json!({
-                "behavior_id": behavior_id,
-                "prompt": prompt,
-                "response": response
-            });
-            let _ =
-                stream.write_all(format!("archive_ai:{}\n", payload).as_bytes());
+        )?;
+        use std::io::Write;
+        stream.write_all(format!("crash:{}\n", payload).as_bytes())?;
+        Ok(())
+    }
+
+    /// How long `follow` waits before subscribing again.
+    pub const FOLLOW_RETRY: std::time::Duration = std::time::Duration::from_secs(2);
+
+    /// §19.2: Hold one subscription to `tos-loggerd` for as long as the
+    /// Brain runs, handing each record that passes `filter` (a `query`
+    /// filter without paging) to `on_record`. Reconnects after
+    /// [`Self::FOLLOW_RETRY`] whenever the daemon is unreachable or restarts.
+    pub async fn follow(&self, filter: serde_json::Value, mut on_record: impl FnMut(LogRecord)) {
+        loop {
+            if let Err(e) = Self::follow_once(self.port(), &filter, &mut on_record).await {
+                tracing::debug!("[LOGGER] tos-loggerd subscription ended: {}", e);
+            }
+            tokio::time::sleep(Self::FOLLOW_RETRY).await;
         }
     }
 
-    /// Automated crash dump collection (§6.10).
-    pub fn crash_report(&self, payload: &str) {
-        let port = self
-            .registry
-            .as_ref()
-            .and_then(|r| r.lock().unwrap().port_of("tos-loggerd"))
-            .unwrap_or(7003);
+    async fn follow_once(
+        port: u16,
+        filter: &serde_json::Value,
+        on_record: &mut impl FnMut(LogRecord),
+    ) -> anyhow::Result<()> {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 
-        let addr = format!("127.0.0.1:{}", port);
-        if let Ok(mut stream) = std::net::TcpStream::connect_timeout(
-            &addr.parse().unwrap(),
-            std::time::Duration::from_millis(50),
-        ) {
-            use std::io::Write;
-            let _ = stream.write_all(format!("crash:{}\n", payload).as_bytes());
+        let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", port)).await?;
+        stream
+            .write_all(format!("subscribe:{}\n", filter).as_bytes())
+            .await?;
+        let mut lines = BufReader::new(stream).lines();
+        match lines.next_line().await? {
+            Some(reply) if reply.starts_with("OK") => {}
+            reply => anyhow::bail!("Subscription refused: {}", reply.unwrap_or_default()),
+        }
+        while let Some(line) = lines.next_line().await? {
+            if let Some(json) = line.strip_prefix("record:") {
+                match serde_json::from_str::<LogRecord>(json) {
+                    Ok(record) => on_record(record),
+                    Err(e) => tracing::warn!("[LOGGER] Malformed log record: {}", e),
+                }
+            } else if let Some(missed) = line.strip_prefix("lagged:") {
+                tracing::warn!(
+                    "[LOGGER] Fell behind tos-loggerd; {} records missed",
+                    missed
+                );
+            }
         }
+        Ok(())
     }
 }
@@ -54,6 +54,14 @@ pub struct ModuleManifest {
     // §1.9: Audio Specifics
     pub audio: Option<AudioConfig>,
 
+    // §3.3.8: IPC commands the module adds to the Brain's registry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commands: Option<Vec<ModuleCommandConfig>>,
+
+    // §28: Completion spec files for tos-heuristicd, relative to the module
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completions: Option<Vec<String>>,
+
     // The Ed25519 cryptographic signature of the manifest contents
     pub signature: Option<String>,
 }
@@ -105,6 +113,22 @@ pub struct ExecutableConfig {
     pub args: Vec<String>,
 }
 
+/// A `[[commands]]` entry: registered as `<module_id>.<name>`, it runs
+/// `exec` (relative to the module directory) with `args` and then the
+/// caller's arguments.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ModuleCommandConfig {
+    pub name: String,
+    pub help: Option<String>,
+    pub exec: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub params: Vec<crate::rpc::Param>,
+    /// Defaults to Operator.
+    pub role: Option<crate::collaboration::ParticipantRole>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct LspConfig {
     pub command: String,
@@ -439,6 +463,8 @@ mod tests {
             lsp: None,
             bezel: None,
             audio: None,
+            commands: None,
+            completions: None,
             signature: None,
         };
 
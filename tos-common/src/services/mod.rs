@@ -1,15 +1,19 @@
 pub mod accessibility;
 pub mod ai;
 pub mod audio;
+pub mod auth;
 pub mod capture;
+pub mod events;
 pub mod haptic;
 pub mod heuristic;
 pub mod logger;
 pub mod marketplace;
 pub mod portal;
 pub mod priority;
+pub mod priority_rules;
 pub mod registry;
 pub mod search;
+pub mod search_feed;
 pub mod session;
 pub mod settings;
 pub mod trust;
@@ -21,7 +25,9 @@ pub mod timeline;
 pub use accessibility::AccessibilityService;
 pub use ai::AiService;
 pub use audio::AudioService;
+pub use auth::AuthService;
 pub use capture::CaptureService;
+pub use events::EventBus;
 pub use haptic::HapticService;
 pub use heuristic::HeuristicService;
 pub use logger::LoggerService;
@@ -50,6 +56,7 @@ pub struct ServiceManager {
     pub search: Arc<SearchService>,
     pub haptic: Arc<HapticService>,
     pub portal: Arc<PortalService>,
+    pub auth: Arc<AuthService>,
     pub priority: Arc<PriorityService>,
     pub registry: Arc<Mutex<ServiceRegistry>>,
     pub session: Arc<SessionService>,
@@ -61,6 +68,7 @@ pub struct ServiceManager {
     pub ssh: Arc<SshService>,
     pub bezel: Arc<BezelService>,
     pub timeline: Arc<TimelineService>,
+    pub events: Arc<EventBus>,
 }
 
 impl Default for ServiceManager {
@@ -90,9 +98,10 @@ impl ServiceManager {
         let (audio_svc, audio_warning) = AudioService::new();
         let audio = Arc::new(audio_svc);
         let ai = Arc::new(AiService::new());
-        let search = Arc::new(SearchService::new(registry.clone()));
+        let search = Arc::new(SearchService::with_config(registry.clone(), config));
         let haptic = Arc::new(HapticService::new());
         let portal = Arc::new(PortalService::new());
+        let auth = Arc::new(AuthService::with_config(config, portal.clone()));
         let priority = Arc::new(PriorityService::new(registry.clone()));
 
         let session = Arc::new(SessionService::with_config(registry.clone(), config));
@@ -108,6 +117,7 @@ impl ServiceManager {
         let ssh = Arc::new(SshService::new());
         let bezel = Arc::new(BezelService::new());
         let timeline = Arc::new(TimelineService::new(100)); // Last 100 states
+        let events = Arc::new(EventBus::new());
 
         // Establish cross-service dependencies
         logger.set_audio_service(audio.clone());
@@ -128,6 +138,7 @@ impl ServiceManager {
             search,
             haptic,
             portal,
+            auth,
             priority,
             registry: registry.clone(),
             session,
@@ -139,6 +150,7 @@ impl ServiceManager {
             ssh,
             bezel,
             timeline,
+            events,
         }
     }
     pub fn set_ipc(&self, ipc: std::sync::Arc<dyn crate::ipc::IpcDispatcher>) {
@@ -1,13 +1,319 @@
+//! Sector priority scoring (Visual Design §1.2).
+//!
+//! The Brain gathers each sector's [`SectorSignals`] and asks
+//! `tos-priorityd` to score them, which adds the CPU and memory of the
+//! processes spawned from the sector's shells. Without the daemon the same
+//! [`score`] runs in the Brain. Every score carries its factors, each with
+//! the reason it contributed, so the Face can explain a rank. User rules
+//! ([`priority_rules`](super::priority_rules)) then adjust or pin the rank.
+
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
+use super::priority_rules::RuleHit;
+
+/// How far back failures and error lines count.
+pub const SIGNAL_WINDOW_SECS: i64 = 600;
+/// Time for the focus factor to halve after the sector loses focus.
+pub const FOCUS_HALF_LIFE_SECS: f32 = 300.0;
+
+/// Factor names and weights; the weights sum to 1.
+pub const WEIGHTS: &[(&str, f32)] = &[
+    ("focus", 0.25),
+    ("failures", 0.20),
+    ("errors", 0.15),
+    ("activity", 0.15),
+    ("confirmation", 0.15),
+    ("collaboration", 0.10),
+];
+
+/// What the Brain knows about one sector when it asks for a score.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SectorSignals {
+    pub sector_id: Uuid,
+    #[serde(default)]
+    pub name: String,
+    /// Hubs whose last command exited non-zero.
+    #[serde(default)]
+    pub failing_hubs: u32,
+    /// Commands that exited non-zero within [`SIGNAL_WINDOW_SECS`].
+    #[serde(default)]
+    pub recent_failures: u32,
+    /// Output lines flagged at error priority (3 and up) within the window.
+    #[serde(default)]
+    pub error_lines: u32,
+    /// PIDs of the sector's hub shells.
+    #[serde(default)]
+    pub shell_pids: Vec<u32>,
+    /// Resource use of the shells and their descendants, once measured.
+    #[serde(default)]
+    pub activity: Option<ProcessActivity>,
+    /// A command in this sector awaits confirmation.
+    #[serde(default)]
+    pub pending_confirmation: bool,
+    #[serde(default)]
+    pub active_participants: u32,
+    #[serde(default)]
+    pub idle_participants: u32,
+    /// The sector is the one in focus.
+    #[serde(default)]
+    pub focused: bool,
+    /// Seconds since the sector last had focus; `None` if it has not had
+    /// focus since the Brain started.
+    #[serde(default)]
+    pub seconds_since_focus: Option<i64>,
+    /// User rules that match the sector.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RuleHit>,
+}
+
+/// CPU and memory of a set of processes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProcessActivity {
+    pub processes: u32,
+    /// Percent of one core; can exceed 100.
+    pub cpu_percent: f32,
+    pub memory_bytes: u64,
+    /// `memory_bytes` as a fraction of the machine's memory.
+    pub memory_fraction: f32,
+}
+
+impl ProcessActivity {
+    /// Measure the processes in `sys` rooted at `roots`, descendants
+    /// included. The caller refreshes `sys`.
+    pub fn measure(sys: &sysinfo::System, roots: &[u32]) -> Self {
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        for (pid, process) in sys.processes() {
+            if let Some(parent) = process.parent() {
+                children
+                    .entry(parent.as_u32())
+                    .or_default()
+                    .push(pid.as_u32());
+            }
+        }
+        let mut activity = Self::default();
+        let mut pending: Vec<u32> = roots.to_vec();
+        let mut seen = std::collections::HashSet::new();
+        while let Some(pid) = pending.pop() {
+            if !seen.insert(pid) {
+                continue;
+            }
+            if let Some(process) = sys.process(sysinfo::Pid::from_u32(pid)) {
+                activity.processes += 1;
+                activity.cpu_percent += process.cpu_usage();
+                activity.memory_bytes += process.memory();
+            }
+            if let Some(kids) = children.get(&pid) {
+                pending.extend(kids);
+            }
+        }
+        let total = sys.total_memory();
+        if total > 0 {
+            activity.memory_fraction = activity.memory_bytes as f32 / total as f32;
+        }
+        activity
+    }
+}
+
+/// One factor of a score.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriorityFactor {
+    pub name: String,
+    /// The signal, normalized to 0.0–1.0.
+    pub value: f32,
+    pub weight: f32,
+    /// `value * weight`; the contributions sum to the total score.
+    pub contribution: f32,
+    /// Why the factor has its value, for the Face to show.
+    pub reason: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PriorityScore {
     pub total_score: f32, // 0.0 to 1.0
     pub rank: u8,         // 1 to 5
     pub factors: HashMap<String, f32>,
+    /// The factors in [`WEIGHTS`] order, with their reasons, then one
+    /// `rule:<id>` entry per matching rule.
+    #[serde(default)]
+    pub breakdown: Vec<PriorityFactor>,
+    /// A rule keeps the sector out of alert audio and haptics.
+    #[serde(default)]
+    pub silenced: bool,
+}
+
+impl PriorityScore {
+    /// The rank a total score maps to.
+    pub fn rank_of(total_score: f32) -> u8 {
+        ((total_score.clamp(0.0, 1.0) * 4.0).round() as u8) + 1
+    }
+
+    /// The matching rules, then the contributing factors largest first, as
+    /// one line.
+    pub fn explain(&self) -> String {
+        let mut factors: Vec<&PriorityFactor> = self
+            .breakdown
+            .iter()
+            .filter(|f| f.contribution > 0.0 || f.name.starts_with("rule:"))
+            .collect();
+        factors.sort_by(|a, b| {
+            b.name
+                .starts_with("rule:")
+                .cmp(&a.name.starts_with("rule:"))
+                .then(b.contribution.total_cmp(&a.contribution))
+        });
+        if factors.is_empty() {
+            return "Nothing needs attention".to_string();
+        }
+        factors
+            .iter()
+            .map(|f| f.reason.as_str())
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+fn weight_of(name: &str) -> f32 {
+    WEIGHTS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map_or(0.0, |(_, w)| *w)
+}
+
+fn plural(n: u32, one: &str, many: &str) -> String {
+    format!("{} {}", n, if n == 1 { one } else { many })
+}
+
+fn ago(secs: i64) -> String {
+    match secs {
+        s if s < 60 => format!("{}s ago", s),
+        s if s < 3600 => format!("{}m ago", s / 60),
+        s => format!("{}h ago", s / 3600),
+    }
+}
+
+/// Score a sector from its signals.
+pub fn score(signals: &SectorSignals) -> PriorityScore {
+    let window = format!("{}m", SIGNAL_WINDOW_SECS / 60);
+
+    let focus = match (signals.focused, signals.seconds_since_focus) {
+        (true, _) => (1.0, "In focus".to_string()),
+        (false, Some(secs)) => (
+            0.5f32.powf(secs.max(0) as f32 / FOCUS_HALF_LIFE_SECS),
+            format!("Focused {}", ago(secs.max(0))),
+        ),
+        (false, None) => (0.0, "Not focused this session".to_string()),
+    };
+
+    let failures = (
+        (signals.failing_hubs as f32 * 0.5 + signals.recent_failures as f32 * 0.25).min(1.0),
+        match (signals.failing_hubs, signals.recent_failures) {
+            (0, 0) => "No failed commands".to_string(),
+            (0, n) => format!("{} in the last {}", plural(n, "failure", "failures"), window),
+            (h, n) => format!(
+                "Last command failed in {}; {} in the last {}",
+                plural(h, "hub", "hubs"),
+                plural(n, "failure", "failures"),
+                window
+            ),
+        },
+    );
+
+    let errors = (
+        (signals.error_lines as f32 / 5.0).min(1.0),
+        match signals.error_lines {
+            0 => "No error output".to_string(),
+            n => format!(
+                "{} in the last {}",
+                plural(n, "error line", "error lines"),
+                window
+            ),
+        },
+    );
+
+    let activity = match &signals.activity {
+        Some(a) => (
+            (a.cpu_percent / 100.0)
+                .max(a.memory_fraction * 4.0)
+                .clamp(0.0, 1.0),
+            format!(
+                "{:.0}% CPU, {} MiB across {}",
+                a.cpu_percent,
+                a.memory_bytes / (1024 * 1024),
+                plural(a.processes, "process", "processes")
+            ),
+        ),
+        None => (0.0, "Process activity not measured".to_string()),
+    };
+
+    let confirmation = match signals.pending_confirmation {
+        true => (1.0, "A command awaits confirmation".to_string()),
+        false => (0.0, "No pending confirmation".to_string()),
+    };
+
+    let collaboration = (
+        ((signals.active_participants as f32 + signals.idle_participants as f32 * 0.5) / 2.0)
+            .min(1.0),
+        match (signals.active_participants, signals.idle_participants) {
+            (0, 0) => "No participants".to_string(),
+            (a, i) => format!("{} active, {} idle", plural(a, "participant", "participants"), i),
+        },
+    );
+
+    let mut breakdown: Vec<PriorityFactor> = [
+        ("focus", focus),
+        ("failures", failures),
+        ("errors", errors),
+        ("activity", activity),
+        ("confirmation", confirmation),
+        ("collaboration", collaboration),
+    ]
+    .into_iter()
+    .map(|(name, (value, reason))| {
+        let weight = weight_of(name);
+        PriorityFactor {
+            name: name.to_string(),
+            value,
+            weight,
+            contribution: value * weight,
+            reason,
+        }
+    })
+    .collect();
+
+    let total_score = breakdown
+        .iter()
+        .map(|f| f.contribution)
+        .sum::<f32>()
+        .clamp(0.0, 1.0);
+    let factors = breakdown
+        .iter()
+        .map(|f| (f.name.clone(), f.value))
+        .collect();
+
+    // User rules: a pinned rank wins over adjustments.
+    let adjust: i32 = signals.rules.iter().map(|r| r.adjust as i32).sum();
+    let rank = match signals.rules.iter().rev().find_map(|r| r.set_rank) {
+        Some(rank) => rank.clamp(1, 5),
+        None => (PriorityScore::rank_of(total_score) as i32 + adjust).clamp(1, 5) as u8,
+    };
+    breakdown.extend(signals.rules.iter().map(|hit| PriorityFactor {
+        name: format!("rule:{}", hit.rule_id),
+        value: 0.0,
+        weight: 0.0,
+        contribution: 0.0,
+        reason: hit.reason(),
+    }));
+
+    PriorityScore {
+        total_score,
+        rank,
+        factors,
+        breakdown,
+        silenced: signals.rules.iter().any(|r| r.silence),
+    }
 }
 
 pub struct PriorityService {
@@ -19,9 +325,9 @@ impl PriorityService {
         Self { registry }
     }
 
-    /// Calculate priority score for a sector based on current system heuristics.
+    /// Score a sector from its signals.
     /// Prioritizes the Priority Daemon if active.
-    pub fn calculate_priority(&self, sector_id: Uuid) -> anyhow::Result<PriorityScore> {
+    pub fn calculate_priority(&self, signals: &SectorSignals) -> anyhow::Result<PriorityScore> {
         let port = self
             .registry
             .lock()
@@ -34,7 +340,9 @@ impl PriorityService {
             std::time::Duration::from_millis(50),
         ) {
             use std::io::{BufRead, BufReader, Write};
-            let _ = stream.write_all(format!("get_priority:{}\n", sector_id).as_bytes());
+            let _ = stream.set_read_timeout(Some(std::time::Duration::from_millis(500)));
+            let request = serde_json::to_string(signals)?;
+            let _ = stream.write_all(format!("get_priority:{}\n", request).as_bytes());
             let mut reader = BufReader::new(&stream);
             let mut response = String::new();
             if reader.read_line(&mut response).is_ok() {
@@ -44,19 +352,22 @@ impl PriorityService {
             }
         }
 
-        // Fallback: Local basic scoring
-        Ok(self.calculate_local_fallback(sector_id))
+        // Fallback: score locally
+        Ok(self.calculate_local_fallback(signals))
     }
 
-    fn calculate_local_fallback(&self, _sector_id: Uuid) -> PriorityScore {
-        let mut factors = HashMap::new();
-        factors.insert("recency".to_string(), 0.8);
-        factors.insert("activity".to_string(), 0.2);
-
-        PriorityScore {
-            total_score: 0.5,
-            rank: 3,
-            factors,
+    /// The daemon's scoring, measuring the sector's processes here.
+    fn calculate_local_fallback(&self, signals: &SectorSignals) -> PriorityScore {
+        static SYS: std::sync::OnceLock<Mutex<sysinfo::System>> = std::sync::OnceLock::new();
+        let mut signals = signals.clone();
+        if signals.activity.is_none() && !signals.shell_pids.is_empty() {
+            let sys = SYS.get_or_init(|| Mutex::new(sysinfo::System::new()));
+            if let Ok(mut sys) = sys.lock() {
+                sys.refresh_processes();
+                sys.refresh_memory();
+                signals.activity = Some(ProcessActivity::measure(&sys, &signals.shell_pids));
+            }
         }
+        score(&signals)
     }
 }
@@ -0,0 +1,427 @@
+//! User-defined priority rules (Visual Design §1.2).
+//!
+//! Rules live as a JSON array in the `tos.priority.rules` setting. Each one
+//! matches a sector's commands and output (output regex, exit status,
+//! process, command line, cwd, sector name) and then adjusts or pins the
+//! sector's rank, silences its alerts, or fires an earcon, haptic cue or
+//! notification. The Brain evaluates them on every scoring pass and sends
+//! the hits to the scorer with the sector's signals. Earcons, haptics and
+//! notifications fire once per matching command or output line.
+
+use crate::state::{CommandBlock, CommandHub, Sector, SettingsStore};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// The setting that holds the rules.
+pub const SETTINGS_KEY: &str = "tos.priority.rules";
+
+/// Evidence text is cut to this many characters.
+const MAX_EVIDENCE_CHARS: usize = 120;
+
+/// A rule as the user writes it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriorityRule {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// All conditions must hold; a rule without conditions matches every
+    /// sector.
+    #[serde(default)]
+    pub when: RuleConditions,
+    pub actions: Vec<RuleAction>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// What a rule matches. Patterns are regular expressions.
+///
+/// `process`, `command`, `exit_status` and `cwd` match finished command
+/// blocks, and `output` then matches a line of that block's output. With
+/// only `output`, any recent output line of the sector's hubs matches.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RuleConditions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_status: Option<ExitCondition>,
+    /// The program a command ran, e.g. `cargo` for `cargo test`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process: Option<String>,
+    /// The full command line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// The directory the command ran in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sector: Option<String>,
+}
+
+/// An exit code, or `"success"` / `"failure"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ExitCondition {
+    Code(i32),
+    Outcome(ExitOutcome),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitOutcome {
+    Success,
+    Failure,
+}
+
+impl ExitCondition {
+    pub fn matches(&self, status: i32) -> bool {
+        match self {
+            Self::Code(code) => status == *code,
+            Self::Outcome(ExitOutcome::Success) => status == 0,
+            Self::Outcome(ExitOutcome::Failure) => status != 0,
+        }
+    }
+}
+
+/// What a rule does while it matches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleAction {
+    /// Raise the rank by `by`, or lower it when negative.
+    Adjust { by: i8 },
+    /// Pin the rank; wins over adjustments.
+    SetRank { rank: u8 },
+    /// Keep the sector out of alert audio and haptics.
+    Silence,
+    Earcon { name: String },
+    Haptic { cue: String },
+    /// Post to the system log, which the Face shows as a notification at
+    /// priority 2 and up. `message` may use `{rule}`, `{sector}` and
+    /// `{evidence}`.
+    Notify {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+        #[serde(default = "default_notify_priority")]
+        priority: u8,
+    },
+}
+
+fn default_notify_priority() -> u8 {
+    2
+}
+
+/// A rule's effect on one sector's score, sent with its signals.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleHit {
+    pub rule_id: String,
+    /// What matched, e.g. the command and its exit status.
+    pub evidence: String,
+    #[serde(default)]
+    pub adjust: i8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub set_rank: Option<u8>,
+    #[serde(default)]
+    pub silence: bool,
+}
+
+impl RuleHit {
+    /// What the rule did and why, for the score breakdown.
+    pub fn reason(&self) -> String {
+        let mut effects = Vec::new();
+        if let Some(rank) = self.set_rank {
+            effects.push(format!("set rank {}", rank));
+        } else if self.adjust > 0 {
+            effects.push(format!("raised rank by {}", self.adjust));
+        } else if self.adjust < 0 {
+            effects.push(format!("lowered rank by {}", -self.adjust));
+        }
+        if self.silence {
+            effects.push("silenced alerts".to_string());
+        }
+        if effects.is_empty() {
+            effects.push("matched".to_string());
+        }
+        format!(
+            "Rule '{}' {}: {}",
+            self.rule_id,
+            effects.join(", "),
+            self.evidence
+        )
+    }
+}
+
+/// One place a rule matched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleMatch {
+    /// Identifies the command block or output line, so actions fire once
+    /// per match.
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hub_id: Option<Uuid>,
+    pub evidence: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at: Option<chrono::DateTime<chrono::Local>>,
+}
+
+/// The rules saved in `settings`. Unreadable JSON yields no rules.
+pub fn load(settings: &SettingsStore) -> Vec<PriorityRule> {
+    settings
+        .global
+        .get(SETTINGS_KEY)
+        .and_then(|json| serde_json::from_str(json).ok())
+        .unwrap_or_default()
+}
+
+/// Save `rules` to `settings`.
+pub fn store(settings: &mut SettingsStore, rules: &[PriorityRule]) {
+    settings.global.insert(
+        SETTINGS_KEY.to_string(),
+        serde_json::to_string(rules).unwrap_or_else(|_| "[]".to_string()),
+    );
+}
+
+/// Compile the enabled rules, skipping any that no longer compile.
+pub fn compile_all(rules: &[PriorityRule]) -> Vec<CompiledRule> {
+    rules
+        .iter()
+        .filter(|r| r.enabled)
+        .filter_map(|r| match r.compile() {
+            Ok(compiled) => Some(compiled),
+            Err(e) => {
+                tracing::debug!("[PRIORITY] Skipping rule '{}': {}", r.id, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// The newest match of each rule in `sector`. Only evidence from `since`
+/// on counts; `None` takes everything recorded.
+pub fn evaluate<'a>(
+    rules: &'a [CompiledRule],
+    sector: &Sector,
+    since: Option<chrono::DateTime<chrono::Local>>,
+) -> Vec<(&'a CompiledRule, RuleMatch)> {
+    rules
+        .iter()
+        .filter_map(|rule| Some((rule, rule.matches(sector, since).into_iter().next()?)))
+        .collect()
+}
+
+fn regex(field: &str, pattern: &Option<String>) -> anyhow::Result<Option<Regex>> {
+    pattern
+        .as_deref()
+        .map(|p| Regex::new(p).map_err(|e| anyhow::anyhow!("Invalid {} pattern: {}", field, e)))
+        .transpose()
+}
+
+impl PriorityRule {
+    /// Check the rule and compile its patterns.
+    pub fn compile(&self) -> anyhow::Result<CompiledRule> {
+        if self.id.trim().is_empty() {
+            return Err(anyhow::anyhow!("Rule id is empty"));
+        }
+        if self.actions.is_empty() {
+            return Err(anyhow::anyhow!("Rule '{}' has no actions", self.id));
+        }
+        for action in &self.actions {
+            if let RuleAction::SetRank { rank } = action {
+                if !(1..=5).contains(rank) {
+                    return Err(anyhow::anyhow!("Rank {} is outside 1-5", rank));
+                }
+            }
+        }
+        let when = &self.when;
+        Ok(CompiledRule {
+            output: regex("output", &when.output)?,
+            process: regex("process", &when.process)?,
+            command: regex("command", &when.command)?,
+            cwd: regex("cwd", &when.cwd)?,
+            sector: regex("sector", &when.sector)?,
+            rule: self.clone(),
+        })
+    }
+}
+
+/// A rule with its patterns compiled.
+#[derive(Debug, Clone)]
+pub struct CompiledRule {
+    rule: PriorityRule,
+    output: Option<Regex>,
+    process: Option<Regex>,
+    command: Option<Regex>,
+    cwd: Option<Regex>,
+    sector: Option<Regex>,
+}
+
+impl CompiledRule {
+    pub fn rule(&self) -> &PriorityRule {
+        &self.rule
+    }
+
+    /// Every match in `sector`, newest first.
+    pub fn matches(
+        &self,
+        sector: &Sector,
+        since: Option<chrono::DateTime<chrono::Local>>,
+    ) -> Vec<RuleMatch> {
+        if self.sector.as_ref().is_some_and(|re| !re.is_match(&sector.name)) {
+            return Vec::new();
+        }
+        let when = &self.rule.when;
+        let recent = |t: chrono::DateTime<chrono::Local>| since.is_none_or(|s| t >= s);
+
+        let mut found = Vec::new();
+        if when.process.is_some()
+            || when.command.is_some()
+            || when.exit_status.is_some()
+            || when.cwd.is_some()
+        {
+            for hub in &sector.hubs {
+                for block in &hub.command_blocks {
+                    if block.finished_at.is_some_and(recent) {
+                        found.extend(self.match_block(hub, block));
+                    }
+                }
+            }
+        } else if let Some(output) = &self.output {
+            for hub in &sector.hubs {
+                for (i, line) in hub.terminal_output.iter().enumerate().rev() {
+                    if !recent(line.timestamp) {
+                        break;
+                    }
+                    if output.is_match(&line.text) {
+                        found.push(RuleMatch {
+                            key: format!("line:{}:{}", hub.id, hub.output_offset + i as u64),
+                            hub_id: Some(hub.id),
+                            evidence: clip(line.text.trim()),
+                            at: Some(line.timestamp),
+                        });
+                    }
+                }
+            }
+        } else {
+            found.push(RuleMatch {
+                key: "sector".to_string(),
+                hub_id: None,
+                evidence: format!("Sector '{}'", sector.name),
+                at: None,
+            });
+        }
+        found.sort_by_key(|m| std::cmp::Reverse(m.at));
+        found
+    }
+
+    fn match_block(&self, hub: &CommandHub, block: &CommandBlock) -> Option<RuleMatch> {
+        let when = &self.rule.when;
+        if let Some(condition) = &when.exit_status {
+            if !block.exit_status.is_some_and(|s| condition.matches(s)) {
+                return None;
+            }
+        }
+        if let Some(re) = &self.process {
+            if !re.is_match(program(&block.command)?) {
+                return None;
+            }
+        }
+        if let Some(re) = &self.command {
+            if !re.is_match(&block.command) {
+                return None;
+            }
+        }
+        if let Some(re) = &self.cwd {
+            if !re.is_match(&block.cwd.to_string_lossy()) {
+                return None;
+            }
+        }
+        let mut evidence = match block.exit_status {
+            Some(status) => format!("`{}` exited {}", block.command, status),
+            None => format!("`{}` finished", block.command),
+        };
+        if let Some(re) = &self.output {
+            let (lines, _) = hub.command_block_output(block);
+            let line = lines.iter().find(|l| re.is_match(&l.text))?;
+            evidence = format!("{}: {}", evidence, line.text.trim());
+        }
+        Some(RuleMatch {
+            key: format!("block:{}", block.id),
+            hub_id: Some(hub.id),
+            evidence: clip(&evidence),
+            at: block.finished_at,
+        })
+    }
+
+    /// The rule's effect on the score, given where it matched.
+    pub fn hit(&self, m: &RuleMatch) -> RuleHit {
+        let mut hit = RuleHit {
+            rule_id: self.rule.id.clone(),
+            evidence: m.evidence.clone(),
+            adjust: 0,
+            set_rank: None,
+            silence: false,
+        };
+        for action in &self.rule.actions {
+            match action {
+                RuleAction::Adjust { by } => hit.adjust = hit.adjust.saturating_add(*by),
+                RuleAction::SetRank { rank } => hit.set_rank = Some(*rank),
+                RuleAction::Silence => hit.silence = true,
+                _ => {}
+            }
+        }
+        hit
+    }
+
+    /// Fire the rule's earcons, haptics and notifications for a new match.
+    pub fn fire(
+        &self,
+        services: &crate::services::ServiceManager,
+        sector_id: Uuid,
+        sector_name: &str,
+        m: &RuleMatch,
+    ) {
+        for action in &self.rule.actions {
+            match action {
+                RuleAction::Earcon { name } => services.audio.play_earcon(name),
+                RuleAction::Haptic { cue } => services.haptic.trigger_haptic(cue),
+                RuleAction::Notify { message, priority } => {
+                    let text = message
+                        .as_deref()
+                        .unwrap_or("Rule '{rule}' matched in sector '{sector}': {evidence}")
+                        .replace("{rule}", &self.rule.id)
+                        .replace("{sector}", sector_name)
+                        .replace("{evidence}", &m.evidence);
+                    let record = crate::services::logger::LogRecord {
+                        sector_id: Some(sector_id),
+                        hub_id: m.hub_id,
+                        ..crate::services::logger::LogRecord::new(
+                            crate::services::logger::Severity::from_priority(*priority),
+                            "priority_rules",
+                            "rule",
+                            &text,
+                        )
+                    }
+                    .with_field("rule", self.rule.id.as_str());
+                    services.logger.record(record);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The program a command line runs, skipping `VAR=value` prefixes.
+fn program(command: &str) -> Option<&str> {
+    let token = command.split_whitespace().find(|t| !t.contains('='))?;
+    Some(token.rsplit('/').next().unwrap_or(token))
+}
+
+fn clip(text: &str) -> String {
+    match text.char_indices().nth(MAX_EVIDENCE_CHARS) {
+        Some((i, _)) => format!("{}…", &text[..i]),
+        None => text.to_string(),
+    }
+}
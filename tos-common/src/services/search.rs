@@ -3,21 +3,97 @@
 //! This service communicates with the `tos-searchd` daemon to provide
 //! indexed file searching and semantic "vector" retrieval.
 
+use crate::redact::Redactor;
 use crate::services::registry::ServiceRegistry;
+use crate::services::search_feed::SearchFeed;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::{Arc, Mutex};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 
 /// The type of a search result hit.
-#[derive(Clone, Copy, serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Hash)]
 pub enum SearchHitType {
     /// The hit is a file.
     File,
     /// The hit is a directory.
     Directory,
+    /// A finished command block (§26.4).
+    Command,
+    /// A stretch of a hub's terminal output.
+    Output,
+    /// A system log record.
+    Log,
+    /// An AI prompt and its response.
+    AiExchange,
+}
+
+impl SearchHitType {
+    /// The name stored in the index and used in record keys.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::File => "file",
+            Self::Directory => "directory",
+            Self::Command => "command",
+            Self::Output => "output",
+            Self::Log => "log",
+            Self::AiExchange => "ai_exchange",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        [
+            Self::File,
+            Self::Directory,
+            Self::Command,
+            Self::Output,
+            Self::Log,
+            Self::AiExchange,
+        ]
+        .into_iter()
+        .find(|kind| kind.as_str() == name)
+    }
+
+    /// Whether hits of this kind are records pushed by the Brain rather
+    /// than paths on disk.
+    pub fn is_record(self) -> bool {
+        !matches!(self, Self::File | Self::Directory)
+    }
+}
+
+/// An excerpt of the matching text.
+#[derive(Clone, serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct HitSnippet {
+    pub text: String,
+    /// Byte ranges of `text` that matched the query.
+    pub highlights: Vec<(usize, usize)>,
+}
+
+/// The engines behind a search.
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchEngine {
+    /// Tantivy BM25 over paths and content.
+    Keyword,
+    /// Nearest neighbours of the query embedding.
+    Semantic,
+}
+
+/// How one engine ranked a hybrid hit (§4.1).
+#[derive(Clone, serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+pub struct EngineMatch {
+    pub engine: SearchEngine,
+    /// 1-based rank in that engine's results.
+    pub rank: usize,
+    /// The engine's own score: BM25, or `1 - distance` for semantic hits.
+    pub score: f32,
+    /// `score` min-max normalized over that engine's results.
+    pub normalized: f32,
+    /// Weighted reciprocal-rank share of the fused score.
+    pub contribution: f32,
 }
 
 /// A single search result from either exact or semantic search.
-#[derive(Clone, serde::Deserialize, Debug)]
+#[derive(Clone, serde::Serialize, serde::Deserialize, Debug)]
 pub struct SearchHit {
     /// The absolute path to the hit.
     pub path: String,
@@ -25,46 +101,397 @@ pub struct SearchHit {
     pub hit_type: SearchHitType,
     /// The relevance score (higher is better).
     pub score: f32,
+    /// 1-based line of the first match, for exact hits in the file body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<HitSnippet>,
+    /// Lowercased file extension, e.g. `rs`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_type: Option<String>,
+    /// Modification time (Unix seconds) when the file was indexed, or
+    /// when a record was made.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<u64>,
+    /// For hybrid hits, the engines that found it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub engines: Vec<EngineMatch>,
+    /// A one-line summary of a record, e.g. the command line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The sector a record came from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sector_id: Option<uuid::Uuid>,
+    /// The hub a record came from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hub_id: Option<uuid::Uuid>,
+}
+
+/// A document the Brain pushes to `tos-searchd`: a command, a stretch of
+/// terminal output, a log record or an AI exchange (§4.1).
+#[derive(Clone, serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct SearchRecord {
+    pub kind: SearchHitType,
+    /// Unique within `kind`; pushing the same id again replaces the record.
+    pub id: String,
+    pub title: String,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sector_id: Option<uuid::Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hub_id: Option<uuid::Uuid>,
+    /// When the record was made, in Unix seconds.
+    pub timestamp: u64,
+}
+
+/// Records collected from the session, with the purge generation they
+/// were collected in.
+pub struct CollectedRecords {
+    generation: u64,
+    pub records: Vec<SearchRecord>,
+}
+
+impl SearchRecord {
+    /// The record's document key, which stands in for a path in hits:
+    /// `tos://<kind>/<id>`.
+    pub fn key(&self) -> String {
+        format!("tos://{}/{}", self.kind.as_str(), self.id)
+    }
+}
+
+/// Restrictions on which documents a query may return (§4.1).
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct SearchFilters {
+    /// Only paths at or below this directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_prefix: Option<String>,
+    /// `*`, `?` and `**` pattern. Absolute patterns match the whole path,
+    /// others its trailing components (`*.rs`, `src/**/mod.rs`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glob: Option<String>,
+    /// Any of these extensions, without the dot.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<String>,
+    /// Only files modified, or records made, at or after this Unix time
+    /// (seconds).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_since: Option<u64>,
+    /// Only paths below one of these roots. The Brain fills this from the
+    /// directories of the sector named in [`SearchQuery::sector`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roots: Vec<String>,
+    /// Only records from one of these sectors. With `roots`, a hit passes
+    /// if it matches either.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sectors: Vec<uuid::Uuid>,
+    /// Only hits of these kinds.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<SearchHitType>,
+}
+
+impl SearchFilters {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A paged, filtered query, as sent to `tos-searchd` and `search_query`.
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "SearchFilters::is_empty")]
+    pub filters: SearchFilters,
+    /// Hits to skip, for paging.
+    #[serde(default)]
+    pub offset: usize,
+    /// Page size; `None` uses the engine default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// Restrict the search to one sector (id). Resolved by the Brain into
+    /// `filters.roots` and `filters.sectors`; the daemon ignores it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sector: Option<String>,
+}
+
+impl SearchQuery {
+    /// An unfiltered query for `text` with the default page size.
+    pub fn text(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
 }
 
 /// Service for interacting with the TOS search daemon.
 pub struct SearchService {
     registry: Arc<Mutex<ServiceRegistry>>,
+    /// Sector directories sent to the daemon, and whether it added them as
+    /// new roots; those it already indexed are not this service's to remove.
+    sector_roots: Mutex<BTreeMap<String, bool>>,
+    feed: Mutex<SearchFeed>,
+    /// Records the daemon has not accepted yet, oldest first, each with
+    /// the purge generation it was collected in.
+    pending: Mutex<Vec<(u64, SearchRecord)>>,
+    /// Purges so far, and the generation each purged sector was last
+    /// purged in; records collected before that are not sent.
+    purges: Mutex<(u64, HashMap<uuid::Uuid, u64>)>,
 }
 
 impl SearchService {
     /// Create a new SearchService with the given service registry.
     pub fn new(registry: Arc<Mutex<ServiceRegistry>>) -> Self {
-        Self { registry }
+        Self::with_config(registry, &crate::TosConfig::default())
+    }
+
+    /// Create a SearchService that redacts pushed commands and output
+    /// with the configured `[heuristic] redact_patterns` as well.
+    pub fn with_config(registry: Arc<Mutex<ServiceRegistry>>, config: &crate::TosConfig) -> Self {
+        let (redactor, errors) = Redactor::new(&config.heuristic.redact_patterns);
+        for error in errors {
+            tracing::warn!("Ignored redaction pattern {}", error);
+        }
+        Self {
+            registry,
+            sector_roots: Mutex::new(BTreeMap::new()),
+            feed: Mutex::new(SearchFeed::with_redactor(redactor)),
+            pending: Mutex::new(Vec::new()),
+            purges: Mutex::new((0, HashMap::new())),
+        }
+    }
+
+    /// Perform an exact keyword search.
+    pub fn query(&self, query: &SearchQuery) -> Vec<SearchHit> {
+        let rt = match tokio::runtime::Handle::try_current() {
+            Ok(h) => h,
+            Err(_) => return vec![], // Not in a tokio context
+        };
+        rt.block_on(async move {
+            self.remote_call("search", &Self::encode(query))
+                .await
+                .unwrap_or_default()
+        })
     }
 
-    /// Perform a literal regex/substring search.
-    pub fn query(&self, pattern: &str) -> Vec<SearchHit> {
+    /// Perform a hybrid search: keyword and semantic rankings fused by
+    /// reciprocal rank. Falls back to keyword ranking when the daemon has
+    /// no embedding backend.
+    pub fn hybrid_query(&self, query: &SearchQuery) -> Vec<SearchHit> {
         let rt = match tokio::runtime::Handle::try_current() {
             Ok(h) => h,
             Err(_) => return vec![], // Not in a tokio context
         };
         rt.block_on(async move {
-            self.remote_call("search", pattern)
+            self.remote_call("hybrid_search", &Self::encode(query))
                 .await
                 .unwrap_or_default()
         })
     }
 
     /// Perform a semantic "vector" search.
-    pub fn semantic_query(&self, prompt: &str) -> Vec<SearchHit> {
+    pub fn semantic_query(&self, query: &SearchQuery) -> Vec<SearchHit> {
         let rt = match tokio::runtime::Handle::try_current() {
             Ok(h) => h,
             Err(_) => return vec![], // Not in a tokio context
         };
         rt.block_on(async move {
-            self.remote_call("semantic_search", prompt)
+            self.remote_call("semantic_search", &Self::encode(query))
                 .await
                 .unwrap_or_default()
         })
     }
 
+    /// A bare query keeps the plain-text wire form; anything else is sent
+    /// as JSON.
+    fn encode(query: &SearchQuery) -> String {
+        if *query == SearchQuery::text(&query.text) && !query.text.starts_with('{') {
+            query.text.clone()
+        } else {
+            serde_json::to_string(query).unwrap_or_default()
+        }
+    }
+
+    /// The daemon's indexed roots.
+    pub fn roots(&self) -> anyhow::Result<Vec<String>> {
+        let reply = self.blocking_command("roots", "")?;
+        Ok(serde_json::from_str(reply.trim())?)
+    }
+
+    /// Ask the daemon to index `path` as another root. Returns its reply.
+    /// A `sector` directory may lie outside the configured roots and home.
+    pub fn add_root(&self, path: &str, sector: bool) -> anyhow::Result<String> {
+        let command = if sector { "root_add_sector" } else { "root_add" };
+        self.blocking_command(command, path)
+    }
+
+    /// Whether a `root_add` reply means the root was new, rather than one
+    /// the daemon already indexed.
+    pub fn root_was_added(reply: &str) -> bool {
+        reply.starts_with("OK") && reply.trim() != "OK: Already indexed"
+    }
+
+    /// Ask the daemon to stop indexing the root `path`. Returns its reply.
+    pub fn remove_root(&self, path: &str) -> anyhow::Result<String> {
+        self.blocking_command("root_remove", path)
+    }
+
+    /// Keep the daemon's roots in step with the sectors' working
+    /// directories (§4.1): directories new to `dirs` are added, and those
+    /// that left it are removed if adding them made them roots. Roots
+    /// added by hand or configuration are left alone.
+    pub async fn sync_sector_roots(&self, dirs: BTreeSet<String>) {
+        let known = match self.sector_roots.lock() {
+            Ok(known) => known.clone(),
+            Err(_) => return,
+        };
+        if known.keys().eq(dirs.iter()) {
+            return;
+        }
+        let mut synced = known.clone();
+        for dir in dirs.iter().filter(|d| !known.contains_key(*d)) {
+            match self.remote_command("root_add_sector", dir).await {
+                Ok(reply) if !reply.starts_with("ERROR") => {
+                    synced.insert(dir.clone(), Self::root_was_added(&reply));
+                }
+                Ok(reply) => tracing::debug!("Search root {} not added: {}", dir, reply.trim()),
+                // The daemon is not up yet; retry on the next sync.
+                Err(_) => return,
+            }
+        }
+        for (dir, &added) in known.iter().filter(|(d, _)| !dirs.contains(*d)) {
+            if !added || self.remote_command("root_remove", dir).await.is_ok() {
+                synced.remove(dir);
+            }
+        }
+        if let Ok(mut known) = self.sector_roots.lock() {
+            *known = synced;
+        }
+    }
+
+    /// Records pushed per `index_records` request.
+    const RECORD_BATCH: usize = 200;
+    /// Records kept for a daemon that is down; the oldest go first.
+    const MAX_PENDING: usize = 5000;
+
+    /// The commands, output, log lines and AI exchanges `state` gained
+    /// since the last call (§4.1). Empty while incognito.
+    pub fn collect_records(&self, state: &crate::TosState) -> CollectedRecords {
+        // Under the feed lock, so a purge falls wholly before or after.
+        let mut feed = match self.feed.lock() {
+            Ok(feed) => feed,
+            Err(_) => return CollectedRecords { generation: 0, records: Vec::new() },
+        };
+        CollectedRecords {
+            generation: self.purges.lock().map(|p| p.0).unwrap_or_default(),
+            records: feed.collect(state),
+        }
+    }
+
+    /// Drop the records whose sector was purged after they were collected.
+    fn drop_purged(&self, queue: &mut Vec<(u64, SearchRecord)>) {
+        if let Ok(purges) = self.purges.lock() {
+            queue.retain(|(generation, record)| !Self::purged(&purges.1, *generation, record));
+        }
+    }
+
+    fn purged(sectors: &HashMap<uuid::Uuid, u64>, generation: u64, record: &SearchRecord) -> bool {
+        record
+            .sector_id
+            .and_then(|sector| sectors.get(&sector))
+            .is_some_and(|&purged| generation < purged)
+    }
+
+    /// Send `records`, after any the daemon missed earlier. Whatever it
+    /// does not accept is kept for the next push.
+    pub async fn push_records(&self, collected: CollectedRecords) {
+        let mut queue = match self.pending.lock() {
+            Ok(mut pending) => std::mem::take(&mut *pending),
+            Err(_) => return,
+        };
+        let generation = collected.generation;
+        queue.extend(collected.records.into_iter().map(|r| (generation, r)));
+
+        loop {
+            self.drop_purged(&mut queue);
+            if queue.is_empty() {
+                break;
+            }
+            let batch: Vec<(u64, SearchRecord)> =
+                queue.drain(..queue.len().min(Self::RECORD_BATCH)).collect();
+            let records: Vec<&SearchRecord> = batch.iter().map(|(_, r)| r).collect();
+            let payload = serde_json::to_string(&records).unwrap_or_default();
+            match self.remote_command("index_records", &payload).await {
+                Ok(reply) if !reply.starts_with("ERROR") => {
+                    // A purge that ran while the batch was on its way may
+                    // have reached the daemon first; purge those again.
+                    let repurge: BTreeSet<uuid::Uuid> = match self.purges.lock() {
+                        Ok(purges) => batch
+                            .iter()
+                            .filter(|(g, r)| Self::purged(&purges.1, *g, r))
+                            .filter_map(|(_, r)| r.sector_id)
+                            .collect(),
+                        Err(_) => BTreeSet::new(),
+                    };
+                    for sector in repurge {
+                        let _ = self.remote_command("purge_sector", &sector.to_string()).await;
+                    }
+                }
+                Ok(reply) => {
+                    // Rejected as malformed; retrying will not help.
+                    tracing::warn!("Search records rejected: {}", reply.trim());
+                }
+                Err(_) => {
+                    let rest = std::mem::replace(&mut queue, batch);
+                    queue.extend(rest);
+                    break;
+                }
+            }
+        }
+
+        if let Ok(mut pending) = self.pending.lock() {
+            // Records collected while this push ran go after the leftovers.
+            queue.append(&mut pending);
+            self.drop_purged(&mut queue);
+            let excess = queue.len().saturating_sub(Self::MAX_PENDING);
+            queue.drain(..excess);
+            *pending = queue;
+        }
+    }
+
+    /// Drop every record of the sector from the index and from the queue,
+    /// and leave the current output of its `hubs` out of future records.
+    pub fn purge_sector(
+        &self,
+        sector_id: uuid::Uuid,
+        hubs: &[crate::CommandHub],
+    ) -> anyhow::Result<String> {
+        if let Ok(mut feed) = self.feed.lock() {
+            feed.purge(hubs);
+            if let Ok(mut purges) = self.purges.lock() {
+                purges.0 += 1;
+                let generation = purges.0;
+                purges.1.insert(sector_id, generation);
+            }
+        }
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.retain(|(_, r)| r.sector_id != Some(sector_id));
+        }
+        self.blocking_command("purge_sector", &sector_id.to_string())
+    }
+
+    fn blocking_command(&self, cmd: &str, payload: &str) -> anyhow::Result<String> {
+        let rt = tokio::runtime::Handle::try_current()
+            .map_err(|_| anyhow::anyhow!("Not in a tokio context"))?;
+        rt.block_on(self.remote_command(cmd, payload))
+    }
+
     async fn remote_call(&self, cmd: &str, payload: &str) -> anyhow::Result<Vec<SearchHit>> {
+        let response = self.remote_command(cmd, payload).await?;
+        let hits: Vec<SearchHit> = serde_json::from_str(response.trim())?;
+        Ok(hits)
+    }
+
+    async fn remote_command(&self, cmd: &str, payload: &str) -> anyhow::Result<String> {
         let port = {
             let reg = self
                 .registry
@@ -82,8 +509,6 @@ impl SearchService {
         let mut reader = BufReader::new(stream);
         let mut response = String::new();
         reader.read_line(&mut response).await?;
-
-        let hits: Vec<SearchHit> = serde_json::from_str(response.trim())?;
-        Ok(hits)
+        Ok(response)
     }
 }
@@ -0,0 +1,255 @@
+//! Search Feed — the Brain's history as search records (§4.1).
+//!
+//! Each heartbeat the feed turns what the state gained since the last pass
+//! into [`SearchRecord`]s: finished command blocks, hub terminal output, the
+//! system log and AI exchanges. Nothing is collected while incognito, and
+//! what happened meanwhile is skipped rather than collected later. Secrets
+//! in commands and output are redacted before they leave the Brain.
+
+use crate::redact::Redactor;
+use crate::services::search::{SearchHitType, SearchRecord};
+use crate::{CommandHub, TosState};
+use chrono::{DateTime, Local};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// How far the feed has read one hub.
+#[derive(Default)]
+struct HubMark {
+    /// Absolute index of the first output line not yet collected.
+    output: u64,
+    /// Output before this line was purged and is left out of its chunk.
+    floor: u64,
+    /// Finish time of the newest block collected.
+    block: Option<DateTime<Local>>,
+    /// Time of the newest AI response collected.
+    ai: Option<DateTime<Local>>,
+}
+
+/// What the feed has already collected.
+#[derive(Default)]
+pub struct SearchFeed {
+    hubs: HashMap<Uuid, HubMark>,
+    /// Time of the newest system log line collected.
+    log: Option<DateTime<Local>>,
+    redactor: Redactor,
+}
+
+impl SearchFeed {
+    /// Output is indexed in fixed stretches of this many lines, so a
+    /// stretch keeps its key while it fills up and across restarts.
+    pub const OUTPUT_CHUNK: u64 = 50;
+    /// Titles are cut to this many characters.
+    const TITLE_CHARS: usize = 80;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A feed that redacts with `redactor` instead of the built-in
+    /// patterns alone.
+    pub fn with_redactor(redactor: Redactor) -> Self {
+        Self {
+            redactor,
+            ..Self::default()
+        }
+    }
+
+    /// Whether the session is incognito (§19.4).
+    pub fn is_private(state: &TosState) -> bool {
+        state.privacy_active
+            || state
+                .settings
+                .resolve("tos.privacy.incognito", None, None)
+                .as_deref()
+                == Some("true")
+    }
+
+    /// The records `state` gained since the last call. While incognito the
+    /// marks still advance and nothing is returned.
+    pub fn collect(&mut self, state: &TosState) -> Vec<SearchRecord> {
+        let private = Self::is_private(state);
+        let mut records = Vec::new();
+
+        for sector in &state.sectors {
+            for hub in &sector.hubs {
+                let mark = self.hubs.entry(hub.id).or_default();
+                let collected =
+                    Self::collect_hub(mark, &self.redactor, sector.id, hub, private);
+                if !private {
+                    records.extend(collected);
+                }
+            }
+        }
+        // Closed hubs have nothing more to give.
+        self.hubs.retain(|id, _| {
+            state
+                .sectors
+                .iter()
+                .any(|s| s.hubs.iter().any(|h| h.id == *id))
+        });
+
+        let since = self.log;
+        for line in state
+            .system_log
+            .iter()
+            .filter(|l| since.is_none_or(|t| l.timestamp > t))
+        {
+            self.log = Some(line.timestamp);
+            if !private {
+                records.push(SearchRecord {
+                    kind: SearchHitType::Log,
+                    id: line
+                        .timestamp
+                        .timestamp_nanos_opt()
+                        .unwrap_or_default()
+                        .to_string(),
+                    title: Self::title(&line.text),
+                    text: line.text.clone(),
+                    sector_id: None,
+                    hub_id: None,
+                    timestamp: Self::unix(line.timestamp),
+                });
+            }
+        }
+        records
+    }
+
+    fn collect_hub(
+        mark: &mut HubMark,
+        redactor: &Redactor,
+        sector_id: Uuid,
+        hub: &CommandHub,
+        private: bool,
+    ) -> Vec<SearchRecord> {
+        let mut records = Vec::new();
+        let record =
+            |kind, id: String, title: &str, text: String, at: DateTime<Local>| SearchRecord {
+                kind,
+                id,
+                title: Self::title(title),
+                text,
+                sector_id: Some(sector_id),
+                hub_id: Some(hub.id),
+                timestamp: Self::unix(at),
+            };
+
+        // Finished command blocks, with where and how they ended.
+        for block in &hub.command_blocks {
+            let finished = match block.finished_at {
+                Some(t) if mark.block.is_none_or(|m| t > m) => t,
+                _ => continue,
+            };
+            mark.block = Some(finished);
+            if block.command.is_empty() {
+                continue;
+            }
+            let status = match block.exit_status {
+                Some(code) => format!("exit {}", code),
+                None => "exit unknown".to_string(),
+            };
+            let command = redactor.redact(&block.command).0;
+            let text = format!("{}\n{}\n{}", command, block.cwd.display(), status);
+            records.push(record(
+                SearchHitType::Command,
+                block.id.to_string(),
+                &command,
+                text,
+                finished,
+            ));
+        }
+
+        // Every output chunk that gained lines is pushed again whole.
+        let next = hub.next_line_index();
+        let start = mark.output.max(hub.output_offset);
+        if start < next {
+            let first_chunk = start / Self::OUTPUT_CHUNK;
+            let last_chunk = (next - 1) / Self::OUTPUT_CHUNK;
+            for chunk in first_chunk..=last_chunk {
+                let from = (chunk * Self::OUTPUT_CHUNK)
+                    .max(hub.output_offset)
+                    .max(mark.floor);
+                let to = ((chunk + 1) * Self::OUTPUT_CHUNK).min(next);
+                if from >= to {
+                    continue;
+                }
+                let lines = &hub.terminal_output
+                    [(from - hub.output_offset) as usize..(to - hub.output_offset) as usize];
+                let last = match lines.last() {
+                    Some(line) => line.timestamp,
+                    None => continue,
+                };
+                let text: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+                let command = hub
+                    .command_blocks
+                    .iter()
+                    .rev()
+                    .find(|b| b.output_start <= from && b.output_end.is_none_or(|end| from < end))
+                    .map(|b| redactor.redact(&b.command).0)
+                    .filter(|c| !c.is_empty())
+                    .unwrap_or_else(|| "Terminal output".to_string());
+                records.push(record(
+                    SearchHitType::Output,
+                    format!("{}/{}", hub.id, chunk),
+                    &command,
+                    redactor.redact(&text.join("\n")).0,
+                    last,
+                ));
+            }
+        }
+        mark.output = next;
+        // Incognito output must not reappear in the chunk it shares with
+        // the next public line.
+        if private {
+            mark.floor = next;
+        }
+
+        // AI responses, each with the prompt before it.
+        let mut prompt: Option<&str> = None;
+        for message in &hub.ai_history {
+            match message.role.as_str() {
+                "user" => prompt = Some(&message.content),
+                "assistant" if mark.ai.is_none_or(|m| message.timestamp > m) => {
+                    mark.ai = Some(message.timestamp);
+                    let prompt = prompt.take().unwrap_or_default();
+                    records.push(record(
+                        SearchHitType::AiExchange,
+                        format!(
+                            "{}/{}",
+                            hub.id,
+                            message.timestamp.timestamp_nanos_opt().unwrap_or_default()
+                        ),
+                        if prompt.is_empty() {
+                            message.content.as_str()
+                        } else {
+                            prompt
+                        },
+                        format!("{}\n\n{}", prompt, message.content),
+                        message.timestamp,
+                    ));
+                }
+                _ => {}
+            }
+        }
+        records
+    }
+
+    /// After a sector's records were purged, keep its hubs' earlier output
+    /// out of the chunks that are still filling up.
+    pub fn purge(&mut self, hubs: &[CommandHub]) {
+        for hub in hubs {
+            let mark = self.hubs.entry(hub.id).or_default();
+            mark.floor = hub.next_line_index();
+            mark.output = mark.output.max(mark.floor);
+        }
+    }
+
+    fn title(text: &str) -> String {
+        let line = text.lines().next().unwrap_or_default().trim();
+        line.chars().take(Self::TITLE_CHARS).collect()
+    }
+
+    fn unix(at: DateTime<Local>) -> u64 {
+        at.timestamp().max(0) as u64
+    }
+}
@@ -411,6 +411,8 @@ mod tests {
             lsp: None,
             bezel: None,
             audio: None,
+            commands: None,
+            completions: None,
             signature: None,
         };
 
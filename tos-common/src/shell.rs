@@ -13,10 +13,62 @@ pub enum OscEvent {
         output: Option<String>,
     },
     JsonContext(serde_json::Value),
+    /// FinalTerm/OSC 133 shell-integration mark.
+    PromptMark(PromptMark),
+    /// OSC 8 hyperlink. `uri: None` closes the currently open link.
+    Hyperlink {
+        id: Option<String>,
+        uri: Option<String>,
+    },
+}
+
+/// OSC 133 semantic prompt marks (`A`, `B`, `C`, `D[;exit]`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PromptMark {
+    /// `A`: the shell is about to draw the prompt.
+    PromptStart,
+    /// `B`: the prompt is drawn; user input starts here.
+    CommandStart,
+    /// `C`: the command was submitted; output starts here.
+    CommandExecuted,
+    /// `D`: the command finished, with its exit status when reported.
+    CommandFinished(Option<i32>),
 }
 
+/// One item of parser output, in stream order.
+#[derive(Debug, Clone)]
+pub enum OscToken {
+    Text(String),
+    Event(OscEvent),
+}
+
+/// Upper bound for a single OSC payload (OSC 9004 / 9002 carry base64 blobs).
+const MAX_OSC_LEN: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseState {
+    Ground,
+    /// Saw `ESC`; waiting to see whether it opens an OSC.
+    Escape,
+    /// Inside `ESC ] ...`.
+    Osc,
+    /// Saw `ESC` inside an OSC; `\` completes the ST terminator.
+    OscEscape,
+}
+
+/// §15.2: Streaming OSC parser for TOS shell integration.
+///
+/// Works on raw PTY bytes and keeps its state between reads, so sequences
+/// split across reads are still recognized. Both BEL and `ESC \` (ST) end a
+/// sequence. Non-OSC bytes, including other escape sequences, pass through
+/// as text; OSC sequences are removed from the text and reported as events.
 pub struct OscParser {
     pub current_priority: u8,
+    state: ParseState,
+    osc_buf: Vec<u8>,
+    /// Incomplete UTF-8 tail held back until the next read.
+    utf8_tail: Vec<u8>,
+    text_buf: Vec<u8>,
 }
 
 impl Default for OscParser {
@@ -29,99 +81,312 @@ impl OscParser {
     pub fn new() -> Self {
         Self {
             current_priority: 0,
+            state: ParseState::Ground,
+            osc_buf: Vec::new(),
+            utf8_tail: Vec::new(),
+            text_buf: Vec::new(),
         }
     }
 
-    pub fn process(&mut self, line: &str) -> (String, Vec<OscEvent>) {
-        let mut events = Vec::new();
-        let mut clean_text = line.to_string();
-
-        // §15.2: Metadata Interception (TOS OSC Extensions)
-        // Format: OSC 50 ; <priority_digit> [; <optional_payload>] ST
-        if let Some(pos) = line.find("\x1b]50;") {
-            if let Some(end) = line[pos..].find('\x07') {
-                let actual_end = pos + end;
-                let payload = &line[pos + 5..actual_end];
-                if let Some((p_str, rest)) = payload.split_once(';') {
-                    if let Ok(p) = p_str.parse::<u8>() {
-                        events.push(OscEvent::Priority(p));
-                        self.current_priority = p;
-                        clean_text = format!("{}{}", &line[..pos], rest);
+    /// Feed a chunk of raw PTY output. Returns text runs and events in the
+    /// order they appeared. Partial sequences are retained for the next call.
+    pub fn feed(&mut self, data: &[u8]) -> Vec<OscToken> {
+        let mut tokens = Vec::new();
+
+        for &b in data {
+            match self.state {
+                ParseState::Ground => {
+                    if b == 0x1b {
+                        self.state = ParseState::Escape;
+                    } else {
+                        self.text_buf.push(b);
                     }
-                } else if let Ok(p) = payload.parse::<u8>() {
-                    events.push(OscEvent::Priority(p));
-                    self.current_priority = p;
-                    clean_text = format!("{}{}", &line[..pos], &line[actual_end + 1..]);
                 }
-            }
-        }
-
-        // OSC 7: Current Working Directory
-        // Format: OSC 7 ; file://hostname/path ST
-        if let Some(pos) = line.find("\x1b]7;") {
-            if let Some(end) = line[pos..].find('\x07') {
-                let actual_end = pos + end;
-                let url = &line[pos + 4..actual_end];
-                if let Some(stripped) = url.strip_prefix("file://") {
-                    if let Some(path_start) = stripped.find('/') {
-                        let path = &stripped[path_start..];
-                        events.push(OscEvent::Cwd(path.to_string()));
+                ParseState::Escape => match b {
+                    b']' => {
+                        self.flush_text(&mut tokens, false);
+                        self.osc_buf.clear();
+                        self.state = ParseState::Osc;
+                    }
+                    0x1b => self.text_buf.push(0x1b),
+                    _ => {
+                        self.text_buf.push(0x1b);
+                        self.text_buf.push(b);
+                        self.state = ParseState::Ground;
+                    }
+                },
+                ParseState::Osc => match b {
+                    0x07 => self.finish_osc(&mut tokens),
+                    0x1b => self.state = ParseState::OscEscape,
+                    _ => {
+                        if self.osc_buf.len() >= MAX_OSC_LEN {
+                            // Runaway sequence: drop it rather than buffer forever.
+                            self.osc_buf.clear();
+                            self.state = ParseState::Ground;
+                        } else {
+                            self.osc_buf.push(b);
+                        }
+                    }
+                },
+                ParseState::OscEscape => {
+                    if b == b'\\' {
+                        self.finish_osc(&mut tokens);
                     } else {
-                        // case file://host (root)
-                        events.push(OscEvent::Cwd("/".to_string()));
+                        // ESC without `\` aborts the OSC and starts a new escape.
+                        self.osc_buf.clear();
+                        self.state = ParseState::Escape;
+                        if b == b']' {
+                            self.state = ParseState::Osc;
+                        } else if b != 0x1b {
+                            self.text_buf.push(0x1b);
+                            self.text_buf.push(b);
+                            self.state = ParseState::Ground;
+                        }
                     }
                 }
-                clean_text = format!("{}{}", &line[..pos], &line[actual_end + 1..]);
             }
         }
 
-        // §27.4: OSC 9012 — Line-Level Priority
-        if let Some(pos) = clean_text.find("\x1b]9012;") {
-            if let Some(end) = clean_text[pos..].find('\x07') {
-                let actual_end = pos + end;
-                let payload = &clean_text[pos + 7..actual_end];
-                if let Ok(p) = payload.trim().parse::<u8>() {
-                    if (1..=3).contains(&p) {
-                        events.push(OscEvent::LinePriority(p));
-                    }
-                }
-                clean_text = format!("{}{}", &clean_text[..pos], &clean_text[actual_end + 1..]);
+        self.flush_text(&mut tokens, true);
+        tokens
+    }
+
+    /// True while an escape or OSC sequence is incomplete.
+    pub fn in_sequence(&self) -> bool {
+        self.state != ParseState::Ground
+    }
+
+    /// Line-oriented convenience wrapper around [`OscParser::feed`].
+    ///
+    /// An unterminated sequence at the end of the line is returned verbatim
+    /// as text instead of being held for the next call.
+    pub fn process(&mut self, line: &str) -> (String, Vec<OscEvent>) {
+        let mut clean_text = String::new();
+        let mut events = Vec::new();
+
+        let mut tokens = self.feed(line.as_bytes());
+        tokens.extend(self.reset_pending());
+
+        for token in tokens {
+            match token {
+                OscToken::Text(t) => clean_text.push_str(&t),
+                OscToken::Event(e) => events.push(e),
             }
         }
 
-        // OSC 9002: Command Result
-        if let Some(pos) = clean_text.find("\x1b]9002;") {
-            if let Some(end) = clean_text[pos..].find('\x07') {
-                let actual_end = pos + end;
-                let payload = &clean_text[pos + 7..actual_end];
-                let parts: Vec<&str> = payload.split(';').collect();
-                if parts.len() >= 2 {
-                    if let Ok(status) = parts[1].trim().parse::<i32>() {
-                        events.push(OscEvent::CommandResult {
-                            command: parts[0].to_string(),
-                            status,
-                            output: None,
-                        });
-                    }
+        (clean_text, events)
+    }
+
+    /// Abandon any partial sequence, returning its bytes as text.
+    pub fn reset_pending(&mut self) -> Vec<OscToken> {
+        let mut pending = std::mem::take(&mut self.utf8_tail);
+        match self.state {
+            ParseState::Ground => {}
+            ParseState::Escape => pending.push(0x1b),
+            ParseState::Osc | ParseState::OscEscape => {
+                pending.extend_from_slice(b"\x1b]");
+                pending.append(&mut self.osc_buf);
+                if self.state == ParseState::OscEscape {
+                    pending.push(0x1b);
                 }
-                clean_text = format!("{}{}", &clean_text[..pos], &clean_text[actual_end + 1..]);
             }
         }
+        self.state = ParseState::Ground;
+
+        if pending.is_empty() {
+            Vec::new()
+        } else {
+            vec![OscToken::Text(String::from_utf8_lossy(&pending).into_owned())]
+        }
+    }
+
+    /// Move buffered text into `tokens`. With `hold_tail`, a trailing partial
+    /// UTF-8 character is kept back for the next read; otherwise it is
+    /// emitted (lossily) because the character can no longer be completed.
+    fn flush_text(&mut self, tokens: &mut Vec<OscToken>, hold_tail: bool) {
+        let mut bytes = std::mem::take(&mut self.utf8_tail);
+        bytes.append(&mut self.text_buf);
+        if bytes.is_empty() {
+            return;
+        }
+
+        if hold_tail {
+            self.utf8_tail = bytes.split_off(incomplete_utf8_start(&bytes));
+        }
 
-        // OSC 9004: JSON Context
-        if let Some(pos) = clean_text.find("\x1b]9004;") {
-            if let Some(end) = clean_text[pos..].find('\x07') {
-                let actual_end = pos + end;
-                let payload = &clean_text[pos + 7..actual_end];
-                if let Ok(decoded) = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, payload) {
+        if !bytes.is_empty() {
+            let text = String::from_utf8_lossy(&bytes).into_owned();
+            match tokens.last_mut() {
+                Some(OscToken::Text(prev)) => prev.push_str(&text),
+                _ => tokens.push(OscToken::Text(text)),
+            }
+        }
+    }
+
+    fn finish_osc(&mut self, tokens: &mut Vec<OscToken>) {
+        self.state = ParseState::Ground;
+        let raw = std::mem::take(&mut self.osc_buf);
+        let payload = String::from_utf8_lossy(&raw);
+        let (code, body) = payload.split_once(';').unwrap_or((&payload, ""));
+
+        match code {
+            // §15.2: Format: OSC 50 ; <priority_digit> [; <optional_payload>] ST
+            "50" => {
+                let (p_str, rest) = match body.split_once(';') {
+                    Some((p, r)) => (p, Some(r)),
+                    None => (body, None),
+                };
+                if let Ok(p) = p_str.trim().parse::<u8>() {
+                    self.current_priority = p;
+                    tokens.push(OscToken::Event(OscEvent::Priority(p)));
+                    if let Some(rest) = rest.filter(|r| !r.is_empty()) {
+                        tokens.push(OscToken::Text(rest.to_string()));
+                    }
+                }
+            }
+            // OSC 7: Current Working Directory. Format: OSC 7 ; file://hostname/path ST
+            "7" => {
+                if let Some(path) = parse_file_url(body) {
+                    tokens.push(OscToken::Event(OscEvent::Cwd(path)));
+                }
+            }
+            // OSC 1337 ; CurrentDir=<path>
+            "1337" => {
+                if let Some(path) = body.strip_prefix("CurrentDir=") {
+                    tokens.push(OscToken::Event(OscEvent::Cwd(path.to_string())));
+                }
+            }
+            // §27.4: OSC 9012 — Line-Level Priority
+            "9012" => {
+                if let Ok(p) = body.trim().parse::<u8>() {
+                    if (1..=3).contains(&p) {
+                        tokens.push(OscToken::Event(OscEvent::LinePriority(p)));
+                    }
+                }
+            }
+            // OSC 9002: Command Result. Format: <command>;<status>[;<base64_output>]
+            "9002" => {
+                if let Some(event) = parse_command_result(body) {
+                    tokens.push(OscToken::Event(event));
+                }
+            }
+            // OSC 9004: JSON Context (base64)
+            "9004" => {
+                if let Ok(decoded) = base64::Engine::decode(
+                    &base64::engine::general_purpose::STANDARD,
+                    body.trim(),
+                ) {
                     if let Ok(val) = serde_json::from_slice::<serde_json::Value>(&decoded) {
-                        events.push(OscEvent::JsonContext(val));
+                        tokens.push(OscToken::Event(OscEvent::JsonContext(val)));
                     }
                 }
-                clean_text = format!("{}{}", &clean_text[..pos], &clean_text[actual_end + 1..]);
             }
+            // OSC 133: semantic prompt marks
+            "133" => {
+                let mut fields = body.split(';');
+                let mark = match fields.next().unwrap_or("") {
+                    "A" => Some(PromptMark::PromptStart),
+                    "B" => Some(PromptMark::CommandStart),
+                    "C" => Some(PromptMark::CommandExecuted),
+                    "D" => Some(PromptMark::CommandFinished(
+                        fields.next().and_then(|s| s.trim().parse::<i32>().ok()),
+                    )),
+                    _ => None,
+                };
+                if let Some(mark) = mark {
+                    tokens.push(OscToken::Event(OscEvent::PromptMark(mark)));
+                }
+            }
+            // OSC 8 ; params ; URI — params are `key=value` pairs split by `:`.
+            "8" => {
+                let (params, uri) = body.split_once(';').unwrap_or((body, ""));
+                let id = params
+                    .split(':')
+                    .find_map(|kv| kv.strip_prefix("id="))
+                    .map(|s| s.to_string());
+                let uri = if uri.is_empty() {
+                    None
+                } else {
+                    Some(uri.to_string())
+                };
+                tokens.push(OscToken::Event(OscEvent::Hyperlink { id, uri }));
+            }
+            // Titles, palette changes etc. are the emulator's concern.
+            _ => {}
         }
+    }
+}
 
-        (clean_text, events)
+/// Offset of a trailing, not yet complete UTF-8 character (or `len` if none).
+fn incomplete_utf8_start(bytes: &[u8]) -> usize {
+    let mut start = 0;
+    loop {
+        match std::str::from_utf8(&bytes[start..]) {
+            Ok(_) => return bytes.len(),
+            Err(e) => match e.error_len() {
+                Some(len) => start += e.valid_up_to() + len,
+                None => return start + e.valid_up_to(),
+            },
+        }
+    }
+}
+
+fn parse_file_url(url: &str) -> Option<String> {
+    let stripped = url.strip_prefix("file://")?;
+    Some(match stripped.find('/') {
+        Some(path_start) => percent_decode(&stripped[path_start..]),
+        // case file://host (root)
+        None => "/".to_string(),
+    })
+}
+
+/// Decode `%XX` escapes that shells emit for spaces and non-ASCII paths.
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = |b: u8| (b as char).to_digit(16);
+            if let (Some(hi), Some(lo)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                out.push((hi * 16 + lo) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn parse_command_result(body: &str) -> Option<OscEvent> {
+    let parts: Vec<&str> = body.split(';').collect();
+    let n = parts.len();
+
+    if n >= 3 {
+        if let Ok(status) = parts[n - 2].trim().parse::<i32>() {
+            let output = base64::Engine::decode(
+                &base64::engine::general_purpose::STANDARD,
+                parts[n - 1].trim(),
+            )
+            .ok()
+            .map(|b| String::from_utf8_lossy(&b).into_owned());
+            return Some(OscEvent::CommandResult {
+                command: parts[..n - 2].join(";"),
+                status,
+                output,
+            });
+        }
+    }
+    if n >= 2 {
+        if let Ok(status) = parts[n - 1].trim().parse::<i32>() {
+            return Some(OscEvent::CommandResult {
+                command: parts[..n - 1].join(";"),
+                status,
+                output: None,
+            });
+        }
     }
+    None
 }
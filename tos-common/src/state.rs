@@ -188,6 +188,9 @@ pub enum TrustTier {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfirmationRequest {
     pub id: Uuid,
+    /// The sector whose command awaits confirmation.
+    #[serde(default)]
+    pub sector_id: Option<Uuid>,
     pub original_request: String,
     pub message: String,
     pub progress: f32,
@@ -278,6 +281,9 @@ pub struct Sector {
     pub trust_tier: TrustTier,
     /// Tactical priority rank (1–5).
     pub priority: u8,
+    /// The factors behind `priority`, with their reasons (Visual Design §1.2).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub priority_factors: Vec<crate::services::priority::PriorityFactor>,
     pub active_apps: Vec<AppInstance>,
     pub active_app_index: usize,
     /// Multi-user collaboration participants (§13).
@@ -319,6 +325,143 @@ pub struct CommandHub {
     pub active_thoughts: Vec<AiThought>,
     pub last_exit_status: Option<i32>,
     pub is_running: bool,
+    /// Emulated screen grid for interactive programs (§26.3).
+    #[serde(default)]
+    pub screen: Option<TerminalScreen>,
+    /// Commands and their output ranges, oldest first (§26.4).
+    #[serde(default)]
+    pub command_blocks: Vec<CommandBlock>,
+    /// Lines trimmed from the front of `terminal_output` so far; block line
+    /// indices are absolute and subtract this to index into the buffer.
+    #[serde(default)]
+    pub output_offset: u64,
+}
+
+impl CommandHub {
+    /// Blocks retained per hub; the oldest are dropped first.
+    pub const MAX_COMMAND_BLOCKS: usize = 200;
+
+    /// Absolute index the next output line will receive.
+    pub fn next_line_index(&self) -> u64 {
+        self.output_offset + self.terminal_output.len() as u64
+    }
+
+    /// Append a line of output, trimming history to `buffer_limit`.
+    pub fn push_output_line(&mut self, line: TerminalLine) {
+        self.terminal_output.push(line);
+        if self.terminal_output.len() > self.buffer_limit {
+            let excess = self.terminal_output.len() - self.buffer_limit;
+            self.terminal_output.drain(..excess);
+            self.output_offset += excess as u64;
+        }
+    }
+
+    /// §26.4: Start a block for a command that is about to produce output.
+    ///
+    /// `shell_marked` is true for OSC 133 `C`. A block opened by
+    /// `prompt_submit` is adopted by the shell's mark instead of duplicated;
+    /// any other open block lost its `D` mark and is closed here.
+    pub fn begin_command_block(&mut self, command: Option<&str>, shell_marked: bool) {
+        let next = self.next_line_index();
+        if let Some(open) = self.command_blocks.last_mut().filter(|b| b.is_open()) {
+            if shell_marked && !open.shell_marked {
+                open.shell_marked = true;
+                open.output_start = next;
+                if let Some(cmd) = command.filter(|_| open.command.is_empty()) {
+                    open.command = cmd.to_string();
+                }
+                return;
+            }
+            open.output_end = Some(next);
+            open.finished_at = Some(chrono::Local::now());
+        }
+
+        self.command_blocks.push(CommandBlock {
+            id: Uuid::new_v4(),
+            command: command.unwrap_or_default().to_string(),
+            cwd: self.current_directory.clone(),
+            started_at: chrono::Local::now(),
+            finished_at: None,
+            exit_status: None,
+            output_start: next,
+            output_end: None,
+            shell_marked,
+        });
+        if self.command_blocks.len() > Self::MAX_COMMAND_BLOCKS {
+            self.command_blocks.remove(0);
+        }
+    }
+
+    /// §26.4: Close the open block (OSC 133 `D` or OSC 9002).
+    ///
+    /// When both marks are emitted for one command, the second one only
+    /// fills in what the first left out.
+    pub fn finish_command_block(&mut self, command: Option<&str>, exit_status: Option<i32>) {
+        let next = self.next_line_index();
+        let block = match self.command_blocks.last_mut() {
+            Some(b) if b.is_open() => {
+                b.output_end = Some(next);
+                b.finished_at = Some(chrono::Local::now());
+                b
+            }
+            Some(b) if b.output_end == Some(next) => b,
+            _ => match command {
+                // A 9002 report for a command the Brain never saw start.
+                Some(cmd) => {
+                    let now = chrono::Local::now();
+                    self.command_blocks.push(CommandBlock {
+                        id: Uuid::new_v4(),
+                        command: cmd.to_string(),
+                        cwd: self.current_directory.clone(),
+                        started_at: now,
+                        finished_at: Some(now),
+                        exit_status,
+                        output_start: next,
+                        output_end: Some(next),
+                        shell_marked: false,
+                    });
+                    if self.command_blocks.len() > Self::MAX_COMMAND_BLOCKS {
+                        self.command_blocks.remove(0);
+                    }
+                    return;
+                }
+                None => return,
+            },
+        };
+        if exit_status.is_some() {
+            block.exit_status = exit_status;
+        }
+        if let Some(cmd) = command.filter(|_| block.command.is_empty()) {
+            block.command = cmd.to_string();
+        }
+    }
+
+    /// Look up a block by id, or by `last` / `last_failed`.
+    pub fn find_command_block(&self, selector: &str) -> Option<&CommandBlock> {
+        match selector {
+            "last" => self.command_blocks.last(),
+            "last_failed" => self
+                .command_blocks
+                .iter()
+                .rev()
+                .find(|b| b.exit_status.is_some_and(|s| s != 0)),
+            id => {
+                let id = Uuid::parse_str(id).ok()?;
+                self.command_blocks.iter().find(|b| b.id == id)
+            }
+        }
+    }
+
+    /// Output lines of `block` still held in the buffer, and whether earlier
+    /// lines of the block were already trimmed.
+    pub fn command_block_output(&self, block: &CommandBlock) -> (&[TerminalLine], bool) {
+        let next = self.next_line_index();
+        let start = block.output_start.clamp(self.output_offset, next);
+        let end = block.output_end.unwrap_or(next).clamp(start, next);
+        let lines = &self.terminal_output
+            [(start - self.output_offset) as usize..(end - self.output_offset) as usize];
+        (lines, block.output_start < self.output_offset)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -724,6 +867,42 @@ pub struct ProcessEntry {
 pub struct SearchResult {
     pub source_sector: String,
     pub matches: Vec<String>,
+    /// Structured hits behind `matches` when they came from `tos-searchd`
+    /// (§4.1): line, snippet, file type and mtime.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hits: Vec<crate::services::search::SearchHit>,
+}
+
+impl SearchResult {
+    /// A result group for hits from the search daemon; `matches` holds one
+    /// `path[:line] [FILE|DIR]` label per hit, or `title [CMD|OUT|LOG|AI]`
+    /// for records.
+    pub fn from_hits(source: &str, hits: Vec<crate::services::search::SearchHit>) -> Self {
+        use crate::services::search::SearchHitType;
+        let matches = hits
+            .iter()
+            .map(|h| {
+                let kind = match h.hit_type {
+                    SearchHitType::Directory => "DIR",
+                    SearchHitType::File => "FILE",
+                    SearchHitType::Command => "CMD",
+                    SearchHitType::Output => "OUT",
+                    SearchHitType::Log => "LOG",
+                    SearchHitType::AiExchange => "AI",
+                };
+                match (&h.title, h.line) {
+                    (Some(title), _) if h.hit_type.is_record() => format!("{} [{}]", title, kind),
+                    (_, Some(line)) => format!("{}:{} [{}]", h.path, line, kind),
+                    (_, None) => format!("{} [{}]", h.path, kind),
+                }
+            })
+            .collect();
+        Self {
+            source_sector: source.to_string(),
+            matches,
+            hits,
+        }
+    }
 }
 
 /// A single line of terminal output with priority and timestamp.
@@ -733,6 +912,92 @@ pub struct TerminalLine {
     /// Line-level priority (1 = Low, 3 = High).
     pub priority: u8,
     pub timestamp: chrono::DateTime<chrono::Local>,
+    /// The component that logged it, for system log lines that came from
+    /// the unified log (§19.1).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// A submitted command and the range of output it produced (§26.4).
+///
+/// Built from OSC 133 `C`/`D` marks or OSC 9002 results. Line indices are
+/// absolute; see [`CommandHub::output_offset`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandBlock {
+    pub id: Uuid,
+    pub command: String,
+    pub cwd: PathBuf,
+    pub started_at: chrono::DateTime<chrono::Local>,
+    pub finished_at: Option<chrono::DateTime<chrono::Local>>,
+    pub exit_status: Option<i32>,
+    /// First output line.
+    pub output_start: u64,
+    /// One past the last output line; `None` while the command runs.
+    pub output_end: Option<u64>,
+    /// The shell confirmed the start with OSC 133 `C`.
+    #[serde(default)]
+    pub shell_marked: bool,
+}
+
+impl CommandBlock {
+    pub fn is_open(&self) -> bool {
+        self.finished_at.is_none()
+    }
+}
+
+/// Styled snapshot of a hub's emulated terminal screen (§26.3).
+///
+/// Produced by the Brain's VT100/xterm emulator so Faces can render
+/// full-screen programs (`vim`, `htop`, `less`) cell-accurately. Rows are
+/// stored as runs of identically styled cells to keep the payload small.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TerminalScreen {
+    pub rows: u16,
+    pub cols: u16,
+    pub cursor_row: u16,
+    pub cursor_col: u16,
+    pub cursor_visible: bool,
+    /// True while a program owns the alternate screen buffer.
+    pub alternate_screen: bool,
+    /// How many rows above the live screen this snapshot is scrolled back.
+    #[serde(default)]
+    pub scrollback_offset: usize,
+    pub lines: Vec<Vec<StyledSpan>>,
+}
+
+/// A run of cells sharing the same SGR attributes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StyledSpan {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "TermColor::is_default")]
+    pub fg: TermColor,
+    #[serde(default, skip_serializing_if = "TermColor::is_default")]
+    pub bg: TermColor,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bold: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dim: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub italic: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub underline: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub inverse: bool,
+}
+
+/// Terminal cell color: the theme default, a 256-color palette index, or truecolor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TermColor {
+    #[default]
+    Default,
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl TermColor {
+    pub fn is_default(&self) -> bool {
+        *self == TermColor::Default
+    }
 }
 
 /// Persistent settings store with cascading resolution.
@@ -857,6 +1122,9 @@ impl Default for TosState {
                 active_thoughts: vec![],
                 last_exit_status: None,
                 is_running: false,
+                screen: None,
+                command_blocks: vec![],
+                output_offset: 0,
             }],
             active_hub_index: 0,
             frozen: false,
@@ -864,6 +1132,7 @@ impl Default for TosState {
             disconnected: false,
             trust_tier: TrustTier::System,
             priority: 1,
+            priority_factors: Vec::new(),
             active_apps: vec![],
             active_app_index: 0,
             participants: vec![],
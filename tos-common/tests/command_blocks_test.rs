@@ -0,0 +1,159 @@
+use std::sync::{Arc, Mutex};
+use tos_common::brain::ipc_handler::IpcHandler;
+use tos_common::{CommandHub, TerminalLine, TosState};
+
+fn line(text: &str) -> TerminalLine {
+    TerminalLine {
+        text: text.to_string(),
+        priority: 1,
+        timestamp: chrono::Local::now(),
+        source: None,
+    }
+}
+
+fn active_hub(state: &mut TosState) -> &mut CommandHub {
+    let idx = state.active_sector_index;
+    let sector = &mut state.sectors[idx];
+    let h_idx = sector.active_hub_index;
+    &mut sector.hubs[h_idx]
+}
+
+#[test]
+fn test_prompt_marks_delimit_block_output() {
+    let mut state = TosState::default();
+    let hub = active_hub(&mut state);
+
+    // Submitted from the prompt; the shell echoes the command, then marks C.
+    hub.begin_command_block(Some("cargo test"), false);
+    hub.push_output_line(line("user@tos $ cargo test"));
+    hub.begin_command_block(Some("cargo test"), true);
+    hub.push_output_line(line("running 1 test"));
+    hub.push_output_line(line("test result: FAILED"));
+    hub.finish_command_block(None, Some(101));
+    // OSC 9002 for the same command only fills gaps; no duplicate block.
+    hub.finish_command_block(Some("cargo test"), Some(101));
+
+    assert_eq!(hub.command_blocks.len(), 1);
+    let block = hub.find_command_block("last_failed").unwrap();
+    assert_eq!(block.command, "cargo test");
+    assert_eq!(block.exit_status, Some(101));
+    assert!(block.shell_marked);
+
+    let (lines, truncated) = hub.command_block_output(block);
+    let texts: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+    assert_eq!(texts, vec!["running 1 test", "test result: FAILED"]);
+    assert!(!truncated);
+}
+
+#[test]
+fn test_blocks_without_integration_and_trimming() {
+    let mut state = TosState::default();
+    let hub = active_hub(&mut state);
+    hub.buffer_limit = 3;
+
+    hub.begin_command_block(Some("make"), false);
+    for i in 0..4 {
+        hub.push_output_line(line(&format!("make line {}", i)));
+    }
+    // No D mark: the next submission closes the previous block.
+    hub.begin_command_block(Some("ls"), false);
+    hub.push_output_line(line("Cargo.toml"));
+
+    assert_eq!(hub.output_offset, 2);
+    assert_eq!(hub.command_blocks.len(), 2);
+
+    let make = hub.command_blocks[0].clone();
+    assert_eq!(make.exit_status, None);
+    let (lines, truncated) = hub.command_block_output(&make);
+    assert!(truncated);
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1].text, "make line 3");
+
+    let ls = hub.find_command_block("last").unwrap();
+    assert!(ls.is_open());
+    assert_eq!(hub.command_block_output(ls).0[0].text, "Cargo.toml");
+    assert!(hub.find_command_block("last_failed").is_none());
+
+    // A 9002 result for a command the Brain never saw start still records it.
+    hub.finish_command_block(None, Some(0));
+    hub.push_output_line(line("prompt"));
+    hub.finish_command_block(Some("git status"), Some(2));
+    assert_eq!(hub.command_blocks.len(), 3);
+    assert_eq!(
+        hub.find_command_block("last_failed").unwrap().command,
+        "git status"
+    );
+}
+
+#[tokio::test]
+async fn test_command_block_ipc() {
+    let state = Arc::new(Mutex::new(TosState::default()));
+    let services = Arc::new(tos_common::services::ServiceManager::new());
+    let mm = Arc::new(tos_common::brain::module_manager::ModuleManager::new(
+        std::path::PathBuf::from("/tmp"),
+    ));
+    let shells = Arc::new(Mutex::new(tos_common::brain::shell::ShellPool::new(
+        state.clone(),
+        mm,
+        services.ai.clone(),
+        services.heuristic.clone(),
+    )));
+    let mut bus = services.events.subscribe();
+    let ipc = IpcHandler::new(state.clone(), shells, services);
+
+    let block_id = {
+        let mut lock = state.lock().unwrap();
+        let hub = active_hub(&mut lock);
+        hub.begin_command_block(Some("false"), true);
+        hub.push_output_line(line("boom"));
+        hub.finish_command_block(None, Some(1));
+        hub.command_blocks[0].id
+    };
+
+    let list: serde_json::Value =
+        serde_json::from_str(&ipc.handle_request("command_blocks:")).unwrap();
+    assert_eq!(list[0]["command"], "false");
+
+    let output: serde_json::Value =
+        serde_json::from_str(&ipc.handle_request("command_block_output:last_failed")).unwrap();
+    assert_eq!(output["block"]["id"], block_id.to_string());
+    assert_eq!(output["lines"][0], "boom");
+
+    assert_eq!(
+        ipc.handle_request(&format!("command_block_to_ai:{}", block_id)),
+        format!("BLOCK_COPIED: {}", block_id)
+    );
+    {
+        let mut lock = state.lock().unwrap();
+        let context = active_hub(&mut lock).ai_history.last().unwrap().clone();
+        assert_eq!(context.role, "context");
+        assert!(context.content.contains("$ false"));
+        assert!(context.content.contains("[exit: 1]"));
+        assert!(context.content.contains("boom"));
+    }
+
+    assert!(ipc
+        .handle_request("command_block_output:00000000-0000-0000-0000-000000000000")
+        .starts_with("ERROR:"));
+    // Status lines the Brain adds are capped and streamed like shell output.
+    let hub_id = {
+        let mut lock = state.lock().unwrap();
+        let hub = active_hub(&mut lock);
+        hub.mode = tos_common::CommandHubMode::Ssh;
+        hub.buffer_limit = 1;
+        hub.id
+    };
+    assert_eq!(ipc.handle_request("remote_ssh_disconnect:"), "SSH_DISCONNECT_OK");
+    {
+        let mut lock = state.lock().unwrap();
+        let hub = active_hub(&mut lock);
+        assert_eq!(hub.terminal_output.len(), 1);
+        assert_eq!(hub.terminal_output[0].text, "SSH DISCONNECTED");
+        assert_eq!(hub.output_offset, 1);
+    }
+    let topic = format!("hub:{}:output", hub_id);
+    let event = std::iter::from_fn(|| bus.try_recv().ok())
+        .find(|e| e.topic == topic)
+        .unwrap();
+    assert_eq!(event.payload["text"], "SSH DISCONNECTED");
+}
@@ -0,0 +1,177 @@
+use std::sync::{Arc, Mutex};
+use tos_common::brain::command_registry::{CommandSource, CommandSpec};
+use tos_common::brain::ipc_handler::IpcHandler;
+use tos_common::brain::module_manager::ModuleManager;
+use tos_common::collaboration::ParticipantRole;
+use tos_common::rpc::ParamKind;
+use tos_common::services::auth::{AuthMethod, ClientIdentity};
+use tos_common::TosState;
+
+fn ipc_with_modules(modules: &std::path::Path) -> (IpcHandler, Arc<ModuleManager>) {
+    let state = Arc::new(Mutex::new(TosState::default()));
+    let services = Arc::new(tos_common::services::ServiceManager::new());
+    let mm = Arc::new(ModuleManager::new(modules.to_path_buf()));
+    let shells = Arc::new(Mutex::new(tos_common::brain::shell::ShellPool::new(
+        state.clone(),
+        mm.clone(),
+        services.ai.clone(),
+        services.heuristic.clone(),
+    )));
+    (IpcHandler::new(state, shells, services), mm)
+}
+
+fn viewer() -> ClientIdentity {
+    ClientIdentity {
+        name: "guest".to_string(),
+        role: ParticipantRole::Viewer,
+        method: AuthMethod::DeviceToken,
+    }
+}
+
+#[tokio::test]
+async fn test_command_list_describes_builtins() {
+    let dir = tempfile::tempdir().unwrap();
+    let (ipc, _) = ipc_with_modules(dir.path());
+
+    let all: serde_json::Value =
+        serde_json::from_str(&ipc.handle_request_as(&viewer(), "command_list:")).unwrap();
+    let all = all.as_array().unwrap();
+    assert_eq!(all.len(), IpcHandler::builtin_commands().len());
+    let save_as = all.iter().find(|c| c["name"] == "editor_save_as").unwrap();
+    assert_eq!(save_as["role"], "operator");
+    assert_eq!(save_as["source"]["kind"], "builtin");
+    assert_eq!(save_as["params"][0]["name"], "pane_id");
+    assert_eq!(save_as["params"][0]["type"], "uuid");
+    assert_eq!(save_as["params"][1]["required"], true);
+    assert!(!save_as["help"].as_str().unwrap().is_empty());
+
+    let split: serde_json::Value =
+        serde_json::from_str(&ipc.handle_request("command_list:split_")).unwrap();
+    let split = split.as_array().unwrap();
+    assert!(split.len() > 5);
+    assert!(split
+        .iter()
+        .all(|c| c["name"].as_str().unwrap().starts_with("split_")));
+
+    // The role declared at registration is the one enforced.
+    assert_eq!(
+        IpcHandler::required_role("command_list"),
+        ParticipantRole::Viewer
+    );
+    assert!(ipc
+        .handle_request_as(&viewer(), "split_equalize:")
+        .starts_with("ERROR: Permission denied"));
+}
+
+#[tokio::test]
+async fn test_runtime_registration() {
+    let dir = tempfile::tempdir().unwrap();
+    let (ipc, _) = ipc_with_modules(dir.path());
+
+    ipc.register_command(
+        CommandSpec::new("echo_args", "Echo the arguments")
+            .param("first", ParamKind::Text)
+            .optional("second", ParamKind::Text)
+            .role(ParticipantRole::Viewer),
+        |cx| cx.args.join("|"),
+    )
+    .unwrap();
+    assert!(ipc
+        .register_command(CommandSpec::new("echo_args", "again"), |_| String::new())
+        .is_err());
+    assert!(ipc
+        .register_command(CommandSpec::new("bad:name", "nope"), |_| String::new())
+        .is_err());
+
+    assert_eq!(ipc.handle_request_as(&viewer(), "echo_args:a;b"), "a|b");
+    let reply = ipc.handle_request(
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "echo_args", "params": {"first": "x;y"}}"#,
+    );
+    let reply: serde_json::Value = serde_json::from_str(&reply).unwrap();
+    assert_eq!(reply["result"], "x;y");
+
+    assert!(ipc.unregister_command("echo_args").is_some());
+    assert_eq!(
+        ipc.handle_request("echo_args:a"),
+        "ERROR: Unknown command 'echo_args'"
+    );
+}
+
+#[tokio::test]
+async fn test_split_detach_modes() {
+    let dir = tempfile::tempdir().unwrap();
+    let (ipc, _) = ipc_with_modules(dir.path());
+    assert!(ipc
+        .handle_request("split_detach:sideways")
+        .starts_with("ERROR: Unknown detach mode"));
+    assert!(!ipc
+        .handle_request("split_detach:context")
+        .starts_with("ERROR: Unknown command"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_module_commands() {
+    let dir = tempfile::tempdir().unwrap();
+    let module_dir = dir.path().join("greeter");
+    std::fs::create_dir_all(&module_dir).unwrap();
+    std::fs::write(
+        module_dir.join("module.toml"),
+        r#"
+id = "greeter"
+name = "Greeter"
+version = "0.1.0"
+module_type = "Application"
+author = "TOS"
+
+[[commands]]
+name = "hello"
+help = "Say hello"
+exec = "/bin/echo"
+args = ["hello"]
+params = [{ name = "who", type = "text" }]
+
+[[commands]]
+name = "fail"
+exec = "/bin/false"
+role = "coowner"
+
+[[commands]]
+name = "wipe"
+exec = "/bin/true"
+role = "viewer"
+"#,
+    )
+    .unwrap();
+
+    let (ipc, modules) = ipc_with_modules(dir.path());
+    assert_eq!(ipc.register_module_commands(&modules), 3);
+    // Re-registering replaces rather than duplicates.
+    assert_eq!(ipc.register_module_commands(&modules), 3);
+
+    let hello = ipc
+        .commands()
+        .into_iter()
+        .find(|c| c.name == "greeter.hello")
+        .unwrap();
+    assert_eq!(hello.help, "Say hello");
+    assert_eq!(
+        hello.source,
+        CommandSource::Module {
+            id: "greeter".to_string()
+        }
+    );
+
+    assert_eq!(ipc.handle_request("greeter.hello:world"), "hello world");
+    assert_eq!(ipc.handle_request("greeter.hello"), "hello");
+    assert!(ipc
+        .handle_request("greeter.fail")
+        .starts_with("ERROR: /bin/false exited"));
+    assert!(ipc
+        .handle_request_as(&viewer(), "greeter.fail")
+        .starts_with("ERROR: Permission denied"));
+    // Manifests cannot open commands below Operator.
+    assert!(ipc
+        .handle_request_as(&viewer(), "greeter.wipe")
+        .starts_with("ERROR: Permission denied"));
+}
@@ -0,0 +1,187 @@
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixListener;
+use tempfile::tempdir;
+use tos_common::daemon::crash::{self, CrashFrame, CrashReport};
+
+fn frame(symbol: &str) -> CrashFrame {
+    CrashFrame {
+        ip: "0x1000".to_string(),
+        offset: Some("0x200".to_string()),
+        symbol: Some(symbol.to_string()),
+        file: None,
+        line: None,
+    }
+}
+
+fn report(service: &str, symbols: &[&str]) -> CrashReport {
+    let mut report = CrashReport::new(service, "index out of bounds: 7", "src/main.rs:10:5");
+    report.frames = symbols.iter().map(|s| frame(s)).collect();
+    report.sign();
+    report
+}
+
+#[test]
+fn test_signature_follows_application_frames() {
+    let first = report(
+        "tos-searchd",
+        &[
+            "backtrace::backtrace::trace::h0123456789abcdef",
+            "std::panicking::rust_panic_with_hook::h0123456789abcdef",
+            "tos_searchd::index::Index::add::h1111111111111111",
+            "tos_searchd::main::h2222222222222222",
+        ],
+    );
+    // The same code built again: new hashes, same signature.
+    let rebuilt = report(
+        "tos-searchd",
+        &[
+            "tos_searchd::index::Index::add::h3333333333333333",
+            "tos_searchd::main::h4444444444444444",
+        ],
+    );
+    assert_eq!(first.signature, rebuilt.signature);
+    assert_eq!(
+        first.top_frame().and_then(|f| f.function()),
+        Some("tos_searchd::index::Index::add")
+    );
+
+    let elsewhere = report(
+        "tos-searchd",
+        &["tos_searchd::query::run::h5555555555555555"],
+    );
+    assert_ne!(first.signature, elsewhere.signature);
+    let other_service = report(
+        "tos-sessiond",
+        &["tos_searchd::index::Index::add::h1111111111111111"],
+    );
+    assert_ne!(first.signature, other_service.signature);
+
+    // Without symbols the location stands in, ignoring the column.
+    let mut moved = report("tos-searchd", &[]);
+    moved.location = "src/main.rs:10:9".to_string();
+    moved.sign();
+    assert_eq!(report("tos-searchd", &[]).signature, moved.signature);
+}
+
+#[test]
+fn test_parse_accepts_legacy_and_plain_reports() {
+    let plain = CrashReport::parse("SIGSEGV at 0x41414141");
+    assert_eq!(plain.service, "unknown");
+    assert_eq!(plain.message, "SIGSEGV at 0x41414141");
+    assert!(!plain.signature.is_empty());
+    // A stored line always reads back with the same id.
+    assert_eq!(CrashReport::parse("SIGSEGV at 0x41414141").id, plain.id);
+
+    let legacy = CrashReport::parse(
+        r#"{"ts":1709299400,"service":"tos-loggerd","thread":"main","message":"boom",
+            "location":"src/main.rs:3:1","backtrace":"   0: backtrace::trace"}"#,
+    );
+    assert_eq!(legacy.service, "tos-loggerd");
+    assert!(legacy.frames.is_empty());
+    assert_eq!(legacy.backtrace, "   0: backtrace::trace");
+
+    let current = report("tos-searchd", &["tos_searchd::main"]);
+    let back = CrashReport::parse(&serde_json::to_string(&current).unwrap());
+    assert_eq!(back, current);
+}
+
+#[test]
+fn test_spooled_reports_wait_for_the_brain() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let spool = dir.path().join("crash-spool");
+    let socket = dir.path().join("brain.sock");
+    let socket_path = socket.to_str().unwrap().to_string();
+
+    let first = report("tos-searchd", &["tos_searchd::main"]);
+    let second = report("tos-sessiond", &["tos_sessiond::main"]);
+    crash::spool(&spool, &first)?;
+    crash::spool(&spool, &second)?;
+
+    // No Brain yet: nothing is lost.
+    assert!(crash::flush_spool(&spool, &socket_path).is_err());
+    assert_eq!(std::fs::read_dir(&spool)?.count(), 2);
+
+    let listener = UnixListener::bind(&socket)?;
+    let brain = std::thread::spawn(move || {
+        let mut received = Vec::new();
+        for _ in 0..2 {
+            let (stream, _) = listener.accept().unwrap();
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line).unwrap();
+            received.push(line.trim().to_string());
+            (&stream).write_all(b"OK\n").unwrap();
+        }
+        received
+    });
+    assert_eq!(crash::flush_spool(&spool, &socket_path)?, 2);
+    assert_eq!(std::fs::read_dir(&spool)?.count(), 0);
+
+    let received = brain.join().unwrap();
+    let ids: Vec<String> = received
+        .iter()
+        .map(|l| CrashReport::parse(l.strip_prefix("crash:").unwrap()).id)
+        .collect();
+    assert!(ids.contains(&first.id) && ids.contains(&second.id));
+    Ok(())
+}
+
+/// With the shipped config, the crash handler's socket is the one the
+/// Brain binds, so reports reach tos-loggerd through it.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_crash_reports_reach_the_brain_socket() -> anyhow::Result<()> {
+    use std::sync::{Arc, Mutex};
+    use tos_common::brain::ipc_handler::IpcHandler;
+
+    std::env::set_var("TOS_CONFIG", concat!(env!("CARGO_MANIFEST_DIR"), "/../tos.toml"));
+    let config = tos_common::TosConfig::load();
+    assert_ne!(config.remote.uds_path, tos_common::TosConfig::default().remote.uds_path);
+
+    let state = Arc::new(Mutex::new(tos_common::TosState::default()));
+    let services = Arc::new(tos_common::services::ServiceManager::new());
+    let mm = Arc::new(tos_common::brain::module_manager::ModuleManager::new(
+        std::path::PathBuf::from("/tmp"),
+    ));
+    let shells = Arc::new(Mutex::new(tos_common::brain::shell::ShellPool::new(
+        state.clone(),
+        mm,
+        services.ai.clone(),
+        services.heuristic.clone(),
+    )));
+
+    // A loggerd that keeps the first line it is sent.
+    let loggerd = std::net::TcpListener::bind("127.0.0.1:0")?;
+    services
+        .registry
+        .lock()
+        .unwrap()
+        .register("tos-loggerd", loggerd.local_addr()?.port(), "127.0.0.1");
+    let received = std::thread::spawn(move || {
+        let (stream, _) = loggerd.accept().unwrap();
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).unwrap();
+        line
+    });
+
+    let ipc = Arc::new(IpcHandler::new(state, shells, services));
+    let server = tos_common::platform::RemoteServer::new(ipc);
+    tokio::spawn(async move { server.run(27300).await });
+
+    let socket = config.remote.uds_path;
+    let sent = report("tos-searchd", &["tos_searchd::main"]);
+    let id = sent.id.clone();
+    let mut delivered = Err(anyhow::anyhow!("Brain socket never came up"));
+    for _ in 0..30 {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        let (socket, sent) = (socket.clone(), sent.clone());
+        delivered = tokio::task::spawn_blocking(move || crash::forward(&socket, &sent)).await?;
+        if delivered.is_ok() {
+            break;
+        }
+    }
+    delivered?;
+
+    let line = received.join().unwrap();
+    let forwarded = CrashReport::parse(line.trim().strip_prefix("crash:").unwrap());
+    assert_eq!(forwarded.id, id);
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::error::TryRecvError;
+use tos_common::brain::ipc_handler::IpcHandler;
+use tos_common::services::events::{topic_matches, BusEvent};
+use tos_common::services::EventBus;
+use tos_common::TosState;
+
+fn drain(rx: &mut tokio::sync::broadcast::Receiver<BusEvent>) -> Vec<BusEvent> {
+    let mut events = Vec::new();
+    loop {
+        match rx.try_recv() {
+            Ok(ev) => events.push(ev),
+            Err(TryRecvError::Empty) => return events,
+            Err(e) => panic!("unexpected bus error: {:?}", e),
+        }
+    }
+}
+
+#[test]
+fn test_topic_patterns() {
+    assert!(topic_matches("system_log", "system_log"));
+    assert!(!topic_matches("system_log", "system_log_extra"));
+    assert!(topic_matches("hub:*", "hub:1234:output"));
+    assert!(topic_matches("*", "confirmation"));
+    assert!(!topic_matches("sector:*", "hub:1234:output"));
+}
+
+#[test]
+fn test_bus_reports_lag_to_slow_subscribers() {
+    let bus = EventBus::new();
+    let mut rx = bus.subscribe();
+    for i in 0..(EventBus::CAPACITY + 10) {
+        bus.publish("hub:x:output", serde_json::json!({ "i": i }));
+    }
+    assert!(matches!(rx.try_recv(), Err(TryRecvError::Lagged(10))));
+    assert_eq!(rx.try_recv().unwrap().payload["i"], 10);
+}
+
+#[tokio::test]
+async fn test_ipc_mutations_publish_topics() {
+    let state = Arc::new(Mutex::new(TosState::default()));
+    let services = Arc::new(tos_common::services::ServiceManager::new());
+    let mm = Arc::new(tos_common::brain::module_manager::ModuleManager::new(
+        std::path::PathBuf::from("/tmp"),
+    ));
+    let shells = Arc::new(Mutex::new(tos_common::brain::shell::ShellPool::new(
+        state.clone(),
+        mm,
+        services.ai.clone(),
+        services.heuristic.clone(),
+    )));
+    let ipc = IpcHandler::new(state.clone(), shells, services);
+    let mut rx = ipc.events().subscribe();
+    let sector_id = state.lock().unwrap().sectors[0].id;
+
+    ipc.handle_request("system_log_append:2;deploy finished");
+    let events = drain(&mut rx);
+    let log = events.iter().find(|e| e.topic == "system_log").unwrap();
+    assert_eq!(log.payload["text"], "deploy finished");
+
+    ipc.handle_request("ai_history_append:hello");
+    let events = drain(&mut rx);
+    let version = state.lock().unwrap().version;
+    assert!(events
+        .iter()
+        .any(|e| e.topic == format!("sector:{}", sector_id) && e.payload["event"] == "updated"));
+    assert!(events
+        .iter()
+        .any(|e| e.topic == EventBus::STATE_TOPIC && e.payload["version"] == version));
+
+    // Reads publish nothing.
+    ipc.handle_request("get_state:");
+    assert!(drain(&mut rx).is_empty());
+}
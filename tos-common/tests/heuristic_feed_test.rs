@@ -0,0 +1,75 @@
+use std::sync::{Arc, Mutex};
+use tos_common::services::registry::ServiceRegistry;
+use tos_common::services::HeuristicService;
+use tos_common::TosState;
+
+fn service() -> HeuristicService {
+    HeuristicService::new(Arc::new(Mutex::new(ServiceRegistry::new(0))))
+}
+
+fn run(state: &mut TosState, command: &str, exit: i32) {
+    let hub = &mut state.sectors[0].hubs[0];
+    hub.begin_command_block(Some(command), true);
+    hub.finish_command_block(None, Some(exit));
+}
+
+#[test]
+fn test_finished_commands_are_collected_once() {
+    let mut state = TosState::default();
+    let sector_id = state.sectors[0].id;
+    let heuristic = service();
+    assert!(heuristic.collect_history(&state).is_empty());
+
+    run(&mut state, "cargo build", 101);
+    state.sectors[0].hubs[0].begin_command_block(Some("cargo watch"), true);
+
+    let entries = heuristic.collect_history(&state);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].command, "cargo build");
+    assert_eq!(entries[0].sector_id, Some(sector_id));
+    assert_eq!(entries[0].exit_status, Some(101));
+    assert_eq!(
+        entries[0].id,
+        state.sectors[0].hubs[0].command_blocks[0].id.to_string()
+    );
+    assert!(heuristic.collect_history(&state).is_empty());
+}
+
+#[test]
+fn test_incognito_commands_are_never_learned() {
+    let mut state = TosState::default();
+    let heuristic = service();
+    state.privacy_active = true;
+    run(&mut state, "export TOKEN=secret", 0);
+    assert!(heuristic.collect_history(&state).is_empty());
+
+    // Leaving incognito does not backfill what ran meanwhile.
+    state.privacy_active = false;
+    assert!(heuristic.collect_history(&state).is_empty());
+    run(&mut state, "ls", 0);
+    assert_eq!(heuristic.collect_history(&state).len(), 1);
+}
+
+#[test]
+fn test_history_import_respects_privacy() {
+    let mut state = TosState::default();
+    let heuristic = service();
+    assert_eq!(HeuristicService::import_blocked(&state), None);
+
+    state.privacy_active = true;
+    assert!(HeuristicService::import_blocked(&state).is_some());
+    assert!(!heuristic.claim_first_import(&state));
+
+    state.privacy_active = false;
+    state
+        .settings
+        .global
+        .insert("tos.privacy.history_import".to_string(), "false".to_string());
+    assert!(HeuristicService::import_blocked(&state).is_some());
+    assert!(!heuristic.claim_first_import(&state));
+
+    // Once allowed, the first-run import is claimed only once.
+    state.settings.global.remove("tos.privacy.history_import");
+    assert!(heuristic.claim_first_import(&state));
+    assert!(!heuristic.claim_first_import(&state));
+}
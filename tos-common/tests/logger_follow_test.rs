@@ -0,0 +1,170 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tos_common::services::logger::{LogRecord, LoggerService, Severity, LOG_RECORD_VERSION};
+use tos_common::services::registry::ServiceRegistry;
+
+fn record(level: Severity, data: &str) -> LogRecord {
+    LogRecord {
+        ts: 1709299400,
+        ..LogRecord::new(level, "brain", "log", data)
+    }
+}
+
+#[test]
+fn test_severity_reads_names_and_priorities() {
+    assert_eq!(Severity::parse("3"), Severity::Critical);
+    assert_eq!(Severity::parse("WARN"), Severity::Warning);
+    assert_eq!(Severity::parse("error"), Severity::Critical);
+    assert_eq!(Severity::parse("ai"), Severity::Info);
+    assert_eq!(Severity::from_priority(7).priority(), 3);
+    assert!(Severity::Debug < Severity::Info);
+
+    let level: Severity = serde_json::from_str("2").unwrap();
+    assert_eq!(level, Severity::Warning);
+    assert_eq!(serde_json::to_string(&level).unwrap(), r#""warning""#);
+}
+
+#[test]
+fn test_record_schema_round_trips() {
+    let sector = uuid::Uuid::new_v4();
+    let record = LogRecord {
+        sector_id: Some(sector),
+        ..record(Severity::Info, "line one;\nline two")
+    }
+    .with_correlation_id("req-1")
+    .with_field("exit_status", 1);
+    let json = serde_json::to_value(&record).unwrap();
+    assert_eq!(json["v"], LOG_RECORD_VERSION);
+    assert_eq!(json["level"], "info");
+    assert_eq!(json["sector_id"], sector.to_string());
+    assert!(json.get("pane_id").is_none(), "unset ids are left out");
+    let back: LogRecord = serde_json::from_value(json).unwrap();
+    assert_eq!(back, record);
+
+    // A record from before the schema reads back as version 0.
+    let old: LogRecord = serde_json::from_str(
+        r#"{"ts":1,"level":"3","source":"security","event":"log","data":"x"}"#,
+    )
+    .unwrap();
+    assert_eq!((old.v, old.level), (0, Severity::Critical));
+    assert!(old.fields.is_empty());
+}
+
+#[tokio::test]
+async fn test_follow_holds_one_subscription_and_reconnects() -> anyhow::Result<()> {
+    // A stand-in for tos-loggerd, registered like the real one.
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    let registry = Arc::new(Mutex::new(ServiceRegistry::new(7000)));
+    registry
+        .lock()
+        .unwrap()
+        .register("tos-loggerd", port, "127.0.0.1");
+    let logger = Arc::new(LoggerService::with_registry(registry));
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let follower = logger.clone();
+    tokio::spawn(async move {
+        follower
+            .follow(serde_json::json!({ "min_level": 2 }), move |r| {
+                let _ = tx.send(r);
+            })
+            .await
+    });
+
+    let wait = Duration::from_secs(10);
+    for data in ["before restart", "after restart"] {
+        let (socket, _) = tokio::time::timeout(wait, listener.accept()).await??;
+        let (reader, mut writer) = socket.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        assert_eq!(
+            lines.next_line().await?.as_deref(),
+            Some(r#"subscribe:{"min_level":2}"#)
+        );
+        writer.write_all(b"OK: Subscribed\n").await?;
+        writer.write_all(b"lagged:3\n").await?;
+        let line = format!(
+            "record:{}\n",
+            serde_json::to_string(&record(Severity::Warning, data))?
+        );
+        writer.write_all(line.as_bytes()).await?;
+
+        let received = tokio::time::timeout(wait, rx.recv()).await?.unwrap();
+        assert_eq!(received, record(Severity::Warning, data));
+        // Dropping the connection, as a daemon restart would, makes the
+        // follower subscribe again.
+    }
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rpc_records_carry_the_request_id() -> anyhow::Result<()> {
+    use std::io::BufRead;
+    use tos_common::brain::ipc_handler::IpcHandler;
+    use tos_common::services::logger::LogContext;
+
+    // A stand-in for tos-loggerd that hands back each line it receives.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for socket in listener.incoming().flatten() {
+            let mut line = String::new();
+            let _ = std::io::BufReader::new(socket).read_line(&mut line);
+            let _ = tx.send(line);
+        }
+    });
+
+    let state = Arc::new(Mutex::new(tos_common::TosState::default()));
+    let services = Arc::new(tos_common::services::ServiceManager::new());
+    services
+        .registry
+        .lock()
+        .unwrap()
+        .register("tos-loggerd", port, "127.0.0.1");
+    let modules = tempfile::tempdir()?;
+    let mm = Arc::new(tos_common::brain::module_manager::ModuleManager::new(
+        modules.path().to_path_buf(),
+    ));
+    let shells = Arc::new(Mutex::new(tos_common::brain::shell::ShellPool::new(
+        state.clone(),
+        mm,
+        services.ai.clone(),
+        services.heuristic.clone(),
+    )));
+    let ipc = IpcHandler::new(state, shells, services.clone());
+    let wait = Duration::from_secs(5);
+
+    let reply = ipc.handle_request(
+        r#"{"jsonrpc":"2.0","id":"req-42","method":"ai_archive_interaction","params":["chat","why?","because"]}"#,
+    );
+    assert!(reply.contains("req-42"), "{}", reply);
+    let line = rx.recv_timeout(wait)?;
+    let record: LogRecord = serde_json::from_str(line.trim().strip_prefix("record:").unwrap())?;
+    assert_eq!(record.event, "ai_exchange");
+    assert_eq!(record.correlation_id.as_deref(), Some("req-42"));
+
+    ipc.handle_request(r#"{"jsonrpc":"2.0","id":7,"method":"crash","params":["face panicked"]}"#);
+    let line = rx.recv_timeout(wait)?;
+    let report: serde_json::Value =
+        serde_json::from_str(line.trim().strip_prefix("crash:").unwrap())?;
+    assert_eq!(report["correlation_id"], "7");
+
+    // Outside a request nothing is added; a placed scope adds its hub.
+    services.logger.log("idle", 1);
+    let record: LogRecord =
+        serde_json::from_str(rx.recv_timeout(wait)?.trim().strip_prefix("record:").unwrap())?;
+    assert_eq!((record.correlation_id, record.hub_id), (None, None));
+    let hub = uuid::Uuid::new_v4();
+    LogContext {
+        hub_id: Some(hub),
+        ..Default::default()
+    }
+    .scope(|| services.logger.log("placed", 1));
+    let record: LogRecord =
+        serde_json::from_str(rx.recv_timeout(wait)?.trim().strip_prefix("record:").unwrap())?;
+    assert_eq!(record.hub_id, Some(hub));
+    Ok(())
+}
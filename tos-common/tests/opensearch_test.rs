@@ -7,16 +7,14 @@ async fn test_opensearch_xml_retrieval() {
     let state = Arc::new(Mutex::new(tos_common::TosState::default()));
     let services = Arc::new(tos_common::services::ServiceManager::new());
     
-    // Create a dummy shell for the IPC handler
+    // Create an empty shell pool for the IPC handler
     let mm = Arc::new(tos_common::brain::module_manager::ModuleManager::new(std::path::PathBuf::from("/tmp")));
-    let shell = Arc::new(Mutex::new(tos_common::brain::shell::ShellApi::new(
+    let shell = Arc::new(Mutex::new(tos_common::brain::shell::ShellPool::new(
         state.clone(),
         mm,
         services.ai.clone(),
         services.heuristic.clone(),
-        uuid::Uuid::new_v4(),
-        uuid::Uuid::new_v4(),
-    ).unwrap()));
+    )));
 
     let ipc = Arc::new(IpcHandler::new(state, shell, services));
     let server = RemoteServer::new(ipc);
@@ -0,0 +1,202 @@
+use std::sync::{Arc, Mutex};
+use tos_common::brain::ipc_handler::IpcHandler;
+use tos_common::brain::module_manager::ModuleManager;
+use tos_common::brain::sector::SectorManager;
+use tos_common::services::priority::{self, SectorSignals};
+use tos_common::services::priority_rules::{self, PriorityRule, RuleAction};
+use tos_common::{TerminalLine, TosState};
+
+fn rule(json: serde_json::Value) -> PriorityRule {
+    serde_json::from_value(json).unwrap()
+}
+
+fn line(text: &str) -> TerminalLine {
+    TerminalLine {
+        text: text.to_string(),
+        priority: 1,
+        timestamp: chrono::Local::now(),
+        source: None,
+    }
+}
+
+/// A "Build" sector whose hub ran `cargo test` (exit 101) and then
+/// `cargo build` (exit 0).
+fn build_state() -> TosState {
+    let mut state = TosState::default();
+    SectorManager::create_sector(&mut state, "Build".to_string());
+    let hub = &mut state.sectors[1].hubs[0];
+    hub.begin_command_block(Some("RUST_LOG=debug cargo test --workspace"), true);
+    hub.push_output_line(line("test parser::nested ... FAILED"));
+    hub.finish_command_block(None, Some(101));
+    hub.begin_command_block(Some("cargo build"), true);
+    hub.push_output_line(line("Finished dev profile"));
+    hub.finish_command_block(None, Some(0));
+    state
+}
+
+#[test]
+fn test_rules_round_trip_through_settings() {
+    let mut state = TosState::default();
+    assert!(priority_rules::load(&state.settings).is_empty());
+
+    let rules = vec![rule(serde_json::json!({
+        "id": "scratch",
+        "when": { "sector": "(?i)^scratch$" },
+        "actions": [{ "type": "silence" }, { "type": "set_rank", "rank": 1 }],
+    }))];
+    priority_rules::store(&mut state.settings, &rules);
+    let loaded = priority_rules::load(&state.settings);
+    assert_eq!(loaded, rules);
+    assert!(loaded[0].enabled);
+    assert_eq!(loaded[0].actions[0], RuleAction::Silence);
+}
+
+#[test]
+fn test_invalid_rules_are_rejected() {
+    let bad_regex = rule(serde_json::json!({
+        "id": "bad", "when": { "output": "(" }, "actions": [{ "type": "silence" }],
+    }));
+    assert!(bad_regex.compile().unwrap_err().to_string().contains("output"));
+
+    let bad_rank = rule(serde_json::json!({
+        "id": "bad", "actions": [{ "type": "set_rank", "rank": 9 }],
+    }));
+    assert!(bad_rank.compile().is_err());
+
+    let no_actions = rule(serde_json::json!({ "id": "bad", "actions": [] }));
+    assert!(no_actions.compile().is_err());
+}
+
+#[test]
+fn test_command_rules_match_process_and_exit_status() {
+    let state = build_state();
+    let failing_tests = rule(serde_json::json!({
+        "id": "cargo-test",
+        "when": { "process": "^cargo$", "command": "\\btest\\b", "exit_status": "failure", "output": "FAILED" },
+        "actions": [{ "type": "adjust", "by": 2 }],
+    }))
+    .compile()
+    .unwrap();
+
+    let matches = failing_tests.matches(&state.sectors[1], None);
+    assert_eq!(matches.len(), 1);
+    assert!(matches[0].key.starts_with("block:"));
+    assert!(matches[0].evidence.contains("exited 101"));
+    assert!(matches[0].evidence.contains("parser::nested ... FAILED"));
+    assert!(failing_tests.matches(&state.sectors[0], None).is_empty());
+
+    let code = rule(serde_json::json!({
+        "id": "ok", "when": { "exit_status": 0 }, "actions": [{ "type": "adjust", "by": -1 }],
+    }))
+    .compile()
+    .unwrap();
+    let matches = code.matches(&state.sectors[1], None);
+    assert_eq!(matches.len(), 1);
+    assert!(matches[0].evidence.starts_with("`cargo build`"));
+
+    // Evidence older than `since` does not count.
+    let later = chrono::Local::now() + chrono::Duration::seconds(5);
+    assert!(failing_tests.matches(&state.sectors[1], Some(later)).is_empty());
+}
+
+#[test]
+fn test_output_rules_match_lines() {
+    let state = build_state();
+    let panics = rule(serde_json::json!({
+        "id": "failed", "when": { "output": "FAILED$" }, "actions": [{ "type": "notify" }],
+    }))
+    .compile()
+    .unwrap();
+    let matches = panics.matches(&state.sectors[1], None);
+    assert_eq!(matches.len(), 1);
+    assert!(matches[0].key.starts_with("line:"));
+    assert_eq!(matches[0].evidence, "test parser::nested ... FAILED");
+}
+
+#[test]
+fn test_rule_hits_adjust_and_pin_the_rank() {
+    let state = build_state();
+    let compiled = priority_rules::compile_all(&[
+        rule(serde_json::json!({
+            "id": "cargo-test",
+            "when": { "command": "cargo test", "exit_status": "failure" },
+            "actions": [{ "type": "adjust", "by": 2 }, { "type": "earcon", "name": "alert" }],
+        })),
+        rule(serde_json::json!({
+            "id": "disabled", "enabled": false, "actions": [{ "type": "set_rank", "rank": 5 }],
+        })),
+    ]);
+    assert_eq!(compiled.len(), 1);
+
+    let hits = priority_rules::evaluate(&compiled, &state.sectors[1], None);
+    assert_eq!(hits.len(), 1);
+    let signals = SectorSignals {
+        rules: hits.iter().map(|(r, m)| r.hit(m)).collect(),
+        ..Default::default()
+    };
+    let score = priority::score(&signals);
+    assert_eq!(score.rank, 3);
+    assert!(!score.silenced);
+    let factor = score.breakdown.last().unwrap();
+    assert_eq!(factor.name, "rule:cargo-test");
+    assert_eq!(factor.contribution, 0.0);
+    assert!(score.explain().starts_with(
+        "Rule 'cargo-test' raised rank by 2: `RUST_LOG=debug cargo test --workspace` exited 101"
+    ));
+
+    let scratch = priority_rules::compile_all(&[rule(serde_json::json!({
+        "id": "scratch",
+        "when": { "sector": "^Build$" },
+        "actions": [{ "type": "silence" }, { "type": "set_rank", "rank": 1 }],
+    }))]);
+    let hits = priority_rules::evaluate(&scratch, &state.sectors[1], None);
+    let signals = SectorSignals {
+        pending_confirmation: true,
+        failing_hubs: 2,
+        rules: hits.iter().map(|(r, m)| r.hit(m)).collect(),
+        ..Default::default()
+    };
+    let score = priority::score(&signals);
+    assert_eq!(score.rank, 1);
+    assert!(score.silenced);
+}
+
+#[tokio::test]
+async fn test_rule_test_command_matches_recorded_output() {
+    let state = Arc::new(Mutex::new(build_state()));
+    let services = Arc::new(tos_common::services::ServiceManager::new());
+    let mm = Arc::new(ModuleManager::new(std::path::PathBuf::from("./modules")));
+    let shells = Arc::new(Mutex::new(tos_common::brain::shell::ShellPool::new(
+        state.clone(),
+        mm,
+        services.ai.clone(),
+        services.heuristic.clone(),
+    )));
+    let ipc = IpcHandler::new(state.clone(), shells, services);
+
+    let request = serde_json::json!({
+        "rule": {
+            "id": "cargo-test",
+            "when": { "process": "cargo", "exit_status": "failure" },
+            "actions": [{ "type": "adjust", "by": 1 }],
+        },
+    });
+    let reply: serde_json::Value =
+        serde_json::from_str(&ipc.handle_request(&format!("priority_rule_test:{}", request)))
+            .unwrap();
+    let results = reply.as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["sector"], "Build");
+    assert_eq!(results[0]["match_count"], 1);
+    assert!(results[0]["effect"]
+        .as_str()
+        .unwrap()
+        .contains("raised rank by 1"));
+
+    // Nothing saved yet, so the saved rules match nothing.
+    assert_eq!(ipc.handle_request("priority_rule_test:"), "[]");
+    assert!(ipc
+        .handle_request(r#"priority_rule_set:{"id":"x","when":{"cwd":"["},"actions":[{"type":"silence"}]}"#)
+        .starts_with("ERROR"));
+    assert_eq!(ipc.handle_request("priority_rules:"), "[]");
+}
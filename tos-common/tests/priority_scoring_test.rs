@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use tos_common::brain::sector::SectorManager;
+use tos_common::services::priority::{self, ProcessActivity, SectorSignals, WEIGHTS};
+use tos_common::{ConfirmationRequest, TerminalLine, TosState};
+
+fn contribution(score: &priority::PriorityScore, name: &str) -> f32 {
+    score
+        .breakdown
+        .iter()
+        .find(|f| f.name == name)
+        .map(|f| f.contribution)
+        .unwrap()
+}
+
+#[test]
+fn test_quiet_sector_ranks_lowest() {
+    let score = priority::score(&SectorSignals::default());
+    assert_eq!(score.rank, 1);
+    assert_eq!(score.total_score, 0.0);
+    assert_eq!(score.breakdown.len(), WEIGHTS.len());
+    assert_eq!(score.explain(), "Nothing needs attention");
+}
+
+#[test]
+fn test_signals_raise_the_score_with_reasons() {
+    let signals = SectorSignals {
+        failing_hubs: 1,
+        recent_failures: 2,
+        error_lines: 5,
+        pending_confirmation: true,
+        activity: Some(ProcessActivity {
+            processes: 3,
+            cpu_percent: 150.0,
+            memory_bytes: 64 * 1024 * 1024,
+            memory_fraction: 0.01,
+        }),
+        ..Default::default()
+    };
+    let score = priority::score(&signals);
+    assert!(score.rank >= 4, "rank {}", score.rank);
+
+    let sum: f32 = score.breakdown.iter().map(|f| f.contribution).sum();
+    assert!((sum - score.total_score).abs() < 1e-5);
+    assert_eq!(contribution(&score, "focus"), 0.0);
+    assert_eq!(score.factors["activity"], 1.0);
+
+    let explanation = score.explain();
+    assert!(explanation.contains("A command awaits confirmation"));
+    assert!(explanation.contains("150% CPU, 64 MiB across 3 processes"));
+    assert!(explanation.contains("5 error lines"));
+}
+
+#[test]
+fn test_focus_fades_after_the_sector_loses_it() {
+    let focused = priority::score(&SectorSignals {
+        focused: true,
+        ..Default::default()
+    });
+    let recent = priority::score(&SectorSignals {
+        seconds_since_focus: Some(priority::FOCUS_HALF_LIFE_SECS as i64),
+        ..Default::default()
+    });
+    let never = priority::score(&SectorSignals::default());
+    let full = contribution(&focused, "focus");
+    assert!((contribution(&recent, "focus") - full / 2.0).abs() < 1e-5);
+    assert_eq!(contribution(&never, "focus"), 0.0);
+    assert!(recent.breakdown[0].reason.starts_with("Focused 5m ago"));
+}
+
+#[test]
+fn test_signals_are_read_per_sector() {
+    let mut state = TosState::default();
+    SectorManager::create_sector(&mut state, "Build".to_string());
+    let now = chrono::Local::now();
+    let (first, second) = (state.sectors[0].id, state.sectors[1].id);
+    let hub_id = state.sectors[1].hubs[0].id;
+    {
+        let hub = &mut state.sectors[1].hubs[0];
+        hub.begin_command_block(Some("cargo build"), true);
+        hub.push_output_line(TerminalLine {
+            text: "error: could not compile".to_string(),
+            priority: 3,
+            timestamp: now,
+            source: None,
+        });
+        hub.finish_command_block(None, Some(101));
+        hub.last_exit_status = Some(101);
+    }
+    state.pending_confirmation = Some(ConfirmationRequest {
+        id: uuid::Uuid::new_v4(),
+        sector_id: Some(second),
+        original_request: "force_prompt_submit:rm -rf build".to_string(),
+        message: String::new(),
+        progress: 0.0,
+    });
+    state.active_sector_index = 0;
+    let pids = HashMap::from([(hub_id, 4242)]);
+
+    let quiet = SectorManager::priority_signals(&state, &state.sectors[0], &pids, None, now);
+    assert_eq!(quiet.sector_id, first);
+    assert!(quiet.focused);
+    assert_eq!(quiet.failing_hubs, 0);
+    assert!(!quiet.pending_confirmation);
+    assert!(quiet.shell_pids.is_empty());
+
+    let busy = SectorManager::priority_signals(
+        &state,
+        &state.sectors[1],
+        &pids,
+        Some(now.timestamp() - 90),
+        now,
+    );
+    assert!(!busy.focused);
+    assert_eq!(busy.seconds_since_focus, Some(90));
+    assert_eq!(busy.failing_hubs, 1);
+    assert_eq!(busy.recent_failures, 1);
+    assert_eq!(busy.error_lines, 1);
+    assert!(busy.pending_confirmation);
+    assert_eq!(busy.shell_pids, vec![4242]);
+    assert!(priority::score(&busy).total_score > priority::score(&quiet).total_score);
+}
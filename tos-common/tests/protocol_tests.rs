@@ -255,6 +255,9 @@ mod tests {
             version: 0,
             last_exit_status: None,
             is_running: false,
+            screen: None,
+            command_blocks: vec![],
+            output_offset: 0,
         };
         let hub_b = CommandHub {
             id: uuid::Uuid::new_v4(),
@@ -277,6 +280,9 @@ mod tests {
             version: 0,
             last_exit_status: Some(0),
             is_running: false,
+            screen: None,
+            command_blocks: vec![],
+            output_offset: 0,
         };
 
         let sector = Sector {
@@ -289,6 +295,7 @@ mod tests {
             disconnected: false,
             trust_tier: TrustTier::Standard,
             priority: 3,
+            priority_factors: Vec::new(),
             active_apps: vec![],
             active_app_index: 0,
             participants: vec![],
@@ -302,4 +309,268 @@ mod tests {
             CommandHubMode::Ai
         );
     }
+
+    // -----------------------------------------------------------------------
+    // JSON-RPC Framing (§3.3.7)
+    // -----------------------------------------------------------------------
+
+    use serde_json::{json, Value};
+    use tos_common::brain::ipc_handler::IpcHandler;
+    use tos_common::rpc;
+
+    const SCHEMA_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../docs/spec/tos_ipc.openrpc.json"
+    );
+
+    fn checked_in_schema() -> Value {
+        let text = std::fs::read_to_string(SCHEMA_PATH).expect("schema document missing");
+        serde_json::from_str(&text).expect("schema document is not JSON")
+    }
+
+    /// Check a request's params against the published schema.
+    fn validate(schema: &Value, method: &str, params: &Value) -> Result<(), String> {
+        let spec = schema["methods"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|m| m["name"] == method)
+            .ok_or_else(|| format!("unknown method {}", method))?;
+        let declared = spec["params"].as_array().unwrap();
+        for (i, param) in declared.iter().enumerate() {
+            let value = match params {
+                Value::Array(items) => items.get(i),
+                Value::Object(map) => map.get(param["name"].as_str().unwrap()),
+                _ => return Err("params must be an array or object".to_string()),
+            };
+            let value = match value {
+                Some(v) => v,
+                None if param["required"] == true => {
+                    return Err(format!("missing {}", param["name"]))
+                }
+                None => continue,
+            };
+            let types: Vec<&str> = match &param["schema"]["type"] {
+                Value::String(t) => vec![t.as_str()],
+                Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+                _ => vec![],
+            };
+            let ok = types.iter().any(|t| match *t {
+                "string" => value.is_string(),
+                "integer" => value.is_i64() || value.is_u64(),
+                "number" => value.is_number(),
+                "boolean" => value.is_boolean(),
+                "object" => value.is_object(),
+                "array" => value.is_array(),
+                _ => false,
+            });
+            if !ok {
+                return Err(format!("{} has the wrong type", param["name"]));
+            }
+        }
+        Ok(())
+    }
+
+    fn ipc() -> (
+        tos_common::brain::ipc_handler::IpcHandler,
+        std::sync::Arc<std::sync::Mutex<TosState>>,
+    ) {
+        use std::sync::{Arc, Mutex};
+        let state = Arc::new(Mutex::new(TosState::default()));
+        let services = Arc::new(tos_common::services::ServiceManager::new());
+        let mm = Arc::new(tos_common::brain::module_manager::ModuleManager::new(
+            std::path::PathBuf::from("/tmp"),
+        ));
+        let shells = Arc::new(Mutex::new(tos_common::brain::shell::ShellPool::new(
+            state.clone(),
+            mm,
+            services.ai.clone(),
+            services.heuristic.clone(),
+        )));
+        let handler =
+            tos_common::brain::ipc_handler::IpcHandler::new(state.clone(), shells, services);
+        (handler, state)
+    }
+
+    fn call(ipc: &tos_common::brain::ipc_handler::IpcHandler, request: Value) -> Value {
+        let schema = checked_in_schema();
+        if let (Some(method), Some(params)) = (request["method"].as_str(), request.get("params")) {
+            validate(&schema, method, params).unwrap();
+        }
+        let reply = ipc.handle_request(&request.to_string());
+        let reply: Value = serde_json::from_str(&reply).unwrap();
+        assert_eq!(reply["jsonrpc"], "2.0");
+        assert_eq!(reply["id"], request["id"]);
+        assert!(reply.get("result").is_some() != reply.get("error").is_some());
+        reply
+    }
+
+    #[test]
+    fn rpc_schema_matches_checked_in_document() {
+        let generated = rpc::schema(&IpcHandler::builtin_commands());
+        if std::env::var_os("TOS_BLESS_SCHEMA").is_some() {
+            let text = serde_json::to_string_pretty(&generated).unwrap() + "\n";
+            std::fs::write(SCHEMA_PATH, text).unwrap();
+        }
+        assert_eq!(
+            checked_in_schema(),
+            generated,
+            "docs/spec/tos_ipc.openrpc.json is stale; rerun with TOS_BLESS_SCHEMA=1"
+        );
+    }
+
+    #[test]
+    fn rpc_schema_covers_registry() {
+        let registry = IpcHandler::builtin_commands();
+        assert!(registry.len() > 150, "command registry looks empty");
+
+        let schema = checked_in_schema();
+        let methods: Vec<&Value> = schema["methods"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|m| m["x-tos-scope"] == "dispatch" && m["name"] != rpc::DISCOVER)
+            .collect();
+        assert_eq!(methods.len(), registry.len());
+        for spec in registry.specs() {
+            let method = methods
+                .iter()
+                .find(|m| m["name"] == spec.name.as_str())
+                .unwrap_or_else(|| panic!("'{}' missing from schema", spec.name));
+            assert_eq!(method["summary"], spec.help.as_str());
+            assert_eq!(method["x-tos-role"], format!("{:?}", spec.role));
+            assert_eq!(method["params"].as_array().unwrap().len(), spec.params.len());
+            assert!(!spec.help.is_empty(), "'{}' has no help text", spec.name);
+        }
+    }
+
+    #[tokio::test]
+    async fn rpc_arguments_are_not_split_on_semicolons() {
+        let (ipc, state) = ipc();
+        let reply = call(
+            &ipc,
+            json!({"jsonrpc": "2.0", "id": 1, "method": "system_log_append",
+                   "params": {"priority": 2, "text": "a;b:c"}}),
+        );
+        assert!(reply.get("result").is_some());
+        let last = state.lock().unwrap().system_log.last().unwrap().text.clone();
+        assert_eq!(last, "a;b:c");
+
+        // The same call in legacy form truncates at the delimiter.
+        ipc.handle_request("system_log_append:2;a;b:c");
+        let last = state.lock().unwrap().system_log.last().unwrap().text.clone();
+        assert_eq!(last, "a");
+
+        // JSON tool arguments keep their semicolons in both forms.
+        state.lock().unwrap().ai_behaviors.push(tos_common::AiBehavior {
+            id: "chat".to_string(),
+            name: "Chat".to_string(),
+            enabled: true,
+            backend_override: None,
+            context_fields: vec![],
+            allowed_tools: Some(vec!["exec_cmd".to_string()]),
+            config: Default::default(),
+        });
+        let tool_call = json!({"name": "exec_cmd", "args": {"cmd": "cd /tmp; ls"}});
+        let reply = call(
+            &ipc,
+            json!({"jsonrpc": "2.0", "id": 2, "method": "ai_tool_call",
+                   "params": {"behavior_id": "chat", "call": tool_call}}),
+        );
+        assert_eq!(reply["result"], "AI_COMMAND_STAGED");
+        let staged = || {
+            let state = state.lock().unwrap();
+            state.sectors[0].hubs[0].staged_command.clone()
+        };
+        assert_eq!(staged().as_deref(), Some("cd /tmp; ls"));
+        let tool_call = json!({"name": "exec_cmd", "args": {"cmd": "a; b; c"}});
+        assert_eq!(
+            ipc.handle_request(&format!("ai_tool_call:chat;{}", tool_call)),
+            "AI_COMMAND_STAGED"
+        );
+        assert_eq!(staged().as_deref(), Some("a; b; c"));
+    }
+
+    #[tokio::test]
+    async fn log_records_keep_their_source_in_the_system_log() {
+        let (ipc, state) = ipc();
+        let record = json!({"ts": 1709299400, "level": "warning", "source": "tos-searchd",
+                            "event": "log", "data": "index; rebuilt\nin 2s"});
+        let reply = call(
+            &ipc,
+            json!({"jsonrpc": "2.0", "id": 1, "method": "system_log_record",
+                   "params": {"record": record}}),
+        );
+        assert_eq!(reply["result"], "LOGGED");
+        let last = state.lock().unwrap().system_log.last().unwrap().clone();
+        assert_eq!(last.text, "index; rebuilt\nin 2s");
+        assert_eq!(last.priority, 2);
+        assert_eq!(last.source.as_deref(), Some("tos-searchd"));
+
+        // AI exchanges stay in the log service.
+        let exchange = json!({"ts": 1, "level": "info", "source": "chat",
+                              "event": "ai_exchange", "data": "hi"});
+        assert_eq!(
+            ipc.handle_request(&format!("system_log_record:{}", exchange)),
+            "IGNORED"
+        );
+        assert!(ipc
+            .handle_request("log_record:not json")
+            .starts_with("ERROR: Invalid log record"));
+    }
+
+    #[tokio::test]
+    async fn rpc_results_and_errors_are_structured() {
+        let (ipc, _) = ipc();
+
+        let reply = call(&ipc, json!({"jsonrpc": "2.0", "id": "s", "method": "get_state"}));
+        assert!(reply["result"]["sectors"].is_array());
+
+        let reply = call(&ipc, json!({"jsonrpc": "2.0", "id": 2, "method": "no_such_command"}));
+        assert_eq!(reply["error"]["code"], rpc::codes::METHOD_NOT_FOUND);
+
+        let reply = ipc.handle_request(
+            r#"{"jsonrpc":"2.0","id":3,"method":"set_active_sector","params":["first"]}"#,
+        );
+        let reply: Value = serde_json::from_str(&reply).unwrap();
+        assert_eq!(reply["error"]["code"], rpc::codes::INVALID_PARAMS);
+        assert!(validate(&checked_in_schema(), "set_active_sector", &json!(["first"])).is_err());
+
+        let reply = call(
+            &ipc,
+            json!({"jsonrpc": "2.0", "id": 4, "method": "editor_save_as",
+                   "params": [uuid::Uuid::new_v4().to_string(), "/tmp/a;b.txt"]}),
+        );
+        assert_eq!(reply["error"]["code"], rpc::codes::COMMAND_FAILED);
+
+        let reply: Value = serde_json::from_str(&ipc.handle_request("{not json")).unwrap();
+        assert_eq!(reply["error"]["code"], rpc::codes::PARSE_ERROR);
+        assert_eq!(reply["id"], Value::Null);
+
+        let reply: Value =
+            serde_json::from_str(&ipc.handle_request(r#"{"jsonrpc":"1.0","id":5,"method":"zoom_in"}"#))
+                .unwrap();
+        assert_eq!(reply["error"]["code"], rpc::codes::INVALID_REQUEST);
+        assert_eq!(reply["id"], 5);
+
+        // Notifications run but get no reply.
+        assert_eq!(
+            ipc.handle_request(r#"{"jsonrpc":"2.0","method":"zoom_in"}"#),
+            ""
+        );
+
+        let viewer = tos_common::services::auth::ClientIdentity {
+            name: "portal".to_string(),
+            role: ParticipantRole::Viewer,
+            method: tos_common::services::auth::AuthMethod::DeviceToken,
+        };
+        let reply = ipc
+            .handle_rpc_as(&viewer, r#"{"jsonrpc":"2.0","id":6,"method":"system_reset"}"#)
+            .unwrap();
+        let reply: Value = serde_json::from_str(&reply).unwrap();
+        assert_eq!(reply["error"]["code"], rpc::codes::PERMISSION_DENIED);
+
+        let reply = call(&ipc, json!({"jsonrpc": "2.0", "id": 7, "method": rpc::DISCOVER}));
+        assert_eq!(reply["result"]["info"]["version"], rpc::PROTOCOL_VERSION);
+    }
 }
@@ -0,0 +1,155 @@
+use std::sync::{Arc, Mutex};
+use tos_common::brain::ipc_handler::IpcHandler;
+use tos_common::collaboration::ParticipantRole;
+use tos_common::config::{ClientCertConfig, DeviceTokenConfig};
+use tos_common::services::auth::{certificate_fingerprint, AuthMethod, ClientIdentity};
+use tos_common::services::{AuthService, PortalService};
+use tos_common::{TosConfig, TosState};
+
+fn auth_with(config: TosConfig) -> (AuthService, Arc<PortalService>) {
+    let portal = Arc::new(PortalService::new());
+    (AuthService::with_config(&config, portal.clone()), portal)
+}
+
+#[test]
+fn test_tokens_and_pinned_certificates() {
+    let cert = b"fake der certificate".to_vec();
+    let mut config = TosConfig::default();
+    config.remote.device_tokens.push(DeviceTokenConfig {
+        name: "tablet".to_string(),
+        token: "s3cret-token".to_string(),
+        role: ParticipantRole::Operator,
+    });
+    config.remote.client_certs.push(ClientCertConfig {
+        name: "laptop".to_string(),
+        sha256: certificate_fingerprint(&cert).to_lowercase(),
+        role: ParticipantRole::CoOwner,
+    });
+    let (auth, portal) = auth_with(config);
+
+    let tablet = auth.authenticate_token(" s3cret-token ").unwrap();
+    assert_eq!(tablet.name, "tablet");
+    assert_eq!(tablet.role, ParticipantRole::Operator);
+    assert_eq!(tablet.method, AuthMethod::DeviceToken);
+    assert!(auth.authenticate_token("s3cret-tokem").is_none());
+    assert!(auth.authenticate_token("").is_none());
+
+    let laptop = auth.authenticate_certificate(&cert).unwrap();
+    assert_eq!(laptop.role, ParticipantRole::CoOwner);
+    assert!(auth.authenticate_certificate(b"other cert").is_none());
+    assert!(auth.has_pinned_certificates());
+
+    // Portal tokens get the configured portal role and stop working on revoke.
+    let sector_id = uuid::Uuid::new_v4();
+    let token = portal.create_token(sector_id);
+    let guest = auth.authenticate_token(&token).unwrap();
+    assert_eq!(guest.role, ParticipantRole::Viewer);
+    assert_eq!(guest.method, AuthMethod::PortalToken { sector_id });
+    portal.revoke_token(&token);
+    assert!(auth.authenticate_token(&token).is_none());
+}
+
+#[test]
+fn test_loopback_trust() {
+    let (auth, _) = auth_with(TosConfig::default());
+    let local = auth.authenticate_peer(&"127.0.0.1:5000".parse().unwrap());
+    assert_eq!(local, Some(ClientIdentity::local()));
+    // IPv4 clients on the dual-stack `[::]` listener appear as mapped addresses.
+    assert!(auth
+        .authenticate_peer(&"[::ffff:127.0.0.1]:5000".parse().unwrap())
+        .is_some());
+    assert!(auth
+        .authenticate_peer(&"192.168.1.20:5000".parse().unwrap())
+        .is_none());
+
+    let mut config = TosConfig::default();
+    config.remote.trust_loopback = false;
+    let (auth, _) = auth_with(config);
+    assert!(auth
+        .authenticate_peer(&"[::1]:5000".parse().unwrap())
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_requests_are_checked_against_role() {
+    let state = Arc::new(Mutex::new(TosState::default()));
+    let services = Arc::new(tos_common::services::ServiceManager::new());
+    let mm = Arc::new(tos_common::brain::module_manager::ModuleManager::new(
+        std::path::PathBuf::from("/tmp"),
+    ));
+    let shells = Arc::new(Mutex::new(tos_common::brain::shell::ShellPool::new(
+        state.clone(),
+        mm,
+        services.ai.clone(),
+        services.heuristic.clone(),
+    )));
+    let ipc = IpcHandler::new(state.clone(), shells, services);
+
+    let viewer = ClientIdentity {
+        name: "portal".to_string(),
+        role: ParticipantRole::Viewer,
+        method: AuthMethod::DeviceToken,
+    };
+    let operator = ClientIdentity {
+        role: ParticipantRole::Operator,
+        ..viewer.clone()
+    };
+
+    assert!(ipc
+        .handle_request_as(&viewer, "get_state:")
+        .contains("\"sectors\""));
+    assert!(ipc
+        .handle_request_as(&viewer, "set_mode:directory")
+        .starts_with("ERROR: Permission denied"));
+    // Predictions and search results come from everyone's command history.
+    for request in ["heuristic_query:", "search:deploy", "search_query:{\"text\":\"x\"}"] {
+        assert!(ipc
+            .handle_request_as(&viewer, request)
+            .starts_with("ERROR: Permission denied"));
+    }
+    assert_eq!(
+        ipc.handle_request_as(&viewer, "cmd:7:tactical_kill_switch"),
+        "res:7:ERROR: Permission denied: 'tactical_kill_switch' requires CoOwner"
+    );
+
+    assert!(!ipc
+        .handle_request_as(&operator, "set_mode:directory")
+        .starts_with("ERROR"));
+    assert_eq!(
+        state.lock().unwrap().sectors[0].hubs[0].mode,
+        tos_common::CommandHubMode::Directory
+    );
+    assert!(ipc
+        .handle_request_as(&operator, "force_prompt_submit:rm -rf /tmp/x")
+        .starts_with("ERROR: Permission denied"));
+    assert!(ipc
+        .handle_request_as(&operator, "system_reset")
+        .starts_with("ERROR: Permission denied"));
+    assert!(ipc
+        .handle_request_as(&operator, "search_root_add:/etc")
+        .starts_with("ERROR: Permission denied"));
+    // Both read or write files on the host.
+    assert!(ipc
+        .handle_request_as(&operator, "heuristic_model_export:model.json")
+        .starts_with("ERROR: Permission denied"));
+    assert!(ipc
+        .handle_request_as(&operator, "heuristic_history_import:bash")
+        .starts_with("ERROR: Permission denied"));
+    // Raw keystrokes reach the shell without trust classification.
+    assert!(ipc
+        .handle_request_as(&operator, "terminal_input:cm0gLXJmIH4N")
+        .starts_with("ERROR: Permission denied"));
+
+    assert_eq!(
+        IpcHandler::required_role("get_anything"),
+        ParticipantRole::Viewer
+    );
+    assert_eq!(
+        IpcHandler::required_role("editor_annotate"),
+        ParticipantRole::Commenter
+    );
+    assert_eq!(
+        IpcHandler::required_role("prompt_submit"),
+        ParticipantRole::Operator
+    );
+}
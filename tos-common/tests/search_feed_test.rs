@@ -0,0 +1,171 @@
+use tos_common::services::search::SearchHitType;
+use tos_common::services::search_feed::SearchFeed;
+use tos_common::{AiMessage, CommandHub, TerminalLine, TosState};
+
+fn line(text: &str) -> TerminalLine {
+    TerminalLine {
+        text: text.to_string(),
+        priority: 1,
+        timestamp: chrono::Local::now(),
+        source: None,
+    }
+}
+
+fn message(role: &str, content: &str) -> AiMessage {
+    AiMessage {
+        role: role.to_string(),
+        content: content.to_string(),
+        timestamp: chrono::Local::now(),
+    }
+}
+
+fn hub(state: &mut TosState) -> &mut CommandHub {
+    &mut state.sectors[0].hubs[0]
+}
+
+fn kinds(records: &[tos_common::services::search::SearchRecord]) -> Vec<SearchHitType> {
+    records.iter().map(|r| r.kind).collect()
+}
+
+#[test]
+fn test_feed_collects_each_kind_once() {
+    let mut state = TosState::default();
+    let sector_id = state.sectors[0].id;
+    let hub_id = state.sectors[0].hubs[0].id;
+    let mut feed = SearchFeed::new();
+    assert!(feed.collect(&state).is_empty());
+
+    let h = hub(&mut state);
+    h.begin_command_block(Some("cargo build"), true);
+    h.push_output_line(line("error[E0425]: cannot find value"));
+    h.finish_command_block(None, Some(101));
+    h.ai_history
+        .push(message("user", "why does the build fail?"));
+    h.ai_history
+        .push(message("assistant", "A variable is misspelled."));
+    state.system_log.push(line("Brain Core Initialized."));
+
+    let records = feed.collect(&state);
+    assert_eq!(
+        kinds(&records),
+        vec![
+            SearchHitType::Command,
+            SearchHitType::Output,
+            SearchHitType::AiExchange,
+            SearchHitType::Log
+        ]
+    );
+    let command = &records[0];
+    assert_eq!(command.title, "cargo build");
+    assert!(command.text.ends_with("exit 101"));
+    assert_eq!(command.sector_id, Some(sector_id));
+    assert_eq!(command.hub_id, Some(hub_id));
+    assert_eq!(records[1].title, "cargo build", "output names its command");
+    assert_eq!(records[2].title, "why does the build fail?");
+    assert!(records[2].text.contains("misspelled"));
+    assert_eq!(records[3].sector_id, None);
+
+    // Nothing new, nothing collected; a grown chunk is pushed again whole.
+    assert!(feed.collect(&state).is_empty());
+    hub(&mut state).push_output_line(line("help: a local variable exists"));
+    let records = feed.collect(&state);
+    assert_eq!(kinds(&records), vec![SearchHitType::Output]);
+    assert_eq!(records[0].text.lines().count(), 2);
+}
+
+#[test]
+fn test_output_chunks_have_stable_keys() {
+    let mut state = TosState::default();
+    let mut feed = SearchFeed::new();
+    for i in 0..SearchFeed::OUTPUT_CHUNK + 5 {
+        hub(&mut state).push_output_line(line(&format!("line {}", i)));
+    }
+    let first = feed.collect(&state);
+    assert_eq!(first.len(), 2);
+    assert_eq!(
+        first[0].text.lines().count() as u64,
+        SearchFeed::OUTPUT_CHUNK
+    );
+
+    // A fresh feed, as after a restart, yields the same keys.
+    let again = SearchFeed::new().collect(&state);
+    let keys = |r: &[tos_common::services::search::SearchRecord]| {
+        r.iter().map(|r| r.key()).collect::<Vec<_>>()
+    };
+    assert_eq!(keys(&first), keys(&again));
+}
+
+#[test]
+fn test_incognito_output_is_never_collected() {
+    let mut state = TosState::default();
+    let mut feed = SearchFeed::new();
+    state.privacy_active = true;
+    hub(&mut state).push_output_line(line("secret token"));
+    hub(&mut state)
+        .ai_history
+        .push(message("assistant", "private answer"));
+    state.system_log.push(line("private log"));
+    assert!(feed.collect(&state).is_empty());
+
+    // Leaving incognito does not backfill what happened meanwhile.
+    state.privacy_active = false;
+    assert!(feed.collect(&state).is_empty());
+
+    state
+        .settings
+        .global
+        .insert("tos.privacy.incognito".to_string(), "true".to_string());
+    hub(&mut state).push_output_line(line("also secret"));
+    assert!(feed.collect(&state).is_empty());
+
+    // Public output later in the same chunk leaves the secrets out.
+    state.settings.global.remove("tos.privacy.incognito");
+    hub(&mut state).push_output_line(line("public"));
+    let records = feed.collect(&state);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].text, "public");
+}
+
+#[test]
+fn test_purge_keeps_earlier_output_out_of_open_chunks() {
+    let mut state = TosState::default();
+    let mut feed = SearchFeed::new();
+    hub(&mut state).push_output_line(line("before purge"));
+    assert_eq!(feed.collect(&state).len(), 1);
+
+    feed.purge(&state.sectors[0].hubs);
+    hub(&mut state).push_output_line(line("after purge"));
+    let records = feed.collect(&state);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].text, "after purge");
+}
+
+#[test]
+fn test_secrets_are_redacted_before_indexing() {
+    let mut state = TosState::default();
+    let (redactor, errors) =
+        tos_common::redact::Redactor::new(&["vault-[0-9a-f]{8}".to_string()]);
+    assert!(errors.is_empty());
+    let mut feed = SearchFeed::with_redactor(redactor);
+
+    let h = hub(&mut state);
+    h.begin_command_block(Some("export DB_PASSWORD=hunter2"), true);
+    h.finish_command_block(None, Some(0));
+    h.begin_command_block(Some("deploy --token vault-0badc0de"), true);
+    h.push_output_line(line("using key vault-0badc0de"));
+    h.finish_command_block(None, Some(0));
+
+    let records = feed.collect(&state);
+    assert_eq!(
+        kinds(&records),
+        vec![SearchHitType::Command, SearchHitType::Command, SearchHitType::Output]
+    );
+    for record in &records {
+        for secret in ["hunter2", "vault-0badc0de"] {
+            assert!(!record.title.contains(secret), "{:?}", record);
+            assert!(!record.text.contains(secret), "{:?}", record);
+        }
+    }
+    assert_eq!(records[0].title, "export DB_PASSWORD=<redacted>");
+    assert_eq!(records[2].text, "using key <redacted>");
+}
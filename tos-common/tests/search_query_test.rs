@@ -0,0 +1,202 @@
+use std::sync::{Arc, Mutex};
+use tos_common::brain::ipc_handler::IpcHandler;
+use tos_common::services::search::{SearchHit, SearchHitType};
+use tos_common::{SearchResult, TerminalLine, TosState};
+
+fn ipc_with_state(state: TosState) -> (IpcHandler, Arc<Mutex<TosState>>) {
+    let state = Arc::new(Mutex::new(state));
+    let services = Arc::new(tos_common::services::ServiceManager::new());
+    let modules = tempfile::tempdir().unwrap();
+    let mm = Arc::new(tos_common::brain::module_manager::ModuleManager::new(
+        modules.path().to_path_buf(),
+    ));
+    let shells = Arc::new(Mutex::new(tos_common::brain::shell::ShellPool::new(
+        state.clone(),
+        mm,
+        services.ai.clone(),
+        services.heuristic.clone(),
+    )));
+    (IpcHandler::new(state.clone(), shells, services), state)
+}
+
+fn line(text: &str) -> TerminalLine {
+    TerminalLine {
+        text: text.to_string(),
+        priority: 1,
+        timestamp: chrono::Local::now(),
+        source: None,
+    }
+}
+
+#[test]
+fn test_search_query_scopes_to_a_sector() {
+    let mut state = TosState::default();
+    let mut other = state.sectors[0].clone();
+    other.id = uuid::Uuid::new_v4();
+    other.name = "Other".to_string();
+    state.sectors[0].hubs[0]
+        .terminal_output
+        .push(line("kestrel in primary"));
+    other.hubs[0].terminal_output.push(line("kestrel in other"));
+    let other_id = other.id;
+    state.sectors.push(other);
+
+    // Search commands block on the daemon, so they run on a plain thread
+    // inside the runtime, as on the Brain's IPC threads.
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let _guard = rt.enter();
+    let (ipc, state) = ipc_with_state(state);
+    let reply = ipc.handle_request(&format!(
+        r#"search_query:{{"text": "kestrel", "sector": "{}"}}"#,
+        other_id
+    ));
+    // No search daemon is running, so there are no indexed hits.
+    assert_eq!(reply, "[]");
+
+    let state = state.lock().unwrap();
+    let hub = &state.sectors[0].hubs[0];
+    let results = hub.search_results.as_ref().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].source_sector, "Other");
+    assert_eq!(results[0].matches, vec!["kestrel in other".to_string()]);
+}
+
+#[test]
+fn test_search_query_errors() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let _guard = rt.enter();
+    let (ipc, _) = ipc_with_state(TosState::default());
+    assert!(ipc
+        .handle_request(r#"search_query:{"text": "x", "sector": "nowhere"}"#)
+        .starts_with("ERROR: Sector not found"));
+    assert!(ipc
+        .handle_request("search_query:not json")
+        .starts_with("ERROR:"));
+    assert_eq!(
+        ipc.handle_request("search_purge_sector:nowhere"),
+        "ERROR: Sector not found: nowhere"
+    );
+}
+
+#[test]
+fn test_result_labels_from_hits() {
+    let hit = |path: &str, line| SearchHit {
+        path: path.to_string(),
+        hit_type: SearchHitType::File,
+        score: 1.0,
+        line,
+        snippet: None,
+        file_type: None,
+        mtime: None,
+        engines: vec![],
+        title: None,
+        sector_id: None,
+        hub_id: None,
+    };
+    let result = SearchResult::from_hits(
+        "Global FS Index",
+        vec![hit("/src/main.rs", Some(12)), hit("/README.md", None)],
+    );
+    assert_eq!(
+        result.matches,
+        vec!["/src/main.rs:12 [FILE]", "/README.md [FILE]"]
+    );
+    assert_eq!(result.hits.len(), 2);
+
+    // Results without structured hits keep their old wire form.
+    let plain = serde_json::to_value(SearchResult {
+        hits: vec![],
+        ..result
+    })
+    .unwrap();
+    assert!(plain.get("hits").is_none());
+}
+
+#[test]
+fn test_search_root_commands_without_daemon() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let _guard = rt.enter();
+    let (ipc, _) = ipc_with_state(TosState::default());
+    assert_eq!(
+        ipc.handle_request("search_root_add:"),
+        "ERROR: Missing path"
+    );
+    assert!(ipc
+        .handle_request("search_roots")
+        .starts_with("ERROR: Search daemon unavailable"));
+    assert!(ipc
+        .handle_request("search_root_remove:/tmp")
+        .starts_with("ERROR: Search daemon unavailable"));
+}
+
+#[test]
+fn test_semantic_search_tool_keeps_its_reply() {
+    let mut state = TosState::default();
+    state.sectors[0].hubs[0]
+        .terminal_output
+        .push(line("kestrel overhead"));
+    state.ai_behaviors.push(tos_common::AiBehavior {
+        id: "chat".to_string(),
+        name: "Chat".to_string(),
+        enabled: true,
+        backend_override: None,
+        context_fields: vec![],
+        allowed_tools: Some(vec!["semantic_search".to_string()]),
+        config: Default::default(),
+    });
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let _guard = rt.enter();
+    let (ipc, state) = ipc_with_state(state);
+    let call = serde_json::json!({"name": "semantic_search", "args": {"query": "kestrel"}});
+    assert_eq!(
+        ipc.handle_request(&format!("ai_tool_call:chat;{}", call)),
+        "SEMANTIC_SEARCH_COMPLETED"
+    );
+    // It runs the hybrid search, so output matches are found too.
+    let state = state.lock().unwrap();
+    let results = state.sectors[0].hubs[0].search_results.as_ref().unwrap();
+    assert!(results.iter().any(|r| r.matches.contains(&"kestrel overhead".to_string())));
+}
+
+#[tokio::test]
+async fn test_sector_roots_leave_configured_roots_alone() {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tos_common::services::registry::ServiceRegistry;
+    use tos_common::services::SearchService;
+
+    // A daemon that already indexes "/configured" and logs every request.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            let (reader, mut writer) = socket.into_split();
+            let mut line = String::new();
+            BufReader::new(reader).read_line(&mut line).await.unwrap();
+            let reply = match line.trim() {
+                "root_add_sector:/configured" => "OK: Already indexed",
+                r if r.starts_with("root_add_sector") => "OK: Root added",
+                _ => "OK: Root removed",
+            };
+            log.lock().unwrap().push(line.trim().to_string());
+            writer.write_all(format!("{}\n", reply).as_bytes()).await.unwrap();
+        }
+    });
+
+    let mut registry = ServiceRegistry::new(0);
+    registry.register("tos-searchd", port, "127.0.0.1");
+    let search = SearchService::new(Arc::new(Mutex::new(registry)));
+    let dirs = |d: &[&str]| d.iter().map(|d| d.to_string()).collect();
+
+    search.sync_sector_roots(dirs(&["/configured", "/project"])).await;
+    search.sync_sector_roots(dirs(&[])).await;
+    assert_eq!(
+        *requests.lock().unwrap(),
+        [
+            "root_add_sector:/configured",
+            "root_add_sector:/project",
+            "root_remove:/project"
+        ]
+    );
+}
@@ -31,6 +31,7 @@ fn test_terminal_line_serde() {
         text: "System boot".to_string(),
         priority: 3,
         timestamp: Local::now(),
+        source: None,
     };
     let serialized = serde_json::to_string(&line).unwrap();
     let deserialized: TerminalLine = serde_json::from_str(&serialized).unwrap();
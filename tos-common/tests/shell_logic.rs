@@ -34,3 +34,13 @@ fn test_osc_partial_sequence() {
     assert_eq!(text, "\x1b]50;3");
     assert_eq!(events.len(), 0);
 }
+
+#[test]
+fn test_osc_cwd_percent_decoding_with_multibyte_input() {
+    let mut parser = OscParser::new();
+    let (_, events) = parser.process("\x1b]7;file://host/tmp/%aé/caf%C3%A9%20x/%\x07");
+    match &events[0] {
+        OscEvent::Cwd(path) => assert_eq!(path, "/tmp/%aé/café x/%"),
+        _ => panic!("Expected cwd event"),
+    }
+}
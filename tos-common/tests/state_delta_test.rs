@@ -0,0 +1,123 @@
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use tos_common::brain::ipc_handler::IpcHandler;
+use tos_common::brain::state_delta::{apply, diff, PatchOp, StateDelta, StateHistory};
+use tos_common::{TerminalLine, TosState};
+
+#[test]
+fn test_diff_round_trips_nested_changes() {
+    let old = json!({
+        "version": 1,
+        "name": "Primary",
+        "a/b": {"~key": 1},
+        "removed": true,
+        "sectors": [{"id": 1, "tags": ["x"]}, {"id": 2}]
+    });
+    let new = json!({
+        "version": 2,
+        "name": "Primary",
+        "a/b": {"~key": 2},
+        "added": [1, 2],
+        "sectors": [{"id": 1, "tags": ["x", "y"]}]
+    });
+
+    let ops = diff(&old, &new);
+    let mut patched = old.clone();
+    apply(&mut patched, &ops).unwrap();
+    assert_eq!(patched, new);
+    assert!(ops.contains(&PatchOp::Replace {
+        path: "/a~1b/~0key".to_string(),
+        value: json!(2)
+    }));
+}
+
+#[test]
+fn test_diff_capped_buffer_shift_is_small() {
+    let old = json!({"lines": (0..500).collect::<Vec<_>>()});
+    let new = json!({"lines": (2..502).collect::<Vec<_>>()});
+
+    let ops = diff(&old, &new);
+    // Two trimmed from the front, two appended — not 500 replacements.
+    assert_eq!(ops.len(), 4);
+    let mut patched = old.clone();
+    apply(&mut patched, &ops).unwrap();
+    assert_eq!(patched, new);
+}
+
+#[test]
+fn test_history_resyncs_unknown_versions() {
+    let mut history = StateHistory::new();
+    history.record(1, json!({"version": 1}));
+
+    match history.delta(1, 2, json!({"version": 2})) {
+        StateDelta::Patch { from, version, ops } => {
+            assert_eq!((from, version), (1, 2));
+            assert_eq!(ops.len(), 1);
+        }
+        other => panic!("expected patch, got {:?}", other),
+    }
+
+    // Pushed out of the window: the client must take a full state.
+    for v in 3..(3 + StateHistory::CAPACITY as u64) {
+        history.record(v, json!({"version": v}));
+    }
+    assert!(matches!(
+        history.delta(1, 100, json!({"version": 100})),
+        StateDelta::Full { version: 100, .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_get_state_delta_patches_client_state() {
+    let state = Arc::new(Mutex::new(TosState::default()));
+    let services = Arc::new(tos_common::services::ServiceManager::new());
+    let mm = Arc::new(tos_common::brain::module_manager::ModuleManager::new(
+        std::path::PathBuf::from("/tmp"),
+    ));
+    let shells = Arc::new(Mutex::new(tos_common::brain::shell::ShellPool::new(
+        state.clone(),
+        mm,
+        services.ai.clone(),
+        services.heuristic.clone(),
+    )));
+    let ipc = IpcHandler::new(state.clone(), shells, services);
+    // Version 0 means "no state yet" and always gets a full reply.
+    state.lock().unwrap().version = 1;
+
+    let mut client: serde_json::Value =
+        serde_json::from_str(&ipc.handle_request("get_state:")).unwrap();
+    let base_version = client["version"].as_u64().unwrap();
+
+    {
+        let mut lock = state.lock().unwrap();
+        lock.sectors[0].hubs[0].push_output_line(TerminalLine {
+            text: "hello".to_string(),
+            priority: 1,
+            timestamp: chrono::Local::now(),
+            source: None,
+        });
+        lock.version += 1;
+    }
+
+    let reply = ipc.handle_request(&format!("get_state_delta:{}", base_version));
+    match serde_json::from_str::<StateDelta>(&reply).unwrap() {
+        StateDelta::Patch { from, ops, .. } => {
+            assert_eq!(from, base_version);
+            assert!(ops.len() < 5, "expected a small patch, got {:?}", ops);
+            apply(&mut client, &ops).unwrap();
+        }
+        other => panic!("expected patch, got {:?}", other),
+    }
+    let full: serde_json::Value = serde_json::from_str(&ipc.handle_request("get_state:")).unwrap();
+    assert_eq!(client, full);
+
+    let current = full["version"].as_u64().unwrap();
+    assert_eq!(
+        ipc.handle_request(&format!("get_state_delta:{}", current)),
+        "NO_CHANGE"
+    );
+    assert!(matches!(
+        serde_json::from_str::<StateDelta>(&ipc.handle_request("get_state_delta:0")).unwrap(),
+        StateDelta::Full { .. }
+    ));
+}
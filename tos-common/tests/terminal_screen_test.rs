@@ -0,0 +1,80 @@
+use tos_common::brain::shell::screen::{sanitize_line, TerminalEmulator};
+use tos_common::TermColor;
+
+#[test]
+fn test_sanitize_resolves_carriage_return_progress() {
+    let raw = "Downloading  10%\rDownloading  55%\rDownloading 100%";
+    assert_eq!(sanitize_line(raw), "Downloading 100%");
+}
+
+#[test]
+fn test_sanitize_strips_escape_sequences() {
+    let raw = "\x1b[1;31merror\x1b[0m: \x1b]8;;https://example.com\x1b\\link\x1b]8;;\x07 done\x08\x08\x08\x08DONE";
+    assert_eq!(sanitize_line(raw), "error: link DONE");
+}
+
+#[test]
+fn test_emulator_tracks_prompt_without_newline() {
+    let mut emu = TerminalEmulator::new(5, 20);
+    emu.process(b"line one\r\nuser@tos $ ");
+    let screen = emu.snapshot();
+
+    assert_eq!(screen.rows, 5);
+    assert_eq!(screen.cursor_row, 1);
+    assert_eq!(screen.cursor_col, 11);
+    assert_eq!(screen.lines[1][0].text, "user@tos $");
+}
+
+#[test]
+fn test_emulator_sgr_attributes() {
+    let mut emu = TerminalEmulator::new(3, 20);
+    emu.process(b"ok \x1b[1;32mPASS\x1b[0m \x1b[38;2;10;20;30mrgb\x1b[0m");
+    let screen = emu.snapshot();
+    let row = &screen.lines[0];
+
+    assert_eq!(row[0].text, "ok ");
+    assert_eq!(row[0].fg, TermColor::Default);
+    assert_eq!(row[1].text, "PASS");
+    assert!(row[1].bold);
+    assert_eq!(row[1].fg, TermColor::Indexed(2));
+    assert_eq!(row[3].text, "rgb");
+    assert_eq!(row[3].fg, TermColor::Rgb(10, 20, 30));
+}
+
+#[test]
+fn test_emulator_alternate_screen_and_cursor_movement() {
+    let mut emu = TerminalEmulator::new(4, 10);
+    emu.process(b"shell$ ");
+    emu.process(b"\x1b[?1049h\x1b[2J\x1b[3;4HTUI");
+    assert!(emu.is_alternate_screen());
+    let screen = emu.snapshot();
+    assert!(screen.alternate_screen);
+    assert_eq!(screen.lines[2][0].text, "   TUI");
+
+    emu.process(b"\x1b[?1049l");
+    assert!(!emu.is_alternate_screen());
+    assert_eq!(emu.snapshot().lines[0][0].text, "shell$");
+}
+
+#[test]
+fn test_emulator_scrollback_snapshot() {
+    let mut emu = TerminalEmulator::new(2, 10);
+    emu.process(b"first\r\nsecond\r\nthird");
+    assert_eq!(emu.snapshot().lines[0][0].text, "second");
+
+    let back = emu.snapshot_at(1);
+    assert_eq!(back.scrollback_offset, 1);
+    assert_eq!(back.lines[0][0].text, "first");
+    assert!(!back.cursor_visible);
+
+    // The live view is restored after a scrollback snapshot.
+    assert_eq!(emu.snapshot().lines[0][0].text, "second");
+}
+
+#[test]
+fn test_emulator_resize() {
+    let mut emu = TerminalEmulator::new(24, 80);
+    emu.resize(40, 120);
+    assert_eq!(emu.size(), (40, 120));
+    assert_eq!(emu.snapshot().lines.len(), 40);
+}
@@ -0,0 +1,149 @@
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tos_common::platform::tls_identity::{
+    connect_pinned, KnownBrains, PinCheck, PinnedServerVerifier,
+};
+use tos_common::platform::BrainIdentity;
+
+#[test]
+fn test_identity_persists_until_rotated() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = BrainIdentity::load_or_create(dir.path()).unwrap();
+    let again = BrainIdentity::load_or_create(dir.path()).unwrap();
+    assert_eq!(first.fingerprint(), again.fingerprint());
+    assert_eq!(first.fingerprint().len(), 32 * 3 - 1);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(dir.path().join(BrainIdentity::KEY_FILE))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    let rotated = BrainIdentity::rotate(dir.path()).unwrap();
+    assert_ne!(rotated.fingerprint(), first.fingerprint());
+    let mut files: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    files.sort();
+    assert_eq!(files, [BrainIdentity::CERT_FILE, BrainIdentity::KEY_FILE]);
+    assert_eq!(
+        BrainIdentity::load(dir.path()).unwrap().fingerprint(),
+        rotated.fingerprint()
+    );
+}
+
+#[test]
+fn test_known_brains_trust_on_first_use() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(KnownBrains::FILE);
+
+    let mut known = KnownBrains::load(&path);
+    assert_eq!(known.check("brain.local:7001", "AA:BB"), PinCheck::Unknown);
+    known.trust("brain.local:7001", "aa:bb").unwrap();
+
+    let known = KnownBrains::load(&path);
+    assert_eq!(known.check("brain.local:7001", "AA:BB"), PinCheck::Trusted);
+    assert_eq!(
+        known.check("brain.local:7001", "CC:DD"),
+        PinCheck::Mismatch {
+            pinned: "AA:BB".to_string()
+        }
+    );
+}
+
+async fn handshake(identity: &BrainIdentity, verifier: Arc<PinnedServerVerifier>) -> bool {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let server_config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(vec![identity.certificate()], identity.private_key())
+        .unwrap();
+    let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(server_config));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let _ = acceptor.accept(socket).await;
+    });
+
+    let connector = tokio_rustls::TlsConnector::from(Arc::new(verifier.client_config()));
+    let socket = TcpStream::connect(addr).await.unwrap();
+    let name = rustls::pki_types::ServerName::try_from("tos-brain.local").unwrap();
+    let ok = connector.connect(name, socket).await.is_ok();
+    let _ = server.await;
+    ok
+}
+
+#[tokio::test]
+async fn test_faces_pin_the_brain_certificate() {
+    let dir = tempfile::tempdir().unwrap();
+    let identity = BrainIdentity::load_or_create(dir.path()).unwrap();
+
+    // First use: accepted, and the presented fingerprint is reported.
+    let tofu = Arc::new(PinnedServerVerifier::new(None));
+    assert!(handshake(&identity, tofu.clone()).await);
+    assert_eq!(tofu.seen(), Some(identity.fingerprint()));
+
+    let pinned = identity.fingerprint().to_lowercase();
+    assert!(
+        handshake(
+            &identity,
+            Arc::new(PinnedServerVerifier::new(Some(&pinned)))
+        )
+        .await
+    );
+
+    // After a rotation the old pin rejects the Brain.
+    let impostor = BrainIdentity::rotate(dir.path()).unwrap();
+    let verifier = Arc::new(PinnedServerVerifier::new(Some(&pinned)));
+    assert!(!handshake(&impostor, verifier.clone()).await);
+    assert_eq!(verifier.seen(), Some(impostor.fingerprint()));
+}
+
+/// Serve TLS with `identity` for `connections` handshakes.
+async fn serve(identity: &BrainIdentity, connections: usize) -> String {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let server_config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(vec![identity.certificate()], identity.private_key())
+        .unwrap();
+    let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(server_config));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        for _ in 0..connections {
+            let (socket, _) = listener.accept().await.unwrap();
+            let _ = acceptor.accept(socket).await;
+        }
+    });
+    addr
+}
+
+#[tokio::test]
+async fn test_connect_pins_on_first_use_and_refuses_a_new_certificate() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(KnownBrains::FILE);
+    let identity = BrainIdentity::load_or_create(&dir.path().join("identity")).unwrap();
+    let address = serve(&identity, 2).await;
+
+    let mut known = KnownBrains::load(&path);
+    assert!(connect_pinned(&address, &mut known).await.is_ok());
+    let mut known = KnownBrains::load(&path);
+    assert_eq!(known.pinned(&address), Some(identity.fingerprint().as_str()));
+    assert!(connect_pinned(&address, &mut known).await.is_ok());
+
+    // The same address now serves another certificate.
+    let impostor = BrainIdentity::ephemeral().unwrap();
+    let address = {
+        let served = serve(&impostor, 1).await;
+        known.trust(&served, &identity.fingerprint()).unwrap();
+        served
+    };
+    let error = connect_pinned(&address, &mut known).await.unwrap_err();
+    assert!(error.to_string().contains(&impostor.fingerprint()), "{}", error);
+    assert_eq!(known.pinned(&address), Some(identity.fingerprint().as_str()));
+}
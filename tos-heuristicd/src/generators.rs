@@ -0,0 +1,348 @@
+//! Dynamic argument values (§28): branches, targets, packages, containers
+//! and scripts read from the directory the prompt is in, and paths
+//! completed through nested directories.
+//!
+//! Generators read project files where they can and only run a program for
+//! what has no file (docker containers, script generators), under a short
+//! timeout. Results are cached for a few seconds, as a prompt queries on
+//! every keystroke.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a generated list is reused.
+const CACHE_TTL: Duration = Duration::from_secs(5);
+/// How long a generator program may run.
+const PROGRAM_TIMEOUT: Duration = Duration::from_secs(1);
+/// Values kept per generator run.
+const MAX_VALUES: usize = 200;
+/// Paths listed per directory.
+const MAX_PATHS: usize = 100;
+
+/// A source of argument values.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Generator {
+    /// Local and remote-tracking branches of the enclosing repository.
+    GitBranches,
+    /// Targets of the Makefile in the directory.
+    MakeTargets,
+    /// Packages of the enclosing Cargo workspace.
+    CargoPackages,
+    /// Names of all Docker containers.
+    DockerContainers,
+    /// Scripts of the nearest `package.json`.
+    NpmScripts,
+    /// A program and its arguments, run in the directory; one value per
+    /// output line.
+    Script(Vec<String>),
+}
+
+impl Generator {
+    /// The values for a prompt in `cwd`; nothing if there are none to find.
+    pub fn generate(&self, cwd: &Path) -> Vec<String> {
+        let mut values = match self {
+            Generator::GitBranches => git_branches(cwd),
+            Generator::MakeTargets => make_targets(cwd),
+            Generator::CargoPackages => cargo_packages(cwd),
+            Generator::DockerContainers => run_lines(
+                &["docker", "ps", "-a", "--format", "{{.Names}}"].map(String::from),
+                cwd,
+            ),
+            Generator::NpmScripts => npm_scripts(cwd),
+            Generator::Script(command) => run_lines(command, cwd),
+        };
+        let mut seen = std::collections::HashSet::new();
+        values.retain(|v| !v.is_empty() && seen.insert(v.clone()));
+        values.truncate(MAX_VALUES);
+        values
+    }
+}
+
+/// Values generated at a time.
+type Generated = (Instant, Vec<String>);
+
+/// Generated values by generator and directory, reused for [`CACHE_TTL`].
+#[derive(Default)]
+pub struct GeneratorCache {
+    entries: Mutex<HashMap<(Generator, PathBuf), Generated>>,
+}
+
+impl GeneratorCache {
+    pub fn run(&self, generator: &Generator, cwd: &Path) -> Vec<String> {
+        let key = (generator.clone(), cwd.to_path_buf());
+        if let Ok(entries) = self.entries.lock() {
+            if let Some((at, values)) = entries.get(&key) {
+                if at.elapsed() < CACHE_TTL {
+                    return values.clone();
+                }
+            }
+        }
+        let values = generator.generate(cwd);
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|_, (at, _)| at.elapsed() < CACHE_TTL);
+            entries.insert(key, (Instant::now(), values.clone()));
+        }
+        values
+    }
+}
+
+/// Paths completing `partial`, resolved from `cwd`, `~/` or `/`: the
+/// entries of the directory it names so far whose names start with the
+/// rest. Directories end in `/` so the next query descends into them.
+/// Hidden entries are listed only once the name starts with `.`.
+pub fn complete_path(partial: &str, cwd: &str, folders_only: bool) -> Vec<String> {
+    let (dir_part, base) = match partial.rfind('/') {
+        Some(i) => partial.split_at(i + 1),
+        None => ("", partial),
+    };
+    let dir = if dir_part.starts_with('/') {
+        PathBuf::from(dir_part)
+    } else if let Some(rest) = dir_part.strip_prefix("~/") {
+        match dirs::home_dir() {
+            Some(home) => home.join(rest),
+            None => return Vec::new(),
+        }
+    } else {
+        Path::new(cwd).join(dir_part)
+    };
+
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(e) => e,
+        Err(_) => return Vec::new(),
+    };
+    let mut paths: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            if !name.starts_with(base) || (name.starts_with('.') && !base.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.path().is_dir();
+            if folders_only && !is_dir {
+                return None;
+            }
+            Some(format!(
+                "{}{}{}",
+                dir_part,
+                name,
+                if is_dir { "/" } else { "" }
+            ))
+        })
+        .collect();
+    paths.sort();
+    paths.truncate(MAX_PATHS);
+    paths
+}
+
+/// The nearest ancestor of `cwd` (itself included) holding `name`.
+fn find_up(cwd: &Path, name: &str) -> Option<PathBuf> {
+    cwd.ancestors().map(|d| d.join(name)).find(|p| p.exists())
+}
+
+/// The repository's git directory, through a worktree's `.git` file and
+/// `commondir` to where the refs live.
+fn git_dir(cwd: &Path) -> Option<PathBuf> {
+    let dot_git = find_up(cwd, ".git")?;
+    let mut dir = if dot_git.is_file() {
+        let text = std::fs::read_to_string(&dot_git).ok()?;
+        let target = text.trim().strip_prefix("gitdir:")?.trim();
+        dot_git.parent()?.join(target)
+    } else {
+        dot_git
+    };
+    if let Ok(common) = std::fs::read_to_string(dir.join("commondir")) {
+        dir = dir.join(common.trim());
+    }
+    Some(dir)
+}
+
+fn git_branches(cwd: &Path) -> Vec<String> {
+    let Some(dir) = git_dir(cwd) else {
+        return Vec::new();
+    };
+
+    fn walk(root: &Path, dir: &Path, out: &mut Vec<String>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                walk(root, &path, out);
+            } else if let Ok(name) = path.strip_prefix(root) {
+                out.push(name.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+
+    let mut local = Vec::new();
+    let mut remote = Vec::new();
+    walk(&dir.join("refs/heads"), &dir.join("refs/heads"), &mut local);
+    walk(
+        &dir.join("refs/remotes"),
+        &dir.join("refs/remotes"),
+        &mut remote,
+    );
+    if let Ok(packed) = std::fs::read_to_string(dir.join("packed-refs")) {
+        for line in packed.lines() {
+            let Some((_, name)) = line.split_once(' ') else {
+                continue;
+            };
+            if let Some(branch) = name.strip_prefix("refs/heads/") {
+                local.push(branch.to_string());
+            } else if let Some(branch) = name.strip_prefix("refs/remotes/") {
+                remote.push(branch.to_string());
+            }
+        }
+    }
+    local.sort();
+    remote.retain(|b| !b.ends_with("/HEAD"));
+    remote.sort();
+    local.extend(remote);
+    local
+}
+
+fn make_targets(cwd: &Path) -> Vec<String> {
+    let Some(text) = ["GNUmakefile", "makefile", "Makefile"]
+        .iter()
+        .find_map(|f| std::fs::read_to_string(cwd.join(f)).ok())
+    else {
+        return Vec::new();
+    };
+
+    let mut targets = Vec::new();
+    for line in text.lines() {
+        if line.starts_with(['\t', ' ', '#', '.']) {
+            continue;
+        }
+        let Some((head, rest)) = line.split_once(':') else {
+            continue;
+        };
+        // `X := y` and `X = a:b` are assignments, not rules.
+        if rest.starts_with('=') || head.contains('=') {
+            continue;
+        }
+        targets.extend(
+            head.split_whitespace()
+                .filter(|t| !t.contains(['%', '$']))
+                .map(String::from),
+        );
+    }
+    targets
+}
+
+fn cargo_packages(cwd: &Path) -> Vec<String> {
+    let read = |dir: &Path| -> Option<toml::Table> {
+        toml::from_str(&std::fs::read_to_string(dir.join("Cargo.toml")).ok()?).ok()
+    };
+    let package = |manifest: &toml::Table| -> Option<String> {
+        Some(manifest.get("package")?.get("name")?.as_str()?.to_string())
+    };
+
+    // The outermost workspace, else the nearest package.
+    let mut nearest = None;
+    let mut workspace = None;
+    for dir in cwd.ancestors() {
+        if let Some(manifest) = read(dir) {
+            if manifest.contains_key("workspace") {
+                workspace = Some((dir.to_path_buf(), manifest));
+                break;
+            }
+            nearest.get_or_insert(manifest);
+        }
+    }
+    let Some((root, manifest)) = workspace else {
+        return nearest.as_ref().and_then(package).into_iter().collect();
+    };
+
+    let mut packages: Vec<String> = package(&manifest).into_iter().collect();
+    let members = manifest
+        .get("workspace")
+        .and_then(|w| w.get("members"))
+        .and_then(|m| m.as_array())
+        .cloned()
+        .unwrap_or_default();
+    for member in members.iter().filter_map(|m| m.as_str()) {
+        let dirs: Vec<PathBuf> = match member.strip_suffix("/*") {
+            Some(parent) => std::fs::read_dir(root.join(parent))
+                .map(|e| e.flatten().map(|e| e.path()).collect())
+                .unwrap_or_default(),
+            None => vec![root.join(member)],
+        };
+        packages.extend(
+            dirs.iter()
+                .filter_map(|d| read(d).as_ref().and_then(package)),
+        );
+    }
+    packages.sort();
+    packages
+}
+
+fn npm_scripts(cwd: &Path) -> Vec<String> {
+    let Some(path) = find_up(cwd, "package.json") else {
+        return Vec::new();
+    };
+    let json: serde_json::Value = match std::fs::read_to_string(path)
+        .ok()
+        .and_then(|t| serde_json::from_str(&t).ok())
+    {
+        Some(j) => j,
+        None => return Vec::new(),
+    };
+    json.get("scripts")
+        .and_then(|s| s.as_object())
+        .map(|scripts| scripts.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// The output lines of a program run in `cwd`, or nothing if it fails or
+/// outlives [`PROGRAM_TIMEOUT`].
+fn run_lines(command: &[String], cwd: &Path) -> Vec<String> {
+    let Some((program, args)) = command.split_first() else {
+        return Vec::new();
+    };
+    let mut child = match std::process::Command::new(program)
+        .args(args)
+        .current_dir(cwd)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+    {
+        Ok(c) => c,
+        Err(_) => return Vec::new(),
+    };
+
+    // Read on a thread so a chatty program can't fill the pipe and stall.
+    let mut stdout = child.stdout.take();
+    let reader = std::thread::spawn(move || {
+        let mut out = String::new();
+        if let Some(s) = stdout.as_mut() {
+            let _ = s.read_to_string(&mut out);
+        }
+        out
+    });
+
+    let deadline = Instant::now() + PROGRAM_TIMEOUT;
+    let success = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status.success(),
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(10)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break false;
+            }
+        }
+    };
+    let out = reader.join().unwrap_or_default();
+    if !success {
+        return Vec::new();
+    }
+    out.lines().map(|l| l.trim().to_string()).collect()
+}
@@ -0,0 +1,410 @@
+//! Shell history import (§28): bash, zsh and fish histories are parsed with
+//! their timestamps and learned into the command model, so a new user's
+//! suggestions start from years of history rather than nothing.
+//!
+//! The model remembers how far it imported each file, so importing again
+//! only learns what was added since; the same command at the same time in
+//! two files is learned once. Entries without a timestamp (bash without
+//! `HISTTIMEFORMAT`, plain zsh) are dated back from the file's modification
+//! time one second apart, keeping their order.
+
+use crate::model::CommandModel;
+use tos_common::redact::Redactor;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tos_common::services::heuristic::HistoryEntry;
+
+/// Newest entries read per history file.
+const MAX_ENTRIES: usize = 100_000;
+
+/// A shell whose history can be imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    pub const ALL: [Shell; 3] = [Shell::Bash, Shell::Zsh, Shell::Fish];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            _ => None,
+        }
+    }
+
+    /// Where the shell keeps its history by default.
+    pub fn default_path(self) -> Option<PathBuf> {
+        let home = dirs::home_dir()?;
+        Some(match self {
+            Shell::Bash => home.join(".bash_history"),
+            Shell::Zsh => std::env::var_os("ZDOTDIR")
+                .map(PathBuf::from)
+                .unwrap_or(home)
+                .join(".zsh_history"),
+            Shell::Fish => dirs::data_dir()?.join("fish").join("fish_history"),
+        })
+    }
+
+    /// The shell whose default history is `path`, if it is one.
+    pub fn for_default_path(path: &Path) -> Option<Self> {
+        Shell::ALL
+            .into_iter()
+            .find(|shell| shell.default_path().as_deref() == Some(path))
+    }
+
+    /// The shell a history file belongs to, from its name, else its first
+    /// line.
+    pub fn detect(path: &Path, content: &[u8]) -> Self {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if name.contains("fish") {
+            Shell::Fish
+        } else if name.contains("zsh") || name.contains("zhistory") {
+            Shell::Zsh
+        } else if name.contains("bash") {
+            Shell::Bash
+        } else if content.starts_with(b": ") {
+            Shell::Zsh
+        } else if content.starts_with(b"- cmd: ") {
+            Shell::Fish
+        } else {
+            Shell::Bash
+        }
+    }
+}
+
+/// A command read from a history file; `ts` is `None` if the file has
+/// no time for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedCommand {
+    pub ts: Option<i64>,
+    pub command: String,
+}
+
+/// Parse a bash history: one command per line, each preceded by a
+/// `#<unix time>` line when `HISTTIMEFORMAT` was set.
+pub fn parse_bash(text: &str) -> Vec<ParsedCommand> {
+    let mut commands = Vec::new();
+    let mut ts = None;
+    for line in text.lines() {
+        if let Some(rest) = line.strip_prefix('#') {
+            if !rest.is_empty() && rest.bytes().all(|b| b.is_ascii_digit()) {
+                ts = rest.parse().ok();
+                continue;
+            }
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        commands.push(ParsedCommand {
+            ts: ts.take(),
+            command: line.to_string(),
+        });
+    }
+    commands
+}
+
+/// Parse a zsh history, plain or in `EXTENDED_HISTORY` format
+/// (`: <start>:<elapsed>;<command>`). A line ending in `\` continues on
+/// the next.
+pub fn parse_zsh(bytes: &[u8]) -> Vec<ParsedCommand> {
+    let text = String::from_utf8_lossy(&unmetafy(bytes)).into_owned();
+    let mut commands = Vec::new();
+    let mut lines = text.lines();
+    while let Some(first) = lines.next() {
+        let mut entry = first.to_string();
+        while entry.ends_with('\\') {
+            entry.pop();
+            match lines.next() {
+                Some(next) => {
+                    entry.push('\n');
+                    entry.push_str(next);
+                }
+                None => break,
+            }
+        }
+
+        let (ts, command) = match extended_zsh(&entry) {
+            Some((ts, command)) => (Some(ts), command.to_string()),
+            None => (None, entry),
+        };
+        if !command.trim().is_empty() {
+            commands.push(ParsedCommand { ts, command });
+        }
+    }
+    commands
+}
+
+/// The start time and command of an extended zsh entry.
+fn extended_zsh(entry: &str) -> Option<(i64, &str)> {
+    let rest = entry.strip_prefix(": ")?;
+    let (times, command) = rest.split_once(';')?;
+    let (start, elapsed) = times.split_once(':')?;
+    if !elapsed.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((start.trim().parse().ok()?, command))
+}
+
+/// Undo zsh's metafication of history files: a byte above 0x82 is
+/// stored as 0x83 followed by that byte XOR 0x20.
+fn unmetafy(bytes: &[u8]) -> Vec<u8> {
+    const META: u8 = 0x83;
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut iter = bytes.iter();
+    while let Some(&b) = iter.next() {
+        if b == META {
+            if let Some(&next) = iter.next() {
+                out.push(next ^ 0x20);
+            }
+        } else {
+            out.push(b);
+        }
+    }
+    out
+}
+
+/// Parse a fish history: `- cmd: <command>` entries with a `when: <unix
+/// time>` line, `\n` and `\\` escaped in the command.
+pub fn parse_fish(text: &str) -> Vec<ParsedCommand> {
+    let mut commands: Vec<ParsedCommand> = Vec::new();
+    for line in text.lines() {
+        if let Some(cmd) = line.strip_prefix("- cmd: ") {
+            commands.push(ParsedCommand {
+                ts: None,
+                command: unescape_fish(cmd),
+            });
+        } else if let Some(when) = line.trim_start().strip_prefix("when: ") {
+            if let Some(last) = commands.last_mut() {
+                last.ts = when.trim().parse().ok();
+            }
+        }
+    }
+    commands.retain(|c| !c.command.trim().is_empty());
+    commands
+}
+
+fn unescape_fish(cmd: &str) -> String {
+    let mut out = String::with_capacity(cmd.len());
+    let mut chars = cmd.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// What to import: every shell's default history, one shell's, or a file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportRequest {
+    #[serde(default)]
+    pub shell: Option<Shell>,
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Only import if the model never has, as on first run.
+    #[serde(default)]
+    pub first_run: bool,
+}
+
+/// How far a history file was imported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportMark {
+    /// Newest timestamp learned from the file.
+    pub ts: i64,
+    /// Entries without a timestamp learned from the start of the file.
+    pub undated: usize,
+}
+
+/// The outcome of importing one history file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceReport {
+    pub shell: Option<Shell>,
+    pub path: String,
+    /// Commands in the file.
+    pub read: usize,
+    pub learned: usize,
+    /// Already imported, duplicated or not learnable.
+    pub skipped: usize,
+    /// Learned with a secret redacted.
+    pub redacted: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The outcome of an import.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub sources: Vec<SourceReport>,
+    pub learned: usize,
+    pub redacted: usize,
+    /// Nothing was imported because the model already had been.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub already_imported: bool,
+}
+
+/// The history files `request` names: its path, its shell's default, or
+/// every shell's default that exists.
+pub fn sources(request: &ImportRequest) -> Vec<(Option<Shell>, PathBuf)> {
+    if let Some(path) = &request.path {
+        return vec![(request.shell, PathBuf::from(path))];
+    }
+    match request.shell {
+        Some(shell) => shell
+            .default_path()
+            .map(|p| (Some(shell), p))
+            .into_iter()
+            .collect(),
+        None => Shell::ALL
+            .iter()
+            .filter_map(|&shell| Some((Some(shell), shell.default_path()?)))
+            .filter(|(_, path)| path.is_file())
+            .collect(),
+    }
+}
+
+/// Parse a history file, dating undated entries from its modification
+/// time, newest [`MAX_ENTRIES`] only.
+pub fn read_history(
+    shell: Option<Shell>,
+    path: &Path,
+) -> anyhow::Result<(Shell, Vec<ParsedCommand>)> {
+    let bytes = std::fs::read(path)?;
+    let shell = shell.unwrap_or_else(|| Shell::detect(path, &bytes));
+    let mut commands = match shell {
+        Shell::Bash => parse_bash(&String::from_utf8_lossy(&bytes)),
+        Shell::Zsh => parse_zsh(&bytes),
+        Shell::Fish => parse_fish(&String::from_utf8_lossy(&bytes)),
+    };
+    let excess = commands.len().saturating_sub(MAX_ENTRIES);
+    commands.drain(..excess);
+    Ok((shell, commands))
+}
+
+/// Import the histories `request` names into `model`, redacting secrets.
+pub fn import(
+    model: &mut CommandModel,
+    redactor: &Redactor,
+    request: &ImportRequest,
+) -> ImportReport {
+    let mut report = ImportReport::default();
+    if request.first_run && !model.imports.is_empty() {
+        report.already_imported = true;
+        return report;
+    }
+
+    // Read every file first so all of them are learned in time order.
+    let mut pending: Vec<(usize, bool, HistoryEntry)> = Vec::new();
+    let mut seen: HashSet<(i64, String)> = HashSet::new();
+    for (shell, path) in sources(request) {
+        let key = path.to_string_lossy().to_string();
+        let mut source = SourceReport {
+            shell,
+            path: key.clone(),
+            ..Default::default()
+        };
+        let (shell, commands) = match read_history(shell, &path) {
+            Ok(read) => read,
+            Err(e) => {
+                source.error = Some(e.to_string());
+                report.sources.push(source);
+                continue;
+            }
+        };
+        source.shell = Some(shell);
+        source.read = commands.len();
+
+        let mtime = std::fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_else(|| chrono::Local::now().timestamp());
+        let undated_total = commands.iter().filter(|c| c.ts.is_none()).count();
+        let mark = model.imports.get(&key).copied().unwrap_or_default();
+        let mut next_mark = mark;
+        let mut undated = 0;
+
+        for parsed in commands {
+            let ts = match parsed.ts {
+                Some(ts) => {
+                    if ts <= mark.ts {
+                        source.skipped += 1;
+                        continue;
+                    }
+                    next_mark.ts = next_mark.ts.max(ts);
+                    ts
+                }
+                None => {
+                    undated += 1;
+                    if undated <= mark.undated {
+                        source.skipped += 1;
+                        continue;
+                    }
+                    next_mark.undated = undated;
+                    mtime - (undated_total - undated) as i64
+                }
+            };
+            let (command, redacted) = redactor.redact(parsed.command.trim());
+            // The same command at the same second was already learned, from
+            // another file or an earlier import of one.
+            let known = model
+                .commands
+                .get(&command)
+                .is_some_and(|s| s.last_used == ts);
+            if known || !seen.insert((ts, command.clone())) {
+                source.skipped += 1;
+                continue;
+            }
+            pending.push((
+                report.sources.len(),
+                redacted,
+                HistoryEntry {
+                    id: String::new(),
+                    command,
+                    cwd: String::new(),
+                    sector_id: None,
+                    exit_status: None,
+                    ts,
+                },
+            ));
+        }
+        model.imports.insert(key, next_mark);
+        report.sources.push(source);
+    }
+
+    pending.sort_by_key(|(_, _, entry)| entry.ts);
+    for (index, redacted, entry) in &pending {
+        let source = &mut report.sources[*index];
+        if model.learn(entry) {
+            source.learned += 1;
+            source.redacted += usize::from(*redacted);
+        } else {
+            source.skipped += 1;
+        }
+    }
+    for source in &report.sources {
+        report.learned += source.learned;
+        report.redacted += source.redacted;
+    }
+    report
+}
@@ -0,0 +1,24 @@
+//! TOS Heuristic Service Library (`tos-heuristicd`)
+//!
+//! Learns a persistent command model from the history the Brain feeds it
+//! (§28) — decayed frequency and recency per directory and sector,
+//! next-command transitions, and the subcommands and flags each program
+//! takes — and ranks prompt suggestions from it alongside typo
+//! corrections and argument completion from command specs.
+
+pub mod generators;
+pub mod import;
+pub mod model;
+pub mod spec;
+pub mod suggest;
+
+pub use generators::{complete_path, Generator, GeneratorCache};
+pub use import::{import, ImportMark, ImportReport, ImportRequest, Shell, SourceReport};
+pub use model::{CommandModel, ModelStats, Stat, MODEL_FILE, MODEL_VERSION};
+pub use tos_common::redact::{self, Redactor, REDACTED};
+pub use spec::{
+    ArgSpec, Candidate, CandidateKind, CompletionSpec, LoadedSpec, OptionSpec, SpecSet, Template,
+    SCRIPT_CAPABILITY,
+};
+pub use suggest::{suggest, Query, Suggestion};
+pub use tos_common::services::heuristic::HistoryEntry;
@@ -4,70 +4,107 @@
 //! suggestions, typo corrections, and heuristic sector labeling. It registers
 //! with the Brain via Unix domain socket.
 
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::{TcpListener, TcpStream};
+use tos_common::brain::module_manager::ModuleManager;
+use tos_heuristicd::{CommandModel, HistoryEntry, ImportRequest, Query, Redactor, Shell, SpecSet};
 
-/// In-memory state for the heuristic service.
+/// How often a changed model is pruned and saved.
+const SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// The learned model and where it lives, and the completion specs.
 struct HeuristicState {
-    /// Cached common commands for typo matching.
-    command_history: Vec<String>,
-    /// Recently executed commands for history echo.
-    recent_commands: Vec<String>,
-    /// Common parameters for known commands.
-    parameter_hints: HashMap<String, Vec<String>>,
+    model: CommandModel,
+    dir: PathBuf,
+    /// Learned something since the last save.
+    dirty: bool,
+    specs: SpecSet,
+    spec_dir: PathBuf,
+    /// Strips secrets from every command before it is learned.
+    redactor: Redactor,
+    import_on_first_run: bool,
 }
 
 impl HeuristicState {
-    fn new() -> Self {
-        Self {
-            command_history: vec![
-                "ls".to_string(),
-                "cd".to_string(),
-                "cp".to_string(),
-                "mv".to_string(),
-                "rm".to_string(),
-                "mkdir".to_string(),
-                "cat".to_string(),
-                "grep".to_string(),
-                "find".to_string(),
-                "git".to_string(),
-                "make".to_string(),
-                "cargo".to_string(),
-                "sudo".to_string(),
-                "apt".to_string(),
-                "systemctl".to_string(),
-            ],
-            recent_commands: Vec::new(),
-            parameter_hints: {
-                let mut m = HashMap::new();
-                m.insert("git".to_string(), vec!["status".to_string(), "add".to_string(), "commit".to_string(), "push".to_string(), "pull".to_string(), "checkout".to_string(), "branch".to_string(), "diff".to_string(), "log".to_string(), "rebase".to_string()]);
-                m.insert("docker".to_string(), vec!["ps".to_string(), "images".to_string(), "run".to_string(), "stop".to_string(), "start".to_string(), "exec".to_string(), "build".to_string(), "pull".to_string(), "push".to_string(), "logs".to_string()]);
-                m.insert("npm".to_string(), vec!["install".to_string(), "start".to_string(), "test".to_string(), "run".to_string(), "build".to_string(), "publish".to_string(), "update".to_string(), "outdated".to_string()]);
-                m.insert("cargo".to_string(), vec!["build".to_string(), "run".to_string(), "test".to_string(), "check".to_string(), "doc".to_string(), "publish".to_string(), "update".to_string(), "expand".to_string()]);
-                m.insert("apt".to_string(), vec!["update".to_string(), "upgrade".to_string(), "install".to_string(), "remove".to_string(), "search".to_string(), "show".to_string(), "autoremove".to_string()]);
-                m
-            },
+    fn save(&mut self) {
+        self.model.prune(chrono::Local::now().timestamp());
+        match self.model.save(&self.dir) {
+            Ok(_) => self.dirty = false,
+            Err(e) => tracing::error!("[HEURISTICD] Failed to save model: {}", e),
         }
     }
 }
 
+/// The bundled specs, those of the installed modules and the user's.
+fn load_specs(spec_dir: &std::path::Path) -> SpecSet {
+    let mut specs = SpecSet::bundled();
+    let mut errors = Vec::new();
+    // `ModuleManager::new` creates the directory it scans; only look in one
+    // that is there.
+    let modules_dir = PathBuf::from("./modules");
+    if modules_dir.is_dir() {
+        errors.extend(specs.add_modules(&ModuleManager::new(modules_dir)));
+    }
+    errors.extend(specs.add_dir(spec_dir));
+    for error in errors {
+        tracing::warn!("[HEURISTICD] Skipped completion spec {}", error);
+    }
+    specs
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
 
-    // Bind ephemeral port
-    let listener = TcpListener::bind("0.0.0.0:0").await?;
+    // Bind ephemeral port; only the Brain talks to the daemon.
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
     let port = listener.local_addr()?.port();
     tracing::info!("TOS-HEURISTICD: Operational on port {}", port);
 
-    let state = Arc::new(Mutex::new(HeuristicState::new()));
+    // §28: The command model survives restarts.
+    let config = tos_common::TosConfig::load();
+    let dir = config.heuristic_model_dir();
+    let model = CommandModel::open(&dir, config.heuristic.clone())?;
+    tracing::info!(
+        "TOS-HEURISTICD: Model {:?} ({} commands)",
+        dir.join(tos_heuristicd::MODEL_FILE),
+        model.commands.len()
+    );
+    let (redactor, errors) = Redactor::new(&config.heuristic.redact_patterns);
+    for error in errors {
+        tracing::warn!("[HEURISTICD] Ignored redaction pattern {}", error);
+    }
+    let spec_dir = config.heuristic_spec_dir();
+    let specs = load_specs(&spec_dir);
+    tracing::info!("TOS-HEURISTICD: {} completion specs", specs.list().count());
+    let state = Arc::new(Mutex::new(HeuristicState {
+        model,
+        dir,
+        dirty: false,
+        specs,
+        spec_dir,
+        redactor,
+        import_on_first_run: config.heuristic.import_on_first_run,
+    }));
 
     // §4.1: Dynamic Port Registration Gate
     tos_common::register_with_brain("tos-heuristicd", port).await?;
 
+    let saved = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SAVE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Ok(mut lock) = saved.lock() {
+                if lock.dirty {
+                    lock.save();
+                }
+            }
+        }
+    });
+
     loop {
         let (socket, _) = listener.accept().await?;
         let state_clone = state.clone();
@@ -103,32 +140,57 @@ async fn handle_client(socket: TcpStream, state: Arc<Mutex<HeuristicState>>) ->
 
         let response = match prefix {
             "heuristic_query" => {
-                // Format: keyword;cwd
+                // Format: keyword;cwd;sector_id — an empty keyword asks for predictions
                 let args: Vec<&str> = payload.split(';').collect();
-                let keyword = args.get(0).copied().unwrap_or("");
-                let cwd = args.get(1).copied().unwrap_or(".");
-
-                if keyword.is_empty() {
-                    "[]".to_string()
-                } else {
-                    let results = generate_suggestions(keyword, cwd, &state);
-                    serde_json::to_string(&results).unwrap_or_else(|_| "[]".to_string())
-                }
+                let query = Query {
+                    keyword: args.first().copied().unwrap_or(""),
+                    cwd: args.get(1).copied().unwrap_or("."),
+                    sector_id: args.get(2).copied().filter(|s| !s.is_empty()),
+                    now: chrono::Local::now().timestamp(),
+                };
+                let lock = state.lock().unwrap();
+                let results = tos_heuristicd::suggest(&lock.model, &lock.specs, &query);
+                serde_json::to_string(&results).unwrap_or_else(|_| "[]".to_string())
             }
-            "history_append" => {
-                let mut lock = state.lock().unwrap();
-                let cmd = payload.trim().to_string();
-                if !cmd.is_empty() {
-                    // Remove if exists to move to end (MRU)
-                    if let Some(pos) = lock.recent_commands.iter().position(|x| x == &cmd) {
-                        lock.recent_commands.remove(pos);
-                    }
-                    lock.recent_commands.push(cmd);
-                    if lock.recent_commands.len() > 50 {
-                        lock.recent_commands.remove(0);
+            "history_append" => handle_history_append(payload, &state),
+            "history_import" => handle_history_import(payload, &state),
+            "model_export" => {
+                let lock = state.lock().unwrap();
+                let path = payload.trim();
+                if path.is_empty() {
+                    lock.model.export()
+                } else {
+                    match export_path(&lock.dir, path) {
+                        Some(target) => match std::fs::write(&target, lock.model.export()) {
+                            Ok(()) => format!("OK: {}", target.display()),
+                            Err(e) => format!("ERROR: Export failed: {}", e),
+                        },
+                        None => format!(
+                            "ERROR: Export path must be inside {}",
+                            lock.dir.display()
+                        ),
                     }
                 }
-                "OK".to_string()
+            }
+            "model_stats" => {
+                let lock = state.lock().unwrap();
+                serde_json::to_string(&lock.model.stats()).unwrap_or_default()
+            }
+            "specs" => {
+                let lock = state.lock().unwrap();
+                let specs: Vec<serde_json::Value> = lock
+                    .specs
+                    .list()
+                    .map(|l| serde_json::json!({ "name": l.spec.name, "origin": l.origin }))
+                    .collect();
+                serde_json::to_string(&specs).unwrap_or_default()
+            }
+            "specs_reload" => {
+                let spec_dir = state.lock().unwrap().spec_dir.clone();
+                let specs = load_specs(&spec_dir);
+                let count = specs.list().count();
+                state.lock().unwrap().specs = specs;
+                format!("OK: {} specs", count)
             }
             "ping" => "pong".to_string(),
             _ => "ERROR: Unknown command".to_string(),
@@ -142,122 +204,105 @@ async fn handle_client(socket: TcpStream, state: Arc<Mutex<HeuristicState>>) ->
     Ok(())
 }
 
-#[derive(serde::Serialize)]
-struct Suggestion {
-    text: String,
-    score: f32,
-    source: String,
-}
-
-fn generate_suggestions(
-    keyword: &str,
-    cwd_str: &str,
-    state: &Arc<Mutex<HeuristicState>>,
-) -> Vec<Suggestion> {
-    let mut suggestions = Vec::new();
-
-    // 1. Path Completion
-    if !keyword.contains(' ') {
-        let cwd = PathBuf::from(cwd_str);
-        if let Ok(entries) = std::fs::read_dir(&cwd) {
-            for entry in entries.flatten() {
-                if let Some(name) = entry.file_name().to_str() {
-                    if name.starts_with(keyword) {
-                        suggestions.push(Suggestion {
-                            text: name.to_string(),
-                            score: 0.9,
-                            source: "Path".to_string(),
-                        });
-                    }
-                }
-            }
+/// Learn a JSON array of [`HistoryEntry`]s, one entry, or a bare command
+/// line run just now.
+fn handle_history_append(payload: &str, state: &Arc<Mutex<HeuristicState>>) -> String {
+    let payload = payload.trim();
+    let entries: Vec<HistoryEntry> = if payload.starts_with('[') {
+        match serde_json::from_str(payload) {
+            Ok(e) => e,
+            Err(e) => return format!("ERROR: Invalid history: {}", e),
         }
-    }
-
-    // 2. Command History Echo
-    {
-        let lock = state.lock().unwrap();
-        // Show in reverse (most recent first)
-        for cmd in lock.recent_commands.iter().rev() {
-            if cmd.starts_with(keyword) && cmd != keyword {
-                suggestions.push(Suggestion {
-                    text: cmd.clone(),
-                    score: 0.95,
-                    source: "History".to_string(),
-                });
-            }
+    } else if payload.starts_with('{') {
+        match serde_json::from_str(payload) {
+            Ok(e) => vec![e],
+            Err(e) => return format!("ERROR: Invalid history: {}", e),
         }
-    }
+    } else if payload.is_empty() {
+        Vec::new()
+    } else {
+        vec![HistoryEntry {
+            id: String::new(),
+            command: payload.to_string(),
+            cwd: String::new(),
+            sector_id: None,
+            exit_status: None,
+            ts: chrono::Local::now().timestamp(),
+        }]
+    };
 
-    // 3. Command Typo Correction
-    {
-        let lock = state.lock().unwrap();
-        for cmd in &lock.command_history {
-            let distance = levenshtein_distance(keyword, cmd);
-            if distance > 0 && distance <= 2 {
-                suggestions.push(Suggestion {
-                    text: cmd.clone(),
-                    score: 1.0 - (distance as f32 * 0.2),
-                    source: "Typo".to_string(),
-                });
-            }
+    let mut lock = state.lock().unwrap();
+    let lock = &mut *lock;
+    let mut learned = 0;
+    for mut entry in entries {
+        entry.command = lock.redactor.redact(&entry.command).0;
+        if lock.model.learn(&entry) {
+            learned += 1;
         }
     }
-
-    // 3. Parameter Hints
-    let words: Vec<&str> = keyword.split_whitespace().collect();
-    if !words.is_empty() {
-        let cmd = words[0];
-        let lock = state.lock().unwrap();
-        if let Some(hints) = lock.parameter_hints.get(cmd) {
-            let last_word = words.last().copied().unwrap_or("");
-            let is_at_command = words.len() == 1 && keyword.ends_with(' ');
-            let is_at_parameter = words.len() > 1;
-
-            if is_at_command || is_at_parameter {
-                let filter = if is_at_parameter && !keyword.ends_with(' ') { last_word } else { "" };
-                for hint in hints {
-                    if hint.starts_with(filter) && hint != filter {
-                        suggestions.push(Suggestion {
-                            text: hint.clone(),
-                            score: 0.85,
-                            source: "Hint".to_string(),
-                        });
-                    }
-                }
-            }
-        }
+    if learned > 0 {
+        lock.dirty = true;
     }
-
-    // Sort by score descending
-    suggestions.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
-    suggestions.truncate(5);
-    suggestions
+    "OK".to_string()
 }
 
-fn levenshtein_distance(s1: &str, s2: &str) -> usize {
-    let v1: Vec<char> = s1.chars().collect();
-    let v2: Vec<char> = s2.chars().collect();
-    let n = v1.len();
-    let m = v2.len();
+/// Where `model_export` may write `requested`: a relative name or an
+/// absolute path inside the model directory, never through `..`.
+fn export_path(dir: &Path, requested: &str) -> Option<PathBuf> {
+    let path = Path::new(requested);
+    let relative = if path.is_absolute() {
+        path.strip_prefix(dir).ok()?
+    } else {
+        path
+    };
+    let plain = relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)));
+    (plain && !relative.as_os_str().is_empty()).then(|| dir.join(relative))
+}
 
-    let mut dp = vec![vec![0; m + 1]; n + 1];
+/// Import shell history named by an [`ImportRequest`] as JSON, a shell
+/// name or a file path; every shell's default history if empty. Only the
+/// shells' default history files may be named.
+fn handle_history_import(payload: &str, state: &Arc<Mutex<HeuristicState>>) -> String {
+    let payload = payload.trim();
+    let request = if payload.starts_with('{') {
+        match serde_json::from_str(payload) {
+            Ok(r) => r,
+            Err(e) => return format!("ERROR: Invalid import request: {}", e),
+        }
+    } else if let Some(shell) = Shell::parse(payload) {
+        ImportRequest {
+            shell: Some(shell),
+            ..Default::default()
+        }
+    } else {
+        ImportRequest {
+            path: (!payload.is_empty()).then(|| payload.to_string()),
+            ..Default::default()
+        }
+    };
 
-    for i in 0..=n {
-        dp[i][0] = i;
-    }
-    for j in 0..=m {
-        dp[0][j] = j;
+    if let Some(path) = &request.path {
+        if Shell::for_default_path(Path::new(path)).is_none() {
+            return format!("ERROR: Not a shell history file: {}", path);
+        }
     }
 
-    for i in 1..=n {
-        for j in 1..=m {
-            let cost = if v1[i - 1] == v2[j - 1] { 0 } else { 1 };
-            dp[i][j] = std::cmp::min(
-                dp[i - 1][j] + 1,
-                std::cmp::min(dp[i][j - 1] + 1, dp[i - 1][j - 1] + cost),
-            );
-        }
+    let mut lock = state.lock().unwrap();
+    let lock = &mut *lock;
+    if request.first_run && !lock.import_on_first_run {
+        return "OK: First-run import disabled".to_string();
+    }
+    let report = tos_heuristicd::import(&mut lock.model, &lock.redactor, &request);
+    if !report.already_imported {
+        tracing::info!(
+            "[HEURISTICD] Imported {} commands from {} history files ({} redacted)",
+            report.learned,
+            report.sources.len(),
+            report.redacted
+        );
+        lock.save();
     }
-    dp[n][m]
+    serde_json::to_string(&report).unwrap_or_default()
 }
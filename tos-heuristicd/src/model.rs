@@ -0,0 +1,363 @@
+//! The command model (§28): decayed frequency/recency statistics learned
+//! from command history, kept globally, per directory and per sector, with
+//! bigram/trigram "next command" transitions and the subcommands and flags
+//! seen after each program.
+//!
+//! Every statistic halves after `half_life_days`, so a command used daily
+//! last month ranks below one used twice this morning. The model is saved
+//! as one JSON document, which is also its export format.
+
+use crate::import::ImportMark;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use tos_common::config::HeuristicConfig;
+use tos_common::services::heuristic::HistoryEntry;
+
+/// Format version of the saved model.
+pub const MODEL_VERSION: u32 = 1;
+
+/// File the model is saved to inside the model directory.
+pub const MODEL_FILE: &str = "model.json";
+
+/// A failed command counts this much of a successful one.
+const FAILURE_WEIGHT: f64 = 0.5;
+/// Exit status of a command the shell could not find; never learned.
+const NOT_FOUND: i32 = 127;
+/// Statistics that decayed below this are forgotten.
+const MIN_SCORE: f64 = 0.01;
+/// Entry ids remembered to ignore an entry sent twice.
+const MAX_SEEN: usize = 10_000;
+/// Subcommand words a shape and an argument context keep.
+const MAX_SUBCOMMANDS: usize = 2;
+
+/// A decayed use count.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stat {
+    /// Weighted uses, decayed to `last_used`.
+    pub score: f64,
+    /// Uses, undecayed.
+    pub count: u64,
+    /// Unix time of the newest use.
+    pub last_used: i64,
+}
+
+impl Stat {
+    /// Count a use at `ts`; uses may arrive out of order.
+    fn bump(&mut self, ts: i64, weight: f64, half_life: f64) {
+        if self.count == 0 || ts >= self.last_used {
+            self.score = self.score * decay(ts - self.last_used, half_life) + weight;
+            self.last_used = ts;
+        } else {
+            self.score += weight * decay(self.last_used - ts, half_life);
+        }
+        self.count += 1;
+    }
+
+    /// The score decayed to `now`.
+    pub fn value(&self, now: i64, half_life: f64) -> f64 {
+        self.score * decay(now - self.last_used, half_life)
+    }
+}
+
+fn decay(seconds: i64, half_life: f64) -> f64 {
+    if seconds <= 0 || half_life <= 0.0 {
+        1.0
+    } else {
+        0.5f64.powf(seconds as f64 / half_life)
+    }
+}
+
+/// Statistics keyed by what they count.
+pub type Table = HashMap<String, Stat>;
+
+/// Counts of what the model holds, for `model_stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelStats {
+    pub commands: usize,
+    pub directories: usize,
+    pub sectors: usize,
+    pub transitions: usize,
+    pub argument_contexts: usize,
+    /// History entries learned since the model was created.
+    pub learned: u64,
+}
+
+/// The learned command model.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CommandModel {
+    pub version: u32,
+    /// Full command lines.
+    pub commands: Table,
+    /// Command lines per working directory.
+    pub by_dir: HashMap<String, Table>,
+    /// Command lines per sector id.
+    pub by_sector: HashMap<String, Table>,
+    /// Command lines following one shape (`"git add"`) or two
+    /// (`"git add\ngit commit"`).
+    pub next: HashMap<String, Table>,
+    /// Subcommands and flags seen after a context: `"git"` → `"commit"`,
+    /// `"git commit"` → `"--amend"`.
+    pub args: HashMap<String, Table>,
+    /// Shapes of the last two commands per sector id (`""` = no sector)
+    /// with the time of the newest, the context of the next prediction.
+    pub recent: HashMap<String, (i64, Vec<String>)>,
+    /// Ids of recently learned entries, oldest first.
+    pub seen: VecDeque<String>,
+    pub learned: u64,
+    /// How far each imported shell history file was learned, by path.
+    #[serde(default)]
+    pub imports: BTreeMap<String, ImportMark>,
+    #[serde(skip)]
+    seen_set: HashSet<String>,
+    #[serde(skip)]
+    config: HeuristicConfig,
+}
+
+impl CommandModel {
+    pub fn new(config: HeuristicConfig) -> Self {
+        Self {
+            version: MODEL_VERSION,
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// The model saved in `dir`, or an empty one if there is none. A model
+    /// that cannot be read is set aside as `model.json.bad` rather than
+    /// overwritten.
+    pub fn open(dir: &Path, config: HeuristicConfig) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(MODEL_FILE);
+        let text = match std::fs::read_to_string(&path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new(config)),
+            Err(e) => return Err(e.into()),
+        };
+        match Self::import(&text, config.clone()) {
+            Ok(model) => Ok(model),
+            Err(e) => {
+                tracing::warn!("[HEURISTICD] Unreadable model {:?}: {}", path, e);
+                std::fs::rename(&path, dir.join(format!("{}.bad", MODEL_FILE)))?;
+                Ok(Self::new(config))
+            }
+        }
+    }
+
+    /// A model from its JSON export.
+    pub fn import(json: &str, config: HeuristicConfig) -> anyhow::Result<Self> {
+        let mut model: Self = serde_json::from_str(json)?;
+        if model.version > MODEL_VERSION {
+            anyhow::bail!("model version {} is newer than {}", model.version, MODEL_VERSION);
+        }
+        model.version = MODEL_VERSION;
+        model.seen_set = model.seen.iter().cloned().collect();
+        model.config = config;
+        Ok(model)
+    }
+
+    /// The model as JSON, the format it is saved in.
+    pub fn export(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Write the model to `dir` through a temporary file, so a crash never
+    /// leaves half a model behind.
+    pub fn save(&self, dir: &Path) -> anyhow::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(MODEL_FILE);
+        let tmp = dir.join(format!("{}.tmp", MODEL_FILE));
+        std::fs::write(&tmp, self.export())?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(path)
+    }
+
+    /// Seconds after which a use counts half.
+    pub fn half_life(&self) -> f64 {
+        self.config.half_life_days * 86_400.0
+    }
+
+    pub fn stats(&self) -> ModelStats {
+        ModelStats {
+            commands: self.commands.len(),
+            directories: self.by_dir.len(),
+            sectors: self.by_sector.len(),
+            transitions: self.next.values().map(|t| t.len()).sum(),
+            argument_contexts: self.args.len(),
+            learned: self.learned,
+        }
+    }
+
+    /// Learn one command. Returns false if it was ignored: empty, not
+    /// found by the shell, or already learned under the same id.
+    pub fn learn(&mut self, entry: &HistoryEntry) -> bool {
+        let command = entry.command.trim();
+        let words = words(command);
+        if words.is_empty() || entry.exit_status == Some(NOT_FOUND) {
+            return false;
+        }
+        if !entry.id.is_empty() {
+            if !self.seen_set.insert(entry.id.clone()) {
+                return false;
+            }
+            self.seen.push_back(entry.id.clone());
+            while self.seen.len() > MAX_SEEN {
+                if let Some(old) = self.seen.pop_front() {
+                    self.seen_set.remove(&old);
+                }
+            }
+        }
+
+        let half_life = self.half_life();
+        let ts = entry.ts;
+        let weight = match entry.exit_status {
+            Some(code) if code != 0 => FAILURE_WEIGHT,
+            _ => 1.0,
+        };
+        let bump = |table: &mut Table, key: &str| {
+            table
+                .entry(key.to_string())
+                .or_default()
+                .bump(ts, weight, half_life);
+        };
+
+        bump(&mut self.commands, command);
+        if !entry.cwd.is_empty() {
+            bump(self.by_dir.entry(entry.cwd.clone()).or_default(), command);
+        }
+        let sector = entry.sector_id.map(|id| id.to_string()).unwrap_or_default();
+        if !sector.is_empty() {
+            bump(self.by_sector.entry(sector.clone()).or_default(), command);
+        }
+
+        // Subcommands, then flags under the deepest subcommand.
+        let subs = subcommand_count(&words);
+        for depth in 0..subs {
+            let ctx = words[..=depth].join(" ");
+            bump(self.args.entry(ctx).or_default(), words[depth + 1]);
+        }
+        let flag_ctx = words[..=subs].join(" ");
+        for word in &words[subs + 1..] {
+            if let Some(flag) = flag(word) {
+                bump(self.args.entry(flag_ctx.clone()).or_default(), flag);
+            }
+        }
+
+        // Transitions from the commands before it in the same sector.
+        let shape = shape(command);
+        let (last_ts, previous) = self.recent.get(&sector).cloned().unwrap_or_default();
+        if ts >= last_ts {
+            if let Some(prev) = previous.last() {
+                bump(self.next.entry(prev.clone()).or_default(), command);
+                if previous.len() == 2 {
+                    bump(self.next.entry(previous.join("\n")).or_default(), command);
+                }
+            }
+            let mut context = previous;
+            context.push(shape);
+            let excess = context.len().saturating_sub(2);
+            context.drain(..excess);
+            self.recent.insert(sector, (ts, context));
+        }
+
+        self.learned += 1;
+        true
+    }
+
+    /// The shapes of the last commands in a sector, oldest first.
+    pub fn context(&self, sector_id: Option<&str>) -> &[String] {
+        self.recent
+            .get(sector_id.unwrap_or(""))
+            .map(|(_, c)| c.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Forget what decayed away, then the least used command lines beyond
+    /// `max_commands`.
+    pub fn prune(&mut self, now: i64) {
+        let half_life = self.half_life();
+        let live = |stat: &Stat| stat.value(now, half_life) >= MIN_SCORE;
+
+        self.commands.retain(|_, s| live(s));
+        let excess = self
+            .commands
+            .len()
+            .saturating_sub(self.config.max_commands);
+        if excess > 0 {
+            let mut ranked: Vec<(f64, String)> = self
+                .commands
+                .iter()
+                .map(|(c, s)| (s.value(now, half_life), c.clone()))
+                .collect();
+            ranked.sort_by(|a, b| a.0.total_cmp(&b.0));
+            for (_, command) in ranked.into_iter().take(excess) {
+                self.commands.remove(&command);
+            }
+        }
+
+        let commands = &self.commands;
+        for tables in [&mut self.by_dir, &mut self.by_sector, &mut self.next] {
+            for table in tables.values_mut() {
+                table.retain(|c, s| live(s) && commands.contains_key(c));
+            }
+            tables.retain(|_, t| !t.is_empty());
+        }
+        for table in self.args.values_mut() {
+            table.retain(|_, s| live(s));
+        }
+        self.args.retain(|_, t| !t.is_empty());
+    }
+}
+
+/// The words of a command line, without leading `VAR=value` assignments.
+pub fn words(command: &str) -> Vec<&str> {
+    command
+        .split_whitespace()
+        .skip_while(|w| is_assignment(w))
+        .collect()
+}
+
+fn is_assignment(word: &str) -> bool {
+    match word.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        None => false,
+    }
+}
+
+/// Whether a word reads like a subcommand rather than a path or value.
+fn is_subcommand(word: &str) -> bool {
+    word.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Subcommand words following the program, at most [`MAX_SUBCOMMANDS`].
+fn subcommand_count(words: &[&str]) -> usize {
+    words[1..]
+        .iter()
+        .take(MAX_SUBCOMMANDS)
+        .take_while(|w| is_subcommand(w))
+        .count()
+}
+
+/// The flag a word sets, without its value: `--color=auto` → `--color`.
+fn flag(word: &str) -> Option<&str> {
+    if !word.starts_with('-') || word == "-" || word == "--" {
+        return None;
+    }
+    let name = word.split('=').next().unwrap_or(word);
+    (name.len() > 1).then_some(name)
+}
+
+/// The program and its subcommands: `git commit -m "x"` → `git commit`.
+/// Transitions are learned between shapes so arguments don't split them.
+pub fn shape(command: &str) -> String {
+    let words = words(command);
+    if words.is_empty() {
+        return String::new();
+    }
+    words[..=subcommand_count(&words)].join(" ")
+}
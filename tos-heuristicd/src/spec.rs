@@ -0,0 +1,399 @@
+//! Completion specs (§28): declarative descriptions of a command's
+//! subcommands, options and arguments, from which the arguments at the
+//! cursor are completed.
+//!
+//! Specs are JSON, one spec or an array of them per file. The bundled specs
+//! are compiled in; modules add their own through `completions` in
+//! `module.toml`, and `[heuristic] spec_dir` holds the user's, each
+//! replacing an earlier spec of the same name. Script generators run a
+//! program at the prompt, so only bundled specs and modules granted
+//! [`SCRIPT_CAPABILITY`] may use them.
+
+use crate::generators::{self, Generator, GeneratorCache};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tos_common::brain::module_manager::ModuleManager;
+
+/// Specs shipped with the daemon.
+const BUNDLED: &[(&str, &str)] = &[
+    ("apt", include_str!("../specs/apt.json")),
+    ("cargo", include_str!("../specs/cargo.json")),
+    ("docker", include_str!("../specs/docker.json")),
+    ("git", include_str!("../specs/git.json")),
+    ("npm", include_str!("../specs/npm.json")),
+];
+
+/// The manifest capability that lets a module's specs run script
+/// generators.
+pub const SCRIPT_CAPABILITY: &str = "completion_scripts";
+
+/// Programs that run the command after them; completion follows that one.
+const PREFIX_COMMANDS: &[&str] = &["sudo", "doas", "time", "nice", "nohup"];
+
+/// A command or subcommand.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompletionSpec {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subcommands: Vec<CompletionSpec>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<OptionSpec>,
+    /// Positional arguments, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<ArgSpec>,
+}
+
+/// A flag, with the values it takes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OptionSpec {
+    /// Every spelling: `["-m", "--message"]`.
+    pub names: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Values following the flag; `--flag=value` needs none.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<ArgSpec>,
+    /// Also offered under every subcommand.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub persistent: bool,
+}
+
+/// A value: fixed suggestions, paths, or generated at the prompt.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArgSpec {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<Template>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub generators: Vec<Generator>,
+    /// Repeats to the end of the command line.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub variadic: bool,
+}
+
+/// Paths an argument takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Template {
+    Filepaths,
+    Folders,
+}
+
+/// What a completion is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandidateKind {
+    Subcommand,
+    Option,
+    /// A fixed or generated argument value.
+    Value,
+    Path,
+}
+
+/// A completion for the word at the cursor, replacing it whole.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub text: String,
+    pub kind: CandidateKind,
+}
+
+/// What may be typed after the words so far.
+struct Position<'a> {
+    /// The command and the subcommands entered, outermost first.
+    path: Vec<&'a CompletionSpec>,
+    /// The value expected next, if any.
+    arg: Option<&'a ArgSpec>,
+    /// No positional argument yet, so a subcommand may follow.
+    subcommands: bool,
+}
+
+impl CompletionSpec {
+    /// Whether any argument of the spec, at any depth, runs a script.
+    pub fn runs_scripts(&self) -> bool {
+        let scripted = |args: &[ArgSpec]| {
+            args.iter()
+                .flat_map(|a| &a.generators)
+                .any(|g| matches!(g, Generator::Script(_)))
+        };
+        scripted(&self.args)
+            || self.options.iter().any(|o| scripted(&o.args))
+            || self.subcommands.iter().any(CompletionSpec::runs_scripts)
+    }
+
+    fn subcommand(&self, word: &str) -> Option<&CompletionSpec> {
+        self.subcommands
+            .iter()
+            .find(|s| s.name == word || s.aliases.iter().any(|a| a == word))
+    }
+
+    /// Walk the words after the program through subcommands, options and
+    /// their values, and positional arguments.
+    fn locate(&self, words: &[&str]) -> Position<'_> {
+        let mut path = vec![self];
+        let mut pending: &[ArgSpec] = &[];
+        let mut positional = 0;
+
+        for word in words {
+            if let Some((_, rest)) = pending.split_first() {
+                pending = rest;
+                continue;
+            }
+            let node = path[path.len() - 1];
+            if word.starts_with('-') && word.len() > 1 {
+                let (name, inline) = match word.split_once('=') {
+                    Some((name, _)) => (name, true),
+                    None => (*word, false),
+                };
+                let option =
+                    Self::visible_options(&path).find(|o| o.names.iter().any(|n| n == name));
+                if let (Some(opt), false) = (option, inline) {
+                    pending = &opt.args;
+                }
+                continue;
+            }
+            if positional == 0 {
+                if let Some(sub) = node.subcommand(word) {
+                    path.push(sub);
+                    continue;
+                }
+            }
+            positional += 1;
+        }
+
+        let node = path[path.len() - 1];
+        if let Some(arg) = pending.first() {
+            return Position {
+                path,
+                arg: Some(arg),
+                subcommands: false,
+            };
+        }
+        let arg = node
+            .args
+            .get(positional)
+            .or_else(|| node.args.last().filter(|a| a.variadic));
+        Position {
+            path,
+            arg,
+            subcommands: positional == 0,
+        }
+    }
+
+    /// Options of the innermost command, and persistent ones of those
+    /// around it.
+    fn visible_options<'a, 'p>(
+        path: &'p [&'a CompletionSpec],
+    ) -> impl Iterator<Item = &'a OptionSpec> + 'p {
+        let last = path.len() - 1;
+        path.iter().copied().enumerate().flat_map(move |(i, node)| {
+            node.options
+                .iter()
+                .filter(move |o| i == last || o.persistent)
+        })
+    }
+
+    /// Subcommands, options and argument values that may replace
+    /// `partial` after `words`, the words typed after the program.
+    pub fn complete(
+        &self,
+        words: &[&str],
+        partial: &str,
+        cwd: &str,
+        cache: &GeneratorCache,
+    ) -> Vec<Candidate> {
+        let position = self.locate(words);
+        let mut candidates = Vec::new();
+        let mut push = |text: String, kind| {
+            if text.starts_with(partial) && text != partial {
+                candidates.push(Candidate { text, kind });
+            }
+        };
+
+        if partial.starts_with('-') {
+            for opt in Self::visible_options(&position.path) {
+                for name in &opt.names {
+                    push(name.clone(), CandidateKind::Option);
+                }
+            }
+            return candidates;
+        }
+
+        if position.subcommands {
+            let node = position.path[position.path.len() - 1];
+            for sub in &node.subcommands {
+                push(sub.name.clone(), CandidateKind::Subcommand);
+            }
+        }
+        if let Some(arg) = position.arg {
+            for value in &arg.suggestions {
+                push(value.clone(), CandidateKind::Value);
+            }
+            for generator in &arg.generators {
+                for value in cache.run(generator, Path::new(cwd)) {
+                    push(value, CandidateKind::Value);
+                }
+            }
+            if let Some(template) = arg.template {
+                let folders = template == Template::Folders;
+                for path in generators::complete_path(partial, cwd, folders) {
+                    push(path, CandidateKind::Path);
+                }
+            }
+        }
+        candidates
+    }
+}
+
+/// A file of specs: one, or several.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SpecFile {
+    One(CompletionSpec),
+    Many(Vec<CompletionSpec>),
+}
+
+/// A loaded spec and where it came from: `bundled`, `module:<id>` or a
+/// file path.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LoadedSpec {
+    pub spec: CompletionSpec,
+    pub origin: String,
+}
+
+/// The specs in effect, by command name.
+#[derive(Default)]
+pub struct SpecSet {
+    specs: BTreeMap<String, LoadedSpec>,
+    cache: GeneratorCache,
+}
+
+impl SpecSet {
+    /// The bundled specs only.
+    pub fn bundled() -> Self {
+        let mut set = Self::default();
+        for (name, json) in BUNDLED {
+            match serde_json::from_str::<CompletionSpec>(json) {
+                Ok(spec) => set.insert(spec, "bundled"),
+                Err(e) => tracing::error!("[HEURISTICD] Bundled spec '{}' is invalid: {}", name, e),
+            }
+        }
+        set
+    }
+
+    /// The bundled specs, then the modules', then those in `spec_dir`.
+    /// Files that fail to load are reported and skipped.
+    pub fn load(modules: &ModuleManager, spec_dir: &Path) -> (Self, Vec<String>) {
+        let mut set = Self::bundled();
+        let mut errors = set.add_modules(modules);
+        errors.extend(set.add_dir(spec_dir));
+        (set, errors)
+    }
+
+    pub fn insert(&mut self, spec: CompletionSpec, origin: &str) {
+        self.specs.insert(
+            spec.name.clone(),
+            LoadedSpec {
+                spec,
+                origin: origin.to_string(),
+            },
+        );
+    }
+
+    /// Add the specs in one file. Without `allow_scripts`, a file with a
+    /// script generator is refused whole.
+    pub fn add_file(
+        &mut self,
+        path: &Path,
+        origin: &str,
+        allow_scripts: bool,
+    ) -> anyhow::Result<usize> {
+        let text = std::fs::read_to_string(path)?;
+        let specs = match serde_json::from_str(&text)? {
+            SpecFile::One(spec) => vec![spec],
+            SpecFile::Many(specs) => specs,
+        };
+        if let Some(spec) = specs.iter().find(|s| !allow_scripts && s.runs_scripts()) {
+            anyhow::bail!(
+                "'{}' runs a script generator, which needs the '{}' capability",
+                spec.name,
+                SCRIPT_CAPABILITY
+            );
+        }
+        let count = specs.len();
+        for spec in specs {
+            if spec.name.trim().is_empty() {
+                anyhow::bail!("a spec has no name");
+            }
+            self.insert(spec, origin);
+        }
+        Ok(count)
+    }
+
+    /// Add the spec files each module lists under `completions`; those of
+    /// modules granted [`SCRIPT_CAPABILITY`] may run scripts.
+    pub fn add_modules(&mut self, modules: &ModuleManager) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut manifests = modules.list_modules();
+        manifests.sort_by(|a, b| a.id.cmp(&b.id));
+        for manifest in manifests {
+            let scripts = manifest
+                .capabilities
+                .iter()
+                .flatten()
+                .any(|c| c == SCRIPT_CAPABILITY);
+            for file in manifest.completions.iter().flatten() {
+                let path = modules.resolve_path(&manifest.id, file);
+                let origin = format!("module:{}", manifest.id);
+                if let Err(e) = self.add_file(&path, &origin, scripts) {
+                    errors.push(format!("{}: {}", path.display(), e));
+                }
+            }
+        }
+        errors
+    }
+
+    /// Add every `*.json` in `dir`, in name order; a missing directory
+    /// holds none. These specs may not run scripts.
+    pub fn add_dir(&mut self, dir: &Path) -> Vec<String> {
+        let mut files: Vec<_> = match std::fs::read_dir(dir) {
+            Ok(entries) => entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|e| e == "json"))
+                .collect(),
+            Err(_) => return Vec::new(),
+        };
+        files.sort();
+        files
+            .iter()
+            .filter_map(|path| {
+                let origin = path.display().to_string();
+                self.add_file(path, &origin, false)
+                    .err()
+                    .map(|e| format!("{}: {}", origin, e))
+            })
+            .collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CompletionSpec> {
+        self.specs.get(name).map(|l| &l.spec)
+    }
+
+    pub fn list(&self) -> impl Iterator<Item = &LoadedSpec> {
+        self.specs.values()
+    }
+
+    /// Completions for `partial` after `words`, the words typed before it
+    /// starting with the program. `None` if no spec covers the program.
+    pub fn complete(&self, words: &[&str], partial: &str, cwd: &str) -> Option<Vec<Candidate>> {
+        let start = words.iter().position(|w| !PREFIX_COMMANDS.contains(w))?;
+        let spec = self.get(words[start])?;
+        Some(spec.complete(&words[start + 1..], partial, cwd, &self.cache))
+    }
+}
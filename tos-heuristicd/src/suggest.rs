@@ -0,0 +1,251 @@
+//! Suggestions for a prompt (§28): learned history and next-command
+//! predictions, argument completions from specs and learned arguments,
+//! typo corrections and paths, ranked together and cut to the best five.
+
+use crate::generators::complete_path;
+use crate::model::{shape, words, CommandModel, Table};
+use crate::spec::{CandidateKind, SpecSet};
+use std::collections::HashMap;
+
+/// Suggestions returned per query.
+pub const MAX_SUGGESTIONS: usize = 5;
+
+/// Programs corrected before any history is learned.
+pub const SEED_COMMANDS: &[&str] = &[
+    "ls", "cd", "cp", "mv", "rm", "mkdir", "cat", "grep", "find", "git", "make", "cargo", "sudo",
+    "apt", "systemctl",
+];
+
+/// Learned weight a spec's subcommands and options start with; learned
+/// ones soon outrank them.
+const SPEC_PRIOR: f64 = 0.5;
+/// Learned weight of a spec's fixed and generated values, which name
+/// things that exist here.
+const VALUE_PRIOR: f64 = 2.0;
+/// Score of a completed path.
+const PATH_SCORE: f32 = 0.7;
+/// How much a directory, sector and transition match count next to the
+/// global score of a command line.
+const DIR_WEIGHT: f64 = 2.0;
+const SECTOR_WEIGHT: f64 = 1.5;
+const NEXT_WEIGHT: f64 = 3.0;
+
+/// One suggestion, in the shape Faces render.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Suggestion {
+    pub text: String,
+    pub score: f32,
+    pub source: String,
+}
+
+/// What the prompt holds and where it is.
+#[derive(Debug, Clone, Copy)]
+pub struct Query<'a> {
+    pub keyword: &'a str,
+    pub cwd: &'a str,
+    pub sector_id: Option<&'a str>,
+    /// Unix time scores are decayed to.
+    pub now: i64,
+}
+
+/// Map a non-negative evidence sum into `floor..floor + span`.
+fn squash(evidence: f64, floor: f64, span: f64) -> f32 {
+    (floor + span * evidence / (evidence + 2.0)) as f32
+}
+
+/// The best suggestions for `query`. An empty keyword asks for the
+/// commands likely to come next.
+pub fn suggest(model: &CommandModel, specs: &SpecSet, query: &Query) -> Vec<Suggestion> {
+    let mut suggestions = Vec::new();
+    let keyword = query.keyword;
+
+    suggestions.extend(history(model, query));
+    if !keyword.trim().is_empty() {
+        suggestions.extend(arguments(model, specs, query));
+        suggestions.extend(typos(model, keyword));
+    }
+
+    // One entry per text, at its best score.
+    let mut best: HashMap<String, Suggestion> = HashMap::new();
+    for s in suggestions {
+        match best.get(&s.text) {
+            Some(b) if b.score >= s.score => {}
+            _ => {
+                best.insert(s.text.clone(), s);
+            }
+        }
+    }
+    let mut suggestions: Vec<Suggestion> = best.into_values().collect();
+    suggestions.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.text.cmp(&b.text)));
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
+}
+
+/// Learned command lines completing the keyword, ranked by how often and
+/// recently they ran overall, here, in this sector and after the
+/// commands before. Without a keyword only those that followed the
+/// recent commands are offered, as predictions.
+fn history(model: &CommandModel, query: &Query) -> Vec<Suggestion> {
+    let half_life = model.half_life();
+    let value = |table: Option<&Table>, command: &str| {
+        table
+            .and_then(|t| t.get(command))
+            .map(|s| s.value(query.now, half_life))
+            .unwrap_or(0.0)
+    };
+
+    let context = model.context(query.sector_id);
+    let bigram = context.last().and_then(|prev| model.next.get(prev));
+    let trigram = if context.len() == 2 {
+        model.next.get(&context.join("\n"))
+    } else {
+        None
+    };
+    let dir = model.by_dir.get(query.cwd);
+    let sector = query.sector_id.and_then(|id| model.by_sector.get(id));
+    let predicting = query.keyword.trim().is_empty();
+
+    let mut suggestions = Vec::new();
+    for (command, stat) in &model.commands {
+        if !predicting && (!command.starts_with(query.keyword) || command == query.keyword) {
+            continue;
+        }
+        let next = value(bigram, command) + 2.0 * value(trigram, command);
+        if predicting && next <= 0.0 {
+            continue;
+        }
+        let evidence = stat.value(query.now, half_life)
+            + DIR_WEIGHT * value(dir, command)
+            + SECTOR_WEIGHT * value(sector, command)
+            + NEXT_WEIGHT * next;
+        suggestions.push(Suggestion {
+            text: command.clone(),
+            score: squash(evidence, 0.6, 0.4),
+            source: if predicting { "Prediction" } else { "History" }.to_string(),
+        });
+    }
+    suggestions
+}
+
+/// Completions for the word being typed, which they replace: from the
+/// program's spec, weighted up by what followed the same program and
+/// subcommands in history, and learned arguments the spec lacks. Paths
+/// complete any other word, the first included.
+fn arguments(model: &CommandModel, specs: &SpecSet, query: &Query) -> Vec<Suggestion> {
+    let keyword = query.keyword;
+    let words = words(keyword);
+    // The words before the one being completed, which is empty after a space.
+    let (done, partial) = match words.split_last() {
+        Some((last, done)) if !keyword.ends_with(char::is_whitespace) => (done, *last),
+        _ => (&words[..], ""),
+    };
+
+    let mut learned: HashMap<&str, f64> = HashMap::new();
+    if !done.is_empty() {
+        if let Some(table) = model.args.get(&shape(&done.join(" "))) {
+            for (arg, stat) in table {
+                *learned.entry(arg.as_str()).or_default() +=
+                    stat.value(query.now, model.half_life());
+            }
+        }
+    }
+
+    let mut suggestions = Vec::new();
+    let candidates = specs.complete(done, partial, query.cwd).unwrap_or_default();
+    for candidate in &candidates {
+        let e = learned.remove(candidate.text.as_str()).unwrap_or(0.0);
+        let (score, source) = match candidate.kind {
+            CandidateKind::Subcommand | CandidateKind::Option => {
+                (squash(SPEC_PRIOR + e, 0.5, 0.45), "Hint")
+            }
+            CandidateKind::Value => (squash(VALUE_PRIOR + e, 0.5, 0.45), "Hint"),
+            CandidateKind::Path => (PATH_SCORE, "Path"),
+        };
+        suggestions.push(Suggestion {
+            text: candidate.text.clone(),
+            score,
+            source: source.to_string(),
+        });
+    }
+    suggestions.extend(
+        learned
+            .into_iter()
+            .filter(|(arg, _)| arg.starts_with(partial) && *arg != partial)
+            .map(|(arg, e)| Suggestion {
+                text: arg.to_string(),
+                score: squash(e, 0.5, 0.45),
+                source: "Hint".to_string(),
+            }),
+    );
+
+    if candidates.is_empty() && !partial.is_empty() && !partial.starts_with('-') {
+        suggestions.extend(
+            complete_path(partial, query.cwd, false)
+                .into_iter()
+                .map(|text| Suggestion {
+                    text,
+                    score: PATH_SCORE,
+                    source: "Path".to_string(),
+                }),
+        );
+    }
+    suggestions
+}
+
+/// Programs one or two edits away from a single-word keyword.
+fn typos(model: &CommandModel, keyword: &str) -> Vec<Suggestion> {
+    if keyword.contains(' ') {
+        return Vec::new();
+    }
+    let mut programs: Vec<&str> = SEED_COMMANDS.to_vec();
+    programs.extend(
+        model
+            .commands
+            .keys()
+            .filter_map(|c| words(c).first().copied()),
+    );
+    programs.sort_unstable();
+    programs.dedup();
+    if programs.contains(&keyword) {
+        return Vec::new();
+    }
+
+    programs
+        .into_iter()
+        .filter_map(|program| {
+            let distance = levenshtein_distance(keyword, program);
+            (distance > 0 && distance <= 2).then(|| Suggestion {
+                text: program.to_string(),
+                score: 1.0 - (distance as f32 * 0.2),
+                source: "Typo".to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn levenshtein_distance(s1: &str, s2: &str) -> usize {
+    let v1: Vec<char> = s1.chars().collect();
+    let v2: Vec<char> = s2.chars().collect();
+    let n = v1.len();
+    let m = v2.len();
+
+    let mut dp = vec![vec![0; m + 1]; n + 1];
+
+    for (i, row) in dp.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in dp[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=n {
+        for j in 1..=m {
+            let cost = if v1[i - 1] == v2[j - 1] { 0 } else { 1 };
+            dp[i][j] = std::cmp::min(
+                dp[i - 1][j] + 1,
+                std::cmp::min(dp[i][j - 1] + 1, dp[i - 1][j - 1] + cost),
+            );
+        }
+    }
+    dp[n][m]
+}
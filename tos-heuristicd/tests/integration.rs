@@ -5,20 +5,114 @@ use tos_common::MockBrain;
 
 #[tokio::test]
 async fn test_heuristicd_integration() -> anyhow::Result<()> {
+    // Keep the learned model out of the real data directory.
+    let data = tempfile::tempdir()?;
+    let config = data.path().join("tos.toml");
+    std::fs::write(
+        &config,
+        format!(
+            "[heuristic]\nmodel_dir = {:?}\nspec_dir = {:?}\n",
+            data.path().join("heuristics"),
+            data.path().join("completions")
+        ),
+    )?;
+    std::fs::create_dir(data.path().join("completions"))?;
+    std::fs::write(
+        data.path().join("completions/tf.json"),
+        r#"{ "name": "tf", "subcommands": [{ "name": "plan" }, { "name": "apply" }] }"#,
+    )?;
+
     let mock_brain: MockBrain = MockBrain::new().await?;
     let bin_path = env!("CARGO_BIN_EXE_tos-heuristicd");
-    let mut child = Command::new(bin_path).spawn()?;
+    // Imports may only read the default histories under this home.
+    let mut child = Command::new(bin_path)
+        .env("TOS_CONFIG", &config)
+        .env("HOME", data.path())
+        .env("XDG_DATA_HOME", data.path().join("share"))
+        .env_remove("ZDOTDIR")
+        .spawn()?;
     
     let (_name, port) = mock_brain.handle_one_registration().await?;
     assert_eq!(_name, "tos-heuristicd");
     
-    let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).await?;
-    stream.write_all(b"heuristic_query:l;.\n").await?;
-    let mut reader = BufReader::new(stream);
-    let mut line = String::new();
-    reader.read_line(&mut line).await?;
+    let stream = TcpStream::connect(format!("127.0.0.1:{}", port)).await?;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    writer.write_all(b"heuristic_query:l;.\n").await?;
+    let line = lines.next_line().await?.unwrap();
     assert!(line.contains("score"));
-    
+
+    // History teaches the model; the reply keeps the Suggestion shape.
+    let sector = uuid::Uuid::new_v4();
+    let history = serde_json::json!([
+        { "id": "a", "command": "terraform plan", "cwd": "/infra", "sector_id": sector, "exit_status": 0, "ts": 1 },
+        { "id": "b", "command": "terraform apply", "cwd": "/infra", "sector_id": sector, "exit_status": 0, "ts": 2 },
+    ]);
+    writer
+        .write_all(format!("history_append:{}\n", history).as_bytes())
+        .await?;
+    assert_eq!(lines.next_line().await?.as_deref(), Some("OK"));
+
+    writer
+        .write_all(format!("heuristic_query:terraform p;/infra;{}\n", sector).as_bytes())
+        .await?;
+    let suggestions: serde_json::Value =
+        serde_json::from_str(&lines.next_line().await?.unwrap())?;
+    assert_eq!(suggestions[0]["text"], "terraform plan");
+    assert_eq!(suggestions[0]["source"], "History");
+    assert!(suggestions[0]["score"].is_number());
+
+    writer.write_all(b"model_stats:\n").await?;
+    let stats: serde_json::Value = serde_json::from_str(&lines.next_line().await?.unwrap())?;
+    assert_eq!(stats["learned"], 2);
+
+    // Exports land in the model directory and nowhere else.
+    writer.write_all(b"model_export:export.json\n").await?;
+    assert!(lines.next_line().await?.unwrap().starts_with("OK"));
+    let export = data.path().join("heuristics/export.json");
+    let exported: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&export)?)?;
+    assert!(exported["commands"]["terraform apply"].is_object());
+    for outside in [
+        data.path().join("escape.json").display().to_string(),
+        "../escape.json".to_string(),
+    ] {
+        writer
+            .write_all(format!("model_export:{}\n", outside).as_bytes())
+            .await?;
+        assert!(lines.next_line().await?.unwrap().starts_with("ERROR"));
+    }
+    assert!(!data.path().join("escape.json").exists());
+
+    writer.write_all(b"specs:\n").await?;
+    let specs: serde_json::Value = serde_json::from_str(&lines.next_line().await?.unwrap())?;
+    let tf = specs.as_array().unwrap().iter().find(|s| s["name"] == "tf").unwrap();
+    assert!(tf["origin"].as_str().unwrap().ends_with("tf.json"));
+
+    writer.write_all(b"heuristic_query:tf pl;/infra;\n").await?;
+    let suggestions: serde_json::Value =
+        serde_json::from_str(&lines.next_line().await?.unwrap())?;
+    assert_eq!(suggestions[0]["text"], "plan");
+    assert_eq!(suggestions[0]["source"], "Hint");
+
+    // Imported history is redacted before it is learned.
+    let history = data.path().join(".bash_history");
+    std::fs::write(&history, "#1700000000\nexport DB_PASSWORD=hunter2\n")?;
+    writer.write_all(b"history_import:bash\n").await?;
+    let report: serde_json::Value = serde_json::from_str(&lines.next_line().await?.unwrap())?;
+    assert_eq!(report["learned"], 1);
+    assert_eq!(report["redacted"], 1);
+    // Other files are not shell history, whatever their name.
+    writer
+        .write_all(format!("history_import:{}\n", config.display()).as_bytes())
+        .await?;
+    assert!(lines.next_line().await?.unwrap().starts_with("ERROR"));
+    writer
+        .write_all(b"history_import:{\"first_run\":true}\n")
+        .await?;
+    let report: serde_json::Value = serde_json::from_str(&lines.next_line().await?.unwrap())?;
+    assert_eq!(report["already_imported"], true);
+
     child.kill().await?;
     Ok(())
 }
@@ -0,0 +1,169 @@
+use tos_common::config::HeuristicConfig;
+use tos_heuristicd::{CommandModel, HistoryEntry, Query, SpecSet, Suggestion};
+use uuid::Uuid;
+
+const DAY: i64 = 86_400;
+const NOW: i64 = 1_760_000_000;
+
+fn entry(command: &str, cwd: &str, sector: Option<Uuid>, exit: i32, ts: i64) -> HistoryEntry {
+    HistoryEntry {
+        id: Uuid::new_v4().to_string(),
+        command: command.to_string(),
+        cwd: cwd.to_string(),
+        sector_id: sector,
+        exit_status: Some(exit),
+        ts,
+    }
+}
+
+fn query<'a>(keyword: &'a str, cwd: &'a str, sector: Option<&'a str>) -> Query<'a> {
+    Query {
+        keyword,
+        cwd,
+        sector_id: sector,
+        now: NOW,
+    }
+}
+
+fn suggest(model: &CommandModel, q: &Query) -> Vec<Suggestion> {
+    tos_heuristicd::suggest(model, &SpecSet::bundled(), q)
+}
+
+fn texts(model: &CommandModel, q: &Query) -> Vec<String> {
+    suggest(model, q).into_iter().map(|s| s.text).collect()
+}
+
+#[test]
+fn test_recent_use_outranks_old_frequent_use() {
+    let mut model = CommandModel::new(HeuristicConfig::default());
+    for day in 30..40 {
+        model.learn(&entry("git log --oneline", "/repo", None, 0, NOW - day * DAY));
+    }
+    model.learn(&entry("git lfs pull", "/repo", None, 0, NOW - 60));
+    model.learn(&entry("git lfs pull", "/repo", None, 0, NOW - 30));
+
+    let results = suggest(&model, &query("git l", "/nowhere", None));
+    let history: Vec<&str> = results
+        .iter()
+        .filter(|s| s.source == "History")
+        .map(|s| s.text.as_str())
+        .collect();
+    assert_eq!(history, ["git lfs pull", "git log --oneline"]);
+    assert!(results.iter().all(|s| s.score > 0.0 && s.score <= 1.0));
+}
+
+#[test]
+fn test_directory_and_sector_rank_their_own_commands() {
+    let mut model = CommandModel::new(HeuristicConfig::default());
+    let web = Uuid::new_v4();
+    for _ in 0..3 {
+        model.learn(&entry("npm run dev", "/web", Some(web), 0, NOW - 100));
+    }
+    model.learn(&entry("npm run build", "/api", None, 0, NOW - 50));
+
+    assert_eq!(texts(&model, &query("npm r", "/web", None))[0], "npm run dev");
+    assert_eq!(texts(&model, &query("npm r", "/api", None))[0], "npm run build");
+    let web = web.to_string();
+    assert_eq!(
+        texts(&model, &query("npm r", "/elsewhere", Some(&web)))[0],
+        "npm run dev"
+    );
+}
+
+#[test]
+fn test_next_command_predictions_follow_the_sector_context() {
+    let mut model = CommandModel::new(HeuristicConfig::default());
+    let sector = Uuid::new_v4();
+    let mut ts = NOW - 1000;
+    for _ in 0..3 {
+        for command in ["git add -A", "git commit -m wip", "git push"] {
+            ts += 10;
+            model.learn(&entry(command, "/repo", Some(sector), 0, ts));
+        }
+    }
+    ts += 10;
+    model.learn(&entry("git add src/main.rs", "/repo", Some(sector), 0, ts));
+
+    let sector = sector.to_string();
+    let predictions = suggest(&model, &query("", "/repo", Some(&sector)));
+    assert_eq!(predictions[0].text, "git commit -m wip");
+    assert_eq!(predictions[0].source, "Prediction");
+    assert!(predictions.iter().all(|s| s.source == "Prediction"));
+
+    // Nothing ran in a fresh sector, so nothing is predicted there.
+    assert!(suggest(&model, &query("", "/repo", Some("fresh"))).is_empty());
+}
+
+#[test]
+fn test_subcommands_and_flags_are_learned() {
+    let mut model = CommandModel::new(HeuristicConfig::default());
+    for _ in 0..3 {
+        model.learn(&entry("kubectl get pods --watch", "/", None, 0, NOW - 10));
+    }
+    model.learn(&entry("cargo nextest run", "/", None, 0, NOW - 10));
+
+    let hints = |keyword: &str| -> Vec<String> {
+        suggest(&model, &query(keyword, "/", None))
+            .into_iter()
+            .filter(|s| s.source == "Hint")
+            .map(|s| s.text)
+            .collect()
+    };
+    assert_eq!(hints("kubectl "), ["get"]);
+    assert_eq!(hints("kubectl get "), ["pods"]);
+    assert_eq!(hints("kubectl get pods --w"), ["--watch"]);
+    // The learned subcommand ranks above the seeded ones.
+    assert_eq!(hints("cargo ")[0], "nextest");
+    assert!(hints("cargo t").contains(&"test".to_string()));
+}
+
+#[test]
+fn test_not_found_and_duplicate_entries_are_ignored() {
+    let mut model = CommandModel::new(HeuristicConfig::default());
+    let once = entry("make check", "/", None, 0, NOW);
+    assert!(model.learn(&once));
+    assert!(!model.learn(&once));
+    assert!(!model.learn(&entry("mkae check", "/", None, 127, NOW)));
+    assert!(!model.learn(&entry("   ", "/", None, 0, NOW)));
+    assert_eq!(model.stats().learned, 1);
+    assert_eq!(model.commands["make check"].count, 1);
+
+    // Learned programs are offered as typo corrections.
+    let results = suggest(&model, &query("mkae", "/nowhere", None));
+    assert!(results.iter().any(|s| s.text == "make" && s.source == "Typo"));
+}
+
+#[test]
+fn test_model_survives_restart_and_prunes() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = HeuristicConfig {
+        max_commands: 2,
+        ..Default::default()
+    };
+    let mut model = CommandModel::open(dir.path(), config.clone()).unwrap();
+    let seen = entry("ls -la", "/", None, 0, NOW - 5);
+    model.learn(&seen);
+    model.learn(&entry("ls -la", "/", None, 0, NOW - 4));
+    model.learn(&entry("htop", "/", None, 0, NOW - 3));
+    model.learn(&entry("du -sh", "/", None, 1, NOW - 2));
+    model.learn(&entry("ancient", "/", None, 0, NOW - 365 * DAY));
+    model.prune(NOW);
+    model.save(dir.path()).unwrap();
+
+    let mut reopened = CommandModel::open(dir.path(), config.clone()).unwrap();
+    let mut kept: Vec<&String> = reopened.commands.keys().collect();
+    kept.sort();
+    assert_eq!(kept, ["htop", "ls -la"]);
+    assert!(!reopened.by_dir["/"].contains_key("du -sh"));
+    // Ids learned before the restart are still recognised.
+    assert!(!reopened.learn(&seen));
+
+    let imported = CommandModel::import(&reopened.export(), config).unwrap();
+    assert_eq!(imported.stats(), reopened.stats());
+
+    // An unreadable model is set aside, not overwritten.
+    std::fs::write(dir.path().join("model.json"), "{ not json").unwrap();
+    let fresh = CommandModel::open(dir.path(), HeuristicConfig::default()).unwrap();
+    assert!(fresh.commands.is_empty());
+    assert!(dir.path().join("model.json.bad").exists());
+}
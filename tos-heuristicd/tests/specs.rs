@@ -0,0 +1,285 @@
+use std::fs;
+use std::path::Path;
+use tos_common::brain::module_manager::ModuleManager;
+use tos_common::config::HeuristicConfig;
+use tos_heuristicd::{
+    complete_path, suggest, CandidateKind, CommandModel, Generator, Query, SpecSet,
+};
+
+fn complete(specs: &SpecSet, line: &str, cwd: &Path) -> Vec<String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (done, partial) = if line.ends_with(' ') {
+        (&words[..], "")
+    } else {
+        (&words[..words.len() - 1], words[words.len() - 1])
+    };
+    specs
+        .complete(done, partial, cwd.to_str().unwrap())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|c| c.text)
+        .collect()
+}
+
+#[test]
+fn test_paths_resolve_through_nested_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("src/bin")).unwrap();
+    fs::write(dir.path().join("src/main.rs"), "").unwrap();
+    fs::write(dir.path().join("src/.hidden"), "").unwrap();
+    fs::write(dir.path().join("README.md"), "").unwrap();
+    let cwd = dir.path().to_str().unwrap();
+
+    assert_eq!(complete_path("s", cwd, false), ["src/"]);
+    assert_eq!(
+        complete_path("src/", cwd, false),
+        ["src/bin/", "src/main.rs"]
+    );
+    assert_eq!(complete_path("src/m", cwd, false), ["src/main.rs"]);
+    assert_eq!(complete_path("src/.", cwd, false), ["src/.hidden"]);
+    assert_eq!(complete_path("src/", cwd, true), ["src/bin/"]);
+    let absolute = format!("{}/src/b", cwd);
+    assert_eq!(
+        complete_path(&absolute, "/", false),
+        [format!("{}/", absolute + "in")]
+    );
+    assert!(complete_path("missing/", cwd, false).is_empty());
+
+    // A spec's file argument completes the same way.
+    let specs = SpecSet::bundled();
+    assert_eq!(
+        complete(&specs, "git add src/m", dir.path()),
+        ["src/main.rs"]
+    );
+}
+
+#[test]
+fn test_subcommands_options_and_their_values() {
+    let dir = tempfile::tempdir().unwrap();
+    let specs = SpecSet::bundled();
+    let at = |line: &str| complete(&specs, line, dir.path());
+
+    assert_eq!(at("git sta"), ["status", "stash"]);
+    assert!(at("git ").contains(&"rebase".to_string()));
+    assert!(at("git commit --am").contains(&"--amend".to_string()));
+    // Persistent options are offered under subcommands, others are not.
+    assert!(at("git commit -").contains(&"-C".to_string()));
+    assert!(!at("git commit -").contains(&"--version".to_string()));
+    // An option's value is completed, then positional arguments again.
+    assert_eq!(at("apt -y ins"), ["install"]);
+    assert_eq!(
+        at("npm version p"),
+        ["patch", "prepatch", "preminor", "premajor", "prerelease"]
+    );
+    assert_eq!(at("git stash p"), ["push", "pop"]);
+    assert_eq!(at("docker compose up --re"), ["--remove-orphans"]);
+    // Aliases walk into the subcommand and prefixes are skipped.
+    assert_eq!(at("sudo npm i --save-d"), ["--save-dev"]);
+    assert_eq!(at("cargo t --no-r"), ["--no-run"]);
+    // Programs without a spec have no completions.
+    assert!(specs.complete(&["kubectl"], "", "/").is_none());
+}
+
+#[test]
+fn test_generators_read_the_project() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+
+    // Git refs, loose and packed, local before remote.
+    fs::create_dir_all(root.join(".git/refs/heads/feature")).unwrap();
+    fs::create_dir_all(root.join(".git/refs/remotes/origin")).unwrap();
+    fs::write(root.join(".git/refs/heads/main"), "0\n").unwrap();
+    fs::write(root.join(".git/refs/heads/feature/specs"), "0\n").unwrap();
+    fs::write(
+        root.join(".git/refs/remotes/origin/HEAD"),
+        "ref: refs/remotes/origin/main\n",
+    )
+    .unwrap();
+    fs::write(
+        root.join(".git/packed-refs"),
+        "# pack-refs with: peeled\n0 refs/heads/release\n0 refs/remotes/origin/main\n0 refs/tags/v1\n",
+    )
+    .unwrap();
+
+    // A Cargo workspace with a glob member and an explicit one.
+    fs::create_dir_all(root.join("crates/alpha")).unwrap();
+    fs::create_dir_all(root.join("tool/src")).unwrap();
+    fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"crates/*\", \"tool\"]\n",
+    )
+    .unwrap();
+    fs::write(
+        root.join("crates/alpha/Cargo.toml"),
+        "[package]\nname = \"alpha\"\n",
+    )
+    .unwrap();
+    fs::write(root.join("tool/Cargo.toml"), "[package]\nname = \"tool\"\n").unwrap();
+
+    fs::write(
+        root.join("Makefile"),
+        "CC := gcc\nVAR = a:b\n.PHONY: all\nall: build\nbuild test: deps\n\tcc -o x\n%.o: %.c\n",
+    )
+    .unwrap();
+    fs::write(
+        root.join("package.json"),
+        r#"{ "scripts": { "dev": "vite", "lint": "eslint ." } }"#,
+    )
+    .unwrap();
+
+    // Generators look upward from a nested directory where files are shared.
+    let nested = root.join("tool/src");
+    assert_eq!(
+        Generator::GitBranches.generate(&nested),
+        ["feature/specs", "main", "release", "origin/main"]
+    );
+    assert_eq!(
+        Generator::CargoPackages.generate(&nested),
+        ["alpha", "tool"]
+    );
+    assert_eq!(Generator::NpmScripts.generate(&nested), ["dev", "lint"]);
+    assert_eq!(
+        Generator::MakeTargets.generate(root),
+        ["all", "build", "test"]
+    );
+    assert_eq!(
+        Generator::Script(vec!["printf".into(), "b\\na\\nb\\n".into()]).generate(root),
+        ["b", "a"]
+    );
+    assert!(Generator::Script(vec!["false".into()])
+        .generate(root)
+        .is_empty());
+
+    let specs = SpecSet::bundled();
+    assert_eq!(
+        complete(&specs, "git checkout feat", root),
+        ["feature/specs"]
+    );
+    assert_eq!(complete(&specs, "cargo test -p a", &nested), ["alpha"]);
+    assert_eq!(complete(&specs, "npm run l", root), ["lint"]);
+}
+
+#[test]
+fn test_module_and_user_specs_override_bundled_ones() {
+    let modules_dir = tempfile::tempdir().unwrap();
+    let module = modules_dir.path().join("kube");
+    fs::create_dir_all(&module).unwrap();
+    fs::write(
+        module.join("module.toml"),
+        "id = \"kube\"\nname = \"Kube\"\nversion = \"0.1.0\"\nmodule_type = \"shell\"\nauthor = \"t\"\ncompletions = [\"kubectl.json\", \"missing.json\"]\n",
+    )
+    .unwrap();
+    fs::write(
+        module.join("kubectl.json"),
+        r#"[{ "name": "kubectl", "subcommands": [{ "name": "get", "args": [{ "name": "kind", "suggestions": ["pods", "services"] }] }] },
+            { "name": "git", "subcommands": [{ "name": "sync" }] }]"#,
+    )
+    .unwrap();
+    let modules = ModuleManager::new(modules_dir.path().to_path_buf());
+
+    let user_dir = tempfile::tempdir().unwrap();
+    fs::write(
+        user_dir.path().join("kubectl.json"),
+        r#"{ "name": "kubectl", "subcommands": [{ "name": "apply" }, { "name": "get" }] }"#,
+    )
+    .unwrap();
+    fs::write(user_dir.path().join("broken.json"), "{ \"subcommands\": ").unwrap();
+
+    let (specs, errors) = SpecSet::load(&modules, user_dir.path());
+    assert_eq!(errors.len(), 2, "{:?}", errors);
+    let origin = |name: &str| {
+        specs
+            .list()
+            .find(|l| l.spec.name == name)
+            .map(|l| l.origin.clone())
+            .unwrap()
+    };
+    assert_eq!(origin("npm"), "bundled");
+    assert_eq!(origin("git"), "module:kube");
+    assert!(origin("kubectl").ends_with("kubectl.json"));
+    assert!(!origin("kubectl").starts_with("module:"));
+
+    let cwd = Path::new("/");
+    assert_eq!(complete(&specs, "git sy", cwd), ["sync"]);
+    assert_eq!(complete(&specs, "kubectl ", cwd), ["apply", "get"]);
+
+    // No modules and a missing spec directory add nothing.
+    let empty = tempfile::tempdir().unwrap();
+    let (bundled, errors) = SpecSet::load(
+        &ModuleManager::new(empty.path().to_path_buf()),
+        &empty.path().join("completions"),
+    );
+    assert!(errors.is_empty());
+    assert_eq!(bundled.list().count(), 5);
+}
+
+#[test]
+fn test_spec_completions_are_ranked_with_history() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join(".git/refs/heads")).unwrap();
+    fs::write(dir.path().join(".git/refs/heads/main"), "0\n").unwrap();
+    fs::create_dir(dir.path().join("docs")).unwrap();
+    let cwd = dir.path().to_str().unwrap();
+    let model = CommandModel::new(HeuristicConfig::default());
+    let specs = SpecSet::bundled();
+    let results = |keyword: &str| {
+        suggest(
+            &model,
+            &specs,
+            &Query {
+                keyword,
+                cwd,
+                sector_id: None,
+                now: 0,
+            },
+        )
+    };
+
+    let branch = results("git switch m");
+    assert_eq!(branch[0].text, "main");
+    assert_eq!(branch[0].source, "Hint");
+    let path = results("git add d");
+    assert_eq!(
+        (path[0].text.as_str(), path[0].source.as_str()),
+        ("docs/", "Path")
+    );
+    // Words no spec covers still complete as paths.
+    assert_eq!(results("cat d")[0].text, "docs/");
+    assert!(results("git commit -")
+        .iter()
+        .all(|s| s.text.starts_with('-')));
+    assert_eq!(
+        specs.complete(&["git"], "swi", cwd).unwrap()[0].kind,
+        CandidateKind::Subcommand
+    );
+}
+
+#[test]
+fn test_only_granted_modules_may_run_script_generators() {
+    let spec = r#"{ "name": "deploy", "args": [{ "name": "env", "generators": [{ "script": ["echo", "prod"] }] }] }"#;
+    let modules_dir = tempfile::tempdir().unwrap();
+    for (id, capabilities) in [("plain", ""), ("granted", "capabilities = [\"completion_scripts\"]\n")] {
+        let module = modules_dir.path().join(id);
+        fs::create_dir_all(&module).unwrap();
+        fs::write(
+            module.join("module.toml"),
+            format!(
+                "id = \"{id}\"\nname = \"{id}\"\nversion = \"0.1.0\"\nmodule_type = \"shell\"\nauthor = \"t\"\n{capabilities}completions = [\"{id}.json\"]\n"
+            ),
+        )
+        .unwrap();
+        fs::write(module.join(format!("{}.json", id)), spec.replace("deploy", id)).unwrap();
+    }
+    let user_dir = tempfile::tempdir().unwrap();
+    fs::write(user_dir.path().join("user.json"), spec.replace("deploy", "user")).unwrap();
+
+    let (specs, errors) = SpecSet::load(
+        &ModuleManager::new(modules_dir.path().to_path_buf()),
+        user_dir.path(),
+    );
+    assert_eq!(errors.len(), 2, "{:?}", errors);
+    assert!(errors.iter().all(|e| e.contains(tos_heuristicd::SCRIPT_CAPABILITY)));
+    assert!(specs.get("granted").is_some());
+    assert!(specs.get("plain").is_none());
+    assert!(specs.get("user").is_none());
+}
@@ -0,0 +1,87 @@
+//! Crash report groups (§19.5) over the segments of the crash log.
+//!
+//! Reports are grouped by signature when listed rather than when stored,
+//! so the crash log stays an append-only record of every report.
+
+use crate::store::Segment;
+use std::collections::HashMap;
+use tos_common::daemon::crash::CrashReport;
+
+/// Every report sharing one signature.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CrashGroup {
+    pub signature: String,
+    pub service: String,
+    /// Message and location of the latest report.
+    pub message: String,
+    pub location: String,
+    /// The latest report's top application frame, if symbolized.
+    #[serde(default)]
+    pub top_frame: Option<String>,
+    pub count: usize,
+    pub first_seen: i64,
+    pub last_seen: i64,
+    pub latest_id: String,
+}
+
+/// Each report in `segments`, oldest first within a segment.
+fn reports(segments: &[Segment]) -> impl Iterator<Item = CrashReport> + '_ {
+    segments
+        .iter()
+        .filter_map(|segment| segment.lines().ok())
+        .flatten()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| CrashReport::parse(&line))
+}
+
+/// Groups by signature, most recently seen first.
+pub fn groups(segments: &[Segment]) -> Vec<CrashGroup> {
+    let mut groups: HashMap<String, CrashGroup> = HashMap::new();
+    for report in reports(segments) {
+        let group = groups
+            .entry(report.signature.clone())
+            .or_insert_with(|| CrashGroup {
+                signature: report.signature.clone(),
+                service: report.service.clone(),
+                message: String::new(),
+                location: String::new(),
+                top_frame: None,
+                count: 0,
+                first_seen: report.ts,
+                last_seen: i64::MIN,
+                latest_id: String::new(),
+            });
+        group.count += 1;
+        group.first_seen = group.first_seen.min(report.ts);
+        if report.ts >= group.last_seen {
+            group.last_seen = report.ts;
+            group.message = report.message.clone();
+            group.location = report.location.clone();
+            group.top_frame = report
+                .top_frame()
+                .and_then(|f| f.function().map(str::to_string));
+            group.latest_id = report.id.clone();
+        }
+    }
+    let mut groups: Vec<CrashGroup> = groups.into_values().collect();
+    groups.sort_by(|a, b| {
+        b.last_seen
+            .cmp(&a.last_seen)
+            .then_with(|| a.signature.cmp(&b.signature))
+    });
+    groups
+}
+
+/// The report with id `key`, or else the latest report of signature `key`.
+pub fn find(segments: &[Segment], key: &str) -> Option<CrashReport> {
+    let mut latest: Option<CrashReport> = None;
+    for report in reports(segments) {
+        if report.id == key {
+            return Some(report);
+        }
+        if report.signature == key && latest.as_ref().is_none_or(|l| report.ts >= l.ts) {
+            latest = Some(report);
+        }
+    }
+    latest
+}
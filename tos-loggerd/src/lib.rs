@@ -0,0 +1,23 @@
+//! TOS Log Service Library (`tos-loggerd`)
+//!
+//! Stores the unified log (§19) as JSON Lines under `{data_dir}`, rotated
+//! into compressed archives and pruned by the `[logging]` retention limits
+//! (§19.4), and answers filtered, paged queries over it (§3.3.4). The same
+//! filters select the records streamed to subscribers. Crash reports are
+//! kept in a log of their own and grouped by signature (§19.5).
+
+pub mod crashes;
+pub mod query;
+pub mod store;
+
+pub use crashes::CrashGroup;
+pub use query::{LogPage, LogQuery, TimeBound};
+pub use store::{LogStore, Segment};
+pub use tos_common::services::logger::{LogRecord, Severity, LOG_RECORD_VERSION};
+
+/// The daemon's listener on an ephemeral port for dynamic registration
+/// (§4.1). Loopback only: `subscribe` streams every record and the crash
+/// commands are unauthenticated, and the Brain is the one client.
+pub async fn listen() -> std::io::Result<tokio::net::TcpListener> {
+    tokio::net::TcpListener::bind("127.0.0.1:0").await
+}
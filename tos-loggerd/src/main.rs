@@ -1,22 +1,36 @@
 use chrono::Local;
-use std::fs::OpenOptions;
-use std::io::Write;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::net::{TcpListener, TcpStream};
-
-#[derive(serde::Serialize, serde::Deserialize)]
-struct LogRecord {
-    ts: i64,
-    level: String,
-    source: String,
-    event: String,
-    data: String,
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::tcp::{ReadHalf, WriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tos_common::daemon::crash::CrashReport;
+use tos_loggerd::{crashes, LogQuery, LogRecord, LogStore, Severity, LOG_RECORD_VERSION};
+
+/// How often the logs are checked for rotation and retention.
+const MAINTENANCE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Records a subscriber may fall behind by before it is told it lagged.
+const FEED_CAPACITY: usize = 1024;
+
+/// The system log, the crash log, and the feed of new system records.
+struct Stores {
+    system: Mutex<LogStore>,
+    crashes: Mutex<LogStore>,
+    feed: broadcast::Sender<LogRecord>,
 }
 
-#[derive(serde::Deserialize)]
-struct QueryRequest {
-    surface: Option<String>,
-    limit: Option<usize>,
+impl Stores {
+    fn append_system(&self, record: &LogRecord) {
+        let json_entry = serde_json::to_string(record).unwrap_or_default();
+        if let Ok(mut store) = self.system.lock() {
+            if let Err(e) = store.append(record.ts, &json_entry) {
+                tracing::error!("[LOGGERD] Failed to write system log: {}", e);
+            }
+        }
+        // Nobody subscribed is not an error.
+        let _ = self.feed.send(record.clone());
+    }
 }
 
 #[tokio::main]
@@ -24,52 +38,63 @@ async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
     tos_common::install_crash_handler("tos-loggerd".to_string());
 
-    // Bind to ephemeral port for dynamic registration (§4.1)
-    let listener = TcpListener::bind("0.0.0.0:0").await?;
+    let listener = tos_loggerd::listen().await?;
     let port = listener.local_addr()?.port();
     tracing::info!("TOS-LOGGERD: Listening on port {}", port);
 
     // §4.1: Dynamic Port Registration Gate
     tos_common::register_with_brain("tos-loggerd", port).await?;
 
-    // Log file management (JSONL format for Alpha-2.1)
-    let log_path = dirs::home_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("/tmp"))
-        .join(".local/share/tos/system.jsonl");
-    if let Some(parent) = log_path.parent() {
-        std::fs::create_dir_all(parent)?;
-    }
+    // Log file management (§19.4)
+    let config = tos_common::TosConfig::load();
+    let log_dir = config.log_dir();
+    let stores = Arc::new(Stores {
+        system: Mutex::new(LogStore::open(&log_dir, "system", config.logging.clone())?),
+        crashes: Mutex::new(LogStore::open(&log_dir, "crashes", config.logging.clone())?),
+        feed: broadcast::channel(FEED_CAPACITY).0,
+    });
 
     tracing::info!("TOS-LOGGERD: Operational on port {}", port);
-    tracing::info!("TOS-LOGGERD: Storage: {:?}", log_path);
- 
-    let crash_path = log_path.parent().unwrap().join("crashes.jsonl");
-    tracing::info!("TOS-LOGGERD: Crash Dumps: {:?}", crash_path);
+    tracing::info!("TOS-LOGGERD: Storage: {:?}", log_dir.join("system.jsonl"));
+    tracing::info!(
+        "TOS-LOGGERD: Crash Dumps: {:?}",
+        log_dir.join("crashes.jsonl")
+    );
+
+    // Quiet logs still age out and expire.
+    let maintained = stores.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(MAINTENANCE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let now = Local::now().timestamp();
+            for store in [&maintained.system, &maintained.crashes] {
+                if let Ok(mut store) = store.lock() {
+                    if let Err(e) = store.maintain(now) {
+                        tracing::error!("[LOGGERD] Log maintenance failed: {}", e);
+                    }
+                }
+            }
+        }
+    });
 
     loop {
         let (socket, _) = listener.accept().await?;
-        let log_path_clone = log_path.clone();
+        let stores = stores.clone();
 
         tokio::spawn(async move {
-            if let Err(e) = handle_client(socket, log_path_clone).await {
+            if let Err(e) = handle_client(socket, stores).await {
                 tracing::error!("[LOGGERD] Client error: {}", e);
             }
         });
     }
 }
 
-async fn handle_client(mut socket: TcpStream, log_path: std::path::PathBuf) -> anyhow::Result<()> {
+async fn handle_client(mut socket: TcpStream, stores: Arc<Stores>) -> anyhow::Result<()> {
     let (reader, mut writer) = socket.split();
-    let mut reader = BufReader::new(reader);
-    let mut line = String::new();
-
-    loop {
-        line.clear();
-        let n = reader.read_line(&mut line).await?;
-        if n == 0 {
-            break;
-        }
+    let mut lines = BufReader::new(reader).lines();
 
+    while let Some(line) = lines.next_line().await? {
         let request = line.trim();
         if request.is_empty() {
             continue;
@@ -84,38 +109,60 @@ async fn handle_client(mut socket: TcpStream, log_path: std::path::PathBuf) -> a
         let prefix = parts[0];
         let payload = parts[1];
 
+        // §19.2: The connection turns into a stream of matching records.
+        if prefix == "subscribe" {
+            let filter: LogQuery = if payload.trim().is_empty() {
+                LogQuery::default()
+            } else {
+                match serde_json::from_str(payload) {
+                    Ok(f) => f,
+                    Err(e) => {
+                        writer
+                            .write_all(format!("ERROR: Invalid filter JSON: {}\n", e).as_bytes())
+                            .await?;
+                        continue;
+                    }
+                }
+            };
+            let feed = stores.feed.subscribe();
+            writer.write_all(b"OK: Subscribed\n").await?;
+            if !stream_records(&mut lines, &mut writer, &filter, feed).await? {
+                break;
+            }
+            continue;
+        }
+
         let response = match prefix {
+            // §19.1: A structured record, stamped if the sender left it bare.
+            "record" => match serde_json::from_str::<LogRecord>(payload) {
+                Ok(mut record) => {
+                    if record.v == 0 {
+                        record.v = LOG_RECORD_VERSION;
+                    }
+                    if record.ts == 0 {
+                        record.ts = Local::now().timestamp();
+                    }
+                    tracing::info!(
+                        "[LVL-{}] [{}] {}",
+                        record.level.priority(),
+                        record.source,
+                        record.data
+                    );
+                    stores.append_system(&record);
+                    "OK".to_string()
+                }
+                Err(e) => format!("ERROR: Invalid log record: {}", e),
+            },
             "log" => {
                 let args: Vec<&str> = payload.split(';').collect();
                 let message = args[0];
-                let level = args.get(1).unwrap_or(&"1");
+                let level = Severity::parse(args.get(1).unwrap_or(&"1"));
                 let source = args.get(2).unwrap_or(&"system");
 
-                let record = LogRecord {
-                    ts: Local::now().timestamp(),
-                    level: level.to_string(),
-                    source: source.to_string(),
-                    event: "log".to_string(),
-                    data: message.to_string(),
-                };
-
-                let json_entry = serde_json::to_string(&record).unwrap_or_default();
-                tracing::info!("[LVL-{}] [{}] {}", level, source, message);
-
-                if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&log_path) {
-                    let _ = writeln!(file, "{}", json_entry);
-                }
-
-                let config = tos_common::TosConfig::load();
-                let addr = format!("127.0.0.1:{}", config.remote.anchor_port);
-                if let Ok(mut brain_stream) = std::net::TcpStream::connect_timeout(
-                    &addr.parse().unwrap(),
-                    std::time::Duration::from_millis(50),
-                ) {
-                    let _ = brain_stream
-                        .write_all(format!("system_log_append:{};{}\n", level, message).as_bytes());
-                }
+                let record = LogRecord::new(level, source, "log", message);
 
+                tracing::info!("[LVL-{}] [{}] {}", level.priority(), source, message);
+                stores.append_system(&record);
                 "OK".to_string()
             }
             "archive_ai" => {
@@ -127,55 +174,109 @@ async fn handle_client(mut socket: TcpStream, log_path: std::path::PathBuf) -> a
                 }
                 match serde_json::from_str::<ArchiveRequest>(payload) {
                     Ok(data) => {
-                        let record = LogRecord {
-                            ts: Local::now().timestamp(),
-                            level: "ai".to_string(),
-                            source: data.behavior_id,
-                            event: "ai_exchange".to_string(),
-                            data: serde_json::json!({
-                                "prompt": data.prompt,
-                                "response": data.response
-                            })
-                            .to_string(),
-                        };
-
-                        let json_entry = serde_json::to_string(&record).unwrap_or_default();
-                        if let Ok(mut file) =
-                            OpenOptions::new().create(true).append(true).open(&log_path)
-                        {
-                            let _ = writeln!(file, "{}", json_entry);
-                        }
+                        let record = LogRecord::new(
+                            Severity::Info,
+                            &data.behavior_id,
+                            "ai_exchange",
+                            &data.prompt,
+                        )
+                        .with_field("prompt", data.prompt.clone())
+                        .with_field("response", data.response);
+
+                        stores.append_system(&record);
                         "OK".to_string()
                     }
                     Err(e) => format!("ERROR: Invalid JSON: {}", e),
                 }
             }
             "crash" => {
-                // Automated crash dump collection (§6.10)
-                let crash_path = log_path.parent().unwrap().join("crashes.jsonl");
-                if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&crash_path) {
-                    let _ = writeln!(file, "{}", payload);
+                // Automated crash dump collection (§6.10, §19.5)
+                let report = CrashReport::parse(payload);
+                let stored = match serde_json::to_string(&report) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        writer
+                            .write_all(format!("ERROR: {}\n", e).as_bytes())
+                            .await?;
+                        continue;
+                    }
+                };
+                let now = Local::now().timestamp();
+                let ts = if report.ts > 0 { report.ts } else { now };
+                if let Ok(mut store) = stores.crashes.lock() {
+                    if let Err(e) = store.append(ts, &stored) {
+                        tracing::error!("[LOGGERD] Failed to write crash report: {}", e);
+                    }
                 }
-                
+
                 // Also log to system log as CRITICAL
-                let record = LogRecord {
-                    ts: Local::now().timestamp(),
-                    level: "3".to_string(), // CRITICAL
-                    source: "system".to_string(),
-                    event: "crash".to_string(),
-                    data: format!("CRASH REPORT RECEIVED: {}", payload),
-                };
-                let json_entry = serde_json::to_string(&record).unwrap_or_default();
-                if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&log_path) {
-                    let _ = writeln!(file, "{}", json_entry);
+                let mut record = LogRecord::new(
+                    Severity::Critical,
+                    &report.service,
+                    "crash",
+                    &format!("CRASH REPORT RECEIVED: {}", report.message),
+                )
+                .with_field("id", report.id.clone())
+                .with_field("signature", report.signature.clone())
+                .with_field("location", report.location.clone());
+                if !report.correlation_id.is_empty() {
+                    record = record.with_correlation_id(&report.correlation_id);
                 }
+                record.ts = now;
+                stores.append_system(&record);
 
-                tracing::error!("CRASH REPORT RECEIVED: {}", payload);
+                tracing::error!(
+                    "CRASH REPORT RECEIVED: [{}] {} ({})",
+                    report.service,
+                    report.message,
+                    report.signature
+                );
                 "OK".to_string()
             }
+            // §19.5: Crash groups, one report, or a purge.
+            "crashes" => {
+                let segments = match stores.crashes.lock() {
+                    Ok(store) => store.segments(),
+                    Err(_) => Vec::new(),
+                };
+                let groups =
+                    tokio::task::spawn_blocking(move || crashes::groups(&segments)).await?;
+                serde_json::to_string(&groups).unwrap_or_default()
+            }
+            "crash_show" => {
+                let segments = match stores.crashes.lock() {
+                    Ok(store) => store.segments(),
+                    Err(_) => Vec::new(),
+                };
+                let key = payload.trim().to_string();
+                let lookup = key.clone();
+                let found =
+                    tokio::task::spawn_blocking(move || crashes::find(&segments, &lookup)).await?;
+                match found {
+                    Some(report) => serde_json::to_string(&report).unwrap_or_default(),
+                    None => format!("ERROR: No crash report '{}'", key),
+                }
+            }
+            "crash_purge" => {
+                let signature = payload.trim();
+                if signature.is_empty() {
+                    "ERROR: Missing crash signature (or 'all')".to_string()
+                } else {
+                    let purged = match stores.crashes.lock() {
+                        Ok(mut store) => store.retain(|line| {
+                            signature != "all" && CrashReport::parse(line).signature != signature
+                        }),
+                        Err(_) => Ok(0),
+                    };
+                    match purged {
+                        Ok(n) => format!("OK: Purged {}", n),
+                        Err(e) => format!("ERROR: {}", e),
+                    }
+                }
+            }
             "query" => {
-                let req: QueryRequest = match serde_json::from_str(payload) {
-                    Ok(r) => r,
+                let query: LogQuery = match serde_json::from_str(payload) {
+                    Ok(q) => q,
                     Err(e) => {
                         writer
                             .write_all(format!("ERROR: Invalid query JSON: {}\n", e).as_bytes())
@@ -184,35 +285,17 @@ async fn handle_client(mut socket: TcpStream, log_path: std::path::PathBuf) -> a
                     }
                 };
 
-                let limit = req.limit.unwrap_or(50);
-                let mut results = Vec::new();
-
-                if let Ok(file) = std::fs::File::open(&log_path) {
-                    use std::io::BufRead;
-                    let reader = std::io::BufReader::new(file);
-                    // For Alpha, we do a simple reverse scan (limited)
-                    let lines: Vec<String> = reader.lines().filter_map(|l| l.ok()).collect();
-                    for line in lines.iter().rev() {
-                        if results.len() >= limit {
-                            break;
-                        }
-                        if let Ok(record) = serde_json::from_str::<LogRecord>(line) {
-                            // Simple filtering
-                            if let Some(s) = &req.surface {
-                                if record.source != *s {
-                                    continue;
-                                }
-                            }
-                            results.push(record);
-                        }
-                    }
+                // Segments are listed under the lock and streamed outside it,
+                // so a long query never holds up logging.
+                let segments = match stores.system.lock() {
+                    Ok(store) => store.segments(),
+                    Err(_) => Vec::new(),
+                };
+                let now = Local::now().timestamp();
+                match tokio::task::spawn_blocking(move || query.run(&segments, now)).await? {
+                    Ok(page) => serde_json::to_string(&page).unwrap_or_default(),
+                    Err(e) => format!("ERROR: {}", e),
                 }
-
-                let output = serde_json::json!({
-                    "query_id": uuid::Uuid::new_v4().to_string(),
-                    "results": results
-                });
-                serde_json::to_string(&output).unwrap_or_default()
             }
             _ => "ERROR: Unknown command".to_string(),
         };
@@ -224,3 +307,43 @@ async fn handle_client(mut socket: TcpStream, log_path: std::path::PathBuf) -> a
     }
     Ok(())
 }
+
+/// Push `record:<json>` for each new record passing `filter` until the
+/// client sends `unsubscribe:` (returns true, back to requests) or closes
+/// the connection (returns false). A client that falls more than
+/// [`FEED_CAPACITY`] records behind gets `lagged:<n>` for the ones it missed.
+async fn stream_records(
+    lines: &mut Lines<BufReader<ReadHalf<'_>>>,
+    writer: &mut WriteHalf<'_>,
+    filter: &LogQuery,
+    mut feed: broadcast::Receiver<LogRecord>,
+) -> anyhow::Result<bool> {
+    loop {
+        tokio::select! {
+            received = feed.recv() => {
+                let push = match received {
+                    Ok(record) if filter.matches(&record) => {
+                        format!("record:{}\n", serde_json::to_string(&record)?)
+                    }
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(n)) => format!("lagged:{}\n", n),
+                    Err(broadcast::error::RecvError::Closed) => return Ok(false),
+                };
+                writer.write_all(push.as_bytes()).await?;
+            }
+            line = lines.next_line() => match line? {
+                None => return Ok(false),
+                Some(l) if l.trim().starts_with("unsubscribe") => {
+                    writer.write_all(b"OK: Unsubscribed\n").await?;
+                    return Ok(true);
+                }
+                Some(l) if l.trim().is_empty() => {}
+                Some(_) => {
+                    writer
+                        .write_all(b"ERROR: Subscribed; send unsubscribe: first\n")
+                        .await?;
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! Log queries (§3.3.4): filters, time bounds and cursor paging over the
+//! segments of a [`LogStore`](crate::LogStore).
+//!
+//! Results are newest first. Segments are read one at a time, newest first,
+//! keeping only as many matches as the page still needs, so a query holds a
+//! page of records in memory however long the history is.
+
+use crate::store::Segment;
+use crate::{LogRecord, Severity};
+use std::collections::VecDeque;
+
+/// Records returned when a query sets no limit.
+pub const DEFAULT_LIMIT: usize = 50;
+/// The most records one page can hold.
+pub const MAX_LIMIT: usize = 1000;
+
+/// A point in time: unix seconds, or an offset back from now.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum TimeBound {
+    At(i64),
+    /// `"-10m"`, `"2h"`, `"30s"`, `"1d"` (the sign is optional), or unix
+    /// seconds as a string.
+    Ago(String),
+}
+
+impl TimeBound {
+    /// The unix time this bound names at `now`.
+    pub fn resolve(&self, now: i64) -> anyhow::Result<i64> {
+        let text = match self {
+            TimeBound::At(ts) => return Ok(*ts),
+            TimeBound::Ago(text) => text.trim(),
+        };
+        if let Ok(ts) = text.parse::<i64>() {
+            return Ok(ts);
+        }
+        let offset = text.strip_prefix('-').unwrap_or(text);
+        let split = offset
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(offset.len());
+        let (amount, unit) = offset.split_at(split);
+        let amount: i64 = amount
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid time bound: {}", text))?;
+        let seconds = match unit.trim() {
+            "s" | "sec" => 1,
+            "m" | "min" => 60,
+            "h" => 3600,
+            "d" => 86400,
+            _ => anyhow::bail!("Invalid time bound: {}", text),
+        };
+        Ok(now - amount * seconds)
+    }
+}
+
+/// A `query:` request. Every filter is optional.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LogQuery {
+    /// Only records from this source; `surface` is accepted for it.
+    #[serde(default, alias = "surface")]
+    pub source: Option<String>,
+    /// Only records at or after this time.
+    #[serde(default)]
+    pub since: Option<TimeBound>,
+    /// Only records at or before this time.
+    #[serde(default)]
+    pub until: Option<TimeBound>,
+    /// Only records of at least this severity: a priority (`0`–`3`) or a
+    /// name (`"warning"`).
+    #[serde(default)]
+    pub min_level: Option<Severity>,
+    /// Only records of this event type (`log`, `ai_exchange`, `crash`...).
+    #[serde(default)]
+    pub event: Option<String>,
+    /// Only records of this request.
+    #[serde(default)]
+    pub correlation_id: Option<String>,
+    /// Only records whose data contains this text, ignoring case.
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Continue after the page that returned this cursor.
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+/// One page of results.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LogPage {
+    pub query_id: String,
+    pub results: Vec<LogRecord>,
+    /// Pass back as `cursor` for the next page; `None` on the last page.
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// Where a page ended: records up to `ts`, less the first `skip` records
+/// stamped exactly `ts`, which an earlier page already returned. It names
+/// a time rather than a file position so it stays valid across rotation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cursor {
+    ts: i64,
+    skip: usize,
+}
+
+impl Cursor {
+    fn parse(text: &str) -> anyhow::Result<Self> {
+        let parsed = text
+            .split_once(':')
+            .and_then(|(ts, skip)| Some((ts.parse().ok()?, skip.parse().ok()?)));
+        match parsed {
+            Some((ts, skip)) => Ok(Self { ts, skip }),
+            None => anyhow::bail!("Invalid cursor: {}", text),
+        }
+    }
+
+    fn encode(&self) -> String {
+        format!("{}:{}", self.ts, self.skip)
+    }
+}
+
+impl LogQuery {
+    /// Whether `record` passes the source, event, level, correlation and
+    /// text filters.
+    /// Time bounds and paging apply only to [`LogQuery::run`].
+    pub fn matches(&self, record: &LogRecord) -> bool {
+        let needle = self.text.as_ref().map(|t| t.to_lowercase());
+        self.matches_with(record, needle.as_deref())
+    }
+
+    fn matches_with(&self, record: &LogRecord, needle: Option<&str>) -> bool {
+        self.source.as_ref().is_none_or(|s| record.source == *s)
+            && self.event.as_ref().is_none_or(|e| record.event == *e)
+            && self.min_level.is_none_or(|min| record.level >= min)
+            && self
+                .correlation_id
+                .as_ref()
+                .is_none_or(|c| record.correlation_id.as_ref() == Some(c))
+            && needle.is_none_or(|n| record.data.to_lowercase().contains(n))
+    }
+
+    /// Run the query over `segments`, newest first, as of `now`.
+    pub fn run(&self, segments: &[Segment], now: i64) -> anyhow::Result<LogPage> {
+        let limit = self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        let since = self.since.as_ref().map(|b| b.resolve(now)).transpose()?;
+        let until = self.until.as_ref().map(|b| b.resolve(now)).transpose()?;
+        let cursor = self.cursor.as_deref().map(Cursor::parse).transpose()?;
+        let upper = match (until, cursor) {
+            (Some(u), Some(c)) => Some(u.min(c.ts)),
+            (u, c) => u.or(c.map(|c| c.ts)),
+        };
+        let skip = cursor.map_or(0, |c| c.skip);
+        let needle = self.text.as_ref().map(|t| t.to_lowercase());
+        let wanted = limit + skip;
+
+        // Matches so far, newest first.
+        let mut found: Vec<LogRecord> = Vec::new();
+        for segment in segments {
+            if found.len() >= wanted {
+                break;
+            }
+            if !segment.overlaps(since, upper) {
+                continue;
+            }
+            let lines = match segment.lines() {
+                Ok(lines) => lines,
+                // Rotated away since the segments were listed.
+                Err(_) => continue,
+            };
+            // The newest matches of this segment that can still make the page.
+            let room = wanted - found.len();
+            let mut newest: VecDeque<LogRecord> = VecDeque::with_capacity(room);
+            for line in lines {
+                let record: LogRecord = match serde_json::from_str(&line) {
+                    Ok(r) => r,
+                    Err(_) => continue,
+                };
+                if since.is_some_and(|s| record.ts < s)
+                    || upper.is_some_and(|u| record.ts > u)
+                    || !self.matches_with(&record, needle.as_deref())
+                {
+                    continue;
+                }
+                if newest.len() == room {
+                    newest.pop_front();
+                }
+                newest.push_back(record);
+            }
+            found.extend(newest.into_iter().rev());
+        }
+
+        let results: Vec<LogRecord> = found.into_iter().skip(skip).take(limit).collect();
+        let next_cursor = match results.last() {
+            Some(last) if results.len() == limit => {
+                let mut skip = results.iter().filter(|r| r.ts == last.ts).count();
+                if let Some(c) = cursor.filter(|c| c.ts == last.ts) {
+                    skip += c.skip;
+                }
+                Some(Cursor { ts: last.ts, skip }.encode())
+            }
+            _ => None,
+        };
+        Ok(LogPage {
+            query_id: uuid::Uuid::new_v4().to_string(),
+            results,
+            next_cursor,
+        })
+    }
+}
@@ -0,0 +1,303 @@
+//! Log storage (§19.4): one active JSON Lines file per log, rotated by size
+//! or age into archives, and archives pruned by the retention limits.
+//!
+//! An archive is named `<log>.<first>-<last>.jsonl[.gz]` after the oldest
+//! and newest record timestamps it holds, so a query can pass over the
+//! archives outside its time range without opening them.
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use tos_common::config::LoggingConfig;
+
+/// One file of a log: the active file or an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub path: PathBuf,
+    /// Oldest and newest record timestamps, `None` if it holds no records.
+    pub span: Option<(i64, i64)>,
+    pub compressed: bool,
+}
+
+impl Segment {
+    /// Whether the segment may hold records timestamped within
+    /// `from..=to`; an open end is unbounded.
+    pub fn overlaps(&self, from: Option<i64>, to: Option<i64>) -> bool {
+        match self.span {
+            Some((first, last)) => from.is_none_or(|f| last >= f) && to.is_none_or(|t| first <= t),
+            None => false,
+        }
+    }
+
+    /// The segment's lines in file order, decompressed as they are read.
+    pub fn lines(&self) -> std::io::Result<impl Iterator<Item = String>> {
+        let file = File::open(&self.path)?;
+        let reader: Box<dyn Read> = if self.compressed {
+            Box::new(GzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+        Ok(BufReader::new(reader).lines().map_while(Result::ok))
+    }
+}
+
+/// A log on disk: `<dir>/<name>.jsonl` and its archives.
+pub struct LogStore {
+    dir: PathBuf,
+    name: String,
+    config: LoggingConfig,
+    /// Bytes in the active file.
+    size: u64,
+    /// Oldest and newest record timestamps in the active file.
+    span: Option<(i64, i64)>,
+}
+
+impl LogStore {
+    /// Open the log `name` in `dir`, reading back the size and time span of
+    /// an active file left by an earlier run.
+    pub fn open(dir: &Path, name: &str, config: LoggingConfig) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let mut store = Self {
+            dir: dir.to_path_buf(),
+            name: name.to_string(),
+            config,
+            size: 0,
+            span: None,
+        };
+        let active = store.active();
+        if let Ok(meta) = std::fs::metadata(&active.path) {
+            store.size = meta.len();
+            for line in active.lines()? {
+                if let Some(ts) = timestamp_of(&line) {
+                    store.extend_span(ts);
+                }
+            }
+            // Lines without a timestamp count as written when the file was.
+            if store.span.is_none() && store.size > 0 {
+                let modified = meta
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map_or(0, |d| d.as_secs() as i64);
+                store.span = Some((modified, modified));
+            }
+        }
+        Ok(store)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The file records are appended to.
+    pub fn active_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", self.name))
+    }
+
+    fn active(&self) -> Segment {
+        Segment {
+            path: self.active_path(),
+            span: self.span,
+            compressed: false,
+        }
+    }
+
+    fn extend_span(&mut self, ts: i64) {
+        self.span = Some(match self.span {
+            Some((first, last)) => (first.min(ts), last.max(ts)),
+            None => (ts, ts),
+        });
+    }
+
+    /// Append one line recorded at `ts`, rotating first if the active file
+    /// is due.
+    pub fn append(&mut self, ts: i64, line: &str) -> std::io::Result<()> {
+        if self.is_due(ts) {
+            self.rotate(ts)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.active_path())?;
+        writeln!(file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+        self.extend_span(ts);
+        Ok(())
+    }
+
+    /// Whether the active file has outgrown the size or age limit at `now`.
+    pub fn is_due(&self, now: i64) -> bool {
+        let max_age = self.config.max_file_age_hours as i64 * 3600;
+        (self.config.max_file_size > 0 && self.size >= self.config.max_file_size)
+            || (max_age > 0 && self.span.is_some_and(|(first, _)| now - first >= max_age))
+    }
+
+    /// Rotate the active file if it is due and apply the retention limits.
+    /// Run periodically so a quiet log still ages out.
+    pub fn maintain(&mut self, now: i64) -> std::io::Result<()> {
+        if self.is_due(now) {
+            self.rotate(now)?;
+        } else {
+            self.prune(now)?;
+        }
+        Ok(())
+    }
+
+    /// Move the active file into an archive, compressing it if configured,
+    /// then apply the retention limits. Returns the archive, or `None` if
+    /// there was nothing to rotate.
+    pub fn rotate(&mut self, now: i64) -> std::io::Result<Option<PathBuf>> {
+        let (first, last) = match self.span {
+            Some(span) => span,
+            None => return Ok(None),
+        };
+        let ext = if self.config.compress {
+            "jsonl.gz"
+        } else {
+            "jsonl"
+        };
+        let mut archive = self
+            .dir
+            .join(format!("{}.{}-{}.{}", self.name, first, last, ext));
+        let mut n = 1;
+        while archive.exists() {
+            archive = self
+                .dir
+                .join(format!("{}.{}-{}-{}.{}", self.name, first, last, n, ext));
+            n += 1;
+        }
+
+        let active = self.active_path();
+        if self.config.compress {
+            // Written aside and renamed, so a reader never sees half of it.
+            let partial = archive.with_extension("gz.tmp");
+            let mut encoder = GzEncoder::new(File::create(&partial)?, Compression::default());
+            std::io::copy(&mut File::open(&active)?, &mut encoder)?;
+            encoder.finish()?.sync_all()?;
+            std::fs::rename(&partial, &archive)?;
+            std::fs::remove_file(&active)?;
+        } else {
+            std::fs::rename(&active, &archive)?;
+        }
+        self.size = 0;
+        self.span = None;
+        tracing::info!("[LOGGERD] Rotated {} into {:?}", self.name, archive);
+
+        self.prune(now)?;
+        Ok(Some(archive))
+    }
+
+    /// Delete the archives past the retention age or count. Returns how many
+    /// were deleted.
+    pub fn prune(&self, now: i64) -> std::io::Result<usize> {
+        let max_age = self.config.retention_days as i64 * 86400;
+        let mut removed = 0;
+        for (i, archive) in self.archives().iter().enumerate() {
+            let expired = max_age > 0 && archive.span.is_some_and(|(_, last)| now - last > max_age);
+            let surplus = self.config.max_archives > 0 && i >= self.config.max_archives;
+            if expired || surplus {
+                std::fs::remove_file(&archive.path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Keep only the lines `keep` accepts, rewriting every segment that
+    /// loses one and deleting those left empty. Archives keep their names:
+    /// the span they claim still covers what remains. Returns how many
+    /// lines were removed.
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) -> std::io::Result<usize> {
+        let mut removed = 0;
+        for segment in self.segments() {
+            if !segment.path.exists() {
+                continue;
+            }
+            let lines: Vec<String> = segment.lines()?.collect();
+            let total = lines.len();
+            let kept: Vec<String> = lines.into_iter().filter(|l| keep(l)).collect();
+            if kept.len() == total {
+                continue;
+            }
+            removed += total - kept.len();
+
+            let is_active = segment.path == self.active_path();
+            if kept.is_empty() {
+                std::fs::remove_file(&segment.path)?;
+            } else {
+                let body: String = kept.iter().map(|l| format!("{}\n", l)).collect();
+                let partial = segment.path.with_extension("tmp");
+                let mut file = File::create(&partial)?;
+                if segment.compressed {
+                    let mut encoder = GzEncoder::new(file, Compression::default());
+                    encoder.write_all(body.as_bytes())?;
+                    encoder.finish()?.sync_all()?;
+                } else {
+                    file.write_all(body.as_bytes())?;
+                }
+                std::fs::rename(&partial, &segment.path)?;
+            }
+            if is_active {
+                self.size = kept.iter().map(|l| l.len() as u64 + 1).sum();
+                self.span = None;
+                for ts in kept.iter().filter_map(|l| timestamp_of(l)) {
+                    self.extend_span(ts);
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Every segment, newest first: the active file, then the archives.
+    pub fn segments(&self) -> Vec<Segment> {
+        let mut segments = vec![self.active()];
+        segments.extend(self.archives());
+        segments
+    }
+
+    /// The archives, newest first.
+    pub fn archives(&self) -> Vec<Segment> {
+        let prefix = format!("{}.", self.name);
+        let mut archives: Vec<(i64, i64, u32, Segment)> = std::fs::read_dir(&self.dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .filter_map(|e| {
+                        let file_name = e.file_name().to_string_lossy().to_string();
+                        let rest = file_name.strip_prefix(&prefix)?;
+                        let (stem, compressed) = match rest.strip_suffix(".jsonl.gz") {
+                            Some(stem) => (stem, true),
+                            None => (rest.strip_suffix(".jsonl")?, false),
+                        };
+                        let mut parts = stem.split('-');
+                        let first = parts.next()?.parse().ok()?;
+                        let last = parts.next()?.parse().ok()?;
+                        let n = match parts.next() {
+                            Some(n) => n.parse().ok()?,
+                            None => 0,
+                        };
+                        let segment = Segment {
+                            path: e.path(),
+                            span: Some((first, last)),
+                            compressed,
+                        };
+                        Some((last, first, n, segment))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        archives.sort_by_key(|a| std::cmp::Reverse((a.0, a.1, a.2)));
+        archives.into_iter().map(|(_, _, _, s)| s).collect()
+    }
+}
+
+/// The `ts` field of a JSON line.
+fn timestamp_of(line: &str) -> Option<i64> {
+    serde_json::from_str::<serde_json::Value>(line)
+        .ok()?
+        .get("ts")?
+        .as_i64()
+}
@@ -0,0 +1,122 @@
+use tempfile::tempdir;
+use tos_common::config::LoggingConfig;
+use tos_common::daemon::crash::{CrashFrame, CrashReport};
+use tos_loggerd::{crashes, LogStore};
+
+fn report(ts: i64, service: &str, symbol: &str, message: &str) -> CrashReport {
+    let mut report = CrashReport::new(service, message, "src/main.rs:1:1");
+    report.ts = ts;
+    report.frames = vec![CrashFrame {
+        ip: "0x1000".to_string(),
+        offset: None,
+        symbol: Some(symbol.to_string()),
+        file: None,
+        line: None,
+    }];
+    report.sign();
+    report
+}
+
+fn store(dir: &std::path::Path) -> LogStore {
+    LogStore::open(
+        dir,
+        "crashes",
+        LoggingConfig {
+            max_file_size: 600,
+            max_file_age_hours: 0,
+            retention_days: 0,
+            max_archives: 0,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_reports_group_by_signature_across_archives() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let mut store = store(dir.path());
+    let mut reports = Vec::new();
+    for i in 0..6 {
+        reports.push(report(
+            100 + i,
+            "tos-searchd",
+            "tos_searchd::index::add",
+            "bad offset",
+        ));
+    }
+    reports.push(report(
+        200,
+        "tos-sessiond",
+        "tos_sessiond::save",
+        "disk full",
+    ));
+    for r in &reports {
+        store.append(r.ts, &serde_json::to_string(r)?)?;
+    }
+    // A report from before signatures were stored.
+    store.append(150, "SIGSEGV at 0x41414141")?;
+    assert!(!store.archives().is_empty(), "spread over archives");
+
+    let groups = crashes::groups(&store.segments());
+    assert_eq!(groups.len(), 3);
+    assert_eq!(groups[0].service, "tos-sessiond");
+    let searchd = groups.iter().find(|g| g.service == "tos-searchd").unwrap();
+    assert_eq!(searchd.count, 6);
+    assert_eq!((searchd.first_seen, searchd.last_seen), (100, 105));
+    assert_eq!(searchd.latest_id, reports[5].id);
+    assert_eq!(
+        searchd.top_frame.as_deref(),
+        Some("tos_searchd::index::add")
+    );
+
+    // By id, or the latest of a signature.
+    let segments = store.segments();
+    assert_eq!(
+        crashes::find(&segments, &reports[2].id),
+        Some(reports[2].clone())
+    );
+    assert_eq!(
+        crashes::find(&segments, &searchd.signature),
+        Some(reports[5].clone())
+    );
+    assert_eq!(crashes::find(&segments, "nothing"), None);
+    Ok(())
+}
+
+#[test]
+fn test_purge_rewrites_segments() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let mut store = store(dir.path());
+    let keep = report(300, "tos-sessiond", "tos_sessiond::save", "disk full");
+    for i in 0..6 {
+        let r = report(
+            100 + i,
+            "tos-searchd",
+            "tos_searchd::index::add",
+            "bad offset",
+        );
+        store.append(r.ts, &serde_json::to_string(&r)?)?;
+    }
+    store.append(keep.ts, &serde_json::to_string(&keep)?)?;
+
+    let signature = crashes::groups(&store.segments())
+        .into_iter()
+        .find(|g| g.service == "tos-searchd")
+        .unwrap()
+        .signature;
+    let removed = store.retain(|line| CrashReport::parse(line).signature != signature)?;
+    assert_eq!(removed, 6);
+    let groups = crashes::groups(&store.segments());
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].latest_id, keep.id);
+
+    // Emptied archives are gone; appending still works.
+    assert!(store.archives().is_empty());
+    store.append(400, &serde_json::to_string(&keep)?)?;
+    assert_eq!(crashes::groups(&store.segments())[0].count, 2);
+
+    assert_eq!(store.retain(|_| false)?, 2);
+    assert!(crashes::groups(&store.segments()).is_empty());
+    Ok(())
+}
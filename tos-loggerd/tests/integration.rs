@@ -12,13 +12,82 @@ async fn test_loggerd_integration() -> anyhow::Result<()> {
     let (_name, port) = mock_brain.handle_one_registration().await?;
     assert_eq!(_name, "tos-loggerd");
     
+    // A subscriber sees matching records as they are logged.
+    let subscriber = TcpStream::connect(format!("127.0.0.1:{}", port)).await?;
+    let (sub_reader, mut sub_writer) = subscriber.into_split();
+    let mut records = BufReader::new(sub_reader).lines();
+    sub_writer
+        .write_all(b"subscribe:{\"source\": \"test-source\", \"min_level\": 2}\n")
+        .await?;
+    assert_eq!(
+        records.next_line().await?.as_deref(),
+        Some("OK: Subscribed")
+    );
+
     let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).await?;
-    stream.write_all(b"log:Test message;1;test-source\n").await?;
+    stream
+        .write_all(b"log:Test message;1;test-source\n")
+        .await?;
+    stream.write_all(b"log:Other source;3;elsewhere\n").await?;
+    stream
+        .write_all(b"log:Disk almost full;2;test-source\n")
+        .await?;
     let mut reader = BufReader::new(stream);
     let mut line = String::new();
-    reader.read_line(&mut line).await?;
-    assert_eq!(line.trim(), "OK");
+    for _ in 0..3 {
+        line.clear();
+        reader.read_line(&mut line).await?;
+        assert_eq!(line.trim(), "OK");
+    }
+
+    let pushed = records.next_line().await?.unwrap();
+    let record: serde_json::Value = serde_json::from_str(pushed.strip_prefix("record:").unwrap())?;
+    assert_eq!(record["data"], "Disk almost full");
+    assert_eq!(record["source"], "test-source");
+    assert_eq!(record["level"], "warning");
+
+    // Unsubscribing hands the connection back to requests.
+    sub_writer.write_all(b"unsubscribe:\n").await?;
+    assert_eq!(
+        records.next_line().await?.as_deref(),
+        Some("OK: Unsubscribed")
+    );
+    sub_writer.write_all(b"query:{\"limit\": 1}\n").await?;
+    let page: serde_json::Value = serde_json::from_str(&records.next_line().await?.unwrap())?;
+    assert_eq!(page["results"].as_array().unwrap().len(), 1);
+
+    // Crash reports, grouped by signature.
+    for _ in 0..2 {
+        sub_writer
+            .write_all(b"crash:{\"service\": \"tos-searchd\", \"message\": \"boom\"}\n")
+            .await?;
+        assert_eq!(records.next_line().await?.as_deref(), Some("OK"));
+    }
+    sub_writer.write_all(b"crashes:\n").await?;
+    let groups: serde_json::Value = serde_json::from_str(&records.next_line().await?.unwrap())?;
+    assert_eq!(groups[0]["count"], 2);
+    let signature = groups[0]["signature"].as_str().unwrap().to_string();
+    sub_writer
+        .write_all(format!("crash_show:{}\n", signature).as_bytes())
+        .await?;
+    let report: serde_json::Value = serde_json::from_str(&records.next_line().await?.unwrap())?;
+    assert_eq!(report["service"], "tos-searchd");
+    sub_writer
+        .write_all(format!("crash_purge:{}\n", signature).as_bytes())
+        .await?;
+    assert_eq!(
+        records.next_line().await?.as_deref(),
+        Some("OK: Purged 2")
+    );
     
     child.kill().await?;
     Ok(())
 }
+
+#[tokio::test]
+async fn test_listener_is_loopback_only() -> anyhow::Result<()> {
+    // `subscribe` and `crash_purge` answer anyone who can connect.
+    let listener = tos_loggerd::listen().await?;
+    assert!(listener.local_addr()?.ip().is_loopback());
+    Ok(())
+}
@@ -0,0 +1,237 @@
+use tempfile::tempdir;
+use tos_common::config::LoggingConfig;
+use tos_loggerd::{LogQuery, LogRecord, LogStore, Severity, TimeBound};
+
+fn record(ts: i64, level: &str, source: &str, event: &str, data: &str) -> LogRecord {
+    LogRecord {
+        ts,
+        ..LogRecord::new(Severity::parse(level), source, event, data)
+    }
+}
+
+fn append(store: &mut LogStore, record: &LogRecord) {
+    store
+        .append(record.ts, &serde_json::to_string(record).unwrap())
+        .unwrap();
+}
+
+fn config(max_file_size: u64) -> LoggingConfig {
+    LoggingConfig {
+        max_file_size,
+        max_file_age_hours: 0,
+        retention_days: 0,
+        max_archives: 0,
+        ..Default::default()
+    }
+}
+
+fn data(page: &tos_loggerd::LogPage) -> Vec<&str> {
+    page.results.iter().map(|r| r.data.as_str()).collect()
+}
+
+#[test]
+fn test_rotation_compresses_and_reopens() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let mut store = LogStore::open(dir.path(), "system", config(200))?;
+    for ts in 0..10 {
+        append(
+            &mut store,
+            &record(100 + ts, "1", "brain", "log", "a line of some length"),
+        );
+    }
+    let archives = store.archives();
+    assert!(archives.len() >= 2, "rotated by size");
+    assert!(archives.iter().all(|a| a.compressed));
+    assert!(archives[0].span.unwrap().0 > archives[1].span.unwrap().1);
+    let name = archives[0].path.file_name().unwrap().to_string_lossy();
+    assert!(name.starts_with("system.") && name.ends_with(".jsonl.gz"));
+
+    // Every record is still there, newest first, across the archives.
+    let page = LogQuery::default().run(&store.segments(), 200)?;
+    let stamps: Vec<i64> = page.results.iter().map(|r| r.ts).collect();
+    assert_eq!(stamps, (100..110).rev().collect::<Vec<_>>());
+
+    // A restart picks the active file's span back up.
+    let span = store.segments()[0].span;
+    drop(store);
+    let store = LogStore::open(dir.path(), "system", config(200))?;
+    assert_eq!(store.segments()[0].span, span);
+    Ok(())
+}
+
+#[test]
+fn test_age_rotation_and_retention() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let day = 86400;
+    let mut store = LogStore::open(
+        dir.path(),
+        "crashes",
+        LoggingConfig {
+            max_file_age_hours: 24,
+            retention_days: 7,
+            max_archives: 3,
+            compress: false,
+            ..Default::default()
+        },
+    )?;
+    for d in 0..6 {
+        append(&mut store, &record(d * day, "3", "brain", "crash", "boom"));
+    }
+    // Each day rotated the one before it.
+    assert_eq!(store.archives().len(), 3, "capped at max_archives");
+    assert!(store.archives().iter().all(|a| !a.compressed));
+
+    // Quiet logs age out when maintained.
+    store.maintain(20 * day)?;
+    assert!(store.archives().is_empty(), "all past retention");
+    assert!(!store.active_path().exists());
+    Ok(())
+}
+
+#[test]
+fn test_query_filters_and_time_bounds() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let mut store = LogStore::open(dir.path(), "system", config(300))?;
+    let records = [
+        record(1000, "1", "brain", "log", "Sector created"),
+        record(1010, "3", "security", "log", "AUDIT [guest]: denied"),
+        record(1020, "ai", "chat", "ai_exchange", "{\"prompt\":\"hi\"}"),
+        record(1030, "2", "brain", "log", "Search daemon unavailable"),
+        record(1040, "3", "system", "crash", "CRASH REPORT RECEIVED"),
+    ];
+    for r in &records {
+        append(&mut store, r);
+    }
+    let segments = store.segments();
+    let run = |query: LogQuery| query.run(&segments, 1100).unwrap();
+
+    let brain = run(LogQuery {
+        source: Some("brain".to_string()),
+        ..Default::default()
+    });
+    assert_eq!(
+        data(&brain),
+        vec!["Search daemon unavailable", "Sector created"]
+    );
+
+    // `surface` is the §3.3.4 name for source.
+    let surface: LogQuery = serde_json::from_str(r#"{"surface": "chat", "limit": 5}"#)?;
+    assert_eq!(run(surface).results.len(), 1);
+
+    let severe = run(LogQuery {
+        min_level: Some(Severity::Warning),
+        ..Default::default()
+    });
+    assert_eq!(severe.results.len(), 3);
+    // A level may be named or given as a priority.
+    let named: LogQuery = serde_json::from_str(r#"{"min_level": "critical"}"#)?;
+    let numbered: LogQuery = serde_json::from_str(r#"{"min_level": 3}"#)?;
+    assert_eq!(named, numbered);
+    assert_eq!(run(named).results.len(), 2);
+
+    let crashes = run(LogQuery {
+        event: Some("crash".to_string()),
+        ..Default::default()
+    });
+    assert_eq!(data(&crashes), vec!["CRASH REPORT RECEIVED"]);
+
+    let text = run(LogQuery {
+        text: Some("audit".to_string()),
+        ..Default::default()
+    });
+    assert_eq!(text.results[0].source, "security");
+
+    let window = run(LogQuery {
+        since: Some(TimeBound::Ago("-80s".to_string())),
+        until: Some(TimeBound::At(1030)),
+        ..Default::default()
+    });
+    assert_eq!(
+        window.results.iter().map(|r| r.ts).collect::<Vec<_>>(),
+        vec![1030, 1020]
+    );
+
+    let bad = LogQuery {
+        since: Some(TimeBound::Ago("yesterday".to_string())),
+        ..Default::default()
+    };
+    assert!(bad.run(&segments, 1100).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_cursor_pages_through_rotation() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let mut store = LogStore::open(dir.path(), "system", config(400))?;
+    // Several records share a second, so pages split within it.
+    for i in 0..30 {
+        append(
+            &mut store,
+            &record(100 + i / 4, "1", "brain", "log", &format!("line {}", i)),
+        );
+    }
+
+    let mut seen = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = LogQuery {
+            limit: Some(7),
+            cursor: cursor.clone(),
+            ..Default::default()
+        }
+        .run(&store.segments(), 200)?;
+        seen.extend(page.results.iter().map(|r| r.data.clone()));
+        // Rotation between pages does not disturb the cursor.
+        append(&mut store, &record(300, "1", "brain", "log", "later"));
+        cursor = page.next_cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    let expected: Vec<String> = (0..30).rev().map(|i| format!("line {}", i)).collect();
+    assert_eq!(seen, expected);
+
+    let bad = LogQuery {
+        cursor: Some("page-two".to_string()),
+        ..Default::default()
+    };
+    assert!(bad.run(&store.segments(), 200).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_records_before_the_schema_still_query() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let mut store = LogStore::open(dir.path(), "system", config(0))?;
+    // As written before records were versioned.
+    store.append(
+        100,
+        r#"{"ts":100,"level":"3","source":"security","event":"log","data":"old audit"}"#,
+    )?;
+    store.append(
+        110,
+        r#"{"ts":110,"level":"ai","source":"chat","event":"ai_exchange","data":"{}"}"#,
+    )?;
+    let request = LogRecord::new(Severity::Warning, "tos-searchd", "log", "slow query")
+        .with_correlation_id("req-7")
+        .with_field("ms", 1200);
+    append(&mut store, &LogRecord { ts: 120, ..request });
+
+    let page = LogQuery::default().run(&store.segments(), 200)?;
+    let levels: Vec<Severity> = page.results.iter().map(|r| r.level).collect();
+    assert_eq!(
+        levels,
+        vec![Severity::Warning, Severity::Info, Severity::Critical]
+    );
+    assert_eq!(page.results[2].v, 0);
+    assert_eq!(page.results[0].v, tos_loggerd::LOG_RECORD_VERSION);
+    assert_eq!(page.results[0].fields["ms"], 1200);
+
+    let correlated = LogQuery {
+        correlation_id: Some("req-7".to_string()),
+        ..Default::default()
+    }
+    .run(&store.segments(), 200)?;
+    assert_eq!(data(&correlated), vec!["slow query"]);
+    Ok(())
+}
@@ -1,40 +1,74 @@
-use std::collections::HashMap;
 use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use sysinfo::System;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::{TcpListener, TcpStream};
+use tos_common::services::priority::{self, ProcessActivity, SectorSignals};
+
+/// Process metrics older than this are refreshed before scoring. CPU usage
+/// is measured between two refreshes, so they must not be too close either.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The process table, shared by every client.
+struct Metrics {
+    sys: System,
+    refreshed: Instant,
+}
+
+impl Metrics {
+    fn activity(&mut self, roots: &[u32]) -> ProcessActivity {
+        if self.refreshed.elapsed() >= REFRESH_INTERVAL {
+            self.sys.refresh_processes();
+            self.sys.refresh_memory();
+            self.refreshed = Instant::now();
+        }
+        ProcessActivity::measure(&self.sys, roots)
+    }
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
 
-    // Bind to ephemeral port for dynamic registration (§4.1)
-    let listener = TcpListener::bind("0.0.0.0:0").await?;
+    // Bind to ephemeral port for dynamic registration (§4.1). Loopback only:
+    // `get_priority` reports on any process tree and can fire haptics.
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
     let port = listener.local_addr()?.port();
     tracing::info!("TOS-PRIORITYD: Listening on port {}", port);
 
     // §4.1: Dynamic Port Registration Gate
     tos_common::register_with_brain("tos-priorityd", port).await?;
 
-    let mut sys = System::new_all();
+    let metrics = Arc::new(Mutex::new(Metrics {
+        sys: System::new_all(),
+        refreshed: Instant::now(),
+    }));
 
     loop {
         let (socket, _) = listener.accept().await?;
-        // Refresh system metrics periodically or on request
-        sys.refresh_all();
-
-        let cpu_load = sys.global_cpu_info().cpu_usage();
-        let mem_usage = (sys.used_memory() as f32 / sys.total_memory() as f32) * 100.0;
-
+        let metrics = metrics.clone();
         tokio::spawn(async move {
-            if let Err(e) = handle_client(socket, cpu_load, mem_usage).await {
+            if let Err(e) = handle_client(socket, metrics).await {
                 tracing::error!("[PRIORITYD] Client error: {}", e);
             }
         });
     }
 }
 
-async fn handle_client(mut socket: TcpStream, cpu_load: f32, mem_usage: f32) -> anyhow::Result<()> {
+/// The signals of a `get_priority:` request. A bare sector id, as older
+/// Brains send, scores the sector with no signals.
+fn parse_signals(payload: &str) -> SectorSignals {
+    match serde_json::from_str::<SectorSignals>(payload) {
+        Ok(signals) => signals,
+        Err(_) => SectorSignals {
+            sector_id: payload.trim().parse().unwrap_or_default(),
+            ..Default::default()
+        },
+    }
+}
+
+async fn handle_client(mut socket: TcpStream, metrics: Arc<Mutex<Metrics>>) -> anyhow::Result<()> {
     let (reader, mut writer) = socket.split();
     let mut reader = BufReader::new(reader);
     let mut line = String::new();
@@ -57,30 +91,22 @@ async fn handle_client(mut socket: TcpStream, cpu_load: f32, mem_usage: f32) ->
 
         let response = match prefix {
             "get_priority" => {
-                let sector_id = payload;
-                // §21.2: Scoring heuristics
-                let mut factors = HashMap::new();
-
-                // Mocking recency and focus data for Alpha
-                factors.insert("recency".to_string(), 0.75);
-                factors.insert("activity_cpu".to_string(), cpu_load / 100.0);
-                factors.insert("activity_mem".to_string(), mem_usage / 100.0);
-                factors.insert(
-                    "sector_context".to_string(),
-                    if sector_id.is_empty() { 0.0 } else { 0.1 },
+                // Visual Design §1.2: Score the sector from its signals
+                let mut signals = parse_signals(payload);
+                if !signals.shell_pids.is_empty() {
+                    signals.activity = Some(metrics.lock().unwrap().activity(&signals.shell_pids));
+                }
+                let score = priority::score(&signals);
+                tracing::debug!(
+                    "[PRIORITYD] Sector {} rank {}: {}",
+                    signals.sector_id,
+                    score.rank,
+                    score.explain()
                 );
 
-                let total_score = (0.4 * 0.75) + (0.15 * (cpu_load / 100.0)) + (0.1 * 0.1); // Simplified
-                let rank = (total_score * 4.0).round() as u8 + 1;
-
-                let score = serde_json::json!({
-                    "total_score": total_score,
-                    "rank": rank,
-                    "factors": factors
-                });
-
-                // Trigger tactile feedback if priority is high (§21.1)
-                if rank >= 4 {
+                // Trigger tactile feedback if priority is high (§21.1),
+                // unless a rule silenced the sector
+                if score.rank >= 4 && !score.silenced {
                     let config = tos_common::TosConfig::load();
                     let addr = format!("127.0.0.1:{}", config.remote.anchor_port);
                     if let Ok(mut haptic_stream) = std::net::TcpStream::connect_timeout(
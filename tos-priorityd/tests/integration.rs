@@ -18,6 +18,28 @@ async fn test_priorityd_integration() -> anyhow::Result<()> {
     let mut line = String::new();
     reader.read_line(&mut line).await?;
     assert!(line.contains("total_score"));
+
+    // A Brain sends the sector's signals; the daemon measures its processes.
+    let signals = serde_json::json!({
+        "sector_id": "6f1c2d3e-4a5b-4c6d-8e7f-9a0b1c2d3e4f",
+        "failing_hubs": 1,
+        "pending_confirmation": true,
+        "shell_pids": [std::process::id()],
+    });
+    let mut stream = reader.into_inner();
+    stream.write_all(format!("get_priority:{}\n", signals).as_bytes()).await?;
+    let mut reader = BufReader::new(stream);
+    line.clear();
+    reader.read_line(&mut line).await?;
+    let score: serde_json::Value = serde_json::from_str(&line)?;
+    assert!(score["rank"].as_u64().unwrap() >= 2);
+    let activity = score["breakdown"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|f| f["name"] == "activity")
+        .unwrap();
+    assert!(activity["reason"].as_str().unwrap().contains("process"));
     
     child.kill().await?;
     Ok(())
@@ -0,0 +1,243 @@
+//! §4.1: Embedding backends for semantic search.
+//!
+//! The daemon picks one at startup from `[search] embedding_backend`: a BERT
+//! model from a local directory or the Hugging Face hub, or the model-free
+//! hashing embedder. When none can be loaded it runs keyword-only.
+
+use candle_core::{Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use hf_hub::{api::sync::Api, Repo, RepoType};
+use std::collections::HashMap;
+use std::path::Path;
+use tokenizers::Tokenizer;
+use tos_common::TosConfig;
+
+/// Turns text into a vector for the HNSW graph.
+pub trait EmbeddingBackend: Send {
+    /// Names the model. Stored with every vector, so switching backends
+    /// re-embeds documents instead of comparing vectors from different
+    /// spaces.
+    fn id(&self) -> &str;
+
+    /// Generate an embedding vector for the given text.
+    fn embed(&self, text: &str) -> anyhow::Result<Vec<f32>>;
+}
+
+/// Handles text embedding using a BERT model.
+pub struct BertEmbedder {
+    id: String,
+    model: BertModel,
+    tokenizer: Tokenizer,
+    device: Device,
+}
+
+impl BertEmbedder {
+    /// The model downloaded by [`BertEmbedder::from_hub`].
+    pub const HUB_MODEL: &'static str = "sentence-transformers/all-MiniLM-L6-v2";
+
+    /// Download (or load from the Hugging Face cache) the default model.
+    pub fn from_hub() -> anyhow::Result<Self> {
+        let api = Api::new()?;
+        let repo = api.repo(Repo::new(Self::HUB_MODEL.to_string(), RepoType::Model));
+
+        let config_path = repo.get("config.json")?;
+        let weights_path = repo.get("model.safetensors")?;
+        let tokenizer_path = repo.get("tokenizer.json")?;
+        Self::load(
+            format!("bert:{}", Self::HUB_MODEL),
+            &config_path,
+            &weights_path,
+            &tokenizer_path,
+        )
+    }
+
+    /// Load a model from `dir`, which holds `config.json`, `tokenizer.json`
+    /// and `model.safetensors`. Needs no network access.
+    pub fn from_dir(dir: &Path) -> anyhow::Result<Self> {
+        let name = dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Self::load(
+            format!("bert:{}", name),
+            &dir.join("config.json"),
+            &dir.join("model.safetensors"),
+            &dir.join("tokenizer.json"),
+        )
+    }
+
+    fn load(
+        id: String,
+        config_path: &Path,
+        weights_path: &Path,
+        tokenizer_path: &Path,
+    ) -> anyhow::Result<Self> {
+        let device = Device::Cpu;
+        let config: Config = serde_json::from_str(&std::fs::read_to_string(config_path)?)?;
+        let tokenizer = Tokenizer::from_file(tokenizer_path).map_err(|e| anyhow::anyhow!(e))?;
+
+        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[weights_path], DTYPE, &device)? };
+        let model = BertModel::load(vb, &config)?;
+
+        Ok(Self {
+            id,
+            model,
+            tokenizer,
+            device,
+        })
+    }
+}
+
+impl EmbeddingBackend for BertEmbedder {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn embed(&self, text: &str) -> anyhow::Result<Vec<f32>> {
+        let tokens = self
+            .tokenizer
+            .encode(text, true)
+            .map_err(|e| anyhow::anyhow!(e))?;
+        let token_ids = Tensor::new(tokens.get_ids(), &self.device)?.unsqueeze(0)?;
+        let token_type_ids = token_ids.zeros_like()?;
+
+        let embeddings = self.model.forward(&token_ids, &token_type_ids, None)?;
+
+        // Mean pooling
+        let (_n_batch, n_tokens, _n_dims) = embeddings.dims3()?;
+        let pooled = (embeddings.sum(1)? / (n_tokens as f64))?;
+        let pooled = pooled.get(0)?; // Squeeze batch
+
+        // Normalization
+        let norm = pooled.sqr()?.sum_all()?.sqrt()?;
+        let norm_val = norm.to_vec0::<f32>()?;
+        let normalized = pooled.broadcast_div(&Tensor::new(norm_val, &self.device)?)?;
+
+        Ok(normalized.to_vec1()?)
+    }
+}
+
+/// Embedder that needs no model files. Words and their character trigrams
+/// are hashed into signed buckets, weighted by sublinear term frequency and
+/// L2-normalized: it matches shared vocabulary and word stems, not synonyms.
+pub struct HashingEmbedder {
+    id: String,
+    dims: usize,
+}
+
+impl HashingEmbedder {
+    pub const DEFAULT_DIMS: usize = 384;
+
+    pub fn new(dims: usize) -> Self {
+        Self {
+            id: format!("hashing:{}", dims),
+            dims: dims.max(1),
+        }
+    }
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_DIMS)
+    }
+}
+
+/// 64-bit FNV-1a: stable across builds, unlike `DefaultHasher`.
+fn fnv1a(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in parts.iter().flat_map(|p| p.iter()) {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+impl EmbeddingBackend for HashingEmbedder {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn embed(&self, text: &str) -> anyhow::Result<Vec<f32>> {
+        // Feature hash -> (occurrences, weight of one occurrence)
+        let mut features: HashMap<u64, (f32, f32)> = HashMap::new();
+        for word in text.split(|c: char| !c.is_alphanumeric()) {
+            if word.chars().count() < 2 {
+                continue;
+            }
+            let word = word.to_lowercase();
+            features
+                .entry(fnv1a(&[b"w:", word.as_bytes()]))
+                .or_insert((0.0, 1.0))
+                .0 += 1.0;
+
+            let padded: Vec<char> = format!("<{}>", word).chars().collect();
+            for trigram in padded.windows(3) {
+                let trigram: String = trigram.iter().collect();
+                features
+                    .entry(fnv1a(&[b"t:", trigram.as_bytes()]))
+                    .or_insert((0.0, 0.5))
+                    .0 += 1.0;
+            }
+        }
+
+        let mut vector = vec![0.0f32; self.dims];
+        for (hash, (count, weight)) in features {
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[(hash % self.dims as u64) as usize] += sign * weight * (1.0 + count.ln());
+        }
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|x| *x /= norm);
+        }
+        Ok(vector)
+    }
+}
+
+/// Pick the backend named by `[search] embedding_backend`. `None` means
+/// keyword-only search, either because it was asked for or because the
+/// requested model could not be loaded.
+pub fn select_backend(config: &TosConfig) -> Option<Box<dyn EmbeddingBackend>> {
+    let model_dir = config.search.resolved_model_dir(&config.platform);
+    let requested = config.search.embedding_backend.as_str();
+    let loaded = match requested {
+        "none" => return None,
+        "hashing" => return Some(Box::new(HashingEmbedder::default())),
+        "local" => BertEmbedder::from_dir(&model_dir),
+        "bert" => BertEmbedder::from_hub(),
+        _ => {
+            if requested != "auto" {
+                tracing::warn!(
+                    "TOS-SEARCHD: Unknown embedding backend '{}', using auto",
+                    requested
+                );
+            }
+            let bert = if model_dir.join("config.json").exists() {
+                BertEmbedder::from_dir(&model_dir)
+            } else {
+                BertEmbedder::from_hub()
+            };
+            return match bert {
+                Ok(bert) => Some(Box::new(bert)),
+                Err(e) => {
+                    tracing::warn!(
+                        "TOS-SEARCHD: No BERT model available ({}), using the hashing embedder",
+                        e
+                    );
+                    Some(Box::new(HashingEmbedder::default()))
+                }
+            };
+        }
+    };
+    match loaded {
+        Ok(bert) => Some(Box::new(bert)),
+        Err(e) => {
+            tracing::warn!(
+                "TOS-SEARCHD: '{}' embedding backend unavailable ({}), keyword-only mode",
+                requested,
+                e
+            );
+            None
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Query filters (§4.1): path prefix, glob, extension, modification time,
+//! document kind and sector scope.
+//!
+//! Path restrictions are compiled to regular expressions over the `key`
+//! field, so Tantivy applies them before scoring and paging stays exact. The
+//! same expressions check semantic hits, which come from the HNSW graph.
+//! Records have no path on disk, so path and extension filters leave them
+//! out; a sector scope admits files below its roots and the records tagged
+//! with it.
+
+use crate::{Fields, Indexed};
+use tantivy::query::{BooleanQuery, Occur, Query, RangeQuery, RegexQuery, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption};
+use tantivy::Term;
+use tos_common::services::search::{SearchFilters, SearchHitType};
+
+/// The full-match expression for a glob. `**` spans directories, `*` and
+/// `?` do not. Relative globs match the trailing components of a path.
+pub(crate) fn glob_to_regex(glob: &str) -> String {
+    let mut out = String::new();
+    if !glob.starts_with('/') {
+        out.push_str("(?:.*/)?");
+    }
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    out.push_str("(?:.*/)?");
+                } else {
+                    out.push_str(".*");
+                }
+            }
+            '*' => out.push_str("[^/]*"),
+            '?' => out.push_str("[^/]"),
+            c => out.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    out
+}
+
+/// The full-match expression for `dir` and everything below it.
+pub(crate) fn prefix_to_regex(dir: &str) -> String {
+    let dir = dir.trim_end_matches('/');
+    if dir.is_empty() {
+        return "/.*".to_string();
+    }
+    format!("{}(?:/.*)?", regex::escape(dir))
+}
+
+/// A compiled [`SearchFilters`].
+pub(crate) struct Filter {
+    /// Expressions a path must all match.
+    patterns: Vec<String>,
+    compiled: Vec<regex::Regex>,
+    extensions: Vec<String>,
+    modified_since: Option<u64>,
+    /// The roots as one alternation; with `sectors`, the scope.
+    roots: Option<(String, regex::Regex)>,
+    sectors: Vec<uuid::Uuid>,
+    kinds: Vec<SearchHitType>,
+}
+
+fn full_match(pattern: &str) -> anyhow::Result<regex::Regex> {
+    regex::Regex::new(&format!("^(?:{})$", pattern))
+        .map_err(|e| anyhow::anyhow!("Invalid path filter: {}", e))
+}
+
+/// A clause matching any of `values` in `field`.
+fn any_term<'a>(field: Field, values: impl Iterator<Item = &'a str>) -> Box<dyn Query> {
+    let terms: Vec<(Occur, Box<dyn Query>)> = values
+        .map(|value| {
+            let term = Term::from_field_text(field, value);
+            let query: Box<dyn Query> = Box::new(TermQuery::new(term, IndexRecordOption::Basic));
+            (Occur::Should, query)
+        })
+        .collect();
+    Box::new(BooleanQuery::new(terms))
+}
+
+impl Filter {
+    pub(crate) fn new(filters: &SearchFilters) -> anyhow::Result<Self> {
+        let mut patterns = Vec::new();
+        if let Some(prefix) = &filters.path_prefix {
+            patterns.push(prefix_to_regex(prefix));
+        }
+        if let Some(glob) = &filters.glob {
+            patterns.push(glob_to_regex(glob));
+        }
+        let roots = if filters.roots.is_empty() {
+            None
+        } else {
+            let roots: Vec<String> = filters
+                .roots
+                .iter()
+                .map(|r| format!("(?:{})", prefix_to_regex(r)))
+                .collect();
+            let pattern = roots.join("|");
+            let compiled = full_match(&pattern)?;
+            Some((pattern, compiled))
+        };
+
+        let compiled = patterns
+            .iter()
+            .map(|p| full_match(p))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            patterns,
+            compiled,
+            extensions: filters
+                .extensions
+                .iter()
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .collect(),
+            modified_since: filters.modified_since,
+            roots,
+            sectors: filters.sectors.clone(),
+            kinds: filters.kinds.clone(),
+        })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+            && self.extensions.is_empty()
+            && self.modified_since.is_none()
+            && self.roots.is_none()
+            && self.sectors.is_empty()
+            && self.kinds.is_empty()
+    }
+
+    /// Whether a document passes every restriction.
+    pub(crate) fn matches(&self, path: &str, doc: &Indexed) -> bool {
+        let scoped = (self.roots.is_none() && self.sectors.is_empty())
+            || self.roots.as_ref().is_some_and(|(_, re)| re.is_match(path))
+            || doc.sector.is_some_and(|s| self.sectors.contains(&s));
+        scoped
+            && self.compiled.iter().all(|re| re.is_match(path))
+            && (self.extensions.is_empty()
+                || doc
+                    .ext
+                    .as_deref()
+                    .is_some_and(|ext| self.extensions.iter().any(|e| e == ext)))
+            && self
+                .modified_since
+                .is_none_or(|since| doc.mtime.is_some_and(|m| m >= since))
+            && (self.kinds.is_empty() || self.kinds.contains(&doc.kind))
+    }
+
+    /// The restrictions as `Must` clauses for a Tantivy boolean query.
+    pub(crate) fn clauses(&self, fields: Fields) -> anyhow::Result<Vec<(Occur, Box<dyn Query>)>> {
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        for pattern in &self.patterns {
+            let query = RegexQuery::from_pattern(pattern, fields.key)
+                .map_err(|e| anyhow::anyhow!("Invalid path filter: {}", e))?;
+            clauses.push((Occur::Must, Box::new(query)));
+        }
+        if !self.extensions.is_empty() {
+            let any_ext = any_term(fields.ext, self.extensions.iter().map(String::as_str));
+            clauses.push((Occur::Must, any_ext));
+        }
+        if self.roots.is_some() || !self.sectors.is_empty() {
+            let mut scope: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+            if let Some((pattern, _)) = &self.roots {
+                let query = RegexQuery::from_pattern(pattern, fields.key)
+                    .map_err(|e| anyhow::anyhow!("Invalid path filter: {}", e))?;
+                scope.push((Occur::Should, Box::new(query)));
+            }
+            if !self.sectors.is_empty() {
+                let ids: Vec<String> = self.sectors.iter().map(|s| s.to_string()).collect();
+                scope.push((
+                    Occur::Should,
+                    any_term(fields.sector, ids.iter().map(String::as_str)),
+                ));
+            }
+            clauses.push((Occur::Must, Box::new(BooleanQuery::new(scope))));
+        }
+        if !self.kinds.is_empty() {
+            let kinds = any_term(fields.kind, self.kinds.iter().map(|k| k.as_str()));
+            clauses.push((Occur::Must, kinds));
+        }
+        if let Some(since) = self.modified_since {
+            let query = RangeQuery::new_u64("mtime".to_string(), since..u64::MAX);
+            clauses.push((Occur::Must, Box::new(query)));
+        }
+        Ok(clauses)
+    }
+}
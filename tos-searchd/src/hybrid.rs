@@ -0,0 +1,111 @@
+//! Hybrid ranking (§4.1): keyword and semantic results fused by weighted
+//! reciprocal rank.
+//!
+//! BM25 and vector scores live on unrelated scales, so the fused score uses
+//! only each engine's ranks: a hit earns `weight / (k + rank)` from every
+//! engine that found it. Each engine's raw score is still min-max
+//! normalized and kept on the hit, with its rank and share, to explain the
+//! result.
+
+use std::collections::HashMap;
+use tos_common::config::SearchConfig;
+use tos_common::services::search::{EngineMatch, SearchEngine, SearchHit};
+
+/// Engine weights and the fusion constant.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HybridWeights {
+    pub keyword: f32,
+    pub semantic: f32,
+    /// The RRF `k`; 60 is the usual choice.
+    pub k: f32,
+}
+
+impl Default for HybridWeights {
+    fn default() -> Self {
+        Self {
+            keyword: 1.0,
+            semantic: 1.0,
+            k: 60.0,
+        }
+    }
+}
+
+impl HybridWeights {
+    /// The `[search]` weights, with negative values clamped to zero.
+    pub fn from_config(config: &SearchConfig) -> Self {
+        Self {
+            keyword: config.keyword_weight.max(0.0),
+            semantic: config.semantic_weight.max(0.0),
+            k: config.rrf_k.max(0.0),
+        }
+    }
+
+    fn weight(&self, engine: SearchEngine) -> f32 {
+        match engine {
+            SearchEngine::Keyword => self.keyword,
+            SearchEngine::Semantic => self.semantic,
+        }
+    }
+}
+
+/// Fuse ranked result lists into one, best first. A hit keeps the
+/// snippet, line and metadata of the first list that has it.
+pub fn fuse(ranked: Vec<(SearchEngine, Vec<SearchHit>)>, weights: HybridWeights) -> Vec<SearchHit> {
+    let mut fused: Vec<SearchHit> = Vec::new();
+    let mut by_path: HashMap<String, usize> = HashMap::new();
+    // The best fused score possible is first place in every engine that
+    // ran; fused scores are divided by it, so they fall in `0..=1`.
+    let ceiling: f32 = ranked
+        .iter()
+        .map(|(engine, _)| weights.weight(*engine) / (weights.k + 1.0))
+        .sum();
+
+    for (engine, hits) in ranked {
+        let weight = weights.weight(engine);
+        let (min, max) = hits.iter().fold((f32::MAX, f32::MIN), |(lo, hi), h| {
+            (lo.min(h.score), hi.max(h.score))
+        });
+        for (i, hit) in hits.into_iter().enumerate() {
+            let rank = i + 1;
+            let normalized = if max > min {
+                (hit.score - min) / (max - min)
+            } else {
+                1.0
+            };
+            let explanation = EngineMatch {
+                engine,
+                rank,
+                score: hit.score,
+                normalized,
+                contribution: weight / (weights.k + rank as f32),
+            };
+            let slot = match by_path.get(&hit.path) {
+                Some(&slot) => slot,
+                None => {
+                    by_path.insert(hit.path.clone(), fused.len());
+                    fused.push(SearchHit {
+                        engines: Vec::new(),
+                        ..hit
+                    });
+                    fused.len() - 1
+                }
+            };
+            fused[slot].engines.push(explanation);
+        }
+    }
+
+    for hit in &mut fused {
+        let total: f32 = hit.engines.iter().map(|e| e.contribution).sum();
+        hit.score = if ceiling > 0.0 { total / ceiling } else { 0.0 };
+    }
+    // Ties (e.g. equal ranks in opposite engines) go to the stronger
+    // normalized score.
+    let best_normalized =
+        |h: &SearchHit| h.engines.iter().map(|e| e.normalized).fold(0.0, f32::max);
+    fused.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(best_normalized(b).total_cmp(&best_normalized(a)))
+    });
+    fused
+}
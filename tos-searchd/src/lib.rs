@@ -1,101 +1,351 @@
 //! TOS Search Engine Library (`tos-search`)
 //!
 //! Provides the core hybrid discovery logic (Tantivy + Candle + HNSW).
+//!
+//! The Tantivy index lives on disk under `{data_dir}/search/` and stores each
+//! document's content hash and embedding next to its text, so a restart only
+//! re-reads files and re-embeds the ones whose content changed (§4.1).
+//! Without an [`EmbeddingBackend`] the state runs keyword-only.
+
+pub mod embedding;
+mod filters;
+pub mod hybrid;
+pub mod scope;
+
+pub use embedding::{select_backend, BertEmbedder, EmbeddingBackend, HashingEmbedder};
+pub use hybrid::HybridWeights;
+pub use scope::{IgnoreRules, Scope};
+pub use tos_common::services::search::{
+    EngineMatch, HitSnippet, SearchEngine, SearchFilters, SearchHit, SearchHitType, SearchQuery,
+    SearchRecord,
+};
 
-use candle_core::{Device, Tensor};
-use candle_nn::VarBuilder;
-use candle_transformers::models::bert::{BertModel, Config, DTYPE};
-use hf_hub::{api::sync::Api, Repo, RepoType};
 use hnsw_rs::prelude::*;
-use std::path::Path;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use tokio::sync::Semaphore;
 
 // [Tantivy and HNSW integration]
 
 use tantivy::collector::TopDocs;
-use tantivy::query::QueryParser;
+use tantivy::query::{AllQuery, BooleanQuery, Occur, Query, QueryParser};
 use tantivy::schema::*;
-use tantivy::{IndexWriter, ReloadPolicy};
-use tokenizers::Tokenizer;
-
-/// The type of a search result hit.
-#[derive(Clone, Copy, serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
-pub enum SearchHitType {
-    /// The hit is a file.
-    File,
-    /// The hit is a directory.
-    Directory,
+use tantivy::snippet::SnippetGenerator;
+use tantivy::tokenizer::TextAnalyzer;
+use tantivy::{IndexWriter, ReloadPolicy, Term};
+
+/// A change to apply to the index, decoded from a filesystem event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexEvent {
+    /// A file or directory was created or written. If it no longer exists
+    /// by the time the event is applied, it is removed instead.
+    Upsert(PathBuf),
+    /// A file or directory is gone.
+    Remove(PathBuf),
+    /// `from` was renamed to `to`.
+    Rename { from: PathBuf, to: PathBuf },
 }
 
-/// A single search result from either exact or semantic search.
-#[derive(Clone, serde::Serialize, serde::Deserialize, Debug)]
-pub struct SearchHit {
-    /// The absolute path to the hit.
-    pub path: String,
-    /// The relevance score (higher is better).
-    pub score: f32,
-    /// Whether the hit is a file or directory.
-    pub hit_type: SearchHitType,
+impl IndexEvent {
+    /// Translate a `notify` event. Access and metadata-only events yield
+    /// nothing; renames the backend could not pair become upserts of each
+    /// path, which resolve to whatever is on disk.
+    pub fn from_notify(event: &notify::Event) -> Vec<IndexEvent> {
+        use notify::event::{ModifyKind, RenameMode};
+        use notify::EventKind;
+
+        let each = |f: fn(PathBuf) -> IndexEvent| -> Vec<IndexEvent> {
+            event.paths.iter().cloned().map(f).collect()
+        };
+        match event.kind {
+            EventKind::Create(_) => each(IndexEvent::Upsert),
+            EventKind::Remove(_) => each(IndexEvent::Remove),
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                vec![IndexEvent::Rename {
+                    from: event.paths[0].clone(),
+                    to: event.paths[1].clone(),
+                }]
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => each(IndexEvent::Remove),
+            EventKind::Modify(ModifyKind::Metadata(_)) => Vec::new(),
+            EventKind::Modify(_) | EventKind::Any => each(IndexEvent::Upsert),
+            EventKind::Access(_) | EventKind::Other => Vec::new(),
+        }
+    }
+
+    /// Restrict the event to what `scope` indexes. Renaming an ignored
+    /// file into place (an editor's atomic save) is an upsert of the
+    /// target; renaming a file out of view is a removal. A change to an
+    /// ignore file re-syncs its directory under the new rules.
+    pub fn scoped(self, scope: &Scope) -> Option<IndexEvent> {
+        let ignore_file = |path: &Path| {
+            path.file_name()
+                .is_some_and(|name| scope::IGNORE_FILES.iter().any(|f| name == *f))
+        };
+        let touched: Vec<&Path> = match &self {
+            IndexEvent::Upsert(path) | IndexEvent::Remove(path) => vec![path],
+            IndexEvent::Rename { from, to } => vec![from, to],
+        };
+        if let Some(dir) = touched
+            .into_iter()
+            .find(|p| ignore_file(p))
+            .and_then(Path::parent)
+        {
+            if scope.root_of(dir).is_some() {
+                scope.invalidate(dir);
+                return Some(IndexEvent::Upsert(dir.to_path_buf()));
+            }
+        }
+
+        match self {
+            IndexEvent::Rename { from, to } => match (scope.tracks(&from), scope.tracks(&to)) {
+                (true, true) => Some(IndexEvent::Rename { from, to }),
+                (false, true) => Some(IndexEvent::Upsert(to)),
+                (true, false) => Some(IndexEvent::Remove(from)),
+                (false, false) => None,
+            },
+            IndexEvent::Upsert(path) | IndexEvent::Remove(path) if !scope.tracks(&path) => None,
+            other => Some(other),
+        }
+    }
 }
 
-/// Handles text embedding using a BERT model.
-pub struct Embedder {
-    model: BertModel,
-    tokenizer: Tokenizer,
-    device: Device,
+/// What a sync, rebuild or event did to the index.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct SyncReport {
+    /// Files (re)indexed because they were new or their content changed.
+    pub indexed: usize,
+    /// Files skipped because their content hash was already indexed.
+    pub unchanged: usize,
+    /// Documents dropped because their file is gone or no longer indexed.
+    pub removed: usize,
+    /// Files left out for being too large or binary.
+    pub skipped: usize,
 }
 
-impl Embedder {
-    /// Create a new Embedder by downloading/loading the BERT model.
-    pub fn new() -> anyhow::Result<Self> {
-        let device = Device::Cpu;
-        let api = Api::new()?;
-        let repo = api.repo(Repo::new(
-            "sentence-transformers/all-MiniLM-L6-v2".to_string(),
-            RepoType::Model,
-        ));
+impl std::ops::AddAssign for SyncReport {
+    fn add_assign(&mut self, other: Self) {
+        self.indexed += other.indexed;
+        self.unchanged += other.unchanged;
+        self.removed += other.removed;
+        self.skipped += other.skipped;
+    }
+}
 
-        let config_path = repo.get("config.json")?;
-        let weights_path = repo.get("model.safetensors")?;
-        let tokenizer_path = repo.get("tokenizer.json")?;
+/// The Tantivy fields of an indexed document.
+#[derive(Clone, Copy)]
+struct Fields {
+    /// Tokenized path, for matching on file and directory names.
+    path: Field,
+    /// The raw path: the document's identity, used to replace or delete it.
+    key: Field,
+    /// SHA-256 of the file content when it was indexed.
+    hash: Field,
+    content: Field,
+    /// The embedding as little-endian `f32`s, so restarts need not re-embed.
+    embedding: Field,
+    /// [`EmbeddingBackend::id`] of the backend that produced `embedding`.
+    model: Field,
+    /// Lowercased extension, for extension filters and `file_type`.
+    ext: Field,
+    /// Modification time in Unix seconds when the file was indexed, or
+    /// when a record was made.
+    mtime: Field,
+    /// [`SearchHitType::as_str`]: `file`, or the kind of a Brain record.
+    kind: Field,
+    /// Sector and hub ids of a record.
+    sector: Field,
+    hub: Field,
+    /// A record's title and text; file snippets are cut from the disk.
+    title: Field,
+    body: Field,
+}
 
-        let config: Config = serde_json::from_str(&std::fs::read_to_string(config_path)?)?;
-        let tokenizer = Tokenizer::from_file(tokenizer_path).map_err(|e| anyhow::anyhow!(e))?;
+impl Fields {
+    fn schema() -> (Schema, Fields) {
+        let mut schema_builder = Schema::builder();
+        let fields = Fields {
+            path: schema_builder.add_text_field("path", TEXT | STORED),
+            key: schema_builder.add_text_field("key", STRING),
+            hash: schema_builder.add_text_field("hash", STORED),
+            content: schema_builder.add_text_field("content", TEXT),
+            embedding: schema_builder.add_bytes_field("embedding", STORED),
+            model: schema_builder.add_text_field("model", STORED),
+            ext: schema_builder.add_text_field("ext", STRING | STORED),
+            mtime: schema_builder.add_u64_field("mtime", INDEXED | STORED | FAST),
+            kind: schema_builder.add_text_field("kind", STRING | STORED),
+            sector: schema_builder.add_text_field("sector", STRING | STORED),
+            hub: schema_builder.add_text_field("hub", STRING | STORED),
+            title: schema_builder.add_text_field("title", STORED),
+            body: schema_builder.add_text_field("body", STORED),
+        };
+        (schema_builder.build(), fields)
+    }
+}
 
-        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[weights_path], DTYPE, &device)? };
-        let model = BertModel::load(vb, &config)?;
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
 
-        Ok(Self {
-            model,
-            tokenizer,
-            device,
-        })
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// One indexed document.
+struct Indexed {
+    hash: String,
+    /// The backend its stored embedding came from, if any.
+    model: Option<String>,
+    kind: SearchHitType,
+    ext: Option<String>,
+    mtime: Option<u64>,
+    title: Option<String>,
+    sector: Option<uuid::Uuid>,
+    hub: Option<uuid::Uuid>,
+    /// Its point in the HNSW graph, if it was embedded by the active backend.
+    vector: Option<usize>,
+}
+
+impl Indexed {
+    /// A hit on this document, without line or snippet.
+    fn hit(&self, path: &str, score: f32) -> SearchHit {
+        SearchHit {
+            path: path.to_string(),
+            hit_type: self.kind,
+            score,
+            line: None,
+            snippet: None,
+            file_type: self.ext.clone(),
+            mtime: self.mtime,
+            engines: Vec::new(),
+            title: self.title.clone(),
+            sector_id: self.sector,
+            hub_id: self.hub,
+        }
+    }
+
+    /// Read back a stored document's metadata.
+    fn from_doc(doc: &tantivy::TantivyDocument, fields: Fields) -> Option<(String, Self)> {
+        let text = |field| doc.get_first(field).and_then(|v| v.as_str());
+        let id = |field| text(field).and_then(|s| uuid::Uuid::parse_str(s).ok());
+        let path = text(fields.path)?.to_string();
+        let indexed = Indexed {
+            hash: text(fields.hash)?.to_string(),
+            model: text(fields.model).map(str::to_string),
+            kind: text(fields.kind)
+                .and_then(SearchHitType::parse)
+                .unwrap_or(SearchHitType::File),
+            ext: text(fields.ext).map(str::to_string),
+            mtime: doc.get_first(fields.mtime).and_then(|v| v.as_u64()),
+            title: text(fields.title).map(str::to_string),
+            sector: id(fields.sector),
+            hub: id(fields.hub),
+            vector: None,
+        };
+        Some((path, indexed))
     }
+}
+
+/// The lowercased extension of `path`.
+fn extension_of(path: &Path) -> Option<String> {
+    path.extension().map(|e| e.to_string_lossy().to_lowercase())
+}
 
-    /// Generate an embedding vector for the given text.
-    pub fn embed(&self, text: &str) -> anyhow::Result<Vec<f32>> {
-        let tokens = self
-            .tokenizer
-            .encode(text, true)
-            .map_err(|e| anyhow::anyhow!(e))?;
-        let token_ids = Tensor::new(tokens.get_ids(), &self.device)?.unsqueeze(0)?;
-        let token_type_ids = token_ids.zeros_like()?;
+fn mtime_of(path: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(
+        modified
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()?
+            .as_secs(),
+    )
+}
 
-        let embeddings = self.model.forward(&token_ids, &token_type_ids, None)?;
+/// The documents in the index, keyed by path, and the HNSW graph over their
+/// embeddings. `hnsw_rs` cannot delete points, so a replaced or removed
+/// document only unlinks its point from `ids`; the graph is rebuilt from the
+/// stored embeddings once stale points outnumber live documents.
+struct Catalog {
+    docs: HashMap<String, Indexed>,
+    hnsw: Hnsw<'static, f32, DistL2>,
+    /// HNSW point id -> path, `None` once superseded.
+    ids: Vec<Option<String>>,
+    stale: usize,
+}
 
-        // Mean pooling
-        let (_n_batch, n_tokens, _n_dims) = embeddings.dims3()?;
-        let pooled = (embeddings.sum(1)? / (n_tokens as f64))?;
-        let pooled = pooled.get(0)?; // Squeeze batch
+impl Catalog {
+    fn new() -> Self {
+        Self {
+            docs: HashMap::new(),
+            hnsw: Hnsw::new(24, 100_000, 16, 200, DistL2),
+            ids: Vec::new(),
+            stale: 0,
+        }
+    }
 
-        // Normalization
-        let norm = pooled.sqr()?.sum_all()?.sqrt()?;
-        let norm_val = norm.to_vec0::<f32>()?;
-        let normalized = pooled.broadcast_div(&Tensor::new(norm_val, &self.device)?)?;
+    /// Rebuild the catalog from the committed documents. Only embeddings
+    /// made by `backend` enter the graph.
+    fn load(
+        searcher: &tantivy::Searcher,
+        fields: Fields,
+        backend: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let mut catalog = Self::new();
+        for segment in searcher.segment_readers() {
+            let store = segment.get_store_reader(64)?;
+            for doc in store.iter::<tantivy::TantivyDocument>(segment.alive_bitset()) {
+                let doc = doc?;
+                let (path, indexed) = match Indexed::from_doc(&doc, fields) {
+                    Some(found) => found,
+                    None => continue,
+                };
+                let vector = doc
+                    .get_first(fields.embedding)
+                    .and_then(|v| v.as_bytes())
+                    .filter(|_| indexed.model.is_some() && indexed.model.as_deref() == backend)
+                    .map(decode_vector);
+                catalog.insert(path, indexed, vector.as_deref());
+            }
+        }
+        Ok(catalog)
+    }
 
-        Ok(normalized.to_vec1()?)
+    /// Add or replace the document for `path`; `vector` becomes its point
+    /// in the graph.
+    fn insert(&mut self, path: String, mut doc: Indexed, vector: Option<&[f32]>) {
+        self.forget(&path);
+        doc.vector = vector.map(|v| {
+            let id = self.ids.len();
+            self.hnsw.insert((v, id));
+            self.ids.push(Some(path.clone()));
+            id
+        });
+        self.docs.insert(path, doc);
     }
+
+    fn forget(&mut self, path: &str) {
+        if let Some(Indexed {
+            vector: Some(id), ..
+        }) = self.docs.remove(path)
+        {
+            self.ids[id] = None;
+            self.stale += 1;
+        }
+    }
+}
+
+/// What indexing one file did.
+#[derive(Debug, PartialEq, Eq)]
+enum Indexing {
+    Indexed,
+    Unchanged,
+    /// Too large or binary.
+    Skipped,
 }
 
 /// The system-wide search state, managing Tantivy and HNSW indices.
@@ -103,181 +353,692 @@ pub struct SearchState {
     tantivy_index: tantivy::Index,
     tantivy_writer: Arc<Mutex<IndexWriter>>,
     tantivy_reader: tantivy::IndexReader,
-    hnsw_index: Arc<Mutex<Hnsw<'static, f32, DistL2>>>,
-    paths: Arc<Mutex<Vec<String>>>,
-    embedder: Arc<Mutex<Embedder>>,
+    catalog: Arc<Mutex<Catalog>>,
+    embedder: Option<Arc<Mutex<Box<dyn EmbeddingBackend>>>>,
+    /// [`EmbeddingBackend::id`] of `embedder`, readable without its lock.
+    backend: Option<String>,
     semaphore: Arc<Semaphore>,
-    path_field: Field,
-    content_field: Field,
+    fields: Fields,
+    weights: HybridWeights,
+    scope: Scope,
+}
+
+/// What `status` reports about the daemon.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SearchStatus {
+    /// The active embedding backend; `None` in keyword-only mode.
+    pub backend: Option<String>,
+    /// Number of indexed documents.
+    pub documents: usize,
 }
 
 impl SearchState {
-    /// Initialize a new SearchState with in-memory indices.
+    /// Default page size of [`Self::search_with`].
+    pub const SEARCH_LIMIT: usize = 20;
+    /// Default page size of [`Self::semantic_search_with`].
+    pub const SEMANTIC_LIMIT: usize = 10;
+    /// Hits each engine contributes to a hybrid page, beyond the page itself.
+    const HYBRID_POOL: usize = 50;
+    const SNIPPET_CHARS: usize = 160;
+
+    /// `{data_dir}/search` for the given config.
+    pub fn default_dir(config: &tos_common::TosConfig) -> PathBuf {
+        config.platform.resolved_data_dir().join("search")
+    }
+
+    /// Initialize a new SearchState with an in-memory index and the BERT
+    /// model from the Hugging Face hub.
     pub fn new() -> anyhow::Result<Self> {
-        let mut schema_builder = Schema::builder();
-        let path_field = schema_builder.add_text_field("path", TEXT | STORED);
-        let content_field = schema_builder.add_text_field("content", TEXT);
-        let schema = schema_builder.build();
+        Self::in_memory(Some(Box::new(BertEmbedder::from_hub()?)))
+    }
+
+    /// Initialize a SearchState with an in-memory index.
+    pub fn in_memory(backend: Option<Box<dyn EmbeddingBackend>>) -> anyhow::Result<Self> {
+        let (schema, fields) = Fields::schema();
+        Self::with_index(tantivy::Index::create_in_ram(schema), fields, backend)
+    }
+
+    /// Open the persistent index in `dir`, creating it on first start.
+    /// Without a backend, semantic search returns nothing.
+    pub fn open(dir: &Path, backend: Option<Box<dyn EmbeddingBackend>>) -> anyhow::Result<Self> {
+        let (index, fields) = Self::open_index(dir)?;
+        Self::with_index(index, fields, backend)
+    }
+
+    /// Open the index in `dir` without an embedding backend. Stored
+    /// embeddings of unchanged files are kept for a later start.
+    pub fn open_keyword_only(dir: &Path) -> anyhow::Result<Self> {
+        Self::open(dir, None)
+    }
+
+    fn open_index(dir: &Path) -> anyhow::Result<(tantivy::Index, Fields)> {
+        let (schema, fields) = Fields::schema();
+        std::fs::create_dir_all(dir)?;
+        if dir.join("meta.json").exists() {
+            match tantivy::Index::open_in_dir(dir) {
+                Ok(index) if index.schema() == schema => return Ok((index, fields)),
+                Ok(_) => {
+                    tracing::warn!("TOS-SEARCHD: Index schema in {:?} changed, rebuilding", dir)
+                }
+                Err(e) => tracing::warn!(
+                    "TOS-SEARCHD: Index in {:?} unreadable ({}), rebuilding",
+                    dir,
+                    e
+                ),
+            }
+            std::fs::remove_dir_all(dir)?;
+            std::fs::create_dir_all(dir)?;
+        }
+        Ok((tantivy::Index::create_in_dir(dir, schema)?, fields))
+    }
 
-        let tantivy_index = tantivy::Index::create_in_ram(schema);
+    fn with_index(
+        tantivy_index: tantivy::Index,
+        fields: Fields,
+        embedder: Option<Box<dyn EmbeddingBackend>>,
+    ) -> anyhow::Result<Self> {
         let tantivy_writer = Arc::new(Mutex::new(tantivy_index.writer(50_000_000)?));
-        let tantivy_reader = tantivy_index
+        let tantivy_reader: tantivy::IndexReader = tantivy_index
             .reader_builder()
             .reload_policy(ReloadPolicy::Manual)
             .try_into()?;
-
-        let hnsw_index = Arc::new(Mutex::new(Hnsw::new(24, 100_000, 16, 200, DistL2)));
-        let paths = Arc::new(Mutex::new(Vec::new()));
-        let embedder = Arc::new(Mutex::new(Embedder::new()?));
-        let semaphore = Arc::new(Semaphore::new(2)); // Limit to 2 concurrent BERT embeddings
+        let backend = embedder.as_ref().map(|e| e.id().to_string());
+        let catalog = Catalog::load(&tantivy_reader.searcher(), fields, backend.as_deref())?;
 
         Ok(Self {
             tantivy_index,
             tantivy_writer,
             tantivy_reader,
-            hnsw_index,
-            paths,
-            embedder,
-            semaphore,
-            path_field,
-            content_field,
+            catalog: Arc::new(Mutex::new(catalog)),
+            embedder: embedder.map(|e| Arc::new(Mutex::new(e))),
+            backend,
+            semaphore: Arc::new(Semaphore::new(2)), // Limit to 2 concurrent embeddings
+            fields,
+            weights: HybridWeights::default(),
+            scope: Scope::default(),
         })
     }
 
+    fn lock_writer(&self) -> anyhow::Result<std::sync::MutexGuard<'_, IndexWriter>> {
+        self.tantivy_writer
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Tantivy writer mutex poisoned"))
+    }
+
+    fn lock_catalog(&self) -> anyhow::Result<std::sync::MutexGuard<'_, Catalog>> {
+        self.catalog
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Catalog mutex poisoned"))
+    }
+
+    /// Number of indexed documents, including changes not yet committed.
+    pub fn len(&self) -> usize {
+        self.catalog.lock().map(|c| c.docs.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The active embedding backend; `None` in keyword-only mode.
+    pub fn backend(&self) -> Option<&str> {
+        self.backend.as_deref()
+    }
+
+    /// Set the engine weights used by [`Self::hybrid_search_with`].
+    pub fn set_hybrid_weights(&mut self, weights: HybridWeights) {
+        self.weights = weights;
+    }
+
+    /// Skip files larger than `bytes`. Call before adding roots.
+    pub fn set_max_file_size(&mut self, bytes: u64) {
+        self.scope = Scope::new(bytes);
+    }
+
+    /// The roots being indexed and the rules applied under them.
+    pub fn scope(&self) -> &Scope {
+        &self.scope
+    }
+
+    pub fn status(&self) -> SearchStatus {
+        SearchStatus {
+            backend: self.backend.clone(),
+            documents: self.len(),
+        }
+    }
+
     /// Index a single file for both exact and semantic search.
-    pub async fn index_file(&self, path: &Path) -> anyhow::Result<()> {
-        if !path.exists() || !path.is_file() {
-            return Ok(());
+    ///
+    /// Documents are keyed by path, so an earlier version of the file is
+    /// replaced; a file whose content hash is already indexed (and embedded
+    /// by the active backend) is left alone. Files over the size limit and
+    /// binary files are not indexed, and drop any earlier document. Returns
+    /// whether the index gained or replaced a document.
+    pub async fn index_file(&self, path: &Path) -> anyhow::Result<bool> {
+        Ok(self.index(path).await? == Indexing::Indexed)
+    }
+
+    async fn index(&self, path: &Path) -> anyhow::Result<Indexing> {
+        if !path.is_file() {
+            return Ok(Indexing::Unchanged);
         }
         let path_str = path.to_string_lossy().to_string();
 
+        if std::fs::metadata(path)?.len() > self.scope.max_file_size() {
+            self.remove_path(path)?;
+            return Ok(Indexing::Skipped);
+        }
+        let bytes = std::fs::read(path)?;
+        if scope::is_binary(&bytes) {
+            self.remove_path(path)?;
+            return Ok(Indexing::Skipped);
+        }
+        let hash = format!("{:x}", Sha256::digest(&bytes));
+        if self.lock_catalog()?.docs.get(&path_str).is_some_and(|doc| {
+            doc.hash == hash && (self.backend.is_none() || doc.model == self.backend)
+        }) {
+            return Ok(Indexing::Unchanged);
+        }
+
         let file_name = path
             .file_name()
             .unwrap_or_default()
             .to_string_lossy()
             .to_string();
-
-        // Read content (limit to 10KB for safety in this version)
-        let file_content = std::fs::read_to_string(path).unwrap_or_default();
+        let file_content = String::from_utf8(bytes).unwrap_or_default();
         let index_content = format!("{} {} {}", file_name, path_str, file_content);
+        let indexed = Indexed {
+            hash,
+            model: None,
+            kind: SearchHitType::File,
+            ext: extension_of(path),
+            mtime: mtime_of(path),
+            title: None,
+            sector: None,
+            hub: None,
+            vector: None,
+        };
+        self.write_doc(path_str, indexed, index_content, None)
+            .await?;
+        Ok(Indexing::Indexed)
+    }
 
-        // 1. Tantivy index
-        {
-            let writer = self
-                .tantivy_writer
-                .lock()
-                .map_err(|_| anyhow::anyhow!("Tantivy writer mutex poisoned"))?;
+    /// Embed `content` and replace the document at `key` with it. `body`
+    /// is stored for snippets of records.
+    async fn write_doc(
+        &self,
+        key: String,
+        mut indexed: Indexed,
+        content: String,
+        body: Option<&str>,
+    ) -> anyhow::Result<()> {
+        // 1. Embedding (guarded by semaphore to prevent CPU pinning)
+        let vector = match &self.embedder {
+            Some(embedder) => {
+                let _permit = self.semaphore.acquire().await?;
+                let embedder = embedder
+                    .lock()
+                    .map_err(|_| anyhow::anyhow!("Embedder mutex poisoned"))?;
+                embedder.embed(&content).ok()
+            }
+            None => None,
+        };
 
-            let mut doc = tantivy::TantivyDocument::default();
-            doc.add_text(self.path_field, path_str.clone());
-            doc.add_text(self.content_field, index_content.clone());
-            writer.add_document(doc)?;
+        // 2. Replace the Tantivy document and the HNSW point together
+        let writer = self.lock_writer()?;
+        let mut catalog = self.lock_catalog()?;
+
+        writer.delete_term(Term::from_field_text(self.fields.key, &key));
+        let mut doc = tantivy::TantivyDocument::default();
+        doc.add_text(self.fields.path, &key);
+        doc.add_text(self.fields.key, &key);
+        doc.add_text(self.fields.hash, &indexed.hash);
+        doc.add_text(self.fields.content, content);
+        doc.add_text(self.fields.kind, indexed.kind.as_str());
+        indexed.model = vector.as_ref().and(self.backend.clone());
+        if let (Some(vector), Some(model)) = (&vector, &indexed.model) {
+            doc.add_bytes(self.fields.embedding, encode_vector(vector));
+            doc.add_text(self.fields.model, model);
+        }
+        if let Some(ext) = &indexed.ext {
+            doc.add_text(self.fields.ext, ext);
+        }
+        if let Some(mtime) = indexed.mtime {
+            doc.add_u64(self.fields.mtime, mtime);
         }
+        if let Some(title) = &indexed.title {
+            doc.add_text(self.fields.title, title);
+        }
+        if let Some(sector) = indexed.sector {
+            doc.add_text(self.fields.sector, sector.to_string());
+        }
+        if let Some(hub) = indexed.hub {
+            doc.add_text(self.fields.hub, hub.to_string());
+        }
+        if let Some(body) = body {
+            doc.add_text(self.fields.body, body);
+        }
+        writer.add_document(doc)?;
+        catalog.insert(key, indexed, vector.as_deref());
+        Ok(())
+    }
 
-        // 2. HNSW embedding (guarded by semaphore to prevent CPU pinning)
-        let _permit = self.semaphore.acquire().await?;
-        let embedder = self
-            .embedder
-            .lock()
-            .map_err(|_| anyhow::anyhow!("Embedder mutex poisoned"))?;
-        if let Ok(vector) = embedder.embed(&index_content) {
-            let hnsw = self
-                .hnsw_index
-                .lock()
-                .map_err(|_| anyhow::anyhow!("HNSW mutex poisoned"))?;
-            let mut paths = self
-                .paths
-                .lock()
-                .map_err(|_| anyhow::anyhow!("Paths mutex poisoned"))?;
+    /// Index a record pushed by the Brain (§4.1), replacing an earlier
+    /// record with the same key. Returns false if it was already indexed
+    /// unchanged.
+    pub async fn index_record(&self, record: &SearchRecord) -> anyhow::Result<bool> {
+        if !record.kind.is_record() {
+            anyhow::bail!("Not a record kind: {}", record.kind.as_str());
+        }
+        let key = record.key();
+        let hash = format!(
+            "{:x}",
+            Sha256::digest(format!("{}\n{}", record.title, record.text))
+        );
+        if self.lock_catalog()?.docs.get(&key).is_some_and(|doc| {
+            doc.hash == hash && (self.backend.is_none() || doc.model == self.backend)
+        }) {
+            return Ok(false);
+        }
+        let indexed = Indexed {
+            hash,
+            model: None,
+            kind: record.kind,
+            ext: None,
+            mtime: Some(record.timestamp),
+            title: Some(record.title.clone()),
+            sector: record.sector_id,
+            hub: record.hub_id,
+            vector: None,
+        };
+        let content = format!("{} {}", record.title, record.text);
+        self.write_doc(key, indexed, content, Some(&record.text))
+            .await?;
+        Ok(true)
+    }
+
+    /// Drop every record tagged with `sector`. Returns how many were
+    /// removed.
+    pub fn purge_sector(&self, sector: uuid::Uuid) -> anyhow::Result<usize> {
+        let writer = self.lock_writer()?;
+        let mut catalog = self.lock_catalog()?;
 
-            let id = paths.len();
-            paths.push(path_str);
-            hnsw.insert((&vector, id));
+        let gone: Vec<String> = catalog
+            .docs
+            .iter()
+            .filter(|(_, doc)| doc.sector == Some(sector))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &gone {
+            writer.delete_term(Term::from_field_text(self.fields.key, key));
+            catalog.forget(key);
         }
+        Ok(gone.len())
+    }
 
-        Ok(())
+    /// Drop the document for `path`, or every document below it if it was a
+    /// directory. Returns how many were removed.
+    pub fn remove_path(&self, path: &Path) -> anyhow::Result<usize> {
+        let writer = self.lock_writer()?;
+        let mut catalog = self.lock_catalog()?;
+
+        let gone: Vec<String> = catalog
+            .docs
+            .keys()
+            .filter(|key| Path::new(key).starts_with(path))
+            .cloned()
+            .collect();
+        for key in &gone {
+            writer.delete_term(Term::from_field_text(self.fields.key, key));
+            catalog.forget(key);
+        }
+        Ok(gone.len())
+    }
+
+    /// Apply one filesystem change.
+    pub async fn apply(&self, event: IndexEvent) -> anyhow::Result<SyncReport> {
+        let mut report = SyncReport::default();
+        match event {
+            IndexEvent::Upsert(path) => report += self.upsert(&path).await?,
+            IndexEvent::Remove(path) => report.removed += self.remove_path(&path)?,
+            IndexEvent::Rename { from, to } => {
+                report.removed += self.remove_path(&from)?;
+                report += self.upsert(&to).await?;
+            }
+        }
+        Ok(report)
+    }
+
+    async fn upsert(&self, path: &Path) -> anyhow::Result<SyncReport> {
+        let mut report = SyncReport::default();
+        if path.is_dir() {
+            return self.sync_dir(path).await;
+        } else if !path.exists() {
+            report.removed = self.remove_path(path)?;
+        } else {
+            match self.index(path).await? {
+                Indexing::Indexed => report.indexed = 1,
+                Indexing::Unchanged => report.unchanged = 1,
+                Indexing::Skipped => report.skipped = 1,
+            }
+        }
+        Ok(report)
+    }
+
+    /// Add `root` to the indexed roots, if it is not one already, and bring
+    /// its documents in line with the disk.
+    pub async fn sync_root(&self, root: &Path) -> anyhow::Result<SyncReport> {
+        self.scope.add_root(root);
+        self.sync_dir(root).await
+    }
+
+    /// Index new and changed files under `dir` and drop documents whose
+    /// file is gone or no longer in scope. Unchanged files are hashed but
+    /// not re-embedded.
+    async fn sync_dir(&self, dir: &Path) -> anyhow::Result<SyncReport> {
+        let mut report = SyncReport::default();
+        for path in self.scope.walk(dir) {
+            match self.index(&path).await {
+                Ok(Indexing::Indexed) => report.indexed += 1,
+                Ok(Indexing::Unchanged) => report.unchanged += 1,
+                Ok(Indexing::Skipped) => report.skipped += 1,
+                Err(e) => tracing::debug!("TOS-SEARCHD: Skipping {:?}: {}", path, e),
+            }
+        }
+        report.removed += self.prune(dir)?;
+        Ok(report)
+    }
+
+    /// Drop the documents under `dir` that are gone or out of scope.
+    fn prune(&self, dir: &Path) -> anyhow::Result<usize> {
+        let stale: Vec<String> = self
+            .lock_catalog()?
+            .docs
+            .keys()
+            .filter(|key| {
+                let path = Path::new(key);
+                path.starts_with(dir) && (!path.is_file() || !self.scope.tracks(path))
+            })
+            .cloned()
+            .collect();
+        let mut removed = 0;
+        for key in stale {
+            removed += self.remove_path(Path::new(&key))?;
+        }
+        Ok(removed)
+    }
+
+    /// Stop indexing `root` and drop its documents, except those another
+    /// root still covers. Returns how many were dropped, or `None` if it
+    /// was not a root.
+    pub fn remove_root(&self, root: &Path) -> anyhow::Result<Option<usize>> {
+        if !self.scope.remove_root(root) {
+            return Ok(None);
+        }
+        self.prune(root).map(Some)
+    }
+
+    /// The indexed roots.
+    pub fn roots(&self) -> Vec<PathBuf> {
+        self.scope.roots()
+    }
+
+    /// Drop every file document and index all roots from scratch,
+    /// re-embedding all of it. Records pushed by the Brain cannot be read
+    /// again and are kept. The result is committed before returning.
+    pub async fn rebuild(&self) -> anyhow::Result<SyncReport> {
+        {
+            let mut writer = self.lock_writer()?;
+            let mut catalog = self.lock_catalog()?;
+            writer.delete_term(Term::from_field_text(
+                self.fields.kind,
+                SearchHitType::File.as_str(),
+            ));
+            writer.commit()?;
+            self.tantivy_reader.reload()?;
+            *catalog = Catalog::load(
+                &self.tantivy_reader.searcher(),
+                self.fields,
+                self.backend.as_deref(),
+            )?;
+        }
+        let mut report = SyncReport::default();
+        for root in self.scope.roots() {
+            report += self.sync_dir(&root).await?;
+        }
+        self.commit()?;
+        Ok(report)
     }
 
     /// Commit pending changes to the Tantivy index and reload the reader.
     pub fn commit(&self) -> anyhow::Result<()> {
-        let mut writer = self
-            .tantivy_writer
-            .lock()
-            .map_err(|_| anyhow::anyhow!("Tantivy writer mutex poisoned"))?;
+        let mut writer = self.lock_writer()?;
         writer.commit()?;
         self.tantivy_reader.reload()?;
+
+        // Everything is committed, so the graph can be rebuilt from the
+        // stored embeddings to shed superseded points.
+        let mut catalog = self.lock_catalog()?;
+        if catalog.stale > catalog.docs.len() {
+            *catalog = Catalog::load(
+                &self.tantivy_reader.searcher(),
+                self.fields,
+                self.backend.as_deref(),
+            )?;
+        }
         Ok(())
     }
 
     /// Perform an exact keyword search using Tantivy.
     pub fn search(&self, pattern: &str) -> Vec<SearchHit> {
+        self.search_with(&SearchQuery::text(pattern))
+            .unwrap_or_default()
+    }
+
+    /// Keyword search with filters and paging (§4.1). Hits carry the line
+    /// of the first match and a highlighted snippet, read from the file as
+    /// it is now. Empty text lists the documents that pass the filters.
+    pub fn search_with(&self, query: &SearchQuery) -> anyhow::Result<Vec<SearchHit>> {
+        let filter = filters::Filter::new(&query.filters)?;
+        let limit = query.limit.unwrap_or(Self::SEARCH_LIMIT);
+        if limit == 0 || (query.text.trim().is_empty() && filter.is_empty()) {
+            return Ok(vec![]);
+        }
         let searcher = self.tantivy_reader.searcher();
 
-        let query_parser =
-            QueryParser::for_index(&self.tantivy_index, vec![self.path_field, self.content_field]);
-        let query = query_parser.parse_query(pattern).ok();
-
-        if let Some(q) = query {
-            if let Ok(top_docs) = searcher.search(&q, &TopDocs::with_limit(20)) {
-                return top_docs
-                    .into_iter()
-                    .filter_map(|(score, doc_address)| {
-                        let doc: tantivy::TantivyDocument = searcher.doc(doc_address).ok()?;
-                        let path = doc
-                            .get_first(self.path_field)
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("")
-                            .to_string();
-                        Some(SearchHit {
-                            path,
-                            score,
-                            hit_type: SearchHitType::File,
-                        })
-                    })
-                    .collect();
+        let text_query: Option<Box<dyn Query>> = if query.text.trim().is_empty() {
+            None
+        } else {
+            let query_parser = QueryParser::for_index(
+                &self.tantivy_index,
+                vec![self.fields.path, self.fields.content],
+            );
+            match query_parser.parse_query(&query.text) {
+                Ok(q) => Some(q),
+                Err(_) => return Ok(vec![]),
+            }
+        };
+
+        let mut clauses = filter.clauses(self.fields)?;
+        match &text_query {
+            Some(q) => clauses.push((Occur::Must, q.box_clone())),
+            None => clauses.push((Occur::Must, Box::new(AllQuery))),
+        }
+        let combined = BooleanQuery::new(clauses);
+
+        let mut snippets = match &text_query {
+            Some(q) => Some(SnippetGenerator::create(
+                &searcher,
+                q.as_ref(),
+                self.fields.content,
+            )?),
+            None => None,
+        };
+        if let Some(generator) = snippets.as_mut() {
+            generator.set_max_num_chars(Self::SNIPPET_CHARS);
+        }
+        let mut tokenizer = self.tantivy_index.tokenizer_for_field(self.fields.content)?;
+
+        let top_docs = searcher.search(
+            &combined,
+            &TopDocs::with_limit(limit).and_offset(query.offset),
+        )?;
+        Ok(top_docs
+            .into_iter()
+            .filter_map(|(score, doc_address)| {
+                let doc: tantivy::TantivyDocument = searcher.doc(doc_address).ok()?;
+                let (path, indexed) = Indexed::from_doc(&doc, self.fields)?;
+                let mut hit = indexed.hit(&path, score);
+                if let Some(generator) = &snippets {
+                    let text = match doc.get_first(self.fields.body).and_then(|v| v.as_str()) {
+                        Some(body) => Some(body.to_string()),
+                        None => std::fs::read_to_string(&path).ok(),
+                    };
+                    if let Some(text) = text {
+                        (hit.line, hit.snippet) = Self::locate(generator, &mut tokenizer, &text);
+                    }
+                }
+                Some(hit)
+            })
+            .collect())
+    }
+
+    /// The line and snippet of the best fragment of a file's or record's
+    /// `text`. Hits that only matched the path or title have neither.
+    fn locate(
+        generator: &SnippetGenerator,
+        tokenizer: &mut TextAnalyzer,
+        text: &str,
+    ) -> (Option<usize>, Option<HitSnippet>) {
+        let snippet = generator.snippet(text);
+        let first = match snippet.highlighted().first() {
+            Some(range) => range.start,
+            None => return (None, None),
+        };
+        let fragment = snippet.fragment();
+        // The same fragment text can occur more than once, so take the
+        // offset of the one the generator chose rather than the first.
+        let line = Self::fragment_start(tokenizer, text, fragment)
+            .or_else(|| text.find(fragment))
+            .map(|start| text[..start + first].matches('\n').count() + 1);
+        let highlights = snippet
+            .highlighted()
+            .iter()
+            .map(|r| (r.start, r.end))
+            .collect();
+        (
+            line,
+            Some(HitSnippet {
+                text: fragment.to_string(),
+                highlights,
+            }),
+        )
+    }
+
+    /// Where `fragment`, chosen by [`SnippetGenerator::snippet`], starts in
+    /// `text`. The generator splits the text into windows of at most
+    /// `SNIPPET_CHARS` at token boundaries and keeps the earliest of the
+    /// best-scoring ones; windows with the same text score the same, so it
+    /// is the first window with that text.
+    fn fragment_start(tokenizer: &mut TextAnalyzer, text: &str, fragment: &str) -> Option<usize> {
+        let (mut start, mut stop) = (0, 0);
+        let mut tokens = tokenizer.token_stream(text);
+        while let Some(token) = tokens.next() {
+            if token.offset_to - start > Self::SNIPPET_CHARS {
+                if &text[start..stop] == fragment {
+                    return Some(start);
+                }
+                start = token.offset_from;
             }
+            stop = token.offset_to;
         }
-        vec![]
+        (&text[start..stop] == fragment).then_some(start)
     }
 
-    /// Perform a semantic search using BERT embeddings and HNSW.
+    /// Perform a semantic search using the embedding backend and HNSW.
     pub fn semantic_search(&self, prompt: &str) -> Vec<SearchHit> {
-        let embedder = match self.embedder.lock() {
-            Ok(e) => e,
-            Err(_) => {
-                tracing::error!("Embedder mutex poisoned in semantic_search");
-                return vec![];
+        self.semantic_search_with(&SearchQuery::text(prompt))
+            .unwrap_or_default()
+    }
+
+    /// Semantic search with filters and paging. The graph cannot filter, so
+    /// a filtered query asks it for more neighbours and drops those outside
+    /// the filters; a very selective filter may return a short page.
+    pub fn semantic_search_with(&self, query: &SearchQuery) -> anyhow::Result<Vec<SearchHit>> {
+        let filter = filters::Filter::new(&query.filters)?;
+        let embedder = match &self.embedder {
+            Some(embedder) => embedder,
+            None => return Ok(vec![]),
+        };
+        let query_vector = {
+            let embedder = embedder
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Embedder mutex poisoned"))?;
+            match embedder.embed(&query.text) {
+                Ok(v) => v,
+                Err(_) => return Ok(vec![]),
             }
         };
 
-        if let Ok(query_vector) = embedder.embed(prompt) {
-            let hnsw = match self.hnsw_index.lock() {
-                Ok(h) => h,
-                Err(_) => {
-                    tracing::error!("HNSW mutex poisoned in semantic_search");
-                    return vec![];
-                }
-            };
-            let paths = match self.paths.lock() {
-                Ok(p) => p,
-                Err(_) => {
-                    tracing::error!("Paths mutex poisoned in semantic_search");
-                    return vec![];
+        let catalog = self.lock_catalog()?;
+        if catalog.ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // Ask for enough neighbours to make up for superseded points and,
+        // when filtering, for the ones the filters reject.
+        let limit = query.limit.unwrap_or(Self::SEMANTIC_LIMIT);
+        let wanted = query.offset + limit;
+        let knbn = if filter.is_empty() {
+            wanted
+        } else {
+            wanted * 10
+        } + catalog.stale;
+        let matches = catalog.hnsw.search(&query_vector, knbn, knbn.max(201));
+        Ok(matches
+            .into_iter()
+            .filter_map(|m| {
+                let path = catalog.ids.get(m.d_id)?.as_ref()?;
+                let doc = catalog.docs.get(path)?;
+                if !filter.matches(path, doc) {
+                    return None;
                 }
-            };
-
-            let matches = hnsw.search(&query_vector, 10, 201);
-            return matches
-                .into_iter()
-                .filter_map(|m| {
-                    paths.get(m.d_id).map(|p: &String| SearchHit {
-                        path: p.clone(),
-                        score: 1.0 - m.distance,
-                        hit_type: SearchHitType::File,
-                    })
-                })
-                .collect();
-        }
-        vec![]
+                Some(doc.hit(path, 1.0 - m.distance))
+            })
+            .skip(query.offset)
+            .take(limit)
+            .collect())
+    }
+
+    /// Keyword and semantic search fused by weighted reciprocal rank
+    /// (§4.1). Both engines rank the same filtered pool; each hit lists
+    /// the engines that found it in `engines`. Without an embedding
+    /// backend, or with an engine weighted zero, only the other ranks.
+    pub fn hybrid_search_with(&self, query: &SearchQuery) -> anyhow::Result<Vec<SearchHit>> {
+        let limit = query.limit.unwrap_or(Self::SEARCH_LIMIT);
+        let pool = SearchQuery {
+            offset: 0,
+            limit: Some((query.offset + limit).max(Self::HYBRID_POOL)),
+            ..query.clone()
+        };
+
+        let mut ranked = Vec::new();
+        if self.weights.keyword > 0.0 {
+            ranked.push((SearchEngine::Keyword, self.search_with(&pool)?));
+        }
+        if self.weights.semantic > 0.0 && self.embedder.is_some() && !query.text.trim().is_empty() {
+            ranked.push((SearchEngine::Semantic, self.semantic_search_with(&pool)?));
+        }
+        Ok(hybrid::fuse(ranked, self.weights)
+            .into_iter()
+            .skip(query.offset)
+            .take(limit)
+            .collect())
+    }
+
+    /// Hybrid search for `text` with the default page size.
+    pub fn hybrid_search(&self, text: &str) -> Vec<SearchHit> {
+        self.hybrid_search_with(&SearchQuery::text(text))
+            .unwrap_or_default()
     }
 }
@@ -2,90 +2,98 @@
 //!
 //! High-performance hybrid search runner.
 
-use notify::{RecursiveMode, Watcher};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::net::UnixListener;
+use tokio::net::TcpListener;
 use tokio::sync::mpsc;
-use tos_searchd::SearchState;
+use tos_searchd::{
+    select_backend, HybridWeights, IndexEvent, SearchQuery, SearchRecord, SearchState,
+};
+
+/// Set while a `rebuild` is running, so a second one is refused.
+static REBUILDING: AtomicBool = AtomicBool::new(false);
 
 /// Entry point for the TOS Search Daemon.
 ///
-/// Initializes the search state, starts the indexing worker, and listens
-/// for search requests on a Unix Domain Socket.
+/// Opens the persistent index, syncs it with the configured roots (or the
+/// working directory), starts the indexing worker, and listens for search
+/// and root requests on a loopback TCP port registered with the Brain,
+/// like every other daemon. The Brain adds and removes
+/// its sectors' directories as roots at runtime, and pushes its commands,
+/// output, logs and AI exchanges as records.
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
 
-    let state = Arc::new(SearchState::new()?);
+    let config = tos_common::TosConfig::load();
+    let index_dir = SearchState::default_dir(&config);
+    let mut state = SearchState::open(&index_dir, select_backend(&config))?;
+    state.set_hybrid_weights(HybridWeights::from_config(&config.search));
+    state.set_max_file_size(config.search.max_file_size);
+    let state = Arc::new(state);
+    tracing::info!(
+        "TOS-SEARCHD: Index at {:?} ({} documents, embeddings: {})",
+        index_dir,
+        state.len(),
+        state.backend().unwrap_or("none, keyword-only")
+    );
 
-    // Unix Domain Socket setup
-    let socket_path = "/tmp/tos-search.sock";
-    let _ = std::fs::remove_file(socket_path);
-    let listener = UnixListener::bind(socket_path)?;
+    // Loopback only: the Brain is the daemon's one client.
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
     tracing::info!(
-        "TOS-SEARCHD: Hybrid Engine ONLINE on {} (Candle Lib)",
-        socket_path
+        "TOS-SEARCHD: Hybrid Engine ONLINE on port {} (Candle Lib)",
+        port
     );
 
-    // Channel for indexing requests to avoid spawning from non-tokio threads
-    let (tx, mut rx) = mpsc::channel::<PathBuf>(100);
+    let mut roots = config.search.resolved_roots();
+    // `root_add` requests may only name directories inside these.
+    let allowed: Arc<Vec<PathBuf>> = Arc::new(
+        roots
+            .iter()
+            .cloned()
+            .chain(dirs::home_dir())
+            .filter_map(|root| root.canonicalize().ok())
+            .collect(),
+    );
+    if roots.is_empty() {
+        roots.push(std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
+    }
+
+    // Channel for index events to avoid spawning from non-tokio threads
+    let (tx, mut rx) = mpsc::channel::<IndexEvent>(100);
     let indexer_state = state.clone();
 
     tokio::spawn(async move {
-        while let Some(path) = rx.recv().await {
-            let _ = indexer_state.index_file(&path).await;
-        }
-    });
-
-    // Initial walk
-    let initial_tx = tx.clone();
-    let initial_state = state.clone();
-    tokio::task::spawn(async move {
-        let root = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-        tracing::info!("TOS-SEARCHD: Starting initial indexing of {:?}", root);
-
-        let walker = walkdir::WalkDir::new(root)
-            .max_depth(3)
-            .into_iter()
-            .filter_entry(|e| {
-                let name = e.file_name().to_string_lossy();
-                !name.starts_with('.') && name != "target" && name != "node_modules"
-            });
-
-        for entry in walker.filter_map(|e| e.ok()) {
-            if entry.file_type().is_file() {
-                let _ = initial_tx.send(entry.path().to_path_buf()).await;
+        while let Some(event) = rx.recv().await {
+            if let Err(e) = indexer_state.apply(event.clone()).await {
+                tracing::debug!("TOS-SEARCHD: Failed to apply {:?}: {}", event, e);
             }
         }
-        tracing::info!("TOS-SEARCHD: Initial Index Queueing Complete");
-
-        // Wait a bit for indexing to catch up and then commit
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-        let _ = initial_state.commit();
-        tracing::info!("TOS-SEARCHD: Initial Index Commit Complete");
     });
 
     // Notify Watcher
     let watcher_tx = tx.clone();
-    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+    let watch_state = state.clone();
+    let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
         if let Ok(event) = res {
-            if event.kind.is_modify() || event.kind.is_create() {
-                for path in event.paths {
-                    // Filter out excluded dirs in watcher too
-                    let path_str = path.to_string_lossy();
-                    if !path_str.contains("/target/")
-                        && !path_str.contains("/node_modules/")
-                        && !path_str.contains("/.git/")
-                    {
-                        let _ = watcher_tx.blocking_send(path);
-                    }
+            for change in IndexEvent::from_notify(&event) {
+                if let Some(change) = change.scoped(watch_state.scope()) {
+                    let _ = watcher_tx.blocking_send(change);
                 }
             }
         }
     })?;
-    watcher.watch(Path::new("."), RecursiveMode::Recursive)?;
+    let watcher = Arc::new(Mutex::new(watcher));
+
+    // Startup sync: only new or changed files are re-embedded
+    for root in roots {
+        let reply = add_root(&state, &watcher, &root);
+        tracing::info!("TOS-SEARCHD: {:?}: {}", root, reply);
+    }
 
     // Periodic commit task
     let commit_state = state.clone();
@@ -98,11 +106,13 @@ async fn main() -> anyhow::Result<()> {
     });
 
     // Register with Brain
-    let _ = tos_common::register_with_brain("tos-searchd", 0).await;
+    let _ = tos_common::register_with_brain("tos-searchd", port).await;
 
     loop {
         let (socket, _) = listener.accept().await?;
         let s = state.clone();
+        let watcher = watcher.clone();
+        let allowed = allowed.clone();
 
         tokio::spawn(async move {
             let (reader, mut writer) = socket.into_split();
@@ -118,22 +128,192 @@ async fn main() -> anyhow::Result<()> {
                 let parts: Vec<&str> = req.splitn(2, ':').collect();
 
                 let response = match parts[0] {
-                    "search" => {
-                        let hits = s.search(parts.get(1).unwrap_or(&""));
-                        serde_json::to_string(&hits).unwrap_or_default()
+                    "search" | "semantic_search" | "semantic" | "hybrid_search" | "hybrid" => {
+                        match parse_query(parts.get(1).unwrap_or(&"")) {
+                            Ok(query) => {
+                                let hits = match parts[0] {
+                                    "search" => s.search_with(&query),
+                                    "hybrid_search" | "hybrid" => s.hybrid_search_with(&query),
+                                    _ => s.semantic_search_with(&query),
+                                };
+                                match hits {
+                                    Ok(hits) => serde_json::to_string(&hits).unwrap_or_default(),
+                                    Err(e) => format!("ERROR: {}", e),
+                                }
+                            }
+                            Err(e) => e,
+                        }
+                    }
+                    "index_records" => index_records(&s, parts.get(1).unwrap_or(&"")).await,
+                    "purge_sector" => match uuid::Uuid::parse_str(parts.get(1).unwrap_or(&"")) {
+                        Ok(sector) => match s.purge_sector(sector).and_then(|n| {
+                            s.commit()?;
+                            Ok(n)
+                        }) {
+                            Ok(n) => format!("OK: Purged {} documents", n),
+                            Err(e) => format!("ERROR: {}", e),
+                        },
+                        Err(_) => "ERROR: Invalid sector id".to_string(),
+                    },
+                    "status" => serde_json::to_string(&s.status()).unwrap_or_default(),
+                    "roots" => serde_json::to_string(&s.roots()).unwrap_or_default(),
+                    "root_add" => request_root(
+                        &s,
+                        &watcher,
+                        Path::new(parts.get(1).unwrap_or(&"")),
+                        Some(&allowed),
+                    ),
+                    // A sector's working directory, which the Brain vouches for.
+                    "root_add_sector" => request_root(
+                        &s,
+                        &watcher,
+                        Path::new(parts.get(1).unwrap_or(&"")),
+                        None,
+                    ),
+                    "root_remove" => {
+                        remove_root(&s, &watcher, Path::new(parts.get(1).unwrap_or(&"")))
                     }
-                    "semantic_search" | "semantic" => {
-                        let hits = s.semantic_search(parts.get(1).unwrap_or(&""));
-                        serde_json::to_string(&hits).unwrap_or_default()
+                    "rebuild" => {
+                        if REBUILDING.swap(true, Ordering::SeqCst) {
+                            "ERROR: Rebuild already in progress".to_string()
+                        } else {
+                            let s = s.clone();
+                            tokio::spawn(async move {
+                                match s.rebuild().await {
+                                    Ok(report) => tracing::info!(
+                                        "TOS-SEARCHD: Rebuild complete ({} documents)",
+                                        report.indexed
+                                    ),
+                                    Err(e) => tracing::error!("TOS-SEARCHD: Rebuild failed: {}", e),
+                                }
+                                REBUILDING.store(false, Ordering::SeqCst);
+                            });
+                            "OK: Rebuild started".to_string()
+                        }
                     }
-                    "rebuild" => "OK".to_string(),
                     _ => "ERROR: Unknown command".to_string(),
                 };
 
-                let _ = writer
-                    .write_all(format!("{}\n", response).as_bytes())
-                    .await;
+                let _ = writer.write_all(format!("{}\n", response).as_bytes()).await;
             }
         });
     }
 }
+
+/// A payload starting with `{` is a JSON [`SearchQuery`]; anything else is
+/// the query text.
+fn parse_query(payload: &str) -> Result<SearchQuery, String> {
+    if payload.trim_start().starts_with('{') {
+        serde_json::from_str(payload).map_err(|e| format!("ERROR: Invalid search query: {}", e))
+    } else {
+        Ok(SearchQuery::text(payload))
+    }
+}
+
+/// Index a JSON list of records from the Brain and commit them, so they
+/// are searchable at once.
+async fn index_records(state: &SearchState, payload: &str) -> String {
+    let records: Vec<SearchRecord> = match serde_json::from_str(payload) {
+        Ok(records) => records,
+        Err(e) => return format!("ERROR: Invalid records: {}", e),
+    };
+    let mut indexed = 0;
+    for record in &records {
+        match state.index_record(record).await {
+            Ok(true) => indexed += 1,
+            Ok(false) => {}
+            Err(e) => tracing::debug!("TOS-SEARCHD: Skipping record {}: {}", record.key(), e),
+        }
+    }
+    match state.commit() {
+        Ok(()) => format!("OK: {} records indexed", indexed),
+        Err(e) => format!("ERROR: {}", e),
+    }
+}
+
+/// Add a root someone asked for: never a hidden directory or one on a
+/// hidden path, and with `allowed`, only inside one of those directories.
+fn request_root(
+    state: &Arc<SearchState>,
+    watcher: &Mutex<RecommendedWatcher>,
+    path: &Path,
+    allowed: Option<&[PathBuf]>,
+) -> String {
+    let root = match path.canonicalize() {
+        Ok(root) if root.is_dir() => root,
+        _ => return format!("ERROR: Not a directory: {}", path.display()),
+    };
+    let hidden = root
+        .components()
+        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'));
+    if hidden {
+        return format!("ERROR: Refusing to index a hidden directory: {}", root.display());
+    }
+    if let Some(allowed) = allowed {
+        if !allowed.iter().any(|dir| root.starts_with(dir)) {
+            return format!(
+                "ERROR: Not inside a configured root or the home directory: {}",
+                root.display()
+            );
+        }
+    }
+    add_root(state, watcher, &root)
+}
+
+/// Start indexing and watching `path`; the sync runs in the background.
+fn add_root(state: &Arc<SearchState>, watcher: &Mutex<RecommendedWatcher>, path: &Path) -> String {
+    let root = match path.canonicalize() {
+        Ok(root) if root.is_dir() => root,
+        _ => return format!("ERROR: Not a directory: {}", path.display()),
+    };
+    if root.parent().is_none() {
+        return "ERROR: Refusing to index the filesystem root".to_string();
+    }
+    // Registered before the watch and the sync so their events are kept;
+    // claiming it here also tells a new root from one already indexed.
+    if !state.scope().add_root(&root) {
+        return "OK: Already indexed".to_string();
+    }
+    if let Ok(mut watcher) = watcher.lock() {
+        if let Err(e) = watcher.watch(&root, RecursiveMode::Recursive) {
+            state.scope().remove_root(&root);
+            return format!("ERROR: Cannot watch {}: {}", root.display(), e);
+        }
+    }
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        tracing::info!("TOS-SEARCHD: Syncing index with {:?}", root);
+        match state.sync_root(&root).await {
+            Ok(report) => {
+                let _ = state.commit();
+                tracing::info!(
+                    "TOS-SEARCHD: Sync of {:?} complete ({} indexed, {} unchanged, {} removed, {} skipped)",
+                    root,
+                    report.indexed,
+                    report.unchanged,
+                    report.removed,
+                    report.skipped
+                );
+            }
+            Err(e) => tracing::error!("TOS-SEARCHD: Sync of {:?} failed: {}", root, e),
+        }
+    });
+    "OK: Root added".to_string()
+}
+
+/// Stop watching `path` and drop the documents no other root covers.
+fn remove_root(state: &SearchState, watcher: &Mutex<RecommendedWatcher>, path: &Path) -> String {
+    let root = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    match state.remove_root(&root) {
+        Ok(Some(removed)) => {
+            if let Ok(mut watcher) = watcher.lock() {
+                let _ = watcher.unwatch(&root);
+            }
+            let _ = state.commit();
+            format!("OK: Root removed ({} documents)", removed)
+        }
+        Ok(None) => format!("ERROR: Not an indexed root: {}", root.display()),
+        Err(e) => format!("ERROR: {}", e),
+    }
+}
@@ -0,0 +1,329 @@
+//! Indexing scope (§4.1): the roots being indexed, the ignore files that
+//! carve paths out of them, and the limits on which files are read.
+//!
+//! Ignore files use `.gitignore` syntax and apply to the directory holding
+//! them and everything below it. In each directory `.gitignore`, `.ignore`
+//! and `.tosignore` are read in that order, later rules overriding earlier
+//! ones, and a deeper directory's rules override its parents'. A path whose
+//! parent directory is ignored stays ignored, as in git.
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Ignore files read in every indexed directory, lowest precedence first.
+pub const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore", ".tosignore"];
+
+/// Directory names the walker and the watcher never descend into.
+const IGNORED_DIRS: &[&str] = &["target", "node_modules"];
+
+/// How many levels below a root are walked.
+const MAX_DEPTH: usize = 3;
+
+/// Files larger than this are not indexed unless configured otherwise.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Leading bytes inspected when deciding whether a file is binary.
+const SNIFF_LEN: usize = 8192;
+
+/// Whether `bytes` look like binary content: a NUL in the first 8 KiB, as
+/// git and grep decide it.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(SNIFF_LEN)].contains(&0)
+}
+
+struct Rule {
+    regex: Regex,
+    negate: bool,
+    dir_only: bool,
+}
+
+/// The rules of the ignore files in one directory.
+#[derive(Default)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+impl IgnoreRules {
+    /// Parse `.gitignore`-style text. Lines that do not form a valid
+    /// pattern are skipped.
+    pub fn parse(text: &str) -> Self {
+        Self {
+            rules: text.lines().filter_map(Self::parse_line).collect(),
+        }
+    }
+
+    fn parse_line(line: &str) -> Option<Rule> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negate, pattern) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, pattern) = match pattern.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+        if pattern.is_empty() {
+            return None;
+        }
+        // A slash anywhere but the end anchors the pattern to this directory.
+        let anchored = pattern.contains('/');
+        let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+
+        let mut expr = String::from("^");
+        if !anchored {
+            expr.push_str("(?:.*/)?");
+        }
+        expr.push_str(&glob_expr(pattern));
+        expr.push('$');
+        Some(Rule {
+            regex: Regex::new(&expr).ok()?,
+            negate,
+            dir_only,
+        })
+    }
+
+    /// `Some(true)` if the last matching rule ignores `relative` (a
+    /// `/`-separated path below the rules' directory), `Some(false)` if it
+    /// re-includes it, `None` if no rule matches.
+    pub fn decide(&self, relative: &str, is_dir: bool) -> Option<bool> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|r| (is_dir || !r.dir_only) && r.regex.is_match(relative))
+            .map(|r| !r.negate)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+/// The expression for one glob pattern, without anchors.
+fn glob_expr(pattern: &str) -> String {
+    let mut out = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    out.push_str("(?:.*/)?");
+                } else {
+                    out.push_str(".*");
+                }
+            }
+            '*' => out.push_str("[^/]*"),
+            '?' => out.push_str("[^/]"),
+            '[' => {
+                let mut class = String::from("[");
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    class.push('^');
+                }
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        closed = true;
+                        break;
+                    }
+                    if c == '\\' || c == '[' {
+                        class.push('\\');
+                    }
+                    class.push(c);
+                }
+                if closed {
+                    out.push_str(&class);
+                    out.push(']');
+                } else {
+                    out.push_str(&regex::escape(&class));
+                }
+            }
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    out.push_str(&regex::escape(&next.to_string()));
+                }
+            }
+            c => out.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    out
+}
+
+/// The roots `tos-searchd` indexes and the rules deciding what under them
+/// is read.
+pub struct Scope {
+    roots: Mutex<Vec<PathBuf>>,
+    max_file_size: u64,
+    /// Parsed ignore files, by directory.
+    rules: Mutex<HashMap<PathBuf, Arc<IgnoreRules>>>,
+}
+
+impl Default for Scope {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_FILE_SIZE)
+    }
+}
+
+impl Scope {
+    pub fn new(max_file_size: u64) -> Self {
+        Self {
+            roots: Mutex::new(Vec::new()),
+            max_file_size,
+            rules: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn max_file_size(&self) -> u64 {
+        self.max_file_size
+    }
+
+    pub fn roots(&self) -> Vec<PathBuf> {
+        self.roots.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+
+    /// Start indexing `root`. Returns false if it already was a root.
+    pub fn add_root(&self, root: &Path) -> bool {
+        let mut roots = match self.roots.lock() {
+            Ok(r) => r,
+            Err(_) => return false,
+        };
+        if roots.iter().any(|r| r == root) {
+            return false;
+        }
+        roots.push(root.to_path_buf());
+        roots.sort();
+        true
+    }
+
+    /// Stop indexing `root`. Returns false if it was not a root.
+    pub fn remove_root(&self, root: &Path) -> bool {
+        let mut roots = match self.roots.lock() {
+            Ok(r) => r,
+            Err(_) => return false,
+        };
+        let before = roots.len();
+        roots.retain(|r| r != root);
+        roots.len() != before
+    }
+
+    /// The innermost root containing `path`.
+    pub fn root_of(&self, path: &Path) -> Option<PathBuf> {
+        self.roots()
+            .into_iter()
+            .filter(|r| path.starts_with(r))
+            .max_by_key(|r| r.components().count())
+    }
+
+    /// Whether `path` is indexed: below a root, within the depth limit and
+    /// not ignored.
+    pub fn tracks(&self, path: &Path) -> bool {
+        match self.root_of(path) {
+            Some(root) => {
+                let depth = path
+                    .strip_prefix(&root)
+                    .map_or(0, |r| r.components().count());
+                depth <= MAX_DEPTH && !self.is_ignored(&root, path, path.is_dir())
+            }
+            None => false,
+        }
+    }
+
+    /// Whether `path` is skipped when indexing `root`: anything below a
+    /// dot-file or dot-directory, build output, `node_modules`, or what an
+    /// ignore file between `root` and `path` excludes.
+    pub fn is_ignored(&self, root: &Path, path: &Path, is_dir: bool) -> bool {
+        let relative = match path.strip_prefix(root) {
+            Ok(r) => r,
+            Err(_) => return true,
+        };
+        let names: Vec<String> = relative
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect();
+
+        for (i, name) in names.iter().enumerate() {
+            if name.starts_with('.') || IGNORED_DIRS.contains(&name.as_str()) {
+                return true;
+            }
+            let last = i + 1 == names.len();
+            if self.excluded(root, &names[..=i], !last || is_dir) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Whether the rules between `root` and the entry `names` (relative to
+    /// `root`) exclude it, ignoring its ancestors.
+    fn excluded(&self, root: &Path, names: &[String], is_dir: bool) -> bool {
+        let mut decision = false;
+        let mut dir = root.to_path_buf();
+        for depth in 0..names.len() {
+            let rules = self.rules_in(&dir);
+            if !rules.is_empty() {
+                let relative = names[depth..].join("/");
+                if let Some(ignored) = rules.decide(&relative, is_dir) {
+                    decision = ignored;
+                }
+            }
+            dir.push(&names[depth]);
+        }
+        decision
+    }
+
+    fn rules_in(&self, dir: &Path) -> Arc<IgnoreRules> {
+        if let Some(rules) = self.rules.lock().ok().and_then(|c| c.get(dir).cloned()) {
+            return rules;
+        }
+        let text: String = IGNORE_FILES
+            .iter()
+            .filter_map(|name| std::fs::read_to_string(dir.join(name)).ok())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let rules = Arc::new(IgnoreRules::parse(&text));
+        if let Ok(mut cache) = self.rules.lock() {
+            cache.insert(dir.to_path_buf(), rules.clone());
+        }
+        rules
+    }
+
+    /// Forget the parsed ignore files of `dir` after one of them changed.
+    pub fn invalidate(&self, dir: &Path) {
+        if let Ok(mut cache) = self.rules.lock() {
+            cache.remove(dir);
+        }
+    }
+
+    /// The files under `dir` that get indexed. `dir` must be tracked.
+    pub fn walk(&self, dir: &Path) -> Vec<PathBuf> {
+        let root = match self.root_of(dir) {
+            Some(root) => root,
+            None => return Vec::new(),
+        };
+        let depth = dir
+            .strip_prefix(&root)
+            .map_or(0, |r| r.components().count());
+        if depth > MAX_DEPTH {
+            return Vec::new();
+        }
+        walkdir::WalkDir::new(dir)
+            .max_depth(MAX_DEPTH - depth)
+            .into_iter()
+            .filter_entry(|e| {
+                e.depth() == 0 || !self.is_ignored(&root, e.path(), e.file_type().is_dir())
+            })
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.into_path())
+            .collect()
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::process::Command;
+use tos_common::services::registry::ServiceRegistry;
+use tos_common::services::search::{SearchHit, SearchQuery};
+use tos_common::services::SearchService;
+use tos_common::{MockBrain, TerminalLine, TosState};
+
+fn line(text: &str) -> TerminalLine {
+    TerminalLine {
+        text: text.to_string(),
+        priority: 1,
+        timestamp: chrono::Local::now(),
+        source: None,
+    }
+}
+
+/// Run a blocking `SearchService` call off the runtime's worker threads.
+async fn blocking<T: Send + 'static>(
+    search: &Arc<SearchService>,
+    call: impl FnOnce(&SearchService) -> T + Send + 'static,
+) -> T {
+    let search = search.clone();
+    tokio::task::spawn_blocking(move || call(&search)).await.unwrap()
+}
+
+/// Query until `found` holds for the hits, or give up after a few seconds.
+async fn wait_for(
+    search: &Arc<SearchService>,
+    text: &str,
+    found: impl Fn(&[SearchHit]) -> bool,
+) -> Vec<SearchHit> {
+    let mut hits = Vec::new();
+    for _ in 0..50 {
+        let query = SearchQuery::text(text);
+        hits = blocking(search, move |s| s.query(&query)).await;
+        if found(&hits) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    hits
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_brain_reaches_searchd_through_the_registry() -> anyhow::Result<()> {
+    // `tempdir()` names start with a dot, and hidden roots are refused.
+    let data = tempfile::Builder::new().prefix("searchd").tempdir()?;
+    let home = data.path().join("home");
+    std::fs::create_dir_all(home.join(".ssh"))?;
+    let configured = data.path().join("configured");
+    let sector_dir = data.path().join("sector");
+    std::fs::create_dir(&configured)?;
+    std::fs::create_dir(&sector_dir)?;
+    std::fs::write(configured.join("notes.txt"), "kestrel migration notes")?;
+    let config = data.path().join("tos.toml");
+    std::fs::write(
+        &config,
+        format!(
+            "[platform]\ndata_dir = {:?}\n[search]\nembedding_backend = \"none\"\nroots = [{:?}]\n",
+            data.path(),
+            configured
+        ),
+    )?;
+
+    let mock_brain = MockBrain::new().await?;
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tos-searchd"))
+        .env("TOS_CONFIG", &config)
+        .env("HOME", &home)
+        .spawn()?;
+    let (name, port) = mock_brain.handle_one_registration().await?;
+    assert_eq!(name, "tos-searchd");
+    assert_ne!(port, 0);
+
+    let mut registry = ServiceRegistry::new(0);
+    registry.register("tos-searchd", port, "127.0.0.1");
+    let search = Arc::new(SearchService::new(Arc::new(Mutex::new(registry))));
+
+    let hits = wait_for(&search, "kestrel", |h| !h.is_empty()).await;
+    assert!(hits[0].path.ends_with("notes.txt"));
+
+    // Session records reach the index.
+    let mut state = TosState::default();
+    let sector = state.sectors[0].id;
+    state.sectors[0].hubs[0].push_output_line(line("falcon error"));
+    search.push_records(search.collect_records(&state)).await;
+    assert!(!wait_for(&search, "falcon", |h| !h.is_empty()).await.is_empty());
+
+    // Records collected before a purge are not indexed after it.
+    state.sectors[0].hubs[0].push_output_line(line("osprey error"));
+    let stale = search.collect_records(&state);
+    let hubs = state.sectors[0].hubs.clone();
+    let reply = blocking(&search, move |s| s.purge_sector(sector, &hubs)).await?;
+    assert!(reply.starts_with("OK"), "{}", reply);
+    search.push_records(stale).await;
+    assert!(blocking(&search, |s| s.query(&SearchQuery::text("osprey"))).await.is_empty());
+    assert!(blocking(&search, |s| s.query(&SearchQuery::text("falcon"))).await.is_empty());
+
+    // Requested roots stay inside the configured roots and home, and out of
+    // hidden directories.
+    for path in ["/etc".into(), home.join(".ssh"), data.path().to_path_buf()] {
+        let reply = blocking(&search, move |s| s.add_root(&path.display().to_string(), false))
+            .await?;
+        assert!(reply.starts_with("ERROR"), "{}", reply);
+    }
+    let reply = blocking(&search, move |s| s.add_root(&home.display().to_string(), false)).await?;
+    assert!(reply.starts_with("OK"), "{}", reply);
+    let roots = blocking(&search, |s| s.roots()).await?;
+    assert_eq!(roots.len(), 2, "{:?}", roots);
+    let home_root = roots.iter().find(|r| r.ends_with("home")).unwrap().clone();
+    let reply = blocking(&search, move |s| s.remove_root(&home_root)).await?;
+    assert!(reply.starts_with("OK"), "{}", reply);
+
+    // Sector roots come and go; the configured root stays.
+    let dirs = |d: &[&std::path::Path]| -> BTreeSet<String> {
+        d.iter().map(|d| d.display().to_string()).collect()
+    };
+    search.sync_sector_roots(dirs(&[&configured, &sector_dir])).await;
+    let roots = blocking(&search, |s| s.roots()).await?;
+    assert_eq!(roots.len(), 2, "{:?}", roots);
+    search.sync_sector_roots(dirs(&[])).await;
+    let roots = blocking(&search, |s| s.roots()).await?;
+    assert_eq!(roots.len(), 1, "{:?}", roots);
+    assert!(roots[0].ends_with("configured"));
+
+    child.kill().await?;
+    Ok(())
+}
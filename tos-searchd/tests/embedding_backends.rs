@@ -0,0 +1,89 @@
+use tempfile::tempdir;
+use tos_common::TosConfig;
+use tos_searchd::{select_backend, EmbeddingBackend, HashingEmbedder, SearchState};
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+#[test]
+fn test_hashing_embedder_is_stable_and_normalized() {
+    let embedder = HashingEmbedder::default();
+    assert_eq!(embedder.id(), "hashing:384");
+
+    let daemon = embedder.embed("Building daemons in Rust").unwrap();
+    assert_eq!(daemon, embedder.embed("Building daemons in Rust").unwrap());
+    assert_eq!(daemon.len(), HashingEmbedder::DEFAULT_DIMS);
+    let norm: f32 = daemon.iter().map(|x| x * x).sum::<f32>().sqrt();
+    assert!((norm - 1.0).abs() < 1e-5);
+
+    let related = embedder.embed("how to build a rust daemon").unwrap();
+    let unrelated = embedder.embed("marketplace fee structure").unwrap();
+    assert!(distance(&daemon, &related) < distance(&daemon, &unrelated));
+}
+
+#[tokio::test]
+async fn test_semantic_search_without_model_files() -> anyhow::Result<()> {
+    let state = SearchState::in_memory(Some(Box::new(HashingEmbedder::default())))?;
+    let dir = tempdir()?;
+    let rust_file = dir.path().join("rust_tutorial.md");
+    std::fs::write(&rust_file, "Learn how to build daemons in Rust.")?;
+    let market_file = dir.path().join("market_spec.txt");
+    std::fs::write(&market_file, "Marketplace fee structure documentation.")?;
+
+    state.index_file(&rust_file).await?;
+    state.index_file(&market_file).await?;
+    state.commit()?;
+
+    let hits = state.semantic_search("building rust daemons");
+    assert!(hits[0].path.ends_with("rust_tutorial.md"));
+    assert_eq!(state.status().backend.as_deref(), Some("hashing:384"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_switching_backends_re_embeds() -> anyhow::Result<()> {
+    let index_dir = tempdir()?;
+    let root = tempdir()?;
+    std::fs::write(root.path().join("notes.md"), "kestrel nesting notes")?;
+
+    {
+        let state = SearchState::open(index_dir.path(), Some(Box::new(HashingEmbedder::new(64))))?;
+        assert_eq!(state.sync_root(root.path()).await?.indexed, 1);
+        state.commit()?;
+    }
+    {
+        // Vectors from another backend are not reused.
+        let state =
+            SearchState::open(index_dir.path(), Some(Box::new(HashingEmbedder::default())))?;
+        assert!(state.semantic_search("kestrel").is_empty());
+        assert_eq!(state.sync_root(root.path()).await?.indexed, 1);
+        state.commit()?;
+        assert_eq!(state.semantic_search("kestrel").len(), 1);
+    }
+
+    // Keyword-only mode keeps what is there and still answers exact queries.
+    let state = SearchState::open_keyword_only(index_dir.path())?;
+    assert_eq!(state.sync_root(root.path()).await?.unchanged, 1);
+    assert_eq!(state.status().backend, None);
+    assert_eq!(state.search("kestrel").len(), 1);
+    assert!(state.semantic_search("kestrel").is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_backend_selection_falls_back_to_keyword_only() {
+    let mut config = TosConfig::default();
+
+    config.search.embedding_backend = "none".to_string();
+    assert!(select_backend(&config).is_none());
+
+    config.search.embedding_backend = "hashing".to_string();
+    assert_eq!(select_backend(&config).unwrap().id(), "hashing:384");
+
+    // A missing local model leaves the daemon running without embeddings.
+    let empty = tempdir().unwrap();
+    config.search.embedding_backend = "local".to_string();
+    config.search.model_dir = empty.path().to_string_lossy().to_string();
+    assert!(select_backend(&config).is_none());
+}
@@ -0,0 +1,122 @@
+use tempfile::tempdir;
+use tos_searchd::hybrid::fuse;
+use tos_searchd::{
+    HashingEmbedder, HybridWeights, SearchEngine, SearchHit, SearchHitType, SearchState,
+};
+
+fn hit(path: &str, score: f32) -> SearchHit {
+    SearchHit {
+        path: path.to_string(),
+        hit_type: SearchHitType::File,
+        score,
+        line: None,
+        snippet: None,
+        file_type: None,
+        mtime: None,
+        engines: vec![],
+        title: None,
+        sector_id: None,
+        hub_id: None,
+    }
+}
+
+fn engines(hit: &SearchHit) -> Vec<SearchEngine> {
+    hit.engines.iter().map(|e| e.engine).collect()
+}
+
+#[test]
+fn test_rank_fusion_rewards_agreement() {
+    let keyword = vec![hit("/a", 9.0), hit("/b", 4.0), hit("/c", 1.0)];
+    let semantic = vec![hit("/b", 0.9), hit("/d", 0.5)];
+    let fused = fuse(
+        vec![
+            (SearchEngine::Keyword, keyword),
+            (SearchEngine::Semantic, semantic),
+        ],
+        HybridWeights::default(),
+    );
+
+    let order: Vec<&str> = fused.iter().map(|h| h.path.as_str()).collect();
+    assert_eq!(order, vec!["/b", "/a", "/d", "/c"]);
+    assert_eq!(
+        engines(&fused[0]),
+        vec![SearchEngine::Keyword, SearchEngine::Semantic]
+    );
+    assert_eq!(engines(&fused[2]), vec![SearchEngine::Semantic]);
+
+    let b_keyword = &fused[0].engines[0];
+    assert_eq!(b_keyword.rank, 2);
+    assert_eq!(b_keyword.score, 4.0);
+    assert!((b_keyword.normalized - 0.375).abs() < 1e-6);
+    assert!((b_keyword.contribution - 1.0 / 62.0).abs() < 1e-6);
+    assert!(fused.iter().all(|h| h.score > 0.0 && h.score <= 1.0));
+}
+
+#[test]
+fn test_weights_shift_the_ranking() {
+    let ranked = || {
+        vec![
+            (
+                SearchEngine::Keyword,
+                vec![hit("/exact", 5.0), hit("/near", 1.0)],
+            ),
+            (
+                SearchEngine::Semantic,
+                vec![hit("/near", 0.9), hit("/exact", 0.1)],
+            ),
+        ]
+    };
+    let keyword_heavy = HybridWeights {
+        keyword: 2.0,
+        ..Default::default()
+    };
+    assert_eq!(fuse(ranked(), keyword_heavy)[0].path, "/exact");
+    let semantic_heavy = HybridWeights {
+        semantic: 2.0,
+        ..Default::default()
+    };
+    assert_eq!(fuse(ranked(), semantic_heavy)[0].path, "/near");
+}
+
+#[tokio::test]
+async fn test_hybrid_search_explains_each_hit() -> anyhow::Result<()> {
+    let state = SearchState::in_memory(Some(Box::new(HashingEmbedder::default())))?;
+    let root = tempdir()?;
+    let tutorial = root.path().join("rust_tutorial.md");
+    std::fs::write(&tutorial, "Learn how to build daemons in Rust.")?;
+    let market = root.path().join("market_spec.txt");
+    std::fs::write(&market, "Marketplace fee structure documentation.")?;
+    state.index_file(&tutorial).await?;
+    state.index_file(&market).await?;
+    state.commit()?;
+
+    let hits = state.hybrid_search("daemons");
+    assert!(hits[0].path.ends_with("rust_tutorial.md"));
+    assert_eq!(
+        engines(&hits[0]),
+        vec![SearchEngine::Keyword, SearchEngine::Semantic]
+    );
+    assert_eq!(hits[0].line, Some(1), "keyword snippet is kept");
+    // The embedding still ranks the unrelated file; keyword search does not.
+    assert!(hits
+        .iter()
+        .filter(|h| h.path.ends_with("market_spec.txt"))
+        .all(|h| engines(h) == vec![SearchEngine::Semantic]));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_hybrid_search_is_keyword_only_without_embeddings() -> anyhow::Result<()> {
+    let index_dir = tempdir()?;
+    let root = tempdir()?;
+    std::fs::write(root.path().join("notes.md"), "kestrel")?;
+    let state = SearchState::open_keyword_only(index_dir.path())?;
+    state.sync_root(root.path()).await?;
+    state.commit()?;
+
+    let hits = state.hybrid_search("kestrel");
+    assert_eq!(hits.len(), 1);
+    assert_eq!(engines(&hits[0]), vec![SearchEngine::Keyword]);
+    assert!((hits[0].score - 1.0).abs() < 1e-6);
+    Ok(())
+}
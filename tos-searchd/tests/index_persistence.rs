@@ -0,0 +1,159 @@
+use notify::event::{CreateKind, ModifyKind, RemoveKind, RenameMode};
+use notify::{Event, EventKind};
+use std::path::PathBuf;
+use tempfile::tempdir;
+use tos_searchd::{IndexEvent, Scope, SearchState, SyncReport};
+
+fn paths(state: &SearchState, query: &str) -> Vec<String> {
+    state.search(query).into_iter().map(|h| h.path).collect()
+}
+
+#[tokio::test]
+async fn test_index_survives_restart_without_reindexing() -> anyhow::Result<()> {
+    let index_dir = tempdir()?;
+    let root = tempdir()?;
+    let notes = root.path().join("notes.md");
+    std::fs::write(&notes, "orbital mechanics primer")?;
+    std::fs::create_dir_all(root.path().join("target"))?;
+    std::fs::write(root.path().join("target/build.log"), "orbital debris")?;
+
+    {
+        let state = SearchState::open_keyword_only(index_dir.path())?;
+        let report = state.sync_root(root.path()).await?;
+        assert_eq!(report.indexed, 1, "ignored directories are not walked");
+        state.commit()?;
+    }
+
+    let state = SearchState::open_keyword_only(index_dir.path())?;
+    assert_eq!(state.len(), 1);
+    assert_eq!(
+        paths(&state, "orbital"),
+        vec![notes.to_string_lossy().to_string()]
+    );
+    assert_eq!(
+        state.sync_root(root.path()).await?,
+        SyncReport {
+            indexed: 0,
+            unchanged: 1,
+            removed: 0,
+            skipped: 0
+        }
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_modify_rename_and_remove_replace_documents() -> anyhow::Result<()> {
+    let index_dir = tempdir()?;
+    let root = tempdir()?;
+    let state = SearchState::open_keyword_only(index_dir.path())?;
+
+    let draft = root.path().join("draft.txt");
+    std::fs::write(&draft, "alpha")?;
+    assert!(state.index_file(&draft).await?);
+    assert!(!state.index_file(&draft).await?, "same content is skipped");
+
+    std::fs::write(&draft, "bravo")?;
+    state.apply(IndexEvent::Upsert(draft.clone())).await?;
+    state.commit()?;
+    assert!(paths(&state, "alpha").is_empty());
+    assert_eq!(paths(&state, "bravo").len(), 1, "no stale duplicate");
+
+    let final_path = root.path().join("final.txt");
+    std::fs::rename(&draft, &final_path)?;
+    state
+        .apply(IndexEvent::Rename {
+            from: draft.clone(),
+            to: final_path.clone(),
+        })
+        .await?;
+    state.commit()?;
+    assert_eq!(
+        paths(&state, "bravo"),
+        vec![final_path.to_string_lossy().to_string()]
+    );
+
+    std::fs::remove_file(&final_path)?;
+    state.apply(IndexEvent::Remove(final_path)).await?;
+    state.commit()?;
+    assert!(paths(&state, "bravo").is_empty());
+    assert!(state.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sync_prunes_deleted_files_and_rebuild_starts_over() -> anyhow::Result<()> {
+    let index_dir = tempdir()?;
+    let root = tempdir()?;
+    let sub = root.path().join("docs");
+    std::fs::create_dir_all(&sub)?;
+    std::fs::write(sub.join("a.txt"), "kestrel")?;
+    std::fs::write(sub.join("b.txt"), "kestrel")?;
+
+    let state = SearchState::open_keyword_only(index_dir.path())?;
+    state.sync_root(root.path()).await?;
+    state.commit()?;
+    assert_eq!(paths(&state, "kestrel").len(), 2);
+
+    // A removed directory takes everything below it.
+    std::fs::remove_dir_all(&sub)?;
+    assert_eq!(
+        state.apply(IndexEvent::Remove(sub.clone())).await?.removed,
+        2
+    );
+
+    std::fs::write(root.path().join("c.txt"), "kestrel")?;
+    let report = state.rebuild().await?;
+    assert_eq!(report.indexed, 1);
+    assert_eq!(paths(&state, "kestrel").len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_notify_events_translate_to_index_events() {
+    let scope = Scope::default();
+    scope.add_root(&PathBuf::from("/work"));
+    let event = |kind, paths: &[&str]| Event {
+        kind,
+        paths: paths.iter().map(PathBuf::from).collect(),
+        attrs: Default::default(),
+    };
+
+    assert_eq!(
+        IndexEvent::from_notify(&event(EventKind::Create(CreateKind::File), &["/work/a.rs"])),
+        vec![IndexEvent::Upsert("/work/a.rs".into())]
+    );
+    assert_eq!(
+        IndexEvent::from_notify(&event(EventKind::Remove(RemoveKind::Any), &["/work/a.rs"])),
+        vec![IndexEvent::Remove("/work/a.rs".into())]
+    );
+    assert_eq!(
+        IndexEvent::from_notify(&event(
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+            &["/work/a.rs", "/work/b.rs"]
+        )),
+        vec![IndexEvent::Rename {
+            from: "/work/a.rs".into(),
+            to: "/work/b.rs".into()
+        }]
+    );
+    assert!(IndexEvent::from_notify(&event(
+        EventKind::Access(notify::event::AccessKind::Any),
+        &["/work/a.rs"]
+    ))
+    .is_empty());
+
+    // An editor's atomic save: write a dot-file, rename it over the target.
+    let save = IndexEvent::Rename {
+        from: "/work/.a.rs.swp".into(),
+        to: "/work/a.rs".into(),
+    };
+    assert_eq!(
+        save.scoped(&scope),
+        Some(IndexEvent::Upsert("/work/a.rs".into()))
+    );
+    assert_eq!(
+        IndexEvent::Upsert("/work/target/debug/x".into()).scoped(&scope),
+        None
+    );
+}
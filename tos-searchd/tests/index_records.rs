@@ -0,0 +1,160 @@
+use tempfile::tempdir;
+use tos_searchd::{SearchFilters, SearchHitType, SearchQuery, SearchRecord, SearchState};
+use uuid::Uuid;
+
+fn record(kind: SearchHitType, id: &str, text: &str, sector: Uuid, timestamp: u64) -> SearchRecord {
+    SearchRecord {
+        kind,
+        id: id.to_string(),
+        title: text.lines().next().unwrap().to_string(),
+        text: text.to_string(),
+        sector_id: Some(sector),
+        hub_id: Some(Uuid::new_v4()),
+        timestamp,
+    }
+}
+
+fn query(text: &str, filters: SearchFilters) -> SearchQuery {
+    SearchQuery {
+        filters,
+        ..SearchQuery::text(text)
+    }
+}
+
+#[tokio::test]
+async fn test_records_are_searchable_by_kind_and_sector() -> anyhow::Result<()> {
+    let index_dir = tempdir()?;
+    let root = tempdir()?;
+    std::fs::write(root.path().join("notes.md"), "linker error in the build")?;
+    let (alpha, beta) = (Uuid::new_v4(), Uuid::new_v4());
+
+    let state = SearchState::open_keyword_only(index_dir.path())?;
+    state.sync_root(root.path()).await?;
+    let command = record(
+        SearchHitType::Command,
+        "c1",
+        "cargo build\n/work\nexit 101",
+        alpha,
+        100,
+    );
+    let output = record(
+        SearchHitType::Output,
+        "hub/0",
+        "Compiling tos\nerror: linker `cc` not found\nwarning: unused",
+        alpha,
+        101,
+    );
+    let log = record(
+        SearchHitType::Log,
+        "l1",
+        "Search daemon linker error",
+        beta,
+        50,
+    );
+    for r in [&command, &output, &log] {
+        assert!(state.index_record(r).await?);
+    }
+    assert!(!state.index_record(&output).await?, "unchanged record");
+    state.commit()?;
+
+    let hits = state.search_with(&query("linker", SearchFilters::default()))?;
+    assert_eq!(hits.len(), 3);
+
+    let out = state.search_with(&query(
+        "linker",
+        SearchFilters {
+            kinds: vec![SearchHitType::Output],
+            ..Default::default()
+        },
+    ))?;
+    assert_eq!(out.len(), 1);
+    assert_eq!(out[0].path, "tos://output/hub/0");
+    assert_eq!(out[0].hit_type, SearchHitType::Output);
+    assert_eq!(out[0].title.as_deref(), Some("Compiling tos"));
+    assert_eq!(out[0].sector_id, Some(alpha));
+    assert_eq!(out[0].mtime, Some(101));
+    assert_eq!(out[0].line, Some(2), "line within the record");
+    assert!(out[0].snippet.as_ref().unwrap().text.contains("linker"));
+
+    // A sector scope admits its roots' files and its own records.
+    let scoped = state.search_with(&query(
+        "linker",
+        SearchFilters {
+            roots: vec![root.path().to_string_lossy().to_string()],
+            sectors: vec![beta],
+            ..Default::default()
+        },
+    ))?;
+    let mut kinds: Vec<SearchHitType> = scoped.iter().map(|h| h.hit_type).collect();
+    kinds.sort_by_key(|k| k.as_str());
+    assert_eq!(kinds, vec![SearchHitType::File, SearchHitType::Log]);
+
+    // Time filters apply to when a record was made.
+    let recent = state.search_with(&query(
+        "linker",
+        SearchFilters {
+            modified_since: Some(60),
+            kinds: vec![SearchHitType::Output, SearchHitType::Log],
+            ..Default::default()
+        },
+    ))?;
+    assert_eq!(recent.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_purge_and_rebuild() -> anyhow::Result<()> {
+    let index_dir = tempdir()?;
+    let root = tempdir()?;
+    std::fs::write(root.path().join("notes.md"), "segfault notes")?;
+    let (alpha, beta) = (Uuid::new_v4(), Uuid::new_v4());
+
+    let state = SearchState::open_keyword_only(index_dir.path())?;
+    state.sync_root(root.path()).await?;
+    state
+        .index_record(&record(SearchHitType::Output, "a/0", "segfault", alpha, 1))
+        .await?;
+    state
+        .index_record(&record(
+            SearchHitType::AiExchange,
+            "a/1",
+            "why segfault",
+            alpha,
+            2,
+        ))
+        .await?;
+    state
+        .index_record(&record(
+            SearchHitType::Command,
+            "b",
+            "segfault repro",
+            beta,
+            3,
+        ))
+        .await?;
+    state.commit()?;
+    assert_eq!(state.search("segfault").len(), 4);
+
+    assert_eq!(state.purge_sector(alpha)?, 2);
+    state.commit()?;
+    let left: Vec<SearchHitType> = state
+        .search("segfault")
+        .iter()
+        .map(|h| h.hit_type)
+        .collect();
+    assert_eq!(left.len(), 2);
+    assert!(!left.contains(&SearchHitType::Output));
+
+    // Records cannot be re-read from disk, so a rebuild keeps them.
+    assert_eq!(state.rebuild().await?.indexed, 1);
+    assert_eq!(state.search("segfault").len(), 2);
+
+    // Records survive a restart.
+    drop(state);
+    let state = SearchState::open_keyword_only(index_dir.path())?;
+    assert_eq!(state.search("repro")[0].sector_id, Some(beta));
+
+    let file = record(SearchHitType::File, "x", "not a record", beta, 4);
+    assert!(state.index_record(&file).await.is_err());
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::path::Path;
+use tempfile::tempdir;
+use tos_searchd::{IgnoreRules, IndexEvent, SearchFilters, SearchQuery, SearchState};
+
+fn names(state: &SearchState, query: &str) -> Vec<String> {
+    let mut names: Vec<String> = state
+        .search(query)
+        .into_iter()
+        .map(|h| h.path.rsplit('/').next().unwrap().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+fn write(path: &Path, content: &[u8]) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+#[test]
+fn test_ignore_rule_syntax() {
+    let rules = IgnoreRules::parse(
+        "# build output\n*.log\n!keep.log\n/dist\nbuild/\ndocs/**/draft.md\n\\#notes\n",
+    );
+    assert_eq!(rules.decide("app.log", false), Some(true));
+    assert_eq!(rules.decide("sub/app.log", false), Some(true));
+    assert_eq!(rules.decide("keep.log", false), Some(false));
+    assert_eq!(rules.decide("dist", true), Some(true));
+    assert_eq!(
+        rules.decide("sub/dist", true),
+        None,
+        "anchored to this directory"
+    );
+    assert_eq!(rules.decide("sub/build", true), Some(true));
+    assert_eq!(rules.decide("build", false), None, "directory-only rule");
+    assert_eq!(rules.decide("docs/a/b/draft.md", false), Some(true));
+    assert_eq!(rules.decide("docs/draft.md", false), Some(true));
+    assert_eq!(rules.decide("#notes", false), Some(true));
+    assert_eq!(rules.decide("main.rs", false), None);
+}
+
+#[tokio::test]
+async fn test_ignore_files_and_limits() -> anyhow::Result<()> {
+    let index_dir = tempdir()?;
+    let root = tempdir()?;
+    let r = root.path();
+    write(&r.join(".gitignore"), b"*.log\ngenerated/\n");
+    write(&r.join(".tosignore"), b"!important.log\n");
+    write(&r.join("src/.ignore"), b"scratch.txt\n");
+    write(&r.join("notes.md"), b"osprey");
+    write(&r.join("run.log"), b"osprey");
+    write(&r.join("important.log"), b"osprey");
+    write(&r.join("generated/out.md"), b"osprey");
+    write(&r.join("src/main.rs"), b"osprey");
+    write(&r.join("src/scratch.txt"), b"osprey");
+    write(&r.join("blob.bin"), b"osprey\0\x01\x02");
+    write(&r.join("huge.txt"), &b"osprey ".repeat(200));
+
+    let mut state = SearchState::open_keyword_only(index_dir.path())?;
+    state.set_max_file_size(1000);
+    let report = state.sync_root(r).await?;
+    state.commit()?;
+    assert_eq!(report.skipped, 2, "binary and oversized files");
+    assert_eq!(
+        names(&state, "osprey"),
+        vec!["important.log", "main.rs", "notes.md"]
+    );
+
+    // Editing an ignore file re-syncs its directory under the new rules.
+    write(&r.join("src/.ignore"), b"main.rs\n");
+    let event = IndexEvent::Upsert(r.join("src/.ignore"))
+        .scoped(state.scope())
+        .unwrap();
+    assert_eq!(event, IndexEvent::Upsert(r.join("src")));
+    let report = state.apply(event).await?;
+    state.commit()?;
+    assert_eq!((report.indexed, report.removed), (1, 1));
+    assert_eq!(
+        names(&state, "osprey"),
+        vec!["important.log", "notes.md", "scratch.txt"]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_roots_are_added_removed_and_scoped() -> anyhow::Result<()> {
+    let index_dir = tempdir()?;
+    let alpha = tempdir()?;
+    let beta = tempdir()?;
+    write(&alpha.path().join("a.md"), b"heron");
+    write(&beta.path().join("b.md"), b"heron");
+
+    let state = SearchState::open_keyword_only(index_dir.path())?;
+    state.sync_root(alpha.path()).await?;
+    state.sync_root(beta.path()).await?;
+    state.commit()?;
+    assert_eq!(state.roots().len(), 2);
+    assert_eq!(names(&state, "heron"), vec!["a.md", "b.md"]);
+
+    // A query scoped to one sector's roots.
+    let scoped = state.search_with(&SearchQuery {
+        filters: SearchFilters {
+            roots: vec![beta.path().to_string_lossy().to_string()],
+            ..Default::default()
+        },
+        ..SearchQuery::text("heron")
+    })?;
+    assert_eq!(scoped.len(), 1);
+    assert!(scoped[0].path.ends_with("b.md"));
+
+    // Events outside every root are dropped.
+    let outside = tempdir()?;
+    assert!(IndexEvent::Upsert(outside.path().join("c.md"))
+        .scoped(state.scope())
+        .is_none());
+
+    assert_eq!(state.remove_root(alpha.path())?, Some(1));
+    assert_eq!(state.remove_root(alpha.path())?, None);
+    state.commit()?;
+    assert_eq!(names(&state, "heron"), vec!["b.md"]);
+
+    // Rebuilding covers every remaining root.
+    assert_eq!(state.rebuild().await?.indexed, 1);
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use tempfile::tempdir;
+use tos_searchd::{HashingEmbedder, SearchFilters, SearchQuery, SearchState};
+
+fn query(text: &str, filters: SearchFilters) -> SearchQuery {
+    SearchQuery {
+        filters,
+        ..SearchQuery::text(text)
+    }
+}
+
+fn file_names(hits: &[tos_searchd::SearchHit]) -> Vec<String> {
+    let mut names: Vec<String> = hits
+        .iter()
+        .map(|h| h.path.rsplit('/').next().unwrap().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+#[tokio::test]
+async fn test_hits_carry_line_snippet_and_metadata() -> anyhow::Result<()> {
+    let index_dir = tempdir()?;
+    let root = tempdir()?;
+    let notes = root.path().join("notes.md");
+    std::fs::write(&notes, "# Notes\n\nnothing here\nthe kestrel hovers\n")?;
+
+    let state = SearchState::open_keyword_only(index_dir.path())?;
+    state.sync_root(root.path()).await?;
+    state.commit()?;
+
+    let hits = state.search("kestrel");
+    assert_eq!(hits.len(), 1);
+    let hit = &hits[0];
+    assert_eq!(hit.line, Some(4));
+    assert_eq!(hit.file_type.as_deref(), Some("md"));
+    assert!(hit.mtime.unwrap() > 0);
+
+    let snippet = hit.snippet.as_ref().unwrap();
+    let (start, end) = snippet.highlights[0];
+    assert_eq!(&snippet.text[start..end], "kestrel");
+
+    // The best fragment's text also occurs earlier, in a window that
+    // scores lower; the line is the chosen fragment's.
+    let repeat = "kestrel kestrel".to_string() + &" zz".repeat(49);
+    std::fs::write(
+        root.path().join("repeat.txt"),
+        format!("{}{}\n{}\n", "a ".repeat(73), repeat, repeat),
+    )?;
+    state.sync_root(root.path()).await?;
+    state.commit()?;
+    let hits = state.search("kestrel");
+    let hit = hits.iter().find(|h| h.path.ends_with("repeat.txt")).unwrap();
+    assert_eq!(hit.line, Some(2));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_filters_and_paging() -> anyhow::Result<()> {
+    let index_dir = tempdir()?;
+    let root = tempdir()?;
+    let src = root.path().join("src");
+    let docs = root.path().join("docs");
+    std::fs::create_dir_all(&src)?;
+    std::fs::create_dir_all(&docs)?;
+    for (dir, name) in [
+        (&src, "main.rs"),
+        (&src, "lib.rs"),
+        (&src, "build.sh"),
+        (&docs, "guide.md"),
+        (&docs, "api.rs"),
+    ] {
+        std::fs::write(dir.join(name), "falcon")?;
+    }
+
+    let state = SearchState::open_keyword_only(index_dir.path())?;
+    state.sync_root(root.path()).await?;
+    state.commit()?;
+    assert_eq!(state.search("falcon").len(), 5);
+
+    let prefix = SearchFilters {
+        path_prefix: Some(format!("{}/", src.display())),
+        ..Default::default()
+    };
+    assert_eq!(
+        file_names(&state.search_with(&query("falcon", prefix))?),
+        vec!["build.sh", "lib.rs", "main.rs"]
+    );
+
+    let glob = SearchFilters {
+        glob: Some("src/*.rs".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        file_names(&state.search_with(&query("falcon", glob))?),
+        vec!["lib.rs", "main.rs"]
+    );
+
+    let ext = SearchFilters {
+        extensions: vec![".MD".to_string(), "sh".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(
+        file_names(&state.search_with(&query("falcon", ext))?),
+        vec!["build.sh", "guide.md"]
+    );
+
+    let roots = SearchFilters {
+        roots: vec![docs.to_string_lossy().to_string()],
+        extensions: vec!["rs".to_string()],
+        ..Default::default()
+    };
+    // Empty text lists whatever passes the filters.
+    assert_eq!(
+        file_names(&state.search_with(&query("", roots))?),
+        vec!["api.rs"]
+    );
+
+    let future = SearchFilters {
+        modified_since: Some(u64::MAX / 2),
+        ..Default::default()
+    };
+    assert!(state.search_with(&query("falcon", future))?.is_empty());
+
+    // Pages do not overlap and together cover every hit.
+    let mut paged = Vec::new();
+    for offset in [0, 2, 4] {
+        let page = state.search_with(&SearchQuery {
+            offset,
+            limit: Some(2),
+            ..SearchQuery::text("falcon")
+        })?;
+        assert!(page.len() <= 2);
+        paged.extend(page.into_iter().map(|h| h.path));
+    }
+    paged.sort();
+    paged.dedup();
+    assert_eq!(paged.len(), 5);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_semantic_search_honours_filters() -> anyhow::Result<()> {
+    let state = SearchState::in_memory(Some(Box::new(HashingEmbedder::default())))?;
+    let root = tempdir()?;
+    let a = root.path().join("daemons.md");
+    let b = root.path().join("daemons.txt");
+    std::fs::write(&a, "building daemons in rust")?;
+    std::fs::write(&b, "building daemons in rust")?;
+    state.index_file(&a).await?;
+    state.index_file(&b).await?;
+    state.commit()?;
+
+    let md_only = SearchFilters {
+        extensions: vec!["md".to_string()],
+        ..Default::default()
+    };
+    let hits = state.semantic_search_with(&query("rust daemons", md_only))?;
+    assert_eq!(file_names(&hits), vec!["daemons.md"]);
+    assert_eq!(hits[0].file_type.as_deref(), Some("md"));
+
+    let second_page = state.semantic_search_with(&SearchQuery {
+        offset: 1,
+        ..SearchQuery::text("rust daemons")
+    })?;
+    assert_eq!(second_page.len(), 1);
+    Ok(())
+}